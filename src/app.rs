@@ -1,20 +1,47 @@
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::ops::Range;
 use std::path::PathBuf;
-use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{Receiver, Sender};
 use std::thread::JoinHandle;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
 
 use arboard::Clipboard;
-use notify::RecommendedWatcher;
+use notify::{EventKind, RecommendedWatcher};
+use ratatui::crossterm::event::Event;
 use ratatui::layout::Rect;
 use ratatui::widgets::ListState;
+use tempfile::TempDir;
 
+use crate::cargo_check::CargoDiagnostics;
+use crate::diff::{DiffHunk, DiffOp, SideBySideRow};
+use crate::file_io::FileIoEvent;
+use crate::git_branch::BranchSwitch;
+use crate::git_log::GitCommit;
+use crate::ignore::IgnoreRules;
+use crate::json_log::{LogFilter, LogRecord};
 use crate::keybinds::{KeyAction, KeyBind, KeyBindings};
 use crate::lsp_client::{LspClient, LspCompletionItem};
+use crate::persistence::{
+    EditorSettings, FileTypeConfig, LspSettings, SearchHistory, StatusSettings, WatchSettings,
+};
+use crate::playground::SnippetRun;
+use crate::preview::ImagePreview;
+use crate::project_search::SearchEvent;
+use crate::syntax::SyntaxLang;
 use crate::tab::{GitChangeSummary, GitFileStatus, GitLineStatus, ProjectSearchHit, Tab};
+use crate::tasks::{Task, TaskEvent};
+use crate::test_explorer::TestOutcome;
 use crate::theme::Theme;
 use crate::tree_item::TreeItem;
-use crate::types::{CommandAction, Focus, PendingAction, PromptState};
+use crate::types::{
+    BreadcrumbTarget, CommandAction, Focus, FormState, PendingAction, ProjectSearchOptions,
+    PromptState, QuickOpenItem, StatusLevel, TreeClipboard,
+};
 
 pub(crate) struct GitResult {
     pub branch: Option<String>,
@@ -23,13 +50,35 @@ pub(crate) struct GitResult {
     pub line_statuses: Vec<(PathBuf, Vec<GitLineStatus>)>,
 }
 
+mod bookmarks;
+mod branches;
+mod breadcrumbs;
+mod cargo_check;
 mod core;
 mod editor;
+mod file_io;
 mod file_tree;
+mod follow;
+mod form;
+mod git_log;
+mod git_revert;
+mod hex_edit;
 mod input;
 mod input_handlers;
+mod locations;
+mod log_view;
 mod lsp;
+mod lsp_actions;
+mod merge_conflicts;
+mod playground;
+mod quick_open;
+mod rust_modules;
 mod search;
+mod settings;
+mod tasks;
+mod test_explorer;
+mod tree_selection;
+mod workspace;
 
 pub(crate) struct ContextMenuState {
     pub(crate) open: bool,
@@ -44,6 +93,23 @@ pub(crate) struct SearchResultsState {
     pub(crate) query: String,
     pub(crate) results: Vec<ProjectSearchHit>,
     pub(crate) index: usize,
+    /// Replacement text once entered; the popup then previews each hit
+    /// as a diff and `a` applies it.
+    pub(crate) replacement: Option<String>,
+    /// Indices into `results` toggled off for the replacement.
+    pub(crate) excluded: HashSet<usize>,
+    /// Flags the results were searched with; `literal` is forced on for
+    /// the built-in search used without ripgrep.
+    pub(crate) options: ProjectSearchOptions,
+    /// The rg search still streaming hits into `results`.
+    pub(crate) running: Option<RunningSearch>,
+}
+
+/// A project search on a worker thread; see `project_search::spawn_search`.
+pub(crate) struct RunningSearch {
+    pub(crate) rx: Receiver<SearchEvent>,
+    pub(crate) stop: Arc<AtomicBool>,
+    pub(crate) started: Instant,
 }
 
 pub(crate) struct CompletionState {
@@ -72,8 +138,277 @@ pub(crate) struct KeybindEditorState {
     pub(crate) actions: Vec<KeyAction>,
 }
 
+/// An in-flight LSP request and where the cursor was when it was sent, so
+/// it can be cancelled once the user moves on.
+#[derive(Debug, Clone)]
+pub(crate) struct PendingLspRequest {
+    pub(crate) id: i64,
+    pub(crate) path: PathBuf,
+    pub(crate) cursor: (usize, usize),
+}
+
+/// A folder added to the workspace next to the project root, shown as its
+/// own section of the tree.
+pub(crate) struct WorkspaceFolder {
+    pub(crate) path: PathBuf,
+    pub(crate) branch: Option<String>,
+    pub(crate) ignore_rules: IgnoreRules,
+}
+
+/// Preview shown instead of a tab for files that are not opened as text:
+/// type and size, an image thumbnail when one decodes, and a hex dump.
+pub(crate) struct HexViewState {
+    pub(crate) path: PathBuf,
+    /// The first `App::BINARY_PREVIEW_BYTES` of the file.
+    pub(crate) bytes: Vec<u8>,
+    /// First visible row (16 bytes per row).
+    pub(crate) scroll: usize,
+    /// Size of the whole file.
+    pub(crate) size: u64,
+    pub(crate) kind: &'static str,
+    /// Why the file was not opened as text.
+    pub(crate) reason: Option<String>,
+    pub(crate) image: Option<ImagePreview>,
+    /// "Open as text" from here opens a preview tab.
+    pub(crate) as_preview: bool,
+    /// Hex rows the last frame showed, for paging and keeping the cursor
+    /// in view.
+    pub(crate) page_rows: usize,
+    /// Set while the bytes are being edited.
+    pub(crate) edit: Option<HexEdit>,
+}
+
+/// Byte editing in the hex view.
+#[derive(Debug, Default)]
+pub(crate) struct HexEdit {
+    /// Byte under the cursor; `bytes.len()` is the append position.
+    pub(crate) cursor: usize,
+    /// Typing inserts bytes instead of overwriting them.
+    pub(crate) insert: bool,
+    /// Typing goes to the ASCII column instead of the hex digits.
+    pub(crate) ascii: bool,
+    /// The high digit of the byte under the cursor was typed; the next
+    /// digit completes it.
+    pub(crate) half_typed: bool,
+    pub(crate) dirty: bool,
+    /// Esc was pressed with unsaved edits; a second Esc discards them.
+    pub(crate) confirm_discard: bool,
+}
+
+/// Full-screen unified diff from the file on disk to the unsaved buffer,
+/// opened from the close and conflict prompts.
+pub(crate) struct DiffViewState {
+    pub(crate) path: PathBuf,
+    pub(crate) rows: Vec<(Option<DiffOp>, String)>,
+    pub(crate) scroll: usize,
+    /// What the `-` and `+` sides are, e.g. "- disk, + buffer".
+    pub(crate) legend: &'static str,
+}
+
+/// "Diff with HEAD": the committed file beside the buffer. The diff runs
+/// from the buffer (`old`) to HEAD (`new`), so reverting a hunk takes its
+/// `new` side.
+pub(crate) struct HeadDiffState {
+    pub(crate) path: PathBuf,
+    pub(crate) head: Vec<String>,
+    pub(crate) buffer: Vec<String>,
+    pub(crate) hunks: Vec<DiffHunk>,
+    pub(crate) rows: Vec<SideBySideRow>,
+    /// Index into `hunks`.
+    pub(crate) selected: usize,
+    pub(crate) scroll: usize,
+}
+
+/// "Show Last Message" popup over the message log.
+pub(crate) struct MessageViewState {
+    /// Entry shown, indexing `message_log` (oldest first).
+    pub(crate) index: usize,
+    pub(crate) scroll: usize,
+    pub(crate) wrap: bool,
+}
+
+/// One file in a batch rename preview.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BatchRenameEntry {
+    pub(crate) from: PathBuf,
+    pub(crate) to: PathBuf,
+    /// Why this rename cannot go ahead, e.g. a name collision.
+    pub(crate) problem: Option<String>,
+}
+
+/// Preview of "Batch Rename" on a folder; Enter applies every rename.
+pub(crate) struct BatchRenameState {
+    pub(crate) dir: PathBuf,
+    pub(crate) entries: Vec<BatchRenameEntry>,
+    pub(crate) selected: usize,
+}
+
+/// Hunk-by-hunk merge of the buffer with the text that changed on disk,
+/// opened from the conflict prompt. Hunks start out keeping the buffer.
+pub(crate) struct MergeViewState {
+    pub(crate) buffer: Vec<String>,
+    pub(crate) disk: Vec<String>,
+    pub(crate) hunks: Vec<DiffHunk>,
+    pub(crate) take_disk: Vec<bool>,
+    pub(crate) selected: usize,
+    pub(crate) scroll: usize,
+}
+
+/// Follow Mode broadcast of the active buffer to `target`.
+pub(crate) struct FollowBroadcast {
+    pub(crate) target: PathBuf,
+    /// Snapshot last written, so an unchanged buffer is not rewritten.
+    pub(crate) last: String,
+    pub(crate) checked_at: Option<Instant>,
+}
+
+/// Read-only mirror of another session's Follow Mode broadcast.
+pub(crate) struct FollowView {
+    pub(crate) source: PathBuf,
+    /// Project root of the broadcasting session, for the title.
+    pub(crate) root: PathBuf,
+    /// Buffer being mirrored; `None` while waiting for a broadcast.
+    pub(crate) tab: Option<Tab>,
+    /// Modification time and size of the snapshot shown.
+    pub(crate) stamp: Option<(SystemTime, u64)>,
+}
+
+/// Log mode for a JSON-lines file: records as aligned, level-colored rows,
+/// filtered, and tailed as the file grows.
+pub(crate) struct LogViewState {
+    pub(crate) path: PathBuf,
+    pub(crate) records: Vec<LogRecord>,
+    /// Bytes of complete lines read; appended lines are read from here.
+    pub(crate) read_bytes: u64,
+    /// The last record is an unterminated line past `read_bytes`.
+    pub(crate) tail_partial: bool,
+    /// Indices into `records` that pass `filter`.
+    pub(crate) visible: Vec<usize>,
+    pub(crate) filter: LogFilter,
+    /// Typing goes to `filter.query` until Enter or Esc.
+    pub(crate) editing_query: bool,
+    /// First row shown, as an index into `visible`.
+    pub(crate) scroll: usize,
+    /// Keep the newest record in view as lines are appended.
+    pub(crate) follow: bool,
+}
+
+/// Popup with the output of "Run Code Block".
+pub(crate) struct SnippetOutputState {
+    pub(crate) title: String,
+    pub(crate) text: String,
+    pub(crate) success: bool,
+    pub(crate) scroll: usize,
+}
+
+/// The "Run Task" picker over the project's tasks.
+pub(crate) struct TaskPicker {
+    pub(crate) tasks: Vec<Task>,
+    pub(crate) query: String,
+    /// Index into the tasks matching `query`.
+    pub(crate) selected: usize,
+}
+
+/// A line of task output and the `file:line` it names, if any.
+pub(crate) struct TaskOutputLine {
+    pub(crate) text: String,
+    pub(crate) location: Option<(PathBuf, usize, usize)>,
+}
+
+/// The bottom panel with the output of the last task run.
+pub(crate) struct TaskPanelState {
+    pub(crate) label: String,
+    /// The env file loaded into the task's environment, as named in
+    /// `lsp.json`.
+    pub(crate) env_file: Option<String>,
+    pub(crate) lines: Vec<TaskOutputLine>,
+    /// The running task's events; `None` once it finished.
+    pub(crate) rx: Option<Receiver<TaskEvent>>,
+    pub(crate) stop: Arc<AtomicBool>,
+    /// Exit code once finished; `None` inside when it was killed.
+    pub(crate) exit: Option<Option<i32>>,
+    /// First line shown.
+    pub(crate) scroll: usize,
+    /// Keep the newest line in view as output arrives.
+    pub(crate) follow: bool,
+    pub(crate) visible: bool,
+}
+
+/// The "Test Explorer" popup over the workspace's `cargo test` tests. It
+/// is kept when closed so results survive reopening.
+pub(crate) struct TestExplorerState {
+    /// Full test paths, sorted.
+    pub(crate) tests: Vec<String>,
+    /// Modules whose children are hidden.
+    pub(crate) collapsed: BTreeSet<String>,
+    /// Index into the visible rows.
+    pub(crate) selected: usize,
+    pub(crate) outcomes: HashMap<String, TestOutcome>,
+    /// Where each failed test panicked, as `(path, row, col)`.
+    pub(crate) failures: HashMap<String, (PathBuf, usize, usize)>,
+    /// Pending `cargo test -- --list`.
+    pub(crate) list_rx: Option<Receiver<io::Result<Vec<String>>>>,
+    pub(crate) visible: bool,
+}
+
+/// "Switch Branch" popup over the local branches.
+pub(crate) struct BranchPicker {
+    pub(crate) branches: Vec<String>,
+    pub(crate) query: String,
+    /// Index into the branches matching `query`.
+    pub(crate) selected: usize,
+}
+
+/// The "Git Log" popup, for the project or one file.
+pub(crate) struct GitLogState {
+    /// The file whose history is shown; `None` for the whole project.
+    pub(crate) file: Option<PathBuf>,
+    pub(crate) commits: Vec<GitCommit>,
+    pub(crate) selected: usize,
+    /// `git show` of the selected commit, while Enter has it open.
+    pub(crate) details: Option<Vec<String>>,
+    pub(crate) details_scroll: usize,
+}
+
+/// The "Preferences: Open Settings" popup.
+pub(crate) struct SettingsViewState {
+    pub(crate) query: String,
+    /// Index into the settings matching `query`.
+    pub(crate) selected: usize,
+    /// Text being typed for the selected setting, until Enter or Esc.
+    pub(crate) editing: Option<String>,
+    /// Why the last edit was rejected.
+    pub(crate) error: Option<String>,
+}
+
+/// The "List Bookmarks" popup: every bookmark of the project as
+/// `(path, row, line text)`.
+pub(crate) struct BookmarksView {
+    pub(crate) entries: Vec<(PathBuf, usize, String)>,
+    pub(crate) selected: usize,
+}
+
+/// Where a tab was left when it was closed, so reopening its file
+/// returns there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ClosedTab {
+    pub(crate) path: PathBuf,
+    pub(crate) cursor: (usize, usize),
+    pub(crate) scroll_row: usize,
+    pub(crate) scroll_col: usize,
+}
+
+/// A picker over file positions as `(path, row, col, context)`: pasted
+/// compiler output, or sibling Rust modules.
+pub(crate) struct LocationPicker {
+    pub(crate) title: String,
+    pub(crate) entries: Vec<(PathBuf, usize, usize, String)>,
+    pub(crate) selected: usize,
+}
+
 pub(crate) struct FsChangeEvent {
     pub(crate) paths: Vec<PathBuf>,
+    pub(crate) kind: EventKind,
     pub(crate) full_refresh: bool,
 }
 
@@ -83,20 +418,44 @@ pub(crate) struct App {
     pub(crate) selected: usize,
     pub(crate) tree_state: ListState,
     pub(crate) expanded: HashSet<PathBuf>,
+    /// Entries of the folders the tree shows, as `(path, is_dir)` in tree
+    /// order, so a refresh re-reads only the folders a change touched.
+    pub(crate) dir_listings: RefCell<HashMap<PathBuf, Vec<(PathBuf, bool)>>>,
     pub(crate) focus: Focus,
     pub(crate) tabs: Vec<Tab>,
     pub(crate) active_tab: usize,
     pub(crate) last_tree_click: Option<(Instant, usize)>,
     pub(crate) status: String,
+    pub(crate) status_level: StatusLevel,
+    pub(crate) status_set_at: Instant,
+    /// Until when the focused pane's border flashes after a rejected action.
+    pub(crate) flash_until: Option<Instant>,
+    /// Ring the terminal bell after the next draw.
+    pub(crate) bell_pending: bool,
+    pub(crate) status_settings: StatusSettings,
+    pub(crate) editor_settings: EditorSettings,
+    /// Diagnostic last echoed for the cursor row, so it is shown once when
+    /// the cursor arrives rather than rewritten every frame.
+    pub(crate) cursor_diag_status: Option<String>,
+    pub(crate) status_rect: Rect,
+    /// Clickable breadcrumbs in the status bar, set each frame.
+    pub(crate) breadcrumb_rects: Vec<(Rect, BreadcrumbTarget)>,
     pub(crate) pending: PendingAction,
+    /// When the current confirmation was asked, for its auto-cancel.
+    pub(crate) pending_since: Option<Instant>,
     pub(crate) quit: bool,
     pub(crate) files_view_open: bool,
     pub(crate) files_pane_width: u16,
     pub(crate) divider_dragging: bool,
+    /// Tab being dragged along the tab bar, at its current index.
+    pub(crate) tab_dragging: Option<usize>,
     pub(crate) menu_open: bool,
     pub(crate) menu_index: usize,
     pub(crate) menu_query: String,
     pub(crate) menu_results: Vec<CommandAction>,
+    /// Commands run from the palette, most recent first, capped at
+    /// `RECENT_COMMANDS_LEN`; persisted with the app state by label.
+    pub(crate) recent_commands: Vec<CommandAction>,
     pub(crate) menu_rect: Rect,
     pub(crate) theme_browser_open: bool,
     pub(crate) theme_browser_rect: Rect,
@@ -111,7 +470,16 @@ pub(crate) struct App {
     pub(crate) editor_rect: Rect,
     pub(crate) divider_rect: Rect,
     pub(crate) tab_rects: Vec<(Rect, Rect)>,
+    /// Tabs currently shown in the tab bar, and the ‹ › markers for the
+    /// ones scrolled out of it (empty rects when nothing is hidden).
+    pub(crate) tab_strip: Range<usize>,
+    pub(crate) tab_scroll_rects: (Rect, Rect),
     pub(crate) context_menu: ContextMenuState,
+    pub(crate) tree_clipboard: Option<TreeClipboard>,
+    /// Tree entries marked with Space or Shift+arrows, in marking order.
+    pub(crate) tree_marked: Vec<PathBuf>,
+    /// Where a Shift+arrow range in the tree starts.
+    pub(crate) tree_mark_anchor: Option<PathBuf>,
     pub(crate) prompt: Option<PromptState>,
     pub(crate) prompt_rect: Rect,
     pub(crate) clipboard: Option<Clipboard>,
@@ -126,21 +494,98 @@ pub(crate) struct App {
     pub(crate) search_results_rect: Rect,
     pub(crate) file_picker_open: bool,
     pub(crate) file_picker_query: String,
-    pub(crate) file_picker_results: Vec<PathBuf>,
+    /// Files, or with an `@`, `#` or `:` prefix on the query, symbols in
+    /// the active file, workspace symbols or a line.
+    pub(crate) file_picker_results: Vec<QuickOpenItem>,
     pub(crate) file_picker_index: usize,
+    /// Quick Open limited to `changed_files`, the files git reports as changed.
+    pub(crate) file_picker_changed_only: bool,
+    pub(crate) changed_files: Vec<(PathBuf, GitFileStatus)>,
     pub(crate) file_picker_rect: Rect,
     pub(crate) lsp: Option<LspClient>,
     pub(crate) completion: CompletionState,
-    pub(crate) pending_completion_request: Option<i64>,
-    pub(crate) pending_definition_request: Option<i64>,
+    pub(crate) pending_completion_request: Option<PendingLspRequest>,
+    pub(crate) pending_definition_request: Option<PendingLspRequest>,
+    /// `workspace/symbol` request id for a `#` query in Quick Open.
+    pub(crate) pending_workspace_symbol_request: Option<i64>,
+    pub(crate) pending_references_request: Option<PendingLspRequest>,
+    /// A `textDocument/rename` request and the new name.
+    pub(crate) pending_rename_request: Option<(PendingLspRequest, String)>,
+    pub(crate) pending_format_request: Option<PendingLspRequest>,
+    pub(crate) watch_settings: WatchSettings,
     pub(crate) fs_watcher: Option<RecommendedWatcher>,
+    /// Set instead of `fs_watcher` for roots `watch.json` says to poll.
+    pub(crate) fs_poll_interval: Option<Duration>,
+    pub(crate) fs_poll_last: Instant,
+    /// Modification time and size last seen for each polled path; `None`
+    /// while the path is missing.
+    pub(crate) fs_poll_stamps: HashMap<PathBuf, Option<(SystemTime, u64)>>,
     pub(crate) fs_rx: Option<Receiver<FsChangeEvent>>,
+    /// Background reads and writes of files on slow filesystems report
+    /// here; see `TabIo`.
+    pub(crate) file_io_tx: Sender<FileIoEvent>,
+    pub(crate) file_io_rx: Receiver<FileIoEvent>,
+    pub(crate) file_writes_in_flight: usize,
     pub(crate) fs_refresh_pending: bool,
     pub(crate) fs_full_refresh_pending: bool,
     pub(crate) fs_changed_paths: HashSet<PathBuf>,
+    /// Keys and pastes waiting for the active file's pending reload.
+    pub(crate) held_input: Vec<Event>,
     pub(crate) last_fs_refresh: Instant,
     pub(crate) autosave_last_write: Instant,
     pub(crate) replace_after_find: bool,
+    pub(crate) replace_after_project_search: bool,
+    pub(crate) project_search_options: ProjectSearchOptions,
+    pub(crate) search_history: SearchHistory,
+    /// Per-file language modes, persisted with the app state.
+    pub(crate) language_overrides: BTreeMap<PathBuf, SyntaxLang>,
+    /// Selected row while the language overrides list is open.
+    pub(crate) language_overrides_view: Option<usize>,
+    /// Trust decisions by project root, persisted with the app state.
+    pub(crate) trusted_projects: BTreeMap<PathBuf, bool>,
+    /// Pinned files and folders by project root, in pin order; persisted
+    /// with the app state.
+    pub(crate) pinned_paths: BTreeMap<PathBuf, Vec<PathBuf>>,
+    /// Files opened in each project root, most recent first; Quick Open
+    /// lists them ahead of the rest.
+    pub(crate) recent_files: BTreeMap<PathBuf, Vec<PathBuf>>,
+    /// Bookmarked rows by project root and file, as of the saved file;
+    /// persisted with the app state. Open tabs keep a live copy that
+    /// follows edits.
+    pub(crate) bookmarks: BTreeMap<PathBuf, BTreeMap<PathBuf, BTreeSet<usize>>>,
+    pub(crate) bookmarks_view: Option<BookmarksView>,
+    pub(crate) location_picker: Option<LocationPicker>,
+    /// Folders added to the workspace by project root; persisted with the
+    /// app state.
+    pub(crate) workspace_folder_paths: BTreeMap<PathBuf, Vec<PathBuf>>,
+    /// This project's workspace folders, loaded from `workspace_folder_paths`.
+    pub(crate) workspace_folders: Vec<WorkspaceFolder>,
+    /// Project roots opened, most recent first, capped at
+    /// `RECENT_PROJECTS_LEN`; persisted with the app state.
+    pub(crate) recent_projects: Vec<PathBuf>,
+    /// Selected entry while the "Open Recent Project" picker is open.
+    pub(crate) recent_projects_view: Option<usize>,
+    /// Recent status messages, oldest first, capped at `MESSAGE_LOG_LEN`.
+    pub(crate) message_log: VecDeque<(StatusLevel, String)>,
+    pub(crate) message_view: Option<MessageViewState>,
+    /// Where edits happened this session as `(path, row, col)`, oldest
+    /// first; "Go to Last Edit Location" walks back through them.
+    pub(crate) edit_locations: Vec<(PathBuf, usize, usize)>,
+    /// Entry the last "Go to Last Edit Location" jumped to; cleared by the
+    /// next edit.
+    pub(crate) edit_location_index: Option<usize>,
+    /// Positions left by jumps as `(path, row, col)`, oldest first, capped
+    /// at `JUMP_LIST_LEN`; "Jump Back" returns to them.
+    pub(crate) jump_back: Vec<(PathBuf, usize, usize)>,
+    /// Positions "Jump Back" left, newest last, for "Jump Forward".
+    pub(crate) jump_forward: Vec<(PathBuf, usize, usize)>,
+    /// Tabs closed this session, newest last, capped at `CLOSED_TABS_LEN`;
+    /// "Reopen Closed Tab" takes the newest.
+    pub(crate) closed_tabs: Vec<ClosedTab>,
+    /// Copied and cut text, newest first, capped at `CLIPBOARD_HISTORY_LEN`.
+    pub(crate) clipboard_history: VecDeque<String>,
+    /// Selected entry while the "Paste from History" popup is open.
+    pub(crate) clipboard_history_view: Option<usize>,
     pub(crate) git_branch: Option<String>,
     pub(crate) enhanced_keys: bool,
     pub(crate) word_wrap: bool,
@@ -154,6 +599,48 @@ pub(crate) struct App {
     pub(crate) git_refresh_in_flight: bool,
     pub(crate) git_thread_handle: Option<JoinHandle<()>>,
     pub(crate) cached_file_list: Vec<PathBuf>,
+    pub(crate) ignore_rules: IgnoreRules,
+    pub(crate) show_ignored: bool,
+    pub(crate) nerd_font_icons: bool,
+    pub(crate) filetype_config: FileTypeConfig,
+    pub(crate) lsp_settings: LspSettings,
+    pub(crate) hex_view: Option<HexViewState>,
+    pub(crate) diff_view: Option<DiffViewState>,
+    pub(crate) merge_view: Option<MergeViewState>,
+    pub(crate) head_diff: Option<HeadDiffState>,
+    pub(crate) batch_rename: Option<BatchRenameState>,
+    pub(crate) form: Option<FormState>,
+    pub(crate) follow_broadcast: Option<FollowBroadcast>,
+    pub(crate) follow_view: Option<FollowView>,
+    /// Pending "Run Code Block" build, polled once per frame.
+    pub(crate) snippet_rx: Option<Receiver<io::Result<SnippetRun>>>,
+    /// Scratch crate directory for "Run Code Block", made on first use.
+    pub(crate) playground_dir: Option<TempDir>,
+    pub(crate) snippet_output: Option<SnippetOutputState>,
+    pub(crate) task_picker: Option<TaskPicker>,
+    pub(crate) task_panel: Option<TaskPanelState>,
+    pub(crate) task_panel_rect: Rect,
+    pub(crate) test_explorer: Option<TestExplorerState>,
+    pub(crate) git_log: Option<GitLogState>,
+    pub(crate) branch_picker: Option<BranchPicker>,
+    /// Running checkout, polled once per frame.
+    pub(crate) branch_switch: Option<(BranchSwitch, Receiver<io::Result<()>>)>,
+    /// The branch name in the status bar, set each frame; clicking it opens
+    /// the branch picker.
+    pub(crate) branch_rect: Rect,
+    /// Diagnostics from the last `cargo check`, for every file.
+    pub(crate) cargo_diagnostics: CargoDiagnostics,
+    /// Running `cargo check`, polled once per frame.
+    pub(crate) cargo_check_rx: Option<Receiver<io::Result<CargoDiagnostics>>>,
+    /// When the next check starts, pushed back by each save.
+    pub(crate) cargo_check_due: Option<Instant>,
+    pub(crate) log_view: Option<LogViewState>,
+    pub(crate) settings_view: Option<SettingsViewState>,
+    /// Cursor position when the Find prompt opened; incremental search
+    /// restarts from here on every keystroke and Esc returns to it.
+    pub(crate) find_origin: Option<(usize, usize)>,
+    /// Find bar left open after confirming a search, so n/N cycle matches.
+    pub(crate) find_bar_open: bool,
 }
 
 impl Drop for App {
@@ -161,5 +648,11 @@ impl Drop for App {
         if let Some(handle) = self.git_thread_handle.take() {
             let _ = handle.join();
         }
+        if let Some(broadcast) = self.follow_broadcast.take() {
+            let _ = fs::remove_file(broadcast.target);
+        }
+        if let Some(panel) = &self.task_panel {
+            panel.stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
     }
 }
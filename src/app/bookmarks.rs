@@ -0,0 +1,297 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use super::{App, BookmarksView};
+use crate::util::relative_path;
+
+impl App {
+    /// Give a newly opened tab the bookmarks saved for its file.
+    pub(crate) fn load_tab_bookmarks(&mut self) {
+        let saved = self.bookmarks.get(&self.root).cloned().unwrap_or_default();
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        let lines = tab.editor.lines().len();
+        tab.bookmarks = saved
+            .get(&tab.path)
+            .map(|rows| rows.iter().copied().filter(|&r| r < lines).collect())
+            .unwrap_or_default();
+        tab.bookmarks_line_count = lines;
+    }
+
+    /// Save the active tab's bookmarks as they are now, which matches the
+    /// file once it is saved.
+    pub(crate) fn store_tab_bookmarks(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let (path, rows) = (tab.path.clone(), tab.bookmarks.clone());
+        let files = self.bookmarks.entry(self.root.clone()).or_default();
+        let changed = if rows.is_empty() {
+            files.remove(&path).is_some()
+        } else {
+            files.insert(path, rows.clone()) != Some(rows)
+        };
+        if files.is_empty() {
+            self.bookmarks.remove(&self.root);
+        }
+        if changed {
+            self.persist_state();
+        }
+    }
+
+    pub(crate) fn toggle_bookmark(&mut self) {
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        let row = tab.editor.cursor().0;
+        let added = tab.bookmarks.insert(row);
+        if !added {
+            tab.bookmarks.remove(&row);
+        }
+        // Unsaved edits may have moved rows; those are stored on save.
+        if !tab.dirty {
+            self.store_tab_bookmarks();
+        }
+        if added {
+            self.set_status(format!("Bookmarked line {}", row + 1));
+        } else {
+            self.set_status(format!("Removed the bookmark on line {}", row + 1));
+        }
+    }
+
+    /// Keep the active tab's bookmarks on their lines after an edit added
+    /// or removed some: rows after the edit move by the difference, and
+    /// bookmarks on removed rows go.
+    pub(crate) fn shift_bookmarks(&mut self) {
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        let lines = tab.editor.lines().len();
+        let before = std::mem::replace(&mut tab.bookmarks_line_count, lines);
+        if lines == before || tab.bookmarks.is_empty() {
+            return;
+        }
+        let row = tab.editor.cursor().0;
+        tab.bookmarks = if lines > before {
+            let added = lines - before;
+            let start = row.saturating_sub(added);
+            tab.bookmarks
+                .iter()
+                .map(|&r| if r > start { r + added } else { r })
+                .collect()
+        } else {
+            let removed = before - lines;
+            tab.bookmarks
+                .iter()
+                .filter(|&&r| r <= row || r > row + removed)
+                .map(|&r| if r > row { r - removed } else { r })
+                .collect()
+        };
+    }
+
+    /// Every bookmark in the project, by path then row. Open tabs give
+    /// their live rows.
+    fn project_bookmarks(&self) -> Vec<(PathBuf, usize)> {
+        let mut files = self.bookmarks.get(&self.root).cloned().unwrap_or_default();
+        for tab in &self.tabs {
+            files.insert(tab.path.clone(), tab.bookmarks.clone());
+        }
+        files
+            .into_iter()
+            .flat_map(|(path, rows)| rows.into_iter().map(move |row| (path.clone(), row)))
+            .collect()
+    }
+
+    /// Go to the next or previous bookmark after the cursor, across files,
+    /// wrapping around at the ends.
+    pub(crate) fn jump_to_bookmark(&mut self, next: bool) -> io::Result<()> {
+        let all = self.project_bookmarks();
+        if all.is_empty() {
+            self.reject_action("No bookmarks");
+            return Ok(());
+        }
+        let here = self
+            .active_tab()
+            .map(|t| (t.path.clone(), t.editor.cursor().0));
+        let target = match (&here, next) {
+            (Some(here), true) => all.iter().find(|&b| b > here).unwrap_or(&all[0]),
+            (Some(here), false) => all
+                .iter()
+                .rev()
+                .find(|&b| b < here)
+                .unwrap_or(&all[all.len() - 1]),
+            (None, _) => &all[0],
+        };
+        let (path, row) = target.clone();
+        let idx = all
+            .iter()
+            .position(|b| *b == (path.clone(), row))
+            .unwrap_or(0);
+        self.go_to_bookmark(path, row)?;
+        self.set_status(format!("Bookmark {}/{}", idx + 1, all.len()));
+        Ok(())
+    }
+
+    fn go_to_bookmark(&mut self, path: PathBuf, row: usize) -> io::Result<()> {
+        self.record_jump();
+        self.go_to_location(&path, row, 0)?;
+        Ok(())
+    }
+
+    pub(crate) fn open_bookmarks_list(&mut self) {
+        let all = self.project_bookmarks();
+        if all.is_empty() {
+            self.reject_action("No bookmarks");
+            return;
+        }
+        let mut unopened: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+        let entries = all
+            .into_iter()
+            .map(|(path, row)| {
+                let text = match self.tabs.iter().find(|t| t.path == path) {
+                    Some(tab) => tab.editor.lines().get(row).cloned(),
+                    None => unopened
+                        .entry(path.clone())
+                        .or_insert_with(|| {
+                            fs::read_to_string(&path)
+                                .map(|s| s.lines().map(str::to_string).collect())
+                                .unwrap_or_default()
+                        })
+                        .get(row)
+                        .cloned(),
+                };
+                (path, row, text.unwrap_or_default().trim().to_string())
+            })
+            .collect();
+        self.bookmarks_view = Some(BookmarksView {
+            entries,
+            selected: 0,
+        });
+    }
+
+    pub(crate) fn handle_bookmarks_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(view) = self.bookmarks_view.as_mut() else {
+            return Ok(());
+        };
+        let last = view.entries.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.bookmarks_view = None,
+            KeyCode::Down | KeyCode::Char('j') => view.selected = (view.selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Delete | KeyCode::Char('d') => {
+                if view.selected >= view.entries.len() {
+                    return Ok(());
+                }
+                let (path, row, _) = view.entries.remove(view.selected);
+                view.selected = view.selected.min(last.saturating_sub(1));
+                if view.entries.is_empty() {
+                    self.bookmarks_view = None;
+                }
+                self.remove_bookmark(&path, row);
+                self.set_status(format!(
+                    "Removed the bookmark on {}:{}",
+                    relative_path(&self.root, &path).display(),
+                    row + 1
+                ));
+            }
+            KeyCode::Enter => {
+                let entry = view.entries.get(view.selected).cloned();
+                self.bookmarks_view = None;
+                if let Some((path, row, _)) = entry {
+                    self.go_to_bookmark(path, row)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn remove_bookmark(&mut self, path: &PathBuf, row: usize) {
+        if let Some(idx) = self.tabs.iter().position(|t| t.path == *path) {
+            let tab = &mut self.tabs[idx];
+            tab.bookmarks.remove(&row);
+            if !tab.dirty {
+                let active = std::mem::replace(&mut self.active_tab, idx);
+                self.store_tab_bookmarks();
+                self.active_tab = active;
+            }
+            return;
+        }
+        let Some(files) = self.bookmarks.get_mut(&self.root) else {
+            return;
+        };
+        if let Some(rows) = files.get_mut(path) {
+            rows.remove(&row);
+            if rows.is_empty() {
+                files.remove(path);
+            }
+        }
+        self.persist_state();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+    use tempfile::tempdir;
+
+    fn ctrl_alt(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+
+    #[test]
+    fn bookmarks_follow_edits_and_cycle_across_files() {
+        let tmp = tempdir().expect("tempdir");
+        let a = tmp.path().join("a.txt");
+        let b = tmp.path().join("b.txt");
+        fs::write(&a, "one\ntwo\nthree\nfour\n").expect("write");
+        fs::write(&b, "alpha\nbeta\n").expect("write");
+        let mut app = App::new_for_test(tmp.path());
+        app.open_file(b.clone()).expect("open");
+        app.tabs[0].jump_to((1, 0));
+        app.handle_key(ctrl_alt('k')).expect("toggle");
+        app.open_file(a.clone()).expect("open");
+        app.tabs[1].jump_to((2, 0));
+        app.handle_key(ctrl_alt('k')).expect("toggle");
+        assert_eq!(
+            app.bookmarks[&app.root][&a]
+                .iter()
+                .copied()
+                .collect::<Vec<_>>(),
+            [2]
+        );
+
+        // Lines added above move the bookmark; it is saved with the file.
+        app.tabs[1].jump_to((0, 3));
+        app.insert_newline_with_indent();
+        app.on_editor_content_changed();
+        assert!(app.tabs[1].bookmarks.contains(&3));
+        assert!(app.bookmarks[&app.root][&a].contains(&2));
+        app.save_file().expect("save");
+        assert!(app.bookmarks[&app.root][&a].contains(&3));
+
+        // Next wraps from a.txt's bookmark round to b.txt's.
+        app.tabs[1].jump_to((3, 0));
+        app.handle_key(ctrl_alt('l')).expect("next");
+        assert_eq!(app.open_path(), Some(&b));
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (1, 0));
+        app.handle_key(ctrl_alt('j')).expect("prev");
+        assert_eq!(app.open_path(), Some(&a));
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (3, 0));
+
+        app.open_bookmarks_list();
+        let view = app.bookmarks_view.as_ref().expect("list");
+        assert_eq!(view.entries[0], (a.clone(), 3, "three".to_string()));
+        assert_eq!(view.entries[1], (b.clone(), 1, "beta".to_string()));
+        app.handle_bookmarks_key(KeyEvent::from(KeyCode::Char('d')))
+            .expect("remove");
+        assert!(app.tabs[1].bookmarks.is_empty());
+        assert!(!app.bookmarks[&app.root].contains_key(&a));
+    }
+}
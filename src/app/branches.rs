@@ -0,0 +1,231 @@
+use std::io;
+use std::sync::mpsc::TryRecvError;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use super::{App, BranchPicker};
+use crate::git_branch::{BranchSwitch, local_branches, spawn_branch_switch};
+use crate::types::{PromptMode, PromptState};
+use crate::util::fuzzy_score;
+
+impl App {
+    /// "Switch Branch", or a click on the branch in the status bar: pick a
+    /// local branch, or type a new name to create it.
+    pub(crate) fn open_branch_picker(&mut self) {
+        if self.branch_switch.is_some() {
+            self.set_warning("A branch switch is already running");
+            return;
+        }
+        match local_branches(&self.root) {
+            Ok(branches) => {
+                self.branch_picker = Some(BranchPicker {
+                    branches,
+                    query: String::new(),
+                    selected: 0,
+                });
+            }
+            Err(err) => self.set_warning(format!("Cannot list branches: {err}")),
+        }
+    }
+
+    /// The picker's branches matching its query, best first.
+    pub(crate) fn branch_picker_matches(picker: &BranchPicker) -> Vec<&String> {
+        let query = picker.query.to_ascii_lowercase();
+        let mut scored: Vec<(usize, &String)> = picker
+            .branches
+            .iter()
+            .filter_map(|branch| fuzzy_score(&query, branch).map(|score| (score, branch)))
+            .collect();
+        scored.sort_by_key(|(score, _)| *score);
+        scored.into_iter().map(|(_, branch)| branch).collect()
+    }
+
+    pub(crate) fn handle_branch_picker_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(picker) = self.branch_picker.as_mut() else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc => self.branch_picker = None,
+            KeyCode::Down => {
+                let last = Self::branch_picker_matches(picker).len().saturating_sub(1);
+                picker.selected = (picker.selected + 1).min(last);
+            }
+            KeyCode::Up => picker.selected = picker.selected.saturating_sub(1),
+            KeyCode::Backspace => {
+                picker.query.pop();
+                picker.selected = 0;
+            }
+            KeyCode::Char(c) => {
+                picker.query.push(c);
+                picker.selected = 0;
+            }
+            KeyCode::Enter => {
+                let query = picker.query.trim().to_string();
+                // A name matching no branch creates it.
+                let switch = match Self::branch_picker_matches(picker).get(picker.selected) {
+                    Some(branch) => Some(BranchSwitch::Checkout((*branch).clone())),
+                    None if !query.is_empty() => Some(BranchSwitch::Create(query)),
+                    None => None,
+                };
+                self.branch_picker = None;
+                if let Some(switch) = switch {
+                    self.switch_branch(switch);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// "Create Branch": name a branch to create from HEAD and switch to.
+    pub(crate) fn open_create_branch_prompt(&mut self) {
+        self.prompt = Some(PromptState {
+            title: "New branch name (created from HEAD)".to_string(),
+            value: String::new(),
+            cursor: 0,
+            anchor: None,
+            history: None,
+            mode: PromptMode::CreateBranch,
+        });
+    }
+
+    /// Check out or create a branch in the background. Checking out is
+    /// refused while buffers have unsaved edits, since the files under
+    /// them may change.
+    pub(crate) fn switch_branch(&mut self, switch: BranchSwitch) {
+        if self.branch_switch.is_some() {
+            self.set_warning("A branch switch is already running");
+            return;
+        }
+        if let BranchSwitch::Checkout(name) = &switch {
+            if self.git_branch.as_deref() == Some(name.as_str()) {
+                self.set_status(format!("Already on {name}"));
+                return;
+            }
+            let dirty = self.tabs.iter().filter(|t| t.dirty).count();
+            if dirty > 0 {
+                self.set_warning(format!(
+                    "Save or revert {dirty} unsaved file{} before switching branches",
+                    if dirty == 1 { "" } else { "s" }
+                ));
+                return;
+            }
+        }
+        let rx = spawn_branch_switch(&self.root, &switch);
+        self.set_status(format!("Switching to {}", switch.branch()));
+        self.branch_switch = Some((switch, rx));
+    }
+
+    /// Take in a finished branch switch: update the branch label, reload
+    /// the tabs whose files changed and refresh the tree and git markers.
+    pub(crate) fn poll_branch_switch(&mut self) -> io::Result<()> {
+        let Some((_, rx)) = self.branch_switch.as_ref() else {
+            return Ok(());
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return Ok(()),
+            Err(TryRecvError::Disconnected) => Err(io::Error::other("git thread exited")),
+        };
+        let Some((switch, _)) = self.branch_switch.take() else {
+            return Ok(());
+        };
+        if let Err(err) = result {
+            self.set_error(format!("Could not switch to {}: {err}", switch.branch()));
+            return Ok(());
+        }
+        self.git_branch = Some(switch.branch().to_string());
+        let active = self.active_tab;
+        for idx in 0..self.tabs.len() {
+            let tab = &self.tabs[idx];
+            if tab.read_only || tab.follow_tail || tab.large_file.is_some() {
+                continue;
+            }
+            self.active_tab = idx;
+            self.reload_open_file_from_disk_if_pristine()?;
+        }
+        self.active_tab = active;
+        self.rebuild_tree()?;
+        self.fs_refresh_pending = true;
+        self.fs_full_refresh_pending = true;
+        self.last_fs_refresh = Instant::now()
+            .checked_sub(Duration::from_millis(Self::FS_REFRESH_DEBOUNCE_MS + 1))
+            .unwrap_or_else(Instant::now);
+        self.set_status(match switch {
+            BranchSwitch::Checkout(name) => format!("Switched to branch {name}"),
+            BranchSwitch::Create(name) => format!("Created and switched to branch {name}"),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
+    use tempfile::tempdir;
+
+    fn git(dir: &Path, args: &[&str]) -> bool {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=t", "-c", "user.email=t@t"])
+            .args(args)
+            .output()
+            .is_ok_and(|o| o.status.success())
+    }
+
+    fn wait_for_switch(app: &mut App) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while app.branch_switch.is_some() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+            app.poll_branch_switch().expect("poll");
+        }
+        assert!(app.branch_switch.is_none(), "switch did not finish");
+    }
+
+    #[test]
+    fn branches_are_created_and_checked_out_in_the_background() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("a.txt");
+        fs::write(&file, "main\n").expect("write");
+        if !git(root, &["init", "-q", "-b", "main"]) || !git(root, &["add", "."]) {
+            return;
+        }
+        assert!(git(root, &["commit", "-qm", "init"]));
+        let mut app = App::new_for_test(root);
+        app.open_file(file.clone()).expect("open");
+
+        app.apply_prompt(PromptMode::CreateBranch, "feature".to_string())
+            .expect("create");
+        wait_for_switch(&mut app);
+        assert_eq!(app.status, "Created and switched to branch feature");
+        assert_eq!(app.git_branch.as_deref(), Some("feature"));
+        fs::write(&file, "feature\n").expect("write");
+        assert!(git(root, &["commit", "-qam", "change"]));
+
+        app.tabs[0].dirty = true;
+        app.switch_branch(BranchSwitch::Checkout("main".to_string()));
+        assert!(app.branch_switch.is_none(), "dirty buffers block checkout");
+        assert!(app.status.starts_with("Save or revert 1 unsaved file"));
+        app.tabs[0].dirty = false;
+
+        app.open_branch_picker();
+        for c in "mai".chars() {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .expect("type");
+        }
+        let picker = app.branch_picker.as_ref().expect("picker");
+        assert_eq!(App::branch_picker_matches(picker), ["main"]);
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("checkout");
+        wait_for_switch(&mut app);
+        assert_eq!(app.git_branch.as_deref(), Some("main"));
+        assert_eq!(app.active_tab().expect("tab").editor.lines()[0], "main");
+    }
+}
@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use super::{App, LocationPicker};
+use crate::outline::{child_symbols, document_symbols, symbol_label};
+use crate::types::BreadcrumbTarget;
+use crate::util::relative_path;
+
+impl App {
+    /// A clicked status bar breadcrumb: the files under a directory, or the
+    /// symbols at one level of the active file.
+    pub(crate) fn open_breadcrumb_picker(&mut self, target: BreadcrumbTarget) {
+        match target {
+            BreadcrumbTarget::Files(dir) => {
+                if self.cached_file_list.is_empty() {
+                    let mut files = Vec::new();
+                    self.collect_workspace_files(&mut files);
+                    self.cached_file_list = files;
+                }
+                let mut files: Vec<_> = self
+                    .cached_file_list
+                    .iter()
+                    .filter(|p| p.starts_with(&dir))
+                    .cloned()
+                    .collect();
+                files.sort();
+                let current = self.open_path();
+                let selected = files.iter().position(|p| Some(p) == current).unwrap_or(0);
+                let title = format!(
+                    "Files in {} - Enter open, Esc close",
+                    relative_path(&self.root, &dir).display()
+                );
+                let entries = files
+                    .into_iter()
+                    .map(|p| (p, 0, 0, String::new()))
+                    .collect();
+                self.show_breadcrumb_picker(title, entries, selected);
+            }
+            BreadcrumbTarget::Symbols { within, row } => {
+                let Some(tab) = self.active_tab() else {
+                    return;
+                };
+                let lines = tab.editor.lines();
+                let symbols = document_symbols(lines, &tab.fold_ranges);
+                let level = match within {
+                    Some((start, _)) => lines.get(start).and_then(|l| symbol_label(l)),
+                    None => tab
+                        .path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string()),
+                };
+                let children = child_symbols(&symbols, within);
+                let selected = children.iter().position(|s| s.row == row).unwrap_or(0);
+                let entries = children
+                    .into_iter()
+                    .map(|s| {
+                        let col = lines[s.row]
+                            .chars()
+                            .take_while(|c| c.is_whitespace())
+                            .count();
+                        (tab.path.clone(), s.row, col, s.label.clone())
+                    })
+                    .collect();
+                let title = format!(
+                    "Symbols in {} - Enter go, Esc close",
+                    level.unwrap_or_default()
+                );
+                self.show_breadcrumb_picker(title, entries, selected);
+            }
+        }
+    }
+
+    fn show_breadcrumb_picker(
+        &mut self,
+        title: String,
+        entries: Vec<(PathBuf, usize, usize, String)>,
+        selected: usize,
+    ) {
+        if entries.is_empty() {
+            self.reject_action("Nothing to pick at this level");
+            return;
+        }
+        self.location_picker = Some(LocationPicker {
+            title,
+            entries,
+            selected,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn breadcrumb_pickers_list_files_and_sibling_symbols() {
+        let tmp = tempdir().expect("tempdir");
+        let src = tmp.path().join("src");
+        fs::create_dir_all(src.join("app")).expect("mkdir");
+        fs::write(src.join("app/core.rs"), "").expect("write");
+        fs::write(tmp.path().join("README.md"), "").expect("write");
+        let file = src.join("app.rs");
+        fs::write(
+            &file,
+            "impl App {\n    fn new() {\n        x();\n    }\n    fn run() {\n        y();\n    }\n}\n",
+        )
+        .expect("write");
+        let mut app = App::new_for_test(tmp.path());
+        app.open_file(file.clone()).expect("open");
+
+        app.open_breadcrumb_picker(BreadcrumbTarget::Files(src.clone()));
+        let picker = app.location_picker.take().expect("files");
+        let files: Vec<_> = picker.entries.iter().map(|e| e.0.clone()).collect();
+        assert_eq!(files, [src.join("app/core.rs"), src.join("app.rs")]);
+        assert_eq!(picker.selected, 1);
+
+        app.open_breadcrumb_picker(BreadcrumbTarget::Symbols {
+            within: Some((0, 7)),
+            row: 4,
+        });
+        let picker = app.location_picker.take().expect("symbols");
+        assert!(picker.title.starts_with("Symbols in impl App"));
+        let symbols: Vec<_> = picker
+            .entries
+            .iter()
+            .map(|(_, row, col, label)| (*row, *col, label.as_str()))
+            .collect();
+        assert_eq!(symbols, [(1, 4, "new"), (4, 4, "run")]);
+        assert_eq!(picker.selected, 1);
+    }
+}
@@ -0,0 +1,216 @@
+use std::path::Path;
+use std::sync::mpsc::TryRecvError;
+use std::time::{Duration, Instant};
+
+use super::{App, LocationPicker};
+use crate::cargo_check::{cargo_check_args, spawn_cargo_check};
+use crate::env_file::load_project_env;
+
+impl App {
+    /// Saves within this long of each other start one check.
+    const CARGO_CHECK_DEBOUNCE_MS: u64 = 750;
+
+    fn is_cargo_project(&self) -> bool {
+        self.root.join("Cargo.toml").is_file()
+    }
+
+    /// After saving `path`, check the workspace once saves settle: Rust
+    /// sources and manifests only, and by default only while
+    /// rust-analyzer, which checks on save itself, is not running.
+    pub(crate) fn schedule_cargo_check(&mut self, path: &Path) {
+        let rust = path.extension().is_some_and(|e| e == "rs")
+            || path.file_name().is_some_and(|n| n == "Cargo.toml");
+        let enabled = self
+            .lsp_settings
+            .cargo_check_on_save
+            .unwrap_or(self.lsp.is_none());
+        if rust && enabled && self.is_cargo_project() {
+            self.cargo_check_due =
+                Some(Instant::now() + Duration::from_millis(Self::CARGO_CHECK_DEBOUNCE_MS));
+        }
+    }
+
+    /// "Run Cargo Check": check the workspace now.
+    pub(crate) fn run_cargo_check(&mut self) {
+        if !self.is_cargo_project() {
+            self.set_warning("No Cargo.toml in the project root");
+            return;
+        }
+        if self.trusted_projects.get(&self.root) != Some(&true) {
+            self.set_warning("Trust the project first: cargo check runs its build scripts");
+            return;
+        }
+        self.cargo_check_due = Some(Instant::now());
+    }
+
+    /// Start a due check, and take in the diagnostics of a finished one.
+    /// A check falling due while one runs starts when that one finishes.
+    pub(crate) fn poll_cargo_check(&mut self) {
+        if let Some(rx) = self.cargo_check_rx.as_ref() {
+            let result = match rx.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    Err(std::io::Error::other("check thread exited"))
+                }
+            };
+            self.cargo_check_rx = None;
+            match result {
+                Ok(diagnostics) => {
+                    self.cargo_diagnostics = diagnostics;
+                    self.apply_cargo_diagnostics();
+                    self.report_cargo_check();
+                }
+                Err(err) => self.set_error(format!("Could not run cargo check: {err}")),
+            }
+        }
+        if self.cargo_check_due.is_none_or(|due| Instant::now() < due) {
+            return;
+        }
+        self.cargo_check_due = None;
+        // Build scripts and proc macros run code from the project.
+        if self.trusted_projects.get(&self.root) != Some(&true) {
+            return;
+        }
+        let env = self
+            .lsp_settings
+            .env_file_name()
+            .and_then(|name| load_project_env(&self.root, name).ok().flatten())
+            .map(|env| env.vars)
+            .unwrap_or_default();
+        self.cargo_check_rx = Some(spawn_cargo_check(
+            &self.root,
+            cargo_check_args(&self.lsp_settings),
+            env,
+        ));
+        self.set_status("cargo check running");
+    }
+
+    /// Show the last check's diagnostics in tabs rust-analyzer is not
+    /// tracking; it publishes its own for the others.
+    pub(crate) fn apply_cargo_diagnostics(&mut self) {
+        for tab in &mut self.tabs {
+            if tab.open_doc_uri.is_none() {
+                tab.diagnostics = self
+                    .cargo_diagnostics
+                    .get(&tab.path)
+                    .cloned()
+                    .unwrap_or_default();
+            }
+        }
+    }
+
+    fn report_cargo_check(&mut self) {
+        let count = |severity: &str| {
+            self.cargo_diagnostics
+                .values()
+                .flatten()
+                .filter(|d| d.severity == severity)
+                .count()
+        };
+        let (errors, warnings) = (count("error"), count("warning"));
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        let summary = format!(
+            "cargo check: {errors} error{}, {warnings} warning{} in {} file{}",
+            plural(errors),
+            plural(warnings),
+            self.cargo_diagnostics.len(),
+            plural(self.cargo_diagnostics.len())
+        );
+        if errors > 0 {
+            self.set_warning(summary);
+        } else if warnings > 0 {
+            self.set_status(summary);
+        } else {
+            self.set_status("cargo check: no problems");
+        }
+    }
+
+    /// "Cargo Check Problems": every diagnostic of the last check, errors
+    /// first.
+    pub(crate) fn open_cargo_problems(&mut self) {
+        let mut entries: Vec<_> = self
+            .cargo_diagnostics
+            .iter()
+            .flat_map(|(path, diagnostics)| {
+                diagnostics.iter().map(move |d| {
+                    let (row, col) = d.range.map_or((d.line - 1, 0), |(start, _)| start);
+                    let context = format!("{}: {}", d.severity, d.message);
+                    (d.severity != "error", path.clone(), row, col, context)
+                })
+            })
+            .collect();
+        if entries.is_empty() {
+            self.set_status("No cargo check problems: run \"Run Cargo Check\" to refresh");
+            return;
+        }
+        entries.sort_by_key(|(not_error, ..)| *not_error);
+        self.location_picker = Some(LocationPicker {
+            title: "Cargo Check Problems - Enter go to, Esc close".to_string(),
+            entries: entries
+                .into_iter()
+                .map(|(_, path, row, col, context)| (path, row, col, context))
+                .collect(),
+            selected: 0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsp_client::LspDiagnostic;
+    use std::fs;
+    use std::sync::mpsc;
+    use tempfile::tempdir;
+
+    #[test]
+    fn cargo_check_results_reach_tabs_and_the_problems_list() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"demo\"\n").expect("write");
+        fs::create_dir(root.join("src")).expect("mkdir");
+        let main = root.join("src/main.rs");
+        let lib = root.join("src/lib.rs");
+        fs::write(&main, "fn main() {}\n").expect("write");
+        fs::write(&lib, "pub fn f() {}\n").expect("write");
+        let mut app = App::new_for_test(root);
+        app.trusted_projects.insert(root.to_path_buf(), false);
+        app.open_file(main.clone()).expect("open");
+
+        app.schedule_cargo_check(&main);
+        assert!(app.cargo_check_due.is_some());
+        app.cargo_check_due = Some(Instant::now());
+        app.poll_cargo_check();
+        assert!(app.cargo_check_due.is_none());
+        assert!(
+            app.cargo_check_rx.is_none(),
+            "untrusted projects are not built"
+        );
+
+        let diagnostic = |line, severity: &str| LspDiagnostic {
+            line,
+            severity: severity.to_string(),
+            message: "oops".to_string(),
+            range: Some(((line - 1, 4), (line - 1, 6))),
+        };
+        let (tx, rx) = mpsc::channel();
+        app.cargo_check_rx = Some(rx);
+        tx.send(Ok([
+            (main.clone(), vec![diagnostic(1, "warning")]),
+            (lib.clone(), vec![diagnostic(1, "error")]),
+        ]
+        .into_iter()
+        .collect()))
+            .expect("send");
+        app.poll_cargo_check();
+        assert!(app.cargo_check_rx.is_none());
+        assert_eq!(app.tabs[0].diagnostics, [diagnostic(1, "warning")]);
+        assert_eq!(app.status, "cargo check: 1 error, 1 warning in 2 files");
+
+        app.open_cargo_problems();
+        let picker = app.location_picker.as_ref().expect("picker");
+        assert_eq!(picker.entries[0], (lib, 0, 4, "error: oops".to_string()));
+        assert_eq!(picker.entries[1].0, main);
+    }
+}
@@ -1,40 +1,65 @@
-use super::{App, CompletionState, ContextMenuState, KeybindEditorState, SearchResultsState};
+use super::{
+    App, CompletionState, ContextMenuState, KeybindEditorState, MessageViewState,
+    SearchResultsState,
+};
 use ratatui::widgets::ListState;
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::mpsc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 use arboard::Clipboard;
 use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
 use ratatui::layout::Rect;
 
+use crate::commands::{COMMANDS, command_by_label};
+use crate::ignore::IgnoreRules;
 use crate::keybinds::{KeyAction, load_keybindings};
 use crate::lsp_client::resolve_rust_analyzer_bin;
+use crate::merge_conflicts::ConflictChoice;
 use crate::persistence::{
-    PersistedState, autosave_path_for, load_persisted_state, save_persisted_state,
+    ActionFeedback, PersistedState, SearchHistory, autosave_path_for, follow_file_path,
+    load_editor_settings, load_filetype_config, load_lsp_settings, load_persisted_state,
+    load_status_settings, load_watch_settings, save_persisted_state, state_dir,
 };
-use crate::syntax::syntax_lang_for_path;
 use crate::tab::{FoldRange, Tab};
 use crate::theme::{Theme, load_themes};
-use crate::types::{CommandAction, Focus, PendingAction, PromptMode, PromptState};
+use crate::types::{CommandAction, Focus, PendingAction, PromptMode, PromptState, StatusLevel};
+use crate::ui::Gutter;
 use crate::util::{
-    command_action_label, compute_fold_ranges, compute_git_change_summary,
-    compute_git_file_statuses, detect_git_branch, relative_path, spawn_git_refresh,
-    text_to_lines, wrap_segments_for_line,
+    command_action_label, compute_git_change_summary, compute_git_file_statuses, decode_text,
+    detect_git_branch, fuzzy_score, lsp_watches_path, open_with_system, pending_auto_cancels,
+    relative_path, spawn_git_refresh, text_to_lines, uses_crlf, utf16_bom,
 };
 
 impl App {
     pub(crate) const INLINE_GHOST_MIN_PREFIX: usize = 3;
-    pub(crate) const EDITOR_GUTTER_WIDTH: u16 = 11;
     pub(crate) const MIN_FILES_PANE_WIDTH: u16 = 18;
     pub(crate) const MIN_EDITOR_PANE_WIDTH: u16 = 28;
     pub(crate) const FS_REFRESH_DEBOUNCE_MS: u64 = 120;
     pub(crate) const AUTOSAVE_INTERVAL_MS: u64 = 2000;
     pub(crate) const SCROLL_LINES: usize = 3;
+    pub(crate) const FLASH_DURATION_MS: u64 = 200;
+    pub(crate) const MESSAGE_LOG_LEN: usize = 50;
+    pub(crate) const EDIT_LOCATIONS_LEN: usize = 100;
+    pub(crate) const JUMP_LIST_LEN: usize = 100;
+    pub(crate) const CLOSED_TABS_LEN: usize = 20;
+    pub(crate) const CLIPBOARD_HISTORY_LEN: usize = 20;
+    pub(crate) const RECENT_PROJECTS_LEN: usize = 20;
+    pub(crate) const RECENT_COMMANDS_LEN: usize = 10;
+    pub(crate) const LARGE_FILE_CHUNK_BYTES: usize = 1024 * 1024;
+    /// Time per frame spent reading more of a large file.
+    pub(crate) const LARGE_FILE_LOAD_BUDGET_MS: u64 = 30;
+    /// Quit, delete and overwrite confirmations cancel themselves after
+    /// this long.
+    pub(crate) const PENDING_CONFIRM_SECS: u64 = 10;
+    /// How much of a binary file the preview reads for its hex dump.
+    pub(crate) const BINARY_PREVIEW_BYTES: u64 = 16 * 1024 * 1024;
 
     pub(crate) fn new(root: PathBuf) -> io::Result<Self> {
         let themes = load_themes();
@@ -44,26 +69,41 @@ impl App {
             .unwrap_or(0);
         let mut expanded = HashSet::new();
         expanded.insert(root.clone());
+        let lsp_settings = load_lsp_settings(&root);
+        let (file_io_tx, file_io_rx) = mpsc::channel();
         let mut app = Self {
             root,
             tree: Vec::new(),
             selected: 0,
             tree_state: ListState::default(),
             expanded,
+            dir_listings: RefCell::default(),
             focus: Focus::Tree,
             tabs: Vec::new(),
             active_tab: 0,
             last_tree_click: None,
             status: String::new(),
+            status_level: StatusLevel::Info,
+            status_set_at: Instant::now(),
+            flash_until: None,
+            bell_pending: false,
+            status_settings: load_status_settings(),
+            editor_settings: load_editor_settings(),
+            cursor_diag_status: None,
+            status_rect: Rect::default(),
+            breadcrumb_rects: Vec::new(),
             pending: PendingAction::None,
+            pending_since: None,
             quit: false,
             files_view_open: true,
             files_pane_width: 32,
             divider_dragging: false,
+            tab_dragging: None,
             menu_open: false,
             menu_index: 0,
             menu_query: String::new(),
             menu_results: Vec::new(),
+            recent_commands: Vec::new(),
             menu_rect: Rect::default(),
             theme_browser_open: false,
             theme_browser_rect: Rect::default(),
@@ -78,6 +118,8 @@ impl App {
             editor_rect: Rect::default(),
             divider_rect: Rect::default(),
             tab_rects: Vec::new(),
+            tab_strip: 0..0,
+            tab_scroll_rects: (Rect::default(), Rect::default()),
             context_menu: ContextMenuState {
                 open: false,
                 index: 0,
@@ -85,6 +127,9 @@ impl App {
                 pos: (0, 0),
                 rect: Rect::default(),
             },
+            tree_clipboard: None,
+            tree_marked: Vec::new(),
+            tree_mark_anchor: None,
             prompt: None,
             prompt_rect: Rect::default(),
             clipboard: Clipboard::new().ok(),
@@ -100,12 +145,18 @@ impl App {
                 query: String::new(),
                 results: Vec::new(),
                 index: 0,
+                replacement: None,
+                excluded: HashSet::new(),
+                options: Default::default(),
+                running: None,
             },
             search_results_rect: Rect::default(),
             file_picker_open: false,
             file_picker_query: String::new(),
             file_picker_results: Vec::new(),
             file_picker_index: 0,
+            file_picker_changed_only: false,
+            changed_files: Vec::new(),
             file_picker_rect: Rect::default(),
             lsp: None,
             completion: CompletionState {
@@ -118,14 +169,50 @@ impl App {
             },
             pending_completion_request: None,
             pending_definition_request: None,
+            pending_workspace_symbol_request: None,
+            pending_references_request: None,
+            pending_rename_request: None,
+            pending_format_request: None,
+            watch_settings: load_watch_settings(),
             fs_watcher: None,
+            fs_poll_interval: None,
+            file_io_tx,
+            file_io_rx,
+            file_writes_in_flight: 0,
+            fs_poll_last: Instant::now(),
+            fs_poll_stamps: HashMap::new(),
             fs_rx: None,
             fs_refresh_pending: false,
             fs_full_refresh_pending: false,
             fs_changed_paths: HashSet::new(),
+            held_input: Vec::new(),
             last_fs_refresh: Instant::now(),
             autosave_last_write: Instant::now(),
             replace_after_find: false,
+            replace_after_project_search: false,
+            project_search_options: Default::default(),
+            search_history: SearchHistory::default(),
+            language_overrides: Default::default(),
+            language_overrides_view: None,
+            trusted_projects: Default::default(),
+            pinned_paths: Default::default(),
+            recent_files: Default::default(),
+            workspace_folder_paths: Default::default(),
+            bookmarks: Default::default(),
+            bookmarks_view: None,
+            location_picker: None,
+            workspace_folders: Vec::new(),
+            recent_projects: Vec::new(),
+            recent_projects_view: None,
+            message_log: VecDeque::new(),
+            message_view: None,
+            edit_locations: Vec::new(),
+            edit_location_index: None,
+            jump_back: Vec::new(),
+            jump_forward: Vec::new(),
+            closed_tabs: Vec::new(),
+            clipboard_history: VecDeque::new(),
+            clipboard_history_view: None,
             git_branch: None,
             enhanced_keys: false,
             word_wrap: false,
@@ -146,7 +233,39 @@ impl App {
             git_refresh_in_flight: false,
             git_thread_handle: None,
             cached_file_list: Vec::new(),
+            ignore_rules: IgnoreRules::default(),
+            show_ignored: false,
+            nerd_font_icons: true,
+            filetype_config: load_filetype_config(),
+            lsp_settings,
+            hex_view: None,
+            diff_view: None,
+            merge_view: None,
+            head_diff: None,
+            batch_rename: None,
+            form: None,
+            follow_broadcast: None,
+            follow_view: None,
+            snippet_rx: None,
+            playground_dir: None,
+            snippet_output: None,
+            task_picker: None,
+            task_panel: None,
+            task_panel_rect: Rect::default(),
+            test_explorer: None,
+            git_log: None,
+            branch_picker: None,
+            branch_switch: None,
+            branch_rect: Rect::default(),
+            cargo_diagnostics: Default::default(),
+            cargo_check_rx: None,
+            cargo_check_due: None,
+            log_view: None,
+            settings_view: None,
+            find_origin: None,
+            find_bar_open: false,
         };
+        app.ignore_rules = IgnoreRules::load(&app.root);
         app.git_branch = detect_git_branch(&app.root);
         app.git_file_statuses = compute_git_file_statuses(&app.root);
         app.git_change_summary = compute_git_change_summary(&app.root);
@@ -163,17 +282,42 @@ impl App {
             if !has_rg {
                 missing.push("rg");
             }
-            app.status = format!(
+            app.set_status(format!(
                 "Missing tools: {}. Run `lazyide --setup` to install.",
                 missing.join(", ")
-            );
+            ));
+        } else if let Some(interval) = app.watch_settings.poll_interval_for(&app.root) {
+            app.set_status(format!(
+                "Root: {} (checking for changes every {} ms)",
+                app.root.display(),
+                interval.as_millis()
+            ));
         } else {
-            app.status = format!("Root: {}", app.root.display());
+            app.set_status(format!("Root: {}", app.root.display()));
         }
         Ok(app)
     }
 
+    /// An app for tests: no filesystem watcher, so changes only arrive
+    /// through an injected `fs_rx`, and input through `handle_event`.
+    #[cfg(test)]
+    pub(crate) fn new_for_test(root: &std::path::Path) -> Self {
+        let mut app = Self::new(root.to_path_buf()).expect("app should initialize");
+        app.fs_watcher = None;
+        app.fs_rx = None;
+        app
+    }
+
     pub(crate) fn start_fs_watcher(&mut self) {
+        self.fs_poll_interval = self.watch_settings.poll_interval_for(&self.root);
+        if self.fs_poll_interval.is_some() {
+            // Network mounts drop notify events; poll mtimes instead.
+            self.fs_watcher = None;
+            self.fs_rx = None;
+            self.fs_poll_stamps.clear();
+            self.poll_fs_mtimes(true);
+            return;
+        }
         let (tx, rx) = mpsc::channel::<super::FsChangeEvent>();
         let mut watcher = match RecommendedWatcher::new(
             move |res: Result<notify::Event, notify::Error>| {
@@ -181,6 +325,7 @@ impl App {
                     let full_refresh = matches!(event.kind, EventKind::Any | EventKind::Other);
                     let _ = tx.send(super::FsChangeEvent {
                         paths: event.paths,
+                        kind: event.kind,
                         full_refresh,
                     });
                 }
@@ -189,94 +334,222 @@ impl App {
         ) {
             Ok(w) => w,
             Err(err) => {
-                self.set_status(format!("Filesystem watch unavailable: {err}"));
+                self.set_warning(format!("Filesystem watch unavailable: {err}"));
                 return;
             }
         };
         if let Err(err) = watcher.watch(&self.root, RecursiveMode::Recursive) {
-            self.set_status(format!("Filesystem watch unavailable: {err}"));
+            self.set_warning(format!("Filesystem watch unavailable: {err}"));
             return;
         }
         self.fs_rx = Some(rx);
         self.fs_watcher = Some(watcher);
+        self.watch_workspace_folders();
         self.fs_refresh_pending = false;
         self.fs_full_refresh_pending = false;
         self.fs_changed_paths.clear();
         self.last_fs_refresh = Instant::now();
     }
 
+    /// Stat the paths a polled root cares about: open files, the folders
+    /// shown in the tree, ignore files and git's index and HEAD. Changes
+    /// since the last poll come back as events for `poll_fs_changes`.
+    fn poll_fs_mtimes(&mut self, force: bool) -> Vec<super::FsChangeEvent> {
+        let Some(interval) = self.fs_poll_interval else {
+            return Vec::new();
+        };
+        if !force && self.fs_poll_last.elapsed() < interval {
+            return Vec::new();
+        }
+        self.fs_poll_last = Instant::now();
+        let git = self.root.join(".git");
+        let mut paths: HashSet<PathBuf> = self.tabs.iter().map(|t| t.path.clone()).collect();
+        paths.extend(self.expanded.iter().cloned());
+        paths.extend([
+            self.root.clone(),
+            self.root.join(".gitignore"),
+            self.root.join(".ignore"),
+            git.join("HEAD"),
+            git.join("index"),
+        ]);
+        let mut changed = Vec::new();
+        for path in paths {
+            let stamp = fs::metadata(&path)
+                .ok()
+                .map(|m| (m.modified().unwrap_or(SystemTime::UNIX_EPOCH), m.len()));
+            if self.fs_poll_stamps.insert(path.clone(), stamp) != Some(stamp) && !force {
+                changed.push(path);
+            }
+        }
+        if changed.is_empty() {
+            return Vec::new();
+        }
+        vec![super::FsChangeEvent {
+            paths: changed,
+            kind: EventKind::Modify(notify::event::ModifyKind::Any),
+            full_refresh: false,
+        }]
+    }
+
     pub(crate) fn poll_fs_changes(&mut self) -> io::Result<()> {
+        self.collect_fs_changes()?;
+        if self.fs_refresh_pending
+            && self.last_fs_refresh.elapsed() >= Duration::from_millis(Self::FS_REFRESH_DEBOUNCE_MS)
+        {
+            self.refresh_after_fs_changes()?;
+        }
+        Ok(())
+    }
+
+    /// Take the watcher's (or poller's) reports into `fs_changed_paths`.
+    /// Also run before each key so typing can be held while the active
+    /// file has a change waiting to be reloaded.
+    pub(crate) fn collect_fs_changes(&mut self) -> io::Result<()> {
         let mut saw_event = false;
+        let mut ignore_files_changed = false;
+        let mut folder_git_changed = false;
+        let mut watched_changes = Vec::new();
+        let mut log_changed = false;
+        let mut changes = self.poll_fs_mtimes(false);
         if let Some(rx) = self.fs_rx.as_ref() {
-            while let Ok(change) = rx.try_recv() {
+            changes.extend(rx.try_iter());
+        }
+        for change in changes {
+            if change.full_refresh {
                 saw_event = true;
-                if change.full_refresh {
+                self.fs_full_refresh_pending = true;
+            }
+            for path in change.paths {
+                let abs = if path.is_absolute() {
+                    path
+                } else {
+                    self.root.join(path)
+                };
+                if self.fs_change_is_noise(&abs) {
+                    continue;
+                }
+                saw_event = true;
+                if abs.starts_with(self.root.join(".git")) {
                     self.fs_full_refresh_pending = true;
                 }
-                for path in change.paths {
-                    let abs = if path.is_absolute() {
-                        path
-                    } else {
-                        self.root.join(path)
-                    };
-                    if abs.starts_with(self.root.join(".git")) {
-                        self.fs_full_refresh_pending = true;
-                    }
-                    self.fs_changed_paths.insert(abs);
+                if self
+                    .workspace_folders
+                    .iter()
+                    .any(|f| abs.starts_with(f.path.join(".git")))
+                {
+                    folder_git_changed = true;
+                }
+                if abs
+                    .file_name()
+                    .is_some_and(|n| n == ".gitignore" || n == ".ignore")
+                {
+                    ignore_files_changed = true;
+                }
+                if self.log_view.as_ref().is_some_and(|v| v.path == abs) {
+                    log_changed = true;
                 }
+                if lsp_watches_path(&self.root, &abs) {
+                    watched_changes.push((abs.clone(), change.kind));
+                }
+                self.fs_changed_paths.insert(abs);
             }
         }
+        self.notify_lsp_watched_files(&watched_changes);
+        if log_changed {
+            self.tail_log_view()?;
+        }
         if saw_event {
             self.fs_refresh_pending = true;
         }
-        if self.fs_refresh_pending
-            && self.last_fs_refresh.elapsed() >= Duration::from_millis(Self::FS_REFRESH_DEBOUNCE_MS)
+        if ignore_files_changed {
+            self.ignore_rules = IgnoreRules::load(&self.root);
+        }
+        if folder_git_changed || ignore_files_changed {
+            self.refresh_workspace_folders(folder_git_changed, ignore_files_changed);
+        }
+        Ok(())
+    }
+
+    /// A change nothing on screen depends on: an ignored path such as
+    /// `target/` or `node_modules/` contents, in a folder the tree does not
+    /// list, and not open in a tab. Dropping these keeps builds and package
+    /// installs from rebuilding the tree over and over.
+    fn fs_change_is_noise(&self, path: &Path) -> bool {
+        if path.components().any(|c| c.as_os_str() == ".git")
+            || self.tabs.iter().any(|t| t.path == path)
+            || self.log_view.as_ref().is_some_and(|v| v.path == path)
         {
+            return false;
+        }
+        let listed = path.parent().is_some_and(|dir| {
+            dir == self.root
+                || self.expanded.contains(dir)
+                || self.workspace_folders.iter().any(|f| f.path == dir)
+        });
+        !listed && self.ignore_rules_for(path).is_ignored(path, path.is_dir())
+    }
+
+    fn refresh_after_fs_changes(&mut self) -> io::Result<()> {
+        if self.fs_full_refresh_pending {
             self.rebuild_tree()?;
-            if self.file_picker_open {
-                self.refresh_file_picker_results();
-            }
-            if let Some(path) = self.open_path().cloned() {
-                if !path.exists() {
-                    if self.is_dirty() {
-                        self.set_status(
-                            "Open file was removed externally (unsaved buffer preserved)",
-                        );
-                    } else {
-                        self.close_file();
-                        self.set_status("Open file was removed externally");
-                    }
-                } else if !self.is_dirty() {
-                    self.reload_open_file_from_disk_if_pristine()?;
+        } else {
+            let changed = std::mem::take(&mut self.fs_changed_paths);
+            self.invalidate_dir_listings(&changed);
+            self.fs_changed_paths = changed;
+            self.refresh_tree()?;
+        }
+        if self.file_picker_open {
+            self.refresh_file_picker_results();
+        }
+        let mut reloaded = false;
+        if let Some(path) = self.open_path().cloned() {
+            if !path.exists() {
+                if self.is_dirty() {
+                    self.set_warning("Open file was removed externally (unsaved buffer preserved)");
                 } else {
-                    self.maybe_flag_external_conflict()?;
+                    self.close_file();
+                    self.set_warning("Open file was removed externally");
                 }
+            } else if self.active_tab().is_some_and(|t| t.follow_tail) {
+                self.refresh_followed_file()?;
+            } else if self.active_tab().is_some_and(|t| t.large_file.is_some()) {
+                // Large files are only read again when rewritten; new
+                // lines at the end load as the view reaches them.
+                reloaded = self.refresh_large_file()?;
+            } else if self.active_tab().is_some_and(|t| t.pending_io.is_some()) {
+                // A background read or write settles the buffer when it
+                // reports; this change may well be that write.
+            } else if !self.is_dirty() {
+                reloaded = self.reload_open_file_from_disk_if_pristine()?;
+            } else {
+                self.maybe_flag_external_conflict()?;
             }
-            // Dispatch async git refresh if not already in flight
-            if !self.git_refresh_in_flight {
-                // Join the previous thread (prevents handle accumulation)
-                if let Some(handle) = self.git_thread_handle.take() {
-                    if handle.join().is_err() {
-                        self.set_status("Git refresh thread panicked");
-                    }
+        }
+        // Dispatch async git refresh if not already in flight
+        if !self.git_refresh_in_flight {
+            // Join the previous thread (prevents handle accumulation)
+            if let Some(handle) = self.git_thread_handle.take() {
+                if handle.join().is_err() {
+                    self.set_error("Git refresh thread panicked");
                 }
-                let root = self.root.clone();
-                let tab_paths: Vec<(PathBuf, usize)> = self
-                    .tabs
-                    .iter()
-                    .map(|tab| (tab.path.clone(), tab.editor.lines().len()))
-                    .collect();
-                let (tx, rx) = mpsc::channel();
-                self.git_result_rx = Some(rx);
-                self.git_refresh_in_flight = true;
-                self.git_thread_handle = Some(spawn_git_refresh(root, tab_paths, tx));
             }
-            self.fs_refresh_pending = false;
-            self.fs_full_refresh_pending = false;
-            self.fs_changed_paths.clear();
-            self.last_fs_refresh = Instant::now();
+            let root = self.root.clone();
+            let tab_paths: Vec<(PathBuf, usize)> = self
+                .tabs
+                .iter()
+                .filter(|tab| tab.large_file.is_none())
+                .map(|tab| (tab.path.clone(), tab.editor.lines().len()))
+                .collect();
+            let (tx, rx) = mpsc::channel();
+            self.git_result_rx = Some(rx);
+            self.git_refresh_in_flight = true;
+            self.git_thread_handle = Some(spawn_git_refresh(root, tab_paths, tx));
         }
-        Ok(())
+        self.fs_refresh_pending = false;
+        self.fs_full_refresh_pending = false;
+        self.fs_changed_paths.clear();
+        self.last_fs_refresh = Instant::now();
+        self.replay_held_input(reloaded)
     }
 
     pub(crate) fn poll_git_results(&mut self) {
@@ -298,20 +571,24 @@ impl App {
         }
     }
 
-    pub(crate) fn reload_open_file_from_disk_if_pristine(&mut self) -> io::Result<()> {
+    /// Replace a clean buffer with the file on disk; true when it changed.
+    pub(crate) fn reload_open_file_from_disk_if_pristine(&mut self) -> io::Result<bool> {
         let Some(path) = self.open_path().cloned() else {
-            return Ok(());
+            return Ok(false);
         };
         if self.is_dirty() || !path.exists() {
-            return Ok(());
+            return Ok(false);
         }
         let bytes = fs::read(&path)?;
-        let disk_text = String::from_utf8_lossy(&bytes).to_string();
-        let current_text = self.tabs[self.active_tab].editor.lines().join("\n");
-        if disk_text == current_text {
-            return Ok(());
-        }
+        let disk_text = decode_text(&bytes);
         let lines = text_to_lines(&disk_text);
+        let tab = &self.tabs[self.active_tab];
+        if lines == tab.editor.lines()
+            && uses_crlf(&disk_text) == tab.crlf
+            && utf16_bom(&bytes) == tab.utf16
+        {
+            return Ok(false);
+        }
         let (row, col) = self.tabs[self.active_tab].editor.cursor();
         let clamped_row = row.min(lines.len().saturating_sub(1));
         let line_len = lines[clamped_row].chars().count();
@@ -319,6 +596,8 @@ impl App {
         self.replace_editor_text(lines, (clamped_row, clamped_col));
         if let Some(tab) = self.active_tab_mut() {
             tab.dirty = false;
+            tab.crlf = uses_crlf(&disk_text);
+            tab.utf16 = utf16_bom(&bytes);
             tab.open_disk_snapshot = Some(disk_text);
         }
         self.notify_lsp_did_change();
@@ -326,7 +605,7 @@ impl App {
             "Reloaded {} from disk",
             relative_path(&self.root, &path).display()
         ));
-        Ok(())
+        Ok(true)
     }
 
     pub(crate) fn active_theme(&self) -> &Theme {
@@ -362,6 +641,9 @@ impl App {
 
     pub(crate) fn switch_to_tab(&mut self, idx: usize) {
         if idx < self.tabs.len() {
+            if idx != self.active_tab {
+                self.record_jump();
+            }
             self.active_tab = idx;
             self.completion.reset();
             self.focus = Focus::Editor;
@@ -375,9 +657,29 @@ impl App {
         if let Some(word_wrap) = saved.word_wrap {
             self.word_wrap = word_wrap;
         }
+        if let Some(show_ignored) = saved.show_ignored {
+            self.show_ignored = show_ignored;
+        }
+        if let Some(nerd_font_icons) = saved.nerd_font_icons {
+            self.nerd_font_icons = nerd_font_icons;
+        }
         if let Some(width) = saved.files_pane_width {
             self.files_pane_width = width.max(Self::MIN_FILES_PANE_WIDTH);
         }
+        self.search_history = saved.search_history;
+        self.language_overrides = saved.language_overrides;
+        self.trusted_projects = saved.trusted_projects;
+        self.pinned_paths = saved.pinned_paths;
+        self.recent_files = saved.recent_files;
+        self.workspace_folder_paths = saved.workspace_folders;
+        self.load_workspace_folders();
+        self.recent_projects = saved.recent_projects;
+        self.recent_commands = saved
+            .recent_commands
+            .iter()
+            .filter_map(|label| command_by_label(label))
+            .collect();
+        self.bookmarks = saved.bookmarks;
         if let Some(idx) = self
             .themes
             .iter()
@@ -389,14 +691,32 @@ impl App {
         }
     }
 
-    pub(crate) fn persist_state(&mut self) {
-        let state = PersistedState {
+    pub(crate) fn persisted_state(&self) -> PersistedState {
+        PersistedState {
             theme_name: self.active_theme().name.clone(),
             files_pane_width: Some(self.files_pane_width),
             word_wrap: Some(self.word_wrap),
-        };
-        if save_persisted_state(&state).is_err() {
-            self.set_status("Failed to persist app state");
+            show_ignored: Some(self.show_ignored),
+            nerd_font_icons: Some(self.nerd_font_icons),
+            search_history: self.search_history.clone(),
+            language_overrides: self.language_overrides.clone(),
+            trusted_projects: self.trusted_projects.clone(),
+            pinned_paths: self.pinned_paths.clone(),
+            recent_files: self.recent_files.clone(),
+            workspace_folders: self.workspace_folder_paths.clone(),
+            bookmarks: self.bookmarks.clone(),
+            recent_projects: self.recent_projects.clone(),
+            recent_commands: self
+                .recent_commands
+                .iter()
+                .map(|a| command_action_label(*a).to_string())
+                .collect(),
+        }
+    }
+
+    pub(crate) fn persist_state(&mut self) {
+        if save_persisted_state(&self.persisted_state()).is_err() {
+            self.set_error("Failed to persist app state");
         }
     }
 
@@ -404,6 +724,28 @@ impl App {
         self.persist_state();
     }
 
+    pub(crate) fn toggle_show_ignored(&mut self) -> io::Result<()> {
+        self.show_ignored = !self.show_ignored;
+        self.rebuild_tree()?;
+        self.persist_state();
+        if self.show_ignored {
+            self.set_status("Showing ignored files (dimmed)");
+        } else {
+            self.set_status("Hiding ignored files");
+        }
+        Ok(())
+    }
+
+    pub(crate) fn toggle_nerd_font_icons(&mut self) {
+        self.nerd_font_icons = !self.nerd_font_icons;
+        self.persist_state();
+        if self.nerd_font_icons {
+            self.set_status("File icons: nerd font");
+        } else {
+            self.set_status("File icons: ASCII");
+        }
+    }
+
     pub(crate) fn toggle_word_wrap(&mut self) {
         self.word_wrap = !self.word_wrap;
         if self.word_wrap {
@@ -424,16 +766,49 @@ impl App {
     }
 
     pub(crate) fn on_editor_content_changed(&mut self) {
+        if let Some(tab) = self.active_tab_mut() {
+            tab.redo_snapshots.clear();
+        }
         self.mark_dirty();
+        self.record_edit_location();
+        self.shift_bookmarks();
         self.notify_lsp_did_change();
         self.recompute_folds();
+        self.refresh_search_matches();
+    }
+
+    /// Remember where the active tab was just edited. Edits on the line of
+    /// the newest location (or next to it) move that location instead of
+    /// adding one, so typing a word leaves a single entry.
+    fn record_edit_location(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let (row, col) = tab.editor.cursor();
+        let path = tab.path.clone();
+        self.edit_location_index = None;
+        if let Some(last) = self.edit_locations.last_mut()
+            && last.0 == path
+            && last.1.abs_diff(row) <= 1
+        {
+            *last = (path, row, col);
+            return;
+        }
+        if self.edit_locations.len() == Self::EDIT_LOCATIONS_LEN {
+            self.edit_locations.remove(0);
+        }
+        self.edit_locations.push((path, row, col));
     }
 
     pub(crate) fn open_find_prompt(&mut self) {
+        self.find_bar_open = false;
+        self.find_origin = self.active_tab().map(|t| t.editor.cursor());
         self.prompt = Some(PromptState {
             title: "Find in file (regex)".to_string(),
             value: String::new(),
             cursor: 0,
+            anchor: None,
+            history: None,
             mode: PromptMode::FindInFile,
         });
     }
@@ -443,15 +818,20 @@ impl App {
             title: "Search in files (ripgrep)".to_string(),
             value: String::new(),
             cursor: 0,
+            anchor: None,
+            history: None,
             mode: PromptMode::FindInProject,
         });
+        self.replace_after_project_search = false;
     }
 
     pub(crate) fn open_go_to_line_prompt(&mut self) {
         self.prompt = Some(PromptState {
-            title: "Go to line".to_string(),
+            title: "Go to line (N or N:COL)".to_string(),
             value: String::new(),
             cursor: 0,
+            anchor: None,
+            history: None,
             mode: PromptMode::GoToLine,
         });
     }
@@ -461,6 +841,23 @@ impl App {
         self.replace_after_find = true;
     }
 
+    pub(crate) fn open_export_todos_prompt(&mut self) {
+        let value = "todos.json".to_string();
+        self.prompt = Some(PromptState {
+            title: "Export TODOs to (.json or .csv)".to_string(),
+            cursor: value.len() - ".json".len(),
+            value,
+            anchor: Some(0),
+            history: None,
+            mode: PromptMode::ExportTodos,
+        });
+    }
+
+    pub(crate) fn open_project_replace_prompt(&mut self) {
+        self.open_project_search_prompt();
+        self.replace_after_project_search = true;
+    }
+
     pub(crate) fn open_command_palette(&mut self) {
         self.menu_open = true;
         self.menu_query.clear();
@@ -468,37 +865,44 @@ impl App {
         self.refresh_menu_results();
     }
 
+    /// Fill the palette from `menu_query`: fuzzy matches best first, recently
+    /// run commands ahead of others that score the same, and with no query
+    /// the recent ones on top.
     pub(crate) fn refresh_menu_results(&mut self) {
-        let all = [
-            CommandAction::Theme,
-            CommandAction::Help,
-            CommandAction::QuickOpen,
-            CommandAction::FindInFile,
-            CommandAction::FindInProject,
-            CommandAction::SaveFile,
-            CommandAction::RefreshTree,
-            CommandAction::ToggleFiles,
-            CommandAction::GotoDefinition,
-            CommandAction::ReplaceInFile,
-            CommandAction::GoToLine,
-            CommandAction::Keybinds,
-            CommandAction::ToggleWordWrap,
-        ];
         let q = self.menu_query.to_ascii_lowercase();
-        self.menu_results = all
-            .into_iter()
-            .filter(|a| {
-                q.is_empty()
-                    || command_action_label(*a)
-                        .to_ascii_lowercase()
-                        .contains(q.as_str())
+        let recency = |action: CommandAction| {
+            self.recent_commands
+                .iter()
+                .position(|a| *a == action)
+                .unwrap_or(usize::MAX)
+        };
+        let mut scored: Vec<(usize, usize, CommandAction)> = COMMANDS
+            .iter()
+            .filter_map(|c| {
+                let score = fuzzy_score(&q, c.label)?;
+                Some((score, recency(c.action), c.action))
             })
             .collect();
+        // Stable, so equal entries keep the table's order.
+        scored.sort_by_key(|&(score, recent, _)| (score, recent));
+        self.menu_results = scored.into_iter().map(|(_, _, a)| a).collect();
         self.menu_index = self
             .menu_index
             .min(self.menu_results.len().saturating_sub(1));
     }
 
+    /// Run a command picked in the palette and move it to the front of the
+    /// recent commands.
+    pub(crate) fn run_palette_command(&mut self, action: CommandAction) -> io::Result<()> {
+        self.menu_open = false;
+        self.menu_query.clear();
+        self.recent_commands.retain(|a| *a != action);
+        self.recent_commands.insert(0, action);
+        self.recent_commands.truncate(Self::RECENT_COMMANDS_LEN);
+        self.persist_state();
+        self.run_command_action(action)
+    }
+
     pub(crate) fn run_command_action(&mut self, action: CommandAction) -> io::Result<()> {
         match action {
             CommandAction::Theme => {
@@ -510,6 +914,7 @@ impl App {
             CommandAction::Help => self.help_open = true,
             CommandAction::QuickOpen => {
                 self.file_picker_open = true;
+                self.file_picker_changed_only = false;
                 self.file_picker_query.clear();
                 self.file_picker_index = 0;
                 self.refresh_file_picker_results();
@@ -520,10 +925,12 @@ impl App {
             CommandAction::FindInProject => {
                 self.open_project_search_prompt();
             }
+            CommandAction::FindInProjectWithOptions => self.open_project_search_form(),
             CommandAction::SaveFile => {
                 self.save_file()?;
             }
             CommandAction::RefreshTree => {
+                self.ignore_rules = IgnoreRules::load(&self.root);
                 self.rebuild_tree()?;
                 self.set_status("Tree refreshed");
             }
@@ -540,6 +947,9 @@ impl App {
             CommandAction::ReplaceInFile => {
                 self.open_replace_prompt();
             }
+            CommandAction::ReplaceInProject => {
+                self.open_project_replace_prompt();
+            }
             CommandAction::GoToLine => {
                 self.open_go_to_line_prompt();
             }
@@ -552,19 +962,304 @@ impl App {
                 self.refresh_keybind_editor_actions();
             }
             CommandAction::ToggleWordWrap => self.toggle_word_wrap(),
+            CommandAction::OpenStateFolder => self.open_state_folder(),
+            CommandAction::ToggleIgnoredFiles => self.toggle_show_ignored()?,
+            CommandAction::ToggleNerdFontIcons => self.toggle_nerd_font_icons(),
+            CommandAction::ExportTodos => self.open_export_todos_prompt(),
+            CommandAction::MoveTabLeft => self.move_active_tab(false),
+            CommandAction::MoveTabRight => self.move_active_tab(true),
+            CommandAction::CloseOtherTabs => self.close_other_tabs(),
+            CommandAction::CloseTabsToRight => self.close_tabs_to_right(),
+            CommandAction::CloseAllTabs => self.close_all_tabs(),
+            CommandAction::ReopenClosedTab => self.reopen_closed_tab()?,
+            CommandAction::TogglePinTab => self.toggle_pin_active_tab(),
+            CommandAction::ChangedFiles => self.open_changed_files_picker(),
+            CommandAction::SetLanguageMode => self.open_language_mode_prompt(),
+            CommandAction::LanguageOverrides => self.open_language_overrides(),
+            CommandAction::PasteFromHistory => self.open_clipboard_history(),
+            CommandAction::ConvertIndentToSpaces => self.convert_indentation(Some(false)),
+            CommandAction::ConvertIndentToTabs => self.convert_indentation(Some(true)),
+            CommandAction::ReindentFile => self.convert_indentation(None),
+            CommandAction::GenerateRustTest => self.generate_rust_test(),
+            CommandAction::GoToTestOrImplementation => self.go_to_test_counterpart()?,
+            CommandAction::EditEnvFile => self.edit_env_file()?,
+            CommandAction::ToggleLineEndings => self.toggle_line_endings(),
+            CommandAction::ToggleFollowBroadcast => self.toggle_follow_broadcast()?,
+            CommandAction::FollowSession => self.follow_session(follow_file_path()),
+            CommandAction::RunCodeBlock => self.run_code_block(),
+            CommandAction::OpenLogView => self.open_log_view_for_active()?,
+            CommandAction::ToggleFollowFile => self.toggle_follow_file()?,
+            CommandAction::OpenSettings => self.open_settings(),
+            CommandAction::OpenHexEditor => self.open_hex_editor()?,
+            CommandAction::AddWorkspaceFolder => self.open_add_workspace_folder_prompt(),
+            CommandAction::RemoveWorkspaceFolder => self.remove_workspace_folder()?,
+            CommandAction::PasteVerbatim => self.paste_verbatim(),
+            CommandAction::OpenRecentProject => self.open_recent_projects(),
+            CommandAction::ListBookmarks => self.open_bookmarks_list(),
+            CommandAction::ToggleGutterLineNumbers => self.toggle_setting("gutter_line_numbers"),
+            CommandAction::ToggleGutterFolds => self.toggle_setting("gutter_folds"),
+            CommandAction::ToggleGutterDiagnostics => self.toggle_setting("gutter_diagnostics"),
+            CommandAction::ToggleGutterGit => self.toggle_setting("gutter_git"),
+            CommandAction::PasteLocations => self.paste_error_locations(),
+            CommandAction::GoToParentModule => self.go_to_parent_module()?,
+            CommandAction::GoToModuleDeclaration => self.go_to_module_declaration()?,
+            CommandAction::SiblingModules => self.open_sibling_modules(),
+            CommandAction::RevertFileToHead => self.revert_file_to_head(),
+            CommandAction::RevertHunk => self.revert_hunk_at_cursor(),
+            CommandAction::ShowLspConfig => self.show_lsp_config(),
+            CommandAction::RunTask => self.open_task_picker(),
+            CommandAction::StopTask => self.stop_task(),
+            CommandAction::ToggleTaskOutput => self.toggle_task_panel(),
+            CommandAction::CompareWithClipboard => self.compare_with_clipboard(),
+            CommandAction::RunCargoCheck => self.run_cargo_check(),
+            CommandAction::CargoCheckProblems => self.open_cargo_problems(),
+            CommandAction::TestExplorer => self.open_test_explorer(),
+            CommandAction::GitLog => self.open_git_log(),
+            CommandAction::GitFileHistory => self.open_git_file_history(),
+            CommandAction::DiffWithHead => self.open_head_diff_view(),
+            CommandAction::SwitchBranch => self.open_branch_picker(),
+            CommandAction::CreateBranch => self.open_create_branch_prompt(),
+            CommandAction::AcceptConflictOurs => self.accept_merge_conflict(ConflictChoice::Ours),
+            CommandAction::AcceptConflictTheirs => {
+                self.accept_merge_conflict(ConflictChoice::Theirs)
+            }
+            CommandAction::AcceptConflictBoth => self.accept_merge_conflict(ConflictChoice::Both),
+            CommandAction::NextConflict => self.jump_to_merge_conflict(true)?,
+            CommandAction::PreviousConflict => self.jump_to_merge_conflict(false)?,
+            CommandAction::FindReferences => self.request_lsp_references(),
+            CommandAction::RenameSymbol => self.open_rename_symbol_prompt(),
+            CommandAction::FormatDocument => self.request_lsp_format(),
+            CommandAction::ToggleProjectTrust => {
+                let trusted = self.trusted_projects.get(&self.root) == Some(&true);
+                self.set_project_trust(!trusted);
+            }
+            CommandAction::ShowLastMessage => self.open_message_view(),
+            CommandAction::JumpToBracket => self.jump_to_matching_bracket(),
+            CommandAction::GoToLastEdit => self.go_to_last_edit_location()?,
+            CommandAction::JumpBack => self.navigate_jump_list(true)?,
+            CommandAction::JumpForward => self.navigate_jump_list(false)?,
+            CommandAction::CenterCursorLine => self.center_cursor_in_view(),
+            CommandAction::CursorLineToTop => self.scroll_cursor_to_top(),
+            CommandAction::CursorLineToBottom => self.scroll_cursor_to_bottom(),
         }
         Ok(())
     }
 
+    pub(crate) fn open_state_folder(&mut self) {
+        let Some(dir) = state_dir() else {
+            self.set_status("No state directory available");
+            return;
+        };
+        if let Err(err) = fs::create_dir_all(&dir) {
+            self.set_error(format!("Cannot create {}: {err}", dir.display()));
+            return;
+        }
+        match open_with_system(&dir) {
+            Ok(_) => self.set_status(format!("Opened state folder: {}", dir.display())),
+            Err(_) => self.set_status(format!("State folder: {}", dir.display())),
+        }
+    }
+
     pub(crate) fn update_status_for_cursor(&mut self) {
-        if self.focus == Focus::Editor {
-            if let Some(tab) = self.active_tab() {
-                let cursor_row = tab.editor.cursor().0;
-                if let Some(diag) = tab.diagnostics.iter().find(|d| d.line == cursor_row + 1) {
-                    self.status = format!("[{}] {}", diag.severity, diag.message);
+        if self.focus != Focus::Editor {
+            return;
+        }
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let cursor_row = tab.editor.cursor().0;
+        let diag = tab
+            .diagnostics
+            .iter()
+            .find(|d| d.line == cursor_row + 1)
+            .map(|d| {
+                let level = match d.severity.as_str() {
+                    "error" | "warning" => StatusLevel::Warn,
+                    _ => StatusLevel::Info,
+                };
+                (level, format!("[{}] {}", d.severity, d.message))
+            });
+        if diag.as_ref().map(|(_, msg)| msg) == self.cursor_diag_status.as_ref() {
+            return;
+        }
+        self.cursor_diag_status = diag.as_ref().map(|(_, msg)| msg.clone());
+        if let Some((level, msg)) = diag {
+            self.set_status_at(level, msg);
+        }
+    }
+
+    /// Show `status` at `level`, unless the current message still outranks
+    /// it: an undismissed error, or a warning inside its minimum display
+    /// time. Confirmation prompts always get through.
+    pub(crate) fn set_status_at<S: Into<String>>(&mut self, level: StatusLevel, status: S) {
+        let status = status.into();
+        self.log_message(level, &status);
+        if level < self.status_level && matches!(self.pending, PendingAction::None) {
+            let held = match self.status_level {
+                StatusLevel::Error => true,
+                StatusLevel::Warn => {
+                    self.status_set_at.elapsed()
+                        < Duration::from_millis(self.status_settings.warn_min_display_ms)
                 }
+                StatusLevel::Info => false,
+            };
+            if held {
+                return;
             }
         }
+        self.status = status;
+        self.status_level = level;
+        self.status_set_at = Instant::now();
+    }
+
+    /// Keep `status` in the message log, skipping repeats of the newest entry.
+    fn log_message(&mut self, level: StatusLevel, status: &str) {
+        if status.is_empty()
+            || self
+                .message_log
+                .back()
+                .is_some_and(|(l, s)| *l == level && s == status)
+        {
+            return;
+        }
+        if self.message_log.len() == Self::MESSAGE_LOG_LEN {
+            self.message_log.pop_front();
+            if let Some(view) = self.message_view.as_mut() {
+                view.index = view.index.saturating_sub(1);
+            }
+        }
+        self.message_log.push_back((level, status.to_string()));
+    }
+
+    pub(crate) fn open_message_view(&mut self) {
+        if self.message_log.is_empty() {
+            self.set_status("No messages yet");
+            return;
+        }
+        self.message_view = Some(MessageViewState {
+            index: self.message_log.len() - 1,
+            scroll: 0,
+            wrap: true,
+        });
+    }
+
+    pub(crate) fn handle_message_view_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        const PAGE_ROWS: usize = 10;
+        let newest = self.message_log.len().saturating_sub(1);
+        let Some(view) = self.message_view.as_mut() else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.message_view = None,
+            KeyCode::Up => {
+                view.index = view.index.saturating_sub(1);
+                view.scroll = 0;
+            }
+            KeyCode::Down => {
+                view.index = (view.index + 1).min(newest);
+                view.scroll = 0;
+            }
+            KeyCode::Char('j') => view.scroll += 1,
+            KeyCode::Char('k') => view.scroll = view.scroll.saturating_sub(1),
+            KeyCode::PageDown => view.scroll += PAGE_ROWS,
+            KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(PAGE_ROWS),
+            KeyCode::Home => view.scroll = 0,
+            KeyCode::Char('w') => view.wrap = !view.wrap,
+            KeyCode::Char('c') | KeyCode::Char('y') => {
+                let text = self.message_log[view.index].1.clone();
+                match self.clipboard.as_mut().map(|c| c.set_text(text)) {
+                    Some(Ok(())) => self.set_status("Copied message"),
+                    _ => self.set_warning("Clipboard unavailable"),
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_warning<S: Into<String>>(&mut self, status: S) {
+        self.set_status_at(StatusLevel::Warn, status);
+    }
+
+    pub(crate) fn set_error<S: Into<String>>(&mut self, status: S) {
+        self.set_status_at(StatusLevel::Error, status);
+    }
+
+    /// An action had nothing to act on: explain why in the status bar and
+    /// flash or ring the bell as `invalid_action_feedback` says.
+    pub(crate) fn reject_action<S: Into<String>>(&mut self, hint: S) {
+        self.set_status(hint);
+        let feedback = self.editor_settings.invalid_action_feedback;
+        if matches!(feedback, ActionFeedback::Flash | ActionFeedback::Both) {
+            self.flash_until =
+                Some(Instant::now() + Duration::from_millis(Self::FLASH_DURATION_MS));
+        }
+        if matches!(feedback, ActionFeedback::Bell | ActionFeedback::Both) {
+            self.bell_pending = true;
+        }
+    }
+
+    /// Whether the focused pane's border is flashing.
+    pub(crate) fn flash_active(&self) -> bool {
+        self.flash_until.is_some_and(|until| Instant::now() < until)
+    }
+
+    pub(crate) fn dismiss_status(&mut self) {
+        self.status.clear();
+        self.status_level = StatusLevel::Info;
+    }
+
+    /// Clear info and warning messages once their timeout has passed so the
+    /// status bar falls back to the keybinding hints.
+    pub(crate) fn poll_status_expiry(&mut self) {
+        let timeout_ms = match self.status_level {
+            StatusLevel::Info => self.status_settings.info_timeout_ms,
+            StatusLevel::Warn => self.status_settings.warn_timeout_ms,
+            StatusLevel::Error => return,
+        };
+        if timeout_ms > 0
+            && !self.status.is_empty()
+            && self.status_set_at.elapsed() >= Duration::from_millis(timeout_ms)
+        {
+            self.dismiss_status();
+        }
+    }
+
+    /// Ask for a confirmation; the banner above the status bar shows its
+    /// keys until it is answered, canceled, or times out.
+    pub(crate) fn set_pending(&mut self, action: PendingAction) {
+        self.pending = action;
+        self.pending_since = Some(Instant::now());
+    }
+
+    /// Seconds left before the pending confirmation cancels itself, for
+    /// those that do.
+    pub(crate) fn pending_seconds_left(&self) -> Option<u64> {
+        if !pending_auto_cancels(&self.pending) {
+            return None;
+        }
+        let elapsed = self.pending_since.map_or(0, |t| t.elapsed().as_secs());
+        Some(Self::PENDING_CONFIRM_SECS.saturating_sub(elapsed))
+    }
+
+    pub(crate) fn poll_pending_expiry(&mut self) {
+        if !pending_auto_cancels(&self.pending) {
+            return;
+        }
+        let since = *self.pending_since.get_or_insert_with(Instant::now);
+        if since.elapsed() < Duration::from_secs(Self::PENDING_CONFIRM_SECS) {
+            return;
+        }
+        let what = match self.pending {
+            PendingAction::Quit => "Quit",
+            PendingAction::Delete(_) | PendingAction::DeleteMany(_) => "Delete",
+            _ => "Paste",
+        };
+        self.pending = PendingAction::None;
+        self.set_status(format!(
+            "{what} canceled: not confirmed within {}s",
+            Self::PENDING_CONFIRM_SECS
+        ));
     }
 
     pub(crate) fn poll_autosave(&mut self) -> io::Result<()> {
@@ -572,7 +1267,8 @@ impl App {
             return Ok(());
         }
         for tab in &self.tabs {
-            if !tab.dirty {
+            // Rewriting a large file every interval would stall the editor.
+            if !tab.dirty || tab.large_file.is_some() {
                 continue;
             }
             let autosave = autosave_path_for(&tab.path);
@@ -617,7 +1313,7 @@ impl App {
             return Ok(());
         }
         let path = tab.path.clone();
-        let disk = fs::read_to_string(&path)?;
+        let disk = decode_text(&fs::read(&path)?);
         let current = self.tabs[self.active_tab].editor.lines().join("\n");
         let snapshot = self.tabs[self.active_tab]
             .open_disk_snapshot
@@ -640,69 +1336,19 @@ impl App {
     }
 
     pub(crate) fn recompute_folds(&mut self) {
-        let Some(tab) = self.active_tab() else {
+        let Some(tab) = self.active_tab_mut() else {
             return;
         };
-        let lang = syntax_lang_for_path(Some(tab.path.as_path()));
-        let (fold_ranges, bracket_depths) =
-            compute_fold_ranges(self.tabs[self.active_tab].editor.lines(), lang);
-        let tab = &mut self.tabs[self.active_tab];
-        tab.fold_ranges = fold_ranges;
-        tab.bracket_depths = bracket_depths;
-        tab.folded_starts
-            .retain(|start| tab.fold_ranges.iter().any(|r| r.start_line == *start));
+        tab.recompute_folds();
         self.rebuild_visible_rows();
     }
 
     pub(crate) fn rebuild_visible_rows(&mut self) {
-        let Some(tab) = self.active_tab() else {
-            return;
-        };
-        let lines = tab.editor.lines().to_vec();
-        let num_lines = lines.len();
         let wrap_width = self.editor_wrap_width_chars();
         let word_wrap = self.word_wrap;
-        // Precompute hidden rows via HashSet for O(1) lookup per row
-        let mut hidden: HashSet<usize> = HashSet::new();
-        let tab = &self.tabs[self.active_tab];
-        for fr in &tab.fold_ranges {
-            if tab.folded_starts.contains(&fr.start_line) {
-                for row in (fr.start_line + 1)..=fr.end_line {
-                    hidden.insert(row);
-                }
-            }
-        }
-        let tab = &mut self.tabs[self.active_tab];
-        tab.visible_rows_map.clear();
-        tab.visible_row_starts.clear();
-        tab.visible_row_ends.clear();
-        tab.visible_rows_map
-            .reserve(num_lines.saturating_sub(hidden.len()));
-        tab.visible_row_starts
-            .reserve(num_lines.saturating_sub(hidden.len()));
-        tab.visible_row_ends
-            .reserve(num_lines.saturating_sub(hidden.len()));
-        for row in 0..num_lines {
-            if !hidden.contains(&row) {
-                let segments = if word_wrap {
-                    wrap_segments_for_line(&lines[row], wrap_width)
-                } else {
-                    vec![(0, lines[row].chars().count())]
-                };
-                for (start, end) in segments {
-                    tab.visible_rows_map.push(row);
-                    tab.visible_row_starts.push(start);
-                    tab.visible_row_ends.push(end);
-                }
-            }
-        }
-        if tab.visible_rows_map.is_empty() {
-            tab.visible_rows_map.push(0);
-            tab.visible_row_starts.push(0);
-            tab.visible_row_ends.push(0);
+        if let Some(tab) = self.active_tab_mut() {
+            tab.rebuild_visible_rows(word_wrap, wrap_width);
         }
-        let max_scroll = tab.visible_rows_map.len().saturating_sub(1);
-        tab.editor_scroll_row = tab.editor_scroll_row.min(max_scroll);
     }
 
     pub(crate) fn rebuild_all_visible_rows(&mut self) {
@@ -725,9 +1371,19 @@ impl App {
         }
     }
 
+    /// The editor gutter's columns, as set in `editor.json`.
+    pub(crate) fn gutter(&self) -> Gutter {
+        Gutter {
+            line_numbers: self.editor_settings.gutter_line_numbers,
+            folds: self.editor_settings.gutter_folds,
+            diagnostics: self.editor_settings.gutter_diagnostics,
+            git: self.editor_settings.gutter_git,
+        }
+    }
+
     fn editor_wrap_width_chars(&self) -> usize {
         let inner_width = self.editor_rect.width.saturating_sub(2);
-        let content_width = inner_width.saturating_sub(Self::EDITOR_GUTTER_WIDTH);
+        let content_width = inner_width.saturating_sub(self.gutter().width());
         if content_width == 0 {
             usize::MAX
         } else {
@@ -736,40 +1392,13 @@ impl App {
     }
 
     pub(crate) fn visible_index_of_source_row(&self, row: usize) -> usize {
-        let Some(tab) = self.active_tab() else {
-            return 0;
-        };
-        tab.visible_rows_map
-            .iter()
-            .position(|r| *r == row)
-            .unwrap_or_else(|| {
-                tab.visible_rows_map
-                    .iter()
-                    .position(|r| *r > row)
-                    .unwrap_or(tab.visible_rows_map.len().saturating_sub(1))
-            })
+        self.active_tab()
+            .map_or(0, |tab| tab.visible_index_of_source_row(row))
     }
 
     pub(crate) fn visible_index_of_source_position(&self, row: usize, col: usize) -> usize {
-        let Some(tab) = self.active_tab() else {
-            return 0;
-        };
-        let mut fallback = None;
-        for idx in 0..tab.visible_rows_map.len() {
-            if tab.visible_rows_map[idx] != row {
-                continue;
-            }
-            fallback.get_or_insert(idx);
-            let start = tab.visible_row_starts.get(idx).copied().unwrap_or(0);
-            let end = tab.visible_row_ends.get(idx).copied().unwrap_or(start);
-            if col >= start && col < end {
-                return idx;
-            }
-            if col >= end {
-                fallback = Some(idx);
-            }
-        }
-        fallback.unwrap_or_else(|| self.visible_index_of_source_row(row))
+        self.active_tab()
+            .map_or(0, |tab| tab.visible_index_of_source_position(row, col))
     }
 
     pub(crate) fn fold_range_starting_at(&self, row: usize) -> Option<&FoldRange> {
@@ -799,67 +1428,38 @@ impl App {
     }
 
     pub(crate) fn fold_current_block(&mut self) {
-        let Some(tab) = self.active_tab() else {
+        let Some(tab) = self.active_tab_mut() else {
             return;
         };
         let (cursor_row, _) = tab.editor.cursor();
-        let mut candidate: Option<(usize, usize)> = None;
-        for fr in &tab.fold_ranges {
-            if fr.start_line == cursor_row {
-                candidate = Some((fr.start_line, fr.end_line));
-                break;
-            }
-            if fr.start_line <= cursor_row && cursor_row <= fr.end_line {
-                candidate = Some((fr.start_line, fr.end_line));
-            }
-        }
-        if let Some((start_line, end_line)) = candidate {
-            self.tabs[self.active_tab].folded_starts.insert(start_line);
+        if let Some((start_line, end_line)) = tab.fold_block_at(cursor_row) {
             self.rebuild_visible_rows();
             self.sync_editor_scroll_guess();
             self.set_status(format!("Folded lines {}-{}", start_line + 1, end_line + 1));
         } else {
-            self.set_status("No foldable block at cursor");
+            self.reject_action("No foldable block at cursor");
         }
     }
 
     pub(crate) fn unfold_current_block(&mut self) {
-        let Some(tab) = self.active_tab() else {
+        let Some(tab) = self.active_tab_mut() else {
             return;
         };
         let (cursor_row, _) = tab.editor.cursor();
-        let mut unfolded = false;
-        let starts: Vec<usize> = tab.folded_starts.iter().copied().collect();
-        for start in starts {
-            if let Some(fr) = tab.fold_ranges.iter().find(|fr| fr.start_line == start) {
-                if fr.start_line == cursor_row
-                    || (fr.start_line <= cursor_row && cursor_row <= fr.end_line)
-                {
-                    self.tabs[self.active_tab].folded_starts.remove(&start);
-                    unfolded = true;
-                    break;
-                }
-            }
-        }
-        if unfolded {
+        if tab.unfold_block_at(cursor_row) {
             self.rebuild_visible_rows();
             self.sync_editor_scroll_guess();
             self.set_status("Unfolded block");
         } else {
-            self.set_status("No folded block at cursor");
+            self.reject_action("No folded block at cursor");
         }
     }
 
     pub(crate) fn fold_all(&mut self) {
-        let Some(tab) = self.active_tab() else {
+        let Some(tab) = self.active_tab_mut() else {
             return;
         };
-        let starts: Vec<usize> = tab.fold_ranges.iter().map(|fr| fr.start_line).collect();
-        let tab = &mut self.tabs[self.active_tab];
-        for start in starts {
-            tab.folded_starts.insert(start);
-        }
-        let count = tab.folded_starts.len();
+        let count = tab.fold_all();
         self.rebuild_visible_rows();
         self.sync_editor_scroll_guess();
         self.set_status(format!("Folded {} blocks", count));
@@ -870,7 +1470,7 @@ impl App {
             return;
         };
         if tab.folded_starts.is_empty() {
-            self.set_status("No folded blocks");
+            self.reject_action("No folded blocks");
             return;
         }
         self.tabs[self.active_tab].folded_starts.clear();
@@ -884,19 +1484,7 @@ impl App {
             return;
         };
         let (cursor_row, _) = tab.editor.cursor();
-        // Check if cursor is on/in a folded block
-        let mut is_folded = false;
-        for &start in &tab.folded_starts {
-            if let Some(fr) = tab.fold_ranges.iter().find(|fr| fr.start_line == start) {
-                if fr.start_line == cursor_row
-                    || (fr.start_line <= cursor_row && cursor_row <= fr.end_line)
-                {
-                    is_folded = true;
-                    break;
-                }
-            }
-        }
-        if is_folded {
+        if tab.is_folded_at(cursor_row) {
             self.unfold_current_block();
         } else {
             self.fold_current_block();
@@ -918,11 +1506,160 @@ impl App {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ratatui::crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
     use std::fs;
     use tempfile::tempdir;
 
     fn new_app(root: &std::path::Path) -> App {
-        App::new(root.to_path_buf()).expect("app should initialize")
+        App::new_for_test(root)
+    }
+
+    #[test]
+    fn rejected_actions_flash_or_ring_as_configured() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("a.txt");
+        fs::write(&file, "plain\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+
+        app.unfold_current_block();
+        assert_eq!(app.status, "No folded block at cursor");
+        assert!(app.flash_active());
+        assert!(!app.bell_pending);
+
+        app.flash_until = None;
+        app.editor_settings.invalid_action_feedback = ActionFeedback::Bell;
+        app.unfold_all();
+        assert!(app.bell_pending && !app.flash_active());
+
+        app.bell_pending = false;
+        app.editor_settings.invalid_action_feedback = ActionFeedback::Off;
+        app.copy_selection_to_clipboard();
+        assert_eq!(app.status, "No selection to copy");
+        assert!(!app.bell_pending && !app.flash_active());
+    }
+
+    #[test]
+    fn command_palette_ranks_fuzzy_matches_then_recent_commands() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        app.recent_commands = vec![CommandAction::RevertHunk, CommandAction::SaveFile];
+        app.refresh_menu_results();
+        assert_eq!(app.menu_results.len(), COMMANDS.len());
+        assert_eq!(
+            app.menu_results[..3],
+            [
+                CommandAction::RevertHunk,
+                CommandAction::SaveFile,
+                CommandAction::Theme
+            ]
+        );
+
+        app.menu_query = "gtl".to_string();
+        app.refresh_menu_results();
+        assert_eq!(app.menu_results.first(), Some(&CommandAction::GoToLine));
+        app.menu_query = "zzq".to_string();
+        app.refresh_menu_results();
+        assert!(app.menu_results.is_empty());
+    }
+
+    #[test]
+    fn typing_during_a_pending_reload_is_held_then_replayed() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("notes.txt");
+        fs::write(&file, "old\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file.clone()).expect("open");
+        let type_char = |app: &mut App, c| {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+            app.handle_event(Event::Key(key)).expect("key");
+        };
+        let settle = |app: &mut App| {
+            app.last_fs_refresh = Instant::now() - Duration::from_secs(1);
+            app.poll_fs_changes().expect("poll");
+        };
+
+        // A clean buffer reloads first; the held keys then apply to the new text.
+        fs::write(&file, "new\n").expect("rewrite");
+        app.fs_changed_paths.insert(file.clone());
+        app.fs_refresh_pending = true;
+        type_char(&mut app, 'X');
+        assert_eq!(app.held_input.len(), 1);
+        assert_eq!(app.tabs[0].editor.lines()[0], "old");
+        settle(&mut app);
+        assert!(app.held_input.is_empty());
+        assert_eq!(app.tabs[0].editor.lines()[0], "Xnew");
+        assert_eq!(app.status_level, StatusLevel::Warn);
+
+        // With local edits the conflict prompt opens, but the typed keys
+        // still reach the buffer rather than the prompt's shortcuts.
+        fs::write(&file, "other\n").expect("rewrite");
+        app.fs_changed_paths.insert(file.clone());
+        app.fs_refresh_pending = true;
+        type_char(&mut app, 'd');
+        type_char(&mut app, 'r');
+        settle(&mut app);
+        let tab = &app.tabs[0];
+        assert_eq!(tab.editor.lines()[0], "Xdrnew");
+        assert!(tab.conflict_prompt_open);
+    }
+
+    #[test]
+    fn changes_under_collapsed_ignored_folders_do_not_refresh() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::write(root.join(".gitignore"), "target/\n").expect("write");
+        fs::create_dir_all(root.join("target/debug")).expect("mkdir");
+        let mut app = new_app(root);
+        let (tx, rx) = mpsc::channel();
+        app.fs_rx = Some(rx);
+        let changed = |app: &mut App, path: PathBuf| {
+            app.fs_refresh_pending = false;
+            tx.send(crate::app::FsChangeEvent {
+                paths: vec![path],
+                kind: EventKind::Any,
+                full_refresh: false,
+            })
+            .expect("send");
+            app.collect_fs_changes().expect("collect");
+            app.fs_refresh_pending
+        };
+
+        assert!(!changed(&mut app, root.join("target/debug/app.d")));
+        assert!(changed(&mut app, root.join("target")), "listed at the root");
+        assert!(changed(&mut app, root.join("main.txt")));
+        app.expanded.insert(root.join("target/debug"));
+        assert!(changed(&mut app, root.join("target/debug/app.d")));
+    }
+
+    #[test]
+    fn polled_roots_reload_external_changes_from_mtimes() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("notes.txt");
+        fs::write(&file, "before\n").expect("write");
+        let mut app = new_app(root);
+        let polled = &mut app.watch_settings.poll_roots;
+        polled.insert(root.to_path_buf(), 100);
+        polled.insert(root.join("elsewhere"), 5_000);
+        assert_eq!(
+            app.watch_settings.poll_interval_for(root),
+            Some(Duration::from_millis(100))
+        );
+        app.start_fs_watcher();
+        assert!(app.fs_watcher.is_none() && app.fs_rx.is_none());
+        app.open_file(file.clone()).expect("open");
+
+        std::thread::sleep(Duration::from_millis(50));
+        fs::write(&file, "after\n").expect("rewrite");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while app.tabs[0].editor.lines()[0] != "after" && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+            // Files on polled roots also load in the background.
+            app.poll_file_io().expect("load");
+            app.poll_fs_changes().expect("poll");
+        }
+        assert_eq!(app.tabs[0].editor.lines()[0], "after");
     }
 
     #[test]
@@ -1166,6 +1903,45 @@ mod tests {
         assert!(tab.visible_rows_map.contains(&5));
     }
 
+    #[test]
+    fn wrapped_lines_navigate_by_visual_row() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("test.txt");
+        fs::write(&file, "hello world this is a long line\nshort\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        app.editor_settings.visual_line_navigation = true;
+        app.word_wrap = true;
+        app.editor_rect = Rect::new(0, 0, 22, 20); // wrap_width ~ 10
+        app.rebuild_visible_rows();
+        let tab = app.active_tab().expect("tab");
+        let (second_start, second_end) = (tab.visible_row_starts[1], tab.visible_row_ends[1]);
+        let last = tab
+            .visible_rows_map
+            .iter()
+            .rposition(|&r| r == 0)
+            .expect("row 0");
+        let last_start = tab.visible_row_starts[last];
+        let press = |app: &mut App, code| {
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+                .expect("key");
+            app.active_tab().expect("tab").editor.cursor()
+        };
+
+        // Down keeps the column within the row instead of the line.
+        app.tabs[0].jump_to((0, 2));
+        assert_eq!(press(&mut app, KeyCode::Down), (0, second_start + 2));
+        // End stops at the row's last char, then goes to the line's end.
+        assert_eq!(press(&mut app, KeyCode::End), (0, second_end - 1));
+        assert_eq!(press(&mut app, KeyCode::End), (0, 31));
+        assert_eq!(press(&mut app, KeyCode::Home), (0, last_start));
+        assert_eq!(press(&mut app, KeyCode::Home), (0, 0));
+
+        // Turned off, Up/Down move by whole lines again.
+        app.editor_settings.visual_line_navigation = false;
+        assert_eq!(press(&mut app, KeyCode::Down).0, 1);
+    }
+
     #[test]
     fn wrap_rebuild_deadline_initialized_none() {
         let tmp = tempdir().expect("tempdir");
@@ -1202,4 +1978,121 @@ mod tests {
             "deadline should NOT be cleared yet"
         );
     }
+
+    #[test]
+    fn status_levels_hold_errors_and_fresh_warnings() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        app.set_error("Autosave error: disk full");
+        app.set_status("Word wrap enabled");
+        assert_eq!(app.status, "Autosave error: disk full");
+        app.poll_status_expiry();
+        assert_eq!(app.status_level, StatusLevel::Error);
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("esc");
+        assert!(app.status.is_empty());
+
+        app.set_warning("Invalid regex: unclosed group");
+        app.set_status("Word wrap enabled");
+        assert_eq!(app.status, "Invalid regex: unclosed group");
+        app.status_settings.warn_min_display_ms = 0;
+        app.set_status("Word wrap enabled");
+        assert_eq!(app.status, "Word wrap enabled");
+
+        app.status_settings.info_timeout_ms = 1;
+        app.status_set_at = Instant::now() - Duration::from_millis(5);
+        app.poll_status_expiry();
+        assert!(app.status.is_empty());
+    }
+
+    #[test]
+    fn message_log_keeps_held_messages_and_browses_them() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        app.message_log.clear();
+        app.set_error("LSP failed: rust-analyzer exited with status 1");
+        app.set_status("Saved");
+        app.set_status("Saved");
+        // The info message never reached the status bar but is still logged.
+        assert_eq!(app.status_level, StatusLevel::Error);
+        assert_eq!(app.message_log.len(), 2);
+
+        app.open_message_view();
+        let view = app.message_view.as_ref().expect("view");
+        assert_eq!(app.message_log[view.index].1, "Saved");
+        app.handle_key(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE))
+            .expect("up");
+        let view = app.message_view.as_ref().expect("view");
+        assert_eq!(view.index, 0);
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("esc");
+        assert!(app.message_view.is_none());
+
+        for i in 0..App::MESSAGE_LOG_LEN {
+            app.set_status(format!("message {i}"));
+        }
+        assert_eq!(app.message_log.len(), App::MESSAGE_LOG_LEN);
+        assert_eq!(app.message_log[0].1, "message 0");
+    }
+
+    #[test]
+    fn injected_events_drive_the_app() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("a.txt");
+        fs::write(&file, "one\n").expect("write");
+        let mut app = new_app(tmp.path());
+        assert!(app.fs_watcher.is_none() && app.fs_rx.is_none());
+        app.open_file(file).expect("open");
+        app.focus = Focus::Editor;
+
+        let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        app.handle_event(key(KeyCode::Char('x'))).expect("key");
+        app.handle_event(Event::Paste("yz".to_string()))
+            .expect("paste");
+        app.handle_event(Event::FocusLost).expect("ignored");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[0], "xyzone");
+        assert!(tab.dirty);
+    }
+
+    #[test]
+    fn pending_quit_lets_navigation_through_and_times_out() {
+        let tmp = tempdir().expect("tempdir");
+        fs::write(tmp.path().join("a.txt"), "one\n").expect("write");
+        fs::write(tmp.path().join("b.txt"), "two\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(tmp.path().join("a.txt")).expect("open");
+        app.tabs[0].dirty = true;
+        app.focus = Focus::Tree;
+        app.selected = 0;
+        let press = |code, mods| KeyEvent::new(code, mods);
+        app.handle_key(press(KeyCode::Char('q'), KeyModifiers::CONTROL))
+            .expect("quit");
+        assert!(matches!(app.pending, PendingAction::Quit));
+        assert_eq!(app.pending_seconds_left(), Some(App::PENDING_CONFIRM_SECS));
+
+        // Arrows move the tree selection; other keys are held.
+        app.handle_key(press(KeyCode::Down, KeyModifiers::NONE))
+            .expect("down");
+        assert_eq!(app.selected, 1);
+        app.handle_key(press(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("held");
+        assert!(matches!(app.pending, PendingAction::Quit));
+
+        app.poll_pending_expiry();
+        assert!(matches!(app.pending, PendingAction::Quit));
+        app.pending_since =
+            Instant::now().checked_sub(Duration::from_secs(App::PENDING_CONFIRM_SECS));
+        app.poll_pending_expiry();
+        assert!(matches!(app.pending, PendingAction::None));
+        assert!(app.status.starts_with("Quit canceled"));
+        assert!(!app.quit);
+
+        // Close prompts never time out.
+        app.set_pending(PendingAction::ClosePrompt);
+        app.pending_since = Some(Instant::now() - Duration::from_secs(60));
+        app.poll_pending_expiry();
+        assert!(matches!(app.pending, PendingAction::ClosePrompt));
+        assert_eq!(app.pending_seconds_left(), None);
+    }
 }
@@ -1,8 +1,7 @@
-use super::App;
-use std::collections::HashSet;
-use std::fs;
-use std::io;
-use std::path::PathBuf;
+use super::{App, ClosedTab, DiffViewState, HexViewState, MergeViewState};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use ratatui::crossterm::event::{KeyCode, KeyEvent};
@@ -10,16 +9,31 @@ use ratatui::style::Style;
 use serde_json::json;
 use ratatui_textarea::TextArea;
 
+use crate::diff::{changed_positions, diff_hunks, diff_lines, merge_hunks, unified_diff};
+use crate::file_io::{FileRead, read_for_open};
+use crate::json_log::looks_like_json_log;
 use crate::keybinds::{KeyAction, KeyScope};
-use crate::persistence::autosave_path_for;
-use crate::syntax::syntax_lang_for_path;
-use crate::tab::Tab;
-use crate::types::{EditorContextAction, Focus};
+use crate::persistence::{
+    UndoHistory, autosave_path_for, content_hash, load_undo_history, save_undo_history,
+};
+use crate::preview::{decode_image_preview, describe_file_type, format_size};
+use crate::syntax::{SyntaxLang, comment_start_for_lang, syntax_lang_for_path};
+use crate::tab::{LargeFile, Tab};
+use crate::types::{EditorContextAction, Focus, IndentStyle, PromptMode, PromptState};
 use crate::util::{
-    comment_prefix_for_path, compute_fold_ranges, compute_git_line_status, editor_context_actions,
-    inside, leading_indent_bytes, relative_path, text_to_lines, to_u16_saturating,
+    binary_reason, char_index_at_display_offset, comment_prefix_for_path, compute_git_line_status,
+    decode_text, detect_indent, display_width_of_range, editor_context_actions,
+    editor_context_needs_lsp, encode_text, file_tail_fingerprint, inside, large_file_threshold,
+    leading_indent_bytes, markdown_list_prefix, read_line_chunk, reindent_line, reindent_pasted,
+    relative_path, rust_fn_name_at, tail_fingerprint, text_to_lines, to_u16_saturating, uses_crlf,
+    utf16_bom,
 };
 
+/// Brackets and quotes typed in the editor, with the closer inserted after
+/// them.
+const AUTO_PAIRS: [(char, char); 5] =
+    [('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')];
+
 impl App {
     pub(crate) fn duplicate_current_line(&mut self, above: bool) {
         let Some(tab) = self.active_tab() else {
@@ -35,8 +49,7 @@ impl App {
         let insert_at = if above { row } else { row + 1 };
         lines.insert(insert_at, line);
         let new_row = if above { row + 1 } else { row };
-        self.replace_editor_text(lines, (new_row, col));
-        self.on_editor_content_changed();
+        self.apply_edit_transaction(&lines, (new_row, col));
         if above {
             self.set_status("Duplicated line above");
         } else {
@@ -44,12 +57,437 @@ impl App {
         }
     }
 
+    /// Move the cursor's line, or every line the selection touches, one line
+    /// up or down; the selection moves with the text.
+    pub(crate) fn move_lines(&mut self, up: bool) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let cursor = tab.editor.cursor();
+        let selection = tab.editor.selection_range();
+        // A selection ending at column 0 does not include that line.
+        let (start, end) = match selection {
+            Some(((s, _), (e, 0))) if e > s => (s, e - 1),
+            Some(((s, _), (e, _))) => (s, e),
+            None => (cursor.0, cursor.0),
+        };
+        let mut lines = tab.editor.lines().to_vec();
+        if (up && start == 0) || (!up && end + 1 >= lines.len()) {
+            return;
+        }
+        if up {
+            let line = lines.remove(start - 1);
+            lines.insert(end, line);
+        } else {
+            let line = lines.remove(end + 1);
+            lines.insert(start, line);
+        }
+        let shift = |(row, col): (usize, usize)| if up { (row - 1, col) } else { (row + 1, col) };
+        let anchor = selection.map(|(s, e)| if cursor == s { e } else { s });
+        self.apply_edit_transaction(&lines, shift(cursor));
+        if let Some(anchor) = anchor
+            && let Some(tab) = self.active_tab_mut()
+        {
+            tab.jump_to(shift(anchor));
+            tab.editor.start_selection();
+            tab.jump_to(shift(cursor));
+        }
+    }
+
+    /// Enter on a Markdown list item or block quote: carry its prefix onto
+    /// the new line, or drop the marker when the item is still empty.
+    /// Returns false to let Enter insert a plain newline.
+    pub(crate) fn continue_markdown_list(&mut self) -> bool {
+        let Some(tab) = self.active_tab() else {
+            return false;
+        };
+        if tab.lang != SyntaxLang::Markdown || tab.editor.selection_range().is_some() {
+            return false;
+        }
+        let (row, col) = tab.editor.cursor();
+        let mut lines = tab.editor.lines().to_vec();
+        let Some((prefix_len, next_prefix)) = lines.get(row).and_then(|l| markdown_list_prefix(l))
+        else {
+            return false;
+        };
+        let line = &lines[row];
+        let split = line.char_indices().nth(col).map_or(line.len(), |(i, _)| i);
+        if split < prefix_len {
+            return false;
+        }
+        if line[prefix_len..].trim().is_empty() {
+            lines[row].clear();
+            self.apply_edit_transaction(&lines, (row, 0));
+            return true;
+        }
+        let rest = lines[row].split_off(split);
+        let cursor = (row + 1, next_prefix.chars().count());
+        lines.insert(row + 1, next_prefix + &rest);
+        self.apply_edit_transaction(&lines, cursor);
+        true
+    }
+
+    /// Enter with auto-indent: the new line keeps the current line's
+    /// indentation, one level deeper after an opening bracket (or `:` in
+    /// Python). Between a bracket pair the closer moves to its own line.
+    pub(crate) fn insert_newline_with_indent(&mut self) -> bool {
+        let Some(tab) = self.active_tab() else {
+            return false;
+        };
+        if tab.editor.selection_range().is_some() {
+            return false;
+        }
+        let (row, col) = tab.editor.cursor();
+        let mut lines = tab.editor.lines().to_vec();
+        let Some(line) = lines.get(row) else {
+            return false;
+        };
+        let split = line.char_indices().nth(col).map_or(line.len(), |(i, _)| i);
+        let indent = line[..leading_indent_bytes(line)].to_string();
+        let unit = match indent.chars().next() {
+            Some('\t') => "\t".to_string(),
+            Some(_) => " ".repeat(self.editor_settings.tab_width.max(1) as usize),
+            None => self.editor_settings.indent_unit(),
+        };
+        let before = line[..split].trim_end();
+        let opens = tab.lang.block_openers();
+        let opener = before.chars().last().filter(|c| opens.contains(c));
+        let rest = line[split..].trim_start().to_string();
+        let closes_pair = matches!(
+            (opener, rest.chars().next()),
+            (Some('{'), Some('}')) | (Some('('), Some(')')) | (Some('['), Some(']'))
+        );
+        let inner = if opener.is_some() {
+            format!("{indent}{unit}")
+        } else {
+            indent.clone()
+        };
+        lines[row].truncate(split);
+        let cursor = (row + 1, inner.chars().count());
+        if closes_pair {
+            lines.insert(row + 1, inner);
+            lines.insert(row + 2, indent + &rest);
+        } else {
+            lines.insert(row + 1, inner + &rest);
+        }
+        self.apply_edit_transaction(&lines, cursor);
+        true
+    }
+
+    /// Typing an opening bracket or quote wraps the selection in the pair,
+    /// or inserts the pair around the cursor; typing a closer this inserted
+    /// steps over it. Returns false for other characters.
+    pub(crate) fn type_auto_pair_char(&mut self, c: char) -> bool {
+        if self.skip_auto_closer(c) {
+            return true;
+        }
+        let Some(&(open, close)) = AUTO_PAIRS.iter().find(|(open, _)| *open == c) else {
+            return false;
+        };
+        let Some(tab) = self.active_tab_mut() else {
+            return false;
+        };
+        if let Some((start, end)) = tab.editor.selection_range() {
+            self.wrap_selection_in_pair(start, end, open, close);
+            return true;
+        }
+        if !tab.editor.insert_str(format!("{open}{close}")) {
+            return false;
+        }
+        tab.editor.move_cursor(ratatui_textarea::CursorMove::Back);
+        let (row, col) = tab.editor.cursor();
+        let len = tab.editor.lines()[row].chars().count();
+        tab.auto_closers.retain(|&(r, _)| r == row);
+        tab.auto_closers.push((row, len - col));
+        self.on_editor_content_changed();
+        self.set_status("Auto-pair inserted");
+        true
+    }
+
+    /// Put `open` before `start` and `close` after `end`, keeping the
+    /// wrapped text selected.
+    fn wrap_selection_in_pair(
+        &mut self,
+        start: (usize, usize),
+        end: (usize, usize),
+        open: char,
+        close: char,
+    ) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let mut lines = tab.editor.lines().to_vec();
+        let insert = |line: &mut String, col: usize, c: char| {
+            let at = line.char_indices().nth(col).map_or(line.len(), |(i, _)| i);
+            line.insert(at, c);
+        };
+        insert(&mut lines[end.0], end.1, close);
+        insert(&mut lines[start.0], start.1, open);
+        let start = (start.0, start.1 + 1);
+        let end = (end.0, end.1 + usize::from(start.0 == end.0));
+        self.apply_edit_transaction(&lines, end);
+        if let Some(tab) = self.active_tab_mut() {
+            tab.jump_to(start);
+            tab.editor.start_selection();
+            tab.jump_to(end);
+        }
+    }
+
+    /// Step over `c` when it is a closer inserted with its opener and the
+    /// cursor is right before it.
+    fn skip_auto_closer(&mut self, c: char) -> bool {
+        let Some(tab) = self.active_tab_mut() else {
+            return false;
+        };
+        if tab.editor.selection_range().is_some() {
+            return false;
+        }
+        let (row, col) = tab.editor.cursor();
+        let Some(line) = tab.editor.lines().get(row) else {
+            return false;
+        };
+        let from_end = line.chars().count().saturating_sub(col);
+        if line.chars().nth(col) != Some(c) || !tab.auto_closers.contains(&(row, from_end)) {
+            return false;
+        }
+        // Closers of enclosing pairs stay ahead of the cursor.
+        tab.auto_closers.retain(|&(r, e)| r == row && e < from_end);
+        tab.editor
+            .move_cursor(ratatui_textarea::CursorMove::Forward);
+        self.sync_editor_scroll_guess();
+        true
+    }
+
+    /// Backspace between an opening bracket or quote and its closer
+    /// deletes both.
+    pub(crate) fn delete_empty_pair(&mut self) -> bool {
+        let Some(tab) = self.active_tab() else {
+            return false;
+        };
+        if tab.editor.selection_range().is_some() {
+            return false;
+        }
+        let (row, col) = tab.editor.cursor();
+        let Some(line) = tab.editor.lines().get(row) else {
+            return false;
+        };
+        let chars: Vec<char> = line.chars().collect();
+        let pair = col
+            .checked_sub(1)
+            .and_then(|before| Some((*chars.get(before)?, *chars.get(col)?)));
+        if !pair.is_some_and(|pair| AUTO_PAIRS.contains(&pair)) {
+            return false;
+        }
+        let from_end = chars.len() - col;
+        let mut lines = tab.editor.lines().to_vec();
+        lines[row] = chars[..col - 1].iter().chain(&chars[col + 1..]).collect();
+        self.apply_edit_transaction(&lines, (row, col - 1));
+        if let Some(tab) = self.active_tab_mut() {
+            tab.auto_closers.retain(|&entry| entry != (row, from_end));
+        }
+        true
+    }
+
+    /// Tab / Shift+Tab on Markdown list items nest or un-nest them by two
+    /// spaces. Returns false unless every line in range is a list item.
+    pub(crate) fn shift_markdown_list(&mut self, indent: bool) -> bool {
+        let Some(tab) = self.active_tab() else {
+            return false;
+        };
+        if tab.lang != SyntaxLang::Markdown {
+            return false;
+        }
+        let (row, col) = tab.editor.cursor();
+        let (start_row, end_row) = match tab.editor.selection_range() {
+            Some(((s, _), (e, _))) => (s.min(e), s.max(e)),
+            None => (row, row),
+        };
+        let mut lines = tab.editor.lines().to_vec();
+        let is_item =
+            |l: &String| markdown_list_prefix(l).is_some() && !l.trim_start().starts_with('>');
+        if end_row >= lines.len() || !lines[start_row..=end_row].iter().all(is_item) {
+            return false;
+        }
+        let mut new_col = col;
+        for (r, line) in lines
+            .iter_mut()
+            .enumerate()
+            .take(end_row + 1)
+            .skip(start_row)
+        {
+            let shift = if indent {
+                line.insert_str(0, "  ");
+                2
+            } else {
+                let spaces = (line.len() - line.trim_start_matches(' ').len()).min(2);
+                line.drain(..spaces);
+                spaces
+            };
+            if r == row {
+                new_col = if indent {
+                    col + shift
+                } else {
+                    col.saturating_sub(shift)
+                };
+            }
+        }
+        self.apply_edit_transaction(&lines, (row, new_col));
+        true
+    }
+
+    /// Jump to the newest edit location, or on repeated use to the one
+    /// before the last jump, wrapping around to the newest.
+    pub(crate) fn go_to_last_edit_location(&mut self) -> io::Result<()> {
+        let Some(newest) = self.edit_locations.len().checked_sub(1) else {
+            self.set_status("No edits yet this session");
+            return Ok(());
+        };
+        let idx = match self.edit_location_index {
+            Some(0) => newest,
+            Some(i) => i - 1,
+            None => {
+                // Already at the newest edit: start one further back.
+                let (path, row, _) = &self.edit_locations[newest];
+                let here = self
+                    .active_tab()
+                    .is_some_and(|t| t.path == *path && t.editor.cursor().0.abs_diff(*row) <= 1);
+                if here && newest > 0 {
+                    newest - 1
+                } else {
+                    newest
+                }
+            }
+        };
+        self.edit_location_index = Some(idx);
+        let (path, row, col) = self.edit_locations[idx].clone();
+        if !self.go_to_location(&path, row, col)? {
+            return Ok(());
+        }
+        self.set_status(format!(
+            "Edit location {}/{}: {}:{}",
+            idx + 1,
+            newest + 1,
+            relative_path(&self.root, &path).display(),
+            row + 1
+        ));
+        Ok(())
+    }
+
+    /// Open `path` if it is not the active tab and put the cursor at `row`
+    /// and `col`, centered. Warns and returns false when the file is gone.
+    pub(crate) fn go_to_location(
+        &mut self,
+        path: &Path,
+        row: usize,
+        col: usize,
+    ) -> io::Result<bool> {
+        if self.open_path().map(PathBuf::as_path) != Some(path) {
+            if !path.is_file() {
+                self.set_warning(format!("File not found: {}", path.display()));
+                return Ok(false);
+            }
+            self.open_file(path.to_path_buf())?;
+        }
+        if let Some(tab) = self.active_tab_mut() {
+            let row = row.min(tab.editor.lines().len().saturating_sub(1));
+            tab.editor.cancel_selection();
+            tab.jump_to((row, col));
+        }
+        self.center_cursor_in_view();
+        Ok(true)
+    }
+
+    /// Remember the cursor position before a jump (go to definition, a
+    /// search result, another file) so "Jump Back" can return to it. A new
+    /// jump drops the positions stepped back from.
+    pub(crate) fn record_jump(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let (row, col) = tab.editor.cursor();
+        let here = (tab.path.clone(), row, col);
+        self.jump_forward.clear();
+        if self.jump_back.last() == Some(&here) {
+            return;
+        }
+        if self.jump_back.len() == Self::JUMP_LIST_LEN {
+            self.jump_back.remove(0);
+        }
+        self.jump_back.push(here);
+    }
+
+    /// Step back, or forward again, through the jump list, skipping
+    /// positions in files that no longer exist.
+    pub(crate) fn navigate_jump_list(&mut self, back: bool) -> io::Result<()> {
+        let here = self.active_tab().map(|t| {
+            let (row, col) = t.editor.cursor();
+            (t.path.clone(), row, col)
+        });
+        let stack = if back {
+            &mut self.jump_back
+        } else {
+            &mut self.jump_forward
+        };
+        let target = loop {
+            let Some(entry) = stack.pop() else {
+                self.set_status(if back {
+                    "No earlier position in the jump list"
+                } else {
+                    "No later position in the jump list"
+                });
+                return Ok(());
+            };
+            if here.as_ref() != Some(&entry) && entry.0.is_file() {
+                break entry;
+            }
+        };
+        // Opening another file records a jump of its own; keep the lists
+        // out of its way.
+        let jump_back = std::mem::take(&mut self.jump_back);
+        let jump_forward = std::mem::take(&mut self.jump_forward);
+        let (path, row, col) = target;
+        let moved = self.go_to_location(&path, row, col);
+        self.jump_back = jump_back;
+        self.jump_forward = jump_forward;
+        moved?;
+        if let Some(here) = here {
+            if back {
+                self.jump_forward.push(here);
+            } else {
+                self.jump_back.push(here);
+            }
+        }
+        self.set_status(format!(
+            "Jumped {} to {}:{}",
+            if back { "back" } else { "forward" },
+            relative_path(&self.root, &path).display(),
+            row + 1
+        ));
+        Ok(())
+    }
+
+    pub(crate) fn jump_to_matching_bracket(&mut self) {
+        let Some(tab) = self.active_tab_mut() else {
+            self.set_status("No file open");
+            return;
+        };
+        let Some((_, target)) = tab.bracket_match_at_cursor() else {
+            self.reject_action("No matching bracket at cursor");
+            return;
+        };
+        tab.editor.cancel_selection();
+        tab.jump_to(target);
+        self.sync_editor_scroll_guess();
+    }
+
     pub(crate) fn toggle_comment(&mut self) {
         let Some(tab) = self.active_tab() else {
             self.set_status("No file open");
             return;
         };
-        let Some(prefix) = comment_prefix_for_path(&tab.path) else {
+        let Some(prefix) =
+            comment_start_for_lang(tab.lang).or_else(|| comment_prefix_for_path(&tab.path))
+        else {
             self.set_status("No comment style for file type");
             return;
         };
@@ -97,11 +535,55 @@ impl App {
             }
         }
         let cursor = self.tabs[self.active_tab].editor.cursor();
-        self.replace_editor_text(lines, cursor);
-        self.on_editor_content_changed();
+        self.apply_edit_transaction(&lines, cursor);
         self.set_status("Toggled comment");
     }
 
+    /// Tab with several lines selected indents each of them by one level,
+    /// the counterpart of `dedent_lines`. Returns false for a selection
+    /// within one line, which Tab replaces as usual.
+    pub(crate) fn indent_selected_lines(&mut self) -> bool {
+        let Some(tab) = self.active_tab() else {
+            return false;
+        };
+        let Some(((start_row, start_col), (end_row, end_col))) = tab.editor.selection_range()
+        else {
+            return false;
+        };
+        if start_row == end_row {
+            return false;
+        }
+        let unit = self.editor_settings.indent_unit();
+        let width = unit.chars().count();
+        let mut lines = tab.editor.lines().to_vec();
+        // A selection ending at column 0 does not include that line.
+        let last = if end_col == 0 { end_row - 1 } else { end_row };
+        for line in lines.iter_mut().take(last + 1).skip(start_row) {
+            if !line.is_empty() {
+                line.insert_str(0, &unit);
+            }
+        }
+        let shift = |row: usize, col: usize| {
+            if row <= last && !lines[row].is_empty() {
+                (row, col + width)
+            } else {
+                (row, col)
+            }
+        };
+        let (mut anchor, mut cursor) = (shift(start_row, start_col), shift(end_row, end_col));
+        if tab.editor.cursor() == (start_row, start_col) {
+            std::mem::swap(&mut anchor, &mut cursor);
+        }
+        self.apply_edit_transaction(&lines, cursor);
+        if let Some(tab) = self.active_tab_mut() {
+            tab.jump_to(anchor);
+            tab.editor.start_selection();
+            tab.jump_to(cursor);
+        }
+        self.set_status("Indented");
+        true
+    }
+
     pub(crate) fn dedent_lines(&mut self) {
         let Some(tab) = self.active_tab() else {
             return;
@@ -118,17 +600,15 @@ impl App {
             return;
         }
         let end_row = end_row.min(lines.len().saturating_sub(1));
+        let width = self.editor_settings.tab_width.max(1) as usize;
         let mut changed = false;
         for line in lines.iter_mut().take(end_row + 1).skip(start_row) {
-            if line.starts_with("    ") {
-                *line = line[4..].to_string();
-                changed = true;
-            } else if line.starts_with('\t') {
+            if line.starts_with('\t') {
                 *line = line[1..].to_string();
                 changed = true;
             } else {
-                // Remove any leading spaces (less than 4)
-                let spaces = line.len() - line.trim_start_matches(' ').len();
+                // Remove up to one indent level of leading spaces
+                let spaces = (line.len() - line.trim_start_matches(' ').len()).min(width);
                 if spaces > 0 {
                     *line = line[spaces..].to_string();
                     changed = true;
@@ -137,40 +617,243 @@ impl App {
         }
         if changed {
             let (row, col) = self.tabs[self.active_tab].editor.cursor();
-            let new_col = col.saturating_sub(4);
-            self.replace_editor_text(lines, (row, new_col));
-            self.on_editor_content_changed();
+            let new_col = col.saturating_sub(width);
+            self.apply_edit_transaction(&lines, (row, new_col));
             self.set_status("Dedented");
         }
     }
 
+    /// Rewrite every line's leading whitespace as tabs, as spaces, or (for
+    /// `None`) the way `editor.json` indents, and report the style found.
+    pub(crate) fn convert_indentation(&mut self, to_tabs: Option<bool>) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let tab_width = self.editor_settings.tab_width.max(1) as usize;
+        let Some(found) = detect_indent(tab.editor.lines(), tab_width) else {
+            self.set_status("No indented lines to convert");
+            return;
+        };
+        let target = IndentStyle {
+            tabs: to_tabs.unwrap_or(!self.editor_settings.insert_spaces),
+            width: tab_width,
+            mixed: false,
+        };
+        let unit = if target.tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(tab_width)
+        };
+        let from_width = if found.tabs { tab_width } else { found.width };
+        let lines: Vec<String> = tab
+            .editor
+            .lines()
+            .iter()
+            .map(|line| reindent_line(line, tab_width, from_width, &unit))
+            .collect();
+        if lines == tab.editor.lines() {
+            self.set_status(format!("Indentation is already {}", found.describe()));
+            return;
+        }
+        let (row, col) = tab.editor.cursor();
+        let (old_lead, new_lead) = (
+            leading_indent_bytes(&tab.editor.lines()[row]),
+            leading_indent_bytes(&lines[row]),
+        );
+        let col = if col >= old_lead {
+            col - old_lead + new_lead
+        } else {
+            col.min(new_lead)
+        };
+        self.apply_edit_transaction(&lines, (row, col));
+        self.set_status(format!(
+            "Indentation was {}, now {}",
+            found.describe(),
+            target.describe()
+        ));
+    }
+
+    /// Add a `#[test]` for the Rust function under the cursor to the file's
+    /// `mod tests`, creating the module at the end if there is none, and
+    /// leave the cursor on its `todo!()` body.
+    pub(crate) fn generate_rust_test(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        if tab.lang != SyntaxLang::Rust {
+            self.set_warning("Test scaffolds are only generated for Rust files");
+            return;
+        }
+        let mut lines = tab.editor.lines().to_vec();
+        let Some(func) = rust_fn_name_at(&lines, tab.editor.cursor().0) else {
+            self.set_warning("No function under the cursor");
+            return;
+        };
+        let mut name = format!("test_{func}");
+        let taken = |name: &str| {
+            let decl = format!("fn {name}(");
+            lines.iter().any(|l| l.contains(&decl))
+        };
+        if taken(&name) {
+            let n = (2..).find(|n| !taken(&format!("{name}_{n}"))).unwrap_or(2);
+            name = format!("{name}_{n}");
+        }
+        let unit = self.editor_settings.indent_unit();
+        let module = lines
+            .windows(2)
+            .position(|w| w[0].trim() == "#[cfg(test)]" && w[1].trim() == "mod tests {")
+            .map(|row| row + 1)
+            .and_then(|row| {
+                let col = lines[row].trim_end().chars().count() - 1;
+                tab.bracket_matches
+                    .get(&(row, col))
+                    .map(|&(end, _)| (row, end))
+            });
+        let (insert_at, indent, mut block) = match module {
+            Some((row, end)) => {
+                let indent = format!("{}{unit}", &lines[row][..leading_indent_bytes(&lines[row])]);
+                (end, indent, vec![String::new()])
+            }
+            None => {
+                // Keep the trailing empty line that stands for the final newline.
+                let end = if lines.last().is_some_and(|l| l.is_empty()) {
+                    lines.len() - 1
+                } else {
+                    lines.len()
+                };
+                let block = vec![
+                    String::new(),
+                    "#[cfg(test)]".to_string(),
+                    "mod tests {".to_string(),
+                    format!("{unit}use super::*;"),
+                    String::new(),
+                ];
+                (end, unit.clone(), block)
+            }
+        };
+        let body_row = insert_at + block.len() + 2;
+        block.extend([
+            format!("{indent}#[test]"),
+            format!("{indent}fn {name}() {{"),
+            format!("{indent}{unit}todo!()"),
+            format!("{indent}}}"),
+        ]);
+        if module.is_none() {
+            block.push("}".to_string());
+        }
+        let body_col = indent.chars().count() + unit.chars().count();
+        lines.splice(insert_at..insert_at, block);
+        self.apply_edit_transaction(&lines, (body_row, body_col));
+        self.set_status(format!("Added test {name}"));
+    }
+
+    /// Tab width and hard tabs from `editor.json`, for a freshly built buffer.
+    fn apply_indent_settings(&self, ta: &mut TextArea<'static>) {
+        ta.set_tab_length(self.editor_settings.tab_width);
+        ta.set_hard_tab_indent(!self.editor_settings.insert_spaces);
+    }
+
+    /// Apply a changed `tab_width` or `insert_spaces` to the open buffers.
+    pub(crate) fn reapply_indent_settings(&mut self) {
+        let width = self.editor_settings.tab_width;
+        let hard_tabs = !self.editor_settings.insert_spaces;
+        for tab in &mut self.tabs {
+            tab.editor.set_tab_length(width);
+            tab.editor.set_hard_tab_indent(hard_tabs);
+        }
+        self.rebuild_all_visible_rows();
+    }
+
     pub(crate) fn replace_editor_text(&mut self, lines: Vec<String>, cursor: (usize, usize)) {
         let mut ta = TextArea::from(lines);
         ta.set_cursor_line_style(Style::default().bg(self.active_theme().bg_alt));
         ta.set_selection_style(Style::default().bg(self.active_theme().selection));
+        self.apply_indent_settings(&mut ta);
         ta.move_cursor(ratatui_textarea::CursorMove::Jump(
             to_u16_saturating(cursor.0),
             to_u16_saturating(cursor.1),
         ));
         if let Some(tab) = self.active_tab_mut() {
+            if let Some(pattern) = tab.editor.search_pattern() {
+                let _ = ta.set_search_pattern(pattern.as_str());
+            }
             tab.editor = ta;
         }
         self.recompute_folds();
+        self.refresh_search_matches();
+        self.sync_editor_scroll_guess();
+    }
+
+    /// Apply a command's rewrite of the buffer as one undo step that brings
+    /// back the current selection when undone.
+    pub(crate) fn apply_edit_transaction(&mut self, lines: &[String], cursor: (usize, usize)) {
+        if self.refuse_read_only_edit() {
+            return;
+        }
+        if !self
+            .active_tab_mut()
+            .is_some_and(|tab| tab.apply_edit(lines, cursor))
+        {
+            return;
+        }
+        self.on_editor_content_changed();
         self.sync_editor_scroll_guess();
     }
 
+    /// Once the live undo history runs out, step back into the snapshots
+    /// restored from before the file was reopened.
+    pub(crate) fn undo_to_restored_snapshot(&mut self) -> bool {
+        let Some(tab) = self.active_tab_mut() else {
+            return false;
+        };
+        let Some(previous) = tab.undo_snapshots.pop() else {
+            return false;
+        };
+        // Swapping the text drops the live redo history, so keep it as
+        // snapshots too.
+        let mut redo = std::mem::take(&mut tab.redo_snapshots);
+        redo.extend(tab.redo_states().into_iter().rev());
+        redo.push(tab.editor.lines().join("\n"));
+        let cursor = tab.editor.cursor();
+        self.restore_snapshot_text(&previous, cursor, redo);
+        true
+    }
+
+    pub(crate) fn redo_to_restored_snapshot(&mut self) -> bool {
+        let Some(tab) = self.active_tab_mut() else {
+            return false;
+        };
+        let Some(next) = tab.redo_snapshots.pop() else {
+            return false;
+        };
+        tab.undo_snapshots.push(tab.editor.lines().join("\n"));
+        let redo = std::mem::take(&mut tab.redo_snapshots);
+        let cursor = tab.editor.cursor();
+        self.restore_snapshot_text(&next, cursor, redo);
+        true
+    }
+
+    fn restore_snapshot_text(&mut self, text: &str, cursor: (usize, usize), redo: Vec<String>) {
+        self.replace_editor_text(text_to_lines(text), cursor);
+        self.on_editor_content_changed();
+        if let Some(tab) = self.active_tab_mut() {
+            tab.redo_snapshots = redo;
+        }
+    }
+
     pub(crate) fn copy_selection_to_clipboard(&mut self) {
         let Some(tab) = self.active_tab_mut() else {
             return;
         };
         if tab.editor.selection_range().is_none() {
-            self.set_status("No selection to copy");
+            self.reject_action("No selection to copy");
             return;
         }
         self.tabs[self.active_tab].editor.copy();
         let copied = self.tabs[self.active_tab].editor.yank_text();
+        self.remember_clipboard_text(&copied);
         if copied.is_empty() {
-            self.set_status("No selection to copy");
+            self.reject_action("No selection to copy");
         } else if let Some(clipboard) = self.clipboard.as_mut() {
             match clipboard.set_text(copied) {
                 Ok(()) => self.set_status("Copied"),
@@ -181,19 +864,90 @@ impl App {
         }
     }
 
-    pub(crate) fn cut_line(&mut self) {
-        let Some(tab) = self.active_tab() else {
+    /// Add copied or cut text to the front of the clipboard history,
+    /// moving it there if it is already listed.
+    pub(crate) fn remember_clipboard_text(&mut self, text: &str) {
+        if text.is_empty() {
             return;
-        };
-        let (row, _col) = tab.editor.cursor();
-        let lines = tab.editor.lines();
-        if lines.is_empty() || row >= lines.len() {
-            self.set_status("No line to cut");
+        }
+        self.clipboard_history.retain(|t| t != text);
+        self.clipboard_history.push_front(text.to_string());
+        self.clipboard_history.truncate(Self::CLIPBOARD_HISTORY_LEN);
+    }
+
+    /// Open the "Paste from History" popup. Text copied in another program
+    /// since the last copy here joins the history first.
+    pub(crate) fn open_clipboard_history(&mut self) {
+        if self.active_tab().is_none() || self.refuse_read_only_edit() {
             return;
         }
-        let line_text = lines[row].to_string();
-        let total_lines = lines.len();
-        let is_last_line = row == total_lines - 1;
+        if let Some(text) = self.clipboard.as_mut().and_then(|c| c.get_text().ok()) {
+            self.remember_clipboard_text(&text);
+        }
+        if self.clipboard_history.is_empty() {
+            self.reject_action("Clipboard history is empty");
+            return;
+        }
+        self.clipboard_history_view = Some(0);
+    }
+
+    pub(crate) fn handle_clipboard_history_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(selected) = self.clipboard_history_view else {
+            return Ok(());
+        };
+        let last = self.clipboard_history.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.clipboard_history_view = None,
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.clipboard_history_view = Some((selected + 1).min(last));
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.clipboard_history_view = Some(selected.saturating_sub(1));
+            }
+            KeyCode::Delete | KeyCode::Char('d') => {
+                self.clipboard_history.remove(selected);
+                self.clipboard_history_view = (!self.clipboard_history.is_empty())
+                    .then(|| selected.min(last.saturating_sub(1)));
+            }
+            KeyCode::Enter => {
+                self.clipboard_history_view = None;
+                let Some(text) = self.clipboard_history.get(selected).cloned() else {
+                    return Ok(());
+                };
+                // The pasted entry becomes the clipboard, so Ctrl+V repeats it.
+                self.remember_clipboard_text(&text);
+                if let Some(clipboard) = self.clipboard.as_mut() {
+                    let _ = clipboard.set_text(text.clone());
+                }
+                let text = self.reindent_paste(text);
+                let pasted = self.active_tab_mut().is_some_and(|t| {
+                    t.editor.set_yank_text(text);
+                    t.editor.paste()
+                });
+                if pasted {
+                    self.on_editor_content_changed();
+                    self.sync_editor_scroll_guess();
+                    self.set_status("Pasted from history");
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn cut_line(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let (row, _col) = tab.editor.cursor();
+        let lines = tab.editor.lines();
+        if lines.is_empty() || row >= lines.len() {
+            self.set_status("No line to cut");
+            return;
+        }
+        let line_text = lines[row].to_string();
+        let total_lines = lines.len();
+        let is_last_line = row == total_lines - 1;
 
         // Select the entire line including its trailing newline, then cut via
         // TextArea so the deletion is recorded in the undo history.
@@ -228,6 +982,7 @@ impl App {
         tab.editor.cut();
 
         // Overwrite yank buffer and system clipboard with the clean line text
+        self.remember_clipboard_text(&line_text);
         if let Some(clipboard) = self.clipboard.as_mut() {
             let _ = clipboard.set_text(line_text.clone());
         }
@@ -251,6 +1006,7 @@ impl App {
             self.on_editor_content_changed();
         }
         let cut = self.tabs[self.active_tab].editor.yank_text();
+        self.remember_clipboard_text(&cut);
         if cut.is_empty() {
             self.set_status("No selection to cut");
         } else if let Some(clipboard) = self.clipboard.as_mut() {
@@ -263,15 +1019,63 @@ impl App {
         }
     }
 
-    /// Handle a bracketed paste event from the terminal. Inserts text
-    /// directly into the editor, bypassing auto-pair logic.
+    /// Bracketed paste. Some terminals also deliver committed IME text this
+    /// way, so route it to whichever text input currently has focus.
     pub(crate) fn handle_paste(&mut self, text: String) {
         if text.is_empty() {
             return;
         }
-        if self.active_tab_mut().is_none() {
+        if self
+            .prompt
+            .as_ref()
+            .is_some_and(|p| matches!(p.mode, PromptMode::PasteLocations))
+        {
+            // Pasted compiler output is read whole, a line at a time.
+            if self.open_location_picker(&text) {
+                self.prompt = None;
+            } else {
+                self.set_warning("No file:line references to files in the project");
+            }
+            return;
+        }
+        // Single-line inputs only take the first line.
+        let line = text.lines().next().unwrap_or_default();
+        if self.form.is_some() {
+            self.paste_into_form(line);
+            return;
+        }
+        if let Some(prompt) = self.prompt.as_mut() {
+            prompt.delete_selection();
+            prompt.value.insert_str(prompt.cursor, line);
+            prompt.cursor += line.len();
+            if matches!(prompt.mode, PromptMode::FindInFile) {
+                let query = prompt.value.clone();
+                self.update_incremental_find(&query);
+            }
+            return;
+        }
+        if self.file_picker_open {
+            self.file_picker_query.push_str(line);
+            self.file_picker_index = 0;
+            self.refresh_file_picker_results();
+            return;
+        }
+        if self.menu_open {
+            self.menu_query.push_str(line);
+            self.refresh_menu_results();
+            return;
+        }
+        if self.keybind_editor.open {
+            if !self.keybind_editor.recording {
+                self.keybind_editor.query.push_str(line);
+                self.refresh_keybind_editor_actions();
+            }
+            return;
+        }
+        if self.active_tab_mut().is_none() || self.refuse_read_only_edit() {
             return;
         }
+        let text = self.reindent_paste(text);
         let inserted = self
             .active_tab_mut()
             .is_some_and(|t| t.editor.insert_str(&text));
@@ -281,7 +1085,65 @@ impl App {
         }
     }
 
+    /// Fit a multi-line paste to the indentation at the cursor, unless
+    /// turned off or the tab is prose. With the cursor in a line's leading
+    /// whitespace the paste starts at the line's indentation: on a blank
+    /// line the cursor moves past it, otherwise to column 0 so the line
+    /// keeps its indentation after the pasted lines.
+    fn reindent_paste(&mut self, text: String) -> String {
+        if !self.editor_settings.reindent_on_paste || !text.contains('\n') {
+            return text;
+        }
+        let tab_width = self.editor_settings.tab_width.max(1) as usize;
+        let default_unit = self.editor_settings.indent_unit();
+        let Some(tab) = self.active_tab_mut() else {
+            return text;
+        };
+        if matches!(tab.lang, SyntaxLang::Plain | SyntaxLang::Markdown)
+            || tab.editor.selection_range().is_some()
+        {
+            return text;
+        }
+        let (row, col) = tab.editor.cursor();
+        let line = tab.editor.lines().get(row).cloned().unwrap_or_default();
+        let indent = line[..leading_indent_bytes(&line)].to_string();
+        let unit = match indent.chars().next() {
+            Some('\t') => "\t".to_string(),
+            Some(_) => " ".repeat(tab_width),
+            None => default_unit,
+        };
+        let text = text.replace("\r\n", "\n");
+        let text = reindent_pasted(&text, &indent, &unit, tab_width, tab.lang.block_openers());
+        let indent_chars = indent.chars().count();
+        if col > indent_chars {
+            return text;
+        }
+        let first = text.trim_start_matches([' ', '\t']);
+        if indent.len() == line.len() {
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(row),
+                to_u16_saturating(indent_chars),
+            ));
+            first.to_string()
+        } else {
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(row),
+                0,
+            ));
+            format!("{indent}{first}")
+        }
+    }
+
     pub(crate) fn paste_from_clipboard(&mut self) {
+        self.paste_clipboard(true);
+    }
+
+    /// Paste as copied, without fitting it to the cursor's indentation.
+    pub(crate) fn paste_verbatim(&mut self) {
+        self.paste_clipboard(false);
+    }
+
+    fn paste_clipboard(&mut self, reindent: bool) {
         let mut from_system = false;
         if let Some(clipboard) = self.clipboard.as_mut() {
             if let Ok(text) = clipboard.get_text() {
@@ -293,6 +1155,12 @@ impl App {
                 }
             }
         }
+        if reindent && let Some(tab) = self.active_tab() {
+            let text = self.reindent_paste(tab.editor.yank_text());
+            if let Some(tab) = self.active_tab_mut() {
+                tab.editor.set_yank_text(text);
+            }
+        }
         if self.active_tab_mut().is_some_and(|t| t.editor.paste()) {
             self.on_editor_content_changed();
             if from_system {
@@ -301,7 +1169,7 @@ impl App {
                 self.set_status("Pasted (internal clipboard)");
             }
         } else {
-            self.set_status("Clipboard empty");
+            self.reject_action("Clipboard empty");
         }
     }
 
@@ -309,676 +1177,3047 @@ impl App {
         self.open_file_as(path, false)
     }
 
-    pub(crate) fn open_file_as(&mut self, path: PathBuf, as_preview: bool) -> io::Result<()> {
-        // If file is already open in a tab, just switch to it
-        if let Some(idx) = self.tabs.iter().position(|t| t.path == path) {
-            self.switch_to_tab(idx);
-            if !as_preview {
-                self.tabs[idx].is_preview = false;
-            }
-            self.set_status(format!(
-                "Switched to {}",
-                relative_path(&self.root, &path).display()
-            ));
+    /// Open a file from outside the project, such as a dependency's source
+    /// reached by go-to-definition, in a read-only tab listed under
+    /// "External sources" in the tree.
+    pub(crate) fn open_external_file(&mut self, path: PathBuf) -> io::Result<()> {
+        self.open_file(path.clone())?;
+        let Some(tab) = self.active_tab_mut().filter(|t| t.path == path) else {
             return Ok(());
+        };
+        if !tab.read_only {
+            tab.read_only = true;
+            self.rebuild_tree()?;
         }
+        Ok(())
+    }
 
-        let bytes = fs::read(&path)?;
-        if bytes.iter().take(8192).any(|&b| b == 0) {
-            self.set_status(format!(
-                "Cannot open binary file: {}",
-                relative_path(&self.root, &path).display()
+    /// Warn and return true when the active tab is read-only.
+    pub(crate) fn refuse_read_only_edit(&mut self) -> bool {
+        let Some(tab) = self.active_tab().filter(|t| t.refuses_edits()) else {
+            return false;
+        };
+        let name = tab
+            .path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if tab.read_only {
+            self.set_warning(format!("{name} is read-only: it is outside the project"));
+        } else if tab.follow_tail {
+            self.set_warning(format!(
+                "{name} is being followed: run \"Follow File\" again to edit it"
             ));
-            return Ok(());
-        }
-        let text = String::from_utf8_lossy(&bytes).to_string();
-        let mut ta = TextArea::from(text_to_lines(&text));
-        ta.set_cursor_line_style(Style::default().bg(self.active_theme().bg_alt));
-        ta.set_selection_style(Style::default().bg(self.active_theme().selection));
-
-        let lang = syntax_lang_for_path(Some(path.as_path()));
-        let (fold_ranges, bracket_depths) = compute_fold_ranges(ta.lines(), lang);
-        let mut visible_rows_map = Vec::new();
-        let mut visible_row_starts = Vec::new();
-        let mut visible_row_ends = Vec::new();
-        for row in 0..ta.lines().len() {
-            visible_rows_map.push(row);
-            visible_row_starts.push(0);
-            visible_row_ends.push(ta.lines()[row].chars().count());
-        }
-        if visible_rows_map.is_empty() {
-            visible_rows_map.push(0);
-            visible_row_starts.push(0);
-            visible_row_ends.push(0);
-        }
-
-        let git_line_status = compute_git_line_status(&self.root, &path, ta.lines().len());
-
-        let tab = Tab {
-            path: path.clone(),
-            is_preview: as_preview,
-            editor: ta,
-            dirty: false,
-            open_disk_snapshot: Some(text),
-            editor_scroll_row: 0,
-            editor_scroll_col: 0,
-            fold_ranges,
-            bracket_depths,
-            folded_starts: HashSet::new(),
-            visible_rows_map,
-            visible_row_starts,
-            visible_row_ends,
-            open_doc_uri: None,
-            open_doc_version: 0,
-            diagnostics: Vec::new(),
-            conflict_prompt_open: false,
-            conflict_disk_text: None,
-            recovery_prompt_open: false,
-            recovery_text: None,
-            git_line_status,
-        };
-
-        // If opening as preview, replace existing preview tab
-        if as_preview {
-            if let Some(idx) = self.tabs.iter().position(|t| t.is_preview) {
-                self.close_tab_at(idx);
-                // Insert new tab at the same position
-                self.tabs.insert(idx, tab);
-                self.active_tab = idx;
-            } else {
-                self.tabs.push(tab);
-                self.active_tab = self.tabs.len() - 1;
-            }
         } else {
-            self.tabs.push(tab);
-            self.active_tab = self.tabs.len() - 1;
+            self.set_warning(format!(
+                "{name} is only partly loaded: scroll to the end to load the rest before editing"
+            ));
         }
+        true
+    }
 
-        self.focus = Focus::Editor;
-        self.completion.reset();
-        self.ensure_lsp_for_path(&path);
-        self.check_recovery_for_open_file();
-        self.set_status(format!(
-            "Opened {}",
-            relative_path(&self.root, &path).display()
-        ));
-        Ok(())
+    pub(crate) fn open_file_as(&mut self, path: PathBuf, as_preview: bool) -> io::Result<()> {
+        self.open_file_checked(path, as_preview, false)
     }
 
-    pub(crate) fn save_file(&mut self) -> io::Result<()> {
-        let Some(tab) = self.active_tab_mut() else {
-            self.set_status("No file open");
-            return Ok(());
-        };
-        let path = tab.path.clone();
-        let mut content = tab.editor.lines().join("\n");
-        // Ensure file ends with a trailing newline (POSIX convention)
-        if !content.ends_with('\n') {
-            content.push('\n');
-        }
-        fs::write(&path, &content)?;
-        tab.dirty = false;
-        tab.open_disk_snapshot = Some(content);
-        tab.conflict_prompt_open = false;
-        tab.conflict_disk_text = None;
-        self.clear_autosave_for_open_file();
-        // Trigger an immediate async git refresh so the gutter updates promptly
-        self.fs_refresh_pending = true;
-        self.fs_full_refresh_pending = true;
-        self.last_fs_refresh = Instant::now()
-            .checked_sub(Duration::from_millis(Self::FS_REFRESH_DEBOUNCE_MS + 1))
-            .unwrap_or_else(Instant::now);
+    /// Open a file the binary check refused, decoding it as lossy text.
+    pub(crate) fn open_file_as_text(&mut self, path: PathBuf, as_preview: bool) -> io::Result<()> {
+        self.open_file_checked(path, as_preview, true)
+    }
+
+    /// Show a file that is not opened as text: its type and size, a
+    /// thumbnail for PNG and JPEG images, and a hex dump of the start.
+    pub(crate) fn open_binary_preview(
+        &mut self,
+        path: PathBuf,
+        as_preview: bool,
+        reason: Option<String>,
+    ) -> io::Result<()> {
+        let size = fs::metadata(&path)?.len();
+        let mut bytes = Vec::new();
+        File::open(&path)?
+            .take(Self::BINARY_PREVIEW_BYTES)
+            .read_to_end(&mut bytes)?;
+        let kind = describe_file_type(&bytes);
+        let image = decode_image_preview(&bytes);
         self.set_status(format!(
-            "Saved {}",
-            relative_path(&self.root, &path).display()
+            "Preview: {} ({kind}, {}) - T open as text, E edit bytes, Esc close",
+            relative_path(&self.root, &path).display(),
+            format_size(size)
         ));
+        self.hex_view = Some(HexViewState {
+            path,
+            bytes,
+            scroll: 0,
+            size,
+            kind,
+            reason,
+            image,
+            as_preview,
+            page_rows: 20,
+            edit: None,
+        });
         Ok(())
     }
 
-    pub(crate) fn close_file(&mut self) {
-        if self.tabs.is_empty() {
+    /// Diff the active buffer against the file on disk: the conflicting disk
+    /// text while a conflict prompt is open, otherwise the text last opened
+    /// or saved.
+    pub(crate) fn open_unsaved_diff(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let disk = tab
+            .conflict_disk_text
+            .clone()
+            .or_else(|| tab.open_disk_snapshot.clone())
+            .or_else(|| fs::read(&tab.path).ok().map(|bytes| decode_text(&bytes)))
+            .unwrap_or_default();
+        let rows = unified_diff(&text_to_lines(&disk), tab.editor.lines(), 3);
+        if rows.is_empty() {
+            self.set_status("Buffer matches the file on disk");
             return;
         }
-        self.close_tab_at(self.active_tab);
+        self.diff_view = Some(DiffViewState {
+            path: tab.path.clone(),
+            rows,
+            scroll: 0,
+            legend: "- disk, + buffer",
+        });
     }
 
-    pub(crate) fn close_tab_at(&mut self, idx: usize) {
-        if idx >= self.tabs.len() {
+    /// "Compare with Clipboard": diff the selection, or the whole buffer
+    /// without one, against the clipboard text. A trailing newline on
+    /// either side is ignored.
+    pub(crate) fn compare_with_clipboard(&mut self) {
+        let clipboard = self
+            .clipboard
+            .as_mut()
+            .and_then(|c| c.get_text().ok())
+            .or_else(|| self.clipboard_history.front().cloned())
+            .unwrap_or_default();
+        if clipboard.is_empty() {
+            self.set_status("Clipboard is empty");
             return;
         }
-        // Close LSP document for this tab
-        let tab = &self.tabs[idx];
-        if let (Some(uri), Some(lsp)) = (tab.open_doc_uri.clone(), self.lsp.as_ref()) {
-            let _ = lsp.send_notification(
-                "textDocument/didClose",
-                json!({
-                    "textDocument": { "uri": uri }
-                }),
-            );
-        }
-        // Clear autosave
-        let _ = fs::remove_file(autosave_path_for(&self.tabs[idx].path));
-        self.tabs.remove(idx);
-        if self.tabs.is_empty() {
-            self.active_tab = 0;
-            self.focus = Focus::Tree;
-            self.completion.reset();
-            self.set_status("Closed file");
-        } else if self.active_tab >= self.tabs.len() {
-            self.active_tab = self.tabs.len() - 1;
-        } else if self.active_tab > idx {
-            self.active_tab -= 1;
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let lines = tab.editor.lines();
+        let (local, legend, what) = match tab.editor.selection_range() {
+            Some(((start_row, start_col), (end_row, end_col))) => {
+                let selected = (start_row..=end_row)
+                    .map(|row| {
+                        let from = if row == start_row { start_col } else { 0 };
+                        let to = if row == end_row { end_col } else { usize::MAX };
+                        lines[row]
+                            .chars()
+                            .skip(from)
+                            .take(to.saturating_sub(from))
+                            .collect::<String>()
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                (selected, "- selection, + clipboard", "Selection")
+            }
+            None => (lines.join("\n"), "- buffer, + clipboard", "Buffer"),
+        };
+        let rows = unified_diff(
+            &text_to_lines(local.trim_end_matches('\n')),
+            &text_to_lines(clipboard.trim_end_matches('\n')),
+            3,
+        );
+        if rows.is_empty() {
+            self.set_status(format!("{what} matches the clipboard"));
+            return;
         }
+        self.diff_view = Some(DiffViewState {
+            path: tab.path.clone(),
+            rows,
+            scroll: 0,
+            legend,
+        });
     }
-    pub(crate) fn handle_help_key(&mut self, key: KeyEvent) -> io::Result<()> {
-        let is_help_key = self.keybinds.lookup(&key, KeyScope::Global) == Some(KeyAction::Help);
-        match (key.modifiers, key.code) {
-            (_, KeyCode::Esc) => {
-                self.help_open = false;
-            }
-            _ if is_help_key => {
-                self.help_open = false;
-            }
-            _ => {}
+
+    /// Show what recovering the autosave would change in the buffer.
+    pub(crate) fn open_recovery_diff(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let Some(recovered) = tab.recovery_text.as_deref() else {
+            return;
+        };
+        let rows = unified_diff(tab.editor.lines(), &text_to_lines(recovered), 3);
+        if rows.is_empty() {
+            self.set_status("Autosave matches the buffer");
+            return;
         }
-        Ok(())
+        self.diff_view = Some(DiffViewState {
+            path: tab.path.clone(),
+            rows,
+            scroll: 0,
+            legend: "- buffer, + autosave",
+        });
     }
 
-    pub(crate) fn handle_editor_context_menu_key(&mut self, key: KeyEvent) -> io::Result<()> {
-        match (key.modifiers, key.code) {
-            (_, KeyCode::Esc) => {
-                self.editor_context_menu_open = false;
+    pub(crate) fn handle_diff_view_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        const PAGE_ROWS: usize = 20;
+        let Some(diff) = self.diff_view.as_mut() else {
+            return Ok(());
+        };
+        let last_row = diff.rows.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.diff_view = None;
+                return Ok(());
             }
-            (_, KeyCode::Down) | (_, KeyCode::Char('j')) => {
-                if self.editor_context_menu_index < editor_context_actions().len().saturating_sub(1)
+            KeyCode::Down | KeyCode::Char('j') => diff.scroll += 1,
+            KeyCode::Up | KeyCode::Char('k') => diff.scroll = diff.scroll.saturating_sub(1),
+            KeyCode::PageDown => diff.scroll += PAGE_ROWS,
+            KeyCode::PageUp => diff.scroll = diff.scroll.saturating_sub(PAGE_ROWS),
+            KeyCode::Home => diff.scroll = 0,
+            KeyCode::End => diff.scroll = last_row,
+            // Jump to the next / previous hunk header.
+            KeyCode::Char('n') => {
+                if let Some(next) = diff.rows[diff.scroll + 1..]
+                    .iter()
+                    .position(|(op, _)| op.is_none())
                 {
-                    self.editor_context_menu_index += 1;
+                    diff.scroll += next + 1;
                 }
             }
-            (_, KeyCode::Up) | (_, KeyCode::Char('k')) => {
-                if self.editor_context_menu_index > 0 {
-                    self.editor_context_menu_index -= 1;
+            KeyCode::Char('N') | KeyCode::Char('p') => {
+                if let Some(prev) = diff.rows[..diff.scroll]
+                    .iter()
+                    .rposition(|(op, _)| op.is_none())
+                {
+                    diff.scroll = prev;
                 }
             }
-            (_, KeyCode::Enter) => {
-                let action = editor_context_actions()[self.editor_context_menu_index];
-                self.apply_editor_context_action(action);
-            }
             _ => {}
         }
+        diff.scroll = diff.scroll.min(last_row);
         Ok(())
     }
 
-    pub(crate) fn apply_editor_context_action(&mut self, action: EditorContextAction) {
-        self.editor_context_menu_open = false;
-        self.focus = Focus::Editor;
-        match action {
-            EditorContextAction::Copy => self.copy_selection_to_clipboard(),
-            EditorContextAction::Cut => self.cut_selection_to_clipboard(),
-            EditorContextAction::Paste => self.paste_from_clipboard(),
-            EditorContextAction::SelectAll => {
-                if let Some(tab) = self.active_tab_mut() {
-                    tab.editor.select_all();
-                }
-                self.set_status("Selected all");
-            }
-            EditorContextAction::Cancel => {}
-        }
-    }
-
-    pub(crate) fn sync_editor_scroll_guess(&mut self) {
+    /// Start merging the conflicting disk text into the buffer hunk by hunk.
+    /// With the text the buffer was loaded from as the common base, hunks
+    /// the buffer never touched are preselected from disk.
+    pub(crate) fn open_conflict_merge(&mut self) {
         let Some(tab) = self.active_tab() else {
             return;
         };
-        let (cursor_row, cursor_col) = tab.editor.cursor();
-        let inner_height = self.editor_rect.height.saturating_sub(2) as usize;
-        if inner_height == 0 {
-            if let Some(tab) = self.active_tab_mut() {
-                tab.editor_scroll_row = 0;
-            }
+        let Some(disk) = tab.conflict_disk_text.as_deref() else {
             return;
-        }
-        if self
-            .active_tab()
-            .is_some_and(|t| t.visible_rows_map.is_empty())
-        {
-            self.rebuild_visible_rows();
-        }
-        let cursor_visible = self.visible_index_of_source_position(cursor_row, cursor_col);
-        let Some(tab) = self.active_tab_mut() else {
+        };
+        let buffer = tab.editor.lines().to_vec();
+        let disk = text_to_lines(disk);
+        let hunks = diff_hunks(&diff_lines(&buffer, &disk), 0);
+        if hunks.is_empty() {
+            self.set_status("Buffer matches the file on disk");
             return;
+        }
+        let take_disk: Vec<bool> = match tab.open_disk_snapshot.as_deref() {
+            Some(base) => {
+                let edited = changed_positions(&text_to_lines(base), &buffer);
+                hunks
+                    .iter()
+                    .map(|h| !edited[h.old_start..=h.old_start + h.old_len].contains(&true))
+                    .collect()
+            }
+            None => vec![false; hunks.len()],
         };
-        if cursor_visible < tab.editor_scroll_row {
-            tab.editor_scroll_row = cursor_visible;
-        } else if cursor_visible >= tab.editor_scroll_row + inner_height {
-            tab.editor_scroll_row = cursor_visible.saturating_sub(inner_height.saturating_sub(1));
+        let preselected = take_disk.iter().filter(|&&d| d).count();
+        if preselected > 0 {
+            self.set_status(format!(
+                "{preselected} hunk(s) changed only on disk are taken from disk"
+            ));
         }
-        self.sync_editor_scroll_col();
+        self.merge_view = Some(MergeViewState {
+            take_disk,
+            buffer,
+            disk,
+            hunks,
+            selected: 0,
+            scroll: 0,
+        });
     }
 
-    pub(crate) fn sync_editor_scroll_col(&mut self) {
-        if self.word_wrap {
-            return;
-        }
-        let Some(tab) = self.active_tab() else {
-            return;
+    pub(crate) fn handle_merge_view_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(merge) = self.merge_view.as_mut() else {
+            return Ok(());
         };
-        let (cursor_row, cursor_col) = tab.editor.cursor();
-        let content_width = self
-            .editor_rect
-            .width
-            .saturating_sub(2)
-            .saturating_sub(Self::EDITOR_GUTTER_WIDTH) as usize;
-        if content_width == 0 {
-            return;
-        }
-        // Compute cursor's display-width offset from start of line
-        let line = tab
-            .editor
-            .lines()
-            .get(cursor_row)
-            .map(|l| l.replace('\t', "    "))
-            .unwrap_or_default();
-        let chars: Vec<char> = line.chars().collect();
-        let mut cursor_display_col = 0usize;
-        for i in 0..cursor_col.min(chars.len()) {
-            cursor_display_col +=
-                unicode_width::UnicodeWidthChar::width(chars[i]).unwrap_or(0);
-        }
-        let scroll_col = tab.editor_scroll_col;
-        if cursor_display_col < scroll_col {
-            if let Some(tab) = self.active_tab_mut() {
-                tab.editor_scroll_col = cursor_display_col;
+        let last = merge.hunks.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc => {
+                self.merge_view = None;
+                return Ok(());
             }
-        } else if cursor_display_col >= scroll_col + content_width {
-            if let Some(tab) = self.active_tab_mut() {
-                tab.editor_scroll_col = cursor_display_col.saturating_sub(content_width.saturating_sub(1));
+            KeyCode::Enter => {
+                self.apply_conflict_merge();
+                return Ok(());
+            }
+            KeyCode::Down | KeyCode::Char('j' | 'n') => {
+                merge.selected = (merge.selected + 1).min(last);
+            }
+            KeyCode::Up | KeyCode::Char('k' | 'N' | 'p') => {
+                merge.selected = merge.selected.saturating_sub(1);
             }
+            KeyCode::Home => merge.selected = 0,
+            KeyCode::End => merge.selected = last,
+            KeyCode::Char('b') => merge.take_disk[merge.selected] = false,
+            KeyCode::Char('d') => merge.take_disk[merge.selected] = true,
+            KeyCode::Char(' ') => {
+                merge.take_disk[merge.selected] = !merge.take_disk[merge.selected];
+            }
+            KeyCode::Char('B') => merge.take_disk.fill(false),
+            KeyCode::Char('D') => merge.take_disk.fill(true),
+            _ => {}
         }
+        Ok(())
     }
 
-    /// After a scroll event, ensure the cursor stays within the visible
-    /// viewport. This prevents `sync_editor_scroll_guess` from snapping
-    /// the viewport back to the old cursor position on the next action.
-    pub(crate) fn clamp_cursor_to_viewport(&mut self) {
-        let inner_height = self.editor_rect.height.saturating_sub(2) as usize;
-        if inner_height == 0 {
-            return;
-        }
-        let Some(tab) = self.active_tab() else {
+    /// Replace the buffer with the merge result and settle the conflict; the
+    /// disk text becomes the new baseline, so the tab stays dirty unless every
+    /// hunk came from disk.
+    fn apply_conflict_merge(&mut self) {
+        let Some(merge) = self.merge_view.take() else {
             return;
         };
-        let (cursor_row, cursor_col) = tab.editor.cursor();
-        let cursor_vis = self.visible_index_of_source_row(cursor_row);
-        let Some(tab) = self.active_tab() else {
-            return;
-        };
-        let scroll = tab.editor_scroll_row;
-        let viewport_end = scroll + inner_height;
+        let merged = merge_hunks(&merge.buffer, &merge.disk, &merge.hunks, &merge.take_disk);
+        let from_disk = merge.take_disk.iter().filter(|&&d| d).count();
+        let matches_disk = merged == merge.disk;
+        let cursor = self.tabs[self.active_tab].editor.cursor();
+        self.apply_edit_transaction(&merged, cursor);
+        if let Some(tab) = self.active_tab_mut() {
+            tab.dirty = !matches_disk;
+            tab.open_disk_snapshot = tab.conflict_disk_text.take();
+            tab.conflict_prompt_open = false;
+        }
+        if matches_disk {
+            self.clear_autosave_for_open_file();
+        }
+        self.set_status(format!(
+            "Merged: {from_disk} hunk(s) from disk, {} kept from buffer",
+            merge.hunks.len() - from_disk
+        ));
+    }
 
-        // Cursor is already in the viewport — nothing to do.
-        if cursor_vis >= scroll && cursor_vis < viewport_end {
-            return;
+    fn open_file_checked(
+        &mut self,
+        path: PathBuf,
+        as_preview: bool,
+        force_text: bool,
+    ) -> io::Result<()> {
+        // If file is already open in a tab, just switch to it
+        if let Some(idx) = self.tabs.iter().position(|t| t.path == path) {
+            self.switch_to_tab(idx);
+            if !as_preview {
+                self.tabs[idx].is_preview = false;
+                self.record_recent_file(&path);
+            }
+            self.set_status(format!(
+                "Switched to {}",
+                relative_path(&self.root, &path).display()
+            ));
+            return Ok(());
         }
 
-        // Pick the closest viewport edge.
-        let target_vis = if cursor_vis < scroll {
-            scroll
+        if self.file_io_is_slow(&path) {
+            self.start_background_open(path, as_preview, force_text);
+            return Ok(());
+        }
+        let read = read_for_open(
+            &path,
+            large_file_threshold(&self.filetype_config),
+            Self::LARGE_FILE_CHUNK_BYTES,
+        )?;
+        self.open_file_read(path, as_preview, force_text, read)
+    }
+
+    /// Open `path` in a tab from what `read_for_open` returned. A read that
+    /// ran in the background fills the loading tab it was started from,
+    /// which need not be the active one by now.
+    pub(crate) fn open_file_read(
+        &mut self,
+        path: PathBuf,
+        as_preview: bool,
+        force_text: bool,
+        read: FileRead,
+    ) -> io::Result<()> {
+        let placeholder = self
+            .tabs
+            .iter()
+            .position(|t| t.path == path && t.is_loading());
+        let FileRead {
+            size,
+            modified,
+            bytes,
+            large,
+        } = read;
+        if !force_text && let Some(reason) = binary_reason(&path, &bytes, &self.filetype_config) {
+            if let Some(idx) = placeholder {
+                self.close_tab_at(idx);
+            }
+            return self.open_binary_preview(path, as_preview, Some(reason));
+        }
+        let text = decode_text(&bytes);
+        let mut ta = TextArea::from(text_to_lines(&text));
+        ta.set_cursor_line_style(Style::default().bg(self.active_theme().bg_alt));
+        ta.set_selection_style(Style::default().bg(self.active_theme().selection));
+        self.apply_indent_settings(&mut ta);
+
+        let mut tab = Tab::new(path.clone(), ta);
+        tab.is_preview = as_preview;
+        tab.crlf = uses_crlf(&text);
+        tab.utf16 = utf16_bom(&bytes);
+        if large {
+            tab.lang = SyntaxLang::Plain;
+            tab.large_file = Some(LargeFile {
+                loaded_bytes: bytes.len() as u64,
+                total_bytes: size,
+                modified,
+                tail: tail_fingerprint(&bytes),
+            });
+            tab.recompute_folds();
         } else {
-            viewport_end.saturating_sub(1)
+            if let Some(&lang) = self.language_overrides.get(&path) {
+                tab.lang = lang;
+                tab.recompute_folds();
+            }
+            tab.open_disk_snapshot = Some(text);
+            tab.git_line_status =
+                compute_git_line_status(&self.root, &path, tab.editor.lines().len());
+            tab.undo_snapshots = load_undo_history(&path, &tab.editor.lines().join("\n"));
+        }
+
+        // The rest runs on the new tab as the active one; after a
+        // background read the tab the user is on is restored at the end.
+        let previous = match placeholder {
+            Some(idx) => {
+                tab.is_preview = self.tabs[idx].is_preview;
+                tab.pinned = self.tabs[idx].pinned;
+                self.tabs[idx] = tab;
+                Some(std::mem::replace(&mut self.active_tab, idx))
+            }
+            None => {
+                if !as_preview {
+                    self.record_jump();
+                    self.record_recent_file(&path);
+                }
+                self.insert_opened_tab(tab, as_preview);
+                self.focus = Focus::Editor;
+                None
+            }
         };
+        let shown = previous.is_none_or(|prev| prev == self.active_tab);
+        self.completion.reset();
+        self.load_tab_bookmarks();
+        self.restore_closed_tab_position();
+        if large {
+            self.set_status(format!(
+                "Opened {} in large file mode ({} MB): no highlighting, folding or LSP",
+                relative_path(&self.root, &path).display(),
+                size / (1024 * 1024)
+            ));
+        } else {
+            self.ensure_lsp_for_path(&path);
+            self.apply_cargo_diagnostics();
+            self.check_recovery_for_open_file();
+            self.set_status(format!(
+                "Opened {}",
+                relative_path(&self.root, &path).display()
+            ));
+        }
+        if let Some(prev) = previous {
+            self.active_tab = prev;
+        }
+        let recovering = self.active_tab().is_some_and(|t| t.recovery_prompt_open);
+        if !large
+            && !as_preview
+            && shown
+            && !recovering
+            && self
+                .active_tab()
+                .is_some_and(|t| looks_like_json_log(t.editor.lines()))
+        {
+            self.open_log_view(path)?;
+        }
+        Ok(())
+    }
 
-        let target_row = tab
-            .visible_rows_map
-            .get(target_vis)
-            .copied()
-            .unwrap_or(cursor_row);
+    /// Add a newly opened tab and make it active. A preview replaces the
+    /// unpinned preview tab, if there is one, in its position.
+    pub(crate) fn insert_opened_tab(&mut self, tab: Tab, as_preview: bool) {
+        if as_preview && let Some(idx) = self.tabs.iter().position(|t| t.is_preview && !t.pinned) {
+            self.close_tab_at(idx);
+            self.tabs.insert(idx, tab);
+            self.active_tab = idx;
+        } else {
+            self.tabs.push(tab);
+            self.active_tab = self.tabs.len() - 1;
+        }
+    }
 
-        if let Some(tab) = self.active_tab_mut() {
-            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                to_u16_saturating(target_row),
-                to_u16_saturating(cursor_col),
-            ));
+    /// Read more of the active large file while the cursor or the view is
+    /// within a page of the end of what is loaded.
+    pub(crate) fn poll_large_file(&mut self) -> io::Result<()> {
+        let page = self.editor_rect.height.max(1) as usize;
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_millis(Self::LARGE_FILE_LOAD_BUDGET_MS) {
+            let Some(tab) = self.active_tab() else {
+                return Ok(());
+            };
+            let Some(large) = tab.large_file.filter(|f| !f.fully_loaded()) else {
+                return Ok(());
+            };
+            let needed = tab.editor.cursor().0.max(tab.editor_scroll_row + page) + page;
+            if needed < tab.editor.lines().len() {
+                return Ok(());
+            }
+            let path = tab.path.clone();
+            if self.refresh_large_file()? {
+                continue;
+            }
+            let chunk = read_line_chunk(&path, large.loaded_bytes, Self::LARGE_FILE_CHUNK_BYTES)?;
+            let loaded = large.loaded_bytes + chunk.len() as u64;
+            let tail = file_tail_fingerprint(&path, loaded)?;
+            let tab = &mut self.tabs[self.active_tab];
+            if let Some(large) = tab.large_file.as_mut() {
+                large.loaded_bytes = loaded;
+                large.tail = tail;
+                // The file shrank since it was last checked.
+                if chunk.is_empty() {
+                    large.total_bytes = large.loaded_bytes;
+                }
+            }
+            tab.append_text(&text_to_lines(&decode_text(&chunk)).join("\n"));
+            if tab.follow_tail {
+                tab.move_to_end();
+            }
+            self.rebuild_visible_rows();
+            if self.tabs[self.active_tab].follow_tail {
+                self.sync_editor_scroll_guess();
+            }
         }
+        Ok(())
     }
 
-    fn page_move(&mut self, down: bool) {
+    /// Check the active large file tab against its file. Bytes added at
+    /// the end only raise the size still to load; a file that was
+    /// truncated or rewritten is read again from the start, unless the
+    /// buffer has edits, which get a warning instead. True when the buffer
+    /// was reloaded.
+    pub(crate) fn refresh_large_file(&mut self) -> io::Result<bool> {
         let Some(tab) = self.active_tab() else {
-            return;
+            return Ok(false);
         };
-        let inner_height = self.editor_rect.height.saturating_sub(2) as usize;
-        if inner_height == 0 {
-            return;
+        let Some(large) = tab.large_file else {
+            return Ok(false);
+        };
+        let path = tab.path.clone();
+        let meta = fs::metadata(&path)?;
+        let (len, modified) = (meta.len(), meta.modified().ok());
+        if len == large.total_bytes && modified == large.modified {
+            return Ok(false);
         }
-        let (cursor_row, cursor_col) = tab.editor.cursor();
-        let visible_rows = &tab.visible_rows_map;
-        if visible_rows.is_empty() {
-            return;
+        // Only an append grows the file and leaves the loaded bytes alone.
+        let appended = len > large.total_bytes
+            && file_tail_fingerprint(&path, large.loaded_bytes)? == large.tail;
+        if appended {
+            if let Some(large) = self.tabs[self.active_tab].large_file.as_mut() {
+                large.total_bytes = len;
+                large.modified = modified;
+            }
+            return Ok(false);
         }
-        let cursor_vis = self.visible_index_of_source_position(cursor_row, cursor_col);
-        let target_vis = if down {
-            (cursor_vis + inner_height).min(visible_rows.len().saturating_sub(1))
-        } else {
-            cursor_vis.saturating_sub(inner_height)
-        };
-        let target_row = visible_rows[target_vis];
-        let target_start_col = tab.visible_row_starts.get(target_vis).copied().unwrap_or(0);
-        let target_end_col = tab
-            .visible_row_ends
-            .get(target_vis)
-            .copied()
-            .unwrap_or(target_start_col);
-        let target_lines = self.active_tab().map_or(0, |t| {
-            t.editor
-                .lines()
-                .get(target_row)
-                .map_or(0, |l| l.chars().count())
+        let name = relative_path(&self.root, &path).display().to_string();
+        if self.is_dirty() {
+            if let Some(large) = self.tabs[self.active_tab].large_file.as_mut() {
+                large.total_bytes = len;
+                large.modified = modified;
+            }
+            self.set_warning(format!("{name} changed on disk (unsaved buffer preserved)"));
+            return Ok(false);
+        }
+        let bytes = read_line_chunk(&path, 0, Self::LARGE_FILE_CHUNK_BYTES)?;
+        self.replace_editor_text(text_to_lines(&decode_text(&bytes)), (0, 0));
+        let tab = &mut self.tabs[self.active_tab];
+        tab.large_file = Some(LargeFile {
+            loaded_bytes: bytes.len() as u64,
+            total_bytes: len,
+            modified,
+            tail: tail_fingerprint(&bytes),
         });
-        let col = cursor_col
-            .min(target_end_col)
-            .max(target_start_col)
-            .min(target_lines);
+        if tab.follow_tail {
+            tab.move_to_end();
+        }
+        self.set_status(format!("Reloaded {name} from disk"));
+        Ok(true)
+    }
+
+    /// Start or stop following the active file: while on, the view stays
+    /// at the end and text appended by other programs is added as the
+    /// watcher reports it, without the conflict prompt.
+    pub(crate) fn toggle_follow_file(&mut self) -> io::Result<()> {
+        let Some(tab) = self.active_tab_mut() else {
+            self.set_status("No file open");
+            return Ok(());
+        };
+        let name = tab
+            .path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if tab.follow_tail {
+            tab.follow_tail = false;
+            self.set_status(format!("Stopped following {name}"));
+            return Ok(());
+        }
+        if tab.dirty {
+            self.set_warning(format!("Save or revert {name} before following it"));
+            return Ok(());
+        }
+        tab.follow_tail = true;
+        tab.is_preview = false;
+        self.refresh_followed_file()?;
         if let Some(tab) = self.active_tab_mut() {
-            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                to_u16_saturating(target_row),
-                to_u16_saturating(col),
-            ));
+            tab.move_to_end();
         }
         self.sync_editor_scroll_guess();
+        self.set_status(format!(
+            "Following {name}: new lines are appended, edits are off until \"Follow File\" is run again"
+        ));
+        Ok(())
     }
 
-    pub(crate) fn move_cursor_visual(&mut self, down: bool) {
-        let Some(tab) = self.active_tab() else {
-            return;
+    /// Catch a followed tab up with its file. Appended text is added at the
+    /// end; a file rewritten in any other way (truncated, rotated) replaces
+    /// the buffer. Large files are checked with `refresh_large_file` and
+    /// load the rest as the pinned view reaches it.
+    pub(crate) fn refresh_followed_file(&mut self) -> io::Result<()> {
+        let Some(tab) = self.active_tab().filter(|t| t.follow_tail) else {
+            return Ok(());
         };
-        if tab.visible_rows_map.is_empty() {
-            return;
+        let path = tab.path.clone();
+        if tab.large_file.is_some() {
+            self.refresh_large_file()?;
+            return Ok(());
         }
-        let (cursor_row, cursor_col) = tab.editor.cursor();
-        let cursor_vis = self.visible_index_of_source_position(cursor_row, cursor_col);
-        let target_vis = if down {
-            (cursor_vis + 1).min(tab.visible_rows_map.len().saturating_sub(1))
+        let disk_text = decode_text(&fs::read(&path)?);
+        let lines = text_to_lines(&disk_text);
+        let tab = &mut self.tabs[self.active_tab];
+        let old = tab.editor.lines();
+        if lines == old {
+            return Ok(());
+        }
+        let last = old.len() - 1;
+        let appended = lines.len() >= old.len()
+            && lines[..last] == old[..last]
+            && lines[last].starts_with(old[last].as_str());
+        if appended {
+            let mut text = lines[last][old[last].len()..].to_string();
+            for line in &lines[last + 1..] {
+                text.push('\n');
+                text.push_str(line);
+            }
+            tab.append_text(&text);
+            tab.move_to_end();
+            self.recompute_folds();
         } else {
-            cursor_vis.saturating_sub(1)
-        };
-        let target_row = tab
-            .visible_rows_map
-            .get(target_vis)
-            .copied()
-            .unwrap_or(cursor_row);
-        let target_start = tab.visible_row_starts.get(target_vis).copied().unwrap_or(0);
-        let target_end = tab
-            .visible_row_ends
-            .get(target_vis)
-            .copied()
-            .unwrap_or(target_start);
-        let line_len = tab
-            .editor
-            .lines()
-            .get(target_row)
-            .map_or(0, |l| l.chars().count());
-        let target_col = cursor_col.max(target_start).min(target_end).min(line_len);
+            self.replace_editor_text(lines, (0, 0));
+        }
         if let Some(tab) = self.active_tab_mut() {
-            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                to_u16_saturating(target_row),
-                to_u16_saturating(target_col),
-            ));
+            tab.move_to_end();
+            tab.dirty = false;
+            tab.crlf = uses_crlf(&disk_text);
+            tab.open_disk_snapshot = Some(disk_text);
         }
         self.sync_editor_scroll_guess();
+        self.notify_lsp_did_change();
+        Ok(())
     }
 
-    pub(crate) fn page_down(&mut self) {
-        self.page_move(true);
+    pub(crate) fn save_file(&mut self) -> io::Result<()> {
+        if self.refuse_read_only_edit() {
+            return Ok(());
+        }
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return Ok(());
+        };
+        let path = tab.path.clone();
+        let content = tab.disk_text();
+        let utf16 = tab.utf16;
+        if self.file_io_is_slow(&path) {
+            self.start_background_save(path, content);
+            return Ok(());
+        }
+        fs::write(&path, encode_text(&content, utf16))?;
+        self.finish_save(path, content);
+        Ok(())
     }
 
-    pub(crate) fn page_up(&mut self) {
-        self.page_move(false);
+    /// Settle a save of `content` to `path`: its tab, if still open, is
+    /// clean unless edited since, and git and cargo check catch up.
+    pub(crate) fn finish_save(&mut self, path: PathBuf, content: String) {
+        if let Some(idx) = self.tabs.iter().position(|t| t.path == path) {
+            let previous = std::mem::replace(&mut self.active_tab, idx);
+            let tab = &mut self.tabs[idx];
+            tab.dirty = tab.disk_text() != content;
+            tab.open_disk_snapshot = Some(content);
+            tab.conflict_prompt_open = false;
+            tab.conflict_disk_text = None;
+            if !self.tabs[idx].dirty {
+                self.clear_autosave_for_open_file();
+            }
+            self.store_tab_bookmarks();
+            self.active_tab = previous;
+        }
+        // Trigger an immediate async git refresh so the gutter updates promptly
+        self.fs_refresh_pending = true;
+        self.fs_full_refresh_pending = true;
+        self.last_fs_refresh = Instant::now()
+            .checked_sub(Duration::from_millis(Self::FS_REFRESH_DEBOUNCE_MS + 1))
+            .unwrap_or_else(Instant::now);
+        self.set_status(format!(
+            "Saved {}",
+            relative_path(&self.root, &path).display()
+        ));
+        self.schedule_cargo_check(&path);
     }
 
-    pub(crate) fn editor_pos_from_mouse(&self, x: u16, y: u16) -> Option<(usize, usize)> {
-        if !inside(x, y, self.editor_rect) {
-            return None;
+    /// Switch the active file between LF and CRLF line endings; the change
+    /// is written on the next save.
+    pub(crate) fn toggle_line_endings(&mut self) {
+        if self.refuse_read_only_edit() {
+            return;
         }
-        let tab = self.active_tab()?;
-        let inner_x = x.saturating_sub(self.editor_rect.x.saturating_add(1)) as usize;
-        let inner_y = y.saturating_sub(self.editor_rect.y.saturating_add(1)) as usize;
-        let lines = tab.editor.lines();
-        if lines.is_empty() {
-            return Some((0, 0));
+        let Some(tab) = self.active_tab_mut() else {
+            self.set_status("No file open");
+            return;
+        };
+        tab.crlf = !tab.crlf;
+        tab.dirty = true;
+        let eol = if tab.crlf { "CRLF" } else { "LF" };
+        self.set_status(format!("Line endings set to {eol}; save to write them"));
+    }
+
+    pub(crate) fn close_file(&mut self) {
+        if self.tabs.is_empty() {
+            return;
         }
-        let visible_idx = tab.editor_scroll_row + inner_y;
-        let row = tab
-            .visible_rows_map
-            .get(visible_idx)
-            .copied()
-            .unwrap_or_else(|| *tab.visible_rows_map.last().unwrap_or(&0));
-        let seg_start = tab
-            .visible_row_starts
-            .get(visible_idx)
-            .copied()
-            .unwrap_or(0);
-        let seg_end = tab
-            .visible_row_ends
-            .get(visible_idx)
-            .copied()
-            .unwrap_or(seg_start);
-        let text_x = inner_x.saturating_sub(Self::EDITOR_GUTTER_WIDTH as usize);
-        let max_col = lines[row].chars().count();
-        // text_x is in screen columns; map to char index within the segment
-        // by walking chars and accumulating display width.
-        let display_line = lines[row].replace('\t', "    ");
-        let chars: Vec<char> = display_line.chars().collect();
-        // When not wrapping, offset text_x by editor_scroll_col so clicks
-        // land on the correct character in the horizontally-scrolled view.
-        let effective_text_x = if !self.word_wrap {
-            text_x + tab.editor_scroll_col
+        self.close_tab_at(self.active_tab);
+    }
+
+    pub(crate) fn close_all_tabs(&mut self) {
+        self.close_tabs_where(|_| true);
+    }
+
+    pub(crate) fn close_other_tabs(&mut self) {
+        let active = self.active_tab;
+        let pinned: Vec<bool> = self.tabs.iter().map(|t| t.pinned).collect();
+        self.close_tabs_where(|idx| idx != active && !pinned[idx]);
+    }
+
+    pub(crate) fn close_tabs_to_right(&mut self) {
+        let active = self.active_tab;
+        let pinned: Vec<bool> = self.tabs.iter().map(|t| t.pinned).collect();
+        self.close_tabs_where(|idx| idx > active && !pinned[idx]);
+    }
+
+    /// Close the tabs `close` picks by index. Tabs with unsaved changes stay
+    /// open so nothing is lost without going through the close prompt.
+    fn close_tabs_where(&mut self, close: impl Fn(usize) -> bool) {
+        let mut closed = 0;
+        let mut kept = 0;
+        for idx in (0..self.tabs.len()).rev().filter(|&idx| close(idx)) {
+            if self.tabs[idx].dirty {
+                kept += 1;
+            } else {
+                self.close_tab_at(idx);
+                closed += 1;
+            }
+        }
+        if kept > 0 {
+            self.set_warning(format!(
+                "Closed {closed} tab(s), kept {kept} with unsaved changes"
+            ));
+        } else if closed > 0 {
+            self.set_status(format!("Closed {closed} tab(s)"));
         } else {
-            text_x
+            self.set_status("No tabs to close");
+        }
+    }
+
+    /// Swap the active tab with its neighbour on the right, or the left.
+    pub(crate) fn move_active_tab(&mut self, right: bool) {
+        let from = self.active_tab;
+        let to = if right {
+            from + 1
+        } else {
+            from.wrapping_sub(1)
+        };
+        if to >= self.tabs.len() {
+            return;
+        }
+        self.move_tab(from, to);
+        if self.active_tab != from {
+            self.set_status(format!("Moved tab to position {}", self.active_tab + 1));
+        }
+    }
+
+    /// Move the tab at `from` to `to`, shifting the ones in between and
+    /// keeping the same tab active. Pinned tabs stay left of the rest.
+    pub(crate) fn move_tab(&mut self, from: usize, to: usize) {
+        if from >= self.tabs.len() || to >= self.tabs.len() {
+            return;
+        }
+        let pinned = self.tabs.iter().filter(|t| t.pinned).count();
+        let to = if self.tabs[from].pinned {
+            to.min(pinned.saturating_sub(1))
+        } else {
+            to.max(pinned)
+        };
+        if from == to {
+            return;
+        }
+        let tab = self.tabs.remove(from);
+        self.tabs.insert(to, tab);
+        let active = self.active_tab;
+        self.active_tab = if active == from {
+            to
+        } else if from < active && active <= to {
+            active - 1
+        } else if to <= active && active < from {
+            active + 1
+        } else {
+            active
+        };
+    }
+
+    /// Pin or unpin the active tab, moving it to the end of the pinned tabs
+    /// or the start of the unpinned ones. A pinned tab is never a preview.
+    pub(crate) fn toggle_pin_active_tab(&mut self) {
+        let Some(tab) = self.active_tab_mut() else {
+            self.set_status("No file open");
+            return;
+        };
+        tab.pinned = !tab.pinned;
+        tab.is_preview = false;
+        let pinned = tab.pinned;
+        let pinned_count = self.tabs.iter().filter(|t| t.pinned).count();
+        let to = if pinned {
+            pinned_count - 1
+        } else {
+            pinned_count
+        };
+        self.move_tab(self.active_tab, to);
+        self.set_status(if pinned { "Pinned tab" } else { "Unpinned tab" });
+    }
+
+    /// Index of the tab whose title spans column `x` of the tab bar.
+    pub(crate) fn tab_at_column(&self, x: u16) -> Option<usize> {
+        self.tab_rects
+            .iter()
+            .position(|(name, close)| name.x <= x && x < close.x + close.width)
+    }
+
+    pub(crate) fn close_tab_at(&mut self, idx: usize) {
+        if idx >= self.tabs.len() {
+            return;
+        }
+        // Close LSP document for this tab
+        let tab = &self.tabs[idx];
+        if let (Some(uri), Some(lsp)) = (tab.open_doc_uri.clone(), self.lsp.as_ref()) {
+            let _ = lsp.transport.send_notification(
+                "textDocument/didClose",
+                json!({
+                    "textDocument": { "uri": uri }
+                }),
+            );
+        }
+        // Keep undo history for when the file is reopened; a tab closed with
+        // unsaved changes no longer matches the file, so its history is dropped.
+        if !tab.dirty && tab.large_file.is_none() {
+            let snapshots = tab.undo_states();
+            if !snapshots.is_empty() {
+                let history = UndoHistory {
+                    content_hash: content_hash(&tab.editor.lines().join("\n")),
+                    snapshots,
+                };
+                let _ = save_undo_history(&tab.path, &history);
+            }
+        }
+        // Clear autosave
+        let _ = fs::remove_file(autosave_path_for(&self.tabs[idx].path));
+        let removed = self.tabs.remove(idx);
+        if removed.read_only {
+            let _ = self.rebuild_tree();
+        }
+        if !removed.is_preview {
+            self.closed_tabs.retain(|c| c.path != removed.path);
+            if self.closed_tabs.len() == Self::CLOSED_TABS_LEN {
+                self.closed_tabs.remove(0);
+            }
+            self.closed_tabs.push(ClosedTab {
+                path: removed.path,
+                cursor: removed.editor.cursor(),
+                scroll_row: removed.editor_scroll_row,
+                scroll_col: removed.editor_scroll_col,
+            });
+        }
+        if self.tabs.is_empty() {
+            self.active_tab = 0;
+            self.focus = Focus::Tree;
+            self.completion.reset();
+            self.set_status("Closed file");
+        } else if self.active_tab >= self.tabs.len() {
+            self.active_tab = self.tabs.len() - 1;
+        } else if self.active_tab > idx {
+            self.active_tab -= 1;
+        }
+    }
+
+    /// Put a just-opened tab back where its file was left when it was
+    /// last closed this session.
+    fn restore_closed_tab_position(&mut self) {
+        let Some(path) = self.open_path().cloned() else {
+            return;
+        };
+        let Some(idx) = self.closed_tabs.iter().position(|c| c.path == path) else {
+            return;
+        };
+        let closed = self.closed_tabs.remove(idx);
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        let (row, col) = closed.cursor;
+        let row = row.min(tab.editor.lines().len().saturating_sub(1));
+        tab.jump_to((row, col));
+        tab.editor_scroll_row = closed.scroll_row.min(row);
+        tab.editor_scroll_col = closed.scroll_col;
+        self.sync_editor_scroll_guess();
+    }
+
+    /// Open the most recently closed tab again, at the position it was
+    /// closed at. Files that are open again or gone are skipped.
+    pub(crate) fn reopen_closed_tab(&mut self) -> io::Result<()> {
+        while let Some(closed) = self.closed_tabs.pop() {
+            if self.tabs.iter().any(|t| t.path == closed.path) || !closed.path.is_file() {
+                continue;
+            }
+            let path = closed.path.clone();
+            // Opening takes the position back off the list.
+            self.closed_tabs.push(closed);
+            return self.open_file(path);
+        }
+        self.set_status("No closed tabs to reopen");
+        Ok(())
+    }
+
+    pub(crate) fn handle_help_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let is_help_key = self.keybinds.lookup(&key, KeyScope::Global) == Some(KeyAction::Help);
+        match (key.modifiers, key.code) {
+            (_, KeyCode::Esc) => {
+                self.help_open = false;
+            }
+            _ if is_help_key => {
+                self.help_open = false;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_hex_view_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(hex) = self.hex_view.as_mut() else {
+            return Ok(());
+        };
+        if hex.edit.is_some() {
+            return self.handle_hex_edit_key(key);
+        }
+        let last_row = hex.bytes.len().div_ceil(16).saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.hex_view = None;
+                self.set_status("Closed preview");
+                return Ok(());
+            }
+            KeyCode::Char('t' | 'T') => {
+                let (path, as_preview) = (hex.path.clone(), hex.as_preview);
+                self.hex_view = None;
+                return self.open_file_as_text(path, as_preview);
+            }
+            KeyCode::Char('e' | 'E') => {
+                self.start_hex_edit();
+                return Ok(());
+            }
+            KeyCode::Down | KeyCode::Char('j') => hex.scroll += 1,
+            KeyCode::Up | KeyCode::Char('k') => hex.scroll = hex.scroll.saturating_sub(1),
+            KeyCode::PageDown => hex.scroll += hex.page_rows,
+            KeyCode::PageUp => hex.scroll = hex.scroll.saturating_sub(hex.page_rows),
+            KeyCode::Home => hex.scroll = 0,
+            KeyCode::End => hex.scroll = last_row,
+            _ => {}
+        }
+        hex.scroll = hex.scroll.min(last_row);
+        Ok(())
+    }
+
+    pub(crate) fn open_language_mode_prompt(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return;
+        };
+        let value = tab.lang.name().to_string();
+        let names: Vec<&str> = SyntaxLang::ALL.iter().map(|lang| lang.name()).collect();
+        self.prompt = Some(PromptState {
+            title: format!("Language mode ({}, or auto)", names.join(", ")),
+            cursor: value.len(),
+            value,
+            anchor: Some(0),
+            history: None,
+            mode: PromptMode::LanguageMode,
+        });
+    }
+
+    /// Switch the active file's language and remember it for next time;
+    /// "auto" forgets the override and goes back to detection by path.
+    pub(crate) fn set_language_mode(&mut self, name: &str) {
+        let Some(path) = self.open_path().cloned() else {
+            self.set_status("No file open");
+            return;
+        };
+        let lang = if name.trim().eq_ignore_ascii_case("auto") {
+            self.language_overrides.remove(&path);
+            syntax_lang_for_path(Some(&path))
+        } else if let Some(lang) = SyntaxLang::from_name(name) {
+            self.language_overrides.insert(path.clone(), lang);
+            lang
+        } else {
+            self.set_warning(format!("Unknown language mode: {}", name.trim()));
+            return;
+        };
+        if let Some(tab) = self.active_tab_mut() {
+            tab.lang = lang;
+        }
+        self.recompute_folds();
+        self.persist_state();
+        if self.language_overrides.contains_key(&path) {
+            self.set_status(format!(
+                "Language mode: {} (remembered for this file)",
+                lang.name()
+            ));
+        } else {
+            self.set_status(format!("Language mode: {} (detected)", lang.name()));
+        }
+    }
+
+    pub(crate) fn open_language_overrides(&mut self) {
+        if self.language_overrides.is_empty() {
+            self.set_status("No language mode overrides");
+            return;
+        }
+        self.language_overrides_view = Some(0);
+    }
+
+    pub(crate) fn handle_language_overrides_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(selected) = self.language_overrides_view else {
+            return Ok(());
+        };
+        let last = self.language_overrides.len().saturating_sub(1);
+        let path = self.language_overrides.keys().nth(selected).cloned();
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.language_overrides_view = None,
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.language_overrides_view = Some((selected + 1).min(last));
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.language_overrides_view = Some(selected.saturating_sub(1));
+            }
+            KeyCode::Delete | KeyCode::Char('d') => {
+                let Some(path) = path else {
+                    return Ok(());
+                };
+                self.language_overrides.remove(&path);
+                let lang = syntax_lang_for_path(Some(&path));
+                for tab in self.tabs.iter_mut().filter(|t| t.path == path) {
+                    tab.lang = lang;
+                    tab.recompute_folds();
+                }
+                self.rebuild_all_visible_rows();
+                self.persist_state();
+                self.language_overrides_view = (!self.language_overrides.is_empty())
+                    .then(|| selected.min(last.saturating_sub(1)));
+                self.set_status(format!(
+                    "Forgot language mode for {}",
+                    relative_path(&self.root, &path).display()
+                ));
+            }
+            KeyCode::Enter => {
+                let Some(path) = path else {
+                    return Ok(());
+                };
+                self.language_overrides_view = None;
+                if path.is_file() {
+                    self.open_file(path)?;
+                } else {
+                    self.set_warning(format!("File not found: {}", path.display()));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn handle_editor_context_menu_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        match (key.modifiers, key.code) {
+            (_, KeyCode::Esc) => {
+                self.editor_context_menu_open = false;
+            }
+            (_, KeyCode::Down) | (_, KeyCode::Char('j')) => {
+                if self.editor_context_menu_index < editor_context_actions().len().saturating_sub(1)
+                {
+                    self.editor_context_menu_index += 1;
+                }
+            }
+            (_, KeyCode::Up) | (_, KeyCode::Char('k')) => {
+                if self.editor_context_menu_index > 0 {
+                    self.editor_context_menu_index -= 1;
+                }
+            }
+            (_, KeyCode::Enter) => {
+                let action = editor_context_actions()[self.editor_context_menu_index];
+                self.apply_editor_context_action(action);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn apply_editor_context_action(&mut self, action: EditorContextAction) {
+        if editor_context_needs_lsp(action) && !self.lsp_attached() {
+            self.set_status("No language server for this file");
+            return;
+        }
+        self.editor_context_menu_open = false;
+        self.focus = Focus::Editor;
+        match action {
+            EditorContextAction::Copy => self.copy_selection_to_clipboard(),
+            EditorContextAction::Cut => self.cut_selection_to_clipboard(),
+            EditorContextAction::Paste => self.paste_from_clipboard(),
+            EditorContextAction::SelectAll => {
+                if let Some(tab) = self.active_tab_mut() {
+                    tab.editor.select_all();
+                }
+                self.set_status("Selected all");
+            }
+            EditorContextAction::GoToDefinition => self.request_lsp_definition(),
+            EditorContextAction::FindReferences => self.request_lsp_references(),
+            EditorContextAction::RenameSymbol => self.open_rename_symbol_prompt(),
+            EditorContextAction::FormatDocument => self.request_lsp_format(),
+            EditorContextAction::ToggleComment => self.toggle_comment(),
+            EditorContextAction::TogglePin => self.toggle_pin_active_tab(),
+            EditorContextAction::Cancel => {}
+        }
+    }
+
+    pub(crate) fn sync_editor_scroll_guess(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let (cursor_row, cursor_col) = tab.editor.cursor();
+        let inner_height = self.editor_rect.height.saturating_sub(2) as usize;
+        if inner_height == 0 {
+            if let Some(tab) = self.active_tab_mut() {
+                tab.editor_scroll_row = 0;
+            }
+            return;
+        }
+        if self
+            .active_tab()
+            .is_some_and(|t| t.visible_rows_map.is_empty())
+        {
+            self.rebuild_visible_rows();
+        }
+        let cursor_visible = self.visible_index_of_source_position(cursor_row, cursor_col);
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        if cursor_visible < tab.editor_scroll_row {
+            tab.editor_scroll_row = cursor_visible;
+        } else if cursor_visible >= tab.editor_scroll_row + inner_height {
+            tab.editor_scroll_row = cursor_visible.saturating_sub(inner_height.saturating_sub(1));
+        }
+        self.sync_editor_scroll_col();
+    }
+
+    /// Scroll so the cursor's visual row sits in the middle of the viewport.
+    pub(crate) fn center_cursor_in_view(&mut self) {
+        self.scroll_cursor_row_to(|height| height / 2);
+    }
+
+    /// Scroll so the cursor's visual row is the first one in the viewport.
+    pub(crate) fn scroll_cursor_to_top(&mut self) {
+        self.scroll_cursor_row_to(|_| 0);
+    }
+
+    /// Scroll so the cursor's visual row is the last one in the viewport.
+    pub(crate) fn scroll_cursor_to_bottom(&mut self) {
+        self.scroll_cursor_row_to(|height| height.saturating_sub(1));
+    }
+
+    /// Scroll so that `rows_above(viewport height)` visual rows show above
+    /// the cursor's, as far as the start and end of the file allow.
+    fn scroll_cursor_row_to(&mut self, rows_above: impl FnOnce(usize) -> usize) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let (cursor_row, cursor_col) = tab.editor.cursor();
+        let inner_height = self.editor_rect.height.saturating_sub(2) as usize;
+        if inner_height == 0 {
+            self.sync_editor_scroll_guess();
+            return;
+        }
+        let cursor_visible = self.visible_index_of_source_position(cursor_row, cursor_col);
+        if let Some(tab) = self.active_tab_mut() {
+            let max_scroll = tab.visible_rows_map.len().saturating_sub(inner_height);
+            tab.editor_scroll_row = cursor_visible
+                .saturating_sub(rows_above(inner_height))
+                .min(max_scroll);
+        }
+        self.sync_editor_scroll_col();
+    }
+
+    /// Scroll the view by one visual row without moving the cursor, unless
+    /// it would leave the viewport; then it stays on the edge row.
+    pub(crate) fn scroll_view_line(&mut self, down: bool) {
+        let inner_height = self.editor_rect.height.saturating_sub(2) as usize;
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        if down {
+            let max_scroll = tab
+                .visible_rows_map
+                .len()
+                .saturating_sub(inner_height.max(1));
+            tab.editor_scroll_row = (tab.editor_scroll_row + 1).min(max_scroll);
+        } else {
+            tab.editor_scroll_row = tab.editor_scroll_row.saturating_sub(1);
+        }
+        self.clamp_cursor_to_viewport();
+    }
+
+    pub(crate) fn sync_editor_scroll_col(&mut self) {
+        if self.word_wrap {
+            return;
+        }
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let (cursor_row, cursor_col) = tab.editor.cursor();
+        let content_width = self
+            .editor_rect
+            .width
+            .saturating_sub(2)
+            .saturating_sub(self.gutter().width()) as usize;
+        if content_width == 0 {
+            return;
+        }
+        // Compute cursor's display-width offset from start of line
+        let cursor_display_col = tab.editor.lines().get(cursor_row).map_or(0, |l| {
+            display_width_of_range(l, 0, cursor_col, tab.tab_width())
+        });
+        let scroll_col = tab.editor_scroll_col;
+        if cursor_display_col < scroll_col {
+            if let Some(tab) = self.active_tab_mut() {
+                tab.editor_scroll_col = cursor_display_col;
+            }
+        } else if cursor_display_col >= scroll_col + content_width {
+            if let Some(tab) = self.active_tab_mut() {
+                tab.editor_scroll_col = cursor_display_col.saturating_sub(content_width.saturating_sub(1));
+            }
+        }
+    }
+
+    /// After a scroll event, ensure the cursor stays within the visible
+    /// viewport. This prevents `sync_editor_scroll_guess` from snapping
+    /// the viewport back to the old cursor position on the next action.
+    pub(crate) fn clamp_cursor_to_viewport(&mut self) {
+        let inner_height = self.editor_rect.height.saturating_sub(2) as usize;
+        if inner_height == 0 {
+            return;
+        }
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let (cursor_row, cursor_col) = tab.editor.cursor();
+        let cursor_vis = self.visible_index_of_source_row(cursor_row);
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let scroll = tab.editor_scroll_row;
+        let viewport_end = scroll + inner_height;
+
+        // Cursor is already in the viewport — nothing to do.
+        if cursor_vis >= scroll && cursor_vis < viewport_end {
+            return;
+        }
+
+        // Pick the closest viewport edge.
+        let target_vis = if cursor_vis < scroll {
+            scroll
+        } else {
+            viewport_end.saturating_sub(1)
+        };
+
+        let target_row = tab
+            .visible_rows_map
+            .get(target_vis)
+            .copied()
+            .unwrap_or(cursor_row);
+
+        if let Some(tab) = self.active_tab_mut() {
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(target_row),
+                to_u16_saturating(cursor_col),
+            ));
+        }
+    }
+
+    fn page_move(&mut self, down: bool) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let inner_height = self.editor_rect.height.saturating_sub(2) as usize;
+        if inner_height == 0 {
+            return;
+        }
+        let (cursor_row, cursor_col) = tab.editor.cursor();
+        let visible_rows = &tab.visible_rows_map;
+        if visible_rows.is_empty() {
+            return;
+        }
+        let cursor_vis = self.visible_index_of_source_position(cursor_row, cursor_col);
+        let target_vis = if down {
+            (cursor_vis + inner_height).min(visible_rows.len().saturating_sub(1))
+        } else {
+            cursor_vis.saturating_sub(inner_height)
+        };
+        let target_row = visible_rows[target_vis];
+        let target_start_col = tab.visible_row_starts.get(target_vis).copied().unwrap_or(0);
+        let target_end_col = tab
+            .visible_row_ends
+            .get(target_vis)
+            .copied()
+            .unwrap_or(target_start_col);
+        let target_lines = self.active_tab().map_or(0, |t| {
+            t.editor
+                .lines()
+                .get(target_row)
+                .map_or(0, |l| l.chars().count())
+        });
+        let col = cursor_col
+            .min(target_end_col)
+            .max(target_start_col)
+            .min(target_lines);
+        if let Some(tab) = self.active_tab_mut() {
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(target_row),
+                to_u16_saturating(col),
+            ));
+        }
+        self.sync_editor_scroll_guess();
+    }
+
+    /// Source row, start and end columns of visual row `vis`, and the last
+    /// column the cursor can take on it: a wrapped segment's end column
+    /// belongs to the next segment.
+    fn visual_segment(&self, vis: usize) -> Option<(usize, usize, usize, usize)> {
+        let tab = self.active_tab()?;
+        let row = *tab.visible_rows_map.get(vis)?;
+        let start = tab.visible_row_starts.get(vis).copied().unwrap_or(0);
+        let end = tab.visible_row_ends.get(vis).copied().unwrap_or(start);
+        let continues = tab.visible_rows_map.get(vis + 1) == Some(&row);
+        let last = if continues {
+            end.saturating_sub(1).max(start)
+        } else {
+            end
+        };
+        Some((row, start, end, last))
+    }
+
+    /// Up/Down over wrapped lines: move one visual row, keeping the
+    /// cursor's screen column within the row.
+    pub(crate) fn move_cursor_visual(&mut self, down: bool) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        if tab.visible_rows_map.is_empty() {
+            return;
+        }
+        let (cursor_row, cursor_col) = tab.editor.cursor();
+        let cursor_vis = self.visible_index_of_source_position(cursor_row, cursor_col);
+        let target_vis = if down {
+            (cursor_vis + 1).min(tab.visible_rows_map.len().saturating_sub(1))
+        } else {
+            cursor_vis.saturating_sub(1)
+        };
+        let (Some((_, start, _, _)), Some((target_row, target_start, target_end, last))) = (
+            self.visual_segment(cursor_vis),
+            self.visual_segment(target_vis),
+        ) else {
+            return;
+        };
+        let lines = tab.editor.lines();
+        let tab_width = tab.tab_width();
+        let offset = display_width_of_range(&lines[cursor_row], start, cursor_col, tab_width);
+        let target_col = char_index_at_display_offset(
+            &lines[target_row],
+            target_start,
+            target_end,
+            offset,
+            tab_width,
+        )
+        .min(last);
+        if let Some(tab) = self.active_tab_mut() {
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(target_row),
+                to_u16_saturating(target_col),
+            ));
+        }
+        self.sync_editor_scroll_guess();
+    }
+
+    /// Home/End over wrapped lines: go to the start or end of the visual
+    /// row, or of the whole line when already there.
+    pub(crate) fn move_cursor_visual_edge(&mut self, end: bool) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let (row, col) = tab.editor.cursor();
+        let vis = self.visible_index_of_source_position(row, col);
+        let Some((_, start, _, last)) = self.visual_segment(vis) else {
+            return;
+        };
+        let target = if end { last } else { start };
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        if col == target {
+            let whole_line = if end {
+                ratatui_textarea::CursorMove::End
+            } else {
+                ratatui_textarea::CursorMove::Head
+            };
+            tab.editor.move_cursor(whole_line);
+        } else {
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(row),
+                to_u16_saturating(target),
+            ));
+        }
+        self.sync_editor_scroll_guess();
+    }
+
+    pub(crate) fn page_down(&mut self) {
+        self.page_move(true);
+    }
+
+    pub(crate) fn page_up(&mut self) {
+        self.page_move(false);
+    }
+
+    pub(crate) fn editor_pos_from_mouse(&self, x: u16, y: u16) -> Option<(usize, usize)> {
+        if !inside(x, y, self.editor_rect) {
+            return None;
+        }
+        let tab = self.active_tab()?;
+        let inner_x = x.saturating_sub(self.editor_rect.x.saturating_add(1)) as usize;
+        let inner_y = y.saturating_sub(self.editor_rect.y.saturating_add(1)) as usize;
+        let lines = tab.editor.lines();
+        if lines.is_empty() {
+            return Some((0, 0));
+        }
+        let visible_idx = tab.editor_scroll_row + inner_y;
+        let row = tab
+            .visible_rows_map
+            .get(visible_idx)
+            .copied()
+            .unwrap_or_else(|| *tab.visible_rows_map.last().unwrap_or(&0));
+        let seg_start = tab
+            .visible_row_starts
+            .get(visible_idx)
+            .copied()
+            .unwrap_or(0);
+        let seg_end = tab
+            .visible_row_ends
+            .get(visible_idx)
+            .copied()
+            .unwrap_or(seg_start);
+        let text_x = inner_x.saturating_sub(self.gutter().width() as usize);
+        let max_col = lines[row].chars().count();
+        // When not wrapping, offset text_x by editor_scroll_col so clicks
+        // land on the correct character in the horizontally-scrolled view.
+        let effective_text_x = if !self.word_wrap {
+            text_x + tab.editor_scroll_col
+        } else {
+            text_x
+        };
+        // text_x is in screen columns; map to char index within the segment.
+        let col = char_index_at_display_offset(
+            &lines[row],
+            seg_start,
+            seg_end,
+            effective_text_x,
+            tab.tab_width(),
+        )
+        .min(max_col);
+        Some((row, col))
+    }
+    pub(crate) fn select_line(&mut self, row: usize) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let lines = tab.editor.lines();
+        let total = lines.len();
+        if total == 0 || row >= total {
+            return;
+        }
+        let line_len = lines[row].chars().count();
+        // Start selection from end and move cursor to start, so the cursor
+        // ends up at the beginning of the line rather than the next line.
+        if let Some(tab) = self.active_tab_mut() {
+            if row + 1 < total {
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(row + 1),
+                    0,
+                ));
+            } else {
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(row),
+                    to_u16_saturating(line_len),
+                ));
+            }
+            tab.editor.start_selection();
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(row),
+                0,
+            ));
+        }
+        self.sync_editor_scroll_guess();
+    }
+
+    pub(crate) fn select_line_range(&mut self, from: usize, to: usize) {
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let lines = tab.editor.lines();
+        let total = lines.len();
+        if total == 0 {
+            return;
+        }
+        let start = from.min(to).min(total.saturating_sub(1));
+        let end = from.max(to).min(total.saturating_sub(1));
+        // Select from start of first line to start of line after last (or end of last line)
+        if let Some(tab) = self.active_tab_mut() {
+            if end + 1 < total {
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(end + 1),
+                    0,
+                ));
+            } else {
+                let line_len = tab
+                    .editor
+                    .lines()
+                    .get(end)
+                    .map_or(0, |l| l.chars().count());
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(end),
+                    to_u16_saturating(line_len),
+                ));
+            }
+            tab.editor.start_selection();
+            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                to_u16_saturating(start),
+                0,
+            ));
+        }
+        self.sync_editor_scroll_guess();
+    }
+
+    pub(crate) fn gutter_row_from_mouse(&self, y: u16) -> Option<usize> {
+        let tab = self.active_tab()?;
+        let inner_y = y.saturating_sub(self.editor_rect.y.saturating_add(1)) as usize;
+        let visible_idx = tab.editor_scroll_row + inner_y;
+        tab.visible_rows_map.get(visible_idx).copied()
+    }
+
+    pub(crate) fn extend_mouse_selection(&mut self, x: u16, y: u16) {
+        if let (Some((anchor_row, anchor_col)), Some((row, col))) =
+            (self.editor_drag_anchor, self.editor_pos_from_mouse(x, y))
+        {
+            if let Some(tab) = self.active_tab_mut() {
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(anchor_row),
+                    to_u16_saturating(anchor_col),
+                ));
+                tab.editor.start_selection();
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(row),
+                    to_u16_saturating(col),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::DiffOp;
+    use crate::persistence::SearchHistory;
+    use crate::types::{CommandAction, PendingAction, PromptMode, PromptState, StatusLevel};
+    use ratatui::crossterm::event::KeyModifiers;
+    use ratatui::layout::Rect;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn new_app(root: &std::path::Path) -> App {
+        App::new_for_test(root)
+    }
+
+    #[test]
+    fn click_line_number_selects_line() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "line 0\nline 1\nline 2\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+
+        app.select_line(1);
+
+        let tab = app.active_tab().expect("tab");
+        let sel = tab.editor.selection_range().expect("should have selection");
+        assert_eq!(sel, ((1, 0), (2, 0)));
+        // Cursor should be at the start of the selected line
+        assert_eq!(tab.editor.cursor(), (1, 0));
+    }
+
+    #[test]
+    fn click_line_number_selects_last_line() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "line 0\nline 1\nlast line").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+
+        app.select_line(2);
+
+        let tab = app.active_tab().expect("tab");
+        let sel = tab.editor.selection_range().expect("should have selection");
+        assert_eq!(sel, ((2, 0), (2, 9)));
+    }
+
+    #[test]
+    fn click_line_number_selects_first_line() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "first\nsecond\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+
+        app.select_line(0);
+
+        let tab = app.active_tab().expect("tab");
+        let sel = tab.editor.selection_range().expect("should have selection");
+        assert_eq!(sel, ((0, 0), (1, 0)));
+    }
+
+    #[test]
+    fn cut_line_removes_middle_line() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "aaa\nbbb\nccc\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        // Move cursor to line 1 (bbb)
+        app.tabs[app.active_tab]
+            .editor
+            .move_cursor(ratatui_textarea::CursorMove::Jump(1, 0));
+        app.cut_line();
+        let lines = app.tabs[app.active_tab].editor.lines().to_vec();
+        assert_eq!(lines, vec!["aaa", "ccc", ""]);
+    }
+
+    #[test]
+    fn cut_line_removes_last_line() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "aaa\nbbb\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        // Move cursor to last content line (index 2 is the empty trailing line)
+        app.tabs[app.active_tab]
+            .editor
+            .move_cursor(ratatui_textarea::CursorMove::Jump(2, 0));
+        app.cut_line();
+        let lines = app.tabs[app.active_tab].editor.lines().to_vec();
+        assert_eq!(lines, vec!["aaa", "bbb"]);
+        // Cursor should be clamped, not out of bounds
+        let (row, _) = app.tabs[app.active_tab].editor.cursor();
+        assert!(row < lines.len());
+    }
+
+    #[test]
+    fn click_after_wide_chars_maps_to_char_index() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("cjk.txt");
+        fs::write(&file, "日本語abc\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        app.editor_rect = Rect::new(0, 0, 80, 10);
+        app.rebuild_visible_rows();
+
+        let text_x = 1 + app.gutter().width();
+        // Each CJK char is two columns wide: column 4 is the third char.
+        assert_eq!(app.editor_pos_from_mouse(text_x + 4, 1), Some((0, 2)));
+        // Column 7 lands on "b", after the three wide chars and "a".
+        assert_eq!(app.editor_pos_from_mouse(text_x + 7, 1), Some((0, 4)));
+    }
+
+    #[test]
+    fn hidden_gutter_columns_give_the_text_their_width() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("a.txt");
+        fs::write(&file, "abcdef\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        app.editor_rect = Rect::new(0, 0, 80, 10);
+        assert_eq!(app.gutter().width(), 11);
+
+        app.editor_settings.gutter_line_numbers = false;
+        app.editor_settings.gutter_git = false;
+        assert_eq!(app.gutter().width(), 5);
+        assert_eq!(app.editor_pos_from_mouse(1 + 5 + 2, 1), Some((0, 2)));
+        let tab = app.active_tab().expect("tab");
+        let gutter = app.gutter();
+        let lines =
+            crate::ui::editor_lines(tab, app.active_theme(), false, gutter, app.editor_rect);
+        let text: String = lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(text.chars().position(|c| c == 'a'), Some(5), "{text:?}");
+    }
+
+    #[test]
+    fn recenter_and_line_scroll_count_folded_lines_once() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("a.rs");
+        let body: String = (0..20).map(|i| format!("let x{i} = {i};\n")).collect();
+        let text = format!("fn a() {{\n{}}}\n{body}", "    x;\n".repeat(5));
+        fs::write(&file, text).expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        app.editor_rect = Rect::new(0, 0, 80, 7);
+        // Opening a Rust file asks about project trust; answer it first.
+        app.pending = PendingAction::None;
+        app.toggle_fold_at_row(0);
+        let scroll = |app: &App| app.active_tab().expect("tab").editor_scroll_row;
+
+        // Row 10 is the fifth visual row once rows 1-6 are folded away.
+        app.tabs[0].jump_to((10, 0));
+        app.scroll_cursor_to_top();
+        assert_eq!(scroll(&app), 4);
+        app.center_cursor_in_view();
+        assert_eq!(scroll(&app), 2);
+        app.scroll_cursor_to_bottom();
+        assert_eq!(scroll(&app), 0);
+
+        // Scrolling leaves the cursor alone until it would go off screen.
+        app.handle_key(KeyEvent::new(KeyCode::Down, KeyModifiers::CONTROL))
+            .expect("scroll");
+        assert_eq!(scroll(&app), 1);
+        assert_eq!(app.tabs[0].editor.cursor(), (10, 0));
+        app.tabs[0].jump_to((0, 0));
+        app.handle_key(KeyEvent::new(KeyCode::Down, KeyModifiers::CONTROL))
+            .expect("scroll");
+        assert_eq!(scroll(&app), 2);
+        assert_eq!(app.tabs[0].editor.cursor().0, 8);
+        app.handle_key(KeyEvent::new(KeyCode::Up, KeyModifiers::CONTROL))
+            .expect("scroll");
+        assert_eq!(scroll(&app), 1);
+        assert_eq!(app.tabs[0].editor.cursor().0, 8);
+    }
+
+    #[test]
+    fn paste_goes_to_open_prompt_instead_of_editor() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("a.txt");
+        fs::write(&file, "body\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+        app.prompt = Some(PromptState {
+            title: "Find".to_string(),
+            value: "ab".to_string(),
+            cursor: 1,
+            anchor: None,
+            history: None,
+            mode: PromptMode::FindInFile,
+        });
+
+        app.handle_paste("日本\nignored".to_string());
+
+        let prompt = app.prompt.as_ref().expect("prompt");
+        assert_eq!(prompt.value, "a日本b");
+        assert_eq!(prompt.cursor, 1 + "日本".len());
+        assert_eq!(app.active_tab().expect("tab").editor.lines()[0], "body");
+    }
+
+    #[test]
+    fn prompt_editing_steps_over_multibyte_chars() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        app.prompt = Some(PromptState {
+            title: "Find".to_string(),
+            value: String::new(),
+            cursor: 0,
+            anchor: None,
+            history: None,
+            mode: PromptMode::FindInFile,
+        });
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        for ch in ['한', '글', 'x'] {
+            app.handle_key(press(KeyCode::Char(ch))).expect("type");
+        }
+        app.handle_key(press(KeyCode::Left)).expect("left");
+        app.handle_key(press(KeyCode::Left)).expect("left");
+        app.handle_key(press(KeyCode::Backspace)).expect("backspace");
+
+        let prompt = app.prompt.as_ref().expect("prompt");
+        assert_eq!(prompt.value, "글x");
+        assert_eq!(prompt.cursor, 0);
+    }
+
+    #[test]
+    fn binary_file_opens_a_preview() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("blob");
+        fs::write(&file, b"\x7fELF\0tail\n").expect("write");
+        let mut app = new_app(tmp.path());
+
+        app.open_file(file.clone()).expect("open");
+        assert!(app.tabs.is_empty());
+        let hex = app.hex_view.as_ref().expect("preview");
+        assert_eq!(hex.kind, "ELF executable");
+        assert_eq!(hex.size, 10);
+        assert!(hex.reason.is_some());
+        assert!(hex.image.is_none());
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('t'), KeyModifiers::NONE))
+            .expect("open as text");
+        assert!(app.hex_view.is_none());
+        assert_eq!(app.active_tab().expect("tab").path, file);
+    }
+
+    #[test]
+    fn hex_view_scrolls_and_closes() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("image.png");
+        fs::write(&file, vec![0u8; 64]).expect("write");
+        let mut app = new_app(tmp.path());
+
+        app.open_binary_preview(file, false, None)
+            .expect("hex view");
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        app.handle_key(press(KeyCode::End)).expect("end");
+        assert_eq!(app.hex_view.as_ref().expect("hex view").scroll, 3);
+        app.handle_key(press(KeyCode::Esc)).expect("esc");
+        assert!(app.hex_view.is_none());
+    }
+
+    #[test]
+    fn close_and_conflict_prompts_offer_unsaved_diff() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("a.txt");
+        fs::write(&file, "one\ntwo\nthree\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        app.focus = Focus::Editor;
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        app.handle_key(press(KeyCode::Char('X'))).expect("type");
+        app.handle_key(press(KeyCode::Esc)).expect("close prompt");
+        assert!(matches!(app.pending, PendingAction::ClosePrompt));
+
+        app.handle_key(press(KeyCode::Char('v')))
+            .expect("view diff");
+        let rows: Vec<&str> = app
+            .diff_view
+            .as_ref()
+            .expect("diff view")
+            .rows
+            .iter()
+            .map(|(_, row)| row.as_str())
+            .collect();
+        assert_eq!(
+            rows,
+            vec!["@@ -1,4 +1,4 @@", "-one", "+Xone", " two", " three", " "]
+        );
+        app.handle_key(press(KeyCode::Esc)).expect("close diff");
+        assert!(app.diff_view.is_none());
+        assert!(matches!(app.pending, PendingAction::ClosePrompt));
+        app.handle_key(press(KeyCode::Char('c'))).expect("cancel");
+
+        let tab = app.active_tab_mut().expect("tab");
+        tab.conflict_prompt_open = true;
+        tab.conflict_disk_text = Some("one\ntwo\n".to_string());
+        app.handle_key(press(KeyCode::Char('V')))
+            .expect("view diff");
+        let diff = app.diff_view.as_ref().expect("diff view");
+        assert!(
+            diff.rows
+                .iter()
+                .any(|(op, row)| *op == Some(DiffOp::Insert) && row == "+three")
+        );
+    }
+
+    #[test]
+    fn compare_with_clipboard_diffs_the_selection_or_the_buffer() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("a.txt");
+        fs::write(&file, "fn a() {\n    1\n}\nfn b() {}\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        app.clipboard = None;
+        app.compare_with_clipboard();
+        assert_eq!(app.status, "Clipboard is empty");
+
+        app.remember_clipboard_text("fn a() {\n    2\n}\n");
+        let tab = app.active_tab_mut().expect("tab");
+        tab.jump_to((0, 0));
+        tab.editor.start_selection();
+        tab.jump_to((3, 0));
+        app.compare_with_clipboard();
+        let diff = app.diff_view.take().expect("diff view");
+        assert_eq!(diff.legend, "- selection, + clipboard");
+        let rows: Vec<&str> = diff.rows.iter().map(|(_, row)| row.as_str()).collect();
+        assert_eq!(
+            rows,
+            ["@@ -1,3 +1,3 @@", " fn a() {", "-    1", "+    2", " }"]
+        );
+
+        app.active_tab_mut().expect("tab").editor.cancel_selection();
+        app.remember_clipboard_text("fn a() {\n    1\n}\nfn b() {}\n");
+        app.compare_with_clipboard();
+        assert!(app.diff_view.is_none());
+        assert_eq!(app.status, "Buffer matches the clipboard");
+    }
+
+    #[test]
+    fn recovery_prompt_offers_autosave_diff() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("a.txt");
+        fs::write(&file, "one\ntwo\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        let tab = app.active_tab_mut().expect("tab");
+        tab.recovery_prompt_open = true;
+        tab.recovery_text = Some("one\n2\n".to_string());
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        app.handle_key(press(KeyCode::Char('v')))
+            .expect("view diff");
+        let diff = app.diff_view.as_ref().expect("diff view");
+        assert_eq!(diff.legend, "- buffer, + autosave");
+        let changed: Vec<&str> = diff
+            .rows
+            .iter()
+            .filter(|(op, _)| matches!(op, Some(DiffOp::Delete | DiffOp::Insert)))
+            .map(|(_, row)| row.as_str())
+            .collect();
+        assert_eq!(changed, vec!["-two", "+2"]);
+
+        app.handle_key(press(KeyCode::Esc)).expect("close diff");
+        assert!(app.active_tab().expect("tab").recovery_prompt_open);
+        app.handle_key(press(KeyCode::Char('r'))).expect("recover");
+        assert_eq!(app.tabs[0].editor.lines()[1], "2");
+    }
+
+    #[test]
+    fn conflict_merge_takes_chosen_hunks() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("a.txt");
+        fs::write(&file, "a\nb\nc\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        app.focus = Focus::Editor;
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        app.handle_key(press(KeyCode::Char('X'))).expect("type");
+        let tab = app.active_tab_mut().expect("tab");
+        tab.conflict_prompt_open = true;
+        tab.conflict_disk_text = Some("A\nb\nC\n".to_string());
+
+        app.handle_key(press(KeyCode::Char('m'))).expect("merge");
+        let merge = app.merge_view.as_ref().expect("merge view");
+        assert_eq!(merge.take_disk, vec![false, true]);
+        app.handle_key(press(KeyCode::Char(' '))).expect("toggle");
+        app.handle_key(press(KeyCode::Char('n'))).expect("next");
+        app.handle_key(press(KeyCode::Char('b')))
+            .expect("take buffer");
+        assert_eq!(
+            app.merge_view.as_ref().expect("merge view").take_disk,
+            vec![true, false]
+        );
+        app.handle_key(press(KeyCode::Enter)).expect("apply");
+
+        assert!(app.merge_view.is_none());
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines(), ["A", "b", "c", ""]);
+        assert!(tab.dirty);
+        assert!(!tab.conflict_prompt_open);
+        assert_eq!(tab.open_disk_snapshot.as_deref(), Some("A\nb\nC\n"));
+    }
+
+    #[test]
+    fn incremental_find_highlights_and_restores_on_escape() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("a.txt");
+        fs::write(&file, "foo bar\nbar foo\nfoo\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        app.open_find_prompt();
+        for ch in "fo".chars() {
+            app.handle_key(press(KeyCode::Char(ch))).expect("type");
+        }
+        assert_eq!(app.active_tab().expect("tab").search_matches.len(), 3);
+        assert_eq!(app.search_match_position(), Some((1, 3)));
+        app.handle_key(press(KeyCode::Char('x'))).expect("type");
+        assert!(app.active_tab().expect("tab").search_matches.is_empty());
+
+        app.handle_key(press(KeyCode::Esc)).expect("esc");
+        let tab = app.active_tab().expect("tab");
+        assert!(tab.search_matches.is_empty());
+        assert_eq!(tab.editor.cursor(), (0, 0));
+    }
+
+    #[test]
+    fn confirmed_find_cycles_with_n_until_another_key() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("a.txt");
+        fs::write(&file, "x foo\nfoo\nfoo\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        app.open_find_prompt();
+        app.handle_paste("foo".to_string());
+        app.handle_key(press(KeyCode::Enter)).expect("confirm");
+        assert!(app.find_bar_open);
+        assert_eq!(app.search_match_position(), Some((1, 3)));
+
+        app.handle_key(press(KeyCode::Char('n'))).expect("next");
+        assert_eq!(app.search_match_position(), Some((2, 3)));
+        app.handle_key(KeyEvent::new(KeyCode::Char('N'), KeyModifiers::SHIFT))
+            .expect("prev");
+        assert_eq!(app.search_match_position(), Some((1, 3)));
+
+        app.handle_key(press(KeyCode::Char('z'))).expect("type");
+        assert!(!app.find_bar_open);
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[0], "x zfoo");
+        assert_eq!(tab.search_matches.len(), 3);
+    }
+
+    #[test]
+    fn project_replace_applies_selected_hits() {
+        let tmp = tempdir().expect("tempdir");
+        let a = tmp.path().join("a.txt");
+        let b = tmp.path().join("b.txt");
+        fs::write(&a, "old one\r\nkeep\r\nold two\r\n").expect("write");
+        fs::write(&b, "old\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(a.clone()).expect("open");
+        let hit = |path: &std::path::Path, line, preview: &str| crate::tab::ProjectSearchHit {
+            path: path.to_path_buf(),
+            line,
+            preview: preview.to_string(),
+        };
+        app.search_results.query = "old".to_string();
+        app.search_results.results = vec![
+            hit(&a, 1, "old one"),
+            hit(&a, 3, "old two"),
+            hit(&b, 1, "old"),
+        ];
+        app.search_results.options.literal = true;
+        app.search_results.open = true;
+        app.set_project_replacement("new".to_string());
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        app.handle_key(press(KeyCode::Down)).expect("down");
+        app.handle_key(press(KeyCode::Char(' '))).expect("toggle");
+        app.handle_key(press(KeyCode::Char('a'))).expect("apply");
+
+        assert_eq!(
+            fs::read_to_string(&a).expect("read"),
+            "new one\r\nkeep\r\nold two\r\n"
+        );
+        assert_eq!(fs::read_to_string(&b).expect("read"), "new\n");
+        assert_eq!(app.active_tab().expect("tab").editor.lines()[0], "new one");
+        assert!(!app.search_results.open);
+    }
+
+    #[test]
+    fn project_search_prompt_toggles_options() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        app.open_project_search_prompt();
+        let alt = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT);
+        app.handle_key(alt('w')).expect("word");
+        app.handle_key(alt('r')).expect("regex");
+        assert!(app.project_search_options.whole_word);
+        assert!(app.project_search_options.literal);
+        assert!(!app.project_search_options.case_sensitive);
+        assert_eq!(app.prompt.as_ref().expect("prompt").value, "");
+    }
+
+    #[test]
+    fn crlf_files_keep_their_line_endings_until_converted() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("win.txt");
+        fs::write(&file, "one\r\ntwo\r\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file.clone()).expect("open");
+        assert!(app.tabs[0].crlf);
+        assert_eq!(app.tabs[0].editor.lines(), ["one", "two", ""]);
+        app.tabs[0].editor.insert_str("zero ");
+        app.on_editor_content_changed();
+        app.save_file().expect("save");
+        assert_eq!(
+            fs::read_to_string(&file).expect("read"),
+            "zero one\r\ntwo\r\n"
+        );
+
+        app.run_command_action(CommandAction::ToggleLineEndings)
+            .expect("convert");
+        assert!(app.tabs[0].dirty);
+        app.save_file().expect("save");
+        assert_eq!(fs::read_to_string(&file).expect("read"), "zero one\ntwo\n");
+        assert!(!uses_crlf("a\nb\r\nc\n"));
+    }
+
+    #[test]
+    fn utf16_files_are_saved_back_as_utf16() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("wide.txt");
+        let wide = |text: &str| {
+            let mut bytes = vec![0xFF, 0xFE];
+            bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+            bytes
+        };
+        fs::write(&file, wide("héllo\r\nwörld\r\n")).expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file.clone()).expect("open");
+        assert_eq!(app.tabs[0].utf16, Some(true));
+        app.tabs[0].dirty = true;
+        app.save_file().expect("save");
+        assert_eq!(fs::read(&file).expect("read"), wide("héllo\r\nwörld\r\n"));
+
+        app.tabs[0].editor.insert_str("¡");
+        app.on_editor_content_changed();
+        app.save_file().expect("save");
+        assert_eq!(fs::read(&file).expect("read"), wide("¡héllo\r\nwörld\r\n"));
+    }
+
+    #[test]
+    fn undo_history_survives_close_and_reopen() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("notes.txt");
+        fs::write(&file, "one\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file.clone()).expect("open");
+        app.tabs[0].editor.insert_str("two ");
+        app.on_editor_content_changed();
+        app.save_file().expect("save");
+        app.close_tab_at(0);
+
+        app.open_file(file.clone()).expect("reopen");
+        assert_eq!(app.tabs[0].editor.lines()[0], "two one");
+        // The cursor comes back where it was left, after "two ".
+        assert_eq!(app.tabs[0].editor.cursor(), (0, 4));
+        app.tabs[0].jump_to((0, 0));
+        app.tabs[0].editor.insert_str("three ");
+        app.on_editor_content_changed();
+        app.run_key_action(KeyAction::Undo).expect("undo live edit");
+        assert_eq!(app.tabs[0].editor.lines()[0], "two one");
+        app.run_key_action(KeyAction::Undo).expect("undo restored");
+        assert_eq!(app.tabs[0].editor.lines()[0], "one");
+        app.run_key_action(KeyAction::Redo).expect("redo restored");
+        assert_eq!(app.tabs[0].editor.lines()[0], "two one");
+        app.run_key_action(KeyAction::Redo).expect("redo live edit");
+        assert_eq!(app.tabs[0].editor.lines()[0], "three two one");
+
+        // A file changed while closed drops its history.
+        app.close_tab_at(0);
+        fs::write(&file, "changed\n").expect("write");
+        app.open_file(file).expect("reopen changed");
+        assert!(app.tabs[0].undo_snapshots.is_empty());
+    }
+
+    #[test]
+    fn grouped_edits_undo_in_one_step_and_restore_selection() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("lib.rs");
+        fs::write(&file, "fn a() {}\nfn b() {}\nfn c() {}\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        let editor = &mut app.tabs[0].editor;
+        editor.move_cursor(ratatui_textarea::CursorMove::Jump(0, 3));
+        editor.start_selection();
+        editor.move_cursor(ratatui_textarea::CursorMove::Jump(1, 2));
+
+        let commented = ["// fn a() {}", "// fn b() {}", "fn c() {}"];
+        app.toggle_comment();
+        assert_eq!(app.tabs[0].editor.lines()[..3], commented);
+        app.replace_in_open_file("fn", "pub fn");
+        assert_eq!(app.tabs[0].editor.lines()[2], "pub fn c() {}");
+
+        app.run_key_action(KeyAction::Undo).expect("undo replace");
+        assert_eq!(app.tabs[0].editor.lines()[..3], commented);
+        app.run_key_action(KeyAction::Undo).expect("undo comment");
+        let tab = &app.tabs[0];
+        assert_eq!(tab.editor.lines()[0], "fn a() {}");
+        assert_eq!(tab.editor.lines()[1], "fn b() {}");
+        assert_eq!(tab.editor.selection_range(), Some(((0, 3), (1, 2))));
+        assert_eq!(tab.editor.cursor(), (1, 2));
+
+        app.run_key_action(KeyAction::Redo).expect("redo comment");
+        app.run_key_action(KeyAction::Redo).expect("redo replace");
+        assert_eq!(app.tabs[0].editor.lines()[2], "pub fn c() {}");
+        assert_eq!(app.tabs[0].editor.lines()[0], "// pub fn a() {}");
+    }
+
+    #[test]
+    fn tab_commands_reorder_and_close_clean_tabs() {
+        use ratatui::crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        for name in ["a", "b", "c", "d"] {
+            let file = tmp.path().join(name);
+            fs::write(&file, name).expect("write");
+            app.open_file(file).expect("open");
+        }
+        let names = |app: &App| -> String {
+            app.tabs
+                .iter()
+                .map(|t| t.path.file_name().expect("name").to_string_lossy())
+                .collect()
+        };
+
+        app.move_active_tab(false);
+        assert_eq!(names(&app), "abdc");
+        assert_eq!(app.active_tab, 2);
+
+        // Drag "a" onto the third tab.
+        app.editor_rect = Rect::new(0, 0, 80, 20);
+        app.tab_rects = (0..4)
+            .map(|i| (Rect::new(1 + 8 * i, 0, 4, 1), Rect::new(5 + 8 * i, 0, 4, 1)))
+            .collect();
+        let mouse = |kind, column| MouseEvent {
+            kind,
+            column,
+            row: 0,
+            modifiers: KeyModifiers::NONE,
+        };
+        let left = MouseButton::Left;
+        app.handle_mouse(mouse(MouseEventKind::Down(left), 2))
+            .expect("press");
+        app.handle_mouse(mouse(MouseEventKind::Drag(left), 18))
+            .expect("drag");
+        app.handle_mouse(mouse(MouseEventKind::Up(left), 18))
+            .expect("release");
+        assert_eq!(names(&app), "bdac");
+        assert_eq!(app.active_tab, 2);
+        assert_eq!(app.tab_dragging, None);
+
+        app.tabs[0].dirty = true;
+        app.close_other_tabs();
+        assert_eq!(names(&app), "ba");
+        assert_eq!(app.active_tab, 1);
+        assert_eq!(app.status, "Closed 2 tab(s), kept 1 with unsaved changes");
+        app.close_tabs_to_right();
+        assert_eq!(names(&app), "ba");
+        app.close_all_tabs();
+        assert_eq!(names(&app), "b");
+        assert_eq!(app.active_tab, 0);
+    }
+
+    #[test]
+    fn pinned_tabs_stay_left_and_survive_close_others() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        for name in ["a", "b", "c"] {
+            let file = tmp.path().join(name);
+            fs::write(&file, name).expect("write");
+            app.open_file(file).expect("open");
+        }
+        let names = |app: &App| -> String {
+            app.tabs
+                .iter()
+                .map(|t| t.path.file_name().expect("name").to_string_lossy())
+                .collect()
+        };
+
+        app.toggle_pin_active_tab();
+        assert_eq!(names(&app), "cab");
+        assert!(app.tabs[0].pinned);
+        assert_eq!(app.active_tab, 0);
+        // A pinned tab can't be moved past unpinned ones, nor they before it.
+        app.move_active_tab(true);
+        assert_eq!(names(&app), "cab");
+        app.switch_to_tab(1);
+        app.move_active_tab(false);
+        assert_eq!(names(&app), "cab");
+
+        app.open_file_as(tmp.path().join("c"), true)
+            .expect("preview");
+        assert!(!app.tabs[0].is_preview);
+        app.switch_to_tab(2);
+        app.close_other_tabs();
+        assert_eq!(names(&app), "cb");
+
+        app.switch_to_tab(0);
+        app.toggle_pin_active_tab();
+        assert!(!app.tabs[0].pinned);
+        assert_eq!(names(&app), "cb");
+    }
+
+    #[test]
+    fn jump_to_matching_bracket_toggles_between_the_pair() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("main.rs");
+        fs::write(&file, "fn main() {\n    call(\"}\");\n}\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        let tab = app.active_tab_mut().expect("tab");
+        tab.jump_to((0, 11));
+        // Just after the `{`: it counts as the bracket at the cursor.
+        assert_eq!(tab.bracket_match_at_cursor(), Some(((0, 10), (2, 0))));
+
+        app.jump_to_matching_bracket();
+        assert_eq!(app.tabs[0].editor.cursor(), (2, 0));
+        app.jump_to_matching_bracket();
+        assert_eq!(app.tabs[0].editor.cursor(), (0, 10));
+
+        app.tabs[0].jump_to((1, 1));
+        app.jump_to_matching_bracket();
+        assert_eq!(app.tabs[0].editor.cursor(), (1, 1));
+    }
+
+    #[test]
+    fn last_edit_location_cycles_back_across_files() {
+        let tmp = tempdir().expect("tempdir");
+        let a = tmp.path().join("a.txt");
+        let b = tmp.path().join("b.txt");
+        fs::write(&a, "one\ntwo\n").expect("write");
+        fs::write(&b, "1\n2\n3\n4\n").expect("write");
+        let mut app = new_app(tmp.path());
+        let type_x = |app: &mut App| {
+            app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+                .expect("type");
         };
-        let mut col = seg_start;
-        let mut width_acc = 0usize;
-        for i in seg_start..seg_end.min(chars.len()) {
-            let cw = unicode_width::UnicodeWidthChar::width(chars[i]).unwrap_or(0);
-            if width_acc + cw > effective_text_x {
-                break;
-            }
-            width_acc += cw;
-            col = i + 1;
-        }
-        let col = col.min(seg_end).min(max_col);
-        Some((row, col))
+        app.open_file(a.clone()).expect("open a");
+        type_x(&mut app);
+        type_x(&mut app);
+        app.open_file(b.clone()).expect("open b");
+        app.tabs[1].jump_to((3, 0));
+        type_x(&mut app);
+        assert_eq!(app.edit_locations.len(), 2);
+
+        app.go_to_last_edit_location().expect("jump");
+        assert_eq!(app.open_path(), Some(&a));
+        assert_eq!(app.tabs[0].editor.cursor(), (0, 2));
+        app.go_to_last_edit_location().expect("jump");
+        assert_eq!(app.open_path(), Some(&b));
+        assert_eq!(app.tabs[1].editor.cursor(), (3, 1));
     }
-    pub(crate) fn select_line(&mut self, row: usize) {
-        let Some(tab) = self.active_tab() else {
-            return;
+
+    #[test]
+    fn reopening_a_closed_tab_returns_to_its_cursor_and_scroll() {
+        let tmp = tempdir().expect("tempdir");
+        let a = tmp.path().join("a.txt");
+        let b = tmp.path().join("b.txt");
+        let text: String = (1..=60).map(|n| format!("line {n}\n")).collect();
+        fs::write(&a, &text).expect("write");
+        fs::write(&b, "b\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.editor_rect = Rect::new(0, 0, 40, 12);
+        app.open_file(a.clone()).expect("open a");
+        app.tabs[0].jump_to((40, 3));
+        app.tabs[0].editor_scroll_row = 35;
+        app.open_file(b.clone()).expect("open b");
+        app.close_tab_at(0);
+        app.close_tab_at(0);
+        assert!(app.tabs.is_empty());
+
+        app.handle_key(KeyEvent::new(
+            KeyCode::Char('T'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        ))
+        .expect("reopen");
+        assert_eq!(app.open_path(), Some(&b));
+        // Opening a closed file any other way also goes back to its place.
+        app.open_file(a.clone()).expect("open a");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.cursor(), (40, 3));
+        assert_eq!(tab.editor_scroll_row, 35);
+        assert!(app.closed_tabs.is_empty());
+        app.reopen_closed_tab().expect("reopen");
+        assert_eq!(app.tabs.len(), 2);
+    }
+
+    #[test]
+    fn jump_list_walks_back_and_forward_across_files() {
+        let tmp = tempdir().expect("tempdir");
+        let a = tmp.path().join("a.txt");
+        let b = tmp.path().join("b.txt");
+        fs::write(&a, "1\n2\n3\n4\n5\n").expect("write");
+        fs::write(&b, "one\ntwo\n").expect("write");
+        let mut app = new_app(tmp.path());
+        let alt = |app: &mut App, code| {
+            app.handle_key(KeyEvent::new(code, KeyModifiers::ALT))
+                .expect("key");
         };
-        let lines = tab.editor.lines();
-        let total = lines.len();
-        if total == 0 || row >= total {
-            return;
-        }
-        let line_len = lines[row].chars().count();
-        // Start selection from end and move cursor to start, so the cursor
-        // ends up at the beginning of the line rather than the next line.
-        if let Some(tab) = self.active_tab_mut() {
-            if row + 1 < total {
-                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                    to_u16_saturating(row + 1),
-                    0,
-                ));
-            } else {
-                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                    to_u16_saturating(row),
-                    to_u16_saturating(line_len),
-                ));
-            }
-            tab.editor.start_selection();
-            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                to_u16_saturating(row),
-                0,
-            ));
-        }
-        self.sync_editor_scroll_guess();
+        app.open_file(a.clone()).expect("open a");
+        app.apply_prompt(PromptMode::GoToLine, "4".to_string())
+            .expect("go to line");
+        app.open_file(b.clone()).expect("open b");
+        assert_eq!(app.jump_back, [(a.clone(), 0, 0), (a.clone(), 3, 0)]);
+
+        alt(&mut app, KeyCode::Left);
+        assert_eq!(app.open_path(), Some(&a));
+        assert_eq!(app.tabs[0].editor.cursor(), (3, 0));
+        alt(&mut app, KeyCode::Left);
+        assert_eq!(app.tabs[0].editor.cursor(), (0, 0));
+        alt(&mut app, KeyCode::Left);
+        assert_eq!(app.tabs[0].editor.cursor(), (0, 0));
+
+        alt(&mut app, KeyCode::Right);
+        assert_eq!(app.tabs[0].editor.cursor(), (3, 0));
+        alt(&mut app, KeyCode::Right);
+        assert_eq!(app.open_path(), Some(&b));
+
+        // A new jump drops the forward history.
+        alt(&mut app, KeyCode::Left);
+        app.apply_prompt(PromptMode::GoToLine, "2".to_string())
+            .expect("go to line");
+        assert!(app.jump_forward.is_empty());
     }
 
-    pub(crate) fn select_line_range(&mut self, from: usize, to: usize) {
-        let Some(tab) = self.active_tab() else {
-            return;
+    #[test]
+    fn enter_carries_indentation_and_opens_bracket_blocks() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("main.py");
+        fs::write(&file, "def f():\n    x = g()\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        app.tabs[0].jump_to((0, 8));
+        app.handle_key(enter).expect("enter");
+        assert_eq!(app.tabs[0].editor.lines()[1], "    ");
+        assert_eq!(app.tabs[0].editor.cursor(), (1, 4));
+
+        // Between `(` and `)` the closer lands on its own line.
+        app.tabs[0].jump_to((2, 10));
+        app.handle_key(enter).expect("enter");
+        let lines = app.tabs[0].editor.lines();
+        assert_eq!(lines[2], "    x = g(");
+        assert_eq!(lines[3], "        ");
+        assert_eq!(lines[4], "    )");
+        assert_eq!(app.tabs[0].editor.cursor(), (3, 8));
+    }
+
+    #[test]
+    fn auto_pairs_wrap_selections_skip_their_closers_and_delete_together() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("main.js");
+        fs::write(&file, "let x = y;\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        let press = |app: &mut App, code| {
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+                .expect("key");
         };
-        let lines = tab.editor.lines();
-        let total = lines.len();
-        if total == 0 {
-            return;
-        }
-        let start = from.min(to).min(total.saturating_sub(1));
-        let end = from.max(to).min(total.saturating_sub(1));
-        // Select from start of first line to start of line after last (or end of last line)
-        if let Some(tab) = self.active_tab_mut() {
-            if end + 1 < total {
-                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                    to_u16_saturating(end + 1),
-                    0,
-                ));
-            } else {
-                let line_len = tab
-                    .editor
-                    .lines()
-                    .get(end)
-                    .map_or(0, |l| l.chars().count());
-                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                    to_u16_saturating(end),
-                    to_u16_saturating(line_len),
-                ));
-            }
-            tab.editor.start_selection();
-            tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                to_u16_saturating(start),
-                0,
-            ));
+
+        app.tabs[0].jump_to((0, 8));
+        app.tabs[0].editor.start_selection();
+        app.tabs[0].jump_to((0, 9));
+        press(&mut app, KeyCode::Char('('));
+        assert_eq!(app.tabs[0].editor.lines()[0], "let x = (y);");
+        assert_eq!(
+            app.tabs[0].editor.selection_range(),
+            Some(((0, 9), (0, 10)))
+        );
+
+        // Typing inside the pair keeps its closer skippable.
+        app.tabs[0].editor.cancel_selection();
+        app.tabs[0].jump_to((0, 4));
+        for code in [KeyCode::Char('['), KeyCode::Char('a'), KeyCode::Char(']')] {
+            press(&mut app, code);
         }
-        self.sync_editor_scroll_guess();
+        assert_eq!(app.tabs[0].editor.lines()[0], "let [a]x = (y);");
+        assert_eq!(app.tabs[0].editor.cursor(), (0, 7));
+        // A closer that was not typed in with its opener is inserted.
+        app.tabs[0].jump_to((0, 13));
+        press(&mut app, KeyCode::Char(')'));
+        assert_eq!(app.tabs[0].editor.lines()[0], "let [a]x = (y));");
+
+        app.tabs[0].jump_to((0, 0));
+        press(&mut app, KeyCode::Char('"'));
+        assert_eq!(app.tabs[0].editor.lines()[0], "\"\"let [a]x = (y));");
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(app.tabs[0].editor.lines()[0], "let [a]x = (y));");
+        assert_eq!(app.tabs[0].editor.cursor(), (0, 0));
     }
 
-    pub(crate) fn gutter_row_from_mouse(&self, y: u16) -> Option<usize> {
-        let tab = self.active_tab()?;
-        let inner_y = y.saturating_sub(self.editor_rect.y.saturating_add(1)) as usize;
-        let visible_idx = tab.editor_scroll_row + inner_y;
-        tab.visible_rows_map.get(visible_idx).copied()
+    #[test]
+    fn definition_outside_project_opens_read_only_external_source() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path().join("project");
+        let dep = tmp.path().join("dep");
+        fs::create_dir_all(&root).expect("mkdir");
+        fs::create_dir_all(&dep).expect("mkdir");
+        fs::write(root.join("main.txt"), "use dep;\n").expect("write");
+        let lib = dep.join("lib.txt");
+        fs::write(&lib, "pub fn dep() {}\n").expect("write");
+        let mut app = new_app(&root);
+        app.open_file(root.join("main.txt")).expect("open");
+        let uri = crate::util::file_uri(&lib).expect("uri");
+        app.handle_definition_response(serde_json::json!([{
+            "uri": uri,
+            "range": { "start": { "line": 0, "character": 7 } }
+        }]))
+        .expect("definition");
+        assert_eq!(app.tabs.len(), 2);
+        assert!(app.tabs[1].read_only);
+        assert_eq!(app.tabs[1].editor.cursor(), (0, 7));
+        let section = app
+            .tree
+            .iter()
+            .position(|i| i.external && i.is_dir)
+            .expect("external section");
+        assert_eq!(app.tree[section].name, "External sources");
+        assert_eq!(app.tree[section + 1].name, "lib.txt");
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("type");
+        app.handle_key(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE))
+            .expect("move");
+        assert_eq!(app.tabs[1].editor.lines()[0], "pub fn dep() {}");
+        assert_eq!(app.tabs[1].editor.cursor(), (0, 8));
+        assert!(!app.tabs[1].dirty);
+        assert_eq!(app.status_level, StatusLevel::Warn);
+
+        app.close_tab_at(1);
+        assert!(!app.tree.iter().any(|i| i.external));
     }
 
-    pub(crate) fn extend_mouse_selection(&mut self, x: u16, y: u16) {
-        if let (Some((anchor_row, anchor_col)), Some((row, col))) =
-            (self.editor_drag_anchor, self.editor_pos_from_mouse(x, y))
-        {
-            if let Some(tab) = self.active_tab_mut() {
-                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                    to_u16_saturating(anchor_row),
-                    to_u16_saturating(anchor_col),
-                ));
-                tab.editor.start_selection();
-                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                    to_u16_saturating(row),
-                    to_u16_saturating(col),
-                ));
-            }
+    #[test]
+    fn paste_from_history_offers_earlier_copies() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("notes.txt");
+        fs::write(&file, "alpha\nbeta\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.clipboard = None;
+        app.open_file(file).expect("open");
+        for row in [0, 1, 0] {
+            app.tabs[0].jump_to((row, 0));
+            app.tabs[0].editor.start_selection();
+            app.tabs[0]
+                .editor
+                .move_cursor(ratatui_textarea::CursorMove::End);
+            app.copy_selection_to_clipboard();
         }
+        // Copying "alpha" again moves it to the front instead of repeating it.
+        assert_eq!(app.clipboard_history, ["alpha", "beta"]);
+
+        app.tabs[0].editor.cancel_selection();
+        app.tabs[0].jump_to((1, 4));
+        app.handle_key(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::ALT))
+            .expect("alt+v");
+        assert_eq!(app.clipboard_history_view, Some(0));
+        app.handle_key(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE))
+            .expect("down");
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("enter");
+        assert_eq!(app.clipboard_history_view, None);
+        assert_eq!(app.tabs[0].editor.lines()[1], "betabeta");
+        assert_eq!(app.clipboard_history, ["beta", "alpha"]);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use tempfile::tempdir;
+    #[test]
+    fn multi_line_paste_takes_the_cursor_indentation() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("main.rs");
+        fs::write(&file, "fn main() {\n    if x {\n        \n    }\n}\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        app.editor_settings.reindent_on_paste = true;
 
-    fn new_app(root: &std::path::Path) -> App {
-        App::new(root.to_path_buf()).expect("app should initialize")
+        // On a blank indented line the block lands at that indentation,
+        // keeping its inner two-space step.
+        app.tabs[0].jump_to((2, 8));
+        app.handle_paste("let a = 1;\nfor i in v {\n  f(i);\n}".to_string());
+        assert_eq!(
+            app.tabs[0].editor.lines()[2..6],
+            [
+                "        let a = 1;",
+                "        for i in v {",
+                "          f(i);",
+                "        }",
+            ]
+        );
+
+        // Whole lines pasted at the start of a line go above it at its
+        // indentation; a partial first line opening a block counts its
+        // body as one level in.
+        app.tabs[0].jump_to((6, 0));
+        app.handle_paste("            if y {\n                g();\n            }\n".to_string());
+        app.tabs[0].jump_to((9, 5));
+        app.handle_paste(" else {\n            h();\n        ".to_string());
+        assert_eq!(
+            app.tabs[0].editor.lines()[6..11],
+            [
+                "    if y {",
+                "        g();",
+                "    }",
+                "    } else {",
+                "        h();",
+            ]
+        );
+
+        app.editor_settings.reindent_on_paste = false;
+        app.tabs[0].jump_to((0, 0));
+        app.handle_paste("a\n      b\n".to_string());
+        assert_eq!(app.tabs[0].editor.lines()[..2], ["a", "      b"]);
     }
 
     #[test]
-    fn click_line_number_selects_line() {
+    fn rust_analyzer_waits_for_the_project_trust_decision() {
         let tmp = tempdir().expect("tempdir");
-        let root = tmp.path();
-        let file = root.join("test.txt");
-        fs::write(&file, "line 0\nline 1\nline 2\n").expect("write");
-        let mut app = new_app(root);
+        fs::write(tmp.path().join("main.rs"), "fn main() {}\n").expect("write");
+        fs::write(tmp.path().join("lib.rs"), "pub fn f() {}\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(tmp.path().join("main.rs")).expect("open");
+        assert!(matches!(app.pending, PendingAction::TrustProject));
+        assert!(app.lsp.is_none());
+
+        // Other keys only repeat the question.
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("key");
+        assert!(matches!(app.pending, PendingAction::TrustProject));
+        assert_eq!(app.tabs[0].editor.lines()[0], "fn main() {}");
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE))
+            .expect("decline");
+        assert!(matches!(app.pending, PendingAction::None));
+        assert_eq!(app.trusted_projects.get(&app.root), Some(&false));
+        app.open_file(tmp.path().join("lib.rs")).expect("open");
+        assert!(matches!(app.pending, PendingAction::None));
+        assert!(app.lsp.is_none());
+    }
+
+    #[test]
+    fn alt_arrows_move_lines_and_keep_the_selection() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("list.txt");
+        fs::write(&file, "a\nb\nc\nd\n").expect("write");
+        let mut app = new_app(tmp.path());
         app.open_file(file).expect("open");
+        let alt = |code| KeyEvent::new(code, KeyModifiers::ALT);
+        app.tabs[0].jump_to((0, 1));
+        app.handle_key(alt(KeyCode::Down)).expect("alt+down");
+        assert_eq!(app.tabs[0].editor.lines()[..4], ["b", "a", "c", "d"]);
+        assert_eq!(app.tabs[0].editor.cursor(), (1, 1));
 
-        app.select_line(1);
+        // Rows 1-2 selected up to the start of row 3: "a" and "c" move.
+        app.tabs[0].jump_to((3, 0));
+        app.tabs[0].editor.start_selection();
+        app.tabs[0].jump_to((1, 0));
+        app.handle_key(alt(KeyCode::Up)).expect("alt+up");
+        assert_eq!(app.tabs[0].editor.lines()[..4], ["a", "c", "b", "d"]);
+        assert_eq!(app.tabs[0].editor.selection_range(), Some(((0, 0), (2, 0))));
+        assert_eq!(app.tabs[0].editor.cursor(), (0, 0));
+        app.handle_key(alt(KeyCode::Up)).expect("alt+up at top");
+        assert_eq!(app.tabs[0].editor.lines()[..4], ["a", "c", "b", "d"]);
 
-        let tab = app.active_tab().expect("tab");
-        let sel = tab.editor.selection_range().expect("should have selection");
-        assert_eq!(sel, ((1, 0), (2, 0)));
-        // Cursor should be at the start of the selected line
-        assert_eq!(tab.editor.cursor(), (1, 0));
+        app.handle_key(KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL))
+            .expect("undo");
+        assert_eq!(app.tabs[0].editor.lines()[..4], ["b", "a", "c", "d"]);
     }
 
     #[test]
-    fn click_line_number_selects_last_line() {
+    fn tab_pads_to_the_configured_stop_and_indents_selections() {
         let tmp = tempdir().expect("tempdir");
-        let root = tmp.path();
-        let file = root.join("test.txt");
-        fs::write(&file, "line 0\nline 1\nlast line").expect("write");
-        let mut app = new_app(root);
+        let file = tmp.path().join("notes.txt");
+        fs::write(&file, "a\nb\n\nc\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.editor_settings.tab_width = 2;
         app.open_file(file).expect("open");
+        let tab_key = KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE);
+        app.tabs[0].jump_to((1, 1));
+        app.handle_key(tab_key).expect("tab");
+        assert_eq!(app.tabs[0].editor.lines()[1], "b ");
 
-        app.select_line(2);
+        // Every line the selection touches moves one level, blank ones stay empty.
+        app.tabs[0].jump_to((0, 1));
+        app.tabs[0].editor.start_selection();
+        app.tabs[0].jump_to((3, 1));
+        app.handle_key(tab_key).expect("tab");
+        assert_eq!(app.tabs[0].editor.lines()[..4], ["  a", "  b ", "", "  c"]);
+        assert_eq!(app.tabs[0].editor.selection_range(), Some(((0, 3), (3, 3))));
+        app.handle_key(KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT))
+            .expect("shift+tab");
+        assert_eq!(app.tabs[0].editor.lines()[..4], ["a", "b ", "", "c"]);
 
-        let tab = app.active_tab().expect("tab");
-        let sel = tab.editor.selection_range().expect("should have selection");
-        assert_eq!(sel, ((2, 0), (2, 9)));
+        let hard = tmp.path().join("hard.txt");
+        fs::write(&hard, "c\n").expect("write");
+        app.editor_settings.insert_spaces = false;
+        app.open_file(hard).expect("open");
+        app.handle_key(tab_key).expect("tab");
+        assert_eq!(app.tabs[1].editor.lines()[0], "\tc");
     }
 
     #[test]
-    fn click_line_number_selects_first_line() {
+    fn changed_indent_settings_apply_to_open_buffers() {
         let tmp = tempdir().expect("tempdir");
-        let root = tmp.path();
-        let file = root.join("test.txt");
-        fs::write(&file, "first\nsecond\n").expect("write");
-        let mut app = new_app(root);
+        let file = tmp.path().join("notes.txt");
+        fs::write(&file, "\tx\n").expect("write");
+        let mut app = new_app(tmp.path());
         app.open_file(file).expect("open");
+        assert_eq!(app.tabs[0].tab_width(), 4);
 
-        app.select_line(0);
+        app.editor_settings.tab_width = 8;
+        app.editor_settings.insert_spaces = false;
+        app.reapply_indent_settings();
+        assert_eq!(app.tabs[0].tab_width(), 8);
+        assert!(app.tabs[0].editor.hard_tab_indent());
+    }
 
-        let tab = app.active_tab().expect("tab");
-        let sel = tab.editor.selection_range().expect("should have selection");
-        assert_eq!(sel, ((0, 0), (1, 0)));
+    #[test]
+    fn indentation_commands_detect_and_convert_the_buffer_style() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("main.py");
+        fs::write(&file, "def f():\n  if x:\n    y()\n   # odd\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        app.tabs[0].jump_to((2, 5));
+
+        app.run_command_action(CommandAction::ConvertIndentToTabs)
+            .expect("to tabs");
+        assert_eq!(
+            app.tabs[0].editor.lines()[..4],
+            ["def f():", "\tif x:", "\t\ty()", "\t # odd"]
+        );
+        assert_eq!(app.tabs[0].editor.cursor(), (2, 3));
+        assert_eq!(app.status, "Indentation was 2 spaces, now tabs");
+
+        app.run_command_action(CommandAction::ReindentFile)
+            .expect("reindent");
+        assert_eq!(
+            app.tabs[0].editor.lines()[..4],
+            ["def f():", "    if x:", "        y()", "     # odd"]
+        );
+        assert_eq!(app.status, "Indentation was tabs, now 4 spaces");
+        app.run_command_action(CommandAction::ConvertIndentToSpaces)
+            .expect("to spaces");
+        assert_eq!(app.status, "Indentation is already 4 spaces");
     }
 
     #[test]
-    fn cut_line_removes_middle_line() {
+    fn rust_test_scaffold_creates_then_extends_the_tests_module() {
         let tmp = tempdir().expect("tempdir");
-        let root = tmp.path();
-        let file = root.join("test.txt");
-        fs::write(&file, "aaa\nbbb\nccc\n").expect("write");
-        let mut app = new_app(root);
+        let file = tmp.path().join("lib.rs");
+        fs::write(
+            &file,
+            "pub fn parse(s: &str) -> u8 {\n    s.len() as u8\n}\n",
+        )
+        .expect("write");
+        let mut app = new_app(tmp.path());
         app.open_file(file).expect("open");
-        // Move cursor to line 1 (bbb)
-        app.tabs[app.active_tab]
-            .editor
-            .move_cursor(ratatui_textarea::CursorMove::Jump(1, 0));
-        app.cut_line();
-        let lines = app.tabs[app.active_tab].editor.lines().to_vec();
-        assert_eq!(lines, vec!["aaa", "ccc", ""]);
+        app.tabs[0].jump_to((1, 4));
+
+        app.run_command_action(CommandAction::GenerateRustTest)
+            .expect("scaffold");
+        let expected = [
+            "pub fn parse(s: &str) -> u8 {",
+            "    s.len() as u8",
+            "}",
+            "",
+            "#[cfg(test)]",
+            "mod tests {",
+            "    use super::*;",
+            "",
+            "    #[test]",
+            "    fn test_parse() {",
+            "        todo!()",
+            "    }",
+            "}",
+            "",
+        ];
+        assert_eq!(app.tabs[0].editor.lines(), expected);
+        assert_eq!(app.tabs[0].editor.cursor(), (10, 8));
+
+        // A second run appends to the same module under a fresh name.
+        app.tabs[0].jump_to((0, 0));
+        app.run_command_action(CommandAction::GenerateRustTest)
+            .expect("scaffold");
+        let lines = app.tabs[0].editor.lines();
+        assert_eq!(lines[12..14], ["", "    #[test]"]);
+        assert_eq!(lines[14], "    fn test_parse_2() {");
+        assert_eq!(lines[15..], ["        todo!()", "    }", "}", ""]);
+        assert_eq!(app.tabs[0].editor.cursor(), (15, 8));
     }
 
     #[test]
-    fn cut_line_removes_last_line() {
+    fn markdown_lists_continue_on_enter_and_nest_with_tab() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("notes.md");
+        fs::write(&file, "1. first\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.open_file(file).expect("open");
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        app.tabs[0].jump_to((0, 8));
+        app.handle_key(press(KeyCode::Enter)).expect("enter");
+        app.handle_key(press(KeyCode::Char('x'))).expect("type");
+        assert_eq!(app.tabs[0].editor.lines()[1], "2. x");
+
+        app.handle_key(press(KeyCode::Tab)).expect("tab");
+        assert_eq!(app.tabs[0].editor.lines()[1], "  2. x");
+        assert_eq!(app.tabs[0].editor.cursor(), (1, 6));
+        app.handle_key(KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT))
+            .expect("shift+tab");
+        assert_eq!(app.tabs[0].editor.lines()[1], "2. x");
+
+        // Enter on an item with nothing in it ends the list.
+        app.handle_key(press(KeyCode::Enter)).expect("enter");
+        assert_eq!(app.tabs[0].editor.lines()[2], "3. ");
+        app.handle_key(press(KeyCode::Enter)).expect("enter");
+        assert_eq!(app.tabs[0].editor.lines()[2], "");
+        assert_eq!(app.tabs[0].editor.cursor(), (2, 0));
+    }
+
+    #[test]
+    fn remembered_language_mode_applies_on_open() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("Justfile");
+        fs::write(&file, "build:\n    cargo build\n").expect("write");
+        let mut app = new_app(tmp.path());
+        app.language_overrides
+            .insert(file.clone(), SyntaxLang::Shell);
+        app.open_file(file).expect("open");
+        assert_eq!(app.tabs[0].lang, SyntaxLang::Shell);
+
+        app.toggle_comment();
+        assert_eq!(app.tabs[0].editor.lines()[0], "# build:");
+
+        app.set_language_mode("cobol");
+        assert_eq!(app.tabs[0].lang, SyntaxLang::Shell);
+        assert_eq!(app.status_level, StatusLevel::Warn);
+    }
+
+    #[test]
+    fn export_todos_writes_csv_report() {
         let tmp = tempdir().expect("tempdir");
         let root = tmp.path();
-        let file = root.join("test.txt");
-        fs::write(&file, "aaa\nbbb\n").expect("write");
+        fs::write(
+            root.join("a.rs"),
+            "// TODO: tidy up\nfn a() {}\n// FIXME and TODO\n",
+        )
+        .expect("write");
+        fs::write(root.join("b.txt"), "nothing to do\n").expect("write");
         let mut app = new_app(root);
+        app.export_todos("todos.csv");
+        let report = fs::read_to_string(root.join("todos.csv")).expect("report");
+        assert_eq!(
+            report,
+            "file,line,text,author\na.rs,1,TODO: tidy up,\na.rs,3,FIXME and TODO,\n"
+        );
+        // Exporting again must not pick up the report itself.
+        app.export_todos("todos.csv");
+        assert_eq!(
+            fs::read_to_string(root.join("todos.csv")).expect("report"),
+            report
+        );
+    }
+
+    #[test]
+    fn prompt_up_down_cycles_search_history() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = new_app(tmp.path());
+        app.search_history = SearchHistory::default();
+        for query in ["older", "newer", "older"] {
+            app.search_history.record(&PromptMode::FindInProject, query);
+        }
+        assert_eq!(app.search_history.project, ["older", "newer"]);
+
+        app.open_project_search_prompt();
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("type");
+        let mut step = |code| {
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+                .expect("step");
+            app.prompt.as_ref().expect("prompt").value.clone()
+        };
+        assert_eq!(step(KeyCode::Up), "older");
+        assert_eq!(step(KeyCode::Up), "newer");
+        assert_eq!(step(KeyCode::Up), "newer");
+        assert_eq!(step(KeyCode::Down), "older");
+        assert_eq!(step(KeyCode::Down), "x");
+    }
+
+    #[test]
+    fn large_files_load_in_chunks_as_the_cursor_nears_the_end() {
+        use ratatui_textarea::CursorMove;
+
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("big.rs");
+        let text: String = (0..100_000).map(|i| format!("fn f{i}() {{}}\n")).collect();
+        fs::write(&file, &text).expect("write");
+        let mut app = new_app(tmp.path());
+        app.filetype_config.large_file_bytes = Some(64 * 1024);
+        app.editor_rect = Rect::new(0, 0, 80, 20);
         app.open_file(file).expect("open");
-        // Move cursor to last content line (index 2 is the empty trailing line)
-        app.tabs[app.active_tab]
-            .editor
-            .move_cursor(ratatui_textarea::CursorMove::Jump(2, 0));
-        app.cut_line();
-        let lines = app.tabs[app.active_tab].editor.lines().to_vec();
-        assert_eq!(lines, vec!["aaa", "bbb"]);
-        // Cursor should be clamped, not out of bounds
-        let (row, _) = app.tabs[app.active_tab].editor.cursor();
-        assert!(row < lines.len());
+
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.lang, SyntaxLang::Plain);
+        assert!(tab.fold_ranges.is_empty());
+        assert!(tab.open_doc_uri.is_none());
+        let loaded = tab.editor.lines().len();
+        assert!(loaded < 100_000);
+        assert_eq!(
+            tab.editor.lines()[loaded - 2],
+            format!("fn f{}() {{}}", loaded - 2)
+        );
+        app.poll_large_file().expect("poll");
+        assert_eq!(app.active_tab().expect("tab").editor.lines().len(), loaded);
+
+        let press = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        app.handle_key(press('x')).expect("type");
+        assert_eq!(app.status_level, StatusLevel::Warn);
+        assert!(!app.is_dirty());
+
+        for _ in 0..10 {
+            let tab = app.active_tab_mut().expect("tab");
+            if tab.large_file.expect("large").fully_loaded() {
+                break;
+            }
+            tab.editor.move_cursor(CursorMove::Bottom);
+            let row = tab.editor.cursor().0;
+            app.poll_large_file().expect("poll");
+            assert_eq!(app.active_tab().expect("tab").editor.cursor(), (row, 0));
+        }
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines().len(), 100_001);
+        assert_eq!(tab.editor.lines()[99_999], "fn f99999() {}");
+        app.handle_key(press('x')).expect("type");
+        assert!(app.is_dirty());
+    }
+
+    #[test]
+    fn large_files_reload_when_rewritten_and_keep_appends_to_load() {
+        use ratatui_textarea::CursorMove;
+        use std::io::Write;
+
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("big.log");
+        let text: String = (0..100_000).map(|i| format!("line {i}\n")).collect();
+        fs::write(&file, &text).expect("write");
+        let mut app = new_app(tmp.path());
+        app.filetype_config.large_file_bytes = Some(64 * 1024);
+        app.editor_rect = Rect::new(0, 0, 80, 20);
+        app.open_file(file.clone()).expect("open");
+        let opened = app.tabs[0].large_file.expect("large");
+
+        let mut log = fs::OpenOptions::new()
+            .append(true)
+            .open(&file)
+            .expect("open log");
+        log.write_all(b"appended\n").expect("append");
+        assert!(!app.refresh_large_file().expect("refresh"));
+        let large = app.tabs[0].large_file.expect("large");
+        assert_eq!(large.loaded_bytes, opened.loaded_bytes);
+        assert_eq!(large.total_bytes, opened.total_bytes + 9);
+
+        let rewritten: String = (0..200_000).map(|i| format!("new {i}\n")).collect();
+        fs::write(&file, &rewritten).expect("write");
+        assert!(app.refresh_large_file().expect("refresh"));
+        let tab = &app.tabs[0];
+        assert_eq!(tab.editor.lines()[0], "new 0");
+        assert_eq!(
+            tab.large_file.expect("large").total_bytes,
+            rewritten.len() as u64
+        );
+        assert!(!tab.dirty);
+
+        // Truncated below what is loaded: the next load starts over.
+        fs::write(&file, "short\n").expect("write");
+        app.tabs[0].editor.move_cursor(CursorMove::Bottom);
+        app.poll_large_file().expect("poll");
+        let tab = &app.tabs[0];
+        assert_eq!(tab.editor.lines()[0], "short");
+        assert!(tab.large_file.expect("large").fully_loaded());
+    }
+
+    #[test]
+    fn followed_files_append_external_writes_and_stay_at_the_end() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("build.log");
+        fs::write(&file, "step 1\nstep 2").expect("write");
+        let mut app = new_app(tmp.path());
+        app.editor_rect = Rect::new(0, 0, 80, 20);
+        app.open_file(file.clone()).expect("open");
+        app.toggle_follow_file().expect("follow");
+        let tab = app.active_tab().expect("tab");
+        assert!(tab.follow_tail);
+        assert_eq!(tab.editor.cursor(), (1, 6));
+
+        let press = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        app.handle_key(press('x')).expect("type");
+        assert_eq!(app.status_level, StatusLevel::Warn);
+        assert!(!app.is_dirty());
+
+        let mut log = fs::OpenOptions::new()
+            .append(true)
+            .open(&file)
+            .expect("open");
+        std::io::Write::write_all(&mut log, b" done\nstep 3\n").expect("append");
+        app.fs_refresh_pending = true;
+        app.last_fs_refresh = Instant::now() - Duration::from_secs(1);
+        app.poll_fs_changes().expect("poll");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines(), ["step 1", "step 2 done", "step 3", ""]);
+        assert_eq!(tab.editor.cursor(), (3, 0));
+        assert!(!tab.conflict_prompt_open);
+
+        fs::write(&file, "rotated\n").expect("truncate");
+        app.refresh_followed_file().expect("refresh");
+        assert_eq!(
+            app.active_tab().expect("tab").editor.lines(),
+            ["rotated", ""]
+        );
+
+        app.toggle_follow_file().expect("unfollow");
+        app.handle_key(press('x')).expect("type");
+        assert!(app.is_dirty());
     }
 }
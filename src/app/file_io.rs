@@ -0,0 +1,189 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ratatui_textarea::TextArea;
+
+use super::App;
+use crate::file_io::{FileIoEvent, spawn_read, spawn_write};
+use crate::tab::{Tab, TabIo};
+use crate::types::Focus;
+use crate::util::{large_file_threshold, relative_path};
+
+impl App {
+    /// Files under a `watch.json` poll root sit on a network mount, so
+    /// they are read and written on a worker thread.
+    pub(crate) fn file_io_is_slow(&self, path: &Path) -> bool {
+        self.watch_settings.poll_interval_for(path).is_some()
+    }
+
+    /// Open a tab for `path` right away, showing "Loading…" until the read
+    /// reports.
+    pub(crate) fn start_background_open(
+        &mut self,
+        path: PathBuf,
+        as_preview: bool,
+        force_text: bool,
+    ) {
+        let mut tab = Tab::new(path.clone(), TextArea::default());
+        tab.is_preview = as_preview;
+        tab.pending_io = Some(TabIo::Loading { force_text });
+        if !as_preview {
+            self.record_jump();
+            self.record_recent_file(&path);
+        }
+        self.insert_opened_tab(tab, as_preview);
+        self.focus = Focus::Editor;
+        self.set_status(format!(
+            "Loading {}…",
+            relative_path(&self.root, &path).display()
+        ));
+        spawn_read(
+            path,
+            large_file_threshold(&self.filetype_config),
+            Self::LARGE_FILE_CHUNK_BYTES,
+            self.file_io_tx.clone(),
+        );
+    }
+
+    /// Write `content` in the background, showing "Saving…" on the tab.
+    /// The buffer stays editable meanwhile.
+    pub(crate) fn start_background_save(&mut self, path: PathBuf, content: String) {
+        let name = relative_path(&self.root, &path).display().to_string();
+        let Some(tab) = self.tabs.iter_mut().find(|t| t.path == path) else {
+            return;
+        };
+        if tab.pending_io == Some(TabIo::Saving) {
+            self.set_warning(format!("{name} is still being saved"));
+            return;
+        }
+        tab.pending_io = Some(TabIo::Saving);
+        let utf16 = tab.utf16;
+        self.file_writes_in_flight += 1;
+        self.set_status(format!("Saving {name}…"));
+        spawn_write(path, content, utf16, self.file_io_tx.clone());
+    }
+
+    /// Apply the background reads and writes that have finished.
+    pub(crate) fn poll_file_io(&mut self) -> io::Result<()> {
+        while let Ok(event) = self.file_io_rx.try_recv() {
+            self.apply_file_io(event)?;
+        }
+        Ok(())
+    }
+
+    /// Wait for background saves before quitting, so none is cut off.
+    pub(crate) fn finish_file_writes(&mut self) {
+        while self.file_writes_in_flight > 0 {
+            let Ok(event) = self.file_io_rx.recv_timeout(Duration::from_secs(30)) else {
+                return;
+            };
+            let _ = self.apply_file_io(event);
+        }
+    }
+
+    fn apply_file_io(&mut self, event: FileIoEvent) -> io::Result<()> {
+        match event {
+            FileIoEvent::Read { path, result } => {
+                // A loading tab closed meanwhile drops its read.
+                let Some((idx, force_text)) =
+                    self.tabs
+                        .iter()
+                        .enumerate()
+                        .find_map(|(idx, t)| match t.pending_io {
+                            Some(TabIo::Loading { force_text }) if t.path == path => {
+                                Some((idx, force_text))
+                            }
+                            _ => None,
+                        })
+                else {
+                    return Ok(());
+                };
+                match result {
+                    Ok(read) => {
+                        let as_preview = self.tabs[idx].is_preview;
+                        self.open_file_read(path, as_preview, force_text, read)?;
+                    }
+                    Err(err) => {
+                        self.close_tab_at(idx);
+                        self.set_error(format!(
+                            "Could not open {}: {err}",
+                            relative_path(&self.root, &path).display()
+                        ));
+                    }
+                }
+            }
+            FileIoEvent::Written {
+                path,
+                content,
+                result,
+            } => {
+                self.file_writes_in_flight = self.file_writes_in_flight.saturating_sub(1);
+                if let Some(tab) = self
+                    .tabs
+                    .iter_mut()
+                    .find(|t| t.path == path && t.pending_io == Some(TabIo::Saving))
+                {
+                    tab.pending_io = None;
+                }
+                match result {
+                    Ok(()) => self.finish_save(path, content),
+                    Err(err) => self.set_error(format!(
+                        "Could not save {}: {err}",
+                        relative_path(&self.root, &path).display()
+                    )),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::Instant;
+    use tempfile::tempdir;
+
+    fn wait_for_io(app: &mut App, done: impl Fn(&App) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done(app) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+            app.poll_file_io().expect("poll");
+        }
+        assert!(done(app), "background file io did not finish");
+    }
+
+    #[test]
+    fn files_on_polled_roots_load_and_save_in_the_background() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("notes.txt");
+        fs::write(&file, "one\ntwo\n").expect("write");
+        let mut app = App::new_for_test(root);
+        app.watch_settings
+            .poll_roots
+            .insert(root.to_path_buf(), 1_000);
+
+        app.open_file(file.clone()).expect("open");
+        let tab = app.active_tab().expect("tab");
+        assert!(tab.is_loading() && tab.refuses_edits());
+        assert_eq!(app.status, "Loading notes.txt…");
+        wait_for_io(&mut app, |app| app.tabs[0].pending_io.is_none());
+        assert_eq!(app.tabs[0].editor.lines(), ["one", "two", ""]);
+        assert_eq!(app.status, "Opened notes.txt");
+
+        app.tabs[0].editor.insert_str("zero\n");
+        app.tabs[0].dirty = true;
+        app.save_file().expect("save");
+        assert_eq!(app.tabs[0].pending_io, Some(TabIo::Saving));
+        assert_eq!(app.status, "Saving notes.txt…");
+        app.tabs[0].editor.insert_str("more ");
+        wait_for_io(&mut app, |app| app.file_writes_in_flight == 0);
+        assert_eq!(fs::read_to_string(&file).expect("read"), "zero\none\ntwo\n");
+        assert_eq!(app.status, "Saved notes.txt");
+        assert!(app.tabs[0].dirty, "edits made while saving stay unsaved");
+        assert!(app.tabs[0].pending_io.is_none());
+    }
+}
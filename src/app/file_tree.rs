@@ -1,11 +1,20 @@
-use super::App;
+use super::{App, BatchRenameEntry, BatchRenameState};
 use std::fs;
 use std::io;
 use std::path::{Component, Path, PathBuf};
 
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use regex::Regex;
+
+use crate::git_branch::BranchSwitch;
 use crate::tree_item::TreeItem;
-use crate::types::{ContextAction, PendingAction, PromptMode, PromptState};
-use crate::util::{collect_all_files, fuzzy_score, relative_path, to_u16_saturating};
+use crate::types::{
+    ContextAction, PendingAction, PromptMode, PromptState, StatusLevel, TreeClipboard, TreePaste,
+};
+use crate::util::{
+    compute_git_changed_files, copy_path_recursive, decode_text, extension_change, file_extension,
+    open_with_system, parse_goto_target, relative_path, remove_path, to_u16_saturating,
+};
 
 impl App {
     fn sanitize_entry_name<'a>(&self, value: &'a str) -> Result<&'a str, &'static str> {
@@ -20,6 +29,40 @@ impl App {
         }
     }
 
+    /// Refuse to delete, rename or move the project root, a workspace folder,
+    /// or anything outside them. Symlinks and `..` in the parent are resolved
+    /// so `root/sub/..` or a path reached through a link cannot slip past a
+    /// plain prefix check.
+    pub(crate) fn check_mutable_path(&self, path: &Path, verb: &str) -> Result<(), String> {
+        let root = self
+            .root
+            .canonicalize()
+            .unwrap_or_else(|_| self.root.clone());
+        let resolved = resolve_parent(path);
+        if path == self.root || resolved == root {
+            return Err(format!("Cannot {verb} project root"));
+        }
+        if let Some(folder) = self.workspace_folder_for(&resolved) {
+            if resolved == folder.path {
+                return Err(format!("Cannot {verb} a workspace folder"));
+            }
+            return Ok(());
+        }
+        if !resolved.starts_with(&root) {
+            return Err(format!("Cannot {verb} paths outside the project"));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_outside_project(&self, path: &Path) -> bool {
+        let root = self
+            .root
+            .canonicalize()
+            .unwrap_or_else(|_| self.root.clone());
+        let path = resolve_parent(path);
+        !path.starts_with(&root) && self.workspace_folder_for(&path).is_none()
+    }
+
     fn close_tabs_for_path_prefix(&mut self, path: &Path) {
         let mut indices: Vec<usize> = self
             .tabs
@@ -52,6 +95,22 @@ impl App {
         }
     }
 
+    fn retarget_pins_for_rename(&mut self, from: &Path, to: &Path) {
+        let Some(pins) = self.pinned_paths.get_mut(&self.root) else {
+            return;
+        };
+        let mut changed = false;
+        for pin in pins.iter_mut() {
+            if let Ok(suffix) = pin.strip_prefix(from) {
+                *pin = to.join(suffix);
+                changed = true;
+            }
+        }
+        if changed {
+            self.persist_state();
+        }
+    }
+
     fn retarget_expanded_for_rename(&mut self, from: &Path, to: &Path) {
         let mut moved = Vec::new();
         for p in &self.expanded {
@@ -69,28 +128,345 @@ impl App {
         }
     }
 
+    /// List what "Batch Rename" would do to the files directly in `dir`
+    /// whose names match `pattern`, for confirmation.
+    pub(crate) fn preview_batch_rename(
+        &mut self,
+        dir: PathBuf,
+        pattern: &str,
+        replacement: &str,
+    ) -> io::Result<()> {
+        let re = match Regex::new(pattern) {
+            Ok(re) => re,
+            Err(err) => {
+                self.set_warning(format!("Invalid regex: {err}"));
+                return Ok(());
+            }
+        };
+        let entries = batch_rename_plan(&dir, &re, replacement)?;
+        if entries.is_empty() {
+            self.set_status(format!("No file names in {} change", dir.display()));
+            return Ok(());
+        }
+        let problems = entries.iter().filter(|e| e.problem.is_some()).count();
+        if problems > 0 {
+            self.set_warning(format!(
+                "{problems} of {} renames conflict; Esc and adjust the pattern",
+                entries.len()
+            ));
+        } else {
+            self.set_status(format!(
+                "{} files to rename: Enter apply, Esc cancel",
+                entries.len()
+            ));
+        }
+        self.batch_rename = Some(BatchRenameState {
+            dir,
+            entries,
+            selected: 0,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn handle_batch_rename_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(state) = self.batch_rename.as_mut() else {
+            return Ok(());
+        };
+        let last = state.entries.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.batch_rename = None;
+                self.set_status("Batch rename canceled");
+            }
+            KeyCode::Down | KeyCode::Char('j') => state.selected = (state.selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => state.selected = state.selected.saturating_sub(1),
+            KeyCode::Enter => {
+                if state.entries.iter().any(|e| e.problem.is_some()) {
+                    self.set_warning("Resolve the conflicting names before applying");
+                    return Ok(());
+                }
+                let Some(state) = self.batch_rename.take() else {
+                    return Ok(());
+                };
+                self.apply_batch_rename(state)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Rename every entry or none: each file first moves to a temporary
+    /// name, so swaps and chains work, and a failure undoes the moves made.
+    fn apply_batch_rename(&mut self, state: BatchRenameState) -> io::Result<()> {
+        let mut done: Vec<(PathBuf, PathBuf)> = Vec::new();
+        let result = (|| {
+            let mut staged = Vec::new();
+            for (idx, entry) in state.entries.iter().enumerate() {
+                let name = entry.from.file_name().unwrap_or_default().to_string_lossy();
+                let temp = state.dir.join(format!(".lazyide-rename-{idx}-{name}"));
+                fs::rename(&entry.from, &temp)?;
+                done.push((entry.from.clone(), temp.clone()));
+                staged.push((temp, entry.to.clone()));
+            }
+            for (temp, to) in staged {
+                fs::rename(&temp, &to)?;
+                done.push((temp, to));
+            }
+            io::Result::Ok(())
+        })();
+        if let Err(err) = result {
+            for (from, to) in done.iter().rev() {
+                let _ = fs::rename(to, from);
+            }
+            self.set_error(format!("Batch rename failed, nothing renamed: {err}"));
+            return Ok(());
+        }
+        for entry in &state.entries {
+            self.retarget_tabs_for_rename(&entry.from, &entry.to);
+            self.retarget_pins_for_rename(&entry.from, &entry.to);
+        }
+        self.retarget_lsp_documents();
+        self.rebuild_tree()?;
+        self.set_status(format!(
+            "Renamed {} files in {}",
+            state.entries.len(),
+            relative_path(&self.root, &state.dir).display()
+        ));
+        Ok(())
+    }
+
+    /// Re-read every folder the tree shows and rebuild it.
     pub(crate) fn rebuild_tree(&mut self) -> io::Result<()> {
-        let selected_path = self.tree.get(self.selected).map(|i| i.path.clone());
+        self.dir_listings.get_mut().clear();
+        self.refresh_tree()
+    }
+
+    /// Rebuild the tree from the cached folder listings, reading only the
+    /// folders not cached: newly expanded ones and those
+    /// `invalidate_dir_listings` dropped.
+    pub(crate) fn refresh_tree(&mut self) -> io::Result<()> {
+        let selected = self
+            .tree
+            .get(self.selected)
+            .map(|i| (i.path.clone(), i.pinned));
         let mut out = Vec::new();
+        self.push_pinned(&mut out);
         self.walk_dir(&self.root, 0, &mut out)?;
-        if out.is_empty() {
+        if out.iter().all(|i| i.pinned) {
             out.push(TreeItem {
                 path: self.root.clone(),
                 name: self.root.display().to_string(),
                 depth: 0,
                 is_dir: true,
                 expanded: true,
+                ignored: false,
+                external: false,
+                pinned: false,
             });
         }
+        self.push_workspace_folders(&mut out)?;
+        self.push_external_sources(&mut out);
         self.tree = out;
-        self.selected = selected_path
-            .and_then(|p| self.tree.iter().position(|i| i.path == p))
+        // Collapsed folders are read again when next expanded.
+        let (root, expanded) = (&self.root, &self.expanded);
+        let folders = &self.workspace_folders;
+        self.dir_listings.get_mut().retain(|d, _| {
+            d == root || expanded.contains(d) || folders.iter().any(|f| f.path == *d)
+        });
+        self.tree_marked.retain(|p| p.exists());
+        self.selected = selected
+            .and_then(|(p, pinned)| {
+                self.tree
+                    .iter()
+                    .position(|i| i.path == p && i.pinned == pinned)
+            })
             .unwrap_or(0);
         // Invalidate the cached file list; it will be rebuilt lazily when needed.
         self.cached_file_list.clear();
         Ok(())
     }
 
+    /// Pinned files and folders of this project, in pin order; ones that
+    /// no longer exist are kept but not listed.
+    pub(crate) fn pinned(&self) -> Vec<PathBuf> {
+        self.pinned_paths
+            .get(&self.root)
+            .map(|pins| pins.iter().filter(|p| p.exists()).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// The "Pinned" section above the project files.
+    fn push_pinned(&self, out: &mut Vec<TreeItem>) {
+        let pins = self.pinned();
+        if pins.is_empty() {
+            return;
+        }
+        out.push(TreeItem {
+            path: PathBuf::new(),
+            name: "Pinned".to_string(),
+            depth: 0,
+            is_dir: true,
+            expanded: true,
+            ignored: false,
+            external: false,
+            pinned: true,
+        });
+        for path in pins {
+            out.push(TreeItem {
+                name: relative_path(&self.root, &path).display().to_string(),
+                is_dir: path.is_dir(),
+                path,
+                depth: 1,
+                expanded: false,
+                ignored: false,
+                external: false,
+                pinned: true,
+            });
+        }
+    }
+
+    pub(crate) fn toggle_pin_path(&mut self, path: PathBuf) -> io::Result<()> {
+        if path.as_os_str().is_empty() || path == self.root || self.is_outside_project(&path) {
+            self.set_warning("Only files and folders inside the project can be pinned");
+            return Ok(());
+        }
+        let rel = relative_path(&self.root, &path).display().to_string();
+        let pins = self.pinned_paths.entry(self.root.clone()).or_default();
+        if let Some(idx) = pins.iter().position(|p| *p == path) {
+            pins.remove(idx);
+            if pins.is_empty() {
+                self.pinned_paths.remove(&self.root);
+            }
+            self.set_status(format!("Unpinned {rel}"));
+        } else {
+            pins.push(path);
+            self.set_status(format!("Pinned {rel}"));
+        }
+        self.persist_state();
+        self.rebuild_tree()
+    }
+
+    /// Open the `n`th pinned entry (1-based): a file opens, a folder is
+    /// revealed in the tree.
+    pub(crate) fn open_pinned(&mut self, n: usize) -> io::Result<()> {
+        let Some(path) = self.pinned().into_iter().nth(n.saturating_sub(1)) else {
+            self.set_status(format!("No pinned entry {n}"));
+            return Ok(());
+        };
+        if path.is_dir() {
+            self.reveal_in_tree(&path)
+        } else {
+            self.open_file(path)
+        }
+    }
+
+    /// Expand the folders down to `path` and select its project tree row.
+    fn reveal_in_tree(&mut self, path: &Path) -> io::Result<()> {
+        for dir in path.ancestors().take_while(|d| d.starts_with(&self.root)) {
+            if dir != self.root {
+                self.expanded.insert(dir.to_path_buf());
+            }
+        }
+        self.refresh_tree()?;
+        if let Some(idx) = self.tree.iter().position(|i| i.path == path && !i.pinned) {
+            self.selected = idx;
+        }
+        self.set_status(format!(
+            "Directory: {}",
+            relative_path(&self.root, path).display()
+        ));
+        Ok(())
+    }
+
+    /// Read-only tabs from outside the project get their own section below
+    /// the project files, so they are not mistaken for part of it.
+    fn push_external_sources(&self, out: &mut Vec<TreeItem>) {
+        let mut external = self.tabs.iter().filter(|t| t.read_only).peekable();
+        if external.peek().is_none() {
+            return;
+        }
+        out.push(TreeItem {
+            path: PathBuf::new(),
+            name: "External sources".to_string(),
+            depth: 0,
+            is_dir: true,
+            expanded: true,
+            ignored: false,
+            external: true,
+            pinned: false,
+        });
+        for tab in external {
+            out.push(TreeItem {
+                path: tab.path.clone(),
+                name: tab
+                    .path
+                    .file_name()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_else(|| tab.path.display().to_string()),
+                depth: 1,
+                is_dir: false,
+                expanded: false,
+                ignored: false,
+                external: true,
+                pinned: false,
+            });
+        }
+    }
+
+    /// Forget the cached listings of the folders `changed` paths are in,
+    /// and of the paths themselves and anything under them when they are
+    /// folders. A path outside the project and workspace folders (e.g. a
+    /// canonicalized one) drops the whole cache.
+    pub(crate) fn invalidate_dir_listings<'a>(
+        &mut self,
+        changed: impl IntoIterator<Item = &'a PathBuf>,
+    ) {
+        let listings = self.dir_listings.get_mut();
+        for path in changed {
+            let known = path.starts_with(&self.root)
+                || self
+                    .workspace_folders
+                    .iter()
+                    .any(|f| path.starts_with(&f.path));
+            if !known {
+                listings.clear();
+                return;
+            }
+            listings.retain(|d, _| !d.starts_with(path));
+            if let Some(parent) = path.parent() {
+                listings.remove(parent);
+            }
+        }
+    }
+
+    /// `dir`'s entries as `(path, is_dir)`, folders first and then by name,
+    /// from `dir_listings` or else read and cached there. Symlinks are left
+    /// out to avoid following directory cycles.
+    fn dir_listing(&self, dir: &Path) -> io::Result<Vec<(PathBuf, bool)>> {
+        if let Some(listing) = self.dir_listings.borrow().get(dir) {
+            return Ok(listing.clone());
+        }
+        let mut entries: Vec<(PathBuf, bool)> = fs::read_dir(dir)?
+            .filter_map(Result::ok)
+            .filter_map(|e| {
+                let ft = e.file_type().ok()?;
+                (!ft.is_symlink()).then(|| (e.path(), ft.is_dir()))
+            })
+            .collect();
+        entries.sort_by_cached_key(|(p, is_dir)| {
+            (
+                !is_dir,
+                p.file_name()
+                    .map(|s| s.to_string_lossy().to_ascii_lowercase())
+                    .unwrap_or_default(),
+            )
+        });
+        self.dir_listings
+            .borrow_mut()
+            .insert(dir.to_path_buf(), entries.clone());
+        Ok(entries)
+    }
+
     pub(crate) fn walk_dir(
         &self,
         dir: &Path,
@@ -113,6 +489,9 @@ impl App {
                 depth,
                 is_dir: true,
                 expanded,
+                ignored: self.ignore_rules_for(dir).is_ignored(dir, true),
+                external: false,
+                pinned: false,
             });
             if !expanded {
                 return Ok(());
@@ -121,28 +500,11 @@ impl App {
 
         let child_depth = if is_root { depth } else { depth + 1 };
 
-        let mut entries: Vec<_> = fs::read_dir(dir)?
-            .filter_map(Result::ok)
-            .map(|e| e.path())
-            .collect();
-        entries.sort_by_key(|p| {
-            (
-                !p.is_dir(),
-                p.file_name()
-                    .map(|s| s.to_string_lossy().to_ascii_lowercase())
-                    .unwrap_or_default(),
-            )
-        });
-
-        for path in entries {
-            let Ok(ft) = fs::symlink_metadata(&path).map(|m| m.file_type()) else {
-                continue;
-            };
-            // Avoid following directory symlink cycles.
-            if ft.is_symlink() {
+        for (path, is_dir) in self.dir_listing(dir)? {
+            let ignored = self.ignore_rules_for(&path).is_ignored(&path, is_dir);
+            if ignored && !self.show_ignored {
                 continue;
             }
-            let is_dir = ft.is_dir();
             let name = path
                 .file_name()
                 .map(|s| s.to_string_lossy().to_string())
@@ -156,6 +518,9 @@ impl App {
                     depth: child_depth,
                     is_dir: false,
                     expanded: false,
+                    ignored,
+                    external: false,
+                    pinned: false,
                 });
             }
         }
@@ -167,48 +532,24 @@ impl App {
     }
 
     pub(crate) fn set_status<S: Into<String>>(&mut self, status: S) {
-        self.status = status.into();
+        self.set_status_at(StatusLevel::Info, status);
     }
 
-    pub(crate) fn refresh_file_picker_results(&mut self) {
-        // Lazily rebuild the file list if it was invalidated
-        if self.cached_file_list.is_empty() {
-            let mut files = Vec::new();
-            collect_all_files(&self.root, &mut files);
-            self.cached_file_list = files;
+    /// Quick Open over the files git reports as changed, most recently
+    /// modified first.
+    pub(crate) fn open_changed_files_picker(&mut self) {
+        self.changed_files = compute_git_changed_files(&self.root);
+        if self.changed_files.is_empty() {
+            self.set_status("No changed files");
+            return;
         }
-        let query = self.file_picker_query.to_ascii_lowercase();
-        let mut scored: Vec<(usize, PathBuf)> = self
-            .cached_file_list
-            .iter()
-            .filter_map(|path| {
-                let rel = relative_path(&self.root, path).display().to_string();
-                fuzzy_score(&query, &rel).map(|score| (score, path.clone()))
-            })
-            .collect();
-        scored.sort_by(|(sa, pa), (sb, pb)| {
-            sa.cmp(sb)
-                .then_with(|| pa.as_os_str().len().cmp(&pb.as_os_str().len()))
-        });
-        self.file_picker_results = scored.into_iter().map(|(_, p)| p).take(200).collect();
-        self.file_picker_index = self
-            .file_picker_index
-            .min(self.file_picker_results.len().saturating_sub(1));
-    }
-
-    pub(crate) fn open_file_picker_selection(&mut self) -> io::Result<()> {
-        let Some(path) = self
-            .file_picker_results
-            .get(self.file_picker_index)
-            .cloned()
-        else {
-            return Ok(());
-        };
-        self.file_picker_open = false;
+        self.file_picker_open = true;
+        self.file_picker_changed_only = true;
         self.file_picker_query.clear();
-        self.open_file(path)?;
-        Ok(())
+        self.file_picker_index = 0;
+        self.refresh_file_picker_results();
     }
+
     pub(crate) fn tree_activate_selected(&mut self) -> io::Result<()> {
         self.tree_activate_selected_as(false)
     }
@@ -217,13 +558,19 @@ impl App {
         let Some(item) = self.selected_item().cloned() else {
             return Ok(());
         };
+        if (item.external || item.pinned) && item.path.as_os_str().is_empty() {
+            return Ok(());
+        }
+        if item.pinned && item.is_dir {
+            return self.reveal_in_tree(&item.path);
+        }
         if item.is_dir {
             if self.expanded.contains(&item.path) {
                 self.expanded.remove(&item.path);
             } else {
                 self.expanded.insert(item.path.clone());
             }
-            self.rebuild_tree()?;
+            self.refresh_tree()?;
             self.set_status(format!("Directory: {}", item.path.display()));
         } else {
             self.open_file_as(item.path.clone(), as_preview)?;
@@ -235,13 +582,17 @@ impl App {
         let Some(item) = self.selected_item().cloned() else {
             return;
         };
+        if item.pinned {
+            self.selected = 0;
+            return;
+        }
         if item.is_dir && self.expanded.contains(&item.path) {
             self.expanded.remove(&item.path);
-            let _ = self.rebuild_tree();
+            let _ = self.refresh_tree();
             return;
         }
         if let Some(parent) = item.path.parent() {
-            if let Some(idx) = self.tree.iter().position(|i| i.path == parent) {
+            if let Some(idx) = self.tree.iter().position(|i| i.path == parent && !i.pinned) {
                 self.selected = idx;
             }
         }
@@ -267,7 +618,7 @@ impl App {
         }
         self.expanded.insert(item.path.clone());
         collect_dirs(&item.path, &mut self.expanded);
-        self.rebuild_tree()
+        self.refresh_tree()
     }
 
     pub(crate) fn tree_collapse_recursive(&mut self) -> io::Result<()> {
@@ -278,7 +629,7 @@ impl App {
         let target = if item.is_dir && self.expanded.contains(&item.path) {
             item.path.clone()
         } else if let Some(parent) = item.path.parent() {
-            if let Some(idx) = self.tree.iter().position(|i| i.path == parent) {
+            if let Some(idx) = self.tree.iter().position(|i| i.path == parent && !i.pinned) {
                 self.selected = idx;
             }
             parent.to_path_buf()
@@ -287,7 +638,7 @@ impl App {
         };
         // Remove target and all descendants from expanded set.
         self.expanded.retain(|p| !p.starts_with(&target));
-        self.rebuild_tree()
+        self.refresh_tree()
     }
 
     pub(crate) fn tree_expand_all(&mut self) -> io::Result<()> {
@@ -304,22 +655,22 @@ impl App {
         }
         self.expanded.insert(self.root.clone());
         collect_dirs(&self.root, &mut self.expanded);
-        self.rebuild_tree()
+        self.refresh_tree()
     }
 
     pub(crate) fn tree_collapse_all(&mut self) -> io::Result<()> {
         self.expanded.clear();
         self.selected = 0;
-        self.rebuild_tree()
+        self.refresh_tree()
     }
 
     pub(crate) fn delete_path(&mut self, path: PathBuf) -> io::Result<()> {
-        if path == self.root {
-            self.set_status("Cannot delete project root");
+        if let Err(msg) = self.check_mutable_path(&path, "delete") {
+            self.set_warning(msg);
             return Ok(());
         }
         if !path.exists() {
-            self.set_status("Path no longer exists");
+            self.set_warning("Path no longer exists");
             self.rebuild_tree()?;
             return Ok(());
         }
@@ -331,14 +682,159 @@ impl App {
         // Close any tab at this path or under this directory.
         self.close_tabs_for_path_prefix(&path);
         self.expanded.retain(|p| !p.starts_with(&path));
+        if let Some(pins) = self.pinned_paths.get_mut(&self.root) {
+            let count = pins.len();
+            pins.retain(|p| !p.starts_with(&path));
+            if pins.len() != count {
+                self.persist_state();
+            }
+        }
         self.rebuild_tree()?;
         self.set_status(format!("Deleted {}", path.display()));
         Ok(())
     }
 
+    fn paste_tree_clipboard(&mut self, target: PathBuf) -> io::Result<()> {
+        let Some(clip) = self.tree_clipboard.clone() else {
+            self.reject_action("Nothing to paste");
+            return Ok(());
+        };
+        let sources: Vec<PathBuf> = clip.paths.into_iter().filter(|p| p.exists()).collect();
+        if sources.is_empty() {
+            self.tree_clipboard = None;
+            self.set_warning("Path no longer exists");
+            return Ok(());
+        }
+        let dir = if target.is_dir() {
+            target
+        } else {
+            target.parent().unwrap_or(&self.root).to_path_buf()
+        };
+        if sources.iter().any(|p| dir.starts_with(p)) {
+            self.set_warning("Cannot paste a folder into itself");
+            return Ok(());
+        }
+        let mut pastes = Vec::new();
+        for from in sources {
+            let Some(name) = from.file_name() else {
+                continue;
+            };
+            let mut to = dir.join(name);
+            if to == from {
+                if clip.cut {
+                    continue;
+                }
+                to = copy_destination(&to);
+            }
+            pastes.push(TreePaste {
+                from,
+                to,
+                cut: clip.cut,
+            });
+        }
+        if pastes.iter().any(TreePaste::replaces_own_folder) {
+            self.set_warning("Cannot replace a folder with something inside it");
+            return Ok(());
+        }
+        // Several entries are confirmed together; one only when it would
+        // replace something.
+        match pastes.len() {
+            0 => self.set_status("Already in this folder"),
+            1 if pastes[0].to.exists() => {
+                self.set_pending(PendingAction::PasteOverwrite(pastes.remove(0)));
+            }
+            1 => self.paste_path(pastes.remove(0))?,
+            _ => self.set_pending(PendingAction::PasteMany(pastes)),
+        }
+        Ok(())
+    }
+
+    /// Move or copy `paste.from` to `paste.to`, replacing anything already there.
+    pub(crate) fn paste_path(&mut self, paste: TreePaste) -> io::Result<()> {
+        if paste.replaces_own_folder() {
+            self.set_warning("Cannot replace a folder with something inside it");
+            return Ok(());
+        }
+        let TreePaste { from, to, cut } = paste;
+        let checked = if cut {
+            self.check_mutable_path(&from, "move")
+        } else {
+            Ok(())
+        };
+        if let Err(msg) = checked.and_then(|()| self.check_mutable_path(&to, "overwrite")) {
+            self.set_warning(msg);
+            return Ok(());
+        }
+        if !from.exists() {
+            self.set_warning("Path no longer exists");
+            self.rebuild_tree()?;
+            return Ok(());
+        }
+        // Put the result next to `to` first, so a failed copy leaves both
+        // `from` and whatever `to` held untouched.
+        let staging = paste_staging_path(&to);
+        // rename fails across filesystems; fall back to copy + remove.
+        let renamed = cut && fs::rename(&from, &staging).is_ok();
+        if !renamed && let Err(err) = copy_path_recursive(&from, &staging) {
+            let _ = remove_path(&staging);
+            return Err(err);
+        }
+        if fs::symlink_metadata(&to).is_ok() {
+            if let Err(err) = remove_path(&to) {
+                let _ = if renamed {
+                    fs::rename(&staging, &from)
+                } else {
+                    remove_path(&staging)
+                };
+                return Err(err);
+            }
+            self.close_tabs_for_path_prefix(&to);
+            self.expanded.retain(|p| !p.starts_with(&to));
+        }
+        fs::rename(&staging, &to)?;
+        if cut {
+            if !renamed {
+                remove_path(&from)?;
+            }
+            self.retarget_tabs_for_rename(&from, &to);
+            self.retarget_expanded_for_rename(&from, &to);
+            self.retarget_pins_for_rename(&from, &to);
+            self.tree_clipboard = None;
+        }
+        self.rebuild_tree()?;
+        self.set_status(format!(
+            "{} {} to {}",
+            if cut { "Moved" } else { "Copied" },
+            relative_path(&self.root, &from).display(),
+            relative_path(&self.root, &to).display()
+        ));
+        Ok(())
+    }
+
+    /// Copy `path` next to itself as "name copy.ext" and select the copy.
+    fn duplicate_path(&mut self, path: &Path) -> io::Result<()> {
+        if let Err(msg) = self.check_mutable_path(path, "duplicate") {
+            self.set_warning(msg);
+            return Ok(());
+        }
+        let dest = copy_destination(path);
+        copy_path_recursive(path, &dest)?;
+        self.rebuild_tree()?;
+        if let Some(idx) = self.tree.iter().position(|i| i.path == dest) {
+            self.selected = idx;
+        }
+        self.set_status(format!(
+            "Duplicated {} as {}",
+            relative_path(&self.root, path).display(),
+            relative_path(&self.root, &dest).display()
+        ));
+        Ok(())
+    }
+
     pub(crate) fn create_new_file(&mut self) -> io::Result<()> {
         let base = self
             .selected_item()
+            .filter(|i| !i.external)
             .map(|i| i.path.clone())
             .unwrap_or_else(|| self.root.clone());
         let parent = if base.is_dir() {
@@ -363,18 +859,22 @@ impl App {
     }
 
     pub(crate) fn apply_prompt(&mut self, mode: PromptMode, value: String) -> io::Result<()> {
+        if self.search_history.entries(&mode).is_some() && !value.is_empty() {
+            self.search_history.record(&mode, &value);
+            self.persist_state();
+        }
         match mode {
             PromptMode::NewFile { parent } => {
                 let name = match self.sanitize_entry_name(&value) {
                     Ok(name) => name,
                     Err(msg) => {
-                        self.set_status(msg);
+                        self.set_warning(msg);
                         return Ok(());
                     }
                 };
                 let target = parent.join(name);
                 if target.exists() {
-                    self.set_status("File already exists");
+                    self.set_warning("File already exists");
                     return Ok(());
                 }
                 fs::write(&target, b"")?;
@@ -386,17 +886,72 @@ impl App {
                     relative_path(&self.root, &target).display()
                 ));
             }
+            PromptMode::NewFileLike { source } => {
+                let name = match self.sanitize_entry_name(&value) {
+                    Ok(name) => name,
+                    Err(msg) => {
+                        self.set_warning(msg);
+                        return Ok(());
+                    }
+                };
+                let parent = source.parent().unwrap_or(&self.root).to_path_buf();
+                let target = parent.join(name);
+                if target.exists() {
+                    self.set_warning("File already exists");
+                    return Ok(());
+                }
+                let text = decode_text(&fs::read(&source)?);
+                fs::write(&target, file_boilerplate(&text, file_extension(name)))?;
+                self.expanded.insert(parent);
+                self.rebuild_tree()?;
+                self.open_file(target.clone())?;
+                self.set_status(format!(
+                    "Created {} from {}",
+                    relative_path(&self.root, &target).display(),
+                    relative_path(&self.root, &source).display()
+                ));
+            }
+            PromptMode::NewCounterpart { source, test } => {
+                let rel = Path::new(value.trim());
+                if value.trim().is_empty()
+                    || !rel.components().all(|c| matches!(c, Component::Normal(_)))
+                {
+                    self.set_warning("Path must stay inside the project");
+                    return Ok(());
+                }
+                let target = self.root.join(rel);
+                if target.exists() {
+                    self.set_warning("File already exists");
+                    return Ok(());
+                }
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                    self.expanded.insert(parent.to_path_buf());
+                }
+                let template = if test {
+                    test_file_template(&source, &target)
+                } else {
+                    String::new()
+                };
+                fs::write(&target, template)?;
+                self.rebuild_tree()?;
+                self.open_file(target.clone())?;
+                self.set_status(format!(
+                    "Created {}",
+                    relative_path(&self.root, &target).display()
+                ));
+            }
             PromptMode::NewFolder { parent } => {
                 let name = match self.sanitize_entry_name(&value) {
                     Ok(name) => name,
                     Err(msg) => {
-                        self.set_status(msg);
+                        self.set_warning(msg);
                         return Ok(());
                     }
                 };
                 let target = parent.join(name);
                 if target.exists() {
-                    self.set_status("Folder already exists");
+                    self.set_warning("Folder already exists");
                     return Ok(());
                 }
                 fs::create_dir_all(&target)?;
@@ -410,18 +965,18 @@ impl App {
                 ));
             }
             PromptMode::Rename { target } => {
-                if target == self.root {
-                    self.set_status("Cannot rename project root");
+                if let Err(msg) = self.check_mutable_path(&target, "rename") {
+                    self.set_warning(msg);
                     return Ok(());
                 }
                 let Some(parent) = target.parent() else {
-                    self.set_status("Cannot rename root");
+                    self.set_warning("Cannot rename root");
                     return Ok(());
                 };
                 let name = match self.sanitize_entry_name(&value) {
                     Ok(name) => name,
                     Err(msg) => {
-                        self.set_status(msg);
+                        self.set_warning(msg);
                         return Ok(());
                     }
                 };
@@ -430,18 +985,35 @@ impl App {
                     self.set_status("Name unchanged");
                     return Ok(());
                 }
-                if renamed.exists() {
-                    self.set_status("Name already exists");
+                if let Some(existing) = rename_collision(&target, &renamed) {
+                    self.set_warning(format!("Name already exists: {existing}"));
                     return Ok(());
                 }
-                fs::rename(&target, &renamed)?;
+                let ext_change = extension_change(&target, name);
+                let level = if ext_change.is_some() {
+                    StatusLevel::Warn
+                } else {
+                    StatusLevel::Info
+                };
+                let ext_note = ext_change
+                    .map(|note| format!(" ({note})"))
+                    .unwrap_or_default();
+                rename_entry(&target, &renamed)?;
                 self.retarget_tabs_for_rename(&target, &renamed);
                 self.retarget_expanded_for_rename(&target, &renamed);
+                self.retarget_pins_for_rename(&target, &renamed);
+                self.retarget_lsp_documents();
                 self.rebuild_tree()?;
-                self.set_status(format!(
-                    "Renamed to {}",
-                    relative_path(&self.root, &renamed).display()
-                ));
+                // Git sees a delete and an add; refresh the tree's markers.
+                self.fs_refresh_pending = true;
+                self.fs_full_refresh_pending = true;
+                self.set_status_at(
+                    level,
+                    format!(
+                        "Renamed to {}{ext_note}",
+                        relative_path(&self.root, &renamed).display()
+                    ),
+                );
             }
             PromptMode::FindInFile => {
                 self.search_in_open_file(&value);
@@ -451,36 +1023,68 @@ impl App {
                         title: format!("Replace '{}' with", value),
                         value: String::new(),
                         cursor: 0,
+                        anchor: None,
+                        history: None,
                         mode: PromptMode::ReplaceInFile { search: value },
                     });
                 }
             }
             PromptMode::FindInProject => {
+                // With "Replace in Project", the replacement prompt opens
+                // once the search has finished with hits.
                 self.search_in_project(&value);
             }
             PromptMode::ReplaceInFile { search } => {
                 self.replace_in_open_file(&search, &value);
             }
+            PromptMode::ReplaceInProject => {
+                self.set_project_replacement(value);
+            }
+            PromptMode::ExportTodos => self.export_todos(&value),
+            PromptMode::AddWorkspaceFolder => self.add_workspace_folder(&value)?,
+            PromptMode::RenameSymbol => self.request_lsp_rename(&value),
+            PromptMode::CreateBranch => {
+                let name = value.trim();
+                if name.is_empty() {
+                    self.set_warning("Branch name is empty");
+                } else {
+                    self.switch_branch(BranchSwitch::Create(name.to_string()));
+                }
+            }
+            PromptMode::PasteLocations => {
+                if !self.open_location_picker(&value) {
+                    self.set_warning("No file:line references to files in the project");
+                }
+            }
+            PromptMode::LanguageMode => self.set_language_mode(&value),
             PromptMode::GoToLine => {
-                if let Ok(line_num) = value.parse::<usize>() {
-                    if line_num == 0 {
-                        self.set_status("Line number must be >= 1");
-                        return Ok(());
-                    }
-                    let target = line_num.saturating_sub(1);
-                    if let Some(tab) = self.active_tab_mut() {
-                        let max_line = tab.editor.lines().len().saturating_sub(1);
-                        let clamped = target.min(max_line);
-                        tab.editor.cancel_selection();
-                        tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
-                            to_u16_saturating(clamped),
-                            0,
-                        ));
-                    }
-                    self.sync_editor_scroll_guess();
-                    self.set_status(format!("Jumped to line {}", target + 1));
+                let Some((line_num, col_num)) = parse_goto_target(&value) else {
+                    self.set_warning("Invalid line number (use N or N:COL)");
+                    return Ok(());
+                };
+                if line_num == 0 || col_num == Some(0) {
+                    self.set_status("Line and column must be >= 1");
+                    return Ok(());
+                }
+                self.record_jump();
+                let Some(tab) = self.active_tab_mut() else {
+                    self.set_status("No file open");
+                    return Ok(());
+                };
+                let max_line = tab.editor.lines().len().saturating_sub(1);
+                let row = line_num.saturating_sub(1).min(max_line);
+                let line_len = tab.editor.lines()[row].chars().count();
+                let col = col_num.map_or(0, |c| c.saturating_sub(1)).min(line_len);
+                tab.editor.cancel_selection();
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(row),
+                    to_u16_saturating(col),
+                ));
+                self.center_cursor_in_view();
+                if col_num.is_some() {
+                    self.set_status(format!("Jumped to line {}:{}", row + 1, col + 1));
                 } else {
-                    self.set_status("Invalid line number");
+                    self.set_status(format!("Jumped to line {}", row + 1));
                 }
             }
         }
@@ -495,11 +1099,14 @@ impl App {
         };
         match action {
             ContextAction::Open => {
-                if let Some(idx) = self.tree.iter().position(|i| i.path == target) {
+                if self.selected_item().is_none_or(|i| i.path != target)
+                    && let Some(idx) = self.tree.iter().position(|i| i.path == target)
+                {
                     self.selected = idx;
                 }
                 self.tree_activate_selected()?;
             }
+            ContextAction::TogglePin => self.toggle_pin_path(target)?,
             ContextAction::NewFile => {
                 let parent = if target.is_dir() {
                     target
@@ -513,13 +1120,35 @@ impl App {
                     ),
                     value: String::new(),
                     cursor: 0,
+                    anchor: None,
+                    history: None,
                     mode: PromptMode::NewFile { parent },
                 });
             }
-            ContextAction::NewFolder => {
-                let parent = if target.is_dir() {
-                    target
-                } else {
+            ContextAction::NewFileLikeThis => {
+                if target.is_dir() || self.is_outside_project(&target) {
+                    self.set_warning("New File Like This needs a file in the project");
+                    return Ok(());
+                }
+                let default_name = target
+                    .file_name()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                // Select the stem so typing a new name keeps the extension.
+                let cursor = default_name.len() - file_extension(&default_name).len();
+                self.prompt = Some(PromptState {
+                    title: format!("New file like {default_name}"),
+                    value: default_name,
+                    cursor,
+                    anchor: Some(0),
+                    history: None,
+                    mode: PromptMode::NewFileLike { source: target },
+                });
+            }
+            ContextAction::NewFolder => {
+                let parent = if target.is_dir() {
+                    target
+                } else {
                     target.parent().unwrap_or(&self.root).to_path_buf()
                 };
                 self.prompt = Some(PromptState {
@@ -529,55 +1158,563 @@ impl App {
                     ),
                     value: String::new(),
                     cursor: 0,
+                    anchor: None,
+                    history: None,
                     mode: PromptMode::NewFolder { parent },
                 });
             }
             ContextAction::Rename => {
-                if target == self.root {
-                    self.set_status("Cannot rename project root");
+                if let Err(msg) = self.check_mutable_path(&target, "rename") {
+                    self.set_warning(msg);
                     return Ok(());
                 }
                 let default_name = target
                     .file_name()
                     .map(|s| s.to_string_lossy().to_string())
                     .unwrap_or_default();
-                let cursor = default_name.len();
+                // Select just the stem so typing keeps the extension.
+                let cursor = if target.is_dir() {
+                    default_name.len()
+                } else {
+                    default_name.len() - file_extension(&default_name).len()
+                };
                 self.prompt = Some(PromptState {
                     title: "Rename to".to_string(),
                     value: default_name,
                     cursor,
+                    anchor: Some(0),
+                    history: None,
                     mode: PromptMode::Rename { target },
                 });
             }
-            ContextAction::Delete => {
-                if target == self.root {
-                    self.set_status("Cannot delete project root");
+            ContextAction::BatchRename => {
+                let dir = if target.is_dir() {
+                    target
+                } else {
+                    target.parent().unwrap_or(&self.root).to_path_buf()
+                };
+                if dir != self.root
+                    && let Err(msg) = self.check_mutable_path(&dir, "rename in")
+                {
+                    self.set_warning(msg);
                     return Ok(());
                 }
-                self.pending = PendingAction::Delete(target.clone());
+                self.open_batch_rename_form(dir);
+            }
+            ContextAction::Cut | ContextAction::Copy => {
+                let cut = action == ContextAction::Cut;
+                let paths = self.tree_action_targets(&target);
+                for path in &paths {
+                    if let Err(msg) =
+                        self.check_mutable_path(path, if cut { "cut" } else { "copy" })
+                    {
+                        self.set_warning(msg);
+                        return Ok(());
+                    }
+                }
+                let what = match paths.as_slice() {
+                    [path] => relative_path(&self.root, path).display().to_string(),
+                    _ => format!("{} items", paths.len()),
+                };
                 self.set_status(format!(
-                    "Delete {} ? Press Enter to confirm, Esc to cancel.",
-                    target
-                        .file_name()
-                        .map(|s| s.to_string_lossy().to_string())
-                        .unwrap_or_else(|| target.display().to_string()),
+                    "{} {what}: choose Paste on a folder",
+                    if cut { "Cut" } else { "Copied" },
                 ));
+                self.tree_clipboard = Some(TreeClipboard { paths, cut });
+            }
+            ContextAction::Paste => self.paste_tree_clipboard(target)?,
+            ContextAction::Duplicate => self.duplicate_path(&target)?,
+            ContextAction::Delete => self.request_tree_delete(target),
+            ContextAction::CopyRelativePath | ContextAction::CopyAbsolutePath => {
+                let text = if action == ContextAction::CopyRelativePath {
+                    relative_path(&self.root, &target).display().to_string()
+                } else {
+                    target.display().to_string()
+                };
+                match self.clipboard.as_mut().map(|c| c.set_text(text.clone())) {
+                    Some(Ok(())) => self.set_status(format!("Copied path: {text}")),
+                    _ => self.set_warning("Clipboard unavailable"),
+                }
             }
+            ContextAction::RevealInFileManager => {
+                let dir = if target.is_dir() {
+                    target
+                } else {
+                    target.parent().unwrap_or(&self.root).to_path_buf()
+                };
+                match open_with_system(&dir) {
+                    Ok(()) => {
+                        self.set_status(format!("Opened {} in the file manager", dir.display()))
+                    }
+                    Err(err) => self.set_error(format!("Cannot open the file manager: {err}")),
+                }
+            }
+            ContextAction::OpenWithDefaultApp => match open_with_system(&target) {
+                Ok(()) => self.set_status(format!(
+                    "Opened {} in its default app",
+                    relative_path(&self.root, &target).display()
+                )),
+                Err(err) => self.set_error(format!("Cannot open {}: {err}", target.display())),
+            },
             ContextAction::Cancel => {}
         }
         Ok(())
     }
+
+    /// Open the test for the active file, or the file a test covers, by the
+    /// usual naming conventions. When neither the conventional spot nor a
+    /// same-named file elsewhere in the project exists, offer to create it.
+    pub(crate) fn go_to_test_counterpart(&mut self) -> io::Result<()> {
+        let Some(path) = self.open_path().cloned() else {
+            self.set_status("No file open");
+            return Ok(());
+        };
+        let Some((is_test, candidates)) = test_counterparts(&self.root, &path) else {
+            self.set_warning("No test naming convention for this file type");
+            return Ok(());
+        };
+        if let Some(found) = candidates.iter().find(|p| p.is_file()) {
+            return self.open_file(found.clone());
+        }
+        if self.cached_file_list.is_empty() {
+            let mut files = Vec::new();
+            self.collect_workspace_files(&mut files);
+            self.cached_file_list = files;
+        }
+        let names: Vec<_> = candidates.iter().filter_map(|p| p.file_name()).collect();
+        if let Some(found) = self
+            .cached_file_list
+            .iter()
+            .find(|p| *p != &path && p.file_name().is_some_and(|n| names.contains(&n)))
+        {
+            return self.open_file(found.clone());
+        }
+        let value = relative_path(&self.root, &candidates[0])
+            .display()
+            .to_string();
+        self.prompt = Some(PromptState {
+            title: if is_test {
+                "No implementation found; create".to_string()
+            } else {
+                "No test found; create".to_string()
+            },
+            cursor: value.len(),
+            value,
+            anchor: None,
+            history: None,
+            mode: PromptMode::NewCounterpart {
+                source: path,
+                test: !is_test,
+            },
+        });
+        Ok(())
+    }
+}
+
+/// Whether `path` is a test by its language's naming convention, and where
+/// its counterpart would conventionally be, most likely first:
+/// `src/foo.rs` ↔ `tests/foo.rs`, `foo.go` ↔ `foo_test.go`,
+/// `module.py` ↔ `test_module.py`, `foo.ts` ↔ `foo.test.ts`.
+fn test_counterparts(root: &Path, path: &Path) -> Option<(bool, Vec<PathBuf>)> {
+    let dir = path.parent()?;
+    let name = path.file_name()?.to_str()?;
+    let ext = file_extension(name);
+    let stem = &name[..name.len() - ext.len()];
+    let in_tests_dir = relative_path(root, path).starts_with("tests");
+    match ext {
+        ".rs" if in_tests_dir => Some((
+            true,
+            vec![
+                root.join("src").join(name),
+                root.join("src").join(stem).join("mod.rs"),
+            ],
+        )),
+        ".rs" => {
+            let stem = match stem {
+                "mod" => dir.file_name()?.to_str()?,
+                _ => stem,
+            };
+            Some((false, vec![root.join("tests").join(format!("{stem}.rs"))]))
+        }
+        ".go" => Some(match stem.strip_suffix("_test") {
+            Some(base) => (true, vec![dir.join(format!("{base}.go"))]),
+            None => (false, vec![dir.join(format!("{stem}_test.go"))]),
+        }),
+        ".py" => {
+            if let Some(base) = stem.strip_prefix("test_").or(stem.strip_suffix("_test")) {
+                let mut impls = vec![dir.join(format!("{base}.py"))];
+                if in_tests_dir {
+                    impls.push(root.join(format!("{base}.py")));
+                }
+                return Some((true, impls));
+            }
+            Some((
+                false,
+                vec![
+                    dir.join(format!("test_{stem}.py")),
+                    root.join("tests").join(format!("test_{stem}.py")),
+                    dir.join(format!("{stem}_test.py")),
+                ],
+            ))
+        }
+        ".js" | ".jsx" | ".mjs" | ".ts" | ".tsx" => {
+            if let Some(base) = stem.strip_suffix(".test").or(stem.strip_suffix(".spec")) {
+                let dir = match dir.file_name() {
+                    Some(d) if d == "__tests__" => dir.parent()?,
+                    _ => dir,
+                };
+                return Some((true, vec![dir.join(format!("{base}{ext}"))]));
+            }
+            Some((
+                false,
+                vec![
+                    dir.join(format!("{stem}.test{ext}")),
+                    dir.join(format!("{stem}.spec{ext}")),
+                    dir.join("__tests__").join(format!("{stem}.test{ext}")),
+                ],
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// A starting point for a new test file covering `source`.
+fn test_file_template(source: &Path, target: &Path) -> String {
+    let stem = |p: &Path| {
+        p.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .map(|n| n[..n.len() - file_extension(&n).len()].to_string())
+            .unwrap_or_default()
+    };
+    let (module, test) = (stem(source), stem(target));
+    match file_extension(&target.to_string_lossy()) {
+        ".rs" => format!("#[test]\nfn {module}_works() {{\n    todo!()\n}}\n"),
+        ".go" => {
+            let package = fs::read_to_string(source)
+                .ok()
+                .and_then(|text| {
+                    text.lines()
+                        .find_map(|l| l.strip_prefix("package ").map(|p| p.trim().to_string()))
+                })
+                .unwrap_or_else(|| "main".to_string());
+            let mut title = module.clone();
+            if let Some(first) = title.get_mut(..1) {
+                first.make_ascii_uppercase();
+            }
+            format!(
+                "package {package}\n\nimport \"testing\"\n\nfunc Test{title}(t *testing.T) {{\n}}\n"
+            )
+        }
+        ".py" => format!("import {module}\n\n\ndef {test}():\n    pass\n"),
+        _ => {
+            let up = if source.parent() == target.parent() {
+                "./"
+            } else {
+                "../"
+            };
+            format!("import * as subject from '{up}{module}';\n\ntest('{module}', () => {{\n}});\n")
+        }
+    }
+}
+
+/// The reusable top of a source file: shebang, header comments, package
+/// line and imports, stopping at the first real item. A Rust `mod tests`
+/// block is carried over as an empty skeleton with its `use` lines.
+fn file_boilerplate(text: &str, ext: &str) -> String {
+    let c_family = matches!(
+        ext,
+        ".c" | ".h" | ".cc" | ".cpp" | ".cxx" | ".hh" | ".hpp" | ".m" | ".mm"
+    );
+    const IMPORTS: [&str; 8] = [
+        "use ",
+        "pub use ",
+        "extern crate ",
+        "import ",
+        "from ",
+        "package ",
+        "#include",
+        "#import",
+    ];
+    let is_import = |line: &str| {
+        let js_require = line.contains("require(")
+            && ["const ", "let ", "var "]
+                .iter()
+                .any(|p| line.starts_with(p));
+        IMPORTS.iter().any(|p| line.starts_with(p))
+            || js_require
+            || matches!(line, "\"use strict\";" | "'use strict';")
+    };
+    let depth = |line: &str| {
+        line.chars().fold(0i32, |d, c| match c {
+            '(' | '{' | '[' => d + 1,
+            ')' | '}' | ']' => d - 1,
+            _ => d,
+        })
+    };
+    let mut out: Vec<&str> = Vec::new();
+    let mut in_comment = false;
+    let mut open_brackets = 0;
+    for (idx, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let keep = if in_comment {
+            in_comment = !trimmed.contains("*/");
+            true
+        } else if open_brackets > 0 {
+            open_brackets += depth(trimmed);
+            true
+        } else if trimmed.is_empty()
+            || trimmed.starts_with("//")
+            || trimmed.starts_with("--")
+            || (trimmed.starts_with("#!") && (idx == 0 || ext == ".rs"))
+        {
+            true
+        } else if trimmed.starts_with("/*") {
+            in_comment = !trimmed.contains("*/");
+            true
+        } else if trimmed.starts_with('#') && !c_family && ext != ".rs" {
+            // `#` starts a comment everywhere else that uses it.
+            true
+        } else if is_import(trimmed) || (c_family && trimmed.starts_with("#pragma")) {
+            open_brackets = depth(trimmed).max(0);
+            true
+        } else {
+            false
+        };
+        if !keep {
+            break;
+        }
+        out.push(line);
+    }
+    while out.last().is_some_and(|l| l.trim().is_empty()) {
+        out.pop();
+    }
+    let mut skeleton = out.join("\n");
+    if ext == ".rs" {
+        let lines: Vec<&str> = text.lines().collect();
+        if let Some(start) = lines
+            .windows(2)
+            .position(|w| w[0].trim() == "#[cfg(test)]" && w[1].trim() == "mod tests {")
+        {
+            let uses: Vec<&str> = lines[start + 2..]
+                .iter()
+                .take_while(|l| l.trim().starts_with("use "))
+                .copied()
+                .collect();
+            if !skeleton.is_empty() {
+                skeleton.push_str("\n\n");
+            }
+            skeleton.push_str("#[cfg(test)]\nmod tests {\n");
+            for line in uses {
+                skeleton.push_str(line);
+                skeleton.push('\n');
+            }
+            skeleton.push('}');
+        }
+    }
+    if !skeleton.is_empty() {
+        skeleton.push('\n');
+    }
+    skeleton
+}
+
+/// Canonicalize the parent of `path` but keep its final component, so a
+/// symlink is judged by where it lives rather than where it points.
+fn resolve_parent(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            parent
+                .canonicalize()
+                .map(|p| p.join(name))
+                .unwrap_or_else(|_| path.to_path_buf())
+        }
+        _ => path.canonicalize().unwrap_or_else(|_| path.to_path_buf()),
+    }
+}
+
+/// The files directly in `dir` whose names match `re`, with the name the
+/// first match's replacement gives them and any reason they cannot take it.
+/// Names that do not change are left out.
+fn batch_rename_plan(
+    dir: &Path,
+    re: &Regex,
+    replacement: &str,
+) -> io::Result<Vec<BatchRenameEntry>> {
+    let mut names: Vec<String> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    let fold_case = is_case_insensitive_dir(dir, &names);
+    let key = |name: &str| {
+        if fold_case {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        }
+    };
+    let renames: Vec<(String, String)> = names
+        .iter()
+        .filter(|name| dir.join(name).is_file() && re.is_match(name))
+        .map(|name| (name.clone(), re.replace(name, replacement).into_owned()))
+        .filter(|(old, new)| old != new)
+        .collect();
+    let moving: Vec<String> = renames.iter().map(|(old, _)| key(old)).collect();
+    let staying: Vec<String> = names
+        .iter()
+        .map(|n| key(n))
+        .filter(|n| !moving.contains(n))
+        .collect();
+    let entries = renames
+        .iter()
+        .map(|(old, new)| {
+            let mut parts = Path::new(new).components();
+            let single_name = matches!(
+                (parts.next(), parts.next()),
+                (Some(Component::Normal(_)), None)
+            );
+            let clashes = renames
+                .iter()
+                .filter(|(_, other)| key(other) == key(new))
+                .count();
+            let problem = if !single_name {
+                Some("not a valid file name".to_string())
+            } else if staying.contains(&key(new)) {
+                Some("already exists".to_string())
+            } else if clashes > 1 {
+                Some("same name as another rename".to_string())
+            } else {
+                None
+            };
+            BatchRenameEntry {
+                from: dir.join(old),
+                to: dir.join(new),
+                problem,
+            }
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// An unused hidden name next to `to` for a paste to be built under
+/// before it replaces `to`.
+fn paste_staging_path(to: &Path) -> PathBuf {
+    let name = to
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let parent = to.parent().unwrap_or(Path::new("."));
+    (0..)
+        .map(|n| parent.join(format!(".{name}.lazyide-paste-{n}")))
+        .find(|p| fs::symlink_metadata(p).is_err())
+        .unwrap_or_else(|| to.with_extension("lazyide-paste"))
+}
+
+/// The existing sibling a rename of `target` to `renamed` would clobber.
+/// Names are compared case-insensitively when the filesystem folds case,
+/// so `Readme.md` collides with `README.md` there but not on Linux.
+fn rename_collision(target: &Path, renamed: &Path) -> Option<String> {
+    let parent = renamed.parent()?;
+    let new_name = renamed.file_name()?.to_string_lossy().to_string();
+    let old_name = target.file_name()?.to_string_lossy().to_string();
+    let names: Vec<String> = fs::read_dir(parent)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    let fold_case = is_case_insensitive_dir(parent, &names);
+    names.into_iter().find(|name| {
+        *name != old_name
+            && (*name == new_name || fold_case && name.to_lowercase() == new_name.to_lowercase())
+    })
+}
+
+/// Whether `from` -> `to` only changes the letter case of the name.
+fn is_case_only_rename(from: &Path, to: &Path) -> bool {
+    let (Some(old), Some(new)) = (from.file_name(), to.file_name()) else {
+        return false;
+    };
+    let (old, new) = (old.to_string_lossy(), new.to_string_lossy());
+    from.parent() == to.parent() && old != new && old.to_lowercase() == new.to_lowercase()
+}
+
+/// Rename `from` to `to`. A case-only rename goes through a temporary
+/// name: on case-insensitive filesystems a direct rename to the same
+/// entry can be a no-op or fail. If the second step fails the original
+/// name is restored.
+fn rename_entry(from: &Path, to: &Path) -> io::Result<()> {
+    if !is_case_only_rename(from, to) {
+        return fs::rename(from, to);
+    }
+    let parent = from.parent().unwrap_or(Path::new(""));
+    let name = from.file_name().unwrap_or_default().to_string_lossy();
+    let temp = (0..)
+        .map(|n| parent.join(format!(".lazyide-rename-{n}-{name}")))
+        .find(|p| !p.exists())
+        .unwrap_or_else(|| parent.join(format!(".lazyide-rename-{name}")));
+    fs::rename(from, &temp)?;
+    if let Err(err) = fs::rename(&temp, to) {
+        let _ = fs::rename(&temp, from);
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Probe whether `dir` folds case by looking up an existing entry under a
+/// different case that is not itself listed.
+fn is_case_insensitive_dir(dir: &Path, names: &[String]) -> bool {
+    names.iter().find_map(|name| {
+        let upper = name.to_uppercase();
+        let flipped = if upper == *name {
+            name.to_lowercase()
+        } else {
+            upper
+        };
+        (flipped != *name && !names.contains(&flipped)).then(|| dir.join(&flipped).exists())
+    }) == Some(true)
+}
+
+/// Pick a free sibling name for copying `path` next to itself:
+/// `a.rs` -> `a copy.rs`, then `a copy 2.rs`, and so on.
+fn copy_destination(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or(Path::new(""));
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut n = 1usize;
+    loop {
+        let name = if n == 1 {
+            format!("{stem} copy{ext}")
+        } else {
+            format!("{stem} copy {n}{ext}")
+        };
+        let candidate = parent.join(name);
+        if !candidate.exists() {
+            return candidate;
+        }
+        n += 1;
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tab::GitFileStatus;
     use std::fs;
     use std::path::Path;
     use tempfile::tempdir;
 
     fn new_app(root: &Path) -> App {
-        App::new(root.to_path_buf()).expect("app should initialize")
+        App::new_for_test(root)
     }
 
     #[test]
@@ -593,6 +1730,45 @@ mod tests {
         assert_eq!(app.status, "Cannot delete project root");
     }
 
+    #[test]
+    fn delete_path_rejects_root_reached_through_parent_dir() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src")).expect("create src");
+        let mut app = new_app(root);
+
+        app.delete_path(root.join("src").join(".."))
+            .expect("delete root alias should be non-fatal");
+
+        assert!(root.join("src").is_dir());
+        assert_eq!(app.status, "Cannot delete project root");
+    }
+
+    #[test]
+    fn delete_and_rename_reject_paths_outside_project() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path().join("project");
+        fs::create_dir_all(&root).expect("create root");
+        let outside = tmp.path().join("outside.txt");
+        fs::write(&outside, "keep\n").expect("write outside");
+        let mut app = new_app(&root);
+
+        app.delete_path(outside.clone())
+            .expect("delete outside should be non-fatal");
+        assert!(outside.exists());
+        assert_eq!(app.status, "Cannot delete paths outside the project");
+
+        app.apply_prompt(
+            PromptMode::Rename {
+                target: outside.clone(),
+            },
+            "moved.txt".to_string(),
+        )
+        .expect("rename outside should be non-fatal");
+        assert!(outside.exists());
+        assert_eq!(app.status, "Cannot rename paths outside the project");
+    }
+
     #[test]
     fn apply_context_action_rejects_rename_root() {
         let tmp = tempdir().expect("tempdir");
@@ -626,6 +1802,187 @@ mod tests {
         }
     }
 
+    #[test]
+    fn refresh_tree_rereads_only_the_folders_a_change_touched() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src")).expect("mkdir");
+        fs::write(root.join("src/x.txt"), "x").expect("write");
+        let mut app = new_app(root);
+        app.expanded.insert(root.join("src"));
+        app.refresh_tree().expect("refresh");
+        let listed = |app: &App, rel: &str| app.tree.iter().any(|i| i.path == root.join(rel));
+        assert!(listed(&app, "src/x.txt"));
+
+        fs::write(root.join("src/y.txt"), "y").expect("write");
+        fs::write(root.join("z.txt"), "z").expect("write");
+        app.invalidate_dir_listings(&[root.join("z.txt")]);
+        app.refresh_tree().expect("refresh");
+        assert!(listed(&app, "z.txt"));
+        assert!(!listed(&app, "src/y.txt"), "src was not re-read");
+
+        app.invalidate_dir_listings(&[root.join("src/y.txt")]);
+        app.refresh_tree().expect("refresh");
+        assert!(listed(&app, "src/y.txt"));
+
+        app.expanded.remove(&root.join("src"));
+        app.refresh_tree().expect("collapse");
+        assert!(!app.dir_listings.borrow().contains_key(&root.join("src")));
+    }
+
+    #[test]
+    fn duplicate_copies_beside_the_original_and_selects_the_copy() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let path = root.join("notes.txt");
+        fs::write(&path, "hello\n").expect("write file");
+        let mut app = new_app(root);
+        for expected in ["notes copy.txt", "notes copy 2.txt"] {
+            app.context_menu.target = Some(path.clone());
+            app.apply_context_action(ContextAction::Duplicate)
+                .expect("duplicate");
+            let copy = root.join(expected);
+            assert_eq!(fs::read_to_string(&copy).expect("read copy"), "hello\n");
+            assert_eq!(app.selected_item().map(|i| &i.path), Some(&copy));
+        }
+        assert_eq!(app.status, "Duplicated notes.txt as notes copy 2.txt");
+
+        app.clipboard = None;
+        app.context_menu.target = Some(path);
+        app.apply_context_action(ContextAction::CopyRelativePath)
+            .expect("copy path");
+        assert_eq!(app.status, "Clipboard unavailable");
+    }
+
+    #[test]
+    fn new_file_like_this_copies_the_header_imports_and_test_skeleton() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let source = root.join("parser.rs");
+        fs::write(
+            &source,
+            "//! Parser module.\n\nuse std::io;\nuse crate::{\n    a,\n    b,\n};\n\n\
+             pub fn parse() {}\n\n#[cfg(test)]\nmod tests {\n    use super::*;\n\n    \
+             #[test]\n    fn t() {}\n}\n",
+        )
+        .expect("write");
+        let mut app = new_app(root);
+        app.context_menu.target = Some(source.clone());
+        app.apply_context_action(ContextAction::NewFileLikeThis)
+            .expect("context");
+        let prompt = app.prompt.take().expect("name prompt");
+        assert_eq!(prompt.value, "parser.rs");
+        assert_eq!(prompt.cursor, "parser".len());
+
+        app.apply_prompt(prompt.mode, "lexer.rs".to_string())
+            .expect("create");
+        let created = root.join("lexer.rs");
+        assert_eq!(
+            fs::read_to_string(&created).expect("read"),
+            "//! Parser module.\n\nuse std::io;\nuse crate::{\n    a,\n    b,\n};\n\n\
+             #[cfg(test)]\nmod tests {\n    use super::*;\n}\n"
+        );
+        assert_eq!(app.tabs[app.active_tab].path, created);
+
+        assert_eq!(
+            file_boilerplate(
+                "#!/usr/bin/env python3\n# tool\nimport os\n\ndef main():\n    pass\n",
+                ".py"
+            ),
+            "#!/usr/bin/env python3\n# tool\nimport os\n"
+        );
+    }
+
+    #[test]
+    fn go_to_test_toggles_between_counterparts_and_offers_to_create() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src")).expect("mkdir");
+        let source = root.join("src/parser.rs");
+        fs::write(&source, "pub fn parse() {}\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(source.clone()).expect("open");
+
+        app.go_to_test_counterpart().expect("toggle");
+        let prompt = app.prompt.take().expect("create prompt");
+        assert_eq!(
+            prompt.value,
+            Path::new("tests").join("parser.rs").display().to_string()
+        );
+        app.apply_prompt(prompt.mode, prompt.value).expect("create");
+        let test = root.join("tests/parser.rs");
+        assert!(
+            fs::read_to_string(&test)
+                .expect("read")
+                .contains("fn parser_works()")
+        );
+        assert_eq!(app.open_path(), Some(&test));
+
+        app.go_to_test_counterpart().expect("back");
+        assert_eq!(app.open_path(), Some(&source));
+        app.go_to_test_counterpart().expect("forth");
+        assert_eq!(app.open_path(), Some(&test));
+
+        let pkg = root.join("pkg");
+        assert_eq!(
+            test_counterparts(root, &pkg.join("client_test.go")),
+            Some((true, vec![pkg.join("client.go")]))
+        );
+        assert_eq!(
+            test_counterparts(root, &pkg.join("module.py")).map(|(t, c)| (t, c[0].clone())),
+            Some((false, pkg.join("test_module.py")))
+        );
+    }
+
+    #[test]
+    fn batch_rename_previews_collisions_and_retargets_tabs() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let dir = root.join("notes");
+        fs::create_dir_all(&dir).expect("mkdir");
+        for name in ["a.txt", "b.txt", "b.md", "keep.log"] {
+            fs::write(dir.join(name), name).expect("write");
+        }
+        let mut app = new_app(root);
+        app.open_file(dir.join("a.txt")).expect("open");
+        let press = |code| KeyEvent::new(code, ratatui::crossterm::event::KeyModifiers::NONE);
+
+        // The pattern and its replacement are entered in one form; a bad
+        // regex keeps the form open.
+        app.open_batch_rename_form(dir.clone());
+        let keys = [KeyCode::Char('('), KeyCode::Tab, KeyCode::Char('.')];
+        for code in keys.into_iter().chain("md".chars().map(KeyCode::Char)) {
+            app.handle_key(press(code)).expect("type");
+        }
+        app.handle_key(press(KeyCode::Enter)).expect("submit");
+        assert!(app.batch_rename.is_none());
+        let form = app.form.as_mut().expect("form kept");
+        assert_eq!(form.text(1), ".md");
+        form.fields[0] = crate::types::FormField::text("Match", r"\.txt$");
+        app.handle_key(press(KeyCode::Enter)).expect("submit");
+        assert!(app.form.is_none());
+        let state = app.batch_rename.as_ref().expect("preview");
+        assert_eq!(state.entries.len(), 2);
+        assert_eq!(state.entries[0].to, dir.join("a.md"));
+        assert_eq!(state.entries[0].problem, None);
+        assert_eq!(state.entries[1].problem.as_deref(), Some("already exists"));
+        app.handle_key(press(KeyCode::Enter)).expect("enter");
+        assert!(app.batch_rename.is_some());
+        assert!(dir.join("b.txt").exists());
+        app.handle_key(press(KeyCode::Esc)).expect("esc");
+
+        app.preview_batch_rename(dir.clone(), r"^(\w)\.txt$", "${1}_note.txt")
+            .expect("preview");
+        app.handle_key(press(KeyCode::Enter)).expect("apply");
+        assert!(app.batch_rename.is_none());
+        assert!(dir.join("a_note.txt").exists());
+        assert!(dir.join("b_note.txt").exists());
+        assert!(!dir.join("a.txt").exists());
+        assert!(dir.join("b.md").exists() && dir.join("keep.log").exists());
+        assert_eq!(app.tabs[0].path, dir.join("a_note.txt"));
+        assert!(fs::read_dir(&dir).expect("read").count() == 4);
+    }
+
     #[test]
     fn rename_directory_retargets_descendant_open_tabs() {
         let tmp = tempdir().expect("tempdir");
@@ -701,6 +2058,103 @@ mod tests {
         assert!(!root.join("a").join("b.txt").exists());
     }
 
+    #[test]
+    fn rename_prompt_selects_stem_and_typing_keeps_extension() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("main.rs");
+        fs::write(&file, "fn main() {}\n").expect("write file");
+        let mut app = new_app(root);
+        app.context_menu.target = Some(file.clone());
+        app.apply_context_action(ContextAction::Rename)
+            .expect("open rename prompt");
+
+        let prompt = app.prompt.as_ref().expect("prompt");
+        assert_eq!((prompt.anchor, prompt.cursor), (Some(0), "main".len()));
+        for ch in "lib".chars() {
+            app.handle_paste(ch.to_string());
+        }
+        assert_eq!(app.prompt.as_ref().expect("prompt").value, "lib.rs");
+    }
+
+    #[test]
+    fn rename_reports_collisions_and_extension_changes() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("notes.txt");
+        fs::write(&file, "x\n").expect("write file");
+        fs::write(root.join("other.txt"), "y\n").expect("write other");
+        let mut app = new_app(root);
+
+        app.apply_prompt(
+            PromptMode::Rename {
+                target: file.clone(),
+            },
+            "other.txt".to_string(),
+        )
+        .expect("rename onto sibling should be non-fatal");
+        assert_eq!(app.status, "Name already exists: other.txt");
+        assert!(file.exists());
+
+        app.apply_prompt(
+            PromptMode::Rename {
+                target: file.clone(),
+            },
+            "notes.md".to_string(),
+        )
+        .expect("rename");
+        assert_eq!(
+            app.status,
+            "Renamed to notes.md (extension changes from .txt to .md)"
+        );
+        assert!(root.join("notes.md").exists());
+    }
+
+    #[test]
+    fn rename_allows_case_only_change() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("readme.md");
+        fs::write(&file, "x\n").expect("write file");
+        let mut app = new_app(root);
+
+        app.apply_prompt(PromptMode::Rename { target: file }, "README.md".to_string())
+            .expect("rename");
+        assert_eq!(app.status, "Renamed to README.md");
+    }
+
+    #[test]
+    fn case_only_rename_retargets_open_tabs_and_leaves_no_temp_file() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("Foo.rs");
+        fs::write(&file, "fn foo() {}\n").expect("write file");
+        let mut app = new_app(root);
+        app.open_file(file.clone()).expect("open");
+        app.pending = PendingAction::None;
+
+        let renamed = root.join("foo.rs");
+        assert!(is_case_only_rename(&file, &renamed));
+        assert!(!is_case_only_rename(&file, &root.join("bar.rs")));
+        assert!(!is_case_only_rename(&file, &root.join("src/foo.rs")));
+
+        app.apply_prompt(
+            PromptMode::Rename {
+                target: file.clone(),
+            },
+            "foo.rs".to_string(),
+        )
+        .expect("rename");
+        assert_eq!(app.status, "Renamed to foo.rs");
+        assert_eq!(app.tabs[0].path, renamed);
+        assert!(app.fs_full_refresh_pending);
+        let names: Vec<_> = fs::read_dir(root)
+            .expect("read dir")
+            .map(|e| e.expect("entry").file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["foo.rs"]);
+    }
+
     #[test]
     fn cached_file_list_populated_on_init() {
         let tmp = tempdir().expect("tempdir");
@@ -740,7 +2194,32 @@ mod tests {
             !app.file_picker_results.is_empty(),
             "should find main.rs via cached list"
         );
-        assert!(app.file_picker_results[0].ends_with("main.rs"));
+        assert!(app.file_picker_results[0].path.ends_with("main.rs"));
+    }
+
+    #[test]
+    fn changed_files_picker_keeps_git_order_and_filters() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let mut app = new_app(root);
+        app.changed_files = vec![
+            (root.join("src/newest.rs"), GitFileStatus::Modified),
+            (root.join("notes.md"), GitFileStatus::Untracked),
+            (root.join("src/older.rs"), GitFileStatus::Added),
+        ];
+        app.file_picker_changed_only = true;
+        app.refresh_file_picker_results();
+        assert_eq!(app.file_picker_results.len(), 3);
+        assert!(app.file_picker_results[0].path.ends_with("src/newest.rs"));
+
+        app.file_picker_query = "rs".to_string();
+        app.refresh_file_picker_results();
+        let names: Vec<_> = app
+            .file_picker_results
+            .iter()
+            .map(|i| i.path.file_name().expect("name").to_string_lossy())
+            .collect();
+        assert_eq!(names, vec!["newest.rs", "older.rs"]);
     }
 
     #[test]
@@ -757,4 +2236,306 @@ mod tests {
             "empty query should return all files"
         );
     }
+
+    #[test]
+    fn go_to_line_with_column_moves_cursor() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "alpha\nbeta\ngamma\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+
+        app.apply_prompt(PromptMode::GoToLine, "2:3".to_string())
+            .expect("go to line");
+
+        assert_eq!(app.tabs[app.active_tab].editor.cursor(), (1, 2));
+        assert_eq!(app.status, "Jumped to line 2:3");
+    }
+
+    #[test]
+    fn go_to_line_clamps_and_centers_view() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        let text: String = (0..100).map(|i| format!("line {i}\n")).collect();
+        fs::write(&file, text).expect("write");
+        let mut app = new_app(root);
+        app.editor_rect = ratatui::layout::Rect::new(0, 0, 80, 12);
+        app.open_file(file).expect("open");
+
+        app.apply_prompt(PromptMode::GoToLine, "50:999".to_string())
+            .expect("go to line");
+
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.cursor(), (49, 7));
+        // 10 inner rows: row 49 should sit in the middle of the viewport
+        assert_eq!(tab.editor_scroll_row, 44);
+    }
+
+    #[test]
+    fn go_to_line_rejects_invalid_input() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("test.txt");
+        fs::write(&file, "alpha\n").expect("write");
+        let mut app = new_app(root);
+        app.open_file(file).expect("open");
+
+        app.apply_prompt(PromptMode::GoToLine, "0".to_string())
+            .expect("go to line");
+        assert_eq!(app.status, "Line and column must be >= 1");
+
+        app.apply_prompt(PromptMode::GoToLine, "x".to_string())
+            .expect("go to line");
+        assert_eq!(app.status, "Invalid line number (use N or N:COL)");
+    }
+
+    #[test]
+    fn cut_paste_moves_folder_and_retargets_tabs() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let src = root.join("src_dir");
+        let dest = root.join("dest");
+        fs::create_dir_all(&src).expect("create src");
+        fs::create_dir_all(&dest).expect("create dest");
+        let file = src.join("a.rs");
+        fs::write(&file, "fn a() {}\n").expect("write a");
+        let mut app = new_app(root);
+        app.open_file(file.clone()).expect("open a");
+
+        app.context_menu.target = Some(src.clone());
+        app.apply_context_action(ContextAction::Cut).expect("cut");
+        app.context_menu.target = Some(dest.clone());
+        app.apply_context_action(ContextAction::Paste)
+            .expect("paste");
+
+        let moved = dest.join("src_dir").join("a.rs");
+        assert!(moved.exists());
+        assert!(!src.exists());
+        assert_eq!(app.tabs[0].path, moved);
+        assert!(app.tree_clipboard.is_none());
+    }
+
+    #[test]
+    fn copy_paste_into_same_folder_picks_free_name() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let dir = root.join("pkg");
+        fs::create_dir_all(dir.join("inner")).expect("create dirs");
+        fs::write(dir.join("inner").join("b.rs"), "b\n").expect("write b");
+        let mut app = new_app(root);
+
+        app.context_menu.target = Some(dir.clone());
+        app.apply_context_action(ContextAction::Copy).expect("copy");
+        app.context_menu.target = Some(root.to_path_buf());
+        app.apply_context_action(ContextAction::Paste)
+            .expect("paste");
+
+        assert!(dir.join("inner").join("b.rs").exists());
+        let copy = root.join("pkg copy").join("inner").join("b.rs");
+        assert_eq!(fs::read_to_string(copy).expect("read copy"), "b\n");
+        assert!(app.tree_clipboard.is_some());
+    }
+
+    #[test]
+    fn paste_over_existing_path_asks_before_overwriting() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let dest = root.join("dest");
+        fs::create_dir_all(&dest).expect("create dest");
+        let src = root.join("a.txt");
+        fs::write(&src, "new\n").expect("write src");
+        fs::write(dest.join("a.txt"), "old\n").expect("write existing");
+        let mut app = new_app(root);
+
+        app.context_menu.target = Some(src.clone());
+        app.apply_context_action(ContextAction::Cut).expect("cut");
+        app.context_menu.target = Some(dest.clone());
+        app.apply_context_action(ContextAction::Paste)
+            .expect("paste");
+
+        let PendingAction::PasteOverwrite(paste) = app.pending.clone() else {
+            panic!("expected pending overwrite");
+        };
+        assert_eq!(
+            fs::read_to_string(dest.join("a.txt")).expect("read"),
+            "old\n"
+        );
+        app.pending = PendingAction::None;
+        app.paste_path(paste).expect("overwrite");
+        assert_eq!(
+            fs::read_to_string(dest.join("a.txt")).expect("read"),
+            "new\n"
+        );
+        assert!(!src.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pasting_over_a_folder_keeps_symlinks_and_leaves_no_staging_copy() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let src = root.join("src").join("pkg");
+        fs::create_dir_all(&src).expect("create src");
+        fs::write(src.join("a.txt"), "a\n").expect("write a");
+        std::os::unix::fs::symlink("a.txt", src.join("link")).expect("symlink");
+        let dest = root.join("dest");
+        fs::create_dir_all(dest.join("pkg")).expect("create dest");
+        fs::write(dest.join("pkg").join("old.txt"), "old\n").expect("write old");
+        let mut app = new_app(root);
+
+        let to = dest.join("pkg");
+        app.paste_path(TreePaste {
+            from: src.clone(),
+            to: to.clone(),
+            cut: false,
+        })
+        .expect("paste");
+        assert!(!to.join("old.txt").exists());
+        assert_eq!(
+            fs::read_link(to.join("link")).expect("link copied"),
+            Path::new("a.txt")
+        );
+        assert_eq!(fs::read_dir(&dest).expect("read dest").count(), 1);
+        assert!(src.join("link").exists());
+    }
+
+    #[test]
+    fn paste_folder_into_itself_is_rejected() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let dir = root.join("dir");
+        fs::create_dir_all(dir.join("child")).expect("create dirs");
+        let mut app = new_app(root);
+
+        app.context_menu.target = Some(dir.clone());
+        app.apply_context_action(ContextAction::Cut).expect("cut");
+        app.context_menu.target = Some(dir.join("child"));
+        app.apply_context_action(ContextAction::Paste)
+            .expect("paste");
+
+        assert_eq!(app.status, "Cannot paste a folder into itself");
+        assert!(dir.join("child").is_dir());
+    }
+
+    #[test]
+    fn paste_over_the_folder_holding_the_source_is_rejected() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let inner = root.join("a").join("a");
+        fs::create_dir_all(&inner).expect("create dirs");
+        fs::write(inner.join("keep.txt"), "keep\n").expect("write");
+        let mut app = new_app(root);
+
+        app.context_menu.target = Some(inner.clone());
+        app.apply_context_action(ContextAction::Cut).expect("cut");
+        app.context_menu.target = Some(root.to_path_buf());
+        app.apply_context_action(ContextAction::Paste)
+            .expect("paste");
+        assert!(matches!(app.pending, PendingAction::None));
+        assert_eq!(
+            app.status,
+            "Cannot replace a folder with something inside it"
+        );
+
+        app.paste_path(TreePaste {
+            from: inner.clone(),
+            to: root.join("a"),
+            cut: true,
+        })
+        .expect("paste");
+        assert!(inner.join("keep.txt").exists());
+    }
+
+    #[test]
+    fn tree_hides_gitignored_entries_until_toggled() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("target/debug")).expect("create target");
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").expect("gitignore");
+        fs::write(root.join("main.rs"), "fn main() {}\n").expect("write main");
+        fs::write(root.join("run.log"), "noise\n").expect("write log");
+        let mut app = new_app(root);
+        app.show_ignored = false;
+        app.rebuild_tree().expect("rebuild");
+
+        let names: Vec<&str> = app.tree.iter().map(|i| i.name.as_str()).collect();
+        assert!(names.contains(&"main.rs"));
+        assert!(!names.contains(&"target"));
+        assert!(!names.contains(&"run.log"));
+
+        app.show_ignored = true;
+        app.rebuild_tree().expect("rebuild");
+        let log = app
+            .tree
+            .iter()
+            .find(|i| i.name == "run.log")
+            .expect("ignored file listed");
+        assert!(log.ignored);
+        assert!(app.tree.iter().any(|i| i.name == "target" && i.ignored));
+        assert!(app.tree.iter().any(|i| i.name == "main.rs" && !i.ignored));
+    }
+
+    #[test]
+    fn quick_open_skips_gitignored_files() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("dist")).expect("create dist");
+        fs::write(root.join(".gitignore"), "dist\n").expect("gitignore");
+        fs::write(root.join("dist/bundle.js"), "x\n").expect("write bundle");
+        fs::write(root.join("app.js"), "y\n").expect("write app");
+        let mut app = new_app(root);
+
+        app.refresh_file_picker_results();
+
+        assert!(app.cached_file_list.iter().any(|p| p.ends_with("app.js")));
+        assert!(
+            !app.cached_file_list
+                .iter()
+                .any(|p| p.ends_with("bundle.js"))
+        );
+    }
+
+    #[test]
+    fn pinned_entries_lead_the_tree_and_open_by_number() {
+        use ratatui::crossterm::event::KeyModifiers;
+
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("src")).expect("mkdir");
+        fs::create_dir_all(root.join("docs")).expect("mkdir");
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").expect("write");
+        let mut app = new_app(root);
+        for path in [root.join("src/main.rs"), root.join("docs")] {
+            app.context_menu.target = Some(path);
+            app.apply_context_action(ContextAction::TogglePin)
+                .expect("pin");
+        }
+        assert_eq!(
+            app.pinned_paths[root],
+            [root.join("src/main.rs"), root.join("docs")]
+        );
+        let names: Vec<_> = app.tree.iter().take(3).map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["Pinned", "src/main.rs", "docs"]);
+        assert!(app.tree[..3].iter().all(|i| i.pinned));
+
+        let press = |app: &mut App, c| {
+            app.handle_tree_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .expect("key")
+        };
+        press(&mut app, '1');
+        assert_eq!(app.open_path(), Some(&root.join("src/main.rs")));
+        press(&mut app, '2');
+        let item = app.selected_item().expect("selected");
+        assert!(item.path == root.join("docs") && !item.pinned);
+
+        app.delete_path(root.join("docs")).expect("delete");
+        assert_eq!(app.pinned_paths[root], [root.join("src/main.rs")]);
+        app.context_menu.target = Some(root.join("src/main.rs"));
+        app.apply_context_action(ContextAction::TogglePin)
+            .expect("unpin");
+        assert!(!app.pinned_paths.contains_key(root));
+        assert!(!app.tree.iter().any(|i| i.pinned));
+    }
 }
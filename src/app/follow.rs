@@ -0,0 +1,227 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::Rect;
+use ratatui::style::Style;
+use ratatui_textarea::{CursorMove, TextArea};
+
+use super::{App, FollowBroadcast, FollowView};
+use crate::persistence::{FollowSnapshot, follow_file_path, write_follow_snapshot};
+use crate::tab::Tab;
+use crate::ui::Gutter;
+use crate::util::{display_width_of_range, text_to_lines, to_u16_saturating};
+
+impl App {
+    /// How often a broadcasting session checks its buffer for changes.
+    const FOLLOW_BROADCAST_INTERVAL: Duration = Duration::from_millis(150);
+
+    pub(crate) fn toggle_follow_broadcast(&mut self) -> io::Result<()> {
+        if self.follow_broadcast.is_some() {
+            self.stop_follow_broadcast();
+            return Ok(());
+        }
+        self.start_follow_broadcast(follow_file_path())
+    }
+
+    /// Start writing the active buffer and cursor to `target` whenever they
+    /// change, for another session to mirror with "Follow Another Session".
+    pub(crate) fn start_follow_broadcast(&mut self, target: PathBuf) -> io::Result<()> {
+        let status = format!("Broadcasting the current buffer to {}", target.display());
+        self.follow_broadcast = Some(FollowBroadcast {
+            target,
+            last: String::new(),
+            checked_at: None,
+        });
+        self.poll_follow_broadcast()?;
+        self.set_status(status);
+        Ok(())
+    }
+
+    /// Stop broadcasting and remove the snapshot so followers go back to
+    /// waiting.
+    pub(crate) fn stop_follow_broadcast(&mut self) {
+        let Some(broadcast) = self.follow_broadcast.take() else {
+            return;
+        };
+        let _ = fs::remove_file(&broadcast.target);
+        self.set_status("Stopped broadcasting");
+    }
+
+    /// Mirror the broadcast at `source` read-only until Esc.
+    pub(crate) fn follow_session(&mut self, source: PathBuf) {
+        let status = format!("Following {} - Esc to stop", source.display());
+        self.follow_view = Some(FollowView {
+            source,
+            root: PathBuf::new(),
+            tab: None,
+            stamp: None,
+        });
+        self.poll_follow_view();
+        self.set_status(status);
+    }
+
+    /// Write the broadcast snapshot and refresh the follow view; called
+    /// once per frame.
+    pub(crate) fn poll_follow(&mut self) -> io::Result<()> {
+        self.poll_follow_view();
+        self.poll_follow_broadcast()
+    }
+
+    fn poll_follow_broadcast(&mut self) -> io::Result<()> {
+        let Some(broadcast) = self.follow_broadcast.as_ref() else {
+            return Ok(());
+        };
+        if broadcast
+            .checked_at
+            .is_some_and(|at| at.elapsed() < Self::FOLLOW_BROADCAST_INTERVAL)
+        {
+            return Ok(());
+        }
+        let snapshot = match self.active_tab() {
+            Some(tab) => FollowSnapshot {
+                root: self.root.clone(),
+                path: tab.path.clone(),
+                cursor: tab.editor.cursor(),
+                text: tab.editor.lines().join("\n"),
+            },
+            None => FollowSnapshot {
+                root: self.root.clone(),
+                path: PathBuf::new(),
+                cursor: (0, 0),
+                text: String::new(),
+            },
+        };
+        let raw = serde_json::to_string(&snapshot)
+            .map_err(|e| io::Error::other(format!("serialize follow snapshot: {e}")))?;
+        let Some(broadcast) = self.follow_broadcast.as_mut() else {
+            return Ok(());
+        };
+        broadcast.checked_at = Some(Instant::now());
+        if raw == broadcast.last {
+            return Ok(());
+        }
+        if let Err(err) = write_follow_snapshot(&broadcast.target, &raw) {
+            self.follow_broadcast = None;
+            return Err(err);
+        }
+        broadcast.last = raw;
+        Ok(())
+    }
+
+    /// Reload the follow view when the broadcast file's mtime or size
+    /// changes; a missing or unreadable file leaves it waiting.
+    fn poll_follow_view(&mut self) {
+        let cursor_line = Style::default().bg(self.active_theme().bg_alt);
+        let selection = Style::default().bg(self.active_theme().selection);
+        let Some(view) = self.follow_view.as_mut() else {
+            return;
+        };
+        let stamp = fs::metadata(&view.source)
+            .ok()
+            .and_then(|meta| Some((meta.modified().ok()?, meta.len())));
+        if stamp == view.stamp {
+            return;
+        }
+        view.stamp = stamp;
+        let snapshot = fs::read_to_string(&view.source)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<FollowSnapshot>(&raw).ok());
+        let Some(snapshot) = snapshot else {
+            view.tab = None;
+            return;
+        };
+        let mut ta = TextArea::from(text_to_lines(&snapshot.text));
+        ta.set_cursor_line_style(cursor_line);
+        ta.set_selection_style(selection);
+        let (row, col) = snapshot.cursor;
+        ta.move_cursor(CursorMove::Jump(
+            to_u16_saturating(row),
+            to_u16_saturating(col),
+        ));
+        let mut tab = Tab::new(snapshot.path, ta);
+        tab.read_only = true;
+        view.root = snapshot.root;
+        view.tab = Some(tab);
+    }
+
+    /// Scroll the follow view so the broadcaster's cursor sits in the
+    /// middle of an editor area of `inner`.
+    pub(crate) fn center_follow_cursor(&mut self, inner: Rect) {
+        let Some(tab) = self.follow_view.as_mut().and_then(|v| v.tab.as_mut()) else {
+            return;
+        };
+        let (row, col) = tab.editor.cursor();
+        let visible = tab.visible_index_of_source_position(row, col);
+        tab.editor_scroll_row = visible.saturating_sub(inner.height as usize / 2);
+        let width = inner.width.saturating_sub(Gutter::ALL.width()) as usize;
+        let display_col = tab
+            .editor
+            .lines()
+            .get(row)
+            .map_or(0, |l| display_width_of_range(l, 0, col, tab.tab_width()));
+        tab.editor_scroll_col = display_col.saturating_sub(width.saturating_sub(1));
+    }
+
+    pub(crate) fn handle_follow_view_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        if matches!(key.code, KeyCode::Esc | KeyCode::Char('q')) {
+            self.follow_view = None;
+            self.set_status("Stopped following");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+    use tempfile::tempdir;
+
+    #[test]
+    fn follower_mirrors_the_broadcast_buffer_and_cursor() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path().join("project");
+        fs::create_dir(&root).expect("mkdir");
+        let file = root.join("main.rs");
+        fs::write(&file, "fn main() {\n    println!(\"hi\");\n}\n").expect("write");
+        let target = tmp.path().join("state").join("follow.json");
+
+        let mut host = App::new_for_test(&root);
+        host.open_file(file.clone()).expect("open");
+        host.start_follow_broadcast(target.clone())
+            .expect("broadcast");
+        let mut guest = App::new_for_test(&root);
+        guest.follow_session(target.clone());
+        let view = guest.follow_view.as_ref().expect("follow view");
+        let tab = view.tab.as_ref().expect("snapshot");
+        assert_eq!(tab.path, file);
+        assert_eq!(tab.editor.lines()[1], "    println!(\"hi\");");
+        assert!(tab.read_only);
+
+        let host_tab = host.active_tab_mut().expect("tab");
+        host_tab.editor.move_cursor(CursorMove::Jump(1, 4));
+        host_tab.editor.insert_str("let x = 1; ");
+        host.follow_broadcast
+            .as_mut()
+            .expect("broadcast")
+            .checked_at = None;
+        host.poll_follow().expect("poll");
+        guest.poll_follow().expect("poll");
+        let tab = guest.follow_view.as_ref().and_then(|v| v.tab.as_ref());
+        let tab = tab.expect("snapshot");
+        assert_eq!(tab.editor.lines()[1], "    let x = 1; println!(\"hi\");");
+        assert_eq!(tab.editor.cursor(), (1, 15));
+
+        host.stop_follow_broadcast();
+        assert!(!target.exists());
+        guest.poll_follow().expect("poll");
+        assert!(guest.follow_view.as_ref().expect("view").tab.is_none());
+        guest
+            .handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("esc");
+        assert!(guest.follow_view.is_none());
+    }
+}
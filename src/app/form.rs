@@ -0,0 +1,205 @@
+use std::io;
+use std::path::PathBuf;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use regex::Regex;
+
+use super::App;
+use crate::types::{FormField, FormKind, FormState};
+use crate::util::{is_text_input, relative_path};
+
+impl App {
+    /// Batch rename as one form: the regex and its replacement together.
+    pub(crate) fn open_batch_rename_form(&mut self, dir: PathBuf) {
+        self.form = Some(FormState::new(
+            format!(
+                "Batch rename files in {}",
+                relative_path(&self.root, &dir).display()
+            ),
+            FormKind::BatchRename { dir },
+            vec![
+                FormField::text("Match (regex)", ""),
+                FormField::text("Rename to ($1 for groups)", ""),
+            ],
+        ));
+    }
+
+    /// "Search in Project with Options": the query, file globs and search
+    /// flags in one form.
+    pub(crate) fn open_project_search_form(&mut self) {
+        let options = &self.project_search_options;
+        let globs = self.search_results.options.globs.join(" ");
+        let fields = vec![
+            FormField::text("Search for", &self.search_results.query),
+            FormField::text("Files (globs, ! excludes)", &globs),
+            FormField::checkbox("Match case", options.case_sensitive),
+            FormField::checkbox("Whole word", options.whole_word),
+            FormField::checkbox("Regex", !options.literal),
+        ];
+        self.form = Some(FormState::new(
+            "Search in Project".to_string(),
+            FormKind::ProjectSearch,
+            fields,
+        ));
+    }
+
+    pub(crate) fn handle_form_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(form) = self.form.as_mut() else {
+            return Ok(());
+        };
+        let ok = form.fields.len();
+        let stops = ok + 2;
+        match (key.modifiers, key.code, form.fields.get_mut(form.focus)) {
+            (_, KeyCode::Esc, _) => {
+                self.form = None;
+                self.set_status("Canceled");
+            }
+            (_, KeyCode::Tab | KeyCode::Down, _) => form.focus = (form.focus + 1) % stops,
+            (_, KeyCode::BackTab | KeyCode::Up, _) => {
+                form.focus = (form.focus + stops - 1) % stops;
+            }
+            (_, KeyCode::Enter | KeyCode::Char(' '), None) if form.focus > ok => {
+                self.form = None;
+                self.set_status("Canceled");
+            }
+            (_, KeyCode::Enter, _) | (_, KeyCode::Char(' '), None) => self.submit_form()?,
+            (_, KeyCode::Char(' '), Some(FormField::Checkbox { checked, .. })) => {
+                *checked = !*checked;
+            }
+            (mods, code, Some(FormField::Text { value, cursor, .. })) => {
+                edit_form_text(value, cursor, mods, code);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Paste into the focused text field; only the first line is taken.
+    pub(crate) fn paste_into_form(&mut self, line: &str) {
+        let Some(form) = self.form.as_mut() else {
+            return;
+        };
+        if let Some(FormField::Text { value, cursor, .. }) = form.fields.get_mut(form.focus) {
+            value.insert_str(*cursor, line);
+            *cursor += line.len();
+        }
+    }
+
+    fn submit_form(&mut self) -> io::Result<()> {
+        let Some(form) = self.form.take() else {
+            return Ok(());
+        };
+        match &form.kind {
+            FormKind::BatchRename { dir } => {
+                let pattern = form.text(0);
+                if pattern.is_empty() {
+                    self.set_warning("Enter a regex to match file names");
+                } else if let Err(err) = Regex::new(pattern) {
+                    self.set_warning(format!("Invalid regex: {err}"));
+                } else {
+                    return self.preview_batch_rename(dir.clone(), pattern, form.text(1));
+                }
+            }
+            FormKind::ProjectSearch => {
+                let query = form.text(0).trim();
+                if query.is_empty() {
+                    self.set_warning("Search query is empty");
+                } else {
+                    let options = &mut self.project_search_options;
+                    options.case_sensitive = form.checked(2);
+                    options.whole_word = form.checked(3);
+                    options.literal = !form.checked(4);
+                    let globs = form.text(1).split_whitespace().map(String::from).collect();
+                    self.run_project_search(query, globs);
+                    return Ok(());
+                }
+            }
+        }
+        // Keep what was typed so the problem can be fixed.
+        self.form = Some(FormState { focus: 0, ..form });
+        Ok(())
+    }
+}
+
+/// Line editing in a form's text field; `cursor` stays on char boundaries.
+fn edit_form_text(value: &mut String, cursor: &mut usize, mods: KeyModifiers, code: KeyCode) {
+    match code {
+        KeyCode::Backspace => {
+            if let Some(ch) = value[..*cursor].chars().next_back() {
+                *cursor -= ch.len_utf8();
+                value.remove(*cursor);
+            }
+        }
+        KeyCode::Delete if *cursor < value.len() => {
+            value.remove(*cursor);
+        }
+        KeyCode::Left => {
+            if let Some(ch) = value[..*cursor].chars().next_back() {
+                *cursor -= ch.len_utf8();
+            }
+        }
+        KeyCode::Right => {
+            if let Some(ch) = value[*cursor..].chars().next() {
+                *cursor += ch.len_utf8();
+            }
+        }
+        KeyCode::Home => *cursor = 0,
+        KeyCode::End => *cursor = value.len(),
+        KeyCode::Char(c) if is_text_input(mods) => {
+            value.insert(*cursor, c);
+            *cursor += c.len_utf8();
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            app.handle_key(press(KeyCode::Char(c))).expect("type");
+        }
+    }
+
+    #[test]
+    fn form_moves_between_fields_toggles_and_submits() {
+        let tmp = tempdir().expect("tempdir");
+        fs::write(tmp.path().join("a.txt"), "Needle\nneedle\n").expect("write");
+        fs::write(tmp.path().join("b.md"), "needle\n").expect("write");
+        let mut app = App::new_for_test(tmp.path());
+        app.open_project_search_form();
+
+        type_text(&mut app, "needle");
+        app.handle_key(press(KeyCode::Tab)).expect("tab");
+        type_text(&mut app, "*.txt");
+        app.handle_key(press(KeyCode::Tab)).expect("tab");
+        app.handle_key(press(KeyCode::Char(' '))).expect("toggle");
+        let form = app.form.as_ref().expect("form");
+        assert_eq!(form.text(1), "*.txt");
+        assert!(form.checked(2));
+
+        // Shift+Tab from the first field wraps round to Cancel.
+        app.form.as_mut().expect("form").focus = 0;
+        app.handle_key(press(KeyCode::BackTab)).expect("backtab");
+        assert_eq!(app.form.as_ref().expect("form").focus, 6);
+        app.handle_key(press(KeyCode::Up)).expect("up");
+        app.handle_key(press(KeyCode::Enter)).expect("ok");
+        assert!(app.form.is_none());
+        while app.search_results.running.is_some() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            app.poll_project_search();
+        }
+        assert!(app.project_search_options.case_sensitive);
+        assert_eq!(app.search_results.options.globs, ["*.txt"]);
+        assert_eq!(app.search_results.results.len(), 1);
+        assert_eq!(app.search_results.results[0].line, 2);
+    }
+}
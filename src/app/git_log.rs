@@ -0,0 +1,235 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use super::{App, DiffViewState, GitLogState};
+use crate::diff::unified_diff;
+use crate::git_log::{GitCommit, git_commit_details, git_file_at, git_log, revision_path};
+use crate::util::{relative_path, text_to_lines};
+
+impl App {
+    /// "Git Log: Project": the project's commits.
+    pub(crate) fn open_git_log(&mut self) {
+        self.show_git_log(None);
+    }
+
+    /// "Git Log: Current File": the commits that changed the active file.
+    pub(crate) fn open_git_file_history(&mut self) {
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return;
+        };
+        if tab.read_only || !tab.path.starts_with(&self.root) {
+            self.set_warning("The file is not part of the project");
+            return;
+        }
+        let path = tab.path.clone();
+        self.show_git_log(Some(path));
+    }
+
+    fn show_git_log(&mut self, file: Option<PathBuf>) {
+        let commits = match git_log(&self.root, file.as_deref()) {
+            Ok(commits) => commits,
+            Err(err) => {
+                self.set_error(format!("Could not read the git log: {err}"));
+                return;
+            }
+        };
+        if commits.is_empty() {
+            self.set_status(match &file {
+                Some(path) => format!(
+                    "No commits touch {}",
+                    relative_path(&self.root, path).display()
+                ),
+                None => "No commits yet".to_string(),
+            });
+            return;
+        }
+        self.git_log = Some(GitLogState {
+            file,
+            commits,
+            selected: 0,
+            details: None,
+            details_scroll: 0,
+        });
+    }
+
+    pub(crate) fn handle_git_log_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(log) = self.git_log.as_mut() else {
+            return Ok(());
+        };
+        let last = log.commits.len().saturating_sub(1);
+        match (key.code, log.details.is_some()) {
+            (KeyCode::Esc, true) => log.details = None,
+            (KeyCode::Esc | KeyCode::Char('q'), _) => self.git_log = None,
+            (KeyCode::Down | KeyCode::Char('j'), true) => log.details_scroll += 1,
+            (KeyCode::Up | KeyCode::Char('k'), true) => {
+                log.details_scroll = log.details_scroll.saturating_sub(1);
+            }
+            (KeyCode::Down | KeyCode::Char('j'), false) => {
+                log.selected = (log.selected + 1).min(last);
+            }
+            (KeyCode::Up | KeyCode::Char('k'), false) => {
+                log.selected = log.selected.saturating_sub(1);
+            }
+            (KeyCode::Enter, true) => log.details = None,
+            (KeyCode::Enter, false) => {
+                let commit = &log.commits[log.selected];
+                log.details = Some(git_commit_details(&self.root, commit));
+                log.details_scroll = 0;
+            }
+            (KeyCode::Char('o'), _) => self.open_revision()?,
+            (KeyCode::Char('d'), _) => self.diff_revision(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The selected commit and the file `o` and `d` act on: the file whose
+    /// history is shown, or the active file in the project log.
+    fn git_log_target(&mut self) -> Option<(GitCommit, PathBuf)> {
+        let log = self.git_log.as_ref()?;
+        let commit = log.commits.get(log.selected)?.clone();
+        let file = log.file.clone().or_else(|| {
+            self.active_tab()
+                .filter(|t| !t.read_only && t.path.starts_with(&self.root))
+                .map(|t| t.path.clone())
+        });
+        if file.is_none() {
+            self.reject_action("Open a project file to see it at a commit");
+        }
+        Some((commit, file?))
+    }
+
+    /// The target file's text at the selected commit, warning when it did
+    /// not exist there.
+    fn git_log_revision_text(&mut self) -> Option<(GitCommit, PathBuf, String)> {
+        let (commit, file) = self.git_log_target()?;
+        let rel = relative_path(&self.root, &file);
+        let Some(text) = git_file_at(&self.root, &commit, &rel) else {
+            self.set_warning(format!(
+                "{} does not exist in {}",
+                rel.display(),
+                commit.short_hash()
+            ));
+            return None;
+        };
+        Some((commit, file, text))
+    }
+
+    /// Open the target file as of the selected commit in a read-only tab.
+    fn open_revision(&mut self) -> io::Result<()> {
+        let Some((commit, file, text)) = self.git_log_revision_text() else {
+            return Ok(());
+        };
+        let rel = relative_path(&self.root, &file);
+        let path = revision_path(&rel, &commit);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, text)?;
+        self.git_log = None;
+        self.open_external_file(path)?;
+        self.set_status(format!(
+            "{} at {} {} (read-only)",
+            rel.display(),
+            commit.short_hash(),
+            commit.subject
+        ));
+        Ok(())
+    }
+
+    /// Diff the target file as of the selected commit against its buffer,
+    /// or the file on disk when it is not open.
+    fn diff_revision(&mut self) {
+        let Some((commit, file, text)) = self.git_log_revision_text() else {
+            return;
+        };
+        let current = match self.tabs.iter().find(|t| t.path == file) {
+            Some(tab) => tab.editor.lines().to_vec(),
+            None => text_to_lines(&fs::read_to_string(&file).unwrap_or_default()),
+        };
+        let rows = unified_diff(&text_to_lines(&text), &current, 3);
+        if rows.is_empty() {
+            self.set_status(format!("No changes since {}", commit.short_hash()));
+            return;
+        }
+        self.git_log = None;
+        self.diff_view = Some(DiffViewState {
+            path: file,
+            rows,
+            scroll: 0,
+            legend: "- commit, + current",
+        });
+        self.set_status(format!(
+            "Comparing with {} {}",
+            commit.short_hash(),
+            commit.subject
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+    use std::path::Path;
+    use std::process::Command;
+    use tempfile::tempdir;
+
+    fn git(dir: &Path, args: &[&str]) -> bool {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=t", "-c", "user.email=t@t"])
+            .args(args)
+            .output()
+            .is_ok_and(|o| o.status.success())
+    }
+
+    #[test]
+    fn file_history_opens_and_diffs_older_revisions() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let file = root.join("notes.txt");
+        fs::write(&file, "one\n").expect("write");
+        if !git(root, &["init", "-q"]) || !git(root, &["add", "."]) {
+            return;
+        }
+        assert!(git(root, &["commit", "-qm", "First"]));
+        fs::write(&file, "one\ntwo\n").expect("write");
+        assert!(git(root, &["commit", "-qam", "Second"]));
+        let mut app = App::new_for_test(root);
+        app.open_file(file.clone()).expect("open");
+
+        app.open_git_file_history();
+        let log = app.git_log.as_ref().expect("log");
+        let subjects: Vec<_> = log.commits.iter().map(|c| c.subject.as_str()).collect();
+        assert_eq!(subjects, ["Second", "First"]);
+
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        app.handle_key(press(KeyCode::Down)).expect("down");
+        app.handle_key(press(KeyCode::Enter)).expect("details");
+        let details = app.git_log.as_ref().and_then(|l| l.details.clone());
+        assert!(details.is_some_and(|d| d.iter().any(|l| l.contains("First"))));
+        app.handle_key(press(KeyCode::Esc)).expect("close details");
+        assert!(app.git_log.as_ref().is_some_and(|l| l.details.is_none()));
+
+        app.handle_key(press(KeyCode::Char('d'))).expect("diff");
+        assert!(app.git_log.is_none());
+        let diff = app.diff_view.take().expect("diff");
+        assert!(diff.rows.iter().any(|(_, text)| text == "+two"));
+
+        app.open_git_file_history();
+        app.handle_key(press(KeyCode::Down)).expect("down");
+        app.handle_key(press(KeyCode::Char('o')))
+            .expect("open revision");
+        let tab = app.active_tab().expect("tab");
+        assert!(tab.read_only);
+        let name = tab.path.file_name().expect("name").to_string_lossy();
+        assert!(name.starts_with("notes@") && name.ends_with(".txt"));
+        assert_eq!(tab.editor.lines(), ["one", ""]);
+    }
+}
@@ -0,0 +1,362 @@
+use std::io;
+use std::path::PathBuf;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use super::{App, DiffViewState, HeadDiffState};
+use crate::diff::{
+    diff_hunks, diff_lines, merge_hunks, revert_hunk_at, side_by_side_rows, unified_diff,
+};
+use crate::types::PendingAction;
+use crate::util::{git_head_text, relative_path, text_to_lines, uses_crlf};
+
+impl HeadDiffState {
+    /// Unchanged lines kept in view above a hunk jumped to.
+    const HUNK_CONTEXT: usize = 3;
+
+    /// Select hunk `idx`, or the last one, and scroll it into view.
+    fn select_hunk(&mut self, idx: usize) {
+        self.selected = idx.min(self.hunks.len().saturating_sub(1));
+        let first = self
+            .rows
+            .iter()
+            .position(|r| r.hunk == Some(self.selected))
+            .unwrap_or(0);
+        self.scroll = first.saturating_sub(Self::HUNK_CONTEXT);
+    }
+}
+
+impl App {
+    /// The active file's text at HEAD, warning when there is none.
+    fn active_head_text(&mut self) -> Option<String> {
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return None;
+        };
+        if tab.large_file.is_some() {
+            self.set_warning("Reverting is not available for partially loaded files");
+            return None;
+        }
+        let path = tab.path.clone();
+        let text = git_head_text(&path);
+        if text.is_none() {
+            self.set_warning(format!(
+                "{} is not committed in HEAD",
+                relative_path(&self.root, &path).display()
+            ));
+        }
+        text
+    }
+
+    /// "Revert File to HEAD": ask first, with the diff one key away.
+    pub(crate) fn revert_file_to_head(&mut self) {
+        if self.refuse_read_only_edit() {
+            return;
+        }
+        let Some(head) = self.active_head_text() else {
+            return;
+        };
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        if tab.editor.lines() == text_to_lines(&head) && !tab.dirty {
+            self.set_status("File matches HEAD");
+            return;
+        }
+        self.set_pending(PendingAction::RevertToHead(tab.path.clone()));
+    }
+
+    /// Show what reverting to HEAD would change in the buffer.
+    pub(crate) fn open_head_diff(&mut self) {
+        let Some(head) = self.active_head_text() else {
+            return;
+        };
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        let rows = unified_diff(tab.editor.lines(), &text_to_lines(&head), 3);
+        if rows.is_empty() {
+            self.set_status("Buffer matches HEAD");
+            return;
+        }
+        self.diff_view = Some(DiffViewState {
+            path: tab.path.clone(),
+            rows,
+            scroll: 0,
+            legend: "- buffer, + HEAD",
+        });
+    }
+
+    /// Replace the buffer with the file at HEAD and save it. The swap is an
+    /// edit, so undo brings the changes back.
+    pub(crate) fn apply_revert_to_head(&mut self, path: PathBuf) -> io::Result<()> {
+        if self.open_path() != Some(&path) {
+            self.set_warning("The file to revert is no longer the active tab");
+            return Ok(());
+        }
+        let Some(head) = self.active_head_text() else {
+            return Ok(());
+        };
+        let lines = text_to_lines(&head);
+        let (row, col) = self.tabs[self.active_tab].editor.cursor();
+        let row = row.min(lines.len().saturating_sub(1));
+        let col = col.min(lines[row].chars().count());
+        self.apply_edit_transaction(&lines, (row, col));
+        if let Some(tab) = self.active_tab_mut() {
+            tab.crlf = uses_crlf(&head);
+        }
+        self.save_file()?;
+        self.set_status(format!(
+            "Reverted {} to HEAD",
+            relative_path(&self.root, &path).display()
+        ));
+        Ok(())
+    }
+
+    /// "Revert Hunk to HEAD": undo the change the cursor line is part of,
+    /// as marked in the git gutter, in the buffer only.
+    pub(crate) fn revert_hunk_at_cursor(&mut self) {
+        if self.refuse_read_only_edit() {
+            return;
+        }
+        let Some(head) = self.active_head_text() else {
+            return;
+        };
+        let tab = &self.tabs[self.active_tab];
+        let row = tab.editor.cursor().0;
+        let Some((lines, start)) = revert_hunk_at(tab.editor.lines(), &text_to_lines(&head), row)
+        else {
+            self.set_status("No change from HEAD on this line");
+            return;
+        };
+        self.apply_edit_transaction(&lines, (start, 0));
+        self.clear_dirty_if_on_disk();
+        self.set_status(format!("Reverted the change at line {}", start + 1));
+    }
+
+    /// Reverting the only unsaved change leaves the buffer as on disk.
+    fn clear_dirty_if_on_disk(&mut self) {
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        let on_disk = tab
+            .open_disk_snapshot
+            .as_deref()
+            .is_some_and(|disk| text_to_lines(disk) == tab.editor.lines());
+        if on_disk {
+            tab.dirty = false;
+            self.clear_autosave_for_open_file();
+        }
+    }
+
+    /// "Diff with HEAD": the committed file and the buffer side by side.
+    pub(crate) fn open_head_diff_view(&mut self) {
+        let Some(head) = self.active_head_text() else {
+            return;
+        };
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        match Self::head_diff_state(tab.path.clone(), text_to_lines(&head), tab.editor.lines()) {
+            Some(state) => self.head_diff = Some(state),
+            None => self.set_status("Buffer matches HEAD"),
+        }
+    }
+
+    /// The side-by-side diff of `buffer` with `head`; `None` when they match.
+    fn head_diff_state(
+        path: PathBuf,
+        head: Vec<String>,
+        buffer: &[String],
+    ) -> Option<HeadDiffState> {
+        let script = diff_lines(buffer, &head);
+        let hunks = diff_hunks(&script, 0);
+        if hunks.is_empty() {
+            return None;
+        }
+        Some(HeadDiffState {
+            path,
+            head,
+            buffer: buffer.to_vec(),
+            hunks,
+            rows: side_by_side_rows(&script),
+            selected: 0,
+            scroll: 0,
+        })
+    }
+
+    pub(crate) fn handle_head_diff_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        const PAGE_ROWS: usize = 20;
+        let Some(diff) = self.head_diff.as_mut() else {
+            return Ok(());
+        };
+        let last_row = diff.rows.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.head_diff = None;
+                return Ok(());
+            }
+            KeyCode::Down | KeyCode::Char('j') => diff.scroll += 1,
+            KeyCode::Up | KeyCode::Char('k') => diff.scroll = diff.scroll.saturating_sub(1),
+            KeyCode::PageDown => diff.scroll += PAGE_ROWS,
+            KeyCode::PageUp => diff.scroll = diff.scroll.saturating_sub(PAGE_ROWS),
+            KeyCode::Char('n') | KeyCode::Char(']') => diff.select_hunk(diff.selected + 1),
+            KeyCode::Char('N') | KeyCode::Char('p') | KeyCode::Char('[') => {
+                diff.select_hunk(diff.selected.saturating_sub(1));
+            }
+            KeyCode::Char('r') => {
+                self.revert_head_diff_hunk();
+                return Ok(());
+            }
+            KeyCode::Enter => {
+                let row = diff.hunks[diff.selected].old_start;
+                self.head_diff = None;
+                if let Some(tab) = self.active_tab_mut() {
+                    let row = row.min(tab.editor.lines().len().saturating_sub(1));
+                    tab.jump_to((row, 0));
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+        diff.scroll = diff.scroll.min(last_row);
+        Ok(())
+    }
+
+    /// Take the selected hunk of "Diff with HEAD" back to HEAD in the
+    /// buffer, then show what is left.
+    fn revert_head_diff_hunk(&mut self) {
+        let Some(diff) = self.head_diff.as_ref() else {
+            return;
+        };
+        let take: Vec<bool> = (0..diff.hunks.len()).map(|i| i == diff.selected).collect();
+        let lines = merge_hunks(&diff.buffer, &diff.head, &diff.hunks, &take);
+        let start = diff.hunks[diff.selected].old_start;
+        let cursor = (start.min(lines.len().saturating_sub(1)), 0);
+        let (path, head, selected) = (diff.path.clone(), diff.head.clone(), diff.selected);
+        if self.open_path() != Some(&path) {
+            self.set_warning("The diffed file is no longer the active tab");
+            return;
+        }
+        if self.refuse_read_only_edit() {
+            return;
+        }
+        self.apply_edit_transaction(&lines, cursor);
+        self.clear_dirty_if_on_disk();
+        self.set_status(format!("Reverted the change at line {}", cursor.0 + 1));
+        let Some(tab) = self.active_tab() else {
+            return;
+        };
+        self.head_diff = Self::head_diff_state(path, head, tab.editor.lines()).map(|mut state| {
+            state.select_hunk(selected);
+            state
+        });
+        if self.head_diff.is_none() {
+            self.set_status("Buffer matches HEAD");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent};
+    use std::fs;
+    use std::process::Command;
+    use tempfile::tempdir;
+
+    fn git(dir: &std::path::Path, args: &[&str]) -> bool {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=t", "-c", "user.email=t@t"])
+            .args(args)
+            .output()
+            .is_ok_and(|o| o.status.success())
+    }
+
+    #[test]
+    fn hunk_and_file_revert_go_back_to_head() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("a.txt");
+        fs::write(&file, "one\ntwo\nthree\nfour\n").expect("write");
+        if !git(tmp.path(), &["init", "-q"]) || !git(tmp.path(), &["add", "."]) {
+            return;
+        }
+        assert!(git(tmp.path(), &["commit", "-qm", "init"]));
+        fs::write(&file, "ONE\ntwo\nthree\nfour\nfive\n").expect("write");
+        let mut app = App::new_for_test(tmp.path());
+        app.open_file(file.clone()).expect("open");
+
+        // Reverting the saved change at line 1 leaves the buffer dirty.
+        app.revert_hunk_at_cursor();
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[0], "one");
+        assert!(tab.dirty);
+        // Line 2 is unchanged, so there is nothing to revert there.
+        app.tabs[0].jump_to((1, 0));
+        app.revert_hunk_at_cursor();
+        assert_eq!(app.active_tab().expect("tab").editor.lines().len(), 6);
+
+        app.revert_file_to_head();
+        assert!(matches!(app.pending, PendingAction::RevertToHead(_)));
+        app.handle_key(KeyEvent::from(KeyCode::Char('v')))
+            .expect("diff");
+        let diff = app.diff_view.take().expect("diff");
+        assert!(diff.rows.iter().any(|(_, row)| row == "-five"));
+        app.handle_key(KeyEvent::from(KeyCode::Enter))
+            .expect("revert");
+        let tab = app.active_tab().expect("tab");
+        assert!(!tab.dirty);
+        assert_eq!(tab.editor.lines(), ["one", "two", "three", "four", ""]);
+        assert_eq!(
+            fs::read_to_string(&file).expect("read"),
+            "one\ntwo\nthree\nfour\n"
+        );
+    }
+
+    #[test]
+    fn head_diff_view_steps_through_and_reverts_hunks() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("a.txt");
+        fs::write(&file, "one\ntwo\nthree\nfour\nfive\nsix\n").expect("write");
+        if !git(tmp.path(), &["init", "-q"]) || !git(tmp.path(), &["add", "."]) {
+            return;
+        }
+        assert!(git(tmp.path(), &["commit", "-qm", "init"]));
+        fs::write(&file, "ONE\ntwo\nthree\nfour\nfive\n").expect("write");
+        let mut app = App::new_for_test(tmp.path());
+        app.open_file(file.clone()).expect("open");
+
+        app.open_head_diff_view();
+        let diff = app.head_diff.as_ref().expect("diff");
+        assert_eq!(diff.hunks.len(), 2);
+        let changed: Vec<_> = diff
+            .rows
+            .iter()
+            .filter(|r| r.hunk.is_some())
+            .map(|r| {
+                (
+                    r.new.map(|i| diff.head[i].as_str()),
+                    r.old.map(|i| diff.buffer[i].as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(changed, [(Some("one"), Some("ONE")), (Some("six"), None)]);
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('n')))
+            .expect("next hunk");
+        app.handle_key(KeyEvent::from(KeyCode::Char('r')))
+            .expect("revert");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines()[5], "six");
+        assert_eq!(tab.editor.lines()[0], "ONE");
+        assert_eq!(app.head_diff.as_ref().expect("diff").hunks.len(), 1);
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('r')))
+            .expect("revert");
+        assert!(app.head_diff.is_none());
+        assert_eq!(app.status, "Buffer matches HEAD");
+        assert_eq!(app.active_tab().expect("tab").editor.lines()[0], "one");
+    }
+}
@@ -0,0 +1,246 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::{App, HexEdit};
+use crate::keybinds::{KeyAction, KeyScope};
+use crate::types::Focus;
+use crate::util::relative_path;
+
+impl HexEdit {
+    /// Type one hex digit: the first sets the high half of the byte under
+    /// the cursor (inserting a byte in insert mode or at the end), the
+    /// second the low half, then the cursor moves on.
+    fn type_digit(&mut self, bytes: &mut Vec<u8>, digit: u8) {
+        if self.half_typed {
+            bytes[self.cursor] = (bytes[self.cursor] & 0xf0) | digit;
+            self.half_typed = false;
+            self.cursor += 1;
+        } else {
+            if self.insert || self.cursor == bytes.len() {
+                bytes.insert(self.cursor, digit << 4);
+            } else {
+                bytes[self.cursor] = (bytes[self.cursor] & 0x0f) | (digit << 4);
+            }
+            self.half_typed = true;
+        }
+        self.dirty = true;
+    }
+
+    /// Write a whole byte at the cursor, as typed in the ASCII column.
+    fn type_byte(&mut self, bytes: &mut Vec<u8>, byte: u8) {
+        if self.insert || self.cursor == bytes.len() {
+            bytes.insert(self.cursor, byte);
+        } else {
+            bytes[self.cursor] = byte;
+        }
+        self.cursor += 1;
+        self.half_typed = false;
+        self.dirty = true;
+    }
+}
+
+impl App {
+    /// Open the selected tree file, or else the active tab's file, in the
+    /// hex view with byte editing on.
+    pub(crate) fn open_hex_editor(&mut self) -> io::Result<()> {
+        let tree_file = self
+            .selected_item()
+            .filter(|item| self.focus == Focus::Tree && !item.is_dir)
+            .map(|item| item.path.clone());
+        let Some(path) = tree_file.or_else(|| self.active_tab().map(|t| t.path.clone())) else {
+            self.set_status("No file selected");
+            return Ok(());
+        };
+        if let Some(tab) = self.tabs.iter().find(|t| t.path == path && t.dirty) {
+            let name = relative_path(&self.root, &tab.path).display().to_string();
+            self.set_warning(format!("Save {name} before editing its bytes"));
+            return Ok(());
+        }
+        self.open_binary_preview(path, false, None)?;
+        self.start_hex_edit();
+        Ok(())
+    }
+
+    pub(crate) fn start_hex_edit(&mut self) {
+        let Some(hex) = self.hex_view.as_mut() else {
+            return;
+        };
+        if !hex.path.starts_with(&self.root) {
+            self.set_warning("Files outside the project are read-only");
+            return;
+        }
+        if hex.size > hex.bytes.len() as u64 {
+            self.set_warning(format!(
+                "Too large to edit: only the first {} MB is loaded",
+                Self::BINARY_PREVIEW_BYTES / (1024 * 1024)
+            ));
+            return;
+        }
+        hex.edit = Some(HexEdit::default());
+        self.set_status(
+            "Hex edit: type hex digits, Tab for ASCII, Insert toggles insert, Ctrl+S save",
+        );
+    }
+
+    /// Write the edited bytes back to the file.
+    pub(crate) fn save_hex_edit(&mut self) -> io::Result<()> {
+        let Some(hex) = self.hex_view.as_mut() else {
+            return Ok(());
+        };
+        fs::write(&hex.path, &hex.bytes)?;
+        hex.size = hex.bytes.len() as u64;
+        if let Some(edit) = hex.edit.as_mut() {
+            edit.dirty = false;
+            edit.confirm_discard = false;
+        }
+        let status = format!(
+            "Saved {} ({} bytes)",
+            relative_path(&self.root, &hex.path).display(),
+            hex.bytes.len()
+        );
+        self.set_status(status);
+        Ok(())
+    }
+
+    pub(crate) fn handle_hex_edit_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        if self.keybinds.lookup(&key, KeyScope::Global) == Some(KeyAction::Save) {
+            return self.save_hex_edit();
+        }
+        let Some(hex) = self.hex_view.as_mut() else {
+            return Ok(());
+        };
+        let Some(edit) = hex.edit.as_mut() else {
+            return Ok(());
+        };
+        let bytes = &mut hex.bytes;
+        let typed = !key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        let confirm_discard = std::mem::take(&mut edit.confirm_discard);
+        let cursor = edit.cursor;
+        match key.code {
+            KeyCode::Esc if edit.dirty && !confirm_discard => {
+                edit.confirm_discard = true;
+                self.set_warning("Unsaved byte edits: Ctrl+S to save, Esc again to discard");
+                return Ok(());
+            }
+            KeyCode::Esc => {
+                let discard = edit.dirty;
+                hex.edit = None;
+                if discard {
+                    let mut original = Vec::new();
+                    File::open(&hex.path)?
+                        .take(Self::BINARY_PREVIEW_BYTES)
+                        .read_to_end(&mut original)?;
+                    hex.bytes = original;
+                    self.set_status("Discarded byte edits");
+                } else {
+                    self.set_status("Stopped editing bytes");
+                }
+                return Ok(());
+            }
+            KeyCode::Insert => edit.insert = !edit.insert,
+            KeyCode::Tab => edit.ascii = !edit.ascii,
+            KeyCode::Left => edit.cursor = cursor.saturating_sub(1),
+            KeyCode::Right => edit.cursor = cursor + 1,
+            KeyCode::Up => edit.cursor = cursor.saturating_sub(16),
+            KeyCode::Down => edit.cursor = cursor + 16,
+            KeyCode::PageUp => edit.cursor = cursor.saturating_sub(16 * hex.page_rows),
+            KeyCode::PageDown => edit.cursor = cursor + 16 * hex.page_rows,
+            KeyCode::Home => edit.cursor = cursor - cursor % 16,
+            KeyCode::End => edit.cursor = cursor - cursor % 16 + 15,
+            KeyCode::Backspace if cursor > 0 => {
+                bytes.remove(cursor - 1);
+                edit.cursor -= 1;
+                edit.dirty = true;
+            }
+            KeyCode::Delete if cursor < bytes.len() => {
+                bytes.remove(cursor);
+                edit.dirty = true;
+            }
+            KeyCode::Char(c) if typed && edit.ascii && (c == ' ' || c.is_ascii_graphic()) => {
+                edit.type_byte(bytes, c as u8);
+            }
+            KeyCode::Char(c) if typed && !edit.ascii => {
+                if let Some(digit) = c.to_digit(16) {
+                    edit.type_digit(bytes, digit as u8);
+                }
+            }
+            _ => {}
+        }
+        if edit.cursor != cursor {
+            edit.half_typed = false;
+        }
+        edit.cursor = edit.cursor.min(bytes.len());
+        // Keep the cursor row in view.
+        let row = edit.cursor / 16;
+        let rows = hex.page_rows.max(1);
+        hex.scroll = hex.scroll.min(row).max((row + 1).saturating_sub(rows));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .expect("key");
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn hex_editor_overwrites_inserts_and_saves_bytes() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("data.bin");
+        fs::write(&file, b"\x00\x01\x02\x03").expect("write");
+        let mut app = App::new_for_test(tmp.path());
+        app.open_file(file.clone()).expect("open");
+        assert!(app.hex_view.is_some());
+        press(&mut app, KeyCode::Char('e'));
+
+        // Overwrite the first byte, then insert one after it.
+        type_text(&mut app, "ab");
+        press(&mut app, KeyCode::Insert);
+        type_text(&mut app, "c");
+        assert_eq!(
+            app.hex_view.as_ref().expect("hex").bytes,
+            b"\xab\xc0\x01\x02\x03"
+        );
+        type_text(&mut app, "d");
+        // The ASCII column writes whole bytes; Delete removes one.
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Insert);
+        type_text(&mut app, "Z");
+        press(&mut app, KeyCode::Delete);
+        assert_eq!(app.hex_view.as_ref().expect("hex").bytes, b"\xab\xcdZ\x03");
+
+        // Esc with unsaved edits asks first; Ctrl+S writes the file.
+        press(&mut app, KeyCode::Esc);
+        assert!(app.hex_view.as_ref().expect("hex").edit.is_some());
+        app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL))
+            .expect("save");
+        assert_eq!(fs::read(&file).expect("read"), b"\xab\xcdZ\x03");
+        press(&mut app, KeyCode::Esc);
+        let hex = app.hex_view.as_ref().expect("hex");
+        assert!(hex.edit.is_none());
+        assert_eq!(hex.size, 4);
+
+        // Discarding reloads the bytes from disk.
+        press(&mut app, KeyCode::Char('e'));
+        press(&mut app, KeyCode::End);
+        press(&mut app, KeyCode::Backspace);
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.hex_view.as_ref().expect("hex").bytes, b"\xab\xcdZ\x03");
+    }
+}
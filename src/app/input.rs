@@ -3,22 +3,159 @@ use std::io;
 use std::time::{Duration, Instant};
 
 use ratatui::crossterm::event::{
-    KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
 };
 
 use crate::keybinds::KeyScope;
-use crate::types::{Focus, PendingAction};
-use crate::util::{inside, to_u16_saturating};
+use crate::types::{Focus, PendingAction, StatusLevel};
+use crate::util::{byte_index_at_display_col, inside, to_u16_saturating};
 
 impl App {
+    /// Apply one terminal event. The run loop feeds every event through
+    /// here; tests can inject events the same way.
+    ///
+    /// Keys and pastes are held while the active file has a change on disk
+    /// that has not been reloaded yet, so nothing typed lands in the old
+    /// text or on the conflict prompt; they are replayed once it is handled.
+    pub(crate) fn handle_event(&mut self, event: Event) -> io::Result<()> {
+        if matches!(event, Event::Key(_) | Event::Paste(_)) {
+            self.collect_fs_changes()?;
+            if self.active_buffer_reload_pending() {
+                self.held_input.push(event);
+                return Ok(());
+            }
+        }
+        self.dispatch_event(event)
+    }
+
+    fn dispatch_event(&mut self, event: Event) -> io::Result<()> {
+        match event {
+            Event::Key(key) => self.handle_key(key),
+            Event::Mouse(mouse) => self.handle_mouse(mouse),
+            Event::Paste(text) => {
+                self.handle_paste(text);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether typing should wait: the active file changed on disk and has
+    /// not been reloaded or checked for a conflict yet, or earlier input is
+    /// still held (so events keep their order).
+    fn active_buffer_reload_pending(&self) -> bool {
+        !self.held_input.is_empty()
+            || self.active_tab().is_some_and(|t| {
+                t.large_file.is_none()
+                    && !t.follow_tail
+                    && t.pending_io.is_none()
+                    && self.fs_changed_paths.contains(&t.path)
+            })
+    }
+
+    /// Replay input held during a reload. It was typed into the buffer, so
+    /// a conflict prompt the check just opened is set aside until it has
+    /// been applied.
+    pub(crate) fn replay_held_input(&mut self, reloaded: bool) -> io::Result<()> {
+        let held = std::mem::take(&mut self.held_input);
+        if held.is_empty() {
+            return Ok(());
+        }
+        let prompt_path = self
+            .active_tab_mut()
+            .filter(|t| t.conflict_prompt_open)
+            .map(|t| {
+                t.conflict_prompt_open = false;
+                t.path.clone()
+            });
+        let count = held.len();
+        let mut result = Ok(());
+        for event in held {
+            result = result.and(self.dispatch_event(event));
+        }
+        if let Some(tab) = prompt_path.and_then(|p| self.tabs.iter_mut().find(|t| t.path == p)) {
+            tab.conflict_prompt_open = true;
+        }
+        if reloaded {
+            self.set_warning(format!(
+                "File changed on disk while typing: reloaded it, then applied {count} held key(s)"
+            ));
+        }
+        result
+    }
+
     pub(crate) fn handle_key(&mut self, key: KeyEvent) -> io::Result<()> {
         if key.kind != KeyEventKind::Press {
             return Ok(());
         }
 
+        if self.message_view.is_some() {
+            return self.handle_message_view_key(key);
+        }
+        if key.code == KeyCode::Esc && self.status_level == StatusLevel::Error {
+            self.dismiss_status();
+            return Ok(());
+        }
+        if self.form.is_some() {
+            return self.handle_form_key(key);
+        }
         if self.keybind_editor.open {
             return self.handle_keybind_editor_key(key);
         }
+        if self.hex_view.is_some() {
+            return self.handle_hex_view_key(key);
+        }
+        if self.diff_view.is_some() {
+            return self.handle_diff_view_key(key);
+        }
+        if self.merge_view.is_some() {
+            return self.handle_merge_view_key(key);
+        }
+        if self.head_diff.is_some() {
+            return self.handle_head_diff_key(key);
+        }
+        if self.language_overrides_view.is_some() {
+            return self.handle_language_overrides_key(key);
+        }
+        if self.clipboard_history_view.is_some() {
+            return self.handle_clipboard_history_key(key);
+        }
+        if self.recent_projects_view.is_some() {
+            return self.handle_recent_projects_key(key);
+        }
+        if self.bookmarks_view.is_some() {
+            return self.handle_bookmarks_key(key);
+        }
+        if self.location_picker.is_some() {
+            return self.handle_location_picker_key(key);
+        }
+        if self.task_picker.is_some() {
+            return self.handle_task_picker_key(key);
+        }
+        if self.test_explorer.as_ref().is_some_and(|e| e.visible) {
+            return self.handle_test_explorer_key(key);
+        }
+        if self.git_log.is_some() {
+            return self.handle_git_log_key(key);
+        }
+        if self.branch_picker.is_some() {
+            return self.handle_branch_picker_key(key);
+        }
+        if self.batch_rename.is_some() {
+            return self.handle_batch_rename_key(key);
+        }
+        if self.follow_view.is_some() {
+            return self.handle_follow_view_key(key);
+        }
+        if self.snippet_output.is_some() {
+            return self.handle_snippet_output_key(key);
+        }
+        if self.log_view.is_some() {
+            return self.handle_log_view_key(key);
+        }
+        if self.settings_view.is_some() {
+            return self.handle_settings_key(key);
+        }
         if self.file_picker_open {
             return self.handle_file_picker_key(key);
         }
@@ -31,6 +168,9 @@ impl App {
         if self.prompt.is_some() {
             return self.handle_prompt_key(key);
         }
+        if self.find_bar_open && self.handle_find_bar_key(key) {
+            return Ok(());
+        }
         if self.completion.open {
             return self.handle_completion_key(key);
         }
@@ -64,10 +204,12 @@ impl App {
 
         // Non-remappable keys
         match (key.modifiers, key.code) {
+            (_, KeyCode::Esc) if self.focus == Focus::Tree && self.clear_tree_marks() => {
+                return Ok(());
+            }
             (_, KeyCode::Esc) => {
                 if self.open_path().is_some() && self.is_dirty() {
-                    self.pending = PendingAction::ClosePrompt;
-                    self.set_status("Unsaved changes: Enter save+close | Esc discard | C cancel");
+                    self.set_pending(PendingAction::ClosePrompt);
                     return Ok(());
                 }
                 if self.focus == Focus::Editor && self.open_path().is_some() {
@@ -92,15 +234,7 @@ impl App {
             (KeyModifiers::NONE, KeyCode::Delete) => {
                 if self.focus == Focus::Tree {
                     if let Some(item) = self.selected_item().cloned() {
-                        if item.path == self.root {
-                            self.set_status("Cannot delete project root");
-                            return Ok(());
-                        }
-                        self.pending = PendingAction::Delete(item.path.clone());
-                        self.set_status(format!(
-                            "Delete {} ? Press Enter to confirm, Esc to cancel.",
-                            item.name,
-                        ));
+                        self.request_tree_delete(item.path);
                     }
                     return Ok(());
                 }
@@ -114,18 +248,129 @@ impl App {
         }
     }
     pub(crate) fn handle_mouse(&mut self, mouse: MouseEvent) -> io::Result<()> {
+        if let Some(hex) = self.hex_view.as_mut() {
+            let last_row = hex.bytes.len().div_ceil(16).saturating_sub(1);
+            match mouse.kind {
+                MouseEventKind::ScrollDown => {
+                    hex.scroll = (hex.scroll + Self::SCROLL_LINES).min(last_row);
+                }
+                MouseEventKind::ScrollUp => {
+                    hex.scroll = hex.scroll.saturating_sub(Self::SCROLL_LINES);
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+        if let Some(merge) = self.merge_view.as_mut() {
+            let last = merge.hunks.len().saturating_sub(1);
+            match mouse.kind {
+                MouseEventKind::ScrollDown => merge.selected = (merge.selected + 1).min(last),
+                MouseEventKind::ScrollUp => merge.selected = merge.selected.saturating_sub(1),
+                _ => {}
+            }
+            return Ok(());
+        }
+        if self.form.is_some()
+            || self.language_overrides_view.is_some()
+            || self.clipboard_history_view.is_some()
+            || self.recent_projects_view.is_some()
+            || self.bookmarks_view.is_some()
+            || self.location_picker.is_some()
+            || self.task_picker.is_some()
+            || self.test_explorer.as_ref().is_some_and(|e| e.visible)
+            || self.git_log.is_some()
+            || self.branch_picker.is_some()
+            || self.batch_rename.is_some()
+            || self.follow_view.is_some()
+        {
+            return Ok(());
+        }
+        if let Some(view) = self.log_view.as_mut() {
+            let last = view.visible.len().saturating_sub(1);
+            let scroll = view.scroll.min(last);
+            match mouse.kind {
+                MouseEventKind::ScrollDown => {
+                    view.scroll = (scroll + Self::SCROLL_LINES).min(last);
+                }
+                MouseEventKind::ScrollUp => {
+                    view.follow = false;
+                    view.scroll = scroll.saturating_sub(Self::SCROLL_LINES);
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+        if let Some(view) = self.snippet_output.as_mut() {
+            match mouse.kind {
+                MouseEventKind::ScrollDown => view.scroll += Self::SCROLL_LINES,
+                MouseEventKind::ScrollUp => {
+                    view.scroll = view.scroll.saturating_sub(Self::SCROLL_LINES);
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+        if let Some(view) = self.message_view.as_mut() {
+            match mouse.kind {
+                MouseEventKind::ScrollDown => view.scroll += Self::SCROLL_LINES,
+                MouseEventKind::ScrollUp => {
+                    view.scroll = view.scroll.saturating_sub(Self::SCROLL_LINES);
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+        if let Some(diff) = self.head_diff.as_mut() {
+            let last_row = diff.rows.len().saturating_sub(1);
+            match mouse.kind {
+                MouseEventKind::ScrollDown => {
+                    diff.scroll = (diff.scroll + Self::SCROLL_LINES).min(last_row);
+                }
+                MouseEventKind::ScrollUp => {
+                    diff.scroll = diff.scroll.saturating_sub(Self::SCROLL_LINES);
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+        if let Some(diff) = self.diff_view.as_mut() {
+            let last_row = diff.rows.len().saturating_sub(1);
+            match mouse.kind {
+                MouseEventKind::ScrollDown => {
+                    diff.scroll = (diff.scroll + Self::SCROLL_LINES).min(last_row);
+                }
+                MouseEventKind::ScrollUp => {
+                    diff.scroll = diff.scroll.saturating_sub(Self::SCROLL_LINES);
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
         if self.help_open {
             if matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
                 self.help_open = false;
             }
             return Ok(());
         }
+        if self.status_level == StatusLevel::Error
+            && matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left))
+            && inside(mouse.column, mouse.row, self.status_rect)
+        {
+            self.dismiss_status();
+            return Ok(());
+        }
 
         // Modal states: handle prompt clicks or dismiss on click outside
         if self.prompt.is_some()
             || matches!(
                 self.pending,
-                PendingAction::ClosePrompt | PendingAction::Delete(_)
+                PendingAction::ClosePrompt
+                    | PendingAction::Delete(_)
+                    | PendingAction::PasteOverwrite(_)
+                    | PendingAction::DeleteMany(_)
+                    | PendingAction::PasteMany(_)
+                    | PendingAction::TrustProject
+                    | PendingAction::RevertToHead(_)
             )
             || self
                 .active_tab()
@@ -139,16 +384,32 @@ impl App {
                     let inner_x =
                         mouse.column.saturating_sub(self.prompt_rect.x + 1) as usize;
                     if let Some(prompt) = self.prompt.as_mut() {
-                        prompt.cursor = inner_x.min(prompt.value.len());
+                        prompt.anchor = None;
+                        prompt.cursor = byte_index_at_display_col(&prompt.value, inner_x);
                     }
                     return Ok(());
                 }
                 // Dismiss the modal on click outside (Esc-equivalent)
                 if self.prompt.is_some() {
                     self.prompt = None;
-                } else if matches!(self.pending, PendingAction::Delete(_)) {
+                } else if matches!(
+                    self.pending,
+                    PendingAction::Delete(_) | PendingAction::DeleteMany(_)
+                ) {
                     self.pending = PendingAction::None;
                     self.set_status("Delete cancelled");
+                } else if matches!(
+                    self.pending,
+                    PendingAction::PasteOverwrite(_) | PendingAction::PasteMany(_)
+                ) {
+                    self.pending = PendingAction::None;
+                    self.set_status("Paste cancelled");
+                } else if matches!(self.pending, PendingAction::RevertToHead(_)) {
+                    self.pending = PendingAction::None;
+                    self.set_status("Revert cancelled");
+                } else if matches!(self.pending, PendingAction::TrustProject) {
+                    self.pending = PendingAction::None;
+                    self.set_status("Trust decision postponed");
                 } else if matches!(self.pending, PendingAction::ClosePrompt) {
                     self.pending = PendingAction::None;
                     self.set_status("Close cancelled");
@@ -163,6 +424,22 @@ impl App {
             return Ok(());
         }
 
+        if matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left))
+            && inside(mouse.column, mouse.row, self.branch_rect)
+        {
+            self.open_branch_picker();
+            return Ok(());
+        }
+        if matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left))
+            && let Some((_, target)) = self
+                .breadcrumb_rects
+                .iter()
+                .find(|(rect, _)| inside(mouse.column, mouse.row, *rect))
+        {
+            let target = target.clone();
+            self.open_breadcrumb_picker(target);
+            return Ok(());
+        }
         if self.search_results.open {
             return self.handle_search_results_mouse(mouse);
         }
@@ -186,6 +463,12 @@ impl App {
             return self.handle_theme_browser_mouse(mouse);
         }
 
+        if self.task_panel.as_ref().is_some_and(|panel| panel.visible)
+            && inside(mouse.column, mouse.row, self.task_panel_rect)
+        {
+            return self.handle_task_panel_mouse(mouse);
+        }
+
         if self.files_view_open {
             match mouse.kind {
                 MouseEventKind::Down(MouseButton::Left) => {
@@ -217,6 +500,23 @@ impl App {
             }
         }
 
+        if let Some(from) = self.tab_dragging {
+            match mouse.kind {
+                MouseEventKind::Drag(MouseButton::Left) => {
+                    if let Some(to) = self.tab_at_column(mouse.column) {
+                        self.move_tab(from, to);
+                        self.tab_dragging = Some(self.active_tab);
+                    }
+                    return Ok(());
+                }
+                MouseEventKind::Up(MouseButton::Left) => {
+                    self.tab_dragging = None;
+                    return Ok(());
+                }
+                _ => self.tab_dragging = None,
+            }
+        }
+
         if inside(mouse.column, mouse.row, self.tree_rect) {
             match mouse.kind {
                 MouseEventKind::Down(MouseButton::Left) => {
@@ -233,7 +533,7 @@ impl App {
                     if let Some(idx) = self.tree_index_from_mouse(mouse.row) {
                         self.selected = idx;
                         let path = self.tree[idx].path.clone();
-                        if path.is_dir() {
+                        if self.tree[idx].is_dir {
                             self.tree_activate_selected()?;
                             self.focus = Focus::Tree;
                         } else {
@@ -272,23 +572,31 @@ impl App {
         if mouse.row == self.editor_rect.y && inside(mouse.column, mouse.row, self.editor_rect) {
             match mouse.kind {
                 MouseEventKind::Down(MouseButton::Left) => {
+                    // The ‹ › markers step to the nearest hidden tab, which
+                    // scrolls it into view.
+                    if inside(mouse.column, mouse.row, self.tab_scroll_rects.0) {
+                        self.switch_to_tab(self.tab_strip.start.saturating_sub(1));
+                        return Ok(());
+                    }
+                    if inside(mouse.column, mouse.row, self.tab_scroll_rects.1) {
+                        self.switch_to_tab(self.tab_strip.end);
+                        return Ok(());
+                    }
                     for (i, (name_rect, close_rect)) in self.tab_rects.iter().enumerate() {
                         if inside(mouse.column, mouse.row, *close_rect) {
                             // Click on [x] — close this tab
                             if self.tabs[i].dirty {
                                 self.switch_to_tab(i);
-                                self.pending = PendingAction::ClosePrompt;
-                                self.set_status(
-                                    "Unsaved changes: Enter save+close | Esc discard | C cancel",
-                                );
+                                self.set_pending(PendingAction::ClosePrompt);
                             } else {
                                 self.close_tab_at(i);
                             }
                             return Ok(());
                         }
                         if inside(mouse.column, mouse.row, *name_rect) {
-                            // Click on tab name — switch to it
+                            // Click on tab name — switch to it; dragging reorders
                             self.switch_to_tab(i);
+                            self.tab_dragging = Some(i);
                             return Ok(());
                         }
                     }
@@ -307,15 +615,16 @@ impl App {
                     let inner_x = mouse
                         .column
                         .saturating_sub(self.editor_rect.x.saturating_add(1));
-                    if inner_x < Self::EDITOR_GUTTER_WIDTH {
-                        if inner_x < 6 {
+                    let gutter = self.gutter();
+                    if inner_x < gutter.width() {
+                        if inner_x < gutter.number_width() {
                             // Line number area → select full line
                             if let Some(row) = self.gutter_row_from_mouse(mouse.row) {
                                 self.select_line(row);
                                 self.gutter_drag_anchor = Some(row);
                                 self.editor_dragging = true;
                             }
-                        } else {
+                        } else if gutter.folds {
                             // Fold/marker area → toggle fold
                             if let Some(row) = self.gutter_row_from_mouse(mouse.row) {
                                 self.toggle_fold_at_row(row);
@@ -7,13 +7,14 @@ use ratatui::crossterm::event::{
 use ratatui::layout::Rect;
 use ratatui_textarea::Input;
 
+use crate::ignore::IgnoreRules;
 use crate::keybinds::{
     KeyAction, KeyBind, KeyBindings, KeyScope, save_keybindings, selected_action,
 };
 use crate::types::{Focus, PendingAction, PromptMode};
 use crate::util::{
-    context_actions, editor_context_actions, inside, pending_hint, primary_mod_label,
-    text_to_lines, to_u16_saturating,
+    context_actions, editor_context_actions, inside, is_text_input, pending_auto_cancels,
+    text_to_lines, to_u16_saturating, uses_crlf,
 };
 
 impl App {
@@ -25,7 +26,7 @@ impl App {
         } else {
             // Right-click on empty tree space: open context menu at root for create actions.
             self.context_menu.target = Some(self.root.clone());
-            self.context_menu.index = 1; // New File
+            self.context_menu.index = 2; // New File
         }
         self.context_menu.pos = (column, row);
         self.context_menu.open = true;
@@ -40,8 +41,32 @@ impl App {
         let Some(prompt) = self.prompt.as_mut() else {
             return Ok(());
         };
+        let before = prompt.value.clone();
         match (key.modifiers, key.code) {
+            (KeyModifiers::ALT, KeyCode::Char(c @ ('c' | 'w' | 'r')))
+                if matches!(prompt.mode, PromptMode::FindInProject) =>
+            {
+                let options = &mut self.project_search_options;
+                let (name, on) = match c {
+                    'c' => {
+                        options.case_sensitive = !options.case_sensitive;
+                        ("Match case", options.case_sensitive)
+                    }
+                    'w' => {
+                        options.whole_word = !options.whole_word;
+                        ("Whole word", options.whole_word)
+                    }
+                    _ => {
+                        options.literal = !options.literal;
+                        ("Regex", !options.literal)
+                    }
+                };
+                self.set_status(format!("{name}: {}", if on { "on" } else { "off" }));
+            }
             (_, KeyCode::Esc) => {
+                if matches!(prompt.mode, PromptMode::FindInFile) {
+                    self.cancel_incremental_find();
+                }
                 self.prompt = None;
                 self.set_status("Canceled");
             }
@@ -50,56 +75,77 @@ impl App {
                 if value.is_empty()
                     && !matches!(prompt.mode, PromptMode::FindInFile | PromptMode::GoToLine)
                 {
-                    self.set_status("Name cannot be empty");
+                    self.set_warning("Name cannot be empty");
                     return Ok(());
                 }
                 let mode = prompt.mode.clone();
                 self.prompt = None;
                 self.apply_prompt(mode, value)?;
             }
+            // `cursor` is a byte offset, so step over whole chars to keep
+            // multi-byte (IME-composed) text intact.
             (_, KeyCode::Backspace) => {
-                if prompt.cursor > 0 {
-                    prompt.value.remove(prompt.cursor - 1);
-                    prompt.cursor -= 1;
+                if !prompt.delete_selection()
+                    && let Some(ch) = prompt.value[..prompt.cursor].chars().next_back()
+                {
+                    prompt.cursor -= ch.len_utf8();
+                    prompt.value.remove(prompt.cursor);
                 }
             }
             (_, KeyCode::Delete) => {
-                if prompt.cursor < prompt.value.len() {
+                if !prompt.delete_selection() && prompt.cursor < prompt.value.len() {
                     prompt.value.remove(prompt.cursor);
                 }
             }
+            // With a selection, Left/Right collapse it to that side.
             (_, KeyCode::Left) => {
-                if prompt.cursor > 0 {
-                    prompt.cursor -= 1;
+                if let Some(anchor) = prompt.anchor.take() {
+                    prompt.cursor = prompt.cursor.min(anchor);
+                } else if let Some(ch) = prompt.value[..prompt.cursor].chars().next_back() {
+                    prompt.cursor -= ch.len_utf8();
                 }
             }
             (_, KeyCode::Right) => {
-                if prompt.cursor < prompt.value.len() {
-                    prompt.cursor += 1;
+                if let Some(anchor) = prompt.anchor.take() {
+                    prompt.cursor = prompt.cursor.max(anchor);
+                } else if let Some(ch) = prompt.value[prompt.cursor..].chars().next() {
+                    prompt.cursor += ch.len_utf8();
+                }
+            }
+            (_, KeyCode::Up | KeyCode::Down) => {
+                if let Some(entries) = self.search_history.entries(&prompt.mode) {
+                    prompt.step_history(entries, key.code == KeyCode::Up);
                 }
             }
             (_, KeyCode::Home) => {
+                prompt.anchor = None;
                 prompt.cursor = 0;
             }
             (_, KeyCode::End) => {
+                prompt.anchor = None;
                 prompt.cursor = prompt.value.len();
             }
-            (_, KeyCode::Char(c)) => {
-                if !key.modifiers.contains(KeyModifiers::CONTROL) {
-                    prompt.value.insert(prompt.cursor, c);
-                    prompt.cursor += 1;
-                }
+            (mods, KeyCode::Char(c)) if is_text_input(mods) => {
+                prompt.delete_selection();
+                prompt.value.insert(prompt.cursor, c);
+                prompt.cursor += c.len_utf8();
             }
             _ => {}
         }
+        if let Some(prompt) = &self.prompt
+            && matches!(prompt.mode, PromptMode::FindInFile)
+            && prompt.value != before
+        {
+            let query = prompt.value.clone();
+            self.update_incremental_find(&query);
+        }
         Ok(())
     }
 
     pub(crate) fn handle_file_picker_key(&mut self, key: KeyEvent) -> io::Result<()> {
         match (key.modifiers, key.code) {
             (_, KeyCode::Esc) => {
-                self.file_picker_open = false;
-                self.file_picker_query.clear();
+                self.close_file_picker();
                 self.set_status("Canceled quick open");
             }
             (_, KeyCode::Enter) => {
@@ -121,9 +167,7 @@ impl App {
                 self.refresh_file_picker_results();
             }
             (_, KeyCode::Char(c)) => {
-                if !key.modifiers.contains(KeyModifiers::CONTROL)
-                    && !key.modifiers.contains(KeyModifiers::ALT)
-                {
+                if is_text_input(key.modifiers) {
                     self.file_picker_query.push(c);
                     self.file_picker_index = 0;
                     self.refresh_file_picker_results();
@@ -136,6 +180,7 @@ impl App {
 
     pub(crate) fn handle_search_results_key(&mut self, key: KeyEvent) -> io::Result<()> {
         match (key.modifiers, key.code) {
+            (_, KeyCode::Esc) if self.cancel_project_search() => {}
             (_, KeyCode::Esc) => {
                 self.search_results.open = false;
                 self.set_status("Closed search results");
@@ -153,6 +198,19 @@ impl App {
             (_, KeyCode::Enter) => {
                 self.open_selected_search_result()?;
             }
+            (KeyModifiers::NONE, KeyCode::Char('r')) if !self.search_results.results.is_empty() => {
+                self.open_project_replacement_prompt();
+            }
+            (KeyModifiers::NONE, KeyCode::Char(' '))
+                if self.search_results.replacement.is_some() =>
+            {
+                self.toggle_selected_search_hit();
+            }
+            (KeyModifiers::NONE, KeyCode::Char('a'))
+                if self.search_results.replacement.is_some() =>
+            {
+                self.apply_project_replace()?;
+            }
             _ => {}
         }
         Ok(())
@@ -244,6 +302,14 @@ impl App {
                 self.close_file();
                 Ok(true)
             }
+            (
+                PendingAction::ClosePrompt,
+                KeyModifiers::NONE | KeyModifiers::SHIFT,
+                KeyCode::Char('v' | 'V'),
+            ) => {
+                self.open_unsaved_diff();
+                Ok(true)
+            }
             (PendingAction::ClosePrompt, KeyModifiers::NONE, KeyCode::Char('c'))
             | (PendingAction::ClosePrompt, KeyModifiers::NONE, KeyCode::Char('C')) => {
                 self.pending = PendingAction::None;
@@ -273,15 +339,112 @@ impl App {
                 self.set_status("Delete canceled");
                 Ok(true)
             }
-            (_, KeyModifiers::NONE, KeyCode::Esc) => {
+            (PendingAction::PasteOverwrite(paste), KeyModifiers::NONE, KeyCode::Enter)
+            | (PendingAction::PasteOverwrite(paste), KeyModifiers::NONE, KeyCode::Char('y'))
+            | (PendingAction::PasteOverwrite(paste), KeyModifiers::NONE, KeyCode::Char('Y')) => {
+                let paste = paste.clone();
                 self.pending = PendingAction::None;
-                self.set_status("Canceled");
+                self.paste_path(paste)?;
                 Ok(true)
             }
-            _ => {
-                self.set_status(pending_hint(&self.pending));
+            (PendingAction::PasteOverwrite(_), KeyModifiers::NONE, KeyCode::Char('n'))
+            | (PendingAction::PasteOverwrite(_), KeyModifiers::NONE, KeyCode::Char('N'))
+            | (PendingAction::PasteOverwrite(_), KeyModifiers::NONE, KeyCode::Esc) => {
+                self.pending = PendingAction::None;
+                self.set_status("Paste canceled");
+                Ok(true)
+            }
+            (PendingAction::DeleteMany(paths), KeyModifiers::NONE, KeyCode::Enter)
+            | (PendingAction::DeleteMany(paths), KeyModifiers::NONE, KeyCode::Char('y'))
+            | (PendingAction::DeleteMany(paths), KeyModifiers::NONE, KeyCode::Char('Y')) => {
+                let paths = paths.clone();
+                self.pending = PendingAction::None;
+                self.delete_paths(paths)?;
                 Ok(true)
             }
+            (PendingAction::DeleteMany(_), KeyModifiers::NONE, KeyCode::Char('n'))
+            | (PendingAction::DeleteMany(_), KeyModifiers::NONE, KeyCode::Char('N'))
+            | (PendingAction::DeleteMany(_), KeyModifiers::NONE, KeyCode::Esc) => {
+                self.pending = PendingAction::None;
+                self.set_status("Delete canceled");
+                Ok(true)
+            }
+            (PendingAction::PasteMany(pastes), KeyModifiers::NONE, KeyCode::Enter)
+            | (PendingAction::PasteMany(pastes), KeyModifiers::NONE, KeyCode::Char('y'))
+            | (PendingAction::PasteMany(pastes), KeyModifiers::NONE, KeyCode::Char('Y')) => {
+                let pastes = pastes.clone();
+                self.pending = PendingAction::None;
+                self.paste_paths(pastes)?;
+                Ok(true)
+            }
+            (PendingAction::PasteMany(_), KeyModifiers::NONE, KeyCode::Char('n'))
+            | (PendingAction::PasteMany(_), KeyModifiers::NONE, KeyCode::Char('N'))
+            | (PendingAction::PasteMany(_), KeyModifiers::NONE, KeyCode::Esc) => {
+                self.pending = PendingAction::None;
+                self.set_status("Paste canceled");
+                Ok(true)
+            }
+            (PendingAction::TrustProject, KeyModifiers::NONE, KeyCode::Enter)
+            | (PendingAction::TrustProject, KeyModifiers::NONE, KeyCode::Char('y'))
+            | (PendingAction::TrustProject, KeyModifiers::SHIFT, KeyCode::Char('Y')) => {
+                self.pending = PendingAction::None;
+                self.set_project_trust(true);
+                Ok(true)
+            }
+            (PendingAction::TrustProject, KeyModifiers::NONE, KeyCode::Char('n'))
+            | (PendingAction::TrustProject, KeyModifiers::SHIFT, KeyCode::Char('N')) => {
+                self.pending = PendingAction::None;
+                self.set_project_trust(false);
+                Ok(true)
+            }
+            (PendingAction::RevertToHead(path), KeyModifiers::NONE, KeyCode::Enter)
+            | (PendingAction::RevertToHead(path), KeyModifiers::NONE, KeyCode::Char('y'))
+            | (PendingAction::RevertToHead(path), KeyModifiers::SHIFT, KeyCode::Char('Y')) => {
+                let path = path.clone();
+                self.pending = PendingAction::None;
+                self.apply_revert_to_head(path)?;
+                Ok(true)
+            }
+            (
+                PendingAction::RevertToHead(_),
+                KeyModifiers::NONE | KeyModifiers::SHIFT,
+                KeyCode::Char('v' | 'V'),
+            ) => {
+                self.open_head_diff();
+                Ok(true)
+            }
+            (PendingAction::RevertToHead(_), KeyModifiers::NONE, KeyCode::Char('n'))
+            | (PendingAction::RevertToHead(_), KeyModifiers::SHIFT, KeyCode::Char('N'))
+            | (PendingAction::RevertToHead(_), KeyModifiers::NONE, KeyCode::Esc) => {
+                self.pending = PendingAction::None;
+                self.set_status("Revert canceled");
+                Ok(true)
+            }
+            (PendingAction::TrustProject, KeyModifiers::NONE, KeyCode::Esc) => {
+                self.pending = PendingAction::None;
+                self.set_status("Trust decision postponed: rust-analyzer stays off for now");
+                Ok(true)
+            }
+            (_, KeyModifiers::NONE, KeyCode::Esc) => {
+                self.pending = PendingAction::None;
+                self.set_status("Canceled");
+                Ok(true)
+            }
+            // Moving around is harmless while a quit, delete or overwrite
+            // waits; anything else is held until it is answered.
+            (
+                pending,
+                KeyModifiers::NONE,
+                KeyCode::Up
+                | KeyCode::Down
+                | KeyCode::Left
+                | KeyCode::Right
+                | KeyCode::PageUp
+                | KeyCode::PageDown
+                | KeyCode::Home
+                | KeyCode::End,
+            ) if pending_auto_cancels(pending) => Ok(false),
+            _ => Ok(true),
         }
     }
 
@@ -305,6 +468,13 @@ impl App {
             (KeyModifiers::NONE, KeyCode::Left) | (KeyModifiers::NONE, KeyCode::Char('h')) => {
                 self.tree_collapse_or_parent();
             }
+            (KeyModifiers::NONE, KeyCode::Char(c @ '1'..='9')) => {
+                self.open_pinned(c as usize - '0' as usize)?;
+            }
+            (KeyModifiers::NONE, KeyCode::Char(' ')) => self.toggle_tree_mark(),
+            (KeyModifiers::SHIFT, KeyCode::Down | KeyCode::Up) => {
+                self.extend_tree_marks(key.code == KeyCode::Down);
+            }
             _ => {}
         }
         Ok(())
@@ -315,6 +485,26 @@ impl App {
             self.set_status("No file open. Focus returned to files.");
             return Ok(());
         }
+        if self.active_tab().is_some_and(|t| t.refuses_edits()) {
+            let edits = match self.keybinds.lookup(&key, KeyScope::Editor) {
+                Some(action) => action.edits_text(),
+                None => !matches!(
+                    key.code,
+                    KeyCode::Left
+                        | KeyCode::Right
+                        | KeyCode::Up
+                        | KeyCode::Down
+                        | KeyCode::Home
+                        | KeyCode::End
+                        | KeyCode::PageUp
+                        | KeyCode::PageDown
+                        | KeyCode::Esc
+                ),
+            };
+            if edits && self.refuse_read_only_edit() {
+                return Ok(());
+            }
+        }
 
         // Non-remappable: Tab (completion/ghost/indent), auto-pair insertion
         match (key.modifiers, key.code) {
@@ -322,6 +512,16 @@ impl App {
                 self.apply_completion();
                 return Ok(());
             }
+            (KeyModifiers::NONE, KeyCode::Tab)
+                if self.shift_markdown_list(true) || self.indent_selected_lines() =>
+            {
+                return Ok(());
+            }
+            (KeyModifiers::NONE, KeyCode::Enter)
+                if self.continue_markdown_list() || self.insert_newline_with_indent() =>
+            {
+                return Ok(());
+            }
             (KeyModifiers::NONE, KeyCode::Tab) => {
                 if let Some(ghost) = self.completion.ghost.clone() {
                     let now_prefix = self.current_identifier_prefix();
@@ -343,61 +543,47 @@ impl App {
                         self.completion.ghost = None;
                     }
                 }
-                if !self.current_identifier_prefix().is_empty() {
-                    self.request_lsp_completion();
-                    return Ok(());
-                }
+            }
+            // Keybindings ignore Shift on letters, so Ctrl+Shift+V only reaches
+            // here on terminals that report it; Alt+V is the remappable binding.
+            (mods, KeyCode::Char('v' | 'V'))
+                if mods == KeyModifiers::CONTROL | KeyModifiers::SHIFT =>
+            {
+                self.open_clipboard_history();
+                return Ok(());
             }
             (KeyModifiers::CONTROL, KeyCode::Null) => {
                 self.request_lsp_completion();
                 return Ok(());
             }
-            (KeyModifiers::NONE, KeyCode::Char(c))
-                if matches!(c, '(' | '[' | '{' | '"' | '\'')
-                    && self
-                        .active_tab()
-                        .is_some_and(|t| t.editor.selection_range().is_none()) =>
+            (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(c))
+                if self.type_auto_pair_char(c) =>
             {
-                let pair = match c {
-                    '(' => "()",
-                    '[' => "[]",
-                    '{' => "{}",
-                    '"' => "\"\"",
-                    '\'' => "''",
-                    _ => "",
-                };
-                if !pair.is_empty() {
-                    let inserted = self
-                        .active_tab_mut()
-                        .is_some_and(|t| t.editor.insert_str(pair));
-                    if inserted {
-                        if let Some(tab) = self.active_tab_mut() {
-                            tab.editor.move_cursor(ratatui_textarea::CursorMove::Back);
-                        }
-                        self.on_editor_content_changed();
-                        self.set_status("Auto-pair inserted");
-                        return Ok(());
-                    }
-                }
+                return Ok(());
+            }
+            (KeyModifiers::NONE, KeyCode::Backspace) if self.delete_empty_pair() => {
+                return Ok(());
             }
             _ => {}
         }
 
         // Editor keybind lookup
-        if self.word_wrap && key.modifiers == KeyModifiers::NONE {
+        if self.word_wrap
+            && self.editor_settings.visual_line_navigation
+            && key.modifiers == KeyModifiers::NONE
+            && matches!(
+                key.code,
+                KeyCode::Down | KeyCode::Up | KeyCode::Home | KeyCode::End
+            )
+        {
             match key.code {
-                KeyCode::Down => {
-                    self.move_cursor_visual(true);
-                    self.refresh_inline_ghost();
-                    return Ok(());
-                }
-                KeyCode::Up => {
-                    self.move_cursor_visual(false);
-                    self.refresh_inline_ghost();
-                    return Ok(());
-                }
-                _ => {}
+                KeyCode::Down => self.move_cursor_visual(true),
+                KeyCode::Up => self.move_cursor_visual(false),
+                KeyCode::Home => self.move_cursor_visual_edge(false),
+                _ => self.move_cursor_visual_edge(true),
             }
+            self.refresh_inline_ghost();
+            return Ok(());
         }
 
         if let Some(action) = self.keybinds.lookup(&key, KeyScope::Editor) {
@@ -422,10 +608,7 @@ impl App {
             KeyAction::CloseTab => {
                 if !self.tabs.is_empty() {
                     if self.is_dirty() {
-                        self.pending = PendingAction::ClosePrompt;
-                        self.set_status(
-                            "Unsaved changes: Enter save+close | Esc discard | C cancel",
-                        );
+                        self.set_pending(PendingAction::ClosePrompt);
                     } else {
                         self.close_file();
                     }
@@ -436,11 +619,7 @@ impl App {
                     if matches!(self.pending, PendingAction::Quit) {
                         self.quit = true;
                     } else {
-                        self.pending = PendingAction::Quit;
-                        self.set_status(format!(
-                            "Unsaved changes. Press {}+Q again to quit.",
-                            primary_mod_label()
-                        ));
+                        self.set_pending(PendingAction::Quit);
                     }
                 } else {
                     self.quit = true;
@@ -458,6 +637,7 @@ impl App {
             KeyAction::CommandPalette => self.open_command_palette(),
             KeyAction::QuickOpen => {
                 self.file_picker_open = true;
+                self.file_picker_changed_only = false;
                 self.file_picker_query.clear();
                 self.file_picker_index = 0;
                 self.refresh_file_picker_results();
@@ -477,6 +657,7 @@ impl App {
             KeyAction::Help => self.help_open = true,
             KeyAction::NewFile => self.create_new_file()?,
             KeyAction::RefreshTree => {
+                self.ignore_rules = IgnoreRules::load(&self.root);
                 self.rebuild_tree()?;
                 self.set_status("Tree refreshed");
             }
@@ -496,6 +677,18 @@ impl App {
                     self.switch_to_tab(next);
                 }
             }
+            KeyAction::MoveTabLeft => self.move_active_tab(false),
+            KeyAction::MoveTabRight => self.move_active_tab(true),
+            KeyAction::CloseOtherTabs => self.close_other_tabs(),
+            KeyAction::CloseTabsToRight => self.close_tabs_to_right(),
+            KeyAction::CloseAllTabs => self.close_all_tabs(),
+            KeyAction::ReopenClosedTab => self.reopen_closed_tab()?,
+            KeyAction::TogglePinTab => self.toggle_pin_active_tab(),
+            KeyAction::ChangedFiles => self.open_changed_files_picker(),
+            KeyAction::ShowLastMessage => self.open_message_view(),
+            KeyAction::GoToLastEdit => self.go_to_last_edit_location()?,
+            KeyAction::JumpBack => self.navigate_jump_list(true)?,
+            KeyAction::JumpForward => self.navigate_jump_list(false)?,
             KeyAction::ToggleWordWrap => self.toggle_word_wrap(),
             KeyAction::TreeExpandAll => {
                 self.tree_expand_all()?;
@@ -523,47 +716,43 @@ impl App {
             KeyAction::Unfold => self.unfold_current_block(),
             KeyAction::FoldAll => self.fold_all(),
             KeyAction::UnfoldAll => self.unfold_all(),
-            KeyAction::FindNext => {
-                if self
-                    .active_tab_mut()
-                    .is_some_and(|t| t.editor.search_forward(false))
-                {
-                    self.set_status("Find next");
-                    self.sync_editor_scroll_guess();
-                } else {
-                    self.set_status("No next match");
+            KeyAction::FindNext => self.find_next(),
+            KeyAction::FindPrev => self.find_prev(),
+            KeyAction::DupLineDown => self.duplicate_current_line(false),
+            KeyAction::DupLineUp => self.duplicate_current_line(true),
+            KeyAction::MoveLineDown => self.move_lines(false),
+            KeyAction::MoveLineUp => self.move_lines(true),
+            KeyAction::Dedent => {
+                if !self.shift_markdown_list(false) {
+                    self.dedent_lines();
                 }
             }
-            KeyAction::FindPrev => {
+            KeyAction::Completion => self.request_lsp_completion(),
+            KeyAction::Undo => {
                 if self
                     .active_tab_mut()
-                    .is_some_and(|t| t.editor.search_back(false))
+                    .is_some_and(|t| t.undo_group() || t.editor.undo())
                 {
-                    self.set_status("Find previous");
-                    self.sync_editor_scroll_guess();
-                } else {
-                    self.set_status("No previous match");
-                }
-            }
-            KeyAction::DupLineDown => self.duplicate_current_line(false),
-            KeyAction::DupLineUp => self.duplicate_current_line(true),
-            KeyAction::Dedent => self.dedent_lines(),
-            KeyAction::Completion => self.request_lsp_completion(),
-            KeyAction::Undo => {
-                if self.active_tab_mut().is_some_and(|t| t.editor.undo()) {
                     self.on_editor_content_changed();
                     self.set_status("Undo");
+                } else if self.undo_to_restored_snapshot() {
+                    self.set_status("Undo (from before the file was reopened)");
                 } else {
-                    self.set_status("Nothing to undo");
+                    self.reject_action("Nothing to undo");
                 }
                 self.sync_editor_scroll_guess();
             }
             KeyAction::Redo => {
-                if self.active_tab_mut().is_some_and(|t| t.editor.redo()) {
+                if self
+                    .active_tab_mut()
+                    .is_some_and(|t| t.redo_group() || t.editor.redo())
+                {
                     self.on_editor_content_changed();
                     self.set_status("Redo");
+                } else if self.redo_to_restored_snapshot() {
+                    self.set_status("Redo");
                 } else {
-                    self.set_status("Nothing to redo");
+                    self.reject_action("Nothing to redo");
                 }
                 self.sync_editor_scroll_guess();
             }
@@ -577,7 +766,9 @@ impl App {
             KeyAction::Cut => self.cut_selection_to_clipboard(),
             KeyAction::CutLine => self.cut_line(),
             KeyAction::Paste => self.paste_from_clipboard(),
+            KeyAction::PasteFromHistory => self.open_clipboard_history(),
             KeyAction::ToggleComment => self.toggle_comment(),
+            KeyAction::JumpToBracket => self.jump_to_matching_bracket(),
             KeyAction::PageDown => self.page_down(),
             KeyAction::PageUp => self.page_up(),
             KeyAction::GoToStart => {
@@ -601,6 +792,12 @@ impl App {
                 self.sync_editor_scroll_guess();
                 self.set_status("End of file");
             }
+            KeyAction::ToggleBookmark => self.toggle_bookmark(),
+            KeyAction::NextBookmark => self.jump_to_bookmark(true)?,
+            KeyAction::PrevBookmark => self.jump_to_bookmark(false)?,
+            KeyAction::ScrollLineUp => self.scroll_view_line(false),
+            KeyAction::ScrollLineDown => self.scroll_view_line(true),
+            KeyAction::CenterCursorLine => self.center_cursor_in_view(),
         }
         Ok(())
     }
@@ -730,9 +927,7 @@ impl App {
                 self.refresh_keybind_editor_actions();
             }
             (_, KeyCode::Char(c)) => {
-                if !key.modifiers.contains(KeyModifiers::CONTROL)
-                    && !key.modifiers.contains(KeyModifiers::ALT)
-                {
+                if is_text_input(key.modifiers) {
                     self.keybind_editor.query.push(c);
                     self.refresh_keybind_editor_actions();
                 }
@@ -776,9 +971,7 @@ impl App {
             }
             (_, KeyCode::Enter) => {
                 if let Some(action) = self.menu_results.get(self.menu_index).copied() {
-                    self.menu_open = false;
-                    self.menu_query.clear();
-                    self.run_command_action(action)?;
+                    self.run_palette_command(action)?;
                 }
             }
             (_, KeyCode::Backspace) => {
@@ -786,9 +979,7 @@ impl App {
                 self.refresh_menu_results();
             }
             (_, KeyCode::Char(c)) => {
-                if !key.modifiers.contains(KeyModifiers::CONTROL)
-                    && !key.modifiers.contains(KeyModifiers::ALT)
-                {
+                if is_text_input(key.modifiers) {
                     self.menu_query.push(c);
                     self.refresh_menu_results();
                 }
@@ -823,6 +1014,7 @@ impl App {
                 }
                 self.set_status("Discarded autosave");
             }
+            (_, KeyCode::Char('v')) | (_, KeyCode::Char('V')) => self.open_recovery_diff(),
             (_, KeyCode::Esc) | (_, KeyCode::Char('c')) | (_, KeyCode::Char('C')) => {
                 if let Some(tab) = self.active_tab_mut() {
                     tab.recovery_prompt_open = false;
@@ -844,6 +1036,7 @@ impl App {
                     self.replace_editor_text(lines, cursor);
                     if let Some(tab) = self.active_tab_mut() {
                         tab.dirty = false;
+                        tab.crlf = uses_crlf(&disk);
                         tab.open_disk_snapshot = Some(disk);
                     }
                     self.clear_autosave_for_open_file();
@@ -855,6 +1048,8 @@ impl App {
                     tab.conflict_disk_text = None;
                 }
             }
+            (_, KeyCode::Char('v')) | (_, KeyCode::Char('V')) => self.open_unsaved_diff(),
+            (_, KeyCode::Char('m')) | (_, KeyCode::Char('M')) => self.open_conflict_merge(),
             (_, KeyCode::Char('k')) | (_, KeyCode::Char('K')) => {
                 if let Some(tab) = self.active_tab_mut() {
                     if let Some(disk) = tab.conflict_disk_text.clone() {
@@ -959,9 +1154,7 @@ impl App {
         if row < self.menu_results.len() {
             self.menu_index = row;
             let action = self.menu_results[self.menu_index];
-            self.menu_open = false;
-            self.menu_query.clear();
-            self.run_command_action(action)?;
+            self.run_palette_command(action)?;
         }
         Ok(())
     }
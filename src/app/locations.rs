@@ -0,0 +1,142 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use super::{App, LocationPicker};
+use crate::types::{PromptMode, PromptState};
+use crate::util::{parse_pasted_locations, relative_path};
+
+impl App {
+    /// "Paste Error / Location": list the `file:line` references in the
+    /// clipboard, or ask for the text to be pasted when it has none.
+    pub(crate) fn paste_error_locations(&mut self) {
+        let text = self
+            .clipboard
+            .as_mut()
+            .and_then(|c| c.get_text().ok())
+            .unwrap_or_default();
+        if self.open_location_picker(&text) {
+            return;
+        }
+        self.prompt = Some(PromptState {
+            title: "Paste compiler output or a backtrace (file:line references)".to_string(),
+            value: String::new(),
+            cursor: 0,
+            anchor: None,
+            history: None,
+            mode: PromptMode::PasteLocations,
+        });
+    }
+
+    /// Open the picker over the references in `text` that name existing
+    /// files. Returns false, leaving things as they are, when there are none.
+    pub(crate) fn open_location_picker(&mut self, text: &str) -> bool {
+        let entries: Vec<_> = parse_pasted_locations(text)
+            .into_iter()
+            .filter_map(|loc| {
+                let path = self.resolve_pasted_path(&loc.path)?;
+                let col = loc.column.map_or(0, |c| c.saturating_sub(1));
+                Some((path, loc.line - 1, col, loc.context))
+            })
+            .collect();
+        if entries.is_empty() {
+            return false;
+        }
+        let count = entries.len();
+        self.set_status(format!(
+            "{count} location{}",
+            if count == 1 { "" } else { "s" }
+        ));
+        self.location_picker = Some(LocationPicker {
+            title: "Pasted Locations - Enter go to, Esc close".to_string(),
+            entries,
+            selected: 0,
+        });
+        true
+    }
+
+    /// A pasted path as a file on disk: absolute, or relative to the
+    /// project or one of the workspace folders.
+    pub(crate) fn resolve_pasted_path(&self, path: &str) -> Option<PathBuf> {
+        let path = Path::new(path.strip_prefix("./").unwrap_or(path));
+        if path.is_absolute() {
+            return path.is_file().then(|| path.to_path_buf());
+        }
+        std::iter::once(&self.root)
+            .chain(self.workspace_folders.iter().map(|f| &f.path))
+            .map(|dir| dir.join(path))
+            .find(|p| p.is_file())
+    }
+
+    pub(crate) fn handle_location_picker_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(picker) = self.location_picker.as_mut() else {
+            return Ok(());
+        };
+        let last = picker.entries.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.location_picker = None,
+            KeyCode::Down | KeyCode::Char('j') => {
+                picker.selected = (picker.selected + 1).min(last);
+            }
+            KeyCode::Up | KeyCode::Char('k') => picker.selected = picker.selected.saturating_sub(1),
+            KeyCode::Enter => {
+                let entry = picker.entries.get(picker.selected).cloned();
+                self.location_picker = None;
+                if let Some((path, row, col, _)) = entry {
+                    self.record_jump();
+                    if self.go_to_location(&path, row, col)? {
+                        self.set_status(format!(
+                            "Opened {}:{}",
+                            relative_path(&self.root, &path).display(),
+                            row + 1
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn pasted_rustc_output_lists_locations_and_jumps() {
+        let tmp = tempdir().expect("tempdir");
+        fs::create_dir(tmp.path().join("src")).expect("mkdir");
+        let lib = tmp.path().join("src/lib.rs");
+        fs::write(&lib, "fn a() {}\nfn b() {\n    let x: u8 = \"\";\n}\n").expect("write");
+        let mut app = App::new_for_test(tmp.path());
+        app.paste_error_locations();
+        assert!(app.prompt.is_some());
+
+        // A terminal paste into the prompt goes straight to the picker.
+        app.handle_paste(
+            "error[E0308]: mismatched types\n --> src/lib.rs:3:17\n --> src/gone.rs:1:1\n"
+                .to_string(),
+        );
+        assert!(app.prompt.is_none());
+        let picker = app.location_picker.as_ref().expect("picker");
+        assert_eq!(
+            picker.entries,
+            [(
+                lib.clone(),
+                2,
+                16,
+                "error[E0308]: mismatched types".to_string()
+            )]
+        );
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("enter");
+        assert!(app.location_picker.is_none());
+        assert_eq!(app.open_path(), Some(&lib));
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (2, 16));
+    }
+}
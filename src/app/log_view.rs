@@ -0,0 +1,241 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use super::{App, LogViewState};
+use crate::json_log::{LogFilter, LogLevel, looks_like_json_log, parse_log_line};
+use crate::util::relative_path;
+
+impl App {
+    /// Show the active file in log mode, if it is a JSON-lines log.
+    pub(crate) fn open_log_view_for_active(&mut self) -> io::Result<()> {
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return Ok(());
+        };
+        if !looks_like_json_log(tab.editor.lines()) {
+            self.set_warning("Not a JSON-lines log (no JSON objects with a time or level)");
+            return Ok(());
+        }
+        let path = tab.path.clone();
+        self.open_log_view(path)
+    }
+
+    pub(crate) fn open_log_view(&mut self, path: PathBuf) -> io::Result<()> {
+        self.log_view = Some(LogViewState {
+            path: path.clone(),
+            records: Vec::new(),
+            read_bytes: 0,
+            tail_partial: false,
+            visible: Vec::new(),
+            filter: LogFilter::default(),
+            editing_query: false,
+            scroll: 0,
+            follow: true,
+        });
+        self.tail_log_view()?;
+        let count = self.log_view.as_ref().map_or(0, |v| v.records.len());
+        self.set_status(format!(
+            "Log view: {} ({count} records) - Esc for the raw file",
+            relative_path(&self.root, &path).display()
+        ));
+        Ok(())
+    }
+
+    /// Read lines appended to the log since the last read; called when the
+    /// watcher reports the file changed. A file that shrank was truncated or
+    /// rotated and is read again from the start.
+    pub(crate) fn tail_log_view(&mut self) -> io::Result<()> {
+        let Some(view) = self.log_view.as_mut() else {
+            return Ok(());
+        };
+        let mut file = File::open(&view.path)?;
+        let len = file.metadata()?.len();
+        if len < view.read_bytes {
+            view.records.clear();
+            view.visible.clear();
+            view.read_bytes = 0;
+            view.tail_partial = false;
+        }
+        if len == view.read_bytes {
+            return Ok(());
+        }
+        // An unterminated last line was shown as it was; read it again now
+        // that more may have been written.
+        if view.tail_partial {
+            view.records.pop();
+            if view.visible.last() == Some(&view.records.len()) {
+                view.visible.pop();
+            }
+        }
+        file.seek(SeekFrom::Start(view.read_bytes))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let complete = bytes.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        view.read_bytes += complete as u64;
+        view.tail_partial = complete < bytes.len();
+        let text = String::from_utf8_lossy(&bytes);
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let record = parse_log_line(line.trim_end_matches('\r'));
+            if view.filter.matches(&record) {
+                view.visible.push(view.records.len());
+            }
+            view.records.push(record);
+        }
+        if view.follow {
+            view.scroll = usize::MAX;
+        }
+        Ok(())
+    }
+
+    fn refilter_log_view(view: &mut LogViewState) {
+        view.visible = view
+            .records
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| view.filter.matches(r))
+            .map(|(i, _)| i)
+            .collect();
+        view.scroll = if view.follow { usize::MAX } else { 0 };
+    }
+
+    pub(crate) fn handle_log_view_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        const PAGE_ROWS: usize = 20;
+        let Some(view) = self.log_view.as_mut() else {
+            return Ok(());
+        };
+        if view.editing_query {
+            match key.code {
+                KeyCode::Enter | KeyCode::Esc => view.editing_query = false,
+                KeyCode::Backspace => {
+                    view.filter.query.pop();
+                    Self::refilter_log_view(view);
+                }
+                KeyCode::Char(c) => {
+                    view.filter.query.push(c);
+                    Self::refilter_log_view(view);
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+        let last = view.visible.len().saturating_sub(1);
+        let scroll = view.scroll.min(last);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.log_view = None;
+                self.set_status("Closed log view");
+                return Ok(());
+            }
+            KeyCode::Down | KeyCode::Char('j') => view.scroll = (scroll + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => {
+                view.follow = false;
+                view.scroll = scroll.saturating_sub(1);
+            }
+            KeyCode::PageDown => view.scroll = (scroll + PAGE_ROWS).min(last),
+            KeyCode::PageUp => {
+                view.follow = false;
+                view.scroll = scroll.saturating_sub(PAGE_ROWS);
+            }
+            KeyCode::Home | KeyCode::Char('g') => {
+                view.follow = false;
+                view.scroll = 0;
+            }
+            KeyCode::End | KeyCode::Char('G') => {
+                view.follow = true;
+                view.scroll = usize::MAX;
+            }
+            KeyCode::Char('f') => {
+                view.follow = !view.follow;
+                if view.follow {
+                    view.scroll = usize::MAX;
+                }
+            }
+            KeyCode::Char('/') => view.editing_query = true,
+            KeyCode::Char('l') => {
+                // All levels, then each minimum from Debug up to Error only.
+                view.filter.min_level = match view.filter.min_level {
+                    None => Some(LogLevel::Debug),
+                    Some(LogLevel::Error) => None,
+                    Some(level) => LogLevel::ALL.into_iter().find(|&l| l > level),
+                };
+                Self::refilter_log_view(view);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::FsChangeEvent;
+    use notify::EventKind;
+    use notify::event::ModifyKind;
+    use ratatui::crossterm::event::KeyModifiers;
+    use std::fs;
+    use std::io::Write;
+    use std::sync::mpsc;
+    use tempfile::tempdir;
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .expect("key");
+    }
+
+    #[test]
+    fn json_logs_open_in_log_mode_and_follow_appended_lines() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("app.log");
+        fs::write(
+            &file,
+            "{\"time\":\"10:00\",\"level\":\"info\",\"msg\":\"started\"}\n\
+             {\"time\":\"10:01\",\"level\":\"error\",\"msg\":\"failed\",\"user\":\"bob\"",
+        )
+        .expect("write");
+        let mut app = App::new_for_test(tmp.path());
+        app.open_file(file.clone()).expect("open");
+        let view = app.log_view.as_ref().expect("log view");
+        assert_eq!(view.records.len(), 2);
+        assert!(view.tail_partial);
+
+        let mut out = fs::OpenOptions::new()
+            .append(true)
+            .open(&file)
+            .expect("append");
+        out.write_all(b"}\n{\"time\":\"10:02\",\"level\":\"warn\",\"msg\":\"slow\"}\n")
+            .expect("write");
+        drop(out);
+        let (tx, rx) = mpsc::channel();
+        app.fs_rx = Some(rx);
+        tx.send(FsChangeEvent {
+            paths: vec![file.clone()],
+            kind: EventKind::Modify(ModifyKind::Any),
+            full_refresh: false,
+        })
+        .expect("send");
+        app.poll_fs_changes().expect("poll");
+        let view = app.log_view.as_ref().expect("log view");
+        assert_eq!(view.records.len(), 3);
+        assert_eq!(view.records[1].message_text(), "failed");
+        assert_eq!(view.records[2].message_text(), "slow");
+        assert!(!view.tail_partial);
+
+        press(&mut app, KeyCode::Char('l'));
+        press(&mut app, KeyCode::Char('l'));
+        press(&mut app, KeyCode::Char('l'));
+        assert_eq!(app.log_view.as_ref().expect("view").visible, [1, 2]);
+        press(&mut app, KeyCode::Char('/'));
+        for c in "user=bob".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.log_view.as_ref().expect("view").visible, [1]);
+        press(&mut app, KeyCode::Esc);
+        assert!(app.log_view.is_none());
+        assert!(app.active_tab().is_some_and(|t| t.path == file));
+    }
+}
@@ -1,30 +1,111 @@
-use super::App;
+use super::{App, PendingLspRequest, SnippetOutputState};
+use std::collections::HashSet;
+use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::TryRecvError;
 
+use notify::EventKind;
 use serde_json::{Value, json};
 use url::Url;
 
-use crate::lsp_client::{LspClient, LspCompletionItem, LspDiagnostic, LspInbound};
-use crate::syntax::{is_ident_char, keywords_for_lang, syntax_lang_for_path};
-use crate::util::{file_uri, to_u16_saturating};
+use crate::env_file::load_project_env;
+use crate::lsp_client::{
+    LspClient, LspCompletionItem, LspDiagnostic, parse_workspace_symbols, resolve_rust_analyzer_bin,
+};
+use crate::lsp_transport::LspEvent;
+use crate::persistence::{lsp_settings_path, project_lsp_settings_path};
+use crate::syntax::{SyntaxLang, is_ident_char, keywords_for_lang};
+use crate::types::PendingAction;
+use crate::util::{
+    changed_lines, file_uri, lsp_content_change, lsp_file_change_type, relative_path,
+    text_to_lines, to_u16_saturating,
+};
 
 impl App {
+    /// Open the project's env file (see `env_file` in `lsp.json`), creating
+    /// it if it does not exist yet.
+    pub(crate) fn edit_env_file(&mut self) -> io::Result<()> {
+        let Some(name) = self.lsp_settings.env_file_name() else {
+            self.set_warning("No env file: env_file is empty in lsp.json");
+            return Ok(());
+        };
+        let path = self.root.join(name);
+        if !path.exists() {
+            fs::write(&path, "")?;
+            self.rebuild_tree()?;
+        }
+        self.open_file(path)
+    }
+
+    /// Popup with the settings rust-analyzer gets here (after project
+    /// overrides) and the capabilities it answered `initialize` with.
+    pub(crate) fn show_lsp_config(&mut self) {
+        self.snippet_output = Some(SnippetOutputState {
+            title: "LSP Configuration".to_string(),
+            text: self.lsp_config_report(),
+            success: true,
+            scroll: 0,
+        });
+    }
+
+    fn lsp_config_report(&self) -> String {
+        let pretty = |value: &Value| serde_json::to_string_pretty(value).unwrap_or_default();
+        let source = |path: Option<PathBuf>| match path {
+            Some(path) if path.is_file() => path.display().to_string(),
+            Some(path) => format!("{} (not found)", path.display()),
+            None => "(no config directory)".to_string(),
+        };
+        let mut command = resolve_rust_analyzer_bin()
+            .map_or_else(|| "rust-analyzer".to_string(), |p| p.display().to_string());
+        for arg in self.lsp_settings.server_args_for("rust") {
+            command.push(' ');
+            command.push_str(arg);
+        }
+        let env_file = self.lsp_settings.env_file_name().unwrap_or("(none)");
+        let mut report = [
+            format!("User settings:    {}", source(lsp_settings_path())),
+            format!(
+                "Project settings: {}",
+                source(Some(project_lsp_settings_path(&self.root)))
+            ),
+            String::new(),
+            format!("Server:   {command}"),
+            format!("Env file: {env_file}"),
+            String::new(),
+            "initializationOptions:".to_string(),
+            pretty(&self.lsp_settings.rust_analyzer_section()),
+            String::new(),
+            String::new(),
+        ]
+        .join("\n");
+        match &self.lsp {
+            Some(lsp) => {
+                report.push_str("Server capabilities:\n");
+                report.push_str(&pretty(&lsp.capabilities));
+            }
+            None => report.push_str("Server capabilities: (rust-analyzer is not running)"),
+        }
+        report
+    }
+
     pub(crate) fn request_lsp_definition(&mut self) {
         if self.try_local_definition_jump() {
             return;
         }
         let uri = self.active_tab().and_then(|t| t.open_doc_uri.clone());
         let Some((row, col)) = self.active_tab().map(|t| t.editor.cursor()) else {
-            self.set_status("Definition unavailable");
+            self.set_warning("Definition unavailable");
             return;
         };
         let (Some(uri), Some(lsp)) = (uri, self.lsp.as_mut()) else {
-            self.set_status("Definition unavailable");
+            self.set_warning("Definition unavailable");
             return;
         };
-        match lsp.send_request(
+        // A newer request supersedes the old one.
+        if let Some(old) = self.pending_definition_request.take() {
+            lsp.transport.cancel(old.id);
+        }
+        match lsp.transport.send_request(
             "textDocument/definition",
             json!({
                 "textDocument": { "uri": uri },
@@ -32,10 +113,14 @@ impl App {
             }),
         ) {
             Ok(id) => {
-                self.pending_definition_request = Some(id);
+                self.pending_definition_request = Some(PendingLspRequest {
+                    id,
+                    path: self.tabs[self.active_tab].path.clone(),
+                    cursor: (row, col),
+                });
                 self.set_status("Go to definition requested");
             }
-            Err(_) => self.set_status("Failed to request definition"),
+            Err(_) => self.set_error("Failed to request definition"),
         }
     }
 
@@ -48,7 +133,7 @@ impl App {
                 .get("message")
                 .and_then(Value::as_str)
                 .unwrap_or("Definition error");
-            self.set_status(format!("Definition error: {}", msg));
+            self.set_error(format!("Definition error: {}", msg));
             return Ok(());
         }
         let mut target: Option<(PathBuf, usize, usize)> = None;
@@ -89,12 +174,18 @@ impl App {
             self.set_status("No definition found");
             return Ok(());
         };
-        if self.is_dirty() && self.open_path() != Some(&path) {
-            self.set_status("Unsaved changes: save or close before jumping to definition");
+        let external = self.is_outside_project(&path);
+        if !external && self.is_dirty() && self.open_path() != Some(&path) {
+            self.set_warning("Unsaved changes: save or close before jumping to definition");
             return Ok(());
         }
+        self.record_jump();
         if self.open_path() != Some(&path) {
-            self.open_file(path)?;
+            if external {
+                self.open_external_file(path)?;
+            } else {
+                self.open_file(path)?;
+            }
         }
         if let Some(tab) = self.active_tab_mut() {
             tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
@@ -138,6 +229,7 @@ impl App {
             ];
             if candidates.iter().any(|p| trimmed.starts_with(p)) {
                 let col = line.find("fn ").unwrap_or(0);
+                self.record_jump();
                 self.tabs[self.active_tab]
                     .editor
                     .move_cursor(ratatui_textarea::CursorMove::Jump(
@@ -152,30 +244,144 @@ impl App {
         false
     }
 
+    /// After files were renamed under open tabs, close the old documents on
+    /// the language server and open them again at their new paths.
+    pub(crate) fn retarget_lsp_documents(&mut self) {
+        let Some(lsp) = self.lsp.as_ref() else {
+            return;
+        };
+        for tab in &mut self.tabs {
+            let Some(old_uri) = tab.open_doc_uri.clone() else {
+                continue;
+            };
+            let Some(uri) = file_uri(&tab.path) else {
+                continue;
+            };
+            if uri == old_uri {
+                continue;
+            }
+            let _ = lsp.transport.send_notification(
+                "textDocument/didClose",
+                json!({ "textDocument": { "uri": old_uri } }),
+            );
+            let _ = lsp.transport.send_notification(
+                "textDocument/didOpen",
+                json!({
+                    "textDocument": {
+                        "uri": uri,
+                        "languageId": "rust",
+                        "version": 1,
+                        "text": tab.editor.lines().join("\n")
+                    }
+                }),
+            );
+            tab.open_doc_uri = Some(uri);
+            tab.open_doc_version = 1;
+            tab.lsp_text = tab.editor.lines().to_vec();
+        }
+    }
+
+    /// Record the trust decision for this project. Trusting starts
+    /// rust-analyzer for the open file; revoking stops it.
+    pub(crate) fn set_project_trust(&mut self, trusted: bool) {
+        self.trusted_projects.insert(self.root.clone(), trusted);
+        self.persist_state();
+        if trusted {
+            self.set_status("Project trusted");
+            if let Some(path) = self.open_path().cloned() {
+                self.ensure_lsp_for_path(&path);
+            }
+            return;
+        }
+        self.stop_lsp();
+        self.cancel_lsp_requests();
+        self.completion.reset();
+        for tab in &mut self.tabs {
+            tab.open_doc_uri = None;
+            tab.open_doc_version = 0;
+            tab.diagnostics.clear();
+        }
+        self.set_status("Project not trusted: rust-analyzer will not run here");
+    }
+
+    /// Whether `what`, which runs code from the project, may start: true
+    /// for a trusted project. Otherwise it is refused, with the trust
+    /// prompt shown when the project was never decided on.
+    pub(crate) fn require_project_trust(&mut self, what: &str) -> bool {
+        match self.trusted_projects.get(&self.root) {
+            Some(true) => true,
+            Some(false) => {
+                self.set_warning(format!(
+                    "{what} runs project code: the project is not trusted (Trust / Untrust Project)"
+                ));
+                false
+            }
+            None => {
+                self.set_pending(PendingAction::TrustProject);
+                false
+            }
+        }
+    }
+
+    /// Ask rust-analyzer to shut down and exit, if it is running.
+    pub(crate) fn stop_lsp(&mut self) {
+        if let Some(mut lsp) = self.lsp.take() {
+            let _ = lsp.transport.send_request("shutdown", Value::Null);
+            let _ = lsp.transport.send_notification("exit", Value::Null);
+        }
+    }
+
     pub(crate) fn ensure_lsp_for_path(&mut self, path: &Path) {
         let is_rust = path
             .extension()
             .and_then(|e| e.to_str())
             .is_some_and(|e| e.eq_ignore_ascii_case("rs"));
-        if !is_rust {
+        let large = self.active_tab().is_some_and(|t| t.large_file.is_some());
+        if !is_rust || large {
             if let Some(tab) = self.active_tab_mut() {
                 tab.open_doc_uri = None;
                 tab.open_doc_version = 0;
                 tab.diagnostics.clear();
             }
             self.completion.reset();
-            self.pending_completion_request = None;
-            self.pending_definition_request = None;
+            self.cancel_lsp_requests();
             return;
         }
         if self.lsp.is_none() {
-            match LspClient::new_rust_analyzer(&self.root) {
+            match self.trusted_projects.get(&self.root) {
+                Some(true) => {}
+                Some(false) => return,
+                None => {
+                    if matches!(self.pending, PendingAction::None) {
+                        self.set_pending(PendingAction::TrustProject);
+                    }
+                    return;
+                }
+            }
+            let env = match self.lsp_settings.env_file_name() {
+                Some(name) => match load_project_env(&self.root, name) {
+                    Ok(env) => env,
+                    Err(err) => {
+                        self.set_warning(format!("Could not read {name}: {err}"));
+                        None
+                    }
+                },
+                None => None,
+            };
+            let vars = env.as_ref().map_or(&[][..], |env| env.vars.as_slice());
+            match LspClient::new_rust_analyzer(&self.root, &self.lsp_settings, vars) {
                 Ok(client) => {
                     self.lsp = Some(client);
-                    self.set_status("LSP connected");
+                    match &env {
+                        Some(env) => self.set_status(format!(
+                            "LSP connected (env from {})",
+                            relative_path(&self.root, &env.path).display()
+                        )),
+                        None => self.set_status("LSP connected"),
+                    }
                 }
                 Err(err) => {
-                    self.set_status(format!("LSP unavailable: {}", err));
+                    self.set_error(format!("LSP unavailable: {}", err));
                     return;
                 }
             }
@@ -186,9 +392,10 @@ impl App {
             if let Some(tab) = self.active_tab_mut() {
                 tab.open_doc_uri = Some(uri.clone());
                 tab.open_doc_version = version;
+                tab.lsp_text = tab.editor.lines().to_vec();
             }
             if let Some(lsp) = self.lsp.as_ref() {
-                let _ = lsp.send_notification(
+                let _ = lsp.transport.send_notification(
                     "textDocument/didOpen",
                     json!({
                         "textDocument": {
@@ -209,54 +416,179 @@ impl App {
             return;
         };
         let tab = &mut self.tabs[self.active_tab];
+        let lines = tab.editor.lines();
+        let Some(change) = changed_lines(&tab.lsp_text, lines) else {
+            return;
+        };
+        let content_change = if lsp.incremental_sync {
+            lsp_content_change(&tab.lsp_text, lines, change)
+        } else {
+            json!({ "text": lines.join("\n") })
+        };
+        tab.lsp_text.splice(
+            change.start..change.old_end,
+            lines[change.start..change.new_end].iter().cloned(),
+        );
         tab.open_doc_version += 1;
-        let text = tab.editor.lines().join("\n");
         let version = tab.open_doc_version;
-        let _ = lsp.send_notification(
+        let _ = lsp.transport.send_notification(
             "textDocument/didChange",
             json!({
                 "textDocument": {
                     "uri": uri,
                     "version": version
                 },
-                "contentChanges": [
-                    { "text": text }
-                ]
+                "contentChanges": [content_change]
             }),
         );
     }
 
-    pub(crate) fn poll_lsp(&mut self) {
-        let mut inbound = Vec::new();
-        if let Some(lsp) = self.lsp.as_ref() {
-            loop {
-                match lsp.rx.try_recv() {
-                    Ok(msg) => inbound.push(msg),
-                    Err(TryRecvError::Empty) => break,
-                    Err(TryRecvError::Disconnected) => break,
-                }
-            }
+    /// Forward watcher events so rust-analyzer sees edits, new files and
+    /// deletions made outside the open tabs.
+    pub(crate) fn notify_lsp_watched_files(&self, changes: &[(PathBuf, EventKind)]) {
+        let Some(lsp) = self.lsp.as_ref() else {
+            return;
+        };
+        // The latest event for a path decides its change type.
+        let mut seen = HashSet::new();
+        let events: Vec<Value> = changes
+            .iter()
+            .rev()
+            .filter(|(path, _)| seen.insert(path))
+            .filter_map(|(path, kind)| {
+                let uri = file_uri(path)
+                    .or_else(|| Url::from_file_path(path).ok().map(|u| u.to_string()))?;
+                let change = lsp_file_change_type(kind, path.exists());
+                Some(json!({ "uri": uri, "type": change }))
+            })
+            .collect();
+        if events.is_empty() {
+            return;
         }
-        for msg in inbound {
-            match msg {
-                LspInbound::Notification { method, params } => {
+        let _ = lsp.transport.send_notification(
+            "workspace/didChangeWatchedFiles",
+            json!({ "changes": events }),
+        );
+    }
+
+    pub(crate) fn poll_lsp(&mut self) {
+        self.cancel_stale_lsp_requests();
+        let events = match self.lsp.as_mut() {
+            Some(lsp) => lsp.transport.poll(),
+            None => return,
+        };
+        let is_request =
+            |req: &Option<PendingLspRequest>, id: i64| req.as_ref().is_some_and(|r| r.id == id);
+        for event in events {
+            match event {
+                LspEvent::Notification { method, params } => {
                     if method == "textDocument/publishDiagnostics" {
                         self.handle_publish_diagnostics(params);
                     }
                 }
-                LspInbound::Response { id, result } => {
-                    if self.pending_completion_request == Some(id) {
+                LspEvent::Response { id, result } => {
+                    if is_request(&self.pending_completion_request, id) {
                         self.pending_completion_request = None;
                         self.handle_completion_response(result);
-                    } else if self.pending_definition_request == Some(id) {
+                    } else if is_request(&self.pending_definition_request, id) {
                         self.pending_definition_request = None;
                         let _ = self.handle_definition_response(result);
+                    } else if self.pending_workspace_symbol_request == Some(id) {
+                        self.pending_workspace_symbol_request = None;
+                        self.show_workspace_symbols(parse_workspace_symbols(&result));
+                    } else if is_request(&self.pending_references_request, id) {
+                        self.pending_references_request = None;
+                        self.handle_references_response(&result);
+                    } else if let Some((_, new_name)) =
+                        self.pending_rename_request.take_if(|(r, _)| r.id == id)
+                    {
+                        self.handle_rename_response(&result, &new_name);
+                    } else if let Some(req) = self.pending_format_request.take_if(|r| r.id == id) {
+                        self.handle_format_response(&result, &req);
+                    }
+                }
+                LspEvent::TimedOut { id, method } => {
+                    if is_request(&self.pending_completion_request, id) {
+                        self.pending_completion_request = None;
+                        self.set_status("Completion timed out");
+                    } else if is_request(&self.pending_definition_request, id) {
+                        self.pending_definition_request = None;
+                        if !self.try_local_definition_jump() {
+                            self.set_status("Go to definition timed out");
+                        }
+                    } else if self.pending_workspace_symbol_request == Some(id) {
+                        self.pending_workspace_symbol_request = None;
+                        self.set_status("Workspace symbols timed out");
+                    } else if is_request(&self.pending_references_request, id) {
+                        self.pending_references_request = None;
+                        self.set_status("Find references timed out");
+                    } else if self
+                        .pending_rename_request
+                        .take_if(|(r, _)| r.id == id)
+                        .is_some()
+                    {
+                        self.set_status("Rename timed out");
+                    } else if self
+                        .pending_format_request
+                        .take_if(|r| r.id == id)
+                        .is_some()
+                    {
+                        self.set_status("Formatting timed out");
+                    } else {
+                        self.set_status(format!("LSP request timed out: {method}"));
                     }
                 }
             }
         }
     }
 
+    /// Cancel requests the user has moved on from: a definition lookup once
+    /// the cursor leaves where it was asked, a completion once the cursor
+    /// leaves that line, and both when another tab becomes active.
+    pub(crate) fn cancel_stale_lsp_requests(&mut self) {
+        let here = self
+            .active_tab()
+            .map(|t| (t.path.clone(), t.editor.cursor()));
+        let stale = |req: &PendingLspRequest, same_line_only: bool| match &here {
+            Some((path, cursor)) if *path == req.path => {
+                if same_line_only {
+                    cursor.0 != req.cursor.0
+                } else {
+                    *cursor != req.cursor
+                }
+            }
+            _ => true,
+        };
+        let mut cancel = Vec::new();
+        if let Some(req) = self.pending_definition_request.take_if(|r| stale(r, false)) {
+            cancel.push(req.id);
+        }
+        if let Some(req) = self.pending_completion_request.take_if(|r| stale(r, true)) {
+            cancel.push(req.id);
+        }
+        if let Some(lsp) = self.lsp.as_mut() {
+            for id in cancel {
+                lsp.transport.cancel(id);
+            }
+        }
+    }
+
+    pub(crate) fn cancel_lsp_requests(&mut self) {
+        let ids = [
+            self.pending_completion_request.take().map(|r| r.id),
+            self.pending_definition_request.take().map(|r| r.id),
+            self.pending_workspace_symbol_request.take(),
+            self.pending_references_request.take().map(|r| r.id),
+            self.pending_rename_request.take().map(|(r, _)| r.id),
+            self.pending_format_request.take().map(|r| r.id),
+        ];
+        if let Some(lsp) = self.lsp.as_mut() {
+            for id in ids.into_iter().flatten() {
+                lsp.transport.cancel(id);
+            }
+        }
+    }
+
     pub(crate) fn handle_publish_diagnostics(&mut self, params: Value) {
         let uri = params
             .get("uri")
@@ -274,6 +606,13 @@ impl App {
         let mut diagnostics = Vec::new();
         if let Some(items) = params.get("diagnostics").and_then(Value::as_array) {
             for d in items {
+                let position = |end: &str| {
+                    let pos = d.get("range")?.get(end)?;
+                    let row = pos.get("line")?.as_u64()? as usize;
+                    let col = pos.get("character")?.as_u64()? as usize;
+                    Some((row, col))
+                };
+                let range = position("start").zip(position("end"));
                 let line = d
                     .get("range")
                     .and_then(|r| r.get("start"))
@@ -298,6 +637,7 @@ impl App {
                     line,
                     severity,
                     message,
+                    range,
                 });
             }
         }
@@ -313,10 +653,13 @@ impl App {
         self.completion.prefix = prefix.clone();
         self.completion.ghost = None;
         let (Some(uri), Some(lsp)) = (uri, self.lsp.as_mut()) else {
-            self.set_status("LSP completion unavailable");
+            self.set_warning("LSP completion unavailable");
             return;
         };
-        match lsp.send_request(
+        if let Some(old) = self.pending_completion_request.take() {
+            lsp.transport.cancel(old.id);
+        }
+        match lsp.transport.send_request(
             "textDocument/completion",
             json!({
                 "textDocument": { "uri": uri },
@@ -325,11 +668,15 @@ impl App {
             }),
         ) {
             Ok(id) => {
-                self.pending_completion_request = Some(id);
+                self.pending_completion_request = Some(PendingLspRequest {
+                    id,
+                    path: self.tabs[self.active_tab].path.clone(),
+                    cursor: (row, col),
+                });
                 self.set_status("Completion requested");
             }
             Err(_) => {
-                self.set_status("Failed to request completion");
+                self.set_error("Failed to request completion");
             }
         }
     }
@@ -342,7 +689,7 @@ impl App {
                 .unwrap_or("LSP completion error");
             self.completion.items.clear();
             self.completion.reset();
-            self.set_status(format!("Completion error: {}", msg));
+            self.set_error(format!("Completion error: {}", msg));
             return;
         }
 
@@ -417,7 +764,8 @@ impl App {
         let prefix = self.current_identifier_prefix();
         let mut seen = std::collections::BTreeSet::new();
         let mut out = Vec::new();
-        for kw in keywords_for_lang(syntax_lang_for_path(self.open_path().map(|p| p.as_path()))) {
+        let lang = self.active_tab().map_or(SyntaxLang::Plain, |t| t.lang);
+        for kw in keywords_for_lang(lang) {
             if (prefix.is_empty() || kw.starts_with(&prefix))
                 && kw != &prefix
                 && seen.insert((*kw).to_string())
@@ -552,19 +900,24 @@ impl App {
             return;
         };
         let insert = item.insert_text.unwrap_or_else(|| item.label.clone());
-        let prefix = self.current_identifier_prefix();
-        if !prefix.is_empty() {
-            if let Some(tab) = self.active_tab_mut() {
-                for _ in 0..prefix.chars().count() {
-                    let _ = tab.editor.delete_char();
-                }
-            }
-        }
-        let inserted = self
-            .active_tab_mut()
-            .is_some_and(|t| t.editor.insert_str(insert));
-        if inserted {
-            self.on_editor_content_changed();
+        let prefix_len = self.current_identifier_prefix().chars().count();
+        if let Some(tab) = self.active_tab() {
+            // Swap the typed prefix for the completion as a single edit.
+            let (row, col) = tab.editor.cursor();
+            let mut lines = tab.editor.lines().to_vec();
+            let chars: Vec<char> = lines[row].chars().collect();
+            let col = col.min(chars.len());
+            let start = col.saturating_sub(prefix_len);
+            let before: String = chars[..start].iter().collect();
+            let after: String = chars[col..].iter().collect();
+            let inserted = text_to_lines(&insert);
+            let cursor = match inserted.len() {
+                1 => (row, start + insert.chars().count()),
+                n => (row + n - 1, inserted[n - 1].chars().count()),
+            };
+            let replaced = text_to_lines(&format!("{before}{insert}{after}"));
+            lines.splice(row..=row, replaced);
+            self.apply_edit_transaction(&lines, cursor);
         }
         self.completion.reset();
         self.set_status(format!("Inserted completion: {}", item.label));
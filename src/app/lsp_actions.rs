@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde_json::{Value, json};
+
+use super::{App, LocationPicker, PendingLspRequest};
+use crate::lsp_client::{
+    LspTextEdit, apply_text_edits, parse_locations, parse_text_edits, parse_workspace_edit,
+};
+use crate::types::{PromptMode, PromptState};
+use crate::util::{text_to_lines, uses_crlf};
+
+/// The server's message when `result` is an error response.
+fn response_error(result: &Value) -> Option<&str> {
+    result.get("code")?;
+    result.get("message")?.as_str()
+}
+
+/// The char index of UTF-16 column `col` in `line`.
+fn char_col(line: &str, col: usize) -> usize {
+    let mut units = 0;
+    line.chars()
+        .take_while(|c| {
+            units += c.len_utf16();
+            units <= col
+        })
+        .count()
+}
+
+impl App {
+    /// A language server is running and has the active file open.
+    pub(crate) fn lsp_attached(&self) -> bool {
+        self.lsp.is_some() && self.active_tab().is_some_and(|t| t.open_doc_uri.is_some())
+    }
+
+    /// Send `method` for the active file, `params` getting its
+    /// `textDocument` and, with `at_cursor`, the cursor `position`.
+    fn send_lsp_file_request(
+        &mut self,
+        method: &str,
+        what: &str,
+        at_cursor: bool,
+        mut params: Value,
+    ) -> Option<PendingLspRequest> {
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return None;
+        };
+        let (uri, path, cursor) = (
+            tab.open_doc_uri.clone(),
+            tab.path.clone(),
+            tab.editor.cursor(),
+        );
+        let (Some(uri), Some(lsp)) = (uri, self.lsp.as_mut()) else {
+            self.set_warning(format!("{what} needs a language server for this file"));
+            return None;
+        };
+        params["textDocument"] = json!({ "uri": uri });
+        if at_cursor {
+            params["position"] = json!({ "line": cursor.0, "character": cursor.1 });
+        }
+        match lsp.transport.send_request(method, params) {
+            Ok(id) => Some(PendingLspRequest { id, path, cursor }),
+            Err(_) => {
+                self.set_error(format!("Failed to request {}", what.to_lowercase()));
+                None
+            }
+        }
+    }
+
+    /// "Find References": list the uses of the symbol at the cursor.
+    pub(crate) fn request_lsp_references(&mut self) {
+        let params = json!({ "context": { "includeDeclaration": true } });
+        if let Some(req) =
+            self.send_lsp_file_request("textDocument/references", "Find References", true, params)
+        {
+            self.pending_references_request = Some(req);
+            self.set_status("Finding references");
+        }
+    }
+
+    pub(crate) fn handle_references_response(&mut self, result: &Value) {
+        if let Some(msg) = response_error(result) {
+            self.set_error(format!("Find references failed: {msg}"));
+            return;
+        }
+        let locations = parse_locations(result);
+        if locations.is_empty() {
+            self.set_status("No references found");
+            return;
+        }
+        let mut files: HashMap<PathBuf, Vec<String>> = HashMap::new();
+        let mut entries = Vec::with_capacity(locations.len());
+        for (path, row, col) in locations {
+            let lines = files.entry(path.clone()).or_insert_with(|| {
+                match self.tabs.iter().find(|t| t.path == path) {
+                    Some(tab) => tab.editor.lines().to_vec(),
+                    None => text_to_lines(&fs::read_to_string(&path).unwrap_or_default()),
+                }
+            });
+            let line = lines.get(row).map_or("", String::as_str);
+            entries.push((path, row, char_col(line, col), line.trim().to_string()));
+        }
+        let count = entries.len();
+        self.set_status(format!(
+            "{count} reference{}",
+            if count == 1 { "" } else { "s" }
+        ));
+        self.location_picker = Some(LocationPicker {
+            title: "References - Enter go to, Esc close".to_string(),
+            entries,
+            selected: 0,
+        });
+    }
+
+    /// "Rename Symbol": ask for the new name of the symbol at the cursor.
+    pub(crate) fn open_rename_symbol_prompt(&mut self) {
+        if !self.lsp_attached() {
+            self.set_warning("Rename Symbol needs a language server for this file");
+            return;
+        }
+        let symbol = self.current_identifier_at_cursor();
+        if symbol.is_empty() {
+            self.reject_action("No symbol at the cursor");
+            return;
+        }
+        self.prompt = Some(PromptState {
+            title: format!("Rename {symbol} to"),
+            cursor: symbol.len(),
+            value: symbol,
+            anchor: None,
+            history: None,
+            mode: PromptMode::RenameSymbol,
+        });
+    }
+
+    pub(crate) fn request_lsp_rename(&mut self, new_name: &str) {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            self.set_warning("The new name is empty");
+            return;
+        }
+        let params = json!({ "newName": new_name });
+        if let Some(req) =
+            self.send_lsp_file_request("textDocument/rename", "Rename Symbol", true, params)
+        {
+            self.pending_rename_request = Some((req, new_name.to_string()));
+            self.set_status(format!("Renaming to {new_name}"));
+        }
+    }
+
+    pub(crate) fn handle_rename_response(&mut self, result: &Value, new_name: &str) {
+        if let Some(msg) = response_error(result) {
+            self.set_error(format!("Rename failed: {msg}"));
+            return;
+        }
+        let edits = parse_workspace_edit(result);
+        if edits.is_empty() {
+            self.set_status("Nothing to rename");
+            return;
+        }
+        match self.apply_workspace_edit(&edits) {
+            Ok(()) => self.set_status(format!(
+                "Renamed to {new_name} in {} file{}",
+                edits.len(),
+                if edits.len() == 1 { "" } else { "s" }
+            )),
+            Err(err) => self.set_error(format!("Rename failed: {err}")),
+        }
+    }
+
+    /// Apply each file's edits: to its buffer as one undo step when it is
+    /// open, or else straight to the file on disk.
+    pub(crate) fn apply_workspace_edit(
+        &mut self,
+        edits: &[(PathBuf, Vec<LspTextEdit>)],
+    ) -> io::Result<()> {
+        let active = self.active_tab;
+        let mut result = Ok(());
+        for (path, file_edits) in edits {
+            match self.tabs.iter().position(|t| t.path == *path) {
+                Some(idx) => {
+                    self.active_tab = idx;
+                    let tab = &self.tabs[idx];
+                    let lines = apply_text_edits(tab.editor.lines(), file_edits);
+                    let cursor = tab.editor.cursor();
+                    self.apply_edit_transaction(&lines, cursor);
+                }
+                None => {
+                    let write = fs::read_to_string(path).and_then(|text| {
+                        let lines = apply_text_edits(&text_to_lines(&text), file_edits);
+                        let newline = if uses_crlf(&text) { "\r\n" } else { "\n" };
+                        fs::write(path, lines.join(newline))
+                    });
+                    if write.is_err() {
+                        result = write;
+                    }
+                }
+            }
+        }
+        self.active_tab = active;
+        result
+    }
+
+    /// "Format Document": reformat the active file with the language
+    /// server, using the indentation settings.
+    pub(crate) fn request_lsp_format(&mut self) {
+        let params = json!({
+            "options": {
+                "tabSize": self.editor_settings.tab_width.max(1),
+                "insertSpaces": self.editor_settings.insert_spaces,
+            }
+        });
+        if let Some(req) =
+            self.send_lsp_file_request("textDocument/formatting", "Format Document", false, params)
+        {
+            self.pending_format_request = Some(req);
+            self.set_status("Formatting");
+        }
+    }
+
+    pub(crate) fn handle_format_response(&mut self, result: &Value, req: &PendingLspRequest) {
+        if let Some(msg) = response_error(result) {
+            self.set_error(format!("Formatting failed: {msg}"));
+            return;
+        }
+        let edits = parse_text_edits(result);
+        if edits.is_empty() {
+            self.set_status("Already formatted");
+            return;
+        }
+        // The file may have been closed meanwhile; then there is nothing
+        // to format.
+        if self.tabs.iter().any(|t| t.path == req.path) {
+            let _ = self.apply_workspace_edit(&[(req.path.clone(), edits)]);
+            self.set_status("Formatted");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    fn edit(line: usize, start: usize, end: usize, text: &str) -> LspTextEdit {
+        LspTextEdit {
+            start: (line, start),
+            end: (line, end),
+            new_text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn workspace_edits_update_open_buffers_and_files_on_disk() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let open = root.join("a.txt");
+        let closed = root.join("b.txt");
+        fs::write(&open, "let old = 1;\n").expect("write");
+        fs::write(&closed, "use old;\r\n").expect("write");
+        let mut app = App::new_for_test(root);
+        app.open_file(open.clone()).expect("open");
+        assert!(!app.lsp_attached());
+
+        app.handle_rename_response(&json!({ "code": -32602, "message": "no symbol" }), "new");
+        assert_eq!(app.status, "Rename failed: no symbol");
+        app.dismiss_status();
+        app.apply_workspace_edit(&[
+            (open.clone(), vec![edit(0, 4, 7, "new")]),
+            (closed.clone(), vec![edit(0, 4, 7, "new")]),
+        ])
+        .expect("apply");
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines(), ["let new = 1;", ""]);
+        assert!(tab.dirty);
+        assert_eq!(fs::read_to_string(&closed).expect("read"), "use new;\r\n");
+
+        let req = PendingLspRequest {
+            id: 1,
+            path: Path::new("gone.txt").to_path_buf(),
+            cursor: (0, 0),
+        };
+        app.handle_format_response(&json!([]), &req);
+        assert_eq!(app.status, "Already formatted");
+
+        let refs = json!([{ "uri": url::Url::from_file_path(&open).expect("uri").as_str(),
+            "range": { "start": { "line": 0, "character": 4 },
+                       "end": { "line": 0, "character": 7 } } }]);
+        app.handle_references_response(&refs);
+        let picker = app.location_picker.as_ref().expect("picker");
+        assert_eq!(picker.entries, [(open, 0, 4, "let new = 1;".to_string())]);
+    }
+}
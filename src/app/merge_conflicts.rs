@@ -0,0 +1,97 @@
+use std::io;
+
+use super::App;
+use crate::merge_conflicts::{ConflictChoice, resolve_conflict};
+
+impl App {
+    /// "Merge Conflict: Accept Ours/Theirs/Both": resolve the conflict under
+    /// the cursor, as one undo step.
+    pub(crate) fn accept_merge_conflict(&mut self, choice: ConflictChoice) {
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return;
+        };
+        let row = tab.editor.cursor().0;
+        let Some(region) = tab
+            .merge_conflicts
+            .iter()
+            .find(|c| c.contains(row))
+            .copied()
+        else {
+            self.reject_action("The cursor is not in a merge conflict");
+            return;
+        };
+        let lines = resolve_conflict(tab.editor.lines(), &region, choice);
+        self.apply_edit_transaction(&lines, (region.start, 0));
+        let left = self.active_tab().map_or(0, |t| t.merge_conflicts.len());
+        self.set_status(format!(
+            "Accepted {}; {left} conflict{} left",
+            choice.label(),
+            if left == 1 { "" } else { "s" }
+        ));
+    }
+
+    /// "Merge Conflict: Next/Previous": move to the next or previous
+    /// conflict in the file, wrapping around.
+    pub(crate) fn jump_to_merge_conflict(&mut self, next: bool) -> io::Result<()> {
+        let Some(tab) = self.active_tab() else {
+            self.set_status("No file open");
+            return Ok(());
+        };
+        let conflicts = &tab.merge_conflicts;
+        if conflicts.is_empty() {
+            self.reject_action("No merge conflicts");
+            return Ok(());
+        }
+        let row = tab.editor.cursor().0;
+        let idx = if next {
+            conflicts.iter().position(|c| c.start > row).unwrap_or(0)
+        } else {
+            conflicts
+                .iter()
+                .rposition(|c| c.start < row)
+                .unwrap_or(conflicts.len() - 1)
+        };
+        let (start, count, path) = (conflicts[idx].start, conflicts.len(), tab.path.clone());
+        self.record_jump();
+        self.go_to_location(&path, start, 0)?;
+        self.set_status(format!("Merge conflict {}/{count}", idx + 1));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn conflicts_are_navigated_and_resolved_in_the_buffer() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("merge.txt");
+        fs::write(
+            &file,
+            "top\n<<<<<<< HEAD\na\n=======\nb\n>>>>>>> x\nmid\n<<<<<<< HEAD\nc\n=======\nd\n>>>>>>> y\n",
+        )
+        .expect("write");
+        let mut app = App::new_for_test(tmp.path());
+        app.open_file(file).expect("open");
+        assert_eq!(app.active_tab().expect("tab").merge_conflicts.len(), 2);
+
+        app.accept_merge_conflict(ConflictChoice::Ours);
+        assert_eq!(app.status, "The cursor is not in a merge conflict");
+        app.jump_to_merge_conflict(false).expect("previous");
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (7, 0));
+        app.jump_to_merge_conflict(true).expect("next wraps");
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (1, 0));
+
+        app.accept_merge_conflict(ConflictChoice::Theirs);
+        assert_eq!(app.status, "Accepted theirs; 1 conflict left");
+        app.jump_to_merge_conflict(true).expect("next");
+        app.accept_merge_conflict(ConflictChoice::Both);
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.editor.lines(), ["top", "b", "mid", "c", "d", ""]);
+        assert!(tab.merge_conflicts.is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+use std::io;
+use std::sync::mpsc;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use super::{App, SnippetOutputState};
+use crate::playground::{
+    code_block_at, create_playground_dir, snippet_main_rs, snippet_manifest, spawn_snippet_run,
+};
+
+impl App {
+    /// Build and run the Rust code block under the cursor in a scratch
+    /// crate; the output opens in a popup when it finishes.
+    pub(crate) fn run_code_block(&mut self) {
+        if self.snippet_rx.is_some() {
+            self.set_warning("A code block is already running");
+            return;
+        }
+        let Some(tab) = self.active_tab() else {
+            self.set_warning("No file open");
+            return;
+        };
+        let (row, _) = tab.editor.cursor();
+        let Some(block) = code_block_at(tab.editor.lines(), row) else {
+            self.set_warning("Cursor is not in a fenced code block");
+            return;
+        };
+        if !block.is_rust() {
+            let lang = if block.info.is_empty() {
+                "untagged"
+            } else {
+                block.info.as_str()
+            };
+            self.set_warning(format!("Only Rust code blocks can be run ({lang})"));
+            return;
+        }
+        // The block comes from the project and may depend on its crate,
+        // whose build scripts then run.
+        if !self.require_project_trust("Run Code Block") {
+            return;
+        }
+        let dir = match self.playground_dir.as_ref() {
+            Some(dir) => dir.path().to_path_buf(),
+            None => match create_playground_dir() {
+                Ok(dir) => self.playground_dir.insert(dir).path().to_path_buf(),
+                Err(err) => {
+                    self.set_error(format!("Could not create the playground folder: {err}"));
+                    return;
+                }
+            },
+        };
+        let run = block.runs();
+        let manifest = snippet_manifest(&block.code, &self.root);
+        let main_rs = snippet_main_rs(&block.code);
+        let (tx, rx) = mpsc::channel();
+        self.snippet_rx = Some(rx);
+        spawn_snippet_run(dir, manifest, main_rs, run, tx);
+        self.set_status(if run {
+            "Running code block..."
+        } else {
+            "Building code block (no_run)..."
+        });
+    }
+
+    /// Show the result of a finished "Run Code Block".
+    pub(crate) fn poll_snippet_run(&mut self) {
+        let result = match self.snippet_rx.as_ref().map(|rx| rx.try_recv()) {
+            Some(Ok(result)) => result,
+            Some(Err(mpsc::TryRecvError::Disconnected)) => {
+                Err(io::Error::other("runner thread exited"))
+            }
+            Some(Err(mpsc::TryRecvError::Empty)) | None => return,
+        };
+        self.snippet_rx = None;
+        match result {
+            Ok(run) => {
+                let title = match (run.success, run.exit_code) {
+                    (true, _) => "Code Block Output - ok".to_string(),
+                    (false, Some(code)) => format!("Code Block Output - failed (exit {code})"),
+                    (false, None) => "Code Block Output - killed".to_string(),
+                };
+                if run.success {
+                    self.set_status("Code block finished");
+                } else {
+                    self.set_warning("Code block failed");
+                }
+                let text = if run.output.is_empty() {
+                    "(no output)".to_string()
+                } else {
+                    run.output
+                };
+                self.snippet_output = Some(SnippetOutputState {
+                    title,
+                    text,
+                    success: run.success,
+                    scroll: 0,
+                });
+            }
+            Err(err) => self.set_error(format!("Could not run cargo: {err}")),
+        }
+    }
+
+    pub(crate) fn handle_snippet_output_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        const PAGE_ROWS: usize = 10;
+        let Some(view) = self.snippet_output.as_mut() else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.snippet_output = None,
+            KeyCode::Down | KeyCode::Char('j') => view.scroll += 1,
+            KeyCode::Up | KeyCode::Char('k') => view.scroll = view.scroll.saturating_sub(1),
+            KeyCode::PageDown => view.scroll += PAGE_ROWS,
+            KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(PAGE_ROWS),
+            KeyCode::Home => view.scroll = 0,
+            KeyCode::Char('c') | KeyCode::Char('y') => {
+                let text = view.text.clone();
+                match self.clipboard.as_mut().map(|c| c.set_text(text)) {
+                    Some(Ok(())) => self.set_status("Copied output"),
+                    _ => self.set_warning("Clipboard unavailable"),
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::playground::SnippetRun;
+    use crate::types::PendingAction;
+    use ratatui::crossterm::event::KeyModifiers;
+    use ratatui_textarea::CursorMove;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn finished_runs_open_the_output_popup() {
+        let tmp = tempdir().expect("tempdir");
+        let file = tmp.path().join("notes.md");
+        fs::write(&file, "```text\nhello\n```\n\n```rust\nfn main() {}\n```\n").expect("write");
+        let mut app = App::new_for_test(tmp.path());
+        app.open_file(file).expect("open");
+        app.run_code_block();
+        assert!(app.snippet_rx.is_none());
+        assert!(app.status.contains("Only Rust"));
+
+        app.tabs[0].editor.move_cursor(CursorMove::Jump(5, 0));
+        app.run_code_block();
+        assert!(app.snippet_rx.is_none() && app.playground_dir.is_none());
+        assert!(matches!(app.pending, PendingAction::TrustProject));
+        app.pending = PendingAction::None;
+
+        let (tx, rx) = mpsc::channel();
+        app.snippet_rx = Some(rx);
+        app.run_code_block();
+        assert!(app.status.contains("already running"));
+        app.poll_snippet_run();
+        assert!(app.snippet_output.is_none());
+        tx.send(Ok(SnippetRun {
+            success: false,
+            exit_code: Some(101),
+            output: "error[E0425]: cannot find value `y`".to_string(),
+        }))
+        .expect("send");
+        app.poll_snippet_run();
+        assert!(app.snippet_rx.is_none());
+        let view = app.snippet_output.as_ref().expect("output");
+        assert_eq!(view.title, "Code Block Output - failed (exit 101)");
+        assert!(view.text.contains("E0425"));
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("esc");
+        assert!(app.snippet_output.is_none());
+    }
+}
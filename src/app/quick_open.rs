@@ -0,0 +1,255 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::json;
+
+use super::App;
+use crate::outline::document_symbols;
+use crate::types::QuickOpenItem;
+use crate::util::{fuzzy_score, parse_goto_target, relative_path};
+
+impl App {
+    /// Files opened in a project that Quick Open remembers.
+    const RECENT_FILES_LEN: usize = 50;
+    const QUICK_OPEN_LIMIT: usize = 200;
+
+    /// Move `path` to the front of this project's recent files.
+    pub(crate) fn record_recent_file(&mut self, path: &Path) {
+        let recent = self.recent_files.entry(self.root.clone()).or_default();
+        recent.retain(|p| p != path);
+        recent.insert(0, path.to_path_buf());
+        recent.truncate(Self::RECENT_FILES_LEN);
+    }
+
+    /// Fill Quick Open from its query. The first character picks the
+    /// provider: `@` symbols in the active file, `#` workspace symbols from
+    /// rust-analyzer, `:` a line; anything else matches file paths.
+    pub(crate) fn refresh_file_picker_results(&mut self) {
+        let query = self.file_picker_query.clone();
+        self.file_picker_results = if self.file_picker_changed_only {
+            self.changed_file_items(&query)
+        } else if let Some(query) = query.strip_prefix('@') {
+            self.document_symbol_items(query)
+        } else if let Some(query) = query.strip_prefix('#') {
+            self.request_workspace_symbols(query);
+            Vec::new()
+        } else if query.starts_with(':') {
+            self.line_items(&query)
+        } else {
+            self.file_items(&query)
+        };
+        self.file_picker_index = self
+            .file_picker_index
+            .min(self.file_picker_results.len().saturating_sub(1));
+    }
+
+    fn changed_file_items(&self, query: &str) -> Vec<QuickOpenItem> {
+        let query = query.to_ascii_lowercase();
+        self.changed_files
+            .iter()
+            .filter(|(path, _)| {
+                let rel = relative_path(&self.root, path).display().to_string();
+                fuzzy_score(&query, &rel).is_some()
+            })
+            .map(|(path, _)| QuickOpenItem::file(path.clone()))
+            .collect()
+    }
+
+    /// Matching files, the ones opened recently first (most recent first),
+    /// then the rest by score.
+    fn file_items(&mut self, query: &str) -> Vec<QuickOpenItem> {
+        // Lazily rebuild the file list if it was invalidated
+        if self.cached_file_list.is_empty() {
+            let mut files = Vec::new();
+            self.collect_workspace_files(&mut files);
+            self.cached_file_list = files;
+        }
+        let query = query.to_ascii_lowercase();
+        let recent = self.recent_files.get(&self.root);
+        let recency = |path: &PathBuf| {
+            recent
+                .and_then(|r| r.iter().position(|p| p == path))
+                .unwrap_or(usize::MAX)
+        };
+        let mut scored: Vec<(usize, usize, PathBuf)> = self
+            .cached_file_list
+            .iter()
+            .filter_map(|path| {
+                let rel = self.workspace_relative(path).display().to_string();
+                fuzzy_score(&query, &rel).map(|score| (recency(path), score, path.clone()))
+            })
+            .collect();
+        scored.sort_by(|(ra, sa, pa), (rb, sb, pb)| {
+            ra.cmp(rb)
+                .then(sa.cmp(sb))
+                .then_with(|| pa.as_os_str().len().cmp(&pb.as_os_str().len()))
+        });
+        scored
+            .into_iter()
+            .map(|(_, _, p)| QuickOpenItem::file(p))
+            .take(Self::QUICK_OPEN_LIMIT)
+            .collect()
+    }
+
+    /// Declarations in the active file matching `query`, best first.
+    fn document_symbol_items(&self, query: &str) -> Vec<QuickOpenItem> {
+        let Some(tab) = self.active_tab() else {
+            return Vec::new();
+        };
+        let lines = tab.editor.lines();
+        let query = query.trim().to_ascii_lowercase();
+        let mut scored: Vec<(usize, QuickOpenItem)> = document_symbols(lines, &tab.fold_ranges)
+            .into_iter()
+            .filter_map(|symbol| {
+                let score = fuzzy_score(&query, &symbol.label)?;
+                let col = lines[symbol.row]
+                    .chars()
+                    .take_while(|c| c.is_whitespace())
+                    .count();
+                let item = QuickOpenItem {
+                    path: tab.path.clone(),
+                    position: Some((symbol.row, col)),
+                    label: Some(symbol.label),
+                };
+                Some((score, item))
+            })
+            .collect();
+        scored.sort_by_key(|(score, _)| *score);
+        scored.into_iter().map(|(_, item)| item).collect()
+    }
+
+    /// `:line[:col]` in the active file.
+    fn line_items(&self, query: &str) -> Vec<QuickOpenItem> {
+        let (Some(tab), Some((line, col))) = (self.active_tab(), parse_goto_target(query)) else {
+            return Vec::new();
+        };
+        if line == 0 || col == Some(0) {
+            return Vec::new();
+        }
+        let row = (line - 1).min(tab.editor.lines().len().saturating_sub(1));
+        let col = col.map_or(0, |c| c - 1);
+        let label = match col {
+            0 => format!("Go to line {}", row + 1),
+            _ => format!("Go to line {}, column {}", row + 1, col + 1),
+        };
+        vec![QuickOpenItem {
+            path: tab.path.clone(),
+            position: Some((row, col)),
+            label: Some(label),
+        }]
+    }
+
+    /// Ask rust-analyzer for workspace symbols matching `query`; the answer
+    /// fills Quick Open when it arrives. A newer query cancels the last one.
+    fn request_workspace_symbols(&mut self, query: &str) {
+        let Some(lsp) = self.lsp.as_mut() else {
+            self.set_status("Workspace symbols need rust-analyzer: open a Rust file first");
+            return;
+        };
+        if let Some(old) = self.pending_workspace_symbol_request.take() {
+            lsp.transport.cancel(old);
+        }
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+        match lsp
+            .transport
+            .send_request("workspace/symbol", json!({ "query": query }))
+        {
+            Ok(id) => self.pending_workspace_symbol_request = Some(id),
+            Err(_) => self.set_error("Failed to request workspace symbols"),
+        }
+    }
+
+    /// Workspace symbols arrived; shown only while a `#` query is still open.
+    pub(crate) fn show_workspace_symbols(&mut self, items: Vec<QuickOpenItem>) {
+        if !self.file_picker_open || !self.file_picker_query.starts_with('#') {
+            return;
+        }
+        self.file_picker_results = items;
+        self.file_picker_index = 0;
+    }
+
+    pub(crate) fn close_file_picker(&mut self) {
+        self.file_picker_open = false;
+        self.file_picker_query.clear();
+        if let Some(id) = self.pending_workspace_symbol_request.take()
+            && let Some(lsp) = self.lsp.as_mut()
+        {
+            lsp.transport.cancel(id);
+        }
+    }
+
+    pub(crate) fn open_file_picker_selection(&mut self) -> io::Result<()> {
+        let Some(item) = self
+            .file_picker_results
+            .get(self.file_picker_index)
+            .cloned()
+        else {
+            return Ok(());
+        };
+        self.close_file_picker();
+        match item.position {
+            Some((row, col)) => {
+                self.record_jump();
+                self.go_to_location(&item.path, row, col)?;
+            }
+            None => self.open_file(item.path)?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn quick_open_ranks_recent_files_and_switches_provider_by_prefix() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        let main = root.join("main.rs");
+        fs::write(
+            &main,
+            "struct App;\nimpl App {\n    fn run(&self) {\n    }\n}\n",
+        )
+        .expect("write");
+        fs::write(root.join("lib.rs"), "").expect("write");
+        fs::write(root.join("zeta_notes.txt"), "").expect("write");
+        let mut app = App::new_for_test(root);
+        app.open_file(root.join("zeta_notes.txt")).expect("open");
+        app.open_file(main.clone()).expect("open");
+        app.pending = crate::types::PendingAction::None;
+
+        app.refresh_file_picker_results();
+        let paths: Vec<_> = app.file_picker_results.iter().map(|i| &i.path).collect();
+        assert_eq!(paths[..2], [&main, &root.join("zeta_notes.txt")]);
+
+        app.file_picker_query = "@run".to_string();
+        app.refresh_file_picker_results();
+        assert_eq!(
+            app.file_picker_results,
+            [QuickOpenItem {
+                path: main.clone(),
+                position: Some((2, 4)),
+                label: Some("run".to_string()),
+            }]
+        );
+
+        app.file_picker_query = ":4:3".to_string();
+        app.refresh_file_picker_results();
+        assert_eq!(app.file_picker_results[0].position, Some((3, 2)));
+        app.file_picker_open = true;
+        app.open_file_picker_selection().expect("jump");
+        assert!(!app.file_picker_open);
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (3, 2));
+
+        app.file_picker_query = "#App".to_string();
+        app.refresh_file_picker_results();
+        assert!(app.file_picker_results.is_empty());
+        assert!(app.status.contains("need rust-analyzer"));
+    }
+}
@@ -0,0 +1,216 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::{App, LocationPicker};
+use crate::util::relative_path;
+
+/// `mod name;` declarations of modules kept in their own files.
+static MOD_DECL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+([A-Za-z_][A-Za-z0-9_]*)\s*;")
+        .expect("valid mod regex")
+});
+
+/// The directory holding a Rust file's parent module and the file's module
+/// name; `None` for crate roots (`lib.rs`, `main.rs`, `build.rs` and the
+/// files directly in `bin`, `tests`, `examples` or `benches`).
+fn module_identity(path: &Path) -> Option<(PathBuf, String)> {
+    let dir = path.parent()?;
+    let name = path.file_name()?.to_str()?;
+    let stem = name.strip_suffix(".rs")?;
+    let dir_name = dir.file_name().and_then(|n| n.to_str());
+    if matches!(stem, "lib" | "main" | "build")
+        || matches!(dir_name, Some("bin" | "tests" | "examples" | "benches"))
+    {
+        return None;
+    }
+    if stem == "mod" {
+        return Some((dir.parent()?.to_path_buf(), dir_name?.to_string()));
+    }
+    Some((dir.to_path_buf(), stem.to_string()))
+}
+
+/// Where the submodules a file declares live: next to it for `mod.rs` and
+/// crate roots, otherwise in the directory named after it.
+fn child_module_dir(path: &Path) -> Option<PathBuf> {
+    let dir = path.parent()?;
+    let stem = path.file_stem()?.to_str()?;
+    if module_identity(path).is_none() || stem == "mod" {
+        Some(dir.to_path_buf())
+    } else {
+        Some(dir.join(stem))
+    }
+}
+
+/// Modules declared with `mod name;`, as `(row, name)`.
+fn declared_modules(text: &str) -> Vec<(usize, String)> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(row, line)| Some((row, MOD_DECL_RE.captures(line)?[1].to_string())))
+        .collect()
+}
+
+/// The file for module `name` declared in `parent`, if it exists.
+fn module_file(parent: &Path, name: &str) -> Option<PathBuf> {
+    let dir = child_module_dir(parent)?;
+    [
+        dir.join(format!("{name}.rs")),
+        dir.join(name).join("mod.rs"),
+    ]
+    .into_iter()
+    .find(|p| p.is_file())
+}
+
+impl App {
+    /// The text of `path`, from its tab when it is open.
+    fn source_text(&self, path: &Path) -> Option<String> {
+        match self.tabs.iter().find(|t| t.path == path) {
+            Some(tab) => Some(tab.editor.lines().join("\n")),
+            None => fs::read_to_string(path).ok(),
+        }
+    }
+
+    /// The file declaring the active Rust file's module, with the row of
+    /// its `mod` line when one is found. Warns and returns `None` when
+    /// there is no parent.
+    fn parent_module(&mut self) -> Option<(PathBuf, Option<usize>)> {
+        let Some(path) = self
+            .open_path()
+            .filter(|p| p.extension() == Some("rs".as_ref()))
+        else {
+            self.set_status("Not a Rust file");
+            return None;
+        };
+        let Some((dir, name)) = module_identity(path) else {
+            self.set_status("This file is a crate root");
+            return None;
+        };
+        let mut candidates = vec![dir.join("mod.rs")];
+        if let (Some(up), Some(dir_name)) = (dir.parent(), dir.file_name()) {
+            candidates.push(up.join(dir_name).with_extension("rs"));
+        }
+        candidates.extend([dir.join("lib.rs"), dir.join("main.rs")]);
+        candidates.retain(|p| p.is_file());
+        let declared = candidates.iter().find_map(|p| {
+            let text = self.source_text(p)?;
+            let row = declared_modules(&text)
+                .into_iter()
+                .find(|(_, n)| *n == name)?
+                .0;
+            Some((p.clone(), Some(row)))
+        });
+        let found = declared.or_else(|| candidates.first().map(|p| (p.clone(), None)));
+        if found.is_none() {
+            self.set_warning(format!("No parent module file found for {name}"));
+        }
+        found
+    }
+
+    /// "Go to Parent Module": open the `mod.rs`, `lib.rs` or `<dir>.rs`
+    /// that declares the active file's module.
+    pub(crate) fn go_to_parent_module(&mut self) -> io::Result<()> {
+        let Some((parent, _)) = self.parent_module() else {
+            return Ok(());
+        };
+        self.open_file(parent)
+    }
+
+    /// "Go to Module Declaration": the `mod name;` line for the active file.
+    pub(crate) fn go_to_module_declaration(&mut self) -> io::Result<()> {
+        let Some((parent, row)) = self.parent_module() else {
+            return Ok(());
+        };
+        let Some(row) = row else {
+            let name = relative_path(&self.root, &parent).display().to_string();
+            self.open_file(parent)?;
+            self.set_warning(format!("No mod declaration for this file in {name}"));
+            return Ok(());
+        };
+        self.record_jump();
+        self.go_to_location(&parent, row, 0)?;
+        Ok(())
+    }
+
+    /// "Sibling Modules": pick one of the modules declared next to the
+    /// active file's, in declaration order.
+    pub(crate) fn open_sibling_modules(&mut self) {
+        let Some((parent, _)) = self.parent_module() else {
+            return;
+        };
+        let current = self.open_path().cloned();
+        let text = self.source_text(&parent).unwrap_or_default();
+        let entries: Vec<_> = declared_modules(&text)
+            .into_iter()
+            .filter_map(|(_, name)| {
+                let file = module_file(&parent, &name)?;
+                Some((file, 0, 0, format!("mod {name}")))
+            })
+            .collect();
+        if entries.is_empty() {
+            self.set_status("No sibling modules");
+            return;
+        }
+        let selected = entries
+            .iter()
+            .position(|e| Some(&e.0) == current.as_ref())
+            .unwrap_or(0);
+        self.location_picker = Some(LocationPicker {
+            title: format!(
+                "Modules in {} - Enter open, Esc close",
+                relative_path(&self.root, &parent).display()
+            ),
+            entries,
+            selected,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn rust_module_navigation_follows_mod_declarations() {
+        let tmp = tempdir().expect("tempdir");
+        let src = tmp.path().join("src");
+        fs::create_dir_all(src.join("app")).expect("mkdir");
+        fs::create_dir_all(src.join("ui")).expect("mkdir");
+        fs::write(src.join("lib.rs"), "mod app;\npub mod ui;\n").expect("write");
+        fs::write(
+            src.join("app.rs"),
+            "use x;\n\nmod core;\npub(crate) mod input;\n",
+        )
+        .expect("write");
+        fs::write(src.join("app/core.rs"), "").expect("write");
+        fs::write(src.join("app/input.rs"), "").expect("write");
+        fs::write(src.join("ui/mod.rs"), "mod editor;\n").expect("write");
+        fs::write(src.join("ui/editor.rs"), "").expect("write");
+        let mut app = App::new_for_test(tmp.path());
+
+        // app/input.rs is declared in app.rs, on its fourth line.
+        app.open_file(src.join("app/input.rs")).expect("open");
+        app.go_to_module_declaration().expect("declaration");
+        assert_eq!(app.open_path(), Some(&src.join("app.rs")));
+        assert_eq!(app.active_tab().expect("tab").editor.cursor(), (3, 0));
+
+        app.open_file(src.join("app/core.rs")).expect("open");
+        app.open_sibling_modules();
+        let picker = app.location_picker.take().expect("picker");
+        let files: Vec<_> = picker.entries.iter().map(|e| e.0.clone()).collect();
+        assert_eq!(files, [src.join("app/core.rs"), src.join("app/input.rs")]);
+        assert_eq!(picker.selected, 0);
+
+        // mod.rs files belong to the directory's parent.
+        app.open_file(src.join("ui/editor.rs")).expect("open");
+        app.go_to_parent_module().expect("parent");
+        assert_eq!(app.open_path(), Some(&src.join("ui/mod.rs")));
+        app.go_to_parent_module().expect("parent");
+        assert_eq!(app.open_path(), Some(&src.join("lib.rs")));
+        app.go_to_parent_module().expect("parent");
+        assert_eq!(app.open_path(), Some(&src.join("lib.rs")));
+    }
+}
@@ -1,11 +1,29 @@
-use super::App;
+use super::{App, RunningSearch};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
 use std::io;
-use std::process::Command;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, TryRecvError};
+use std::time::Instant;
 
-use crate::util::{parse_rg_line, relative_path, to_u16_saturating};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::ignore::globs_allow;
+use crate::keybinds::{KeyAction, KeyScope};
+use crate::project_search::{SearchEvent, rg_command, spawn_search};
+use crate::tab::{ProjectSearchHit, SearchMatch};
+use crate::types::{ProjectSearchOptions, PromptMode, PromptState, TodoItem};
+use crate::util::{
+    ReplaceSegment, TODO_MARKERS, git_blame_authors, parse_rg_line, parse_search_globs,
+    project_search_regex, relative_path, replace_segments, search_files, to_u16_saturating,
+    todo_comment_text, todo_report,
+};
 
 impl App {
     pub(crate) fn search_in_open_file(&mut self, query: &str) {
+        self.find_origin = None;
         if self.open_path().is_none() {
             self.set_status("Open a file first");
             return;
@@ -14,22 +32,161 @@ impl App {
             if let Some(tab) = self.active_tab_mut() {
                 let _ = tab.editor.set_search_pattern("");
             }
+            self.refresh_search_matches();
             self.set_status("Find cleared");
             return;
         }
         let tab = &mut self.tabs[self.active_tab];
         match tab.editor.set_search_pattern(query) {
             Ok(()) => {
-                if tab.editor.search_forward(true) {
+                let found = tab.editor.search_forward(true);
+                self.refresh_search_matches();
+                if found {
+                    self.find_bar_open = true;
                     self.set_status(format!("Find: {}", query));
                 } else {
                     self.set_status(format!("No match: {}", query));
                 }
             }
             Err(err) => {
-                self.set_status(format!("Invalid regex: {}", err));
+                self.set_warning(format!("Invalid regex: {}", err));
+            }
+        }
+    }
+
+    /// Re-run the Find prompt's query from where the cursor was when the
+    /// prompt opened, so each keystroke jumps to the first match after it.
+    pub(crate) fn update_incremental_find(&mut self, query: &str) {
+        let Some((row, col)) = self.find_origin else {
+            return;
+        };
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+            to_u16_saturating(row),
+            to_u16_saturating(col),
+        ));
+        // A half-typed regex is common while typing; show no matches
+        // rather than keeping the previous pattern's.
+        if tab.editor.set_search_pattern(query).is_err() {
+            let _ = tab.editor.set_search_pattern("");
+        } else if !query.is_empty() {
+            tab.editor.search_forward(true);
+        }
+        self.refresh_search_matches();
+        self.sync_editor_scroll_guess();
+    }
+
+    /// Esc in the Find prompt: go back to where the search started.
+    pub(crate) fn cancel_incremental_find(&mut self) {
+        let origin = self.find_origin.take();
+        if let Some(tab) = self.active_tab_mut() {
+            let _ = tab.editor.set_search_pattern("");
+            if let Some((row, col)) = origin {
+                tab.editor.move_cursor(ratatui_textarea::CursorMove::Jump(
+                    to_u16_saturating(row),
+                    to_u16_saturating(col),
+                ));
+            }
+        }
+        self.refresh_search_matches();
+        self.sync_editor_scroll_guess();
+    }
+
+    /// Recompute the active tab's match cache for its search pattern.
+    pub(crate) fn refresh_search_matches(&mut self) {
+        let Some(tab) = self.active_tab_mut() else {
+            return;
+        };
+        let mut matches = Vec::new();
+        if let Some(re) = tab.editor.search_pattern() {
+            for (row, line) in tab.editor.lines().iter().enumerate() {
+                for m in re.find_iter(line).filter(|m| !m.is_empty()) {
+                    let start = line[..m.start()].chars().count();
+                    let end = start + m.as_str().chars().count();
+                    matches.push(SearchMatch { row, start, end });
+                }
             }
         }
+        tab.search_matches = matches;
+    }
+
+    /// 1-based index of the match at (or after) the cursor, and the total.
+    pub(crate) fn search_match_position(&self) -> Option<(usize, usize)> {
+        let tab = self.active_tab()?;
+        let total = tab.search_matches.len();
+        if total == 0 {
+            return None;
+        }
+        let cursor = tab.editor.cursor();
+        let idx = tab
+            .search_matches
+            .partition_point(|m| (m.row, m.start) < cursor)
+            .min(total - 1);
+        Some((idx + 1, total))
+    }
+
+    pub(crate) fn find_next(&mut self) {
+        if self
+            .active_tab_mut()
+            .is_some_and(|t| t.editor.search_forward(false))
+        {
+            self.sync_editor_scroll_guess();
+            self.set_find_status("Find next");
+        } else {
+            self.reject_action("No next match");
+        }
+    }
+
+    pub(crate) fn find_prev(&mut self) {
+        if self
+            .active_tab_mut()
+            .is_some_and(|t| t.editor.search_back(false))
+        {
+            self.sync_editor_scroll_guess();
+            self.set_find_status("Find previous");
+        } else {
+            self.reject_action("No previous match");
+        }
+    }
+
+    fn set_find_status(&mut self, action: &str) {
+        match self.search_match_position() {
+            Some((idx, total)) => self.set_status(format!("{action}: {idx}/{total}")),
+            None => self.set_status(action),
+        }
+    }
+
+    /// Keys for the Find bar left open after confirming a search: n/N cycle
+    /// and Esc closes it and clears highlights. Returns `false` for any
+    /// other key, which closes the bar and is handled as usual.
+    pub(crate) fn handle_find_bar_key(&mut self, key: KeyEvent) -> bool {
+        match (key.modifiers, key.code) {
+            (KeyModifiers::NONE, KeyCode::Char('n')) => self.find_next(),
+            (KeyModifiers::SHIFT, KeyCode::Char('N'))
+            | (KeyModifiers::NONE, KeyCode::Char('N')) => self.find_prev(),
+            (_, KeyCode::Esc) => {
+                self.find_bar_open = false;
+                if let Some(tab) = self.active_tab_mut() {
+                    let _ = tab.editor.set_search_pattern("");
+                }
+                self.refresh_search_matches();
+                self.set_status("Find closed");
+            }
+            _ if matches!(
+                self.keybinds.lookup(&key, KeyScope::Editor),
+                Some(KeyAction::FindNext | KeyAction::FindPrev)
+            ) =>
+            {
+                return false;
+            }
+            _ => {
+                self.find_bar_open = false;
+                return false;
+            }
+        }
+        true
     }
 
     pub(crate) fn replace_in_open_file(&mut self, search: &str, replacement: &str) {
@@ -38,7 +195,7 @@ impl App {
             return;
         }
         if search.is_empty() {
-            self.set_status("Search pattern cannot be empty");
+            self.set_warning("Search pattern cannot be empty");
             return;
         }
         let mut lines = self.tabs[self.active_tab].editor.lines().to_vec();
@@ -52,8 +209,7 @@ impl App {
         }
         if count > 0 {
             let cursor = self.tabs[self.active_tab].editor.cursor();
-            self.replace_editor_text(lines, cursor);
-            self.on_editor_content_changed();
+            self.apply_edit_transaction(&lines, cursor);
             self.set_status(format!("Replaced {} occurrence(s)", count));
         } else {
             self.set_status(format!("No occurrences of '{}' found", search));
@@ -61,45 +217,193 @@ impl App {
     }
 
     pub(crate) fn search_in_project(&mut self, query: &str) {
-        let trimmed = query.trim();
-        if trimmed.is_empty() {
+        let (query, globs) = parse_search_globs(query);
+        if query.is_empty() {
+            self.replace_after_project_search = false;
             self.set_status("Search query is empty");
             return;
         }
-        let output = Command::new("rg")
-            .arg("--line-number")
-            .arg("--no-heading")
-            .arg("--color")
-            .arg("never")
-            .arg("--smart-case")
-            .arg(trimmed)
-            .arg(&self.root)
-            .output();
-        let Ok(output) = output else {
-            self.set_status(
-                "rg (ripgrep) not found -- install: https://github.com/BurntSushi/ripgrep#installation",
-            );
-            return;
+        self.run_project_search(&query, globs);
+    }
+
+    /// Search the project for `trimmed` in the files matching `globs`, with
+    /// the flags from `project_search_options`. ripgrep runs in the
+    /// background and its hits stream into the results popup; without it
+    /// the built-in search runs here.
+    pub(crate) fn run_project_search(&mut self, trimmed: &str, globs: Vec<String>) {
+        self.cancel_project_search();
+        let mut options = ProjectSearchOptions {
+            globs,
+            ..self.project_search_options.clone()
         };
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut hits = Vec::new();
-        for line in stdout.lines() {
-            if let Some(hit) = parse_rg_line(line) {
-                hits.push(hit);
-            }
-        }
         self.search_results.query = trimmed.to_string();
-        self.search_results.results = hits;
+        self.search_results.results.clear();
         self.search_results.index = 0;
+        self.search_results.replacement = None;
+        self.search_results.excluded.clear();
         self.search_results.open = true;
-        if self.search_results.results.is_empty() {
-            self.set_status(format!("No results for '{}'", trimmed));
+        let mut dirs = vec![self.root.as_path()];
+        dirs.extend(self.workspace_folders.iter().map(|f| f.path.as_path()));
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        match spawn_search(rg_command(trimmed, &options, &dirs), tx, stop.clone()) {
+            Ok(()) => {
+                self.search_results.options = options;
+                self.search_results.running = Some(RunningSearch {
+                    rx,
+                    stop,
+                    started: Instant::now(),
+                });
+                self.set_status(format!("Searching for '{trimmed}' (Esc to cancel)"));
+            }
+            Err(_) => {
+                self.search_results.results = self.builtin_search_hits(trimmed, &mut options);
+                self.search_results.options = options;
+                self.finish_project_search(" (built-in text search; install ripgrep for regex)");
+            }
+        }
+    }
+
+    /// Move the running search's hits into the results, once per frame.
+    pub(crate) fn poll_project_search(&mut self) {
+        let Some(running) = self.search_results.running.as_ref() else {
+            return;
+        };
+        let mut finished = false;
+        loop {
+            match running.rx.try_recv() {
+                Ok(SearchEvent::Hit(hit)) => self.search_results.results.push(hit),
+                Ok(SearchEvent::Finished) | Err(TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+        if finished {
+            self.search_results.running = None;
+            self.finish_project_search("");
+        }
+    }
+
+    /// Stop the running search, keeping the hits found so far; false when
+    /// none is running.
+    pub(crate) fn cancel_project_search(&mut self) -> bool {
+        let Some(running) = self.search_results.running.take() else {
+            return false;
+        };
+        running.stop.store(true, Ordering::Relaxed);
+        let count = self.search_results.results.len();
+        self.set_status(format!(
+            "Search canceled: {count} result{} so far",
+            if count == 1 { "" } else { "s" }
+        ));
+        true
+    }
+
+    fn finish_project_search(&mut self, note: &str) {
+        let query = &self.search_results.query;
+        let count = self.search_results.results.len();
+        if count == 0 {
+            self.set_status(format!("No results for '{query}'{note}"));
         } else {
-            self.set_status(format!(
-                "{} results for '{}'",
-                self.search_results.results.len(),
-                trimmed
-            ));
+            self.set_status(format!("{count} results for '{query}'{note}"));
+        }
+        if std::mem::take(&mut self.replace_after_project_search)
+            && count > 0
+            && self.search_results.open
+        {
+            self.open_project_replacement_prompt();
+        }
+    }
+
+    /// Run a project search with ripgrep, waiting for it. Without it, fall
+    /// back to the built-in search.
+    fn project_search_hits(
+        &self,
+        query: &str,
+        options: &mut ProjectSearchOptions,
+    ) -> Vec<ProjectSearchHit> {
+        let mut dirs = vec![self.root.as_path()];
+        dirs.extend(self.workspace_folders.iter().map(|f| f.path.as_path()));
+        match rg_command(query, options, &dirs).output() {
+            Ok(output) => String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(parse_rg_line)
+                .collect(),
+            Err(_) => self.builtin_search_hits(query, options),
+        }
+    }
+
+    /// A plain-text scan (forcing `options.literal`) of the same files
+    /// quick open uses, so ignored paths stay out of the results.
+    fn builtin_search_hits(
+        &self,
+        query: &str,
+        options: &mut ProjectSearchOptions,
+    ) -> Vec<ProjectSearchHit> {
+        options.literal = true;
+        let mut files = Vec::new();
+        self.collect_workspace_files(&mut files);
+        files.retain(|f| {
+            let rel = match self.workspace_folder_for(f) {
+                Some(folder) => relative_path(&folder.path, f),
+                None => relative_path(&self.root, f),
+            };
+            globs_allow(&options.globs, &rel)
+        });
+        files.sort();
+        match project_search_regex(query, options) {
+            Ok(re) => search_files(&files, &re),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Scan the project for TODO/FIXME comments and write them, with the
+    /// `git blame` author of each line, to `name` in the project root: CSV
+    /// for a `.csv` name, JSON otherwise.
+    pub(crate) fn export_todos(&mut self, name: &str) {
+        let target = self.root.join(name);
+        let csv = target
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        let mut hits = Vec::new();
+        for marker in TODO_MARKERS {
+            let mut options = ProjectSearchOptions {
+                case_sensitive: true,
+                whole_word: true,
+                literal: true,
+                globs: Vec::new(),
+            };
+            hits.extend(self.project_search_hits(marker, &mut options));
+        }
+        // A previous report would otherwise list its own contents.
+        hits.retain(|hit| hit.path != target);
+        hits.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+        hits.dedup_by(|a, b| a.path == b.path && a.line == b.line);
+        let mut authors: HashMap<PathBuf, Vec<String>> = HashMap::new();
+        let items: Vec<TodoItem> = hits
+            .into_iter()
+            .filter_map(|hit| {
+                let text = todo_comment_text(&hit.preview)?;
+                let file_authors = authors
+                    .entry(hit.path.clone())
+                    .or_insert_with(|| git_blame_authors(&self.root, &hit.path));
+                Some(TodoItem {
+                    file: relative_path(&self.root, &hit.path).display().to_string(),
+                    line: hit.line,
+                    text,
+                    author: hit
+                        .line
+                        .checked_sub(1)
+                        .and_then(|idx| file_authors.get(idx))
+                        .cloned(),
+                })
+            })
+            .collect();
+        match fs::write(&target, todo_report(&items, csv)) {
+            Ok(()) => self.set_status(format!("Exported {} TODO(s) to {name}", items.len())),
+            Err(err) => self.set_error(format!("Failed to write {name}: {err}")),
         }
     }
 
@@ -112,6 +416,7 @@ impl App {
         else {
             return Ok(());
         };
+        self.record_jump();
         self.open_file(hit.path.clone())?;
         let target_row = hit.line.saturating_sub(1);
         if let Some(tab) = self.active_tab_mut() {
@@ -129,4 +434,174 @@ impl App {
         ));
         Ok(())
     }
+
+    pub(crate) fn open_project_replacement_prompt(&mut self) {
+        let value = self.search_results.replacement.clone().unwrap_or_default();
+        self.prompt = Some(PromptState {
+            title: format!("Replace '{}' in project with", self.search_results.query),
+            cursor: value.len(),
+            value,
+            anchor: None,
+            history: None,
+            mode: PromptMode::ReplaceInProject,
+        });
+    }
+
+    pub(crate) fn set_project_replacement(&mut self, replacement: String) {
+        if let Err(err) =
+            project_search_regex(&self.search_results.query, &self.search_results.options)
+        {
+            self.set_warning(format!("Invalid regex: {}", err));
+            return;
+        }
+        self.search_results.replacement = Some(replacement);
+        self.search_results.open = true;
+        self.set_status("Space: toggle hit   a: apply   r: edit replacement");
+    }
+
+    pub(crate) fn toggle_selected_search_hit(&mut self) {
+        let idx = self.search_results.index;
+        if idx >= self.search_results.results.len() {
+            return;
+        }
+        if !self.search_results.excluded.remove(&idx) {
+            self.search_results.excluded.insert(idx);
+        }
+        if self.search_results.index + 1 < self.search_results.results.len() {
+            self.search_results.index += 1;
+        }
+    }
+
+    /// Rewrite every hit still toggled on, file by file. Files open with
+    /// unsaved edits are skipped since the hit lines may no longer match
+    /// the buffer; clean open tabs are reloaded from disk afterwards.
+    pub(crate) fn apply_project_replace(&mut self) -> io::Result<()> {
+        let Some(replacement) = self.search_results.replacement.clone() else {
+            self.set_status("Press r to enter a replacement first");
+            return Ok(());
+        };
+        let options = &self.search_results.options;
+        let literal = options.literal;
+        let re = match project_search_regex(&self.search_results.query, options) {
+            Ok(re) => re,
+            Err(err) => {
+                self.set_warning(format!("Invalid regex: {}", err));
+                return Ok(());
+            }
+        };
+        let mut by_file: BTreeMap<PathBuf, HashSet<usize>> = BTreeMap::new();
+        for (idx, hit) in self.search_results.results.iter().enumerate() {
+            if !self.search_results.excluded.contains(&idx) {
+                by_file
+                    .entry(hit.path.clone())
+                    .or_default()
+                    .insert(hit.line);
+            }
+        }
+        if by_file.is_empty() {
+            self.set_status("No hits selected");
+            return Ok(());
+        }
+        let mut replaced = 0usize;
+        let mut files_changed = 0usize;
+        let mut skipped_dirty = 0usize;
+        let mut reload = Vec::new();
+        for (path, lines) in by_file {
+            let tab_idx = self.tabs.iter().position(|t| t.path == path);
+            if tab_idx.is_some_and(|idx| self.tabs[idx].dirty) {
+                skipped_dirty += 1;
+                continue;
+            }
+            let Ok(text) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let mut out = String::with_capacity(text.len());
+            let mut count = 0usize;
+            for (idx, line) in text.split_inclusive('\n').enumerate() {
+                let body = line.trim_end_matches(['\r', '\n']);
+                if !lines.contains(&(idx + 1)) {
+                    out.push_str(line);
+                    continue;
+                }
+                for segment in replace_segments(&re, body, &replacement, literal) {
+                    match segment {
+                        ReplaceSegment::Keep(text) | ReplaceSegment::Inserted(text) => {
+                            out.push_str(&text)
+                        }
+                        ReplaceSegment::Removed(_) => count += 1,
+                    }
+                }
+                out.push_str(&line[body.len()..]);
+            }
+            if count == 0 {
+                continue;
+            }
+            fs::write(&path, out)?;
+            replaced += count;
+            files_changed += 1;
+            if let Some(idx) = tab_idx {
+                reload.push(idx);
+            }
+        }
+        let previous = self.active_tab;
+        for idx in reload {
+            self.active_tab = idx;
+            self.reload_open_file_from_disk_if_pristine()?;
+        }
+        self.active_tab = previous;
+        self.search_results.open = false;
+        self.search_results.replacement = None;
+        self.search_results.excluded.clear();
+        // Refresh git status for the rewritten files
+        self.fs_refresh_pending = true;
+        self.fs_full_refresh_pending = true;
+        let skipped = if skipped_dirty > 0 {
+            format!(", skipped {} file(s) with unsaved changes", skipped_dirty)
+        } else {
+            String::new()
+        };
+        self.set_status(format!(
+            "Replaced {} occurrence(s) in {} file(s){}",
+            replaced, files_changed, skipped
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::{KeyEvent, KeyModifiers};
+    use tempfile::tempdir;
+
+    #[test]
+    fn esc_cancels_a_running_search_before_closing_the_results() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = App::new_for_test(tmp.path());
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        app.search_results.open = true;
+        app.search_results.running = Some(RunningSearch {
+            rx,
+            stop: stop.clone(),
+            started: Instant::now(),
+        });
+        let hit = ProjectSearchHit {
+            path: PathBuf::from("a.txt"),
+            line: 1,
+            preview: "needle".to_string(),
+        };
+        tx.send(SearchEvent::Hit(hit)).expect("send");
+        app.poll_project_search();
+        assert_eq!(app.search_results.results.len(), 1);
+        assert!(app.search_results.running.is_some());
+
+        let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        app.handle_key(esc).expect("esc");
+        assert!(stop.load(Ordering::Relaxed));
+        assert!(app.search_results.running.is_none() && app.search_results.open);
+        assert_eq!(app.status, "Search canceled: 1 result so far");
+        app.handle_key(esc).expect("esc");
+        assert!(!app.search_results.open);
+    }
 }
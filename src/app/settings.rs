@@ -0,0 +1,272 @@
+use std::io;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use serde_json::{Value, json};
+
+use super::{App, SettingsViewState};
+use crate::persistence::{
+    load_editor_settings, load_filetype_config, load_lsp_settings, load_status_settings,
+    save_setting,
+};
+use crate::settings::{
+    SETTINGS, SettingDef, SettingFile, SettingKind, format_setting_value, matching_settings,
+    parse_setting_value,
+};
+
+impl App {
+    pub(crate) fn open_settings(&mut self) {
+        self.settings_view = Some(SettingsViewState {
+            query: String::new(),
+            selected: 0,
+            editing: None,
+            error: None,
+        });
+    }
+
+    /// Current value of `def`, as it is stored.
+    pub(crate) fn setting_value(&self, def: &SettingDef) -> Value {
+        let config = match def.file {
+            SettingFile::State => serde_json::to_value(self.persisted_state()),
+            SettingFile::Editor => serde_json::to_value(&self.editor_settings),
+            SettingFile::Status => serde_json::to_value(&self.status_settings),
+            SettingFile::FileTypes => serde_json::to_value(&self.filetype_config),
+            SettingFile::Lsp => serde_json::to_value(&self.lsp_settings),
+        };
+        config
+            .ok()
+            .and_then(|c| c.get(def.field).cloned())
+            .unwrap_or(Value::Null)
+    }
+
+    /// Validate `input` for `def`, then apply it and save it.
+    pub(crate) fn apply_setting(&mut self, def: &SettingDef, input: &str) -> Result<(), String> {
+        let mut value = parse_setting_value(def.kind, input)?;
+        if def.kind == SettingKind::Theme {
+            let name = input.trim();
+            let theme = self
+                .themes
+                .iter()
+                .find(|t| t.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("No theme named \"{name}\""))?;
+            value = Value::from(theme.name.clone());
+        }
+        if def.file == SettingFile::State {
+            self.apply_state_setting(def.field, &value)
+                .map_err(|e| e.to_string())?;
+            self.persist_state();
+        } else {
+            save_setting(def.file, def.field, value.clone())
+                .map_err(|e| format!("Could not save: {e}"))?;
+            self.reload_settings(def.file);
+        }
+        if value.is_null() {
+            self.set_status(format!("{} reset to the default", def.name));
+        } else {
+            self.set_status(format!("{} = {}", def.name, format_setting_value(&value)));
+        }
+        Ok(())
+    }
+
+    /// Flip the on/off setting stored under `field`, as Enter does in the
+    /// settings editor.
+    pub(crate) fn toggle_setting(&mut self, field: &str) {
+        let Some(def) = SETTINGS.iter().find(|d| d.field == field) else {
+            return;
+        };
+        let flipped = if self.setting_value(def).as_bool() == Some(true) {
+            "false"
+        } else {
+            "true"
+        };
+        if let Err(err) = self.apply_setting(def, flipped) {
+            self.set_warning(err);
+        }
+    }
+
+    fn apply_state_setting(&mut self, field: &str, value: &Value) -> io::Result<()> {
+        let flag = value.as_bool();
+        match field {
+            "theme_name" => {
+                let name = value.as_str().unwrap_or_default();
+                if let Some(idx) = self.themes.iter().position(|t| t.name == name) {
+                    self.active_theme_index = idx;
+                    self.theme_index = idx;
+                    self.preview_revert_index = idx;
+                }
+            }
+            "word_wrap" if flag != Some(self.word_wrap) => self.toggle_word_wrap(),
+            "show_ignored" if flag != Some(self.show_ignored) => self.toggle_show_ignored()?,
+            "nerd_font_icons" if flag != Some(self.nerd_font_icons) => {
+                self.toggle_nerd_font_icons()
+            }
+            "files_pane_width" => {
+                if let Some(width) = value.as_u64() {
+                    self.files_pane_width = (width as u16).max(Self::MIN_FILES_PANE_WIDTH);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Re-read a config file the settings editor wrote, so the change
+    /// applies now. rust-analyzer is sent the new configuration.
+    fn reload_settings(&mut self, file: SettingFile) {
+        match file {
+            SettingFile::State => {}
+            SettingFile::Editor => {
+                self.editor_settings = load_editor_settings();
+                self.reapply_indent_settings();
+            }
+            SettingFile::Status => self.status_settings = load_status_settings(),
+            SettingFile::FileTypes => self.filetype_config = load_filetype_config(),
+            SettingFile::Lsp => {
+                self.lsp_settings = load_lsp_settings(&self.root);
+                if let Some(lsp) = self.lsp.as_mut() {
+                    let section = self.lsp_settings.rust_analyzer_section();
+                    lsp.transport.settings = json!({ "rust-analyzer": section.clone() });
+                    let _ = lsp.transport.send_notification(
+                        "workspace/didChangeConfiguration",
+                        json!({ "settings": { "rust-analyzer": section } }),
+                    );
+                }
+            }
+        }
+    }
+
+    pub(crate) fn handle_settings_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(view) = self.settings_view.as_mut() else {
+            return Ok(());
+        };
+        let matches = matching_settings(&view.query);
+        let selected = matches.get(view.selected).copied();
+        if let Some(text) = view.editing.as_mut() {
+            match key.code {
+                KeyCode::Esc => {
+                    view.editing = None;
+                    view.error = None;
+                }
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) => text.push(c),
+                KeyCode::Enter => {
+                    let input = text.clone();
+                    let Some(def) = selected else {
+                        return Ok(());
+                    };
+                    let result = self.apply_setting(def, &input);
+                    if let Some(view) = self.settings_view.as_mut() {
+                        match result {
+                            Ok(()) => {
+                                view.editing = None;
+                                view.error = None;
+                            }
+                            Err(err) => view.error = Some(err),
+                        }
+                    }
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Esc => self.settings_view = None,
+            KeyCode::Down => {
+                view.selected = (view.selected + 1).min(matches.len().saturating_sub(1))
+            }
+            KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Backspace => {
+                view.query.pop();
+                view.selected = 0;
+            }
+            KeyCode::Char(c) => {
+                view.query.push(c);
+                view.selected = 0;
+            }
+            KeyCode::Enter => {
+                let Some(def) = selected else {
+                    return Ok(());
+                };
+                let current = self.setting_value(def);
+                if def.kind == SettingKind::Bool {
+                    let flipped = if current.as_bool() == Some(true) {
+                        "false"
+                    } else {
+                        "true"
+                    };
+                    if let Err(err) = self.apply_setting(def, flipped) {
+                        self.set_warning(err);
+                    }
+                } else if let Some(view) = self.settings_view.as_mut() {
+                    view.editing = Some(format_setting_value(&current));
+                    view.error = None;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+    use tempfile::tempdir;
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .expect("key");
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn settings_search_and_reject_invalid_values() {
+        let tmp = tempdir().expect("tempdir");
+        let mut app = App::new_for_test(tmp.path());
+        app.open_settings();
+        type_text(&mut app, "tab width");
+        let view = app.settings_view.as_ref().expect("settings");
+        let def = matching_settings(&view.query)[0];
+        assert_eq!(def.name, "Editor: Tab Width");
+        let width = app.editor_settings.tab_width;
+        assert_eq!(app.setting_value(def), json!(width));
+
+        press(&mut app, KeyCode::Enter);
+        let view = app.settings_view.as_ref().expect("settings");
+        assert_eq!(view.editing.as_deref(), Some(width.to_string().as_str()));
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Backspace);
+        }
+        type_text(&mut app, "40");
+        press(&mut app, KeyCode::Enter);
+        let view = app.settings_view.as_ref().expect("settings");
+        assert_eq!(
+            view.error.as_deref(),
+            Some("Expected a whole number from 1 to 16")
+        );
+        assert!(view.editing.is_some());
+        assert_eq!(app.editor_settings.tab_width, width);
+
+        press(&mut app, KeyCode::Esc);
+        assert!(
+            app.settings_view
+                .as_ref()
+                .is_some_and(|v| v.editing.is_none())
+        );
+        press(&mut app, KeyCode::Esc);
+        assert!(app.settings_view.is_none());
+
+        let theme = crate::settings::SETTINGS[0];
+        assert_eq!(
+            app.apply_setting(&theme, "No Such Theme"),
+            Err("No theme named \"No Such Theme\"".to_string())
+        );
+    }
+}
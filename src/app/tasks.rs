@@ -0,0 +1,346 @@
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, TryRecvError};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+
+use super::{App, TaskOutputLine, TaskPanelState, TaskPicker};
+use crate::env_file::{ProjectEnv, load_project_env};
+use crate::tasks::{Task, TaskEvent, detect_tasks, spawn_task};
+use crate::util::{fuzzy_score, parse_pasted_locations, relative_path};
+
+impl App {
+    /// Output kept in the panel; older lines are dropped.
+    const TASK_OUTPUT_LIMIT: usize = 10_000;
+    /// Rows of the output panel, borders included.
+    pub(crate) const TASK_PANEL_HEIGHT: u16 = 12;
+
+    /// "Run Task": pick one of the project's tasks.
+    pub(crate) fn open_task_picker(&mut self) {
+        if !self.require_project_trust("Run Task") {
+            return;
+        }
+        let tasks = detect_tasks(&self.root);
+        if tasks.is_empty() {
+            self.set_warning("No tasks found (Cargo.toml, package.json scripts or a Makefile)");
+            return;
+        }
+        self.task_picker = Some(TaskPicker {
+            tasks,
+            query: String::new(),
+            selected: 0,
+        });
+    }
+
+    /// The picker's tasks matching its query, best first.
+    pub(crate) fn task_picker_matches(picker: &TaskPicker) -> Vec<&Task> {
+        let query = picker.query.to_ascii_lowercase();
+        let mut scored: Vec<(usize, &Task)> = picker
+            .tasks
+            .iter()
+            .filter_map(|task| fuzzy_score(&query, &task.label).map(|score| (score, task)))
+            .collect();
+        scored.sort_by_key(|(score, _)| *score);
+        scored.into_iter().map(|(_, task)| task).collect()
+    }
+
+    pub(crate) fn handle_task_picker_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(picker) = self.task_picker.as_mut() else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc => self.task_picker = None,
+            KeyCode::Down => {
+                let last = Self::task_picker_matches(picker).len().saturating_sub(1);
+                picker.selected = (picker.selected + 1).min(last);
+            }
+            KeyCode::Up => picker.selected = picker.selected.saturating_sub(1),
+            KeyCode::Backspace => {
+                picker.query.pop();
+                picker.selected = 0;
+            }
+            KeyCode::Char(c) => {
+                picker.query.push(c);
+                picker.selected = 0;
+            }
+            KeyCode::Enter => {
+                let task = Self::task_picker_matches(picker)
+                    .get(picker.selected)
+                    .map(|task| (*task).clone());
+                self.task_picker = None;
+                if let Some(task) = task {
+                    self.run_task(task);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Start `task`, replacing the panel's output. A task still running is
+    /// stopped first.
+    pub(crate) fn run_task(&mut self, task: Task) {
+        if !self.require_project_trust(&task.label) {
+            return;
+        }
+        self.stop_task_quietly();
+        let env = self.task_env();
+        let vars = env.as_ref().map_or(&[][..], |env| env.vars.as_slice());
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        if let Err(err) = spawn_task(&task, vars, tx, Arc::clone(&stop)) {
+            self.set_error(format!("Could not run {}: {err}", task.label));
+            return;
+        }
+        self.set_status(format!("Running {}", task.label));
+        self.task_panel = Some(TaskPanelState {
+            label: task.label,
+            env_file: env.map(|env| relative_path(&self.root, &env.path).display().to_string()),
+            lines: Vec::new(),
+            rx: Some(rx),
+            stop,
+            exit: None,
+            scroll: 0,
+            follow: true,
+            visible: true,
+        });
+    }
+
+    /// The project env file tasks run with, if there is one; an unreadable
+    /// one is reported and skipped.
+    fn task_env(&mut self) -> Option<ProjectEnv> {
+        let name = self.lsp_settings.env_file_name()?.to_string();
+        match load_project_env(&self.root, &name) {
+            Ok(env) => env,
+            Err(err) => {
+                self.set_warning(format!("Could not read {name}: {err}"));
+                None
+            }
+        }
+    }
+
+    pub(crate) fn stop_task(&mut self) {
+        if self.stop_task_quietly() {
+            self.set_status("Stopping task");
+        } else {
+            self.set_status("No task running");
+        }
+    }
+
+    /// Ask the running task to stop; false when none is running.
+    fn stop_task_quietly(&mut self) -> bool {
+        match self.task_panel.as_ref() {
+            Some(panel) if panel.rx.is_some() => {
+                panel.stop.store(true, Ordering::Relaxed);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn toggle_task_panel(&mut self) {
+        match self.task_panel.as_mut() {
+            Some(panel) => panel.visible = !panel.visible,
+            None => self.set_status("No task output yet: run a task first"),
+        }
+    }
+
+    /// Move the running task's output into the panel, once per frame.
+    pub(crate) fn poll_task(&mut self) {
+        let Some(rx) = self.task_panel.as_ref().and_then(|p| p.rx.as_ref()) else {
+            return;
+        };
+        let mut events = Vec::new();
+        loop {
+            match rx.try_recv() {
+                Ok(event) => events.push(event),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    events.push(TaskEvent::Finished(None));
+                    break;
+                }
+            }
+        }
+        for event in events {
+            match event {
+                TaskEvent::Line(text) => {
+                    self.record_test_output(&text);
+                    let location = parse_pasted_locations(&text).into_iter().find_map(|loc| {
+                        let path = self.resolve_pasted_path(&loc.path)?;
+                        let col = loc.column.map_or(0, |c| c.saturating_sub(1));
+                        Some((path, loc.line - 1, col))
+                    });
+                    let Some(panel) = self.task_panel.as_mut() else {
+                        return;
+                    };
+                    panel.lines.push(TaskOutputLine { text, location });
+                    if panel.lines.len() > Self::TASK_OUTPUT_LIMIT {
+                        panel.lines.remove(0);
+                    }
+                }
+                TaskEvent::Finished(code) => {
+                    let Some(panel) = self.task_panel.as_mut() else {
+                        return;
+                    };
+                    panel.rx = None;
+                    panel.exit = Some(code);
+                    let label = panel.label.clone();
+                    match code {
+                        Some(0) => self.set_status(format!("{label} finished")),
+                        Some(code) => self.set_warning(format!("{label} failed (exit {code})")),
+                        None => self.set_warning(format!("{label} stopped")),
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Scroll the panel, or open the location on a clicked line.
+    pub(crate) fn handle_task_panel_mouse(&mut self, mouse: MouseEvent) -> io::Result<()> {
+        let rect = self.task_panel_rect;
+        let Some(panel) = self.task_panel.as_mut() else {
+            return Ok(());
+        };
+        let last = panel
+            .lines
+            .len()
+            .saturating_sub(rect.height.saturating_sub(2) as usize);
+        match mouse.kind {
+            MouseEventKind::ScrollDown => {
+                panel.scroll = (panel.scroll + Self::SCROLL_LINES).min(last);
+                panel.follow = panel.scroll == last;
+            }
+            MouseEventKind::ScrollUp => {
+                panel.follow = false;
+                panel.scroll = panel.scroll.saturating_sub(Self::SCROLL_LINES);
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                let row = mouse.row.saturating_sub(rect.y + 1) as usize;
+                let location = panel
+                    .lines
+                    .get(panel.scroll + row)
+                    .and_then(|line| line.location.clone());
+                if let Some((path, row, col)) = location {
+                    self.record_jump();
+                    self.go_to_location(&path, row, col)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+    use ratatui::layout::Rect;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn task_output_links_locations_and_reports_the_exit_code() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir(root.join("src")).expect("mkdir");
+        let main = root.join("src/main.rs");
+        fs::write(&main, "fn main() {\n    let x: u8 = \"\";\n}\n").expect("write");
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"demo\"\n").expect("write");
+        let mut app = App::new_for_test(root);
+
+        app.open_task_picker();
+        assert!(matches!(
+            app.pending,
+            crate::types::PendingAction::TrustProject
+        ));
+        assert!(app.task_picker.is_none());
+        app.pending = crate::types::PendingAction::None;
+        app.trusted_projects.insert(root.to_path_buf(), true);
+        app.open_task_picker();
+        for c in "tst".chars() {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .expect("type");
+        }
+        let picker = app.task_picker.as_ref().expect("picker");
+        let labels: Vec<_> = App::task_picker_matches(picker)
+            .iter()
+            .map(|t| t.label.as_str())
+            .collect();
+        assert_eq!(labels, ["cargo test"]);
+        app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("esc");
+        assert!(app.task_picker.is_none());
+
+        // Feed the panel by hand instead of running cargo.
+        let (tx, rx) = mpsc::channel();
+        app.task_panel = Some(TaskPanelState {
+            label: "cargo build".to_string(),
+            env_file: None,
+            lines: Vec::new(),
+            rx: Some(rx),
+            stop: Arc::default(),
+            exit: None,
+            scroll: 0,
+            follow: true,
+            visible: true,
+        });
+        for line in ["error[E0308]: mismatched types", " --> src/main.rs:2:17"] {
+            tx.send(TaskEvent::Line(line.to_string())).expect("send");
+        }
+        tx.send(TaskEvent::Finished(Some(101))).expect("send");
+        app.poll_task();
+        let panel = app.task_panel.as_ref().expect("panel");
+        assert!(panel.rx.is_none());
+        assert_eq!(panel.exit, Some(Some(101)));
+        assert_eq!(panel.lines[0].location, None);
+        assert_eq!(panel.lines[1].location, Some((main.clone(), 1, 16)));
+        assert!(app.status.contains("failed (exit 101)"));
+
+        app.task_panel_rect = Rect::new(0, 20, 80, 10);
+        app.handle_task_panel_mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column: 5,
+            row: 22,
+            modifiers: KeyModifiers::NONE,
+        })
+        .expect("click");
+        app.pending = crate::types::PendingAction::None;
+        let tab = app.active_tab().expect("tab");
+        assert_eq!(tab.path, main);
+        assert_eq!(tab.editor.cursor(), (1, 16));
+
+        app.toggle_task_panel();
+        assert!(!app.task_panel.as_ref().expect("panel").visible);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tasks_need_trust_and_run_with_the_project_env_file() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::write(root.join(".env"), "GREETING=hello\n").expect("write");
+        let mut app = App::new_for_test(root);
+        let task = Task::new(root, "sh", &["-c", "echo $GREETING"]);
+
+        app.trusted_projects.insert(root.to_path_buf(), false);
+        app.run_task(task.clone());
+        assert!(app.task_panel.is_none());
+        assert!(app.status.contains("not trusted"));
+
+        app.trusted_projects.insert(root.to_path_buf(), true);
+        app.run_task(task);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while app.task_panel.as_ref().is_some_and(|p| p.rx.is_some())
+            && std::time::Instant::now() < deadline
+        {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            app.poll_task();
+        }
+        let panel = app.task_panel.as_ref().expect("panel");
+        assert_eq!(panel.env_file.as_deref(), Some(".env"));
+        assert_eq!(panel.lines[0].text, "hello");
+    }
+}
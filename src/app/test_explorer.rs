@@ -0,0 +1,268 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::sync::mpsc::TryRecvError;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use super::{App, TestExplorerState};
+use crate::tasks::Task;
+use crate::test_explorer::{
+    TestOutcome, TestRow, parse_test_panic, parse_test_result, spawn_test_list, test_rows,
+    test_run_args,
+};
+use crate::types::PendingAction;
+use crate::util::parse_pasted_locations;
+
+impl App {
+    /// "Test Explorer": show the tests, listing them the first time.
+    pub(crate) fn open_test_explorer(&mut self) {
+        if !self.root.join("Cargo.toml").is_file() {
+            self.set_warning("No Cargo.toml in the project root");
+            return;
+        }
+        match self.test_explorer.as_mut() {
+            Some(explorer) => explorer.visible = true,
+            None => {
+                // Listing the tests builds the project, running its build
+                // scripts.
+                if !self.require_project_trust("Test Explorer") {
+                    return;
+                }
+                self.test_explorer = Some(TestExplorerState {
+                    tests: Vec::new(),
+                    collapsed: BTreeSet::new(),
+                    selected: 0,
+                    outcomes: HashMap::new(),
+                    failures: HashMap::new(),
+                    list_rx: None,
+                    visible: true,
+                });
+                self.reload_test_list();
+            }
+        }
+    }
+
+    /// Build the tests and list them again.
+    fn reload_test_list(&mut self) {
+        if !self.test_runs_allowed() {
+            return;
+        }
+        let rx = spawn_test_list(&self.root);
+        if let Some(explorer) = self.test_explorer.as_mut() {
+            explorer.list_rx = Some(rx);
+        }
+        self.set_status("Listing tests (cargo test -- --list)");
+    }
+
+    pub(crate) fn poll_test_list(&mut self) {
+        let Some(explorer) = self.test_explorer.as_mut() else {
+            return;
+        };
+        let result = match explorer.list_rx.as_ref().map(|rx| rx.try_recv()) {
+            Some(Ok(result)) => result,
+            Some(Err(TryRecvError::Disconnected)) => Err(io::Error::other("cargo exited")),
+            Some(Err(TryRecvError::Empty)) | None => return,
+        };
+        explorer.list_rx = None;
+        match result {
+            Ok(tests) => {
+                let count = tests.len();
+                explorer.outcomes.retain(|test, _| tests.contains(test));
+                explorer.failures.retain(|test, _| tests.contains(test));
+                explorer.tests = tests;
+                explorer.selected = 0;
+                self.set_status(format!("{count} test{}", if count == 1 { "" } else { "s" }));
+            }
+            Err(err) => self.set_error(format!("Could not list tests: {err}")),
+        }
+    }
+
+    /// The explorer's visible rows.
+    pub(crate) fn test_explorer_rows(explorer: &TestExplorerState) -> Vec<TestRow> {
+        test_rows(&explorer.tests, &explorer.collapsed)
+    }
+
+    pub(crate) fn handle_test_explorer_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(explorer) = self.test_explorer.as_mut() else {
+            return Ok(());
+        };
+        let rows = Self::test_explorer_rows(explorer);
+        let row = rows.get(explorer.selected).cloned();
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => explorer.visible = false,
+            KeyCode::Down | KeyCode::Char('j') => {
+                explorer.selected = (explorer.selected + 1).min(rows.len().saturating_sub(1));
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                explorer.selected = explorer.selected.saturating_sub(1);
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Char(' ') => {
+                let Some(row) = row.filter(|r| !r.is_test) else {
+                    return Ok(());
+                };
+                let collapse = match key.code {
+                    KeyCode::Left => true,
+                    KeyCode::Right => false,
+                    _ => !explorer.collapsed.contains(&row.path),
+                };
+                if collapse {
+                    explorer.collapsed.insert(row.path);
+                } else {
+                    explorer.collapsed.remove(&row.path);
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(row) = row {
+                    self.run_tests(Some(&row));
+                }
+            }
+            KeyCode::Char('a') => self.run_tests(None),
+            KeyCode::Char('r') => self.reload_test_list(),
+            KeyCode::Char('g') => {
+                let failure = row.and_then(|row| explorer.failures.get(&row.path).cloned());
+                match failure {
+                    Some((path, line, col)) => {
+                        explorer.visible = false;
+                        self.record_jump();
+                        self.go_to_location(&path, line, col)?;
+                    }
+                    None => self.reject_action("No failure location for this test"),
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Run one test, a module's tests, or all of them (`row` is `None`) as a
+    /// task, forgetting their previous results.
+    fn run_tests(&mut self, row: Option<&TestRow>) {
+        if !self.test_runs_allowed() {
+            return;
+        }
+        let Some(explorer) = self.test_explorer.as_mut() else {
+            return;
+        };
+        let in_scope = |test: &String| match row {
+            Some(row) if row.is_test => *test == row.path,
+            Some(row) => test.starts_with(&format!("{}::", row.path)),
+            None => true,
+        };
+        explorer.outcomes.retain(|test, _| !in_scope(test));
+        explorer.failures.retain(|test, _| !in_scope(test));
+        let args = test_run_args(row);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        self.run_task(Task::new(&self.root, "cargo", &args));
+    }
+
+    /// Whether cargo may build and run the tests: only in a trusted project.
+    /// The popup is hidden when the trust prompt shows, as it would cover it.
+    fn test_runs_allowed(&mut self) -> bool {
+        if self.require_project_trust("Test Explorer") {
+            return true;
+        }
+        if matches!(self.pending, PendingAction::TrustProject)
+            && let Some(explorer) = self.test_explorer.as_mut()
+        {
+            explorer.visible = false;
+        }
+        false
+    }
+
+    /// Read a line of task output for test results and panic locations.
+    pub(crate) fn record_test_output(&mut self, line: &str) {
+        let Some(explorer) = self.test_explorer.as_ref() else {
+            return;
+        };
+        if let Some((test, outcome)) = parse_test_result(line) {
+            if let Some(explorer) = self.test_explorer.as_mut() {
+                explorer.outcomes.insert(test.to_string(), outcome);
+            }
+            return;
+        }
+        let Some((test, rest)) = parse_test_panic(line) else {
+            return;
+        };
+        if !explorer.tests.iter().any(|t| t == test) {
+            return;
+        }
+        let location = parse_pasted_locations(rest).into_iter().find_map(|loc| {
+            let path = self.resolve_pasted_path(&loc.path)?;
+            let col = loc.column.map_or(0, |c| c.saturating_sub(1));
+            Some((path, loc.line - 1, col))
+        });
+        if let (Some(location), Some(explorer)) = (location, self.test_explorer.as_mut()) {
+            explorer.failures.insert(test.to_string(), location);
+            explorer
+                .outcomes
+                .insert(test.to_string(), TestOutcome::Failed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_results_and_panics_update_the_explorer() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir(root.join("src")).expect("mkdir");
+        let lib = root.join("src/lib.rs");
+        fs::write(&lib, "#[test]\nfn adds() {\n    assert_eq!(1, 2);\n}\n").expect("write");
+        let mut app = App::new_for_test(root);
+        app.test_explorer = Some(TestExplorerState {
+            tests: vec!["math::adds".to_string(), "math::subs".to_string()],
+            collapsed: BTreeSet::new(),
+            selected: 0,
+            outcomes: HashMap::new(),
+            failures: HashMap::new(),
+            list_rx: None,
+            visible: true,
+        });
+
+        for line in [
+            "test math::subs ... ok",
+            "test math::adds ... FAILED",
+            "thread 'math::adds' panicked at src/lib.rs:3:5:",
+        ] {
+            app.record_test_output(line);
+        }
+        let explorer = app.test_explorer.as_ref().expect("explorer");
+        assert_eq!(explorer.outcomes["math::subs"], TestOutcome::Passed);
+        assert_eq!(explorer.failures["math::adds"], (lib.clone(), 2, 4));
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE))
+            .expect("run all");
+        assert!(matches!(app.pending, PendingAction::TrustProject));
+        assert!(app.task_panel.is_none());
+        let explorer = app.test_explorer.as_mut().expect("explorer");
+        assert!(!explorer.visible);
+        assert_eq!(explorer.outcomes.len(), 2, "results are kept when refused");
+        explorer.visible = true;
+        app.pending = PendingAction::None;
+
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        app.handle_key(press(KeyCode::Left)).expect("collapse");
+        let explorer = app.test_explorer.as_ref().expect("explorer");
+        assert_eq!(App::test_explorer_rows(explorer).len(), 1);
+        app.handle_key(press(KeyCode::Char(' '))).expect("expand");
+        app.handle_key(press(KeyCode::Down)).expect("down");
+        app.handle_key(press(KeyCode::Char('g')))
+            .expect("go to failure");
+        assert!(!app.test_explorer.as_ref().expect("explorer").visible);
+        let tab = app.active_tab().expect("tab");
+        assert_eq!((tab.path.clone(), tab.editor.cursor()), (lib, (2, 4)));
+
+        app.test_explorer = None;
+        app.trusted_projects.insert(root.to_path_buf(), false);
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"demo\"\n").expect("write");
+        app.open_test_explorer();
+        assert!(app.test_explorer.is_none());
+        assert!(app.status.contains("not trusted"));
+    }
+}
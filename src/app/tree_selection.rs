@@ -0,0 +1,205 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::App;
+use crate::types::{PendingAction, TreePaste};
+use crate::util::relative_path;
+
+impl App {
+    /// Space in the tree: mark or unmark the selected entry, which also
+    /// anchors a Shift+arrow range there.
+    pub(crate) fn toggle_tree_mark(&mut self) {
+        let Some(path) = self.selected_item().map(|i| i.path.clone()) else {
+            return;
+        };
+        if let Some(idx) = self.tree_marked.iter().position(|p| *p == path) {
+            self.tree_marked.remove(idx);
+        } else {
+            self.tree_marked.push(path.clone());
+        }
+        self.tree_mark_anchor = Some(path);
+        self.report_tree_marks();
+    }
+
+    /// Shift+Up/Down in the tree: move the selection and mark everything
+    /// between it and the anchor.
+    pub(crate) fn extend_tree_marks(&mut self, down: bool) {
+        if self.tree.is_empty() {
+            return;
+        }
+        let anchor_path = match &self.tree_mark_anchor {
+            Some(path) => path.clone(),
+            None => self.tree[self.selected].path.clone(),
+        };
+        let anchor = match self.tree.iter().position(|i| i.path == anchor_path) {
+            Some(idx) => idx,
+            None => self.selected,
+        };
+        self.tree_mark_anchor = Some(self.tree[anchor].path.clone());
+        self.selected = if down {
+            (self.selected + 1).min(self.tree.len() - 1)
+        } else {
+            self.selected.saturating_sub(1)
+        };
+        let (from, to) = (anchor.min(self.selected), anchor.max(self.selected));
+        self.tree_marked = self.tree[from..=to]
+            .iter()
+            .map(|i| i.path.clone())
+            .collect();
+        self.report_tree_marks();
+    }
+
+    /// Unmark everything; false when nothing was marked.
+    pub(crate) fn clear_tree_marks(&mut self) -> bool {
+        self.tree_mark_anchor = None;
+        if self.tree_marked.is_empty() {
+            return false;
+        }
+        self.tree_marked.clear();
+        self.set_status("Selection cleared");
+        true
+    }
+
+    fn report_tree_marks(&mut self) {
+        match self.tree_marked.len() {
+            0 => self.set_status("Selection cleared"),
+            n => self.set_status(format!(
+                "{n} selected: right-click for Cut/Copy/Delete, Esc to clear"
+            )),
+        }
+    }
+
+    /// What a tree action on `target` applies to: every marked entry when
+    /// `target` is one of them, else `target` alone. Entries inside a
+    /// marked folder are dropped, since the folder already covers them.
+    pub(crate) fn tree_action_targets(&self, target: &Path) -> Vec<PathBuf> {
+        if !self.tree_marked.iter().any(|p| p == target) {
+            return vec![target.to_path_buf()];
+        }
+        let marked = &self.tree_marked;
+        marked
+            .iter()
+            .filter(|p| !marked.iter().any(|q| q != *p && p.starts_with(q)))
+            .cloned()
+            .collect()
+    }
+
+    /// Ask before deleting `target`, or every marked entry with it.
+    pub(crate) fn request_tree_delete(&mut self, target: PathBuf) {
+        let mut targets = self.tree_action_targets(&target);
+        for path in &targets {
+            if let Err(msg) = self.check_mutable_path(path, "delete") {
+                self.set_warning(msg);
+                return;
+            }
+        }
+        if targets.len() == 1 {
+            self.set_pending(PendingAction::Delete(targets.remove(0)));
+        } else {
+            self.set_pending(PendingAction::DeleteMany(targets));
+        }
+    }
+
+    /// Delete each of `paths`, as confirmed together.
+    pub(crate) fn delete_paths(&mut self, paths: Vec<PathBuf>) -> io::Result<()> {
+        let count = paths.len();
+        for path in paths {
+            self.delete_path(path)?;
+        }
+        self.tree_marked.clear();
+        self.tree_mark_anchor = None;
+        self.set_status(format!("Deleted {count} items"));
+        Ok(())
+    }
+
+    /// Move or copy each of `pastes`, as confirmed together.
+    pub(crate) fn paste_paths(&mut self, pastes: Vec<TreePaste>) -> io::Result<()> {
+        let cut = pastes.iter().any(|p| p.cut);
+        let mut done = 0;
+        let mut dir = None;
+        for paste in pastes {
+            dir = paste.to.parent().map(Path::to_path_buf);
+            let to = paste.to.clone();
+            self.paste_path(paste)?;
+            if to.exists() {
+                done += 1;
+            }
+        }
+        self.tree_marked.clear();
+        self.tree_mark_anchor = None;
+        let dir = dir.unwrap_or_else(|| self.root.clone());
+        self.set_status(format!(
+            "{} {done} items to {}",
+            if cut { "Moved" } else { "Copied" },
+            relative_path(&self.root, &dir).display()
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ContextAction;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn key(app: &mut App, modifiers: KeyModifiers, code: KeyCode) {
+        app.handle_tree_key(KeyEvent::new(code, modifiers))
+            .expect("key");
+    }
+
+    #[test]
+    fn marked_entries_are_moved_and_deleted_together() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("dest")).expect("mkdir");
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(root.join(name), name).expect("write");
+        }
+        let mut app = App::new_for_test(root);
+        let row = |app: &App, name: &str| {
+            app.tree
+                .iter()
+                .position(|i| i.path == root.join(name))
+                .expect("row")
+        };
+
+        app.selected = row(&app, "a.txt");
+        key(&mut app, KeyModifiers::SHIFT, KeyCode::Down);
+        key(&mut app, KeyModifiers::SHIFT, KeyCode::Down);
+        key(&mut app, KeyModifiers::NONE, KeyCode::Char(' '));
+        assert_eq!(app.tree_marked, [root.join("a.txt"), root.join("b.txt")]);
+
+        app.context_menu.target = Some(root.join("b.txt"));
+        app.apply_context_action(ContextAction::Cut).expect("cut");
+        app.context_menu.target = Some(root.join("dest"));
+        app.apply_context_action(ContextAction::Paste)
+            .expect("paste");
+        let PendingAction::PasteMany(pastes) = app.pending.clone() else {
+            panic!("expected one confirmation for the whole paste");
+        };
+        assert_eq!(pastes.len(), 2);
+        app.pending = PendingAction::None;
+        app.paste_paths(pastes).expect("move");
+        assert!(root.join("dest/a.txt").exists() && root.join("dest/b.txt").exists());
+        assert!(!root.join("a.txt").exists());
+        assert_eq!(app.status, "Moved 2 items to dest");
+        assert!(app.tree_marked.is_empty());
+
+        app.rebuild_tree().expect("rebuild");
+        app.selected = row(&app, "c.txt");
+        key(&mut app, KeyModifiers::NONE, KeyCode::Char(' '));
+        app.selected = row(&app, "dest");
+        key(&mut app, KeyModifiers::NONE, KeyCode::Char(' '));
+        app.request_tree_delete(root.join("dest"));
+        let PendingAction::DeleteMany(paths) = app.pending.clone() else {
+            panic!("expected one confirmation for the whole delete");
+        };
+        app.pending = PendingAction::None;
+        app.delete_paths(paths).expect("delete");
+        assert!(!root.join("c.txt").exists() && !root.join("dest").exists());
+        assert_eq!(app.status, "Deleted 2 items");
+    }
+}
@@ -0,0 +1,422 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use notify::{RecursiveMode, Watcher};
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use super::{App, WorkspaceFolder};
+use crate::ignore::IgnoreRules;
+use crate::tree_item::TreeItem;
+use crate::types::{PromptMode, PromptState};
+use crate::util::{collect_all_files, detect_git_branch, relative_path};
+
+impl WorkspaceFolder {
+    pub(crate) fn load(path: PathBuf) -> Self {
+        Self {
+            branch: detect_git_branch(&path),
+            ignore_rules: IgnoreRules::load(&path),
+            path,
+        }
+    }
+
+    /// The folder's name, as its tree section and path prefix show it.
+    pub(crate) fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+}
+
+impl App {
+    pub(crate) fn open_add_workspace_folder_prompt(&mut self) {
+        self.prompt = Some(PromptState {
+            title: "Add folder to workspace (absolute, ~/ or relative to the project)".to_string(),
+            value: String::new(),
+            cursor: 0,
+            anchor: None,
+            history: None,
+            mode: PromptMode::AddWorkspaceFolder,
+        });
+    }
+
+    /// Load the folders saved for this project, skipping ones that are gone.
+    pub(crate) fn load_workspace_folders(&mut self) {
+        let paths = self
+            .workspace_folder_paths
+            .get(&self.root)
+            .cloned()
+            .unwrap_or_default();
+        self.workspace_folders = paths
+            .into_iter()
+            .filter(|p| p.is_dir())
+            .map(WorkspaceFolder::load)
+            .collect();
+    }
+
+    pub(crate) fn add_workspace_folder(&mut self, input: &str) -> io::Result<()> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(());
+        }
+        let path = match input.strip_prefix("~/") {
+            Some(rest) => std::env::var_os("HOME").map_or_else(
+                || PathBuf::from(input),
+                |home| PathBuf::from(home).join(rest),
+            ),
+            None => self.root.join(input),
+        };
+        let Ok(path) = path.canonicalize() else {
+            self.set_warning(format!("No such folder: {input}"));
+            return Ok(());
+        };
+        if !path.is_dir() {
+            self.set_warning(format!("Not a folder: {}", path.display()));
+            return Ok(());
+        }
+        let root = self
+            .root
+            .canonicalize()
+            .unwrap_or_else(|_| self.root.clone());
+        if path.starts_with(&root) || root.starts_with(&path) {
+            self.set_warning("That folder overlaps the project root");
+            return Ok(());
+        }
+        if self
+            .workspace_folders
+            .iter()
+            .any(|f| path.starts_with(&f.path) || f.path.starts_with(&path))
+        {
+            self.set_warning("That folder overlaps a workspace folder");
+            return Ok(());
+        }
+        if let Some(watcher) = self.fs_watcher.as_mut()
+            && let Err(err) = watcher.watch(&path, RecursiveMode::Recursive)
+        {
+            self.set_warning(format!("Filesystem watch unavailable: {err}"));
+        }
+        self.expanded.insert(path.clone());
+        self.workspace_folder_paths
+            .entry(self.root.clone())
+            .or_default()
+            .push(path.clone());
+        let folder = WorkspaceFolder::load(path);
+        let status = format!("Added {} to the workspace", folder.path.display());
+        self.workspace_folders.push(folder);
+        self.persist_state();
+        self.rebuild_tree()?;
+        self.set_status(status);
+        Ok(())
+    }
+
+    /// Remove the workspace folder holding the selected tree entry.
+    pub(crate) fn remove_workspace_folder(&mut self) -> io::Result<()> {
+        let selected = self.selected_item().map(|item| item.path.clone());
+        let Some(idx) = selected.and_then(|path| {
+            self.workspace_folders
+                .iter()
+                .position(|f| path.starts_with(&f.path))
+        }) else {
+            self.set_warning("Select a workspace folder in the tree to remove it");
+            return Ok(());
+        };
+        let folder = self.workspace_folders.remove(idx);
+        if let Some(watcher) = self.fs_watcher.as_mut() {
+            let _ = watcher.unwatch(&folder.path);
+        }
+        if let Some(paths) = self.workspace_folder_paths.get_mut(&self.root) {
+            paths.retain(|p| *p != folder.path);
+        }
+        self.persist_state();
+        self.rebuild_tree()?;
+        self.set_status(format!(
+            "Removed {} from the workspace",
+            folder.path.display()
+        ));
+        Ok(())
+    }
+
+    pub(crate) fn workspace_folder_for(&self, path: &Path) -> Option<&WorkspaceFolder> {
+        self.workspace_folders
+            .iter()
+            .find(|f| path.starts_with(&f.path))
+    }
+
+    /// The ignore rules of the root `path` is under.
+    pub(crate) fn ignore_rules_for(&self, path: &Path) -> &IgnoreRules {
+        self.workspace_folder_for(path)
+            .map_or(&self.ignore_rules, |f| &f.ignore_rules)
+    }
+
+    /// `path` relative to the project root, or prefixed with its workspace
+    /// folder's name when it is in one.
+    pub(crate) fn workspace_relative(&self, path: &Path) -> PathBuf {
+        match self.workspace_folder_for(path) {
+            Some(folder) => Path::new(&folder.name()).join(relative_path(&folder.path, path)),
+            None => relative_path(&self.root, path),
+        }
+    }
+
+    /// One tree section per workspace folder, after the project's files.
+    pub(crate) fn push_workspace_folders(&self, out: &mut Vec<TreeItem>) -> io::Result<()> {
+        for folder in &self.workspace_folders {
+            let heading = out.len();
+            self.walk_dir(&folder.path, 0, out)?;
+            if let Some(branch) = &folder.branch {
+                out[heading].name = format!("{} ({branch})", folder.name());
+            }
+        }
+        Ok(())
+    }
+
+    /// Every file of the project and of the workspace folders, for Quick
+    /// Open.
+    pub(crate) fn collect_workspace_files(&self, out: &mut Vec<PathBuf>) {
+        collect_all_files(&self.root, &self.ignore_rules, out);
+        for folder in &self.workspace_folders {
+            collect_all_files(&folder.path, &folder.ignore_rules, out);
+        }
+    }
+
+    pub(crate) fn watch_workspace_folders(&mut self) {
+        let Some(watcher) = self.fs_watcher.as_mut() else {
+            return;
+        };
+        for folder in &self.workspace_folders {
+            let _ = watcher.watch(&folder.path, RecursiveMode::Recursive);
+        }
+    }
+
+    /// Move this project to the front of the recent projects list.
+    pub(crate) fn record_recent_project(&mut self) {
+        let root = self
+            .root
+            .canonicalize()
+            .unwrap_or_else(|_| self.root.clone());
+        self.recent_projects.retain(|p| *p != root);
+        self.recent_projects.insert(0, root);
+        self.recent_projects.truncate(Self::RECENT_PROJECTS_LEN);
+        self.persist_state();
+    }
+
+    /// Recent projects other than the open one, most recent first.
+    pub(crate) fn recent_project_choices(&self) -> Vec<PathBuf> {
+        let root = self
+            .root
+            .canonicalize()
+            .unwrap_or_else(|_| self.root.clone());
+        self.recent_projects
+            .iter()
+            .filter(|p| **p != root)
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn open_recent_projects(&mut self) {
+        if self.recent_project_choices().is_empty() {
+            self.set_status("No other recent projects");
+            return;
+        }
+        self.recent_projects_view = Some(0);
+    }
+
+    pub(crate) fn handle_recent_projects_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let Some(selected) = self.recent_projects_view else {
+            return Ok(());
+        };
+        let choices = self.recent_project_choices();
+        let last = choices.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.recent_projects_view = None,
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.recent_projects_view = Some((selected + 1).min(last));
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.recent_projects_view = Some(selected.saturating_sub(1));
+            }
+            KeyCode::Delete | KeyCode::Char('d') => {
+                if let Some(path) = choices.get(selected) {
+                    self.recent_projects.retain(|p| p != path);
+                    self.persist_state();
+                }
+                self.recent_projects_view =
+                    (choices.len() > 1).then(|| selected.min(last.saturating_sub(1)));
+            }
+            KeyCode::Enter => {
+                self.recent_projects_view = None;
+                if let Some(path) = choices.get(selected).cloned() {
+                    self.switch_project(path)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Reopen the app on another project root: the tree, watcher, git
+    /// state, workspace folders and tabs all start over, and rust-analyzer
+    /// restarts for the new root when a Rust file is opened. Refused while
+    /// a tab has unsaved changes.
+    pub(crate) fn switch_project(&mut self, root: PathBuf) -> io::Result<()> {
+        if self.any_tab_dirty() {
+            self.set_warning("Save or close modified tabs before switching projects");
+            return Ok(());
+        }
+        if !root.is_dir() {
+            self.recent_projects.retain(|p| *p != root);
+            self.persist_state();
+            self.set_warning(format!("Project folder is gone: {}", root.display()));
+            return Ok(());
+        }
+        self.persist_state();
+        self.stop_lsp();
+        let mut next = App::new(root)?;
+        next.enhanced_keys = self.enhanced_keys;
+        *self = next;
+        self.record_recent_project();
+        Ok(())
+    }
+
+    /// After a filesystem change: re-read git branches when a folder's
+    /// `.git` changed, and ignore rules when an ignore file did.
+    pub(crate) fn refresh_workspace_folders(&mut self, git_changed: bool, ignores_changed: bool) {
+        for folder in &mut self.workspace_folders {
+            if git_changed {
+                folder.branch = detect_git_branch(&folder.path);
+            }
+            if ignores_changed {
+                folder.ignore_rules = IgnoreRules::load(&folder.path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::QuickOpenItem;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn workspace_folders_get_a_tree_section_and_join_quick_open() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path().join("app");
+        let lib = tmp.path().join("shared-lib");
+        fs::create_dir_all(root.join("src")).expect("mkdir");
+        fs::create_dir_all(lib.join("src")).expect("mkdir");
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").expect("write");
+        fs::write(lib.join("src/lib.rs"), "pub fn f() {}\n").expect("write");
+        fs::write(lib.join(".gitignore"), "build/\n").expect("write");
+        fs::create_dir_all(lib.join("build")).expect("mkdir");
+        fs::write(lib.join("build/out.txt"), "x\n").expect("write");
+        let mut app = App::new_for_test(&root);
+
+        app.add_workspace_folder("../shared-lib").expect("add");
+        let lib = lib.canonicalize().expect("canonical");
+        assert_eq!(app.workspace_folders.len(), 1);
+        let heading = app
+            .tree
+            .iter()
+            .position(|i| i.path == lib)
+            .expect("folder heading");
+        assert_eq!(app.tree[heading].depth, 0);
+        assert_eq!(app.tree[heading].name, "shared-lib");
+        let src = &app.tree[heading + 1];
+        assert_eq!((src.name.as_str(), src.depth), ("src", 1));
+        // The folder's own .gitignore applies.
+        assert!(app.tree.iter().all(|i| i.name != "build"));
+        assert!(!app.is_outside_project(&lib.join("src/lib.rs")));
+        assert_eq!(
+            app.workspace_relative(&lib.join("src/lib.rs")),
+            PathBuf::from("shared-lib/src/lib.rs")
+        );
+
+        app.file_picker_query = "shlibrs".to_string();
+        app.refresh_file_picker_results();
+        assert_eq!(
+            app.file_picker_results,
+            [QuickOpenItem::file(lib.join("src/lib.rs"))]
+        );
+
+        app.add_workspace_folder(".").expect("add root");
+        assert_eq!(app.workspace_folders.len(), 1);
+        assert_eq!(
+            app.persisted_state().workspace_folders.get(&root),
+            Some(&vec![lib.clone()])
+        );
+
+        app.selected = heading + 1;
+        app.remove_workspace_folder().expect("remove");
+        assert!(app.workspace_folders.is_empty());
+        assert!(app.tree.iter().all(|i| !i.path.starts_with(&lib)));
+    }
+
+    #[test]
+    fn entries_in_workspace_folders_can_be_renamed_but_not_the_folder() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path().join("app");
+        let lib = tmp.path().join("shared-lib");
+        fs::create_dir_all(&root).expect("mkdir");
+        fs::create_dir_all(&lib).expect("mkdir");
+        fs::write(lib.join("old.txt"), "x\n").expect("write");
+        let mut app = App::new_for_test(&root);
+        app.add_workspace_folder("../shared-lib").expect("add");
+        let lib = lib.canonicalize().expect("canonical");
+
+        app.apply_prompt(
+            PromptMode::Rename {
+                target: lib.join("old.txt"),
+            },
+            "new.txt".to_string(),
+        )
+        .expect("rename");
+        assert!(lib.join("new.txt").exists() && !lib.join("old.txt").exists());
+        assert_eq!(
+            app.check_mutable_path(&lib, "delete"),
+            Err("Cannot delete a workspace folder".to_string())
+        );
+        assert!(
+            app.check_mutable_path(&tmp.path().join("other"), "delete")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn recent_project_picker_switches_the_root() {
+        let tmp = tempdir().expect("tempdir");
+        let first = tmp.path().join("first");
+        let second = tmp.path().join("second");
+        fs::create_dir_all(&first).expect("mkdir");
+        fs::create_dir_all(&second).expect("mkdir");
+        fs::write(first.join("a.txt"), "a\n").expect("write");
+        fs::write(second.join("b.txt"), "b\n").expect("write");
+        let (first, second) = (
+            first.canonicalize().expect("canonical"),
+            second.canonicalize().expect("canonical"),
+        );
+        let mut app = App::new_for_test(&first);
+        app.recent_projects = vec![first.clone(), second.clone(), tmp.path().join("gone")];
+        app.open_file(first.join("a.txt")).expect("open");
+
+        // The open project is left out of the list.
+        app.open_recent_projects();
+        assert_eq!(
+            app.recent_project_choices(),
+            [second.clone(), tmp.path().join("gone")]
+        );
+        app.tabs[0].dirty = true;
+        app.handle_recent_projects_key(KeyEvent::from(KeyCode::Enter))
+            .expect("enter");
+        assert_eq!(app.root, first);
+
+        app.tabs[0].dirty = false;
+        app.open_recent_projects();
+        app.handle_recent_projects_key(KeyEvent::from(KeyCode::Enter))
+            .expect("enter");
+        assert_eq!(app.root, second);
+        assert!(app.tabs.is_empty());
+        assert!(app.tree.iter().any(|i| i.path == second.join("b.txt")));
+        assert_eq!(app.recent_projects[..2], [second.clone(), first.clone()]);
+    }
+}
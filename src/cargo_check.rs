@@ -0,0 +1,192 @@
+//! Project-wide diagnostics from `cargo check --message-format=json`, for
+//! every file in the workspace rather than only those open in
+//! rust-analyzer.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use serde_json::Value;
+
+use crate::lsp_client::LspDiagnostic;
+use crate::persistence::LspSettings;
+
+/// Diagnostics by absolute file path.
+pub(crate) type CargoDiagnostics = BTreeMap<PathBuf, Vec<LspDiagnostic>>;
+
+/// `cargo` arguments for a check of the whole workspace, using the check
+/// command and features configured for rust-analyzer.
+pub(crate) fn cargo_check_args(settings: &LspSettings) -> Vec<String> {
+    let command = settings
+        .check_command
+        .as_deref()
+        .filter(|c| !c.trim().is_empty())
+        .unwrap_or("check");
+    let mut args = vec![
+        command.to_string(),
+        "--workspace".to_string(),
+        "--message-format=json".to_string(),
+    ];
+    if settings.all_features {
+        args.push("--all-features".to_string());
+    } else if !settings.cargo_features.is_empty() {
+        args.push("--features".to_string());
+        args.push(settings.cargo_features.join(","));
+    }
+    args
+}
+
+/// The compiler messages in cargo's JSON output, at their primary span.
+/// Span paths are relative to the workspace root, which may be `root` or
+/// one of its parents.
+pub(crate) fn parse_cargo_messages(output: &str, root: &Path) -> CargoDiagnostics {
+    let mut found = CargoDiagnostics::new();
+    for line in output.lines() {
+        let Ok(record) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if record.get("reason").and_then(Value::as_str) != Some("compiler-message") {
+            continue;
+        }
+        let message = &record["message"];
+        let severity = match message.get("level").and_then(Value::as_str) {
+            Some("error") => "error",
+            Some("warning") => "warning",
+            Some("note") => "info",
+            Some("help") => "hint",
+            _ => continue,
+        };
+        let Some(span) = message
+            .get("spans")
+            .and_then(Value::as_array)
+            .and_then(|spans| spans.iter().find(|s| s["is_primary"] == true))
+        else {
+            continue;
+        };
+        let Some(path) = span
+            .get("file_name")
+            .and_then(Value::as_str)
+            .and_then(|name| resolve_span_path(name, root))
+        else {
+            continue;
+        };
+        let number = |key: &str| span.get(key).and_then(Value::as_u64).unwrap_or(1) as usize;
+        let start = (
+            number("line_start").saturating_sub(1),
+            number("column_start").saturating_sub(1),
+        );
+        let end = (
+            number("line_end").saturating_sub(1),
+            number("column_end").saturating_sub(1),
+        );
+        let diagnostic = LspDiagnostic {
+            line: start.0 + 1,
+            severity: severity.to_string(),
+            message: message
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            range: Some((start, end)),
+        };
+        let entries = found.entry(path).or_default();
+        if !entries.contains(&diagnostic) {
+            entries.push(diagnostic);
+        }
+    }
+    found
+}
+
+fn resolve_span_path(name: &str, root: &Path) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    root.ancestors()
+        .map(|dir| dir.join(path))
+        .find(|p| p.is_file())
+}
+
+/// Run cargo with `args` in `root` on a background thread; the parsed
+/// diagnostics arrive on the returned channel when it exits.
+pub(crate) fn spawn_cargo_check(
+    root: &Path,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+) -> Receiver<io::Result<CargoDiagnostics>> {
+    let (tx, rx) = mpsc::channel();
+    let root = root.to_path_buf();
+    thread::spawn(move || {
+        let result = Command::new("cargo")
+            .args(&args)
+            .envs(env)
+            .current_dir(&root)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .map(|output| parse_cargo_messages(&String::from_utf8_lossy(&output.stdout), &root));
+        let _ = tx.send(result);
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn parses_primary_spans_of_compiler_messages() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir(root.join("src")).expect("mkdir");
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").expect("write");
+        let output = [
+            r#"{"reason":"compiler-artifact","target":{"name":"demo"}}"#,
+            r#"{"reason":"compiler-message","message":{"level":"error","message":"mismatched types","spans":[{"file_name":"src/main.rs","is_primary":false,"line_start":1,"line_end":1,"column_start":1,"column_end":3},{"file_name":"src/main.rs","is_primary":true,"line_start":2,"line_end":2,"column_start":17,"column_end":19}]}}"#,
+            r#"{"reason":"compiler-message","message":{"level":"warning","message":"unused variable: `x`","spans":[{"file_name":"src/main.rs","is_primary":true,"line_start":2,"line_end":2,"column_start":9,"column_end":10}]}}"#,
+            r#"{"reason":"compiler-message","message":{"level":"error","message":"aborting due to 1 previous error","spans":[]}}"#,
+            r#"{"reason":"compiler-message","message":{"level":"error","message":"gone","spans":[{"file_name":"src/gone.rs","is_primary":true,"line_start":1,"line_end":1,"column_start":1,"column_end":2}]}}"#,
+            r#"{"reason":"build-finished","success":false}"#,
+        ]
+        .join("\n");
+        let found = parse_cargo_messages(&output, root);
+        assert_eq!(found.len(), 1);
+        let diagnostics = &found[&root.join("src/main.rs")];
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| (d.line, d.severity.as_str(), d.range))
+                .collect::<Vec<_>>(),
+            [
+                (2, "error", Some(((1, 16), (1, 18)))),
+                (2, "warning", Some(((1, 8), (1, 9)))),
+            ]
+        );
+
+        // A member crate opened on its own still finds workspace-relative paths.
+        let member = root.join("crates/member");
+        fs::create_dir_all(&member).expect("mkdir");
+        assert_eq!(parse_cargo_messages(&output, &member).len(), 1);
+
+        let settings = LspSettings {
+            check_command: Some("clippy".to_string()),
+            cargo_features: vec!["a".to_string(), "b".to_string()],
+            ..LspSettings::default()
+        };
+        assert_eq!(
+            cargo_check_args(&settings),
+            [
+                "clippy",
+                "--workspace",
+                "--message-format=json",
+                "--features",
+                "a,b"
+            ]
+        );
+    }
+}
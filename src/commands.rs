@@ -0,0 +1,203 @@
+//! The command palette's commands. Each entry names the action, its label
+//! and the key action doing the same thing, whose binding the palette shows;
+//! a new command is registered by adding it here.
+
+use crate::keybinds::{KeyAction, KeyAction as K};
+use crate::types::{CommandAction, CommandAction as C};
+
+pub(crate) struct Command {
+    pub(crate) action: CommandAction,
+    pub(crate) label: &'static str,
+    pub(crate) key: Option<KeyAction>,
+}
+
+const fn command(action: CommandAction, label: &'static str, key: Option<KeyAction>) -> Command {
+    Command { action, label, key }
+}
+
+/// Every command, in the palette's default order.
+pub(crate) const COMMANDS: &[Command] = &[
+    command(C::Theme, "Theme Picker", None),
+    command(C::Help, "Help", Some(K::Help)),
+    command(C::QuickOpen, "Quick Open Files", Some(K::QuickOpen)),
+    command(C::FindInFile, "Find in File", Some(K::Find)),
+    command(C::FindInProject, "Search in Project", Some(K::SearchFiles)),
+    command(
+        C::FindInProjectWithOptions,
+        "Search in Project with Options",
+        None,
+    ),
+    command(C::SaveFile, "Save File", Some(K::Save)),
+    command(C::RefreshTree, "Refresh Tree", Some(K::RefreshTree)),
+    command(C::ToggleFiles, "Toggle Files Pane", Some(K::ToggleFiles)),
+    command(
+        C::GotoDefinition,
+        "Go to Definition",
+        Some(K::GoToDefinition),
+    ),
+    command(C::ReplaceInFile, "Find and Replace", Some(K::FindReplace)),
+    command(C::ReplaceInProject, "Replace in Project", None),
+    command(C::GoToLine, "Go to Line", Some(K::GoToLine)),
+    command(C::Keybinds, "Keybind Editor", None),
+    command(
+        C::ToggleWordWrap,
+        "Toggle Word Wrap",
+        Some(K::ToggleWordWrap),
+    ),
+    command(C::OpenStateFolder, "Open State Folder", None),
+    command(C::ToggleIgnoredFiles, "Toggle Ignored Files", None),
+    command(C::ToggleNerdFontIcons, "Toggle Nerd Font Icons", None),
+    command(C::ExportTodos, "Export TODOs (JSON/CSV)", None),
+    command(C::MoveTabLeft, "Move Tab Left", Some(K::MoveTabLeft)),
+    command(C::MoveTabRight, "Move Tab Right", Some(K::MoveTabRight)),
+    command(
+        C::CloseOtherTabs,
+        "Close Other Tabs",
+        Some(K::CloseOtherTabs),
+    ),
+    command(
+        C::CloseTabsToRight,
+        "Close Tabs to the Right",
+        Some(K::CloseTabsToRight),
+    ),
+    command(C::CloseAllTabs, "Close All Tabs", Some(K::CloseAllTabs)),
+    command(
+        C::ReopenClosedTab,
+        "Reopen Closed Tab",
+        Some(K::ReopenClosedTab),
+    ),
+    command(C::TogglePinTab, "Pin/Unpin Tab", Some(K::TogglePinTab)),
+    command(
+        C::ChangedFiles,
+        "Open Changed File (git)",
+        Some(K::ChangedFiles),
+    ),
+    command(C::SetLanguageMode, "Set Language Mode", None),
+    command(C::LanguageOverrides, "Language Mode Overrides", None),
+    command(
+        C::ShowLastMessage,
+        "Show Last Message",
+        Some(K::ShowLastMessage),
+    ),
+    command(
+        C::JumpToBracket,
+        "Jump to Matching Bracket",
+        Some(K::JumpToBracket),
+    ),
+    command(
+        C::GoToLastEdit,
+        "Go to Last Edit Location",
+        Some(K::GoToLastEdit),
+    ),
+    command(C::JumpBack, "Jump Back", Some(K::JumpBack)),
+    command(C::JumpForward, "Jump Forward", Some(K::JumpForward)),
+    command(
+        C::CenterCursorLine,
+        "Scroll Cursor Line to Center",
+        Some(K::CenterCursorLine),
+    ),
+    command(C::CursorLineToTop, "Scroll Cursor Line to Top", None),
+    command(C::CursorLineToBottom, "Scroll Cursor Line to Bottom", None),
+    command(C::ToggleProjectTrust, "Trust / Untrust Project", None),
+    command(
+        C::PasteFromHistory,
+        "Paste from History",
+        Some(K::PasteFromHistory),
+    ),
+    command(
+        C::ConvertIndentToSpaces,
+        "Convert Indentation to Spaces",
+        None,
+    ),
+    command(C::ConvertIndentToTabs, "Convert Indentation to Tabs", None),
+    command(C::ReindentFile, "Reindent File", None),
+    command(C::GenerateRustTest, "Generate Test for Function", None),
+    command(
+        C::GoToTestOrImplementation,
+        "Go to Test / Go to Implementation",
+        None,
+    ),
+    command(C::EditEnvFile, "Edit .env", None),
+    command(
+        C::ToggleLineEndings,
+        "Convert Line Endings (LF / CRLF)",
+        None,
+    ),
+    command(
+        C::ToggleFollowBroadcast,
+        "Start / Stop Follow Mode Broadcast",
+        None,
+    ),
+    command(C::FollowSession, "Follow Another Session", None),
+    command(C::RunCodeBlock, "Run Code Block", None),
+    command(C::OpenLogView, "Open Log View", None),
+    command(C::ToggleFollowFile, "Follow File", None),
+    command(C::OpenSettings, "Preferences: Open Settings", None),
+    command(C::OpenHexEditor, "Open in Hex Editor", None),
+    command(C::AddWorkspaceFolder, "Add Folder to Workspace", None),
+    command(
+        C::RemoveWorkspaceFolder,
+        "Remove Folder from Workspace",
+        None,
+    ),
+    command(C::PasteVerbatim, "Paste Verbatim (No Re-indent)", None),
+    command(C::OpenRecentProject, "Open Recent Project", None),
+    command(C::ListBookmarks, "List Bookmarks", None),
+    command(
+        C::ToggleGutterLineNumbers,
+        "Toggle Gutter: Line Numbers",
+        None,
+    ),
+    command(C::ToggleGutterFolds, "Toggle Gutter: Fold Markers", None),
+    command(
+        C::ToggleGutterDiagnostics,
+        "Toggle Gutter: Diagnostics",
+        None,
+    ),
+    command(C::ToggleGutterGit, "Toggle Gutter: Git Changes", None),
+    command(C::PasteLocations, "Paste Error / Location", None),
+    command(C::GoToParentModule, "Go to Parent Module (Rust)", None),
+    command(
+        C::GoToModuleDeclaration,
+        "Go to Module Declaration (Rust)",
+        None,
+    ),
+    command(C::SiblingModules, "Sibling Modules (Rust)", None),
+    command(C::RevertFileToHead, "Revert File to HEAD", None),
+    command(C::RevertHunk, "Revert Hunk to HEAD", None),
+    command(C::DiffWithHead, "Diff with HEAD", None),
+    command(C::ShowLspConfig, "Show LSP Configuration", None),
+    command(C::RunTask, "Run Task", None),
+    command(C::StopTask, "Stop Task", None),
+    command(C::ToggleTaskOutput, "Toggle Task Output", None),
+    command(C::CompareWithClipboard, "Compare with Clipboard", None),
+    command(C::RunCargoCheck, "Run Cargo Check", None),
+    command(C::CargoCheckProblems, "Cargo Check Problems", None),
+    command(C::TestExplorer, "Test Explorer", None),
+    command(C::GitLog, "Git Log: Project", None),
+    command(C::GitFileHistory, "Git Log: Current File", None),
+    command(C::SwitchBranch, "Switch Branch", None),
+    command(C::CreateBranch, "Create Branch", None),
+    command(C::AcceptConflictOurs, "Merge Conflict: Accept Ours", None),
+    command(
+        C::AcceptConflictTheirs,
+        "Merge Conflict: Accept Theirs",
+        None,
+    ),
+    command(C::AcceptConflictBoth, "Merge Conflict: Accept Both", None),
+    command(C::NextConflict, "Merge Conflict: Next", None),
+    command(C::PreviousConflict, "Merge Conflict: Previous", None),
+    command(C::FindReferences, "Find References", None),
+    command(C::RenameSymbol, "Rename Symbol", None),
+    command(C::FormatDocument, "Format Document", None),
+];
+
+/// The entry for `action`.
+pub(crate) fn command_for(action: CommandAction) -> Option<&'static Command> {
+    COMMANDS.iter().find(|c| c.action == action)
+}
+
+/// The command whose label is `label`, for recency lists saved by label.
+pub(crate) fn command_by_label(label: &str) -> Option<CommandAction> {
+    COMMANDS.iter().find(|c| c.label == label).map(|c| c.action)
+}
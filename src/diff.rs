@@ -0,0 +1,449 @@
+//! Line-based diff (Myers) used to compare a buffer against its file on disk.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DiffOp {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// One line of an edit script. `old` and `new` index the line on each side;
+/// a deleted line has no `new` index and an inserted one no `old` index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DiffLine {
+    pub(crate) op: DiffOp,
+    pub(crate) old: Option<usize>,
+    pub(crate) new: Option<usize>,
+}
+
+impl DiffLine {
+    fn equal(old: usize, new: usize) -> Self {
+        Self {
+            op: DiffOp::Equal,
+            old: Some(old),
+            new: Some(new),
+        }
+    }
+
+    fn delete(old: usize) -> Self {
+        Self {
+            op: DiffOp::Delete,
+            old: Some(old),
+            new: None,
+        }
+    }
+
+    fn insert(new: usize) -> Self {
+        Self {
+            op: DiffOp::Insert,
+            old: None,
+            new: Some(new),
+        }
+    }
+}
+
+/// A run of changes with up to `context` unchanged lines around it.
+/// Starts are zero-based line indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DiffHunk {
+    pub(crate) old_start: usize,
+    pub(crate) old_len: usize,
+    pub(crate) new_start: usize,
+    pub(crate) new_len: usize,
+    pub(crate) lines: Vec<DiffLine>,
+}
+
+/// Shortest edit script turning `old` into `new`. Within a change, deleted
+/// lines come before the inserted lines that replace them.
+pub(crate) fn diff_lines(old: &[String], new: &[String]) -> Vec<DiffLine> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut out: Vec<DiffLine> = (0..prefix).map(|i| DiffLine::equal(i, i)).collect();
+    out.extend(myers(old_mid, new_mid).into_iter().map(|line| DiffLine {
+        old: line.old.map(|i| i + prefix),
+        new: line.new.map(|i| i + prefix),
+        ..line
+    }));
+    let (old_tail, new_tail) = (old.len() - suffix, new.len() - suffix);
+    out.extend((0..suffix).map(|i| DiffLine::equal(old_tail + i, new_tail + i)));
+    out
+}
+
+fn myers(a: &[String], b: &[String]) -> Vec<DiffLine> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    // trace[d] holds the furthest x per diagonal k in -d..=d before round d.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    'search: for d in 0..=max {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let at = |k: isize| v[(k + offset) as usize];
+            let mut x = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+                at(k + 1)
+            } else {
+                at(k - 1) + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(k + offset) as usize] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, row) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let at = |k: isize| row[(k + d) as usize];
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = if d == 0 { 0 } else { at(prev_k) };
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            out.push(DiffLine::equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                y -= 1;
+                out.push(DiffLine::insert(y as usize));
+            } else {
+                x -= 1;
+                out.push(DiffLine::delete(x as usize));
+            }
+        }
+    }
+    out.reverse();
+    // Backtracking interleaves deletes and inserts; list each change's
+    // deletions first so a replaced block reads old-then-new.
+    for run in out.chunk_by_mut(|a, b| a.op != DiffOp::Equal && b.op != DiffOp::Equal) {
+        run.sort_by_key(|line| line.op == DiffOp::Insert);
+    }
+    out
+}
+
+/// Group an edit script into hunks, merging changes separated by at most
+/// `2 * context` unchanged lines.
+pub(crate) fn diff_hunks(lines: &[DiffLine], context: usize) -> Vec<DiffHunk> {
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.op != DiffOp::Equal)
+        .map(|(i, _)| i)
+        .collect();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &i in &changed {
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(lines.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let slice = &lines[start..end];
+            // Where the hunk begins on each side, even if its first line
+            // exists on only one of them.
+            let old_start = lines[..start].iter().filter(|l| l.old.is_some()).count();
+            let new_start = lines[..start].iter().filter(|l| l.new.is_some()).count();
+            DiffHunk {
+                old_start,
+                old_len: slice.iter().filter(|l| l.old.is_some()).count(),
+                new_start,
+                new_len: slice.iter().filter(|l| l.new.is_some()).count(),
+                lines: slice.to_vec(),
+            }
+        })
+        .collect()
+}
+
+/// Which lines of `new` differ from `old`: index `i` is set when line `i`
+/// was inserted or lines were deleted just before it. The extra last entry
+/// covers deletions at the end.
+pub(crate) fn changed_positions(old: &[String], new: &[String]) -> Vec<bool> {
+    let mut changed = vec![false; new.len() + 1];
+    let mut new_pos = 0;
+    for line in diff_lines(old, new) {
+        match line.op {
+            DiffOp::Equal => new_pos += 1,
+            DiffOp::Insert => {
+                changed[new_pos] = true;
+                new_pos += 1;
+            }
+            DiffOp::Delete => changed[new_pos] = true,
+        }
+    }
+    changed
+}
+
+/// Rebuild `old` with some of its differences from `new` applied: hunk `i`
+/// (from `diff_hunks` with no context) comes from `new` when `take_new[i]`.
+pub(crate) fn merge_hunks(
+    old: &[String],
+    new: &[String],
+    hunks: &[DiffHunk],
+    take_new: &[bool],
+) -> Vec<String> {
+    let mut out = Vec::with_capacity(old.len().max(new.len()));
+    let mut old_pos = 0;
+    for (hunk, &take) in hunks.iter().zip(take_new) {
+        out.extend_from_slice(&old[old_pos..hunk.old_start]);
+        if take {
+            out.extend_from_slice(&new[hunk.new_start..hunk.new_start + hunk.new_len]);
+        } else {
+            out.extend_from_slice(&old[hunk.old_start..hunk.old_start + hunk.old_len]);
+        }
+        old_pos = hunk.old_start + hunk.old_len;
+    }
+    out.extend_from_slice(&old[old_pos..]);
+    out
+}
+
+/// `lines` with the one change covering `row` taken back to `base`: the
+/// hunk whose lines include `row`, or the deletion just before it. Returns
+/// the result and the row the hunk now starts on.
+pub(crate) fn revert_hunk_at(
+    lines: &[String],
+    base: &[String],
+    row: usize,
+) -> Option<(Vec<String>, usize)> {
+    let hunks = diff_hunks(&diff_lines(lines, base), 0);
+    let last = lines.len().saturating_sub(1);
+    let idx = hunks.iter().position(|h| {
+        if h.old_len == 0 {
+            row == h.old_start.min(last)
+        } else {
+            (h.old_start..h.old_start + h.old_len).contains(&row)
+        }
+    })?;
+    let take: Vec<bool> = (0..hunks.len()).map(|i| i == idx).collect();
+    let reverted = merge_hunks(lines, base, &hunks, &take);
+    let start = hunks[idx].old_start.min(reverted.len().saturating_sub(1));
+    Some((reverted, start))
+}
+
+/// One row of a side-by-side diff: the line shown on each side, if any, and
+/// for changed rows the hunk (as `diff_hunks` with no context numbers them)
+/// the row belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SideBySideRow {
+    pub(crate) old: Option<usize>,
+    pub(crate) new: Option<usize>,
+    pub(crate) hunk: Option<usize>,
+}
+
+/// Lay an edit script out in two columns: unchanged lines side by side,
+/// and each change's deleted lines beside the lines inserted for them.
+pub(crate) fn side_by_side_rows(lines: &[DiffLine]) -> Vec<SideBySideRow> {
+    let mut rows = Vec::with_capacity(lines.len());
+    let mut hunk = 0;
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].op == DiffOp::Equal {
+            rows.push(SideBySideRow {
+                old: lines[i].old,
+                new: lines[i].new,
+                hunk: None,
+            });
+            i += 1;
+            continue;
+        }
+        let end = lines[i..]
+            .iter()
+            .position(|l| l.op == DiffOp::Equal)
+            .map_or(lines.len(), |n| i + n);
+        let deleted: Vec<usize> = lines[i..end].iter().filter_map(|l| l.old).collect();
+        let inserted: Vec<usize> = lines[i..end].iter().filter_map(|l| l.new).collect();
+        for k in 0..deleted.len().max(inserted.len()) {
+            rows.push(SideBySideRow {
+                old: deleted.get(k).copied(),
+                new: inserted.get(k).copied(),
+                hunk: Some(hunk),
+            });
+        }
+        hunk += 1;
+        i = end;
+    }
+    rows
+}
+
+/// Rows of a unified diff: a `None` op marks an `@@ -a,b +c,d @@` hunk
+/// header, other rows are the line prefixed with ' ', '-' or '+'.
+pub(crate) fn unified_diff(
+    old: &[String],
+    new: &[String],
+    context: usize,
+) -> Vec<(Option<DiffOp>, String)> {
+    // Like `diff -u`, an empty side is numbered from the line before it.
+    let start = |start: usize, len: usize| if len == 0 { start } else { start + 1 };
+    let mut rows = Vec::new();
+    for hunk in diff_hunks(&diff_lines(old, new), context) {
+        rows.push((
+            None,
+            format!(
+                "@@ -{},{} +{},{} @@",
+                start(hunk.old_start, hunk.old_len),
+                hunk.old_len,
+                start(hunk.new_start, hunk.new_len),
+                hunk.new_len
+            ),
+        ));
+        for line in hunk.lines {
+            let row = match (line.op, line.old, line.new) {
+                (DiffOp::Insert, _, Some(i)) => format!("+{}", new[i]),
+                (DiffOp::Delete, Some(i), _) => format!("-{}", old[i]),
+                (_, Some(i), _) => format!(" {}", old[i]),
+                _ => continue,
+            };
+            rows.push((Some(line.op), row));
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split('\n').map(ToString::to_string).collect()
+    }
+
+    fn apply(old: &[String], new: &[String], script: &[DiffLine]) -> (Vec<String>, Vec<String>) {
+        let mut from = Vec::new();
+        let mut to = Vec::new();
+        for line in script {
+            if let Some(i) = line.old {
+                from.push(old[i].clone());
+            }
+            if let Some(i) = line.new {
+                to.push(new[i].clone());
+            }
+        }
+        (from, to)
+    }
+
+    #[test]
+    fn diff_lines_is_minimal_and_reconstructs_both_sides() {
+        let old = lines("a\nb\nc\nd\ne");
+        let new = lines("a\nx\nc\nd\ne\nf");
+        let script = diff_lines(&old, &new);
+        assert_eq!(apply(&old, &new, &script), (old.clone(), new.clone()));
+        let ops: Vec<DiffOp> = script.iter().map(|l| l.op).collect();
+        assert_eq!(
+            ops,
+            vec![
+                DiffOp::Equal,
+                DiffOp::Delete,
+                DiffOp::Insert,
+                DiffOp::Equal,
+                DiffOp::Equal,
+                DiffOp::Equal,
+                DiffOp::Insert,
+            ]
+        );
+
+        let empty = diff_lines(&[], &lines("one\ntwo"));
+        assert!(empty.iter().all(|l| l.op == DiffOp::Insert));
+        assert_eq!(diff_lines(&old, &old).len(), old.len());
+    }
+
+    #[test]
+    fn diff_hunks_group_changes_with_context() {
+        let old: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        let mut new = old.clone();
+        new[2] = "two".to_string();
+        new[4] = "four".to_string();
+        new[15] = "fifteen".to_string();
+        let hunks = diff_hunks(&diff_lines(&old, &new), 2);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!((hunks[0].old_start, hunks[0].old_len), (0, 7));
+        assert_eq!((hunks[1].new_start, hunks[1].new_len), (13, 5));
+        assert!(diff_hunks(&diff_lines(&old, &old), 3).is_empty());
+    }
+
+    #[test]
+    fn merge_hunks_takes_each_hunk_from_the_chosen_side() {
+        let old = lines("a\nb\nc\nd\ne");
+        let new = lines("a\nB\nc\nd\nE\nf");
+        let hunks = diff_hunks(&diff_lines(&old, &new), 0);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(merge_hunks(&old, &new, &hunks, &[false, false]), old);
+        assert_eq!(merge_hunks(&old, &new, &hunks, &[true, true]), new);
+        assert_eq!(
+            merge_hunks(&old, &new, &hunks, &[true, false]),
+            lines("a\nB\nc\nd\ne")
+        );
+        assert_eq!(
+            changed_positions(&old, &new),
+            vec![false, true, false, false, true, true, false]
+        );
+    }
+
+    #[test]
+    fn unified_diff_renders_headers_and_prefixes() {
+        let rows = unified_diff(&lines("a\nb\nc"), &lines("a\nB\nc\nd"), 1);
+        let text: Vec<&str> = rows.iter().map(|(_, row)| row.as_str()).collect();
+        assert_eq!(text, vec!["@@ -1,3 +1,4 @@", " a", "-b", "+B", " c", "+d"]);
+        assert_eq!(rows[0].0, None);
+        assert_eq!(rows[2].0, Some(DiffOp::Delete));
+    }
+
+    #[test]
+    fn revert_hunk_at_undoes_only_the_change_under_the_row() {
+        let base = lines("a\nb\nc\nd\ne");
+        let edited = lines("a\nB\nc\ne\nf");
+        let (reverted, start) = revert_hunk_at(&edited, &base, 1).expect("hunk");
+        assert_eq!((reverted, start), (lines("a\nb\nc\ne\nf"), 1));
+        // "d" was deleted before "e": the hunk sits on the line after it.
+        let (reverted, start) = revert_hunk_at(&edited, &base, 3).expect("hunk");
+        assert_eq!((reverted, start), (lines("a\nB\nc\nd\ne\nf"), 3));
+        let (reverted, _) = revert_hunk_at(&edited, &base, 4).expect("hunk");
+        assert_eq!(reverted, lines("a\nB\nc\ne"));
+        assert_eq!(revert_hunk_at(&edited, &base, 2), None);
+    }
+
+    #[test]
+    fn side_by_side_rows_pair_changes_and_number_hunks() {
+        let old = lines("a\nb\nc\nd");
+        let new = lines("a\nB\nB2\nc");
+        let script = diff_lines(&old, &new);
+        let rows: Vec<_> = side_by_side_rows(&script)
+            .into_iter()
+            .map(|r| (r.old, r.new, r.hunk))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (Some(0), Some(0), None),
+                (Some(1), Some(1), Some(0)),
+                (None, Some(2), Some(0)),
+                (Some(2), Some(3), None),
+                (Some(3), None, Some(1)),
+            ]
+        );
+        assert_eq!(diff_hunks(&script, 0).len(), 2);
+    }
+}
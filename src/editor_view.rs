@@ -0,0 +1,357 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use ratatui::buffer::Buffer;
+use ratatui::crossterm::event::{Event, KeyEvent, KeyEventKind, MouseEventKind};
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Paragraph, Widget};
+use ratatui_textarea::{CursorMove, Input, TextArea};
+
+use crate::keybinds::{KeyAction, KeyBindings, KeyScope, load_keybindings};
+use crate::tab::Tab;
+use crate::theme::{Theme, load_themes};
+use crate::ui::{Gutter, editor_cursor_position, editor_lines};
+use crate::util::{decode_text, display_width_of_range, text_to_lines, to_u16_saturating};
+
+/// lazyide's editor pane as a standalone ratatui widget: the text buffer with
+/// syntax highlighting, folding and the user's editor keybindings, driven by
+/// whatever event loop embeds it.
+///
+/// Feed terminal events to [`EditorView::handle_event`] and render with
+/// `frame.render_widget(&mut view, area)`. Copy, cut and paste use the
+/// widget's own yank buffer rather than the system clipboard.
+pub struct EditorView {
+    tab: Tab,
+    themes: Vec<Theme>,
+    theme_index: usize,
+    keybinds: KeyBindings,
+    word_wrap: bool,
+    focused: bool,
+    last_area: Rect,
+}
+
+impl EditorView {
+    /// An unnamed buffer holding `text`, highlighted as plain text.
+    pub fn new(text: &str) -> Self {
+        Self::with_path(PathBuf::new(), text)
+    }
+
+    /// Load `path` into a buffer highlighted according to its extension.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let text = decode_text(&fs::read(&path)?);
+        Ok(Self::with_path(path, &text))
+    }
+
+    fn with_path(path: PathBuf, text: &str) -> Self {
+        let themes = load_themes();
+        let theme_index = themes
+            .iter()
+            .position(|t| t.name == "One Dark Pro")
+            .unwrap_or(0);
+        let mut view = Self {
+            tab: Tab::new(path, TextArea::from(text_to_lines(text))),
+            themes,
+            theme_index,
+            keybinds: load_keybindings(),
+            word_wrap: false,
+            focused: true,
+            last_area: Rect::default(),
+        };
+        view.apply_theme_styles();
+        view
+    }
+
+    /// The buffer contents, lines joined with `\n`.
+    pub fn text(&self) -> String {
+        self.tab.editor.lines().join("\n")
+    }
+
+    pub fn lines(&self) -> &[String] {
+        self.tab.editor.lines()
+    }
+
+    /// Cursor position as `(row, col)`, both zero-based, col in chars.
+    pub fn cursor(&self) -> (usize, usize) {
+        self.tab.editor.cursor()
+    }
+
+    /// Whether the text has been edited since the view was created.
+    pub fn is_modified(&self) -> bool {
+        self.tab.dirty
+    }
+
+    /// Whether the cursor is drawn; unfocused views render without one.
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    pub fn set_word_wrap(&mut self, word_wrap: bool) {
+        self.word_wrap = word_wrap;
+        self.rebuild_visible_rows();
+    }
+
+    /// Switch to the theme called `name`; returns false if no theme has that name.
+    pub fn set_theme(&mut self, name: &str) -> bool {
+        let Some(idx) = self.themes.iter().position(|t| t.name == name) else {
+            return false;
+        };
+        self.theme_index = idx;
+        self.apply_theme_styles();
+        true
+    }
+
+    /// Apply a terminal event. Returns true when the text changed.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::Key(key) if key.kind != KeyEventKind::Release => self.handle_key(*key),
+            Event::Paste(text) => {
+                let changed = self.tab.editor.insert_str(text);
+                if changed {
+                    self.on_content_changed();
+                }
+                changed
+            }
+            Event::Mouse(mouse) => {
+                let step = match mouse.kind {
+                    MouseEventKind::ScrollDown => 3isize,
+                    MouseEventKind::ScrollUp => -3,
+                    _ => return false,
+                };
+                let max_scroll = self.tab.visible_rows_map.len().saturating_sub(1);
+                self.tab.editor_scroll_row = self
+                    .tab
+                    .editor_scroll_row
+                    .saturating_add_signed(step)
+                    .min(max_scroll);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if let Some(action) = self.keybinds.lookup(&key, KeyScope::Editor)
+            && let Some(changed) = self.run_key_action(action)
+        {
+            return changed;
+        }
+        let changed = self.tab.editor.input(Input::from(key));
+        if changed {
+            self.on_content_changed();
+        }
+        self.scroll_cursor_into_view();
+        changed
+    }
+
+    /// Run an editor keybinding, or `None` for actions that need the full app
+    /// (LSP, find, line editing commands) so the key falls through to the buffer.
+    fn run_key_action(&mut self, action: KeyAction) -> Option<bool> {
+        let row = self.tab.editor.cursor().0;
+        let changed = match action {
+            KeyAction::FoldToggle => {
+                if !self.tab.unfold_block_at(row) {
+                    self.tab.fold_block_at(row);
+                }
+                false
+            }
+            KeyAction::FoldAllToggle => {
+                if self.tab.folded_starts.is_empty() {
+                    self.tab.fold_all();
+                } else {
+                    self.tab.folded_starts.clear();
+                }
+                false
+            }
+            KeyAction::Fold => {
+                self.tab.fold_block_at(row);
+                false
+            }
+            KeyAction::Unfold => {
+                self.tab.unfold_block_at(row);
+                false
+            }
+            KeyAction::FoldAll => {
+                self.tab.fold_all();
+                false
+            }
+            KeyAction::UnfoldAll => {
+                self.tab.folded_starts.clear();
+                false
+            }
+            KeyAction::Undo => self.tab.editor.undo(),
+            KeyAction::Redo => self.tab.editor.redo(),
+            KeyAction::SelectAll => {
+                self.tab.editor.select_all();
+                false
+            }
+            KeyAction::Copy => {
+                self.tab.editor.copy();
+                false
+            }
+            KeyAction::Cut => self.tab.editor.cut(),
+            KeyAction::Paste => self.tab.editor.paste(),
+            KeyAction::PageDown | KeyAction::PageUp => {
+                let page = self.last_area.height.max(1);
+                let (row, col) = self.tab.editor.cursor();
+                let target = if action == KeyAction::PageDown {
+                    row.saturating_add(page as usize)
+                } else {
+                    row.saturating_sub(page as usize)
+                };
+                let target = target.min(self.tab.editor.lines().len().saturating_sub(1));
+                self.tab.editor.move_cursor(CursorMove::Jump(
+                    to_u16_saturating(target),
+                    to_u16_saturating(col),
+                ));
+                false
+            }
+            KeyAction::JumpToBracket => {
+                if let Some((_, target)) = self.tab.bracket_match_at_cursor() {
+                    self.tab.jump_to(target);
+                }
+                false
+            }
+            KeyAction::GoToStart => {
+                self.tab.editor.move_cursor(CursorMove::Jump(0, 0));
+                false
+            }
+            KeyAction::GoToEnd => {
+                self.tab.editor.move_cursor(CursorMove::Bottom);
+                self.tab.editor.move_cursor(CursorMove::End);
+                false
+            }
+            _ => return None,
+        };
+        if changed {
+            self.on_content_changed();
+        } else {
+            self.rebuild_visible_rows();
+        }
+        self.scroll_cursor_into_view();
+        Some(changed)
+    }
+
+    fn on_content_changed(&mut self) {
+        self.tab.dirty = true;
+        self.tab.recompute_folds();
+        self.rebuild_visible_rows();
+    }
+
+    fn apply_theme_styles(&mut self) {
+        let theme = &self.themes[self.theme_index];
+        let cursor_line = Style::default().bg(theme.bg_alt);
+        let selection = Style::default().bg(theme.selection);
+        self.tab.editor.set_cursor_line_style(cursor_line);
+        self.tab.editor.set_selection_style(selection);
+    }
+
+    fn content_width(&self) -> usize {
+        self.last_area.width.saturating_sub(Gutter::ALL.width()) as usize
+    }
+
+    fn rebuild_visible_rows(&mut self) {
+        let wrap_width = match self.content_width() {
+            0 => usize::MAX,
+            width => width,
+        };
+        self.tab.rebuild_visible_rows(self.word_wrap, wrap_width);
+    }
+
+    fn scroll_cursor_into_view(&mut self) {
+        let height = self.last_area.height as usize;
+        if height == 0 {
+            return;
+        }
+        let (row, col) = self.tab.editor.cursor();
+        let visible = self.tab.visible_index_of_source_position(row, col);
+        if visible < self.tab.editor_scroll_row {
+            self.tab.editor_scroll_row = visible;
+        } else if visible >= self.tab.editor_scroll_row + height {
+            self.tab.editor_scroll_row = visible + 1 - height;
+        }
+        let width = self.content_width();
+        if self.word_wrap || width == 0 {
+            return;
+        }
+        let display_col = self.tab.editor.lines().get(row).map_or(0, |l| {
+            display_width_of_range(l, 0, col, self.tab.tab_width())
+        });
+        if display_col < self.tab.editor_scroll_col {
+            self.tab.editor_scroll_col = display_col;
+        } else if display_col >= self.tab.editor_scroll_col + width {
+            self.tab.editor_scroll_col = display_col + 1 - width;
+        }
+    }
+}
+
+impl Widget for &mut EditorView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width != self.last_area.width || area.height != self.last_area.height {
+            self.last_area = area;
+            self.rebuild_visible_rows();
+            self.scroll_cursor_into_view();
+        }
+        self.last_area = area;
+        let theme = &self.themes[self.theme_index];
+        let lines = editor_lines(&self.tab, theme, self.word_wrap, Gutter::ALL, area);
+        Paragraph::new(lines)
+            .style(Style::default().bg(theme.bg).fg(theme.fg))
+            .render(area, buf);
+        if self.focused
+            && let Some(pos) = editor_cursor_position(&self.tab, self.word_wrap, Gutter::ALL, area)
+            && let Some(cell) = buf.cell_mut(pos)
+        {
+            cell.modifier.insert(Modifier::REVERSED);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsp_client::LspDiagnostic;
+    use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+
+    fn row_text(buf: &Buffer, y: u16) -> String {
+        (0..buf.area.width)
+            .map(|x| buf[(x, y)].symbol())
+            .collect::<String>()
+    }
+
+    #[test]
+    fn editor_view_renders_edits_and_folds() {
+        let mut view = EditorView::new("fn main() {\n    body();\n}");
+        let area = Rect::new(0, 0, 40, 4);
+        let mut buf = Buffer::empty(area);
+        (&mut view).render(area, &mut buf);
+        assert!(row_text(&buf, 0).contains("1 "));
+        assert!(row_text(&buf, 1).contains("body();"));
+
+        let typed = view.handle_event(&Event::Key(KeyEvent::new(
+            KeyCode::Char('x'),
+            KeyModifiers::NONE,
+        )));
+        assert!(typed);
+        assert!(view.is_modified());
+        assert_eq!(view.lines()[0], "xfn main() {");
+
+        view.tab.fold_block_at(0);
+        view.tab.diagnostics.push(LspDiagnostic {
+            line: 2,
+            severity: "error".to_string(),
+            message: "cannot find function".to_string(),
+            range: None,
+        });
+        view.rebuild_visible_rows();
+        let area = Rect::new(0, 0, 60, 4);
+        let mut buf = Buffer::empty(area);
+        (&mut view).render(area, &mut buf);
+        assert!(row_text(&buf, 0).contains("▸ "));
+        assert!(row_text(&buf, 0).contains("body();  … 1 line, 1 error"));
+        assert!(!row_text(&buf, 1).contains("body();"));
+        assert_eq!(view.text(), "xfn main() {\n    body();\n}");
+    }
+}
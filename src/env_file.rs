@@ -0,0 +1,78 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Variables from a dotenv file: `KEY=value` lines, optionally prefixed
+/// with `export`. Blank lines and `#` comments are skipped, values may be
+/// quoted, and an unquoted value ends at ` #`.
+pub(crate) fn parse_env_file(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let key = key.trim();
+            let valid = !key.is_empty()
+                && !key.starts_with('#')
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return None;
+            }
+            let value = value.trim();
+            let quoted = ['"', '\'']
+                .into_iter()
+                .find(|&q| value.len() >= 2 && value.starts_with(q) && value.ends_with(q));
+            let value = match quoted {
+                Some('"') => value[1..value.len() - 1].replace("\\n", "\n"),
+                Some(_) => value[1..value.len() - 1].to_string(),
+                None => match value.find(" #") {
+                    Some(idx) => value[..idx].trim_end().to_string(),
+                    None => value.to_string(),
+                },
+            };
+            Some((key.to_string(), value))
+        })
+        .collect()
+}
+
+/// A project env file and the variables it sets.
+pub(crate) struct ProjectEnv {
+    pub(crate) path: PathBuf,
+    pub(crate) vars: Vec<(String, String)>,
+}
+
+/// Read the env file `name` in `root`, if there is one.
+pub(crate) fn load_project_env(root: &Path, name: &str) -> io::Result<Option<ProjectEnv>> {
+    let path = root.join(name);
+    match fs::read_to_string(&path) {
+        Ok(text) => Ok(Some(ProjectEnv {
+            vars: parse_env_file(&text),
+            path,
+        })),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dotenv_lines() {
+        let vars = parse_env_file(
+            "# db\nexport DATABASE_URL=\"postgres://localhost/app\"\n\nMODE=dev # local\n\
+             GREETING='hi #1'\nMULTI=\"a\\nb\"\nnot a pair\n",
+        );
+        let pairs: Vec<(&str, &str)> = vars.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        assert_eq!(
+            pairs,
+            [
+                ("DATABASE_URL", "postgres://localhost/app"),
+                ("MODE", "dev"),
+                ("GREETING", "hi #1"),
+                ("MULTI", "a\nb"),
+            ]
+        );
+    }
+}
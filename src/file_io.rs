@@ -0,0 +1,134 @@
+//! Reading and writing open files on a worker thread, for projects on
+//! network mounts where a single read or write can stall the draw loop.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::SystemTime;
+
+use crate::util::{encode_text, read_line_chunk, utf16_bom};
+
+/// A file as read for opening.
+#[derive(Debug)]
+pub(crate) struct FileRead {
+    pub(crate) size: u64,
+    pub(crate) modified: Option<SystemTime>,
+    pub(crate) bytes: Vec<u8>,
+    /// Only the first chunk of a file over the large file threshold.
+    pub(crate) large: bool,
+}
+
+/// What a background read or write reports when done.
+#[derive(Debug)]
+pub(crate) enum FileIoEvent {
+    Read {
+        path: PathBuf,
+        result: io::Result<FileRead>,
+    },
+    Written {
+        path: PathBuf,
+        content: String,
+        result: io::Result<()>,
+    },
+}
+
+/// Read `path` for opening: the first `chunk` bytes' worth of lines when
+/// it is over `threshold` bytes, else all of it. UTF-16 files are still
+/// read whole, since chunks are split on newline bytes.
+pub(crate) fn read_for_open(path: &Path, threshold: u64, chunk: usize) -> io::Result<FileRead> {
+    let meta = fs::metadata(path)?;
+    let (size, modified) = (meta.len(), meta.modified().ok());
+    if size > threshold {
+        let bytes = read_line_chunk(path, 0, chunk)?;
+        if utf16_bom(&bytes).is_none() {
+            return Ok(FileRead {
+                size,
+                modified,
+                bytes,
+                large: true,
+            });
+        }
+    }
+    Ok(FileRead {
+        size,
+        modified,
+        bytes: fs::read(path)?,
+        large: false,
+    })
+}
+
+/// Run `read_for_open` on a worker thread and send the result to `tx`.
+pub(crate) fn spawn_read(path: PathBuf, threshold: u64, chunk: usize, tx: Sender<FileIoEvent>) {
+    thread::spawn(move || {
+        let result = read_for_open(&path, threshold, chunk);
+        let _ = tx.send(FileIoEvent::Read { path, result });
+    });
+}
+
+/// Write `content` to `path`, as UTF-16 when `utf16` is set (see
+/// `encode_text`), on a worker thread and send the result to `tx`.
+pub(crate) fn spawn_write(
+    path: PathBuf,
+    content: String,
+    utf16: Option<bool>,
+    tx: Sender<FileIoEvent>,
+) {
+    thread::spawn(move || {
+        let result = fs::write(&path, encode_text(&content, utf16));
+        let _ = tx.send(FileIoEvent::Written {
+            path,
+            content,
+            result,
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn large_files_are_read_a_chunk_at_a_time_unless_utf16() {
+        let tmp = tempdir().expect("tempdir");
+        let big = tmp.path().join("big.log");
+        fs::write(&big, "line\n".repeat(100)).expect("write");
+        let read = read_for_open(&big, 100, 20).expect("read");
+        assert!(read.large);
+        assert_eq!((read.size, read.bytes.len()), (500, 20));
+
+        // A line longer than the chunk is cut, between chars.
+        let long = tmp.path().join("long.txt");
+        fs::write(&long, "x".repeat(300)).expect("write");
+        assert_eq!(read_line_chunk(&long, 0, 20).expect("read").len(), 20);
+        fs::write(&long, format!("a\n{}", "é".repeat(100))).expect("write");
+        assert_eq!(read_line_chunk(&long, 0, 20).expect("read"), b"a\n");
+        assert_eq!(read_line_chunk(&long, 2, 19).expect("read").len(), 18);
+
+        let wide = tmp.path().join("wide.txt");
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend("a\n".repeat(100).encode_utf16().flat_map(u16::to_le_bytes));
+        fs::write(&wide, &bytes).expect("write");
+        let read = read_for_open(&wide, 100, 20).expect("read");
+        assert!(!read.large);
+        assert_eq!(read.bytes, bytes);
+
+        let (tx, rx) = mpsc::channel();
+        spawn_write(big.clone(), "short\n".to_string(), None, tx.clone());
+        let Ok(FileIoEvent::Written { result, .. }) = rx.recv_timeout(Duration::from_secs(5))
+        else {
+            panic!("expected the write to finish");
+        };
+        assert!(result.is_ok());
+        spawn_read(big, 100, 20, tx);
+        let Ok(FileIoEvent::Read { result, .. }) = rx.recv_timeout(Duration::from_secs(5)) else {
+            panic!("expected the read to finish");
+        };
+        let read = result.expect("read");
+        assert_eq!((read.bytes, read.large), (b"short\n".to_vec(), false));
+    }
+}
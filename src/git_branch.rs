@@ -0,0 +1,85 @@
+//! Local branches, and checking one out or creating one on a worker thread.
+
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Local branch names, most recently committed to first.
+pub(crate) fn local_branches(root: &Path) -> io::Result<Vec<String>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args([
+            "for-each-ref",
+            "--sort=-committerdate",
+            "--format=%(refname:short)",
+            "refs/heads",
+        ])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("not a git repository"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// What a branch switch does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BranchSwitch {
+    Checkout(String),
+    Create(String),
+}
+
+impl BranchSwitch {
+    pub(crate) fn branch(&self) -> &str {
+        match self {
+            Self::Checkout(name) | Self::Create(name) => name,
+        }
+    }
+
+    fn git_args(&self) -> Vec<&str> {
+        match self {
+            Self::Checkout(name) => vec!["checkout", name.as_str()],
+            Self::Create(name) => vec!["checkout", "-b", name.as_str()],
+        }
+    }
+}
+
+/// Run the switch on a background thread. A failure comes back with git's
+/// first error line, e.g. why a checkout would overwrite local changes.
+pub(crate) fn spawn_branch_switch(root: &Path, switch: &BranchSwitch) -> Receiver<io::Result<()>> {
+    let (tx, rx) = mpsc::channel();
+    let root = root.to_path_buf();
+    let args: Vec<String> = switch.git_args().into_iter().map(str::to_string).collect();
+    thread::spawn(move || {
+        let result = Command::new("git")
+            .arg("-C")
+            .arg(&root)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .and_then(|output| {
+                if output.status.success() {
+                    return Ok(());
+                }
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let reason = stderr
+                    .lines()
+                    .find(|l| l.starts_with("error") || l.starts_with("fatal"))
+                    .or_else(|| stderr.lines().next())
+                    .unwrap_or("git checkout failed");
+                Err(io::Error::other(reason.to_string()))
+            });
+        let _ = tx.send(result);
+    });
+    rx
+}
@@ -0,0 +1,160 @@
+//! `git log` for the project or one file, a commit's details, and a file's
+//! text at a commit.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::util::decode_text;
+
+/// Commits listed at most; older history is left out.
+const LOG_LIMIT: usize = 500;
+
+/// One commit of the log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GitCommit {
+    pub(crate) hash: String,
+    pub(crate) author: String,
+    /// Author date, `YYYY-MM-DD`.
+    pub(crate) date: String,
+    pub(crate) subject: String,
+    /// In a file's history, the file's path in this commit relative to the
+    /// repository, which differs from today's before a rename.
+    pub(crate) path: Option<String>,
+}
+
+impl GitCommit {
+    pub(crate) fn short_hash(&self) -> &str {
+        &self.hash[..self.hash.len().min(7)]
+    }
+}
+
+/// Parse `git log --format=%x1e%H%x1f%an%x1f%ad%x1f%s`, with the file names
+/// of `--name-only` following each header.
+pub(crate) fn parse_git_log(raw: &str) -> Vec<GitCommit> {
+    raw.split('\x1e')
+        .filter_map(|record| {
+            let mut lines = record.lines();
+            let mut fields = lines.next()?.split('\x1f');
+            let hash = fields.next()?.to_string();
+            let author = fields.next()?.to_string();
+            let date = fields.next()?.to_string();
+            let subject = fields.next().unwrap_or_default().to_string();
+            let path = lines.find(|l| !l.is_empty()).map(str::to_string);
+            Some(GitCommit {
+                hash,
+                author,
+                date,
+                subject,
+                path,
+            })
+        })
+        .collect()
+}
+
+/// The project's log, or `file`'s following renames, newest first.
+pub(crate) fn git_log(root: &Path, file: Option<&Path>) -> io::Result<Vec<GitCommit>> {
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(root)
+        .args(["log", "--date=short", "--format=%x1e%H%x1f%an%x1f%ad%x1f%s"])
+        .arg(format!("--max-count={LOG_LIMIT}"));
+    if let Some(file) = file {
+        command.args(["--follow", "--name-only", "--"]).arg(file);
+    }
+    let output = command
+        .stdin(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().next().unwrap_or("git log failed");
+        return Err(io::Error::other(reason.to_string()));
+    }
+    Ok(parse_git_log(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// `git show` for a commit: the full message and a diffstat, plus the
+/// file's patch in a file's history.
+pub(crate) fn git_commit_details(root: &Path, commit: &GitCommit) -> Vec<String> {
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(root)
+        .args(["show", "--format=fuller", "--stat", "--no-color"]);
+    match commit.path.as_deref() {
+        Some(path) => command.args(["--patch", &commit.hash, "--", &format!(":/{path}")]),
+        None => command.arg(&commit.hash),
+    };
+    match command.stdin(Stdio::null()).stderr(Stdio::null()).output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect(),
+        _ => vec![format!("Could not show {}", commit.short_hash())],
+    }
+}
+
+/// `path`'s text in `commit`: the path recorded in a file's history, or
+/// `file` (relative to `root`) otherwise. `None` when it did not exist.
+pub(crate) fn git_file_at(root: &Path, commit: &GitCommit, file: &Path) -> Option<String> {
+    let spec = match commit.path.as_deref() {
+        Some(path) => format!("{}:{path}", commit.hash),
+        None => format!("{}:./{}", commit.hash, file.display()),
+    };
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["show", &spec])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then(|| decode_text(&output.stdout))
+}
+
+/// Where a revision of `file`, relative to the project, is written to open
+/// it in a tab: a scratch file named after the commit, keeping the
+/// extension for highlighting.
+pub(crate) fn revision_path(file: &Path, commit: &GitCommit) -> PathBuf {
+    let stem = file
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = file
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let parent = file.parent().unwrap_or(Path::new(""));
+    std::env::temp_dir()
+        .join("lazyide-revisions")
+        .join(commit.short_hash())
+        .join(parent)
+        .join(format!("{stem}@{}{ext}", commit.short_hash()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_log_records_with_and_without_file_names() {
+        let raw = "\x1eaaaaaaaaaa\x1fAda\x1f2024-05-01\x1fFix parser\n\nsrc/parse.rs\n\
+                   \x1ebbbbbbbbbb\x1fBob\x1f2024-04-30\x1fAdd parser\n\nparse.rs\n";
+        let commits = parse_git_log(raw);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].short_hash(), "aaaaaaa");
+        assert_eq!(commits[0].subject, "Fix parser");
+        assert_eq!(commits[0].path.as_deref(), Some("src/parse.rs"));
+        assert_eq!(commits[1].author, "Bob");
+        assert_eq!(commits[1].path.as_deref(), Some("parse.rs"));
+
+        let project = parse_git_log("\x1ecccccccc\x1fCy\x1f2024-01-02\x1fInitial\n");
+        assert_eq!(project[0].path, None);
+        assert_eq!(project[0].date, "2024-01-02");
+
+        let path = revision_path(Path::new("src/parse.rs"), &commits[0]);
+        assert!(path.ends_with("aaaaaaa/src/parse@aaaaaaa.rs"));
+    }
+}
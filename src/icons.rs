@@ -0,0 +1,127 @@
+use ratatui::style::Color;
+
+/// Glyph and color for a tree entry. `color` is `None` for entries that
+/// should keep the row's own style (directories, unknown file types).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FileIcon {
+    pub(crate) glyph: &'static str,
+    pub(crate) color: Option<Color>,
+}
+
+const DIR_CLOSED: &str = "\u{f07b}";
+const DIR_OPEN: &str = "\u{f07c}";
+const FILE_DEFAULT: &str = "\u{f15b}";
+const ASCII_FILE: &str = "·";
+
+const fn icon(glyph: &'static str, r: u8, g: u8, b: u8) -> FileIcon {
+    FileIcon {
+        glyph,
+        color: Some(Color::Rgb(r, g, b)),
+    }
+}
+
+/// Whole-name matches, checked before extensions.
+fn icon_for_name(name: &str) -> Option<FileIcon> {
+    let icon = match name {
+        "Cargo.toml" => icon("\u{e7a8}", 0xde, 0xa5, 0x84),
+        "Cargo.lock" | "package-lock.json" | "yarn.lock" | "pnpm-lock.yaml" | "poetry.lock" => {
+            icon("\u{f023}", 0xbb, 0xbb, 0xbb)
+        }
+        ".gitignore" | ".gitattributes" | ".gitmodules" => icon("\u{e702}", 0xf1, 0x4c, 0x28),
+        "Dockerfile" | "docker-compose.yml" | "docker-compose.yaml" => {
+            icon("\u{f308}", 0x45, 0x8e, 0xe6)
+        }
+        "Makefile" | "makefile" | "justfile" => icon("\u{e779}", 0x6d, 0x80, 0x86),
+        "LICENSE" | "LICENSE-MIT" | "LICENSE-APACHE" => icon("\u{f0e3}", 0xd0, 0xbf, 0x41),
+        _ => return None,
+    };
+    Some(icon)
+}
+
+fn icon_for_extension(ext: &str) -> Option<FileIcon> {
+    let icon = match ext {
+        "rs" => icon("\u{e7a8}", 0xde, 0xa5, 0x84),
+        "py" | "pyi" => icon("\u{e606}", 0x37, 0x76, 0xab),
+        "js" | "mjs" | "cjs" => icon("\u{e74e}", 0xf1, 0xe0, 0x5a),
+        "ts" | "mts" | "cts" => icon("\u{e628}", 0x31, 0x78, 0xc6),
+        "jsx" | "tsx" => icon("\u{e7ba}", 0x61, 0xda, 0xfb),
+        "go" => icon("\u{e627}", 0x00, 0xad, 0xd8),
+        "php" => icon("\u{e73d}", 0x77, 0x7b, 0xb4),
+        "c" | "h" => icon("\u{e61e}", 0x59, 0x9e, 0xff),
+        "cpp" | "cc" | "cxx" | "hpp" => icon("\u{e61d}", 0xf3, 0x4b, 0x7d),
+        "java" => icon("\u{e738}", 0xb0, 0x72, 0x19),
+        "html" | "htm" => icon("\u{e736}", 0xe3, 0x4c, 0x26),
+        "css" => icon("\u{e749}", 0x56, 0x3d, 0x7c),
+        "scss" | "sass" => icon("\u{e603}", 0xc6, 0x53, 0x8c),
+        "xml" => icon("\u{f05c0}", 0xe3, 0x7e, 0x33),
+        "json" | "jsonc" => icon("\u{e60b}", 0xcb, 0xcb, 0x41),
+        "toml" | "yaml" | "yml" | "ini" | "cfg" | "conf" => icon("\u{e615}", 0x6d, 0x80, 0x86),
+        "md" | "markdown" => icon("\u{e609}", 0x51, 0x9a, 0xba),
+        "txt" => icon("\u{f15c}", 0x89, 0xe0, 0x51),
+        "lock" => icon("\u{f023}", 0xbb, 0xbb, 0xbb),
+        "sh" | "bash" | "zsh" | "fish" => icon("\u{f489}", 0x89, 0xe0, 0x51),
+        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "ico" | "webp" | "svg" => {
+            icon("\u{f1c5}", 0xa0, 0x74, 0xc4)
+        }
+        "zip" | "gz" | "tgz" | "xz" | "zst" | "7z" | "tar" => icon("\u{f1c6}", 0xec, 0xa5, 0x17),
+        "pdf" => icon("\u{f1c1}", 0xb3, 0x0b, 0x00),
+        _ => return None,
+    };
+    Some(icon)
+}
+
+/// Icon for a tree entry. With `nerd_font` off, directories and files fall
+/// back to the plain arrow and dot markers, still colored by file type.
+pub(crate) fn file_icon(name: &str, is_dir: bool, expanded: bool, nerd_font: bool) -> FileIcon {
+    if is_dir {
+        let glyph = match (nerd_font, expanded) {
+            (true, true) => DIR_OPEN,
+            (true, false) => DIR_CLOSED,
+            (false, _) => "",
+        };
+        return FileIcon { glyph, color: None };
+    }
+    let ext = name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    let typed = icon_for_name(name).or_else(|| icon_for_extension(&ext));
+    let glyph = match (nerd_font, typed) {
+        (false, _) => ASCII_FILE,
+        (true, Some(icon)) => icon.glyph,
+        (true, None) => FILE_DEFAULT,
+    };
+    FileIcon {
+        glyph,
+        color: typed.and_then(|icon| icon.color),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_types_get_distinct_icons() {
+        let rs = file_icon("main.rs", false, false, true);
+        let py = file_icon("app.PY", false, false, true);
+        assert_ne!(rs.glyph, py.glyph);
+        assert!(rs.color.is_some());
+        assert_eq!(
+            file_icon("Cargo.lock", false, false, true).glyph,
+            "\u{f023}"
+        );
+        let unknown = file_icon("notes.xyz", false, false, true);
+        assert_eq!(unknown.glyph, FILE_DEFAULT);
+        assert_eq!(unknown.color, None);
+    }
+
+    #[test]
+    fn ascii_mode_keeps_type_colors() {
+        let rs = file_icon("main.rs", false, false, false);
+        assert_eq!(rs.glyph, ASCII_FILE);
+        assert!(rs.color.is_some());
+        assert_eq!(file_icon("src", true, true, false).glyph, "");
+        assert_eq!(file_icon("src", true, true, true).glyph, DIR_OPEN);
+    }
+}
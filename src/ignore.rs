@@ -0,0 +1,322 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Ignore files read in every directory, lowest precedence first.
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Directory holding the ignore file; patterns are relative to it.
+    base: PathBuf,
+    pattern: Vec<char>,
+    negate: bool,
+    dir_only: bool,
+    /// Pattern contained a `/`, so it matches the path from `base` rather
+    /// than just the final name.
+    anchored: bool,
+}
+
+/// Parsed `.gitignore` / `.ignore` rules for a project, including nested
+/// files in subdirectories and `.git/info/exclude`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IgnoreRules {
+    root: PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    pub(crate) fn load(root: &Path) -> Self {
+        let mut rules = Self {
+            root: root.to_path_buf(),
+            rules: Vec::new(),
+        };
+        rules.add_file(root, &root.join(".git").join("info").join("exclude"));
+        rules.load_dir(root);
+        rules
+    }
+
+    /// Read the ignore files in `dir`, then recurse into subdirectories that
+    /// are not themselves ignored. Parents load first so deeper files win.
+    fn load_dir(&mut self, dir: &Path) {
+        for name in IGNORE_FILES {
+            self.add_file(dir, &dir.join(name));
+        }
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut subdirs: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_ok_and(|ft| ft.is_dir()))
+            .map(|e| e.path())
+            .filter(|p| !self.is_ignored(p, true))
+            .collect();
+        subdirs.sort();
+        for sub in subdirs {
+            self.load_dir(&sub);
+        }
+    }
+
+    fn add_file(&mut self, base: &Path, file: &Path) {
+        let Ok(text) = fs::read_to_string(file) else {
+            return;
+        };
+        for line in text.lines() {
+            if let Some(rule) = parse_rule(base, line) {
+                self.rules.push(rule);
+            }
+        }
+    }
+
+    /// Whether `path` is ignored, either directly or because one of its
+    /// parent directories is. `.git` is always ignored.
+    pub(crate) fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(rel) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let components: Vec<_> = rel.components().collect();
+        let mut current = self.root.clone();
+        for (idx, component) in components.iter().enumerate() {
+            current.push(component);
+            let last = idx + 1 == components.len();
+            if component.as_os_str() == ".git" && (!last || is_dir) {
+                return true;
+            }
+            if self.matches(&current, !last || is_dir) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Last matching rule decides, as in git.
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let Ok(rel) = path.strip_prefix(&rule.base) else {
+                continue;
+            };
+            let text: Vec<char> = if rule.anchored {
+                rel.to_string_lossy().replace('\\', "/").chars().collect()
+            } else {
+                match rel.file_name() {
+                    Some(name) => name.to_string_lossy().chars().collect(),
+                    None => continue,
+                }
+            };
+            if glob_match(&rule.pattern, &text) {
+                ignored = !rule.negate;
+            }
+        }
+        ignored
+    }
+}
+
+fn parse_rule(base: &Path, line: &str) -> Option<Rule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negate, line) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let (dir_only, line) = match line.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let anchored = line.contains('/');
+    let line = line.strip_prefix('/').unwrap_or(line);
+    if line.is_empty() {
+        return None;
+    }
+    Some(Rule {
+        base: base.to_path_buf(),
+        pattern: line.chars().collect(),
+        negate,
+        dir_only,
+        anchored,
+    })
+}
+
+/// Whether the file at `rel` passes ripgrep-style `-g` globs: the last
+/// matching glob decides, and when any include glob is given, files
+/// matching none of them are left out. An exclude (`!`) glob without a
+/// `/` also drops every file under a directory of that name.
+pub(crate) fn globs_allow(globs: &[String], rel: &Path) -> bool {
+    let path: Vec<char> = rel.to_string_lossy().replace('\\', "/").chars().collect();
+    let names: Vec<Vec<char>> = rel
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().chars().collect())
+        .collect();
+    let mut allowed = !globs.iter().any(|g| !g.starts_with('!'));
+    for glob in globs {
+        let (include, glob) = match glob.strip_prefix('!') {
+            Some(rest) => (false, rest),
+            None => (true, glob.as_str()),
+        };
+        let pattern: Vec<char> = glob.trim_start_matches('/').chars().collect();
+        let matched = if glob.contains('/') {
+            glob_match(&pattern, &path)
+        } else if include {
+            names.last().is_some_and(|name| glob_match(&pattern, name))
+        } else {
+            names.iter().any(|name| glob_match(&pattern, name))
+        };
+        if matched {
+            allowed = include;
+        }
+    }
+    allowed
+}
+
+/// Gitignore-style glob: `*` and `?` stay within one path segment, `**`
+/// spans segments, and `[...]` matches a character class.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            // `**/` may also match zero directories.
+            if rest.first() == Some(&'/') && glob_match(&rest[1..], text) {
+                return true;
+            }
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => match text.first() {
+            Some(&c) if c != '/' => glob_match(&pattern[1..], &text[1..]),
+            _ => false,
+        },
+        Some('[') => {
+            let Some(&c) = text.first() else {
+                return false;
+            };
+            match match_class(&pattern[1..], c) {
+                Some((true, consumed)) => glob_match(&pattern[1 + consumed..], &text[1..]),
+                Some((false, _)) => false,
+                // Unterminated class: treat `[` literally.
+                None => c == '[' && glob_match(&pattern[1..], &text[1..]),
+            }
+        }
+        Some('\\') if pattern.len() > 1 => {
+            text.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &text[1..])
+        }
+        Some(&p) => text.first() == Some(&p) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Match `c` against the class body after `[`. Returns whether it matched
+/// and how many pattern chars the class used, including the closing `]`.
+fn match_class(body: &[char], c: char) -> Option<(bool, usize)> {
+    let mut idx = 0;
+    let negate = matches!(body.first(), Some('!' | '^'));
+    if negate {
+        idx += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while idx < body.len() {
+        let ch = body[idx];
+        if ch == ']' && !first {
+            return Some((matched != negate, idx + 1));
+        }
+        first = false;
+        if body.get(idx + 1) == Some(&'-') && body.get(idx + 2).is_some_and(|&e| e != ']') {
+            if (ch..=body[idx + 2]).contains(&c) {
+                matched = true;
+            }
+            idx += 3;
+        } else {
+            if ch == c {
+                matched = true;
+            }
+            idx += 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn glob(p: &str, t: &str) -> bool {
+        let p: Vec<char> = p.chars().collect();
+        let t: Vec<char> = t.chars().collect();
+        glob_match(&p, &t)
+    }
+
+    #[test]
+    fn glob_star_stays_in_segment() {
+        assert!(glob("*.log", "debug.log"));
+        assert!(!glob("*.log", "logs/debug.log"));
+        assert!(glob("src/*.rs", "src/main.rs"));
+        assert!(!glob("src/*.rs", "src/app/core.rs"));
+    }
+
+    #[test]
+    fn glob_double_star_and_classes() {
+        assert!(glob("**/build", "build"));
+        assert!(glob("**/build", "a/b/build"));
+        assert!(glob("docs/**", "docs/a/b.md"));
+        assert!(glob("a/**/z", "a/z"));
+        assert!(glob("a/**/z", "a/b/c/z"));
+        assert!(glob("file[0-9].txt", "file7.txt"));
+        assert!(!glob("file[!0-9].txt", "file7.txt"));
+        assert!(glob("?.rs", "a.rs"));
+    }
+
+    #[test]
+    fn rules_respect_dir_only_negation_and_nesting() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("target/debug")).expect("target");
+        fs::create_dir_all(root.join("sub/gen")).expect("sub");
+        fs::write(
+            root.join(".gitignore"),
+            "# build output\ntarget/\n*.log\n!keep.log\n/only_root.txt\n",
+        )
+        .expect("gitignore");
+        fs::write(root.join("sub/.ignore"), "gen\n").expect("nested ignore");
+        let rules = IgnoreRules::load(root);
+
+        assert!(rules.is_ignored(&root.join("target"), true));
+        assert!(rules.is_ignored(&root.join("target/debug/app"), false));
+        assert!(rules.is_ignored(&root.join("sub/trace.log"), false));
+        assert!(!rules.is_ignored(&root.join("keep.log"), false));
+        assert!(rules.is_ignored(&root.join("only_root.txt"), false));
+        assert!(!rules.is_ignored(&root.join("sub/only_root.txt"), false));
+        assert!(rules.is_ignored(&root.join("sub/gen"), true));
+        assert!(!rules.is_ignored(&root.join("gen"), true));
+        assert!(rules.is_ignored(&root.join(".git/config"), false));
+        assert!(!rules.is_ignored(&root.join("src/main.rs"), false));
+    }
+
+    #[test]
+    fn search_globs_include_and_exclude() {
+        let globs = |g: &[&str]| g.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let rs = globs(&["*.rs", "!target"]);
+        assert!(globs_allow(&rs, Path::new("src/main.rs")));
+        assert!(!globs_allow(&rs, Path::new("README.md")));
+        assert!(!globs_allow(&rs, Path::new("target/debug/out.rs")));
+        let exclude = globs(&["!docs/**"]);
+        assert!(globs_allow(&exclude, Path::new("src/lib.rs")));
+        assert!(!globs_allow(&exclude, Path::new("docs/a.md")));
+        assert!(globs_allow(&[], Path::new("any.txt")));
+    }
+}
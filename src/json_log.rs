@@ -0,0 +1,252 @@
+use serde_json::Value;
+
+/// Keys read as a record's timestamp, level and message, in order of
+/// preference.
+const TIME_KEYS: &[&str] = &["time", "timestamp", "ts", "@timestamp", "datetime", "date"];
+const LEVEL_KEYS: &[&str] = &[
+    "level",
+    "lvl",
+    "severity",
+    "loglevel",
+    "log.level",
+    "@level",
+];
+const MESSAGE_KEYS: &[&str] = &["msg", "message", "@message", "event"];
+
+/// Lines checked when deciding whether a file is a JSON-lines log.
+const DETECT_LINES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub(crate) const ALL: [LogLevel; 5] = [
+        LogLevel::Trace,
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warn,
+        LogLevel::Error,
+    ];
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+
+    /// Level named by a log field: a name such as `warning` or `fatal`, or a
+    /// bunyan/pino number (10 trace up to 50 error, 60 fatal).
+    fn from_value(value: &Value) -> Option<LogLevel> {
+        if let Some(n) = value.as_u64() {
+            return Some(match n {
+                0..=19 => LogLevel::Trace,
+                20..=29 => LogLevel::Debug,
+                30..=39 => LogLevel::Info,
+                40..=49 => LogLevel::Warn,
+                _ => LogLevel::Error,
+            });
+        }
+        let name = value.as_str()?.to_ascii_lowercase();
+        Some(match name.as_str() {
+            "trace" | "verbose" => LogLevel::Trace,
+            "debug" | "dbg" => LogLevel::Debug,
+            "info" | "information" | "notice" => LogLevel::Info,
+            "warn" | "warning" => LogLevel::Warn,
+            "error" | "err" | "fatal" | "critical" | "crit" | "panic" | "alert" | "emerg" => {
+                LogLevel::Error
+            }
+            _ => return None,
+        })
+    }
+}
+
+/// One line of a JSON-lines log. Lines that are not JSON objects keep their
+/// text as the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LogRecord {
+    /// Every top-level field as `(key, value)`; strings are unquoted.
+    pub(crate) fields: Vec<(String, String)>,
+    /// Indices into `fields` of the timestamp and message.
+    pub(crate) time: Option<usize>,
+    pub(crate) message: Option<usize>,
+    pub(crate) level: Option<LogLevel>,
+    pub(crate) level_field: Option<usize>,
+    pub(crate) raw: String,
+}
+
+impl LogRecord {
+    pub(crate) fn time_text(&self) -> &str {
+        self.time.map_or("", |i| self.fields[i].1.as_str())
+    }
+
+    pub(crate) fn message_text(&self) -> &str {
+        match self.message {
+            Some(i) => &self.fields[i].1,
+            None if self.fields.is_empty() => &self.raw,
+            None => "",
+        }
+    }
+
+    /// Fields other than the timestamp, level and message.
+    pub(crate) fn extra_fields(&self) -> impl Iterator<Item = &(String, String)> {
+        self.fields
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| ![self.time, self.message, self.level_field].contains(&Some(*i)))
+            .map(|(_, f)| f)
+    }
+}
+
+fn find_key(fields: &[(String, String)], keys: &[&str]) -> Option<usize> {
+    keys.iter()
+        .find_map(|key| fields.iter().position(|(k, _)| k.eq_ignore_ascii_case(key)))
+}
+
+pub(crate) fn parse_log_line(line: &str) -> LogRecord {
+    let object = match serde_json::from_str::<Value>(line) {
+        Ok(Value::Object(object)) => object,
+        _ => {
+            return LogRecord {
+                fields: Vec::new(),
+                time: None,
+                message: None,
+                level: None,
+                level_field: None,
+                raw: line.to_string(),
+            };
+        }
+    };
+    let fields: Vec<(String, String)> = object
+        .iter()
+        .map(|(k, v)| {
+            let text = match v {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            (k.clone(), text)
+        })
+        .collect();
+    let level_field = find_key(&fields, LEVEL_KEYS);
+    let level = level_field.and_then(|i| LogLevel::from_value(&object[&fields[i].0]));
+    LogRecord {
+        time: find_key(&fields, TIME_KEYS),
+        message: find_key(&fields, MESSAGE_KEYS),
+        level,
+        level_field,
+        fields,
+        raw: line.to_string(),
+    }
+}
+
+/// Whether `lines` look like a JSON-lines log: the first few non-empty
+/// lines start JSON objects (the last may still be being written) and one
+/// of them has a timestamp or level field.
+pub(crate) fn looks_like_json_log(lines: &[String]) -> bool {
+    let sample: Vec<&String> = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .take(DETECT_LINES)
+        .collect();
+    if sample.is_empty() || !sample.iter().all(|l| l.trim_start().starts_with('{')) {
+        return false;
+    }
+    sample.iter().any(|l| {
+        let record = parse_log_line(l);
+        record.time.is_some() || record.level_field.is_some()
+    })
+}
+
+/// What the log view shows: records at or above `min_level` matching every
+/// term of `query`. A `key=value` term matches a field whose value contains
+/// `value`; any other term matches anywhere in the line. Case is ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct LogFilter {
+    pub(crate) min_level: Option<LogLevel>,
+    pub(crate) query: String,
+}
+
+impl LogFilter {
+    pub(crate) fn matches(&self, record: &LogRecord) -> bool {
+        if let Some(min) = self.min_level
+            && record.level.is_none_or(|level| level < min)
+        {
+            return false;
+        }
+        self.query.split_whitespace().all(|term| {
+            let term = term.to_lowercase();
+            match term.split_once('=') {
+                Some((key, value)) if !key.is_empty() => record
+                    .fields
+                    .iter()
+                    .any(|(k, v)| k.to_lowercase() == key && v.to_lowercase().contains(value)),
+                _ => record.raw.to_lowercase().contains(&term),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn parses_common_log_shapes() {
+        let record = parse_log_line(
+            r#"{"ts":"2024-05-01T10:00:00Z","level":"warning","msg":"disk low","free_mb":120}"#,
+        );
+        assert_eq!(record.time_text(), "2024-05-01T10:00:00Z");
+        assert_eq!(record.level, Some(LogLevel::Warn));
+        assert_eq!(record.message_text(), "disk low");
+        let extra: Vec<_> = record.extra_fields().collect();
+        assert_eq!(extra, [&("free_mb".to_string(), "120".to_string())]);
+
+        let pino = parse_log_line(r#"{"level":50,"time":1714557600000,"msg":"boom"}"#);
+        assert_eq!(pino.level, Some(LogLevel::Error));
+        assert_eq!(pino.time_text(), "1714557600000");
+
+        let plain = parse_log_line("panic: not json");
+        assert_eq!(plain.level, None);
+        assert_eq!(plain.message_text(), "panic: not json");
+    }
+
+    #[test]
+    fn detects_json_lines_logs() {
+        assert!(looks_like_json_log(&lines(
+            "{\"time\":\"t1\",\"msg\":\"a\"}\n\n{\"time\":\"t2\",\"msg\":\"b\"}\n"
+        )));
+        assert!(!looks_like_json_log(&lines("{\n  \"name\": \"x\"\n}\n")));
+        assert!(!looks_like_json_log(&lines("{\"a\":1}\n{\"b\":2}\n")));
+        assert!(!looks_like_json_log(&lines("2024-05-01 INFO started\n")));
+        assert!(!looks_like_json_log(&[]));
+    }
+
+    #[test]
+    fn filters_by_level_and_query() {
+        let info = parse_log_line(r#"{"level":"info","msg":"login ok","user":"alice"}"#);
+        let error = parse_log_line(r#"{"level":"error","msg":"login failed","user":"Bob"}"#);
+        let mut filter = LogFilter {
+            min_level: Some(LogLevel::Warn),
+            query: String::new(),
+        };
+        assert!(!filter.matches(&info) && filter.matches(&error));
+        filter.min_level = None;
+        filter.query = "user=bob".to_string();
+        assert!(!filter.matches(&info) && filter.matches(&error));
+        filter.query = "LOGIN ok".to_string();
+        assert!(filter.matches(&info) && !filter.matches(&error));
+    }
+}
@@ -27,6 +27,18 @@ pub(crate) enum KeyAction {
     RefreshTree,
     PrevTab,
     NextTab,
+    MoveTabLeft,
+    MoveTabRight,
+    CloseOtherTabs,
+    CloseTabsToRight,
+    CloseAllTabs,
+    ReopenClosedTab,
+    TogglePinTab,
+    ChangedFiles,
+    ShowLastMessage,
+    GoToLastEdit,
+    JumpBack,
+    JumpForward,
     ToggleWordWrap,
     TreeExpandAll,
     TreeCollapseAll,
@@ -44,6 +56,8 @@ pub(crate) enum KeyAction {
     FindPrev,
     DupLineDown,
     DupLineUp,
+    MoveLineDown,
+    MoveLineUp,
     Dedent,
     Completion,
     Undo,
@@ -53,11 +67,19 @@ pub(crate) enum KeyAction {
     Cut,
     CutLine,
     Paste,
+    PasteFromHistory,
     ToggleComment,
+    JumpToBracket,
     PageDown,
     PageUp,
     GoToStart,
     GoToEnd,
+    ToggleBookmark,
+    NextBookmark,
+    PrevBookmark,
+    ScrollLineUp,
+    ScrollLineDown,
+    CenterCursorLine,
 }
 
 impl KeyAction {
@@ -79,11 +101,25 @@ impl KeyAction {
                 | KeyAction::RefreshTree
                 | KeyAction::PrevTab
                 | KeyAction::NextTab
+                | KeyAction::MoveTabLeft
+                | KeyAction::MoveTabRight
+                | KeyAction::CloseOtherTabs
+                | KeyAction::CloseTabsToRight
+                | KeyAction::CloseAllTabs
+                | KeyAction::ReopenClosedTab
+                | KeyAction::TogglePinTab
+                | KeyAction::ChangedFiles
+                | KeyAction::ShowLastMessage
+                | KeyAction::GoToLastEdit
+                | KeyAction::JumpBack
+                | KeyAction::JumpForward
                 | KeyAction::ToggleWordWrap
                 | KeyAction::TreeExpandAll
                 | KeyAction::TreeCollapseAll
                 | KeyAction::TreeExpandRecursive
                 | KeyAction::TreeCollapseRecursive
+                | KeyAction::NextBookmark
+                | KeyAction::PrevBookmark
         )
     }
 
@@ -91,6 +127,26 @@ impl KeyAction {
         !self.is_global()
     }
 
+    /// Editor actions that change the text, refused in read-only tabs.
+    pub(crate) fn edits_text(self) -> bool {
+        matches!(
+            self,
+            KeyAction::DupLineDown
+                | KeyAction::DupLineUp
+                | KeyAction::MoveLineDown
+                | KeyAction::MoveLineUp
+                | KeyAction::Dedent
+                | KeyAction::Completion
+                | KeyAction::Undo
+                | KeyAction::Redo
+                | KeyAction::Cut
+                | KeyAction::CutLine
+                | KeyAction::Paste
+                | KeyAction::PasteFromHistory
+                | KeyAction::ToggleComment
+        )
+    }
+
     pub(crate) fn label(self) -> &'static str {
         match self {
             KeyAction::Save => "Save",
@@ -108,6 +164,18 @@ impl KeyAction {
             KeyAction::RefreshTree => "Refresh Tree",
             KeyAction::PrevTab => "Previous Tab",
             KeyAction::NextTab => "Next Tab",
+            KeyAction::MoveTabLeft => "Move Tab Left",
+            KeyAction::MoveTabRight => "Move Tab Right",
+            KeyAction::CloseOtherTabs => "Close Other Tabs",
+            KeyAction::CloseTabsToRight => "Close Tabs to the Right",
+            KeyAction::CloseAllTabs => "Close All Tabs",
+            KeyAction::ReopenClosedTab => "Reopen Closed Tab",
+            KeyAction::TogglePinTab => "Pin/Unpin Tab",
+            KeyAction::ChangedFiles => "Changed Files",
+            KeyAction::ShowLastMessage => "Show Last Message",
+            KeyAction::GoToLastEdit => "Go to Last Edit Location",
+            KeyAction::JumpBack => "Jump Back",
+            KeyAction::JumpForward => "Jump Forward",
             KeyAction::ToggleWordWrap => "Toggle Word Wrap",
             KeyAction::TreeExpandAll => "Expand All Folders",
             KeyAction::TreeCollapseAll => "Collapse All Folders",
@@ -124,6 +192,8 @@ impl KeyAction {
             KeyAction::FindPrev => "Find Previous",
             KeyAction::DupLineDown => "Duplicate Line Down",
             KeyAction::DupLineUp => "Duplicate Line Up",
+            KeyAction::MoveLineDown => "Move Line Down",
+            KeyAction::MoveLineUp => "Move Line Up",
             KeyAction::Dedent => "Dedent",
             KeyAction::Completion => "Completion",
             KeyAction::Undo => "Undo",
@@ -133,11 +203,19 @@ impl KeyAction {
             KeyAction::Cut => "Cut",
             KeyAction::CutLine => "Cut Line",
             KeyAction::Paste => "Paste",
+            KeyAction::PasteFromHistory => "Paste from History",
             KeyAction::ToggleComment => "Toggle Comment",
+            KeyAction::JumpToBracket => "Jump to Matching Bracket",
             KeyAction::PageDown => "Page Down",
             KeyAction::PageUp => "Page Up",
             KeyAction::GoToStart => "Go to Start",
             KeyAction::GoToEnd => "Go to End",
+            KeyAction::ToggleBookmark => "Toggle Bookmark",
+            KeyAction::NextBookmark => "Next Bookmark",
+            KeyAction::PrevBookmark => "Previous Bookmark",
+            KeyAction::ScrollLineUp => "Scroll Up a Line",
+            KeyAction::ScrollLineDown => "Scroll Down a Line",
+            KeyAction::CenterCursorLine => "Center Cursor Line",
         }
     }
 
@@ -158,6 +236,18 @@ impl KeyAction {
             KeyAction::RefreshTree,
             KeyAction::PrevTab,
             KeyAction::NextTab,
+            KeyAction::MoveTabLeft,
+            KeyAction::MoveTabRight,
+            KeyAction::CloseOtherTabs,
+            KeyAction::CloseTabsToRight,
+            KeyAction::CloseAllTabs,
+            KeyAction::ReopenClosedTab,
+            KeyAction::TogglePinTab,
+            KeyAction::ChangedFiles,
+            KeyAction::ShowLastMessage,
+            KeyAction::GoToLastEdit,
+            KeyAction::JumpBack,
+            KeyAction::JumpForward,
             KeyAction::ToggleWordWrap,
             KeyAction::TreeExpandAll,
             KeyAction::TreeCollapseAll,
@@ -174,6 +264,8 @@ impl KeyAction {
             KeyAction::FindPrev,
             KeyAction::DupLineDown,
             KeyAction::DupLineUp,
+            KeyAction::MoveLineDown,
+            KeyAction::MoveLineUp,
             KeyAction::Dedent,
             KeyAction::Completion,
             KeyAction::Undo,
@@ -183,11 +275,19 @@ impl KeyAction {
             KeyAction::Cut,
             KeyAction::CutLine,
             KeyAction::Paste,
+            KeyAction::PasteFromHistory,
             KeyAction::ToggleComment,
+            KeyAction::JumpToBracket,
             KeyAction::PageDown,
             KeyAction::PageUp,
             KeyAction::GoToStart,
             KeyAction::GoToEnd,
+            KeyAction::ToggleBookmark,
+            KeyAction::NextBookmark,
+            KeyAction::PrevBookmark,
+            KeyAction::ScrollLineUp,
+            KeyAction::ScrollLineDown,
+            KeyAction::CenterCursorLine,
         ]
     }
 }
@@ -524,6 +624,18 @@ impl KeyBindings {
         bind(KeyAction::RefreshTree, "ctrl+r");
         bind(KeyAction::PrevTab, "f1");
         bind(KeyAction::NextTab, "f2");
+        bind(KeyAction::MoveTabLeft, "shift+f1");
+        bind(KeyAction::MoveTabRight, "shift+f2");
+        bind(KeyAction::CloseOtherTabs, "alt+w");
+        bind(KeyAction::CloseTabsToRight, "ctrl+f2");
+        bind(KeyAction::CloseAllTabs, "ctrl+alt+w");
+        bind(KeyAction::ReopenClosedTab, "ctrl+shift+t");
+        bind(KeyAction::TogglePinTab, "alt+p");
+        bind(KeyAction::ChangedFiles, "ctrl+alt+o");
+        bind(KeyAction::ShowLastMessage, "alt+m");
+        bind(KeyAction::GoToLastEdit, "ctrl+shift+backspace");
+        bind(KeyAction::JumpBack, "alt+left");
+        bind(KeyAction::JumpForward, "alt+right");
         bind(KeyAction::ToggleWordWrap, "alt+z");
         bind(KeyAction::ToggleWordWrap, "f6");
         bind(KeyAction::TreeExpandAll, "ctrl+shift+e");
@@ -544,11 +656,14 @@ impl KeyBindings {
         bind(KeyAction::FindPrev, "shift+f3");
         bind(KeyAction::DupLineDown, "shift+alt+down");
         bind(KeyAction::DupLineUp, "shift+alt+up");
+        bind(KeyAction::MoveLineDown, "alt+down");
+        bind(KeyAction::MoveLineUp, "alt+up");
         bind(KeyAction::Dedent, "shift+backtab");
         bind(KeyAction::Completion, "ctrl+space");
         bind(KeyAction::Completion, "ctrl+.");
         bind(KeyAction::GoToLine, "ctrl+g");
         bind(KeyAction::ToggleComment, "ctrl+/");
+        bind(KeyAction::JumpToBracket, "ctrl+m");
         bind(KeyAction::Undo, "ctrl+z");
         bind(KeyAction::Redo, "ctrl+shift+z");
         bind(KeyAction::Redo, "ctrl+y");
@@ -557,10 +672,17 @@ impl KeyBindings {
         bind(KeyAction::Cut, "ctrl+x");
         bind(KeyAction::CutLine, "ctrl+k");
         bind(KeyAction::Paste, "ctrl+v");
+        bind(KeyAction::PasteFromHistory, "alt+v");
         bind(KeyAction::PageDown, "pagedown");
         bind(KeyAction::PageUp, "pageup");
         bind(KeyAction::GoToStart, "ctrl+home");
         bind(KeyAction::GoToEnd, "ctrl+end");
+        bind(KeyAction::ToggleBookmark, "ctrl+alt+k");
+        bind(KeyAction::NextBookmark, "ctrl+alt+l");
+        bind(KeyAction::PrevBookmark, "ctrl+alt+j");
+        bind(KeyAction::ScrollLineUp, "ctrl+up");
+        bind(KeyAction::ScrollLineDown, "ctrl+down");
+        bind(KeyAction::CenterCursorLine, "ctrl+l");
 
         KeyBindings { map }
     }
@@ -586,11 +708,16 @@ impl KeyBindings {
     }
 
     pub(crate) fn display_for(&self, action: KeyAction) -> String {
+        self.bound_display(action)
+            .unwrap_or_else(|| "unbound".to_string())
+    }
+
+    /// The first key bound to `action`, or `None` when it is unbound.
+    pub(crate) fn bound_display(&self, action: KeyAction) -> Option<String> {
         self.map
             .get(&action)
             .and_then(|v| v.first())
             .map(|b| b.display())
-            .unwrap_or_else(|| "unbound".to_string())
     }
 
     #[cfg(test)]
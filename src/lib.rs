@@ -1,4 +1,4 @@
-use std::io::{self, Stdout};
+use std::io::{self, Stdout, Write};
 use std::path::PathBuf;
 use std::process::Command;
 use std::time::Duration;
@@ -7,7 +7,7 @@ use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
 use ratatui::crossterm::event::{
     self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
-    Event, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
 };
 use ratatui::crossterm::execute;
 use ratatui::crossterm::terminal::{
@@ -15,17 +15,38 @@ use ratatui::crossterm::terminal::{
 };
 
 mod app;
+mod cargo_check;
+mod commands;
+mod diff;
+mod editor_view;
+mod env_file;
+mod file_io;
+mod git_branch;
+mod git_log;
+mod icons;
+mod ignore;
+mod json_log;
 mod keybinds;
 mod lsp_client;
+mod lsp_transport;
+mod merge_conflicts;
+mod outline;
 mod persistence;
+mod playground;
+mod preview;
+mod project_search;
+mod settings;
 mod syntax;
 mod tab;
+mod tasks;
+mod test_explorer;
 mod theme;
 mod tree_item;
 mod types;
 mod ui;
 mod util;
 use app::App;
+pub use editor_view::EditorView;
 use lsp_client::resolve_rust_analyzer_bin;
 use ui::draw;
 
@@ -43,24 +64,53 @@ pub fn run() -> io::Result<()> {
         println!("Usage: lazyide [OPTIONS] [PATH]");
         println!();
         println!("Arguments:");
-        println!("  [PATH]    Directory to open (default: current directory)");
+        println!("  [PATH]             Directory to open (default: current directory)");
         println!();
         println!("Options:");
-        println!("  --setup   Check for and install optional tools (rust-analyzer, ripgrep)");
-        println!("  --help    Show this help message");
+        println!(
+            "  --setup            Check for and install optional tools (rust-analyzer, ripgrep)"
+        );
+        println!("  --state-dir <DIR>  Store autosaves and other local state in DIR");
+        println!("  --recent           Start with the recent projects picker open");
+        println!("  --help             Show this help message");
         return Ok(());
     }
 
-    let root = if let Some(path) = std::env::args().nth(1) {
-        PathBuf::from(path)
-    } else {
-        std::env::current_dir()?
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut path_arg = None;
+    let mut open_recent = false;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if let Some(dir) = arg.strip_prefix("--state-dir=") {
+            persistence::set_state_dir_override(PathBuf::from(dir));
+        } else if arg == "--state-dir" {
+            let Some(dir) = args.get(i + 1) else {
+                eprintln!("--state-dir requires a directory");
+                return Ok(());
+            };
+            persistence::set_state_dir_override(PathBuf::from(dir));
+            i += 1;
+        } else if arg == "--recent" {
+            open_recent = true;
+        } else if path_arg.is_none() && !arg.starts_with('-') {
+            path_arg = Some(PathBuf::from(arg));
+        }
+        i += 1;
+    }
+    let root = match path_arg {
+        Some(path) => path,
+        None => std::env::current_dir()?,
     };
     if !root.is_dir() {
         eprintln!("Root path is not a directory: {}", root.display());
         return Ok(());
     }
 
+    if let Err(err) = persistence::migrate_legacy_state() {
+        eprintln!("lazyide: failed to migrate state directory: {err}");
+    }
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(
@@ -97,6 +147,10 @@ pub fn run() -> io::Result<()> {
 
     let mut app = App::new(root)?;
     app.enhanced_keys = enhanced_keys;
+    app.record_recent_project();
+    if open_recent {
+        app.open_recent_projects();
+    }
     let result = run_app(terminal, app);
 
     disable_raw_mode()?;
@@ -119,39 +173,52 @@ fn run_app(mut terminal: Terminal<CrosstermBackend<Stdout>>, mut app: App) -> io
         app.poll_lsp();
         app.poll_git_results();
         app.poll_wrap_rebuild();
+        if let Err(err) = app.poll_file_io() {
+            app.set_error(format!("File load error: {err}"));
+        }
         if let Err(err) = app.poll_fs_changes() {
-            app.set_status(format!("Filesystem update error: {err}"));
+            app.set_error(format!("Filesystem update error: {err}"));
         }
         if let Err(err) = app.poll_autosave() {
-            app.set_status(format!("Autosave error: {err}"));
+            app.set_error(format!("Autosave error: {err}"));
+        }
+        if let Err(err) = app.poll_large_file() {
+            app.set_error(format!("Large file read error: {err}"));
+        }
+        if let Err(err) = app.poll_follow() {
+            app.set_error(format!("Follow mode broadcast stopped: {err}"));
+        }
+        app.poll_snippet_run();
+        app.poll_project_search();
+        app.poll_task();
+        app.poll_test_list();
+        app.poll_cargo_check();
+        if let Err(err) = app.poll_branch_switch() {
+            app.set_error(format!("Branch switch refresh failed: {err}"));
         }
         app.update_status_for_cursor();
+        app.poll_status_expiry();
+        app.poll_pending_expiry();
         terminal.draw(|f| draw(&mut app, f))?;
+        if std::mem::take(&mut app.bell_pending) {
+            terminal.backend_mut().write_all(b"\x07")?;
+            terminal.backend_mut().flush()?;
+        }
         if app.quit {
+            app.finish_file_writes();
+            app.persist_state();
             return Ok(());
         }
         if event::poll(Duration::from_millis(100))? {
             // Drain all pending events before the next draw to avoid
             // queuing hundreds of redraws during rapid mouse scrolling.
             loop {
-                let ev = event::read()?;
-                match ev {
-                    Event::Key(key) => {
-                        if let Err(err) = app.handle_key(key) {
-                            app.set_status(format!("Action failed: {err}"));
-                        }
-                    }
-                    Event::Mouse(mouse) => {
-                        if let Err(err) = app.handle_mouse(mouse) {
-                            app.set_status(format!("Action failed: {err}"));
-                        }
-                    }
-                    Event::Paste(text) => {
-                        app.handle_paste(text);
-                    }
-                    _ => {}
+                if let Err(err) = app.handle_event(event::read()?) {
+                    app.set_error(format!("Action failed: {err}"));
                 }
                 if app.quit {
+                    app.finish_file_writes();
+                    app.persist_state();
                     return Ok(());
                 }
                 // If no more events are pending, break and redraw.
@@ -1,20 +1,24 @@
 use std::env;
-use std::io::{self, BufRead, BufReader, Read, Write};
+use std::io;
 use std::path::{Path, PathBuf};
-use std::process::{ChildStdin, Command, Stdio};
-use std::sync::mpsc::{self, Receiver, Sender};
-use std::sync::{Arc, Mutex};
-use std::thread;
+use std::process::{Command, Stdio};
 use std::time::Duration;
 
 use serde_json::{Value, json};
 use url::Url;
 
-#[derive(Debug, Clone)]
+use crate::lsp_transport::LspTransport;
+use crate::persistence::LspSettings;
+use crate::types::QuickOpenItem;
+
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) struct LspDiagnostic {
     pub(crate) line: usize,
     pub(crate) severity: String,
     pub(crate) message: String,
+    /// Start and end as 0-based `(row, char col)`, when the server sent a
+    /// range; `line` is the start row plus one.
+    pub(crate) range: Option<((usize, usize), (usize, usize))>,
 }
 
 #[derive(Debug, Clone)]
@@ -24,22 +28,26 @@ pub(crate) struct LspCompletionItem {
     pub(crate) detail: Option<String>,
 }
 
-#[derive(Debug)]
-pub(crate) enum LspInbound {
-    Notification { method: String, params: Value },
-    Response { id: i64, result: Value },
-}
-
+/// A running language server process and the transport talking to it.
 pub(crate) struct LspClient {
-    pub(crate) writer: Arc<Mutex<ChildStdin>>,
-    pub(crate) rx: Receiver<LspInbound>,
-    pub(crate) next_id: i64,
+    pub(crate) transport: LspTransport,
+    /// The server accepts ranged `didChange` edits (`TextDocumentSyncKind`
+    /// 2); otherwise every change sends the whole document.
+    pub(crate) incremental_sync: bool,
+    /// The `capabilities` the server answered `initialize` with.
+    pub(crate) capabilities: Value,
 }
 
 impl LspClient {
-    pub(crate) fn new_rust_analyzer(root: &Path) -> io::Result<Self> {
+    pub(crate) fn new_rust_analyzer(
+        root: &Path,
+        settings: &LspSettings,
+        env: &[(String, String)],
+    ) -> io::Result<Self> {
         let ra_bin = resolve_rust_analyzer_bin().unwrap_or_else(|| PathBuf::from("rust-analyzer"));
         let mut child = Command::new(ra_bin)
+            .args(settings.server_args_for("rust"))
+            .envs(env.iter().map(|(k, v)| (k, v)))
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::null())
@@ -53,93 +61,211 @@ impl LspClient {
             .take()
             .ok_or_else(|| io::Error::other("failed to open rust-analyzer stdout"))?;
 
-        let writer = Arc::new(Mutex::new(stdin));
-        let (tx, rx) = mpsc::channel::<LspInbound>();
-        thread::spawn(move || lsp_reader_loop(stdout, tx));
         let mut client = Self {
-            writer,
-            rx,
-            next_id: 1,
+            transport: LspTransport::new(stdin, stdout),
+            incremental_sync: false,
+            capabilities: Value::Null,
         };
+        let section = settings.rust_analyzer_section();
+        client.transport.settings = json!({ "rust-analyzer": section.clone() });
         let root_uri = Url::from_directory_path(root)
             .map_err(|_| io::Error::other("invalid root path for URI"))?
             .to_string();
-        let init_id = client.send_request(
+        let init_id = client.transport.send_request(
             "initialize",
             json!({
                 "processId": std::process::id(),
                 "rootUri": root_uri,
                 "capabilities": {
                     "textDocument": {
+                        "synchronization": { "dynamicRegistration": false },
                         "publishDiagnostics": {},
-                        "completion": {}
+                        "completion": {},
+                        "references": {},
+                        "rename": {},
+                        "formatting": {}
+                    },
+                    "workspace": {
+                        "configuration": true,
+                        "symbol": {},
+                        "didChangeWatchedFiles": { "dynamicRegistration": true }
                     }
                 },
+                "initializationOptions": section,
                 "clientInfo": { "name": "lazyide", "version": "0.1.0" },
             }),
         )?;
         client.wait_for_initialize(init_id)?;
-        client.send_notification("initialized", json!({}))?;
+        client
+            .transport
+            .send_notification("initialized", json!({}))?;
         Ok(client)
     }
 
-    pub(crate) fn wait_for_initialize(&self, init_id: i64) -> io::Result<()> {
-        let deadline = std::time::Instant::now() + Duration::from_secs(3);
-        loop {
-            let now = std::time::Instant::now();
-            if now >= deadline {
-                return Err(io::Error::other("LSP initialize timeout"));
-            }
-            let timeout = deadline.saturating_duration_since(now);
-            match self.rx.recv_timeout(timeout) {
-                Ok(LspInbound::Response { id, result }) if id == init_id => {
-                    if result.get("code").is_some() && result.get("message").is_some() {
-                        return Err(io::Error::other(format!(
-                            "LSP initialize error: {}",
-                            result
-                        )));
-                    }
-                    return Ok(());
-                }
-                Ok(_) => continue,
-                Err(_) => return Err(io::Error::other("LSP initialize response missing")),
-            }
+    pub(crate) fn wait_for_initialize(&mut self, init_id: i64) -> io::Result<()> {
+        let result = self
+            .transport
+            .wait_for_response(init_id, Duration::from_secs(3))
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::TimedOut => io::Error::other("LSP initialize timeout"),
+                _ => io::Error::other("LSP initialize response missing"),
+            })?;
+        if result.get("code").is_some() && result.get("message").is_some() {
+            return Err(io::Error::other(format!(
+                "LSP initialize error: {}",
+                result
+            )));
         }
+        self.incremental_sync = sync_kind(&result) == Some(2);
+        self.capabilities = result.get("capabilities").cloned().unwrap_or(Value::Null);
+        Ok(())
     }
+}
 
-    pub(crate) fn send_notification(&self, method: &str, params: Value) -> io::Result<()> {
-        self.send_raw(json!({
-            "jsonrpc": "2.0",
-            "method": method,
-            "params": params,
-        }))
-    }
+/// `TextDocumentSyncKind` from an initialize result, given either as a
+/// number or as the `change` field of `TextDocumentSyncOptions`.
+fn sync_kind(result: &Value) -> Option<u64> {
+    let sync = result.get("capabilities")?.get("textDocumentSync")?;
+    sync.as_u64()
+        .or_else(|| sync.get("change").and_then(Value::as_u64))
+}
 
-    pub(crate) fn send_request(&mut self, method: &str, params: Value) -> io::Result<i64> {
-        let id = self.next_id;
-        self.next_id += 1;
-        self.send_raw(json!({
-            "jsonrpc": "2.0",
-            "id": id,
-            "method": method,
-            "params": params,
-        }))?;
-        Ok(id)
+/// Quick Open items from a `workspace/symbol` result, either
+/// `SymbolInformation` or `WorkspaceSymbol` (whose location may have no
+/// range). The container, when given, is shown after the name.
+pub(crate) fn parse_workspace_symbols(result: &Value) -> Vec<QuickOpenItem> {
+    let Some(symbols) = result.as_array() else {
+        return Vec::new();
+    };
+    symbols
+        .iter()
+        .filter_map(|symbol| {
+            let name = symbol.get("name")?.as_str()?;
+            let location = symbol.get("location")?;
+            let uri = location.get("uri")?.as_str()?;
+            let path = Url::parse(uri).ok()?.to_file_path().ok()?;
+            let start = location.get("range").and_then(|r| r.get("start"));
+            let at = |key| {
+                start
+                    .and_then(|s| s.get(key))
+                    .and_then(Value::as_u64)
+                    .map_or(0, |n| n as usize)
+            };
+            let label = match symbol.get("containerName").and_then(Value::as_str) {
+                Some(container) if !container.is_empty() => format!("{name}  {container}"),
+                _ => name.to_string(),
+            };
+            Some(QuickOpenItem {
+                path,
+                position: Some((at("line"), at("character"))),
+                label: Some(label),
+            })
+        })
+        .collect()
+}
+
+/// A `TextEdit` from the server: replace `start..end`, each `(line, UTF-16
+/// column)`, with `new_text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LspTextEdit {
+    pub(crate) start: (usize, usize),
+    pub(crate) end: (usize, usize),
+    pub(crate) new_text: String,
+}
+
+fn lsp_position(value: &Value) -> Option<(usize, usize)> {
+    let at = |key| value.get(key)?.as_u64().map(|n| n as usize);
+    Some((at("line")?, at("character")?))
+}
+
+/// The edits of a `TextEdit[]` result, as `textDocument/formatting`
+/// answers.
+pub(crate) fn parse_text_edits(result: &Value) -> Vec<LspTextEdit> {
+    let Some(edits) = result.as_array() else {
+        return Vec::new();
+    };
+    edits
+        .iter()
+        .filter_map(|edit| {
+            let range = edit.get("range")?;
+            Some(LspTextEdit {
+                start: lsp_position(range.get("start")?)?,
+                end: lsp_position(range.get("end")?)?,
+                new_text: edit.get("newText")?.as_str()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The text edits of a `WorkspaceEdit` per file, from either `changes` or
+/// `documentChanges`. File creations, renames and deletions are left out.
+pub(crate) fn parse_workspace_edit(result: &Value) -> Vec<(PathBuf, Vec<LspTextEdit>)> {
+    let path = |uri: &str| Url::parse(uri).ok()?.to_file_path().ok();
+    if let Some(changes) = result.get("documentChanges").and_then(Value::as_array) {
+        return changes
+            .iter()
+            .filter_map(|change| {
+                let uri = change.get("textDocument")?.get("uri")?.as_str()?;
+                Some((path(uri)?, parse_text_edits(change.get("edits")?)))
+            })
+            .collect();
     }
+    let Some(changes) = result.get("changes").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    changes
+        .iter()
+        .filter_map(|(uri, edits)| Some((path(uri)?, parse_text_edits(edits))))
+        .collect()
+}
 
-    pub(crate) fn send_raw(&self, value: Value) -> io::Result<()> {
-        let payload = serde_json::to_vec(&value)
-            .map_err(|e| io::Error::other(format!("lsp serialize error: {e}")))?;
-        let header = format!("Content-Length: {}\r\n\r\n", payload.len());
-        let mut guard = self
-            .writer
-            .lock()
-            .map_err(|_| io::Error::other("lsp writer lock poisoned"))?;
-        guard.write_all(header.as_bytes())?;
-        guard.write_all(&payload)?;
-        guard.flush()?;
-        Ok(())
+/// `(path, line, UTF-16 column)` of each `Location` in a
+/// `textDocument/references` result.
+pub(crate) fn parse_locations(result: &Value) -> Vec<(PathBuf, usize, usize)> {
+    let Some(locations) = result.as_array() else {
+        return Vec::new();
+    };
+    locations
+        .iter()
+        .filter_map(|location| {
+            let uri = location.get("uri")?.as_str()?;
+            let path = Url::parse(uri).ok()?.to_file_path().ok()?;
+            let (line, col) = lsp_position(location.get("range")?.get("start")?)?;
+            Some((path, line, col))
+        })
+        .collect()
+}
+
+/// `lines` with `edits` applied. The edits must not overlap, as the
+/// protocol requires; positions past the end are clamped to it.
+pub(crate) fn apply_text_edits(lines: &[String], edits: &[LspTextEdit]) -> Vec<String> {
+    let mut text = lines.join("\n");
+    let mut line_starts = vec![0];
+    line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+    let offset = |(line, col): (usize, usize)| {
+        let Some(&start) = line_starts.get(line) else {
+            return text.len();
+        };
+        let line = &text[start..line_starts.get(line + 1).map_or(text.len(), |&s| s - 1)];
+        let mut units = 0;
+        for (i, c) in line.char_indices() {
+            if units >= col {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        start + line.len()
+    };
+    let mut ranges: Vec<(usize, usize, &str)> = edits
+        .iter()
+        .map(|e| (offset(e.start), offset(e.end), e.new_text.as_str()))
+        .collect();
+    // From the end, so earlier offsets stay valid.
+    ranges.sort_by_key(|&(start, end, _)| std::cmp::Reverse((start, end)));
+    for (start, end, new_text) in ranges {
+        text.replace_range(start..end.max(start), new_text);
     }
+    text.split('\n').map(str::to_string).collect()
 }
 
 pub(crate) fn resolve_rust_analyzer_bin() -> Option<PathBuf> {
@@ -166,235 +292,17 @@ pub(crate) fn resolve_rust_analyzer_bin() -> Option<PathBuf> {
     candidates.into_iter().find(|p| p.is_file())
 }
 
-pub(crate) fn lsp_reader_loop(stdout: impl Read, tx: Sender<LspInbound>) {
-    let mut reader = BufReader::new(stdout);
-    loop {
-        let mut content_length = 0usize;
-        loop {
-            let mut line = String::new();
-            let Ok(n) = reader.read_line(&mut line) else {
-                return;
-            };
-            if n == 0 {
-                return;
-            }
-            let trimmed = line.trim_end();
-            if trimmed.is_empty() {
-                break;
-            }
-            if let Some(rest) = trimmed.strip_prefix("Content-Length:") {
-                content_length = rest.trim().parse::<usize>().unwrap_or(0);
-            }
-        }
-        if content_length == 0 {
-            continue;
-        }
-        let mut buf = vec![0u8; content_length];
-        if reader.read_exact(&mut buf).is_err() {
-            return;
-        }
-        let Ok(msg) = serde_json::from_slice::<Value>(&buf) else {
-            continue;
-        };
-        if let Some(method) = msg.get("method").and_then(Value::as_str) {
-            let params = msg.get("params").cloned().unwrap_or(Value::Null);
-            let _ = tx.send(LspInbound::Notification {
-                method: method.to_string(),
-                params,
-            });
-            continue;
-        }
-        if let Some(id) = msg.get("id").and_then(Value::as_i64) {
-            let result = msg
-                .get("result")
-                .cloned()
-                .or_else(|| msg.get("error").cloned())
-                .unwrap_or(Value::Null);
-            let _ = tx.send(LspInbound::Response { id, result });
-        }
-    }
-}
 #[cfg(test)]
 mod lsp_and_struct_tests {
     use super::*;
+    use crate::syntax::{HighlightCache, SyntaxLang};
     use crate::tab::{FoldRange, Tab};
     use crate::tree_item::TreeItem;
     use crate::util::file_uri;
-    use serde_json::json;
-    use std::collections::HashSet;
-    use std::io::Cursor;
+    use std::collections::{BTreeSet, HashMap, HashSet};
     use std::path::PathBuf;
-    use std::sync::mpsc;
     use ratatui_textarea::TextArea;
 
-    #[test]
-    fn test_lsp_reader_loop_valid_notification() {
-        let notification = json!({
-            "jsonrpc": "2.0",
-            "method": "textDocument/publishDiagnostics",
-            "params": { "uri": "file:///test.rs", "diagnostics": [] }
-        });
-        let payload = serde_json::to_string(&notification).unwrap();
-        let message = format!("Content-Length: {}\r\n\r\n{}", payload.len(), payload);
-
-        let (tx, rx) = mpsc::channel();
-        let handle = std::thread::spawn(move || {
-            lsp_reader_loop(Cursor::new(message.as_bytes()), tx);
-        });
-
-        std::thread::sleep(std::time::Duration::from_millis(50));
-        let received = rx.try_recv().unwrap();
-        match received {
-            LspInbound::Notification { method, params } => {
-                assert_eq!(method, "textDocument/publishDiagnostics");
-                assert!(params.get("uri").is_some());
-            }
-            _ => panic!("Expected Notification"),
-        }
-        let _ = handle.join();
-    }
-
-    #[test]
-    fn test_lsp_reader_loop_valid_response() {
-        let response = json!({
-            "jsonrpc": "2.0", "id": 42,
-            "result": { "capabilities": { "textDocumentSync": 1 } }
-        });
-        let payload = serde_json::to_string(&response).unwrap();
-        let message = format!("Content-Length: {}\r\n\r\n{}", payload.len(), payload);
-
-        let (tx, rx) = mpsc::channel();
-        let handle = std::thread::spawn(move || {
-            lsp_reader_loop(Cursor::new(message.as_bytes()), tx);
-        });
-
-        std::thread::sleep(std::time::Duration::from_millis(50));
-        match rx.try_recv().unwrap() {
-            LspInbound::Response { id, result } => {
-                assert_eq!(id, 42);
-                assert!(result.get("capabilities").is_some());
-            }
-            _ => panic!("Expected Response"),
-        }
-        let _ = handle.join();
-    }
-
-    #[test]
-    fn test_lsp_reader_loop_multiple_messages() {
-        let msg1 = json!({"jsonrpc":"2.0","method":"initialized","params":{}});
-        let msg2 = json!({"jsonrpc":"2.0","id":1,"result":null});
-        let msg3 = json!({"jsonrpc":"2.0","method":"window/logMessage","params":{"type":4,"message":"Started"}});
-
-        let p1 = serde_json::to_string(&msg1).unwrap();
-        let p2 = serde_json::to_string(&msg2).unwrap();
-        let p3 = serde_json::to_string(&msg3).unwrap();
-        let messages = format!(
-            "Content-Length: {}\r\n\r\n{}Content-Length: {}\r\n\r\n{}Content-Length: {}\r\n\r\n{}",
-            p1.len(),
-            p1,
-            p2.len(),
-            p2,
-            p3.len(),
-            p3
-        );
-
-        let (tx, rx) = mpsc::channel();
-        let handle = std::thread::spawn(move || {
-            lsp_reader_loop(Cursor::new(messages.as_bytes()), tx);
-        });
-
-        std::thread::sleep(std::time::Duration::from_millis(100));
-        let mut received = Vec::new();
-        while let Ok(msg) = rx.try_recv() {
-            received.push(msg);
-        }
-        assert_eq!(received.len(), 3);
-        let _ = handle.join();
-    }
-
-    #[test]
-    fn test_lsp_reader_loop_invalid_json_skipped() {
-        let invalid = "not valid json!";
-        let valid = json!({"jsonrpc":"2.0","method":"test","params":{}});
-        let vp = serde_json::to_string(&valid).unwrap();
-        let message = format!(
-            "Content-Length: {}\r\n\r\n{}Content-Length: {}\r\n\r\n{}",
-            invalid.len(),
-            invalid,
-            vp.len(),
-            vp
-        );
-
-        let (tx, rx) = mpsc::channel();
-        let handle = std::thread::spawn(move || {
-            lsp_reader_loop(Cursor::new(message.as_bytes()), tx);
-        });
-
-        std::thread::sleep(std::time::Duration::from_millis(100));
-        match rx.try_recv().unwrap() {
-            LspInbound::Notification { method, .. } => assert_eq!(method, "test"),
-            _ => panic!("Expected Notification"),
-        }
-        let _ = handle.join();
-    }
-
-    #[test]
-    fn test_lsp_reader_loop_truncated_input() {
-        let (tx, rx) = mpsc::channel();
-        let handle = std::thread::spawn(move || {
-            lsp_reader_loop(
-                Cursor::new("Content-Length: 100\r\n\r\nincomplete".as_bytes()),
-                tx,
-            );
-        });
-        assert!(handle.join().is_ok());
-        assert!(rx.try_recv().is_err());
-    }
-
-    #[test]
-    fn test_lsp_reader_loop_response_with_error() {
-        let error_resp =
-            json!({"jsonrpc":"2.0","id":5,"error":{"code":-32601,"message":"Method not found"}});
-        let payload = serde_json::to_string(&error_resp).unwrap();
-        let message = format!("Content-Length: {}\r\n\r\n{}", payload.len(), payload);
-
-        let (tx, rx) = mpsc::channel();
-        let handle = std::thread::spawn(move || {
-            lsp_reader_loop(Cursor::new(message.as_bytes()), tx);
-        });
-
-        std::thread::sleep(std::time::Duration::from_millis(50));
-        match rx.try_recv().unwrap() {
-            LspInbound::Response { id, result } => {
-                assert_eq!(id, 5);
-                assert!(result.get("code").is_some());
-            }
-            _ => panic!("Expected Response"),
-        }
-        let _ = handle.join();
-    }
-
-    #[test]
-    fn test_lsp_jsonrpc_format() {
-        let notification = json!({
-            "jsonrpc": "2.0",
-            "method": "textDocument/didOpen",
-            "params": { "textDocument": { "uri": "file:///test.rs", "languageId": "rust", "version": 1, "text": "fn main() {}" } }
-        });
-        let payload = serde_json::to_vec(&notification).unwrap();
-        let header = format!("Content-Length: {}\r\n\r\n", payload.len());
-        assert!(header.starts_with("Content-Length: "));
-        assert!(header.ends_with("\r\n\r\n"));
-        let cl: usize = header
-            .strip_prefix("Content-Length: ")
-            .unwrap()
-            .strip_suffix("\r\n\r\n")
-            .unwrap()
-            .parse()
-            .unwrap();
-        assert_eq!(cl, payload.len());
-    }
-
     #[test]
     fn test_file_uri_absolute_path() {
         let test_file = std::env::temp_dir().join("lazyide_test_file_uri.txt");
@@ -423,6 +331,7 @@ mod lsp_and_struct_tests {
             line: 10,
             severity: "Error".to_string(),
             message: "unused variable".to_string(),
+            range: None,
         };
         assert_eq!(d.line, 10);
         assert_eq!(d.severity, "Error");
@@ -435,6 +344,7 @@ mod lsp_and_struct_tests {
             line: 100,
             severity: "Error".to_string(),
             message: "type mismatch".to_string(),
+            range: None,
         };
         let c = d.clone();
         assert_eq!(d.line, c.line);
@@ -482,25 +392,46 @@ mod lsp_and_struct_tests {
         let tab = Tab {
             path: PathBuf::from("/test/file.rs"),
             is_preview: false,
+            read_only: false,
+            lang: SyntaxLang::Plain,
+            pinned: false,
             editor: TextArea::default(),
             dirty: false,
+            crlf: false,
+            utf16: None,
             open_disk_snapshot: None,
             editor_scroll_row: 0,
             editor_scroll_col: 0,
             fold_ranges: Vec::new(),
             bracket_depths: Vec::new(),
+            line_states: Vec::new(),
+            bracket_matches: HashMap::new(),
             folded_starts: HashSet::new(),
             visible_rows_map: Vec::new(),
             visible_row_starts: Vec::new(),
             visible_row_ends: Vec::new(),
             open_doc_uri: None,
             open_doc_version: 0,
+            lsp_text: Vec::new(),
             diagnostics: Vec::new(),
             conflict_prompt_open: false,
             conflict_disk_text: None,
             recovery_prompt_open: false,
             recovery_text: None,
             git_line_status: Vec::new(),
+            search_matches: Vec::new(),
+            undo_snapshots: Vec::new(),
+            redo_snapshots: Vec::new(),
+            undo_groups: Vec::new(),
+            redo_groups: Vec::new(),
+            large_file: None,
+            follow_tail: false,
+            highlight_cache: HighlightCache::default(),
+            bookmarks: BTreeSet::new(),
+            bookmarks_line_count: 0,
+            merge_conflicts: Vec::new(),
+            auto_closers: Vec::new(),
+            pending_io: None,
         };
         assert_eq!(tab.path, PathBuf::from("/test/file.rs"));
         assert!(!tab.is_preview);
@@ -514,8 +445,13 @@ mod lsp_and_struct_tests {
         let tab = Tab {
             path: PathBuf::from("/src/main.rs"),
             is_preview: true,
+            read_only: false,
+            lang: SyntaxLang::Plain,
+            pinned: false,
             editor,
             dirty: true,
+            crlf: false,
+            utf16: None,
             open_disk_snapshot: Some("old".to_string()),
             editor_scroll_row: 10,
             editor_scroll_col: 0,
@@ -524,6 +460,8 @@ mod lsp_and_struct_tests {
                 end_line: 15,
             }],
             bracket_depths: Vec::new(),
+            line_states: Vec::new(),
+            bracket_matches: HashMap::new(),
             folded_starts: {
                 let mut s = HashSet::new();
                 s.insert(5);
@@ -534,16 +472,31 @@ mod lsp_and_struct_tests {
             visible_row_ends: vec![10, 10, 10, 10, 10],
             open_doc_uri: Some("file:///src/main.rs".to_string()),
             open_doc_version: 3,
+            lsp_text: Vec::new(),
             diagnostics: vec![LspDiagnostic {
                 line: 1,
                 severity: "Warning".to_string(),
                 message: "unused".to_string(),
+                range: None,
             }],
             conflict_prompt_open: true,
             conflict_disk_text: Some("disk".to_string()),
             recovery_prompt_open: false,
             recovery_text: None,
             git_line_status: Vec::new(),
+            search_matches: Vec::new(),
+            undo_snapshots: Vec::new(),
+            redo_snapshots: Vec::new(),
+            undo_groups: Vec::new(),
+            redo_groups: Vec::new(),
+            large_file: None,
+            follow_tail: false,
+            highlight_cache: HighlightCache::default(),
+            bookmarks: BTreeSet::new(),
+            bookmarks_line_count: 0,
+            merge_conflicts: Vec::new(),
+            auto_closers: Vec::new(),
+            pending_io: None,
         };
         assert!(tab.is_preview);
         assert!(tab.dirty);
@@ -560,6 +513,9 @@ mod lsp_and_struct_tests {
             depth: 2,
             is_dir: false,
             expanded: false,
+            ignored: false,
+            external: false,
+            pinned: false,
         };
         assert_eq!(item.name, "main.rs");
         assert_eq!(item.depth, 2);
@@ -574,6 +530,9 @@ mod lsp_and_struct_tests {
             depth: 1,
             is_dir: true,
             expanded: true,
+            ignored: false,
+            external: false,
+            pinned: false,
         };
         assert!(item.is_dir);
         assert!(item.expanded);
@@ -587,9 +546,94 @@ mod lsp_and_struct_tests {
             depth: 1,
             is_dir: false,
             expanded: false,
+            ignored: false,
+            external: false,
+            pinned: false,
         };
         let c = item.clone();
         assert_eq!(item.path, c.path);
         assert_eq!(item.name, c.name);
     }
+
+    #[test]
+    fn workspace_symbols_parse_both_result_shapes() {
+        let result = json!([
+            {
+                "name": "App",
+                "kind": 23,
+                "containerName": "app",
+                "location": {
+                    "uri": "file:///src/app.rs",
+                    "range": { "start": { "line": 40, "character": 11 } }
+                }
+            },
+            { "name": "main", "kind": 12, "location": { "uri": "file:///src/main.rs" } },
+            { "name": "broken", "location": { "uri": "not a uri" } }
+        ]);
+        let items = parse_workspace_symbols(&result);
+        let shown: Vec<_> = items
+            .iter()
+            .map(|i| (i.path.clone(), i.position, i.label.as_deref()))
+            .collect();
+        assert_eq!(
+            shown,
+            [
+                (PathBuf::from("/src/app.rs"), Some((40, 11)), Some("App  app")),
+                (PathBuf::from("/src/main.rs"), Some((0, 0)), Some("main")),
+            ]
+        );
+        assert!(parse_workspace_symbols(&Value::Null).is_empty());
+    }
+
+    #[test]
+    fn workspace_edits_parse_both_forms_and_apply_by_utf16_column() {
+        let edit = |line, start, end, text: &str| {
+            json!({
+                "range": {
+                    "start": { "line": line, "character": start },
+                    "end": { "line": line, "character": end }
+                },
+                "newText": text
+            })
+        };
+        let changes = json!({ "changes": { "file:///src/a.rs": [edit(0, 3, 6, "new")] } });
+        let parsed = parse_workspace_edit(&changes);
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].0, PathBuf::from("/src/a.rs"));
+        let documents = json!({ "documentChanges": [
+            { "textDocument": { "uri": "file:///src/b.rs", "version": 3 },
+              "edits": [edit(1, 0, 0, "// "), edit(0, 4, 7, "new")] },
+            { "kind": "create", "uri": "file:///src/c.rs" }
+        ] });
+        let parsed = parse_workspace_edit(&documents);
+        assert_eq!(parsed.len(), 1);
+
+        // "é" is one UTF-16 unit and "𝄞" two.
+        let lines = ["é𝄞 old x".to_string(), "old".to_string()];
+        assert_eq!(
+            apply_text_edits(&lines, &parsed[0].1),
+            ["é𝄞 new x", "// old"]
+        );
+        let merge = LspTextEdit {
+            start: (0, 9),
+            end: (1, 0),
+            new_text: " ".to_string(),
+        };
+        assert_eq!(apply_text_edits(&lines, &[merge]), ["é𝄞 old x old"]);
+
+        let refs = json!([
+            { "uri": "file:///src/a.rs", "range": { "start": { "line": 2, "character": 4 },
+                                                      "end": { "line": 2, "character": 7 } } }
+        ]);
+        assert_eq!(parse_locations(&refs), [(PathBuf::from("/src/a.rs"), 2, 4)]);
+    }
+
+    #[test]
+    fn sync_kind_reads_number_or_options() {
+        let number = json!({ "capabilities": { "textDocumentSync": 2 } });
+        let options = json!({ "capabilities": { "textDocumentSync": { "change": 1 } } });
+        assert_eq!(sync_kind(&number), Some(2));
+        assert_eq!(sync_kind(&options), Some(1));
+        assert_eq!(sync_kind(&json!({ "capabilities": {} })), None);
+    }
 }
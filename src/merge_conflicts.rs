@@ -0,0 +1,156 @@
+//! Merge conflict blocks left in a file by git: finding them and resolving
+//! one by keeping either side or both.
+
+/// One `<<<<<<<` ... `>>>>>>>` block, by line index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ConflictRegion {
+    /// The `<<<<<<<` line; our side follows it.
+    pub(crate) start: usize,
+    /// The `|||||||` line before the common ancestor, with
+    /// `merge.conflictStyle = diff3`.
+    pub(crate) base: Option<usize>,
+    /// The `=======` line; their side follows it.
+    pub(crate) separator: usize,
+    /// The `>>>>>>>` line.
+    pub(crate) end: usize,
+}
+
+impl ConflictRegion {
+    pub(crate) fn contains(&self, row: usize) -> bool {
+        (self.start..=self.end).contains(&row)
+    }
+
+    pub(crate) fn is_marker(&self, row: usize) -> bool {
+        row == self.start || row == self.separator || row == self.end || self.base == Some(row)
+    }
+
+    /// Lines of our side.
+    pub(crate) fn ours(&self) -> std::ops::Range<usize> {
+        self.start + 1..self.base.unwrap_or(self.separator)
+    }
+
+    /// Lines of their side.
+    pub(crate) fn theirs(&self) -> std::ops::Range<usize> {
+        self.separator + 1..self.end
+    }
+}
+
+/// Which side of a conflict to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConflictChoice {
+    Ours,
+    Theirs,
+    Both,
+}
+
+impl ConflictChoice {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Ours => "ours",
+            Self::Theirs => "theirs",
+            Self::Both => "both",
+        }
+    }
+}
+
+fn is_marker(line: &str, marker: &str) -> bool {
+    line.strip_prefix(marker)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+}
+
+/// The conflict blocks of `lines`, top to bottom. A block missing its
+/// separator or end marker is not one.
+pub(crate) fn find_conflicts(lines: &[String]) -> Vec<ConflictRegion> {
+    let mut conflicts = Vec::new();
+    let mut start = None;
+    let mut base = None;
+    let mut separator = None;
+    for (row, line) in lines.iter().enumerate() {
+        if is_marker(line, "<<<<<<<") {
+            (start, base, separator) = (Some(row), None, None);
+        } else if start.is_none() {
+            continue;
+        } else if is_marker(line, "|||||||") && base.is_none() && separator.is_none() {
+            base = Some(row);
+        } else if line == "=======" && separator.is_none() {
+            separator = Some(row);
+        } else if is_marker(line, ">>>>>>>") {
+            if let (Some(start), Some(separator)) = (start, separator) {
+                conflicts.push(ConflictRegion {
+                    start,
+                    base,
+                    separator,
+                    end: row,
+                });
+            }
+            (start, base, separator) = (None, None, None);
+        }
+    }
+    conflicts
+}
+
+/// `lines` with `region` replaced by the side or sides `choice` keeps.
+pub(crate) fn resolve_conflict(
+    lines: &[String],
+    region: &ConflictRegion,
+    choice: ConflictChoice,
+) -> Vec<String> {
+    let mut out = lines[..region.start].to_vec();
+    if choice != ConflictChoice::Theirs {
+        out.extend_from_slice(&lines[region.ours()]);
+    }
+    if choice != ConflictChoice::Ours {
+        out.extend_from_slice(&lines[region.theirs()]);
+    }
+    out.extend_from_slice(&lines[region.end + 1..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn finds_and_resolves_conflicts_including_diff3_bases() {
+        let text = lines(
+            "a\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> feature\nb\n\
+             <<<<<<< HEAD\nmine\n||||||| base\nold\n=======\nyours\n>>>>>>> other\n\
+             <<<<<<< unterminated\nx\n",
+        );
+        let conflicts = find_conflicts(&text);
+        assert_eq!(
+            conflicts,
+            [
+                ConflictRegion {
+                    start: 1,
+                    base: None,
+                    separator: 3,
+                    end: 5,
+                },
+                ConflictRegion {
+                    start: 7,
+                    base: Some(9),
+                    separator: 11,
+                    end: 13,
+                },
+            ]
+        );
+        let first = &conflicts[0];
+        assert_eq!(
+            resolve_conflict(&text, first, ConflictChoice::Ours)[..3],
+            ["a", "ours", "b"]
+        );
+        assert_eq!(
+            resolve_conflict(&text, first, ConflictChoice::Both)[..4],
+            ["a", "ours", "theirs", "b"]
+        );
+        let second = resolve_conflict(&text, &conflicts[1], ConflictChoice::Ours);
+        assert_eq!(second[7..9], ["mine", "<<<<<<< unterminated"]);
+        let theirs = resolve_conflict(&text, &conflicts[1], ConflictChoice::Theirs);
+        assert_eq!(theirs[7], "yours");
+    }
+}
@@ -0,0 +1,240 @@
+//! Symbols read off the fold structure: a fold whose first line declares
+//! something (`fn`, `impl`, `class`, `def`, ...) is that symbol's body.
+
+use crate::tab::FoldRange;
+
+/// A declaration spanning `row..=end`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Symbol {
+    pub(crate) row: usize,
+    pub(crate) end: usize,
+    pub(crate) label: String,
+}
+
+/// Words that may come before the declaring keyword.
+const MODIFIERS: &[&str] = &[
+    "pub",
+    "async",
+    "unsafe",
+    "extern",
+    "export",
+    "default",
+    "static",
+    "abstract",
+    "public",
+    "private",
+    "protected",
+    "override",
+    "final",
+    "inline",
+    "virtual",
+];
+
+/// Keywords whose symbol is shown by name alone.
+const FUNCTION_KEYWORDS: &[&str] = &["fn", "def", "function", "func"];
+
+/// Keywords shown together with the name, as in `impl App`.
+const TYPE_KEYWORDS: &[&str] = &[
+    "impl",
+    "struct",
+    "enum",
+    "trait",
+    "mod",
+    "union",
+    "class",
+    "interface",
+    "module",
+    "namespace",
+];
+
+fn identifier(s: &str) -> &str {
+    let end = s
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .unwrap_or(s.len());
+    &s[..end]
+}
+
+/// How a line declaring a symbol shows in breadcrumbs, e.g. `impl App` or
+/// `handle_key`; `None` for any other line.
+pub(crate) fn symbol_label(line: &str) -> Option<String> {
+    let mut rest = line.trim_start();
+    loop {
+        let word = identifier(rest);
+        if !MODIFIERS.contains(&word) {
+            break;
+        }
+        rest = rest[word.len()..].trim_start();
+        // `pub(crate)`, `extern "C"`
+        if let Some(inner) = rest.strip_prefix('(') {
+            rest = inner.split_once(')')?.1.trim_start();
+        } else if let Some(inner) = rest.strip_prefix('"') {
+            rest = inner.split_once('"')?.1.trim_start();
+        }
+    }
+    let keyword = identifier(rest);
+    let after = &rest[keyword.len()..];
+    if !after.starts_with([' ', '<', '(']) {
+        return None;
+    }
+    if FUNCTION_KEYWORDS.contains(&keyword) {
+        let mut after = after.trim_start();
+        // Go methods: `func (r *Reader) Read(`
+        if keyword == "func"
+            && let Some(receiver) = after.strip_prefix('(')
+        {
+            after = receiver.split_once(')')?.1.trim_start();
+        }
+        let name = identifier(after);
+        return (!name.is_empty()).then(|| name.to_string());
+    }
+    if !TYPE_KEYWORDS.contains(&keyword) {
+        return None;
+    }
+    if keyword == "impl" {
+        let head = after.split(['{', ';']).next().unwrap_or_default();
+        let head = head.split(" where").next().unwrap_or_default().trim_end();
+        return (!head.trim().is_empty()).then(|| format!("impl{head}"));
+    }
+    let name = identifier(after.trim_start());
+    (!name.is_empty()).then(|| format!("{keyword} {name}"))
+}
+
+/// Every line of `lines` declaring a symbol, ending where its widest fold
+/// ends (or on the same line when it has no body).
+pub(crate) fn document_symbols(lines: &[String], folds: &[FoldRange]) -> Vec<Symbol> {
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(row, line)| {
+            let label = symbol_label(line)?;
+            let end = folds
+                .iter()
+                .filter(|f| f.start_line == row)
+                .map(|f| f.end_line)
+                .max()
+                .unwrap_or(row);
+            Some(Symbol { row, end, label })
+        })
+        .collect()
+}
+
+/// The symbols containing `row`, outermost first. Only the folds around
+/// `row` are looked at, so this is cheap enough to run on every frame.
+pub(crate) fn symbol_path(lines: &[String], folds: &[FoldRange], row: usize) -> Vec<Symbol> {
+    let mut path: Vec<Symbol> = Vec::new();
+    for fold in folds
+        .iter()
+        .filter(|f| f.start_line <= row && row <= f.end_line)
+    {
+        let Some(label) = lines.get(fold.start_line).and_then(|l| symbol_label(l)) else {
+            continue;
+        };
+        match path.iter_mut().find(|s| s.row == fold.start_line) {
+            Some(symbol) => symbol.end = symbol.end.max(fold.end_line),
+            None => path.push(Symbol {
+                row: fold.start_line,
+                end: fold.end_line,
+                label,
+            }),
+        }
+    }
+    if !path.iter().any(|s| s.row == row)
+        && let Some(label) = lines.get(row).and_then(|l| symbol_label(l))
+    {
+        path.push(Symbol {
+            row,
+            end: row,
+            label,
+        });
+    }
+    path.sort_by_key(|s| s.row);
+    path
+}
+
+/// The symbols directly inside `within` (a symbol's `(row, end)`), or the
+/// top-level ones when it is `None`.
+pub(crate) fn child_symbols(symbols: &[Symbol], within: Option<(usize, usize)>) -> Vec<&Symbol> {
+    let inside = |s: &Symbol, (row, end): (usize, usize)| s.row > row && s.end <= end;
+    symbols
+        .iter()
+        .filter(|s| within.is_none_or(|w| inside(s, w)))
+        .filter(|s| {
+            !symbols.iter().any(|outer| {
+                outer.row != s.row
+                    && inside(s, (outer.row, outer.end))
+                    && within.is_none_or(|w| inside(outer, w))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::SyntaxLang;
+    use crate::util::compute_fold_ranges;
+
+    #[test]
+    fn symbol_labels_name_declarations_only() {
+        assert_eq!(
+            symbol_label("    pub(crate) fn handle_key(&mut self) {").as_deref(),
+            Some("handle_key")
+        );
+        assert_eq!(
+            symbol_label("impl<T> From<T> for App where T: X {").as_deref(),
+            Some("impl<T> From<T> for App")
+        );
+        assert_eq!(
+            symbol_label("pub struct Tab<'a> {").as_deref(),
+            Some("struct Tab")
+        );
+        assert_eq!(
+            symbol_label("class Parser(Base):").as_deref(),
+            Some("class Parser")
+        );
+        assert_eq!(
+            symbol_label("func (r *Reader) Read(p []byte) {").as_deref(),
+            Some("Read")
+        );
+        assert_eq!(
+            symbol_label("export default function main() {").as_deref(),
+            Some("main")
+        );
+        assert_eq!(symbol_label("    if fn_count > 0 {"), None);
+        assert_eq!(symbol_label("// fn commented() {"), None);
+    }
+
+    #[test]
+    fn symbol_path_and_children_follow_nesting() {
+        let lines: Vec<String> = [
+            "struct App;",
+            "impl App {",
+            "    fn new() -> Self {",
+            "        App",
+            "    }",
+            "    fn run(&self) {",
+            "        if true {",
+            "            go();",
+            "        }",
+            "    }",
+            "}",
+            "fn main() {}",
+        ]
+        .map(String::from)
+        .to_vec();
+        let (folds, _) = compute_fold_ranges(&lines, SyntaxLang::Rust);
+        let labels = |path: Vec<Symbol>| path.into_iter().map(|s| s.label).collect::<Vec<_>>();
+        assert_eq!(labels(symbol_path(&lines, &folds, 7)), ["impl App", "run"]);
+        assert_eq!(labels(symbol_path(&lines, &folds, 11)), ["main"]);
+
+        let symbols = document_symbols(&lines, &folds);
+        let rows = |within| {
+            child_symbols(&symbols, within)
+                .into_iter()
+                .map(|s| s.row)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rows(None), [0, 1, 11]);
+        assert_eq!(rows(Some((1, 10))), [2, 5]);
+    }
+}
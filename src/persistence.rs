@@ -1,12 +1,32 @@
 use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::hash::{Hash, Hasher};
 use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::settings::SettingFile;
+use crate::syntax::SyntaxLang;
+use crate::types::PromptMode;
 
 const STATE_FILE_REL: &str = "lazyide/state.json";
+const FILETYPES_FILE: &str = "filetypes.json";
+const LSP_SETTINGS_FILE: &str = "lsp.json";
+const STATUS_SETTINGS_FILE: &str = "status.json";
+const EDITOR_SETTINGS_FILE: &str = "editor.json";
+const WATCH_SETTINGS_FILE: &str = "watch.json";
+const FOLLOW_FILE: &str = "follow.json";
+/// Project-local overrides for `lsp.json`, relative to the project root.
+const PROJECT_LSP_SETTINGS_FILE: &str = ".lazyide/lsp.json";
+const APP_DIR: &str = "lazyide";
+
+/// Set once from `--state-dir`; takes precedence over the platform default.
+static STATE_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
 
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct PersistedState {
@@ -15,18 +35,554 @@ pub(crate) struct PersistedState {
     pub(crate) files_pane_width: Option<u16>,
     #[serde(default)]
     pub(crate) word_wrap: Option<bool>,
+    #[serde(default)]
+    pub(crate) show_ignored: Option<bool>,
+    /// `false` falls back to ASCII tree markers for terminals without a
+    /// nerd font.
+    #[serde(default)]
+    pub(crate) nerd_font_icons: Option<bool>,
+    #[serde(default)]
+    pub(crate) search_history: SearchHistory,
+    /// Language modes picked with "Set Language Mode", by absolute path.
+    #[serde(default)]
+    pub(crate) language_overrides: BTreeMap<PathBuf, SyntaxLang>,
+    /// Answers to the trust prompt, by project root. rust-analyzer builds
+    /// the project (build scripts, proc macros), so it only starts in
+    /// trusted ones.
+    #[serde(default)]
+    pub(crate) trusted_projects: BTreeMap<PathBuf, bool>,
+    /// Files and folders pinned to the top of the tree, by project root.
+    #[serde(default)]
+    pub(crate) pinned_paths: BTreeMap<PathBuf, Vec<PathBuf>>,
+    /// Folders added to the workspace, by project root.
+    #[serde(default)]
+    pub(crate) workspace_folders: BTreeMap<PathBuf, Vec<PathBuf>>,
+    /// Bookmarked rows by project root and file.
+    #[serde(default)]
+    pub(crate) bookmarks: BTreeMap<PathBuf, BTreeMap<PathBuf, BTreeSet<usize>>>,
+    /// Files opened by project root, most recent first.
+    #[serde(default)]
+    pub(crate) recent_files: BTreeMap<PathBuf, Vec<PathBuf>>,
+    /// Project roots opened, most recent first.
+    #[serde(default)]
+    pub(crate) recent_projects: Vec<PathBuf>,
+    /// Labels of commands run from the palette, most recent first.
+    #[serde(default)]
+    pub(crate) recent_commands: Vec<String>,
+}
+
+/// Recent Find, Replace and project-search entries, newest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct SearchHistory {
+    #[serde(default)]
+    pub(crate) find: Vec<String>,
+    #[serde(default)]
+    pub(crate) replace: Vec<String>,
+    #[serde(default)]
+    pub(crate) project: Vec<String>,
+}
+
+impl SearchHistory {
+    const MAX_ENTRIES: usize = 50;
+
+    /// The history a prompt cycles through with Up/Down, if it has one.
+    pub(crate) fn entries(&self, mode: &PromptMode) -> Option<&[String]> {
+        match mode {
+            PromptMode::FindInFile => Some(&self.find),
+            PromptMode::FindInProject => Some(&self.project),
+            PromptMode::ReplaceInFile { .. } | PromptMode::ReplaceInProject => Some(&self.replace),
+            _ => None,
+        }
+    }
+
+    /// Move `value` to the front of the history for `mode`, dropping duplicates.
+    pub(crate) fn record(&mut self, mode: &PromptMode, value: &str) {
+        let list = match mode {
+            PromptMode::FindInFile => &mut self.find,
+            PromptMode::FindInProject => &mut self.project,
+            PromptMode::ReplaceInFile { .. } | PromptMode::ReplaceInProject => &mut self.replace,
+            _ => return,
+        };
+        list.retain(|v| v != value);
+        list.insert(0, value.to_string());
+        list.truncate(Self::MAX_ENTRIES);
+    }
+}
+
+/// User overrides for binary detection, read from `filetypes.json` next to
+/// `state.json`. Extensions are matched case-insensitively, without the dot.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct FileTypeConfig {
+    /// Always refuse to open these as text (added to the built-in list).
+    #[serde(default)]
+    pub(crate) binary_extensions: Vec<String>,
+    /// Always open these as text, skipping every binary check.
+    #[serde(default)]
+    pub(crate) text_extensions: Vec<String>,
+    /// Files larger than this are refused; defaults to 20 MB.
+    #[serde(default)]
+    pub(crate) max_text_bytes: Option<u64>,
+    /// Files larger than this open in large file mode; defaults to 5 MB.
+    #[serde(default)]
+    pub(crate) large_file_bytes: Option<u64>,
+}
+
+pub(crate) fn load_filetype_config() -> FileTypeConfig {
+    load_config_file(FILETYPES_FILE)
+}
+
+/// rust-analyzer settings, read from `lsp.json` next to `state.json` with
+/// the project's `.lazyide/lsp.json` laid over it. Served to the server's
+/// `workspace/configuration` requests and sent as `initializationOptions`.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub(crate) struct LspSettings {
+    /// `cargo.features`: features to enable when analyzing the workspace.
+    #[serde(default)]
+    pub(crate) cargo_features: Vec<String>,
+    /// Enable every feature (`cargo.features = "all"`).
+    #[serde(default)]
+    pub(crate) all_features: bool,
+    /// `check.command`: `check` by default, `clippy` for lints on save.
+    #[serde(default)]
+    pub(crate) check_command: Option<String>,
+    /// `cargo.targetDir`: a separate target directory, so analysis does not
+    /// block `cargo build`.
+    #[serde(default)]
+    pub(crate) target_dir: Option<String>,
+    /// `procMacro.enable`: expand proc macros (on by default in the server).
+    #[serde(default)]
+    pub(crate) proc_macros: Option<bool>,
+    /// Run `cargo check` after saving a Rust file for diagnostics in every
+    /// file. Unset: only while rust-analyzer is not running.
+    #[serde(default)]
+    pub(crate) cargo_check_on_save: Option<bool>,
+    /// Extra command-line arguments for each language's server, keyed by
+    /// language mode (`rust`). Applied when the server starts.
+    #[serde(default)]
+    pub(crate) server_args: BTreeMap<String, Vec<String>>,
+    /// Any other rust-analyzer settings, passed through as-is. The named
+    /// fields above take precedence.
+    #[serde(default)]
+    pub(crate) rust_analyzer: Option<Value>,
+    /// Env file in the project root whose variables rust-analyzer (and the
+    /// build scripts it runs) sees; `.env` when unset, `""` for none.
+    #[serde(default)]
+    pub(crate) env_file: Option<String>,
+}
+
+impl LspSettings {
+    pub(crate) fn env_file_name(&self) -> Option<&str> {
+        match self.env_file.as_deref() {
+            None => Some(".env"),
+            Some("") => None,
+            Some(name) => Some(name),
+        }
+    }
+
+    pub(crate) fn server_args_for(&self, lang: &str) -> &[String] {
+        self.server_args.get(lang).map_or(&[], Vec::as_slice)
+    }
+
+    /// The `rust-analyzer` configuration section.
+    pub(crate) fn rust_analyzer_section(&self) -> Value {
+        let mut section = match &self.rust_analyzer {
+            Some(value @ Value::Object(_)) => value.clone(),
+            _ => json!({}),
+        };
+        let features = if self.all_features {
+            Some(json!("all"))
+        } else if !self.cargo_features.is_empty() {
+            Some(json!(self.cargo_features))
+        } else {
+            None
+        };
+        let check_command = self.check_command.clone().map(Value::from);
+        let target_dir = self.target_dir.clone().map(Value::from);
+        let proc_macros = self.proc_macros.map(Value::from);
+        for (group, key, value) in [
+            ("cargo", "features", features),
+            ("cargo", "targetDir", target_dir),
+            ("check", "command", check_command),
+            ("procMacro", "enable", proc_macros),
+        ] {
+            let Some(value) = value else {
+                continue;
+            };
+            if !section[group].is_object() {
+                section[group] = json!({});
+            }
+            section[group][key] = value;
+        }
+        section
+    }
+}
+
+pub(crate) fn project_lsp_settings_path(root: &Path) -> PathBuf {
+    root.join(PROJECT_LSP_SETTINGS_FILE)
+}
+
+pub(crate) fn lsp_settings_path() -> Option<PathBuf> {
+    config_file_path(LSP_SETTINGS_FILE)
+}
+
+/// The user's `lsp.json` with the project's `.lazyide/lsp.json` merged over
+/// it key by key, so a project can set just its features or target dir.
+pub(crate) fn load_lsp_settings(root: &Path) -> LspSettings {
+    let mut merged = lsp_settings_path()
+        .and_then(|path| read_json_file(&path))
+        .unwrap_or_else(|| json!({}));
+    if let Some(project) = read_json_file(&project_lsp_settings_path(root)) {
+        merge_json(&mut merged, project);
+    }
+    serde_json::from_value(merged).unwrap_or_else(|err| {
+        eprintln!("lazyide: invalid LSP settings: {err}");
+        LspSettings::default()
+    })
+}
+
+fn read_json_file(path: &Path) -> Option<Value> {
+    let raw = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&raw)
+        .map_err(|err| eprintln!("lazyide: invalid {}: {err}", path.display()))
+        .ok()
+}
+
+/// Lay `overlay` over `base`: objects merge recursively, anything else
+/// replaces what was there.
+fn merge_json(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge_json(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Status-line message timing, read from `status.json` next to `state.json`.
+/// All durations are in milliseconds; a timeout of 0 keeps the message until
+/// something replaces it. Errors never time out.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct StatusSettings {
+    /// How long an info message stays before the keybinding hints return.
+    pub(crate) info_timeout_ms: u64,
+    pub(crate) warn_timeout_ms: u64,
+    /// How long a warning is shown before an info message may replace it.
+    pub(crate) warn_min_display_ms: u64,
+}
+
+impl Default for StatusSettings {
+    fn default() -> Self {
+        Self {
+            info_timeout_ms: 5_000,
+            warn_timeout_ms: 10_000,
+            warn_min_display_ms: 1_500,
+        }
+    }
+}
+
+pub(crate) fn load_status_settings() -> StatusSettings {
+    load_config_file(STATUS_SETTINGS_FILE)
+}
+
+/// Indentation settings, read from `editor.json` next to `state.json`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct EditorSettings {
+    /// Columns per indent level; Tab pads to the next multiple.
+    pub(crate) tab_width: u8,
+    /// Indent with spaces rather than a tab character.
+    pub(crate) insert_spaces: bool,
+    /// Re-indent multi-line pastes to the cursor's indentation.
+    pub(crate) reindent_on_paste: bool,
+    /// With word wrap on, Up/Down/Home/End follow wrapped rows rather
+    /// than whole lines.
+    pub(crate) visual_line_navigation: bool,
+    /// Gutter columns; each can be hidden to give narrow terminals the
+    /// width back.
+    pub(crate) gutter_line_numbers: bool,
+    pub(crate) gutter_folds: bool,
+    pub(crate) gutter_diagnostics: bool,
+    pub(crate) gutter_git: bool,
+    /// What an action that cannot apply does besides its status hint.
+    pub(crate) invalid_action_feedback: ActionFeedback,
+}
+
+/// Feedback for an action that does nothing where it was tried, such as
+/// folding where there is no block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ActionFeedback {
+    /// Flash the border of the focused pane.
+    #[default]
+    Flash,
+    /// Ring the terminal bell.
+    Bell,
+    Both,
+    /// Only the status hint.
+    Off,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        Self {
+            tab_width: 4,
+            insert_spaces: true,
+            reindent_on_paste: true,
+            visual_line_navigation: true,
+            gutter_line_numbers: true,
+            gutter_folds: true,
+            gutter_diagnostics: true,
+            gutter_git: true,
+            invalid_action_feedback: ActionFeedback::Flash,
+        }
+    }
+}
+
+impl EditorSettings {
+    /// One level of indentation as inserted by Tab on a selection.
+    pub(crate) fn indent_unit(&self) -> String {
+        if self.insert_spaces {
+            " ".repeat(self.tab_width.max(1) as usize)
+        } else {
+            "\t".to_string()
+        }
+    }
+}
+
+pub(crate) fn load_editor_settings() -> EditorSettings {
+    load_config_file(EDITOR_SETTINGS_FILE)
+}
+
+/// External-change detection, read from `watch.json` next to `state.json`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct WatchSettings {
+    /// Folders where filesystem events are unreliable (NFS/SMB mounts),
+    /// mapped to a polling interval in milliseconds. A project inside one
+    /// of them is watched by comparing mtimes on that interval instead.
+    pub(crate) poll_roots: BTreeMap<PathBuf, u64>,
+}
+
+impl WatchSettings {
+    /// How often to poll `root`, if it lies in one of `poll_roots`; the
+    /// most specific entry wins.
+    pub(crate) fn poll_interval_for(&self, root: &Path) -> Option<Duration> {
+        self.poll_roots
+            .iter()
+            .filter(|(dir, _)| root.starts_with(dir))
+            .max_by_key(|(dir, _)| dir.components().count())
+            .map(|(_, &ms)| Duration::from_millis(ms.max(100)))
+    }
+}
+
+pub(crate) fn load_watch_settings() -> WatchSettings {
+    load_config_file(WATCH_SETTINGS_FILE)
+}
+
+fn config_file_path(name: &str) -> Option<PathBuf> {
+    state_file_path().and_then(|p| p.parent().map(|d| d.join(name)))
+}
+
+/// Read an optional JSON config file from the state directory. A missing
+/// file gives the defaults; an invalid one is reported and ignored.
+fn load_config_file<T: Default + for<'de> Deserialize<'de>>(name: &str) -> T {
+    let Some(path) = config_file_path(name) else {
+        return T::default();
+    };
+    let Ok(raw) = fs::read_to_string(&path) else {
+        return T::default();
+    };
+    serde_json::from_str(&raw).unwrap_or_else(|err| {
+        eprintln!("lazyide: invalid {}: {err}", path.display());
+        T::default()
+    })
+}
+
+/// Store a value from the settings editor in the file it belongs to.
+/// `state.json` settings are saved with the rest of the app state instead.
+pub(crate) fn save_setting(file: SettingFile, key: &str, value: Value) -> io::Result<()> {
+    let name = match file {
+        SettingFile::State => return Ok(()),
+        SettingFile::Editor => EDITOR_SETTINGS_FILE,
+        SettingFile::Status => STATUS_SETTINGS_FILE,
+        SettingFile::FileTypes => FILETYPES_FILE,
+        SettingFile::Lsp => LSP_SETTINGS_FILE,
+    };
+    save_config_value(name, key, value)
+}
+
+/// Set `key` in a JSON config file in the state directory, keeping its
+/// other keys; `null` removes the key so the default applies.
+fn save_config_value(name: &str, key: &str, value: Value) -> io::Result<()> {
+    let Some(path) = config_file_path(name) else {
+        return Ok(());
+    };
+    let mut config = match fs::read_to_string(&path) {
+        Ok(raw) => serde_json::from_str::<Value>(&raw)
+            .map_err(|e| io::Error::other(format!("{name} is not valid JSON: {e}")))?,
+        Err(_) => json!({}),
+    };
+    let Some(object) = config.as_object_mut() else {
+        return Err(io::Error::other(format!("{name} is not a JSON object")));
+    };
+    if value.is_null() {
+        object.remove(key);
+    } else {
+        object.insert(key.to_string(), value);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let raw = serde_json::to_string_pretty(&config)
+        .map_err(|e| io::Error::other(format!("serialize {name}: {e}")))?;
+    fs::write(path, raw + "\n")
 }
 
 pub(crate) fn autosave_path_for(path: &Path) -> PathBuf {
     let mut hasher = DefaultHasher::new();
     path.hash(&mut hasher);
     let hash = hasher.finish();
-    let base = state_file_path()
-        .and_then(|p| p.parent().map(|pp| pp.to_path_buf()))
-        .unwrap_or_else(|| PathBuf::from("."));
+    let base = state_dir().unwrap_or_else(|| PathBuf::from("."));
     base.join("autosave").join(format!("{hash:016x}.autosave"))
 }
 
+/// Undo snapshots of a closed file, kept next to its autosave.
+pub(crate) fn undo_history_path_for(path: &Path) -> PathBuf {
+    autosave_path_for(path).with_extension("undo.json")
+}
+
+/// Earlier versions of a file, saved when its tab closes so undo can step
+/// back past the reopen.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct UndoHistory {
+    /// Hash of the text the snapshots lead up to; a file changed since
+    /// then drops its history.
+    pub(crate) content_hash: u64,
+    /// Oldest first.
+    pub(crate) snapshots: Vec<String>,
+}
+
+pub(crate) fn content_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub(crate) fn save_undo_history(path: &Path, history: &UndoHistory) -> io::Result<()> {
+    let target = undo_history_path_for(path);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let raw = serde_json::to_string(history)
+        .map_err(|e| io::Error::other(format!("serialize undo history: {e}")))?;
+    fs::write(target, raw)
+}
+
+/// Snapshots saved for `path`, if they were taken when it held `text`.
+pub(crate) fn load_undo_history(path: &Path, text: &str) -> Vec<String> {
+    let Ok(raw) = fs::read_to_string(undo_history_path_for(path)) else {
+        return Vec::new();
+    };
+    match serde_json::from_str::<UndoHistory>(&raw) {
+        Ok(history) if history.content_hash == content_hash(text) => history.snapshots,
+        _ => Vec::new(),
+    }
+}
+
+/// What Follow Mode broadcasts: the active buffer and cursor, rewritten as
+/// they change so another session can mirror them read-only.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct FollowSnapshot {
+    pub(crate) root: PathBuf,
+    pub(crate) path: PathBuf,
+    /// Zero-based `(row, col)`, col in chars.
+    pub(crate) cursor: (usize, usize),
+    pub(crate) text: String,
+}
+
+/// `follow.json` in the state dir, written by the broadcasting session and
+/// watched by followers.
+pub(crate) fn follow_file_path() -> PathBuf {
+    let base = state_dir().unwrap_or_else(|| PathBuf::from("."));
+    base.join(FOLLOW_FILE)
+}
+
+/// Replace `target` in one step so a follower never reads half a snapshot.
+pub(crate) fn write_follow_snapshot(target: &Path, raw: &str) -> io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = target.with_extension("json.tmp");
+    fs::write(&tmp, raw)?;
+    fs::rename(tmp, target)
+}
+
+pub(crate) fn set_state_dir_override(dir: PathBuf) {
+    let _ = STATE_DIR_OVERRIDE.set(dir);
+}
+
+/// Directory for machine-local data that should survive restarts but is not
+/// configuration (autosaves, history): `$XDG_STATE_HOME/lazyide`, falling back
+/// to `~/.local/state/lazyide`, `~/Library/Application Support/lazyide` on
+/// macOS and `%LOCALAPPDATA%\lazyide` on Windows.
+pub(crate) fn state_dir() -> Option<PathBuf> {
+    if let Some(dir) = STATE_DIR_OVERRIDE.get() {
+        return Some(dir.clone());
+    }
+    state_dir_from_env(|key| std::env::var(key).ok())
+}
+
+pub(crate) fn state_dir_from_env(get: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let var = |key: &str| get(key).filter(|v| !v.is_empty()).map(PathBuf::from);
+    if let Some(xdg) = var("XDG_STATE_HOME") {
+        return Some(xdg.join(APP_DIR));
+    }
+    if let Some(local) = var("LOCALAPPDATA") {
+        return Some(local.join(APP_DIR));
+    }
+    let home = var("HOME")?;
+    if cfg!(target_os = "macos") {
+        Some(home.join("Library/Application Support").join(APP_DIR))
+    } else {
+        Some(home.join(".local/state").join(APP_DIR))
+    }
+}
+
+/// Move autosaves written by older versions (under the config dir) into the
+/// state dir. Only runs when the new location does not exist yet.
+pub(crate) fn migrate_legacy_state() -> io::Result<()> {
+    let Some(config_dir) = state_file_path().and_then(|p| p.parent().map(Path::to_path_buf)) else {
+        return Ok(());
+    };
+    let Some(state) = state_dir() else {
+        return Ok(());
+    };
+    migrate_dir(&config_dir.join("autosave"), &state.join("autosave"))
+}
+
+pub(crate) fn migrate_dir(old: &Path, new: &Path) -> io::Result<()> {
+    if !old.is_dir() || new.exists() || old == new {
+        return Ok(());
+    }
+    if let Some(parent) = new.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(old, new).is_ok() {
+        return Ok(());
+    }
+    // Different filesystems: copy the flat directory, then drop the original.
+    fs::create_dir_all(new)?;
+    for entry in fs::read_dir(old)?.filter_map(Result::ok) {
+        if entry.file_type().is_ok_and(|t| t.is_file()) {
+            fs::copy(entry.path(), new.join(entry.file_name()))?;
+        }
+    }
+    fs::remove_dir_all(old)
+}
+
 pub(crate) fn state_file_path() -> Option<PathBuf> {
     if let Ok(xdg) = std::env::var("XDG_CONFIG_HOME")
         && !xdg.is_empty()
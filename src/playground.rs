@@ -0,0 +1,308 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+
+use tempfile::TempDir;
+
+/// Rustdoc attributes that still mark an untagged doc fence as Rust.
+const RUSTDOC_ATTRS: &[&str] = &[
+    "should_panic",
+    "no_run",
+    "edition2015",
+    "edition2018",
+    "edition2021",
+    "edition2024",
+];
+
+/// A fenced code block around the cursor, from Markdown or from `///` and
+/// `//!` doc comments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CodeBlock {
+    /// Info string after the opening fence, e.g. `rust,no_run`.
+    pub(crate) info: String,
+    pub(crate) code: String,
+    /// Whether the fence sits in a doc comment, where an untagged block
+    /// is Rust.
+    pub(crate) doc: bool,
+}
+
+impl CodeBlock {
+    fn attrs(&self) -> impl Iterator<Item = &str> {
+        self.info
+            .split([',', ' '])
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+    }
+
+    /// Whether the block is Rust that is meant to compile.
+    pub(crate) fn is_rust(&self) -> bool {
+        let mut attrs = self.attrs().peekable();
+        if attrs.peek().is_none() {
+            return self.doc;
+        }
+        let mut rust = self.doc;
+        for attr in attrs {
+            match attr {
+                "rust" | "rs" => rust = true,
+                a if RUSTDOC_ATTRS.contains(&a) => {}
+                _ => return false,
+            }
+        }
+        rust
+    }
+
+    /// `no_run` blocks are built but not run.
+    pub(crate) fn runs(&self) -> bool {
+        !self.attrs().any(|a| a == "no_run")
+    }
+}
+
+/// Split a doc comment prefix off `line`: `/// text` gives `(true, "text")`.
+fn strip_doc_prefix(line: &str) -> (bool, &str) {
+    let trimmed = line.trim_start();
+    match trimmed
+        .strip_prefix("///")
+        .or_else(|| trimmed.strip_prefix("//!"))
+    {
+        Some(rest) => (true, rest.strip_prefix(' ').unwrap_or(rest)),
+        None => (false, line),
+    }
+}
+
+/// The fenced block whose fences enclose `row`, fences included.
+pub(crate) fn code_block_at(lines: &[String], row: usize) -> Option<CodeBlock> {
+    let mut open: Option<(usize, bool, String)> = None;
+    for (i, line) in lines.iter().enumerate() {
+        let (doc, text) = strip_doc_prefix(line);
+        let Some(info) = text.trim_start().strip_prefix("```") else {
+            continue;
+        };
+        match open.take() {
+            None if i > row => return None,
+            None => open = Some((i, doc, info.trim().to_string())),
+            // A fence in a different comment style is block content.
+            Some(start) if start.1 != doc => open = Some(start),
+            Some((start, doc, info)) if i >= row => {
+                let code = lines[start + 1..i]
+                    .iter()
+                    .map(|l| strip_doc_prefix(l).1)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                return Some(CodeBlock { info, code, doc });
+            }
+            Some(_) => {}
+        }
+    }
+    None
+}
+
+/// `main.rs` for `code`: rustdoc's hidden `# ` lines are shown, unused
+/// warnings are off, and a block without `fn main` is wrapped in one.
+pub(crate) fn snippet_main_rs(code: &str) -> String {
+    let body: Vec<&str> = code
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed == "#" {
+                ""
+            } else {
+                trimmed.strip_prefix("# ").unwrap_or(line)
+            }
+        })
+        .collect();
+    let mut out = String::from("#![allow(unused)]\n");
+    if body.iter().any(|l| l.contains("fn main(")) {
+        for line in body {
+            out.push_str(line);
+            out.push('\n');
+        }
+    } else {
+        out.push_str("fn main() {\n");
+        for line in body {
+            if !line.is_empty() {
+                out.push_str("    ");
+            }
+            out.push_str(line);
+            out.push('\n');
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+/// Library crate name at `root`, if it has one, for snippets that use it.
+pub(crate) fn project_lib_name(root: &Path) -> Option<String> {
+    if !root.join("src/lib.rs").is_file() {
+        return None;
+    }
+    let manifest = fs::read_to_string(root.join("Cargo.toml")).ok()?;
+    let mut in_package = false;
+    for line in manifest.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package
+            && let Some((key, value)) = line.split_once('=')
+            && key.trim() == "name"
+        {
+            return Some(value.trim().trim_matches('"').to_string());
+        }
+    }
+    None
+}
+
+/// `Cargo.toml` for the scratch crate; when `code` refers to the project
+/// library, it depends on it by path.
+pub(crate) fn snippet_manifest(code: &str, root: &Path) -> String {
+    let mut out = String::from(
+        "[package]\nname = \"lazyide-snippet\"\nversion = \"0.0.0\"\nedition = \"2024\"\n\n\
+         [workspace]\n\n[dependencies]\n",
+    );
+    if let Some(name) = project_lib_name(root)
+        && code.contains(&format!("{}::", name.replace('-', "_")))
+    {
+        // A JSON string is a valid TOML basic string.
+        let path = serde_json::to_string(&root.to_string_lossy()).unwrap_or_default();
+        out.push_str(&format!("{name} = {{ path = {path} }}\n"));
+    }
+    out
+}
+
+/// Outcome of building, and unless `no_run` running, a snippet.
+#[derive(Debug)]
+pub(crate) struct SnippetRun {
+    pub(crate) success: bool,
+    pub(crate) exit_code: Option<i32>,
+    /// Compiler errors and the program's stderr, then its stdout.
+    pub(crate) output: String,
+}
+
+/// A scratch directory where this process builds its snippets, private to
+/// the user and removed when dropped. It is kept between runs so rebuilds
+/// are incremental.
+pub(crate) fn create_playground_dir() -> io::Result<TempDir> {
+    tempfile::Builder::new()
+        .prefix("lazyide-playground-")
+        .tempdir()
+}
+
+/// Write the scratch crate to `dir` and `cargo run` it (or `cargo build`
+/// when `run` is false).
+pub(crate) fn run_snippet(
+    dir: &Path,
+    manifest: &str,
+    main_rs: &str,
+    run: bool,
+) -> io::Result<SnippetRun> {
+    fs::create_dir_all(dir.join("src"))?;
+    fs::write(dir.join("Cargo.toml"), manifest)?;
+    fs::write(dir.join("src/main.rs"), main_rs)?;
+    let output = Command::new("cargo")
+        .arg(if run { "run" } else { "build" })
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(dir.join("Cargo.toml"))
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()?;
+    let mut text = String::from_utf8_lossy(&output.stderr).into_owned();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !text.is_empty() && !stdout.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str(&stdout);
+    Ok(SnippetRun {
+        success: output.status.success(),
+        exit_code: output.status.code(),
+        output: text,
+    })
+}
+
+/// Run [`run_snippet`] on a background thread, sending its result to `tx`.
+pub(crate) fn spawn_snippet_run(
+    dir: PathBuf,
+    manifest: String,
+    main_rs: String,
+    run: bool,
+    tx: Sender<io::Result<SnippetRun>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let _ = tx.send(run_snippet(&dir, &manifest, &main_rs, run));
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn finds_the_block_around_the_cursor() {
+        let md = lines(
+            "# Intro\n```sh\ncargo run\n```\n\n```rust\nlet x = 1;\nprintln!(\"{x}\");\n```\n",
+        );
+        assert_eq!(code_block_at(&md, 0), None);
+        assert_eq!(code_block_at(&md, 4), None);
+        let block = code_block_at(&md, 6).expect("rust block");
+        assert_eq!(block.code, "let x = 1;\nprintln!(\"{x}\");");
+        assert!(block.is_rust());
+        let block = code_block_at(&md, 1).expect("sh block");
+        assert!(!block.is_rust());
+
+        let src = lines(
+            "/// Adds one.\n///\n/// ```no_run\n/// # use demo::add_one;\n/// assert_eq!(add_one(1), 2);\n/// ```\npub fn add_one(x: i32) -> i32 { x + 1 }\n",
+        );
+        let block = code_block_at(&src, 4).expect("doc block");
+        assert_eq!(
+            block.code,
+            "# use demo::add_one;\nassert_eq!(add_one(1), 2);"
+        );
+        assert!(block.doc && block.is_rust() && !block.runs());
+        assert_eq!(code_block_at(&src, 6), None);
+    }
+
+    #[test]
+    fn wraps_snippets_without_main_and_unhides_lines() {
+        assert_eq!(
+            snippet_main_rs("# use std::fmt;\n#\n#[derive(Debug)]\nstruct A;"),
+            "#![allow(unused)]\nfn main() {\n    use std::fmt;\n\n    #[derive(Debug)]\n    struct A;\n}\n"
+        );
+        assert_eq!(
+            snippet_main_rs("fn main() {}"),
+            "#![allow(unused)]\nfn main() {}\n"
+        );
+    }
+
+    #[test]
+    fn depends_on_the_project_library_only_when_used() {
+        let tmp = tempdir().expect("tempdir");
+        fs::create_dir(tmp.path().join("src")).expect("mkdir");
+        fs::write(tmp.path().join("src/lib.rs"), "").expect("write");
+        fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[package]\nname = \"my-lib\"\nversion = \"0.1.0\"\n\n[dependencies]\nname = \"x\"\n",
+        )
+        .expect("write");
+        assert_eq!(project_lib_name(tmp.path()).as_deref(), Some("my-lib"));
+        let manifest = snippet_manifest("my_lib::go();", tmp.path());
+        assert!(manifest.contains("my-lib = { path = "));
+        assert!(!snippet_manifest("go();", tmp.path()).contains("my-lib"));
+    }
+
+    #[test]
+    fn each_playground_dir_is_separate_and_removed_on_drop() {
+        let first = create_playground_dir().expect("dir");
+        let second = create_playground_dir().expect("dir");
+        assert_ne!(first.path(), second.path());
+        let path = first.path().to_path_buf();
+        drop(first);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,178 @@
+use image::RgbImage;
+use image::imageops::FilterType;
+
+/// Images are decoded once and shrunk to fit this many pixels a side;
+/// the overlay samples the thumbnail down further to fit its area.
+const THUMBNAIL_PIXELS: u32 = 256;
+
+/// What a binary file is, from its leading magic bytes.
+pub(crate) fn describe_file_type(bytes: &[u8]) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "PNG image"),
+        (b"\xff\xd8\xff", "JPEG image"),
+        (b"GIF8", "GIF image"),
+        (b"BM", "BMP image"),
+        (b"%PDF", "PDF document"),
+        (b"PK\x03\x04", "ZIP archive"),
+        (b"\x1f\x8b", "gzip data"),
+        (b"\x28\xb5\x2f\xfd", "zstd data"),
+        (b"7z\xbc\xaf\x27\x1c", "7-zip archive"),
+        (b"\x7fELF", "ELF executable"),
+        (b"\xcf\xfa\xed\xfe", "Mach-O executable"),
+        (b"\xca\xfe\xba\xbe", "Mach-O universal binary or Java class"),
+        (b"MZ", "Windows executable"),
+        (b"\0asm", "WebAssembly module"),
+        (b"SQLite format 3\0", "SQLite database"),
+        (b"wOFF", "WOFF font"),
+        (b"wOF2", "WOFF2 font"),
+        (b"ID3", "MP3 audio"),
+        (b"OggS", "Ogg media"),
+        (b"fLaC", "FLAC audio"),
+    ];
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" {
+        return match &bytes[8..12] {
+            b"WEBP" => "WebP image",
+            b"WAVE" => "WAV audio",
+            b"AVI " => "AVI video",
+            _ => "RIFF data",
+        };
+    }
+    if bytes.len() >= 8 && &bytes[4..8] == b"ftyp" {
+        return "MP4/QuickTime media";
+    }
+    SIGNATURES
+        .iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+        .map_or("binary data", |(_, name)| name)
+}
+
+/// `bytes` as `12.3 KB`, `4.0 MB`, ...
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} bytes");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// A decoded PNG or JPEG: its real size and a small RGB thumbnail.
+pub(crate) struct ImagePreview {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) thumbnail: RgbImage,
+}
+
+/// Decode `bytes` if they are a PNG or JPEG image.
+pub(crate) fn decode_image_preview(bytes: &[u8]) -> Option<ImagePreview> {
+    if !matches!(describe_file_type(bytes), "PNG image" | "JPEG image") {
+        return None;
+    }
+    let image = image::load_from_memory(bytes).ok()?;
+    let (width, height) = (image.width(), image.height());
+    let thumbnail = if width > THUMBNAIL_PIXELS || height > THUMBNAIL_PIXELS {
+        image.resize(THUMBNAIL_PIXELS, THUMBNAIL_PIXELS, FilterType::Triangle)
+    } else {
+        image
+    };
+    Some(ImagePreview {
+        width,
+        height,
+        thumbnail: thumbnail.to_rgb8(),
+    })
+}
+
+/// `image` sampled to fit `cols` x `rows` terminal cells, two pixels per
+/// cell: each cell is `(top, bottom)`, drawn as an upper half block in the
+/// top color over the bottom color. Keeps the aspect ratio and never
+/// scales up.
+pub(crate) fn half_block_cells(
+    image: &RgbImage,
+    cols: u16,
+    rows: u16,
+) -> Vec<Vec<([u8; 3], [u8; 3])>> {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 || cols == 0 || rows == 0 {
+        return Vec::new();
+    }
+    let scale = (cols as f64 / width as f64)
+        .min(rows as f64 * 2.0 / height as f64)
+        .min(1.0);
+    let out_w = ((width as f64 * scale).round() as u32).max(1);
+    let out_h = ((height as f64 * scale).round() as u32).max(1);
+    let pixel = |x: u32, y: u32| {
+        let sx = (x * width / out_w).min(width - 1);
+        let sy = (y * height / out_h).min(height - 1);
+        image.get_pixel(sx, sy).0
+    };
+    (0..out_h.div_ceil(2))
+        .map(|row| {
+            (0..out_w)
+                .map(|x| {
+                    let top = pixel(x, row * 2);
+                    // An odd last pixel row leaves the bottom half blank.
+                    let bottom = if row * 2 + 1 < out_h {
+                        pixel(x, row * 2 + 1)
+                    } else {
+                        [0, 0, 0]
+                    };
+                    (top, bottom)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgb};
+    use std::io::Cursor;
+
+    #[test]
+    fn describes_common_binary_formats() {
+        assert_eq!(describe_file_type(b"\x7fELF\x02\x01"), "ELF executable");
+        assert_eq!(describe_file_type(b"RIFF\0\0\0\0WEBPVP8 "), "WebP image");
+        assert_eq!(
+            describe_file_type(b"\0\0\0\x18ftypmp42"),
+            "MP4/QuickTime media"
+        );
+        assert_eq!(describe_file_type(b"head\0tail"), "binary data");
+        assert_eq!(format_size(512), "512 bytes");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+    }
+
+    #[test]
+    fn png_thumbnails_render_as_half_blocks() {
+        // 4x2: red on the top row, blue on the bottom one.
+        let image = RgbImage::from_fn(4, 2, |_, y| {
+            if y == 0 {
+                Rgb([255, 0, 0])
+            } else {
+                Rgb([0, 0, 255])
+            }
+        });
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .expect("encode");
+        assert_eq!(describe_file_type(&png), "PNG image");
+        let preview = decode_image_preview(&png).expect("decode");
+        assert_eq!((preview.width, preview.height), (4, 2));
+
+        let cells = half_block_cells(&preview.thumbnail, 80, 20);
+        assert_eq!(cells.len(), 1);
+        assert_eq!(cells[0].len(), 4);
+        assert_eq!(cells[0][0], ([255, 0, 0], [0, 0, 255]));
+        // Squeezed into 2 columns, the aspect ratio is kept.
+        let cells = half_block_cells(&preview.thumbnail, 2, 20);
+        assert_eq!((cells.len(), cells[0].len()), (1, 2));
+        assert!(decode_image_preview(b"not an image").is_none());
+    }
+}
@@ -0,0 +1,148 @@
+//! Project search with ripgrep on a worker thread, its hits streamed back
+//! as rg prints them so large projects fill the results popup gradually.
+
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+use crate::tab::ProjectSearchHit;
+use crate::types::ProjectSearchOptions;
+use crate::util::parse_rg_line;
+
+/// What a running search reports.
+#[derive(Debug, Clone)]
+pub(crate) enum SearchEvent {
+    Hit(ProjectSearchHit),
+    /// rg exited, or was killed after a stop.
+    Finished,
+}
+
+/// The rg invocation searching `dirs` for `query` with `options`.
+pub(crate) fn rg_command(query: &str, options: &ProjectSearchOptions, dirs: &[&Path]) -> Command {
+    let mut rg = Command::new("rg");
+    rg.arg("--line-number")
+        .arg("--no-heading")
+        .arg("--color")
+        .arg("never")
+        .arg(if options.case_sensitive {
+            "--case-sensitive"
+        } else {
+            "--smart-case"
+        });
+    if options.whole_word {
+        rg.arg("--word-regexp");
+    }
+    if options.literal {
+        rg.arg("--fixed-strings");
+    }
+    for glob in &options.globs {
+        rg.arg("--glob").arg(glob);
+    }
+    rg.arg("--regexp").arg(query).args(dirs);
+    rg
+}
+
+/// Start `rg` and send each hit to `tx` as it is printed, then
+/// `Finished`. Setting `stop` kills rg. Fails when rg cannot be run.
+pub(crate) fn spawn_search(
+    mut rg: Command,
+    tx: Sender<SearchEvent>,
+    stop: Arc<AtomicBool>,
+) -> io::Result<()> {
+    let mut child = rg
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdout = child.stdout.take();
+    let hits_tx = tx.clone();
+    let reader = thread::spawn(move || {
+        let Some(stdout) = stdout else {
+            return;
+        };
+        for line in BufReader::new(stdout).split(b'\n').map_while(Result::ok) {
+            let line = String::from_utf8_lossy(&line);
+            if let Some(hit) = parse_rg_line(line.trim_end_matches('\r'))
+                && hits_tx.send(SearchEvent::Hit(hit)).is_err()
+            {
+                break;
+            }
+        }
+    });
+    thread::spawn(move || {
+        let stopped = loop {
+            if stop.load(Ordering::Relaxed) {
+                let _ = child.kill();
+                let _ = child.wait();
+                break true;
+            }
+            match child.try_wait() {
+                Ok(Some(_)) | Err(_) => break false,
+                Ok(None) => thread::sleep(Duration::from_millis(20)),
+            }
+        };
+        // After a stop the remaining output is unwanted, and the reader
+        // may wait on a pipe a grandchild still holds.
+        if !stopped {
+            let _ = reader.join();
+        }
+        let _ = tx.send(SearchEvent::Finished);
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[cfg(unix)]
+    #[test]
+    fn hits_stream_before_rg_exits_and_stop_kills_it() {
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut fake_rg = Command::new("sh");
+        fake_rg.args(["-c", "printf 'src/a.rs:3:let x = 1;\\nnoise\\n'; sleep 5"]);
+        spawn_search(fake_rg, tx, stop.clone()).expect("spawn");
+
+        let Ok(SearchEvent::Hit(hit)) = rx.recv_timeout(Duration::from_secs(5)) else {
+            panic!("expected a hit while the search runs");
+        };
+        assert_eq!((hit.path.to_str(), hit.line), (Some("src/a.rs"), 3));
+        stop.store(true, Ordering::Relaxed);
+        assert!(matches!(
+            rx.recv_timeout(Duration::from_secs(5)),
+            Ok(SearchEvent::Finished)
+        ));
+    }
+
+    #[test]
+    fn rg_arguments_follow_the_search_options() {
+        let options = ProjectSearchOptions {
+            whole_word: true,
+            literal: true,
+            globs: vec!["*.rs".to_string()],
+            ..Default::default()
+        };
+        let rg = rg_command("a.b", &options, &[Path::new("/p")]);
+        let args: Vec<_> = rg.get_args().map(|a| a.to_string_lossy()).collect();
+        assert_eq!(
+            args[4..],
+            [
+                "--smart-case",
+                "--word-regexp",
+                "--fixed-strings",
+                "--glob",
+                "*.rs",
+                "--regexp",
+                "a.b",
+                "/p"
+            ]
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use serde_json::Value;
+
+use crate::util::fuzzy_score;
+
+/// Which file a setting is stored in, all in the state directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SettingFile {
+    /// `state.json`, shared with the rest of the saved app state.
+    State,
+    Editor,
+    Status,
+    FileTypes,
+    Lsp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SettingKind {
+    Bool,
+    /// A whole number in `min..=max`; `optional` ones can be cleared to
+    /// fall back to the built-in default.
+    Number {
+        min: u64,
+        max: u64,
+        optional: bool,
+    },
+    /// Free text; empty clears it.
+    Text,
+    /// Comma-separated words, stored as a JSON array.
+    List,
+    /// The name of an installed theme.
+    Theme,
+    /// One of a fixed set of words.
+    Choice(&'static [&'static str]),
+}
+
+/// One entry of the settings editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SettingDef {
+    /// Name shown in the list and matched by the search.
+    pub(crate) name: &'static str,
+    pub(crate) file: SettingFile,
+    /// Key in the file.
+    pub(crate) field: &'static str,
+    pub(crate) kind: SettingKind,
+    pub(crate) description: &'static str,
+}
+
+const fn number(min: u64, max: u64) -> SettingKind {
+    SettingKind::Number {
+        min,
+        max,
+        optional: false,
+    }
+}
+
+const fn optional_number(min: u64, max: u64) -> SettingKind {
+    SettingKind::Number {
+        min,
+        max,
+        optional: true,
+    }
+}
+
+pub(crate) const SETTINGS: &[SettingDef] = &[
+    SettingDef {
+        name: "Theme",
+        file: SettingFile::State,
+        field: "theme_name",
+        kind: SettingKind::Theme,
+        description: "Color theme, by name",
+    },
+    SettingDef {
+        name: "Word Wrap",
+        file: SettingFile::State,
+        field: "word_wrap",
+        kind: SettingKind::Bool,
+        description: "Wrap long lines instead of scrolling sideways",
+    },
+    SettingDef {
+        name: "Show Ignored Files",
+        file: SettingFile::State,
+        field: "show_ignored",
+        kind: SettingKind::Bool,
+        description: "List .gitignore'd files in the tree, dimmed",
+    },
+    SettingDef {
+        name: "Nerd Font Icons",
+        file: SettingFile::State,
+        field: "nerd_font_icons",
+        kind: SettingKind::Bool,
+        description: "File icons from a nerd font; off uses ASCII markers",
+    },
+    SettingDef {
+        name: "Files Pane Width",
+        file: SettingFile::State,
+        field: "files_pane_width",
+        kind: number(18, 200),
+        description: "Width of the file tree in columns",
+    },
+    SettingDef {
+        name: "Editor: Tab Width",
+        file: SettingFile::Editor,
+        field: "tab_width",
+        kind: number(1, 16),
+        description: "Columns per indent level",
+    },
+    SettingDef {
+        name: "Editor: Insert Spaces",
+        file: SettingFile::Editor,
+        field: "insert_spaces",
+        kind: SettingKind::Bool,
+        description: "Indent with spaces rather than a tab character",
+    },
+    SettingDef {
+        name: "Editor: Re-indent on Paste",
+        file: SettingFile::Editor,
+        field: "reindent_on_paste",
+        kind: SettingKind::Bool,
+        description: "Fit pasted blocks to the cursor's indentation",
+    },
+    SettingDef {
+        name: "Editor: Move by Visual Lines",
+        file: SettingFile::Editor,
+        field: "visual_line_navigation",
+        kind: SettingKind::Bool,
+        description: "With word wrap, Up/Down/Home/End follow wrapped rows",
+    },
+    SettingDef {
+        name: "Editor: Gutter Line Numbers",
+        file: SettingFile::Editor,
+        field: "gutter_line_numbers",
+        kind: SettingKind::Bool,
+        description: "Show line numbers; off leaves only the bookmark marker",
+    },
+    SettingDef {
+        name: "Editor: Gutter Fold Markers",
+        file: SettingFile::Editor,
+        field: "gutter_folds",
+        kind: SettingKind::Bool,
+        description: "Show fold markers and the wrapped-line arrow",
+    },
+    SettingDef {
+        name: "Editor: Gutter Diagnostics",
+        file: SettingFile::Editor,
+        field: "gutter_diagnostics",
+        kind: SettingKind::Bool,
+        description: "Show a dot on lines with errors or warnings",
+    },
+    SettingDef {
+        name: "Editor: Gutter Git Changes",
+        file: SettingFile::Editor,
+        field: "gutter_git",
+        kind: SettingKind::Bool,
+        description: "Show +/~/- for lines changed since the last commit",
+    },
+    SettingDef {
+        name: "Editor: Invalid Action Feedback",
+        file: SettingFile::Editor,
+        field: "invalid_action_feedback",
+        kind: SettingKind::Choice(&["flash", "bell", "both", "off"]),
+        description: "When an action cannot apply: flash the pane border, bell, both or off",
+    },
+    SettingDef {
+        name: "Status: Info Timeout (ms)",
+        file: SettingFile::Status,
+        field: "info_timeout_ms",
+        kind: number(0, 3_600_000),
+        description: "How long info messages stay; 0 keeps them",
+    },
+    SettingDef {
+        name: "Status: Warning Timeout (ms)",
+        file: SettingFile::Status,
+        field: "warn_timeout_ms",
+        kind: number(0, 3_600_000),
+        description: "How long warnings stay; 0 keeps them",
+    },
+    SettingDef {
+        name: "Status: Warning Minimum (ms)",
+        file: SettingFile::Status,
+        field: "warn_min_display_ms",
+        kind: number(0, 60_000),
+        description: "How long a warning shows before info may replace it",
+    },
+    SettingDef {
+        name: "Files: Max Text Size (bytes)",
+        file: SettingFile::FileTypes,
+        field: "max_text_bytes",
+        kind: optional_number(1, u64::MAX),
+        description: "Larger files are refused; empty for 20 MB",
+    },
+    SettingDef {
+        name: "Files: Large File Size (bytes)",
+        file: SettingFile::FileTypes,
+        field: "large_file_bytes",
+        kind: optional_number(1, u64::MAX),
+        description: "Larger files open in large file mode; empty for 5 MB",
+    },
+    SettingDef {
+        name: "Files: Binary Extensions",
+        file: SettingFile::FileTypes,
+        field: "binary_extensions",
+        kind: SettingKind::List,
+        description: "Never open these as text, e.g. dat, bin",
+    },
+    SettingDef {
+        name: "Files: Text Extensions",
+        file: SettingFile::FileTypes,
+        field: "text_extensions",
+        kind: SettingKind::List,
+        description: "Always open these as text, skipping binary checks",
+    },
+    SettingDef {
+        name: "LSP: Check Command",
+        file: SettingFile::Lsp,
+        field: "check_command",
+        kind: SettingKind::Text,
+        description: "rust-analyzer check on save, e.g. clippy; empty for check",
+    },
+    SettingDef {
+        name: "LSP: Cargo Features",
+        file: SettingFile::Lsp,
+        field: "cargo_features",
+        kind: SettingKind::List,
+        description: "Features rust-analyzer enables",
+    },
+    SettingDef {
+        name: "LSP: All Features",
+        file: SettingFile::Lsp,
+        field: "all_features",
+        kind: SettingKind::Bool,
+        description: "Enable every cargo feature",
+    },
+    SettingDef {
+        name: "LSP: Target Directory",
+        file: SettingFile::Lsp,
+        field: "target_dir",
+        kind: SettingKind::Text,
+        description: "Separate target dir for rust-analyzer, so it does not block cargo",
+    },
+    SettingDef {
+        name: "LSP: Proc Macros",
+        file: SettingFile::Lsp,
+        field: "proc_macros",
+        kind: SettingKind::Bool,
+        description: "Expand proc macros in rust-analyzer",
+    },
+    SettingDef {
+        name: "LSP: Cargo Check on Save",
+        file: SettingFile::Lsp,
+        field: "cargo_check_on_save",
+        kind: SettingKind::Bool,
+        description: "Project-wide cargo check after saving; unset: without rust-analyzer",
+    },
+];
+
+/// Settings whose name fuzzy-matches `query` or whose description
+/// contains it, in table order.
+pub(crate) fn matching_settings(query: &str) -> Vec<&'static SettingDef> {
+    let query = query.to_ascii_lowercase();
+    SETTINGS
+        .iter()
+        .filter(|def| {
+            fuzzy_score(&query, def.name).is_some()
+                || def.description.to_ascii_lowercase().contains(&query)
+        })
+        .collect()
+}
+
+/// `value` as shown in the list and prefilled when editing.
+pub(crate) fn format_setting_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string))
+            .collect::<Vec<_>>()
+            .join(", "),
+        other => other.to_string(),
+    }
+}
+
+/// Check `input` for `kind` and turn it into the value to store; `Null`
+/// clears the setting. Theme names are checked by the caller.
+pub(crate) fn parse_setting_value(kind: SettingKind, input: &str) -> Result<Value, String> {
+    let input = input.trim();
+    match kind {
+        SettingKind::Bool => match input.to_ascii_lowercase().as_str() {
+            "true" | "on" | "yes" | "1" => Ok(Value::Bool(true)),
+            "false" | "off" | "no" | "0" => Ok(Value::Bool(false)),
+            _ => Err("Expected true or false".to_string()),
+        },
+        SettingKind::Number { optional: true, .. } if input.is_empty() => Ok(Value::Null),
+        SettingKind::Number { min, max, .. } => match input.replace('_', "").parse::<u64>() {
+            Ok(n) if (min..=max).contains(&n) => Ok(Value::from(n)),
+            _ if max == u64::MAX => Err(format!("Expected a whole number of at least {min}")),
+            _ => Err(format!("Expected a whole number from {min} to {max}")),
+        },
+        SettingKind::Text if input.is_empty() => Ok(Value::Null),
+        SettingKind::Text | SettingKind::Theme => Ok(Value::from(input)),
+        SettingKind::Choice(choices) => choices
+            .iter()
+            .find(|c| c.eq_ignore_ascii_case(input))
+            .map(|c| Value::from(*c))
+            .ok_or_else(|| format!("Expected one of {}", choices.join(", "))),
+        SettingKind::List => Ok(Value::from(
+            input
+                .split(',')
+                .map(str::trim)
+                .filter(|w| !w.is_empty())
+                .collect::<Vec<_>>(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_and_validates_setting_input() {
+        let tab_width = number(1, 16);
+        assert_eq!(parse_setting_value(tab_width, " 8 "), Ok(json!(8)));
+        assert_eq!(
+            parse_setting_value(tab_width, "0"),
+            Err("Expected a whole number from 1 to 16".to_string())
+        );
+        assert!(parse_setting_value(tab_width, "four").is_err());
+        assert_eq!(
+            parse_setting_value(tab_width, ""),
+            Err("Expected a whole number from 1 to 16".to_string())
+        );
+        assert_eq!(
+            parse_setting_value(optional_number(1, u64::MAX), ""),
+            Ok(Value::Null)
+        );
+        assert_eq!(
+            parse_setting_value(optional_number(1, u64::MAX), "5_000_000"),
+            Ok(json!(5_000_000))
+        );
+        assert_eq!(
+            parse_setting_value(SettingKind::Bool, "Off"),
+            Ok(json!(false))
+        );
+        assert!(parse_setting_value(SettingKind::Bool, "maybe").is_err());
+        let list = parse_setting_value(SettingKind::List, "dat, bin,,").expect("list");
+        assert_eq!(list, json!(["dat", "bin"]));
+        assert_eq!(format_setting_value(&list), "dat, bin");
+        assert_eq!(
+            parse_setting_value(SettingKind::Text, "  "),
+            Ok(Value::Null)
+        );
+        let choice = SettingKind::Choice(&["flash", "bell"]);
+        assert_eq!(parse_setting_value(choice, " Bell "), Ok(json!("bell")));
+        assert_eq!(
+            parse_setting_value(choice, "beep"),
+            Err("Expected one of flash, bell".to_string())
+        );
+    }
+
+    #[test]
+    fn setting_names_are_unique() {
+        for (i, def) in SETTINGS.iter().enumerate() {
+            assert!(
+                SETTINGS[i + 1..].iter().all(|d| d.name != def.name),
+                "duplicate setting {}",
+                def.name
+            );
+        }
+    }
+}
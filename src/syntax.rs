@@ -1,10 +1,16 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Range;
 use std::path::Path;
 
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
+use serde::{Deserialize, Serialize};
 
 use crate::theme::Theme;
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub(crate) enum SyntaxLang {
     Plain,
     Rust,
@@ -18,6 +24,67 @@ pub(crate) enum SyntaxLang {
     Json,
     Markdown,
 }
+
+impl SyntaxLang {
+    pub(crate) const ALL: [SyntaxLang; 11] = [
+        SyntaxLang::Plain,
+        SyntaxLang::Rust,
+        SyntaxLang::Python,
+        SyntaxLang::JsTs,
+        SyntaxLang::Go,
+        SyntaxLang::Php,
+        SyntaxLang::Css,
+        SyntaxLang::HtmlXml,
+        SyntaxLang::Shell,
+        SyntaxLang::Json,
+        SyntaxLang::Markdown,
+    ];
+
+    /// Short name used in the language mode prompt and the status bar.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            SyntaxLang::Plain => "plain",
+            SyntaxLang::Rust => "rust",
+            SyntaxLang::Python => "python",
+            SyntaxLang::JsTs => "js",
+            SyntaxLang::Go => "go",
+            SyntaxLang::Php => "php",
+            SyntaxLang::Css => "css",
+            SyntaxLang::HtmlXml => "html",
+            SyntaxLang::Shell => "shell",
+            SyntaxLang::Json => "json",
+            SyntaxLang::Markdown => "markdown",
+        }
+    }
+
+    /// Parse a name typed in the language mode prompt; a few common
+    /// aliases are accepted alongside [`Self::name`].
+    pub(crate) fn from_name(name: &str) -> Option<SyntaxLang> {
+        let name = name.trim().to_ascii_lowercase();
+        let alias = match name.as_str() {
+            "text" | "txt" => SyntaxLang::Plain,
+            "rs" => SyntaxLang::Rust,
+            "py" => SyntaxLang::Python,
+            "javascript" | "typescript" | "ts" => SyntaxLang::JsTs,
+            "xml" => SyntaxLang::HtmlXml,
+            "sh" | "bash" => SyntaxLang::Shell,
+            "toml" | "yaml" => SyntaxLang::Json,
+            "md" => SyntaxLang::Markdown,
+            _ => return Self::ALL.into_iter().find(|lang| lang.name() == name),
+        };
+        Some(alias)
+    }
+
+    /// Characters that open an indented block at the end of a line.
+    pub(crate) fn block_openers(self) -> &'static [char] {
+        match self {
+            SyntaxLang::Plain | SyntaxLang::Markdown | SyntaxLang::HtmlXml => &[],
+            SyntaxLang::Python => &['{', '(', '[', ':'],
+            _ => &['{', '(', '['],
+        }
+    }
+}
+
 pub(crate) fn syntax_lang_for_path(path: Option<&Path>) -> SyntaxLang {
     let Some(path) = path else {
         return SyntaxLang::Plain;
@@ -175,11 +242,13 @@ pub(crate) fn comment_start_for_lang(lang: SyntaxLang) -> Option<&'static str> {
     }
 }
 
+/// Highlight `line`, which starts at `bracket_depth` and inside `state`.
 pub(crate) fn highlight_line(
     line: &str,
     lang: SyntaxLang,
     theme: &Theme,
     bracket_depth: u16,
+    state: LineState,
     bracket_colors: &[Color; 3],
 ) -> Line<'static> {
     let base = Style::default().fg(theme.fg);
@@ -265,101 +334,348 @@ pub(crate) fn highlight_line(
         return Line::from(spans);
     }
 
-    let bytes = line.as_bytes();
-    let mut i = 0usize;
     let mut spans: Vec<Span<'static>> = Vec::new();
     let mut bd = bracket_depth;
-    let is_block_comment_lang = matches!(lang, SyntaxLang::Php | SyntaxLang::Css);
-
-    // Heuristic for middle lines of multiline block comments in CSS/PHP.
-    let trimmed = line.trim_start();
-    if is_block_comment_lang && (trimmed.starts_with('*') || trimmed.starts_with("*/")) {
-        return Line::from(vec![Span::styled(line.to_string(), comment_style)]);
-    }
-    while i < bytes.len() {
-        if let Some(comment) = comment_start_for_lang(lang) {
-            if comment == "/*" && line[i..].starts_with("/*") {
-                // Style only the block segment when it closes on this line.
-                if let Some(close_rel) = line[i + 2..].find("*/") {
-                    let end = i + 2 + close_rel + 2;
-                    spans.push(Span::styled(line[i..end].to_string(), comment_style));
-                    i = end;
-                    continue;
+    for (range, region) in scan_regions(line, lang, state).0 {
+        let text = &line[range];
+        match region {
+            Region::Comment => spans.push(Span::styled(text.to_string(), comment_style)),
+            Region::String => spans.push(Span::styled(text.to_string(), string_style)),
+            Region::Code => {
+                let mut i = 0usize;
+                while i < text.len() {
+                    let ch = text[i..].chars().next().unwrap_or('\0');
+                    if ch.is_ascii_digit() {
+                        let start = i;
+                        i += ch.len_utf8();
+                        while i < text.len() {
+                            let c = text[i..].chars().next().unwrap_or('\0');
+                            if c.is_ascii_digit() || c == '_' || c == '.' {
+                                i += c.len_utf8();
+                            } else {
+                                break;
+                            }
+                        }
+                        spans.push(Span::styled(text[start..i].to_string(), number_style));
+                        continue;
+                    }
+                    if is_ident_char(ch) {
+                        let start = i;
+                        i += ch.len_utf8();
+                        while i < text.len() {
+                            let c = text[i..].chars().next().unwrap_or('\0');
+                            if is_ident_char(c) {
+                                i += c.len_utf8();
+                            } else {
+                                break;
+                            }
+                        }
+                        let token = &text[start..i];
+                        if keywords_for_lang(lang).contains(&token) {
+                            spans.push(Span::styled(token.to_string(), keyword_style));
+                        } else {
+                            spans.push(Span::styled(token.to_string(), base));
+                        }
+                        continue;
+                    }
+                    if ch == '{' || ch == '(' || ch == '[' {
+                        let color = bracket_colors[(bd % 3) as usize];
+                        spans.push(Span::styled(ch.to_string(), Style::default().fg(color)));
+                        bd = bd.saturating_add(1);
+                    } else if ch == '}' || ch == ')' || ch == ']' {
+                        bd = bd.saturating_sub(1);
+                        let color = bracket_colors[(bd % 3) as usize];
+                        spans.push(Span::styled(ch.to_string(), Style::default().fg(color)));
+                    } else {
+                        spans.push(Span::styled(ch.to_string(), base));
+                    }
+                    i += ch.len_utf8();
                 }
-                spans.push(Span::styled(line[i..].to_string(), comment_style));
-                break;
-            } else if comment != "/*" && line[i..].starts_with(comment) {
-                spans.push(Span::styled(line[i..].to_string(), comment_style));
-                break;
             }
         }
-        let ch = line[i..].chars().next().unwrap_or('\0');
-        if ch == '"' || ch == '\'' {
-            let quote = ch;
-            let start = i;
-            i += ch.len_utf8();
-            while i < bytes.len() {
-                let c = line[i..].chars().next().unwrap_or('\0');
-                i += c.len_utf8();
-                if c == '\\' && i < bytes.len() {
-                    let escaped = line[i..].chars().next().unwrap_or('\0');
-                    i += escaped.len_utf8();
-                    continue;
-                }
-                if c == quote {
-                    break;
+    }
+    Line::from(spans)
+}
+
+/// Where a line starts: in code, or inside a comment or string left open
+/// by the lines above. Computed for every line in a prepass, like bracket
+/// depths, so highlighting continues block comments and strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) enum LineState {
+    #[default]
+    Code,
+    /// In a `/* */` comment; Rust's nest, so the depth is kept.
+    BlockComment(u16),
+    /// In a string closed by this quote, with backslash escapes.
+    Quoted(char),
+    /// In a Python `"""` or `'''` string.
+    TripleQuoted(char),
+    /// In a Rust raw string closed by `"` and this many `#`.
+    RawString(u8),
+}
+
+/// What a stretch of a line is, as split by [`scan_regions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Region {
+    Code,
+    Comment,
+    String,
+}
+
+enum Opener {
+    LineComment,
+    /// The state inside the comment or string, and the opener's length.
+    Enter(LineState, usize),
+}
+
+/// Whether a string opened with `quote` may continue onto the next line.
+fn multiline_quote(lang: SyntaxLang, quote: char) -> bool {
+    match quote {
+        '"' => matches!(lang, SyntaxLang::Rust | SyntaxLang::Shell),
+        '`' => matches!(lang, SyntaxLang::JsTs | SyntaxLang::Go),
+        _ => false,
+    }
+}
+
+/// The comment or string opening at byte `i` of `line`, if any.
+fn opener_at(line: &str, i: usize, lang: SyntaxLang) -> Option<Opener> {
+    let rest = &line[i..];
+    match comment_start_for_lang(lang) {
+        Some("/*") => {}
+        Some(comment) if rest.starts_with(comment) => return Some(Opener::LineComment),
+        _ => {}
+    }
+    let block_comments = matches!(
+        lang,
+        SyntaxLang::Rust | SyntaxLang::JsTs | SyntaxLang::Go | SyntaxLang::Php | SyntaxLang::Css
+    );
+    if block_comments && rest.starts_with("/*") {
+        return Some(Opener::Enter(LineState::BlockComment(1), 2));
+    }
+    if lang == SyntaxLang::Python {
+        for quote in ['"', '\''] {
+            if rest.starts_with(&quote.to_string().repeat(3)) {
+                return Some(Opener::Enter(LineState::TripleQuoted(quote), 3));
+            }
+        }
+    }
+    if lang == SyntaxLang::Rust && !line[..i].chars().next_back().is_some_and(is_ident_char) {
+        let raw = rest.strip_prefix('b').unwrap_or(rest);
+        if let Some(after_r) = raw.strip_prefix('r') {
+            let hashes = after_r.chars().take_while(|&c| c == '#').count();
+            if after_r[hashes..].starts_with('"') {
+                let len = rest.len() - after_r.len() + hashes + 1;
+                return Some(Opener::Enter(
+                    LineState::RawString(u8::try_from(hashes).unwrap_or(u8::MAX)),
+                    len,
+                ));
+            }
+        }
+    }
+    let ch = rest.chars().next()?;
+    if ch == '"' || ch == '\'' || (ch == '`' && matches!(lang, SyntaxLang::JsTs | SyntaxLang::Go)) {
+        return Some(Opener::Enter(LineState::Quoted(ch), 1));
+    }
+    None
+}
+
+/// Scan `line` from byte `i` inside `state` for its closer: the byte just
+/// past it, or `None` when it stays open past the end of the line.
+fn close_at(line: &str, mut i: usize, state: LineState, lang: SyntaxLang) -> Option<usize> {
+    let next_char = |i: usize| line[i..].chars().next().map_or(1, char::len_utf8);
+    match state {
+        LineState::Code => Some(i),
+        LineState::BlockComment(mut depth) => {
+            while i < line.len() {
+                let rest = &line[i..];
+                if rest.starts_with("*/") {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                } else if lang == SyntaxLang::Rust && rest.starts_with("/*") {
+                    depth += 1;
+                    i += 2;
+                } else {
+                    i += next_char(i);
                 }
             }
-            spans.push(Span::styled(line[start..i].to_string(), string_style));
-            continue;
+            None
         }
-        if ch.is_ascii_digit() {
-            let start = i;
-            i += ch.len_utf8();
-            while i < bytes.len() {
-                let c = line[i..].chars().next().unwrap_or('\0');
-                if c.is_ascii_digit() || c == '_' || c == '.' {
-                    i += c.len_utf8();
+        LineState::Quoted(quote) | LineState::TripleQuoted(quote) => {
+            let triple = quote.to_string().repeat(3);
+            let closer = if matches!(state, LineState::TripleQuoted(_)) {
+                triple.as_str()
+            } else {
+                &triple[..quote.len_utf8()]
+            };
+            while i < line.len() {
+                if line[i..].starts_with('\\') {
+                    i += 1;
+                    if i < line.len() {
+                        i += next_char(i);
+                    }
+                } else if line[i..].starts_with(closer) {
+                    return Some(i + closer.len());
                 } else {
-                    break;
+                    i += next_char(i);
                 }
             }
-            spans.push(Span::styled(line[start..i].to_string(), number_style));
-            continue;
+            None
         }
-        if is_ident_char(ch) {
-            let start = i;
-            i += ch.len_utf8();
-            while i < bytes.len() {
-                let c = line[i..].chars().next().unwrap_or('\0');
-                if is_ident_char(c) {
-                    i += c.len_utf8();
+        LineState::RawString(hashes) => {
+            let closer = format!("\"{}", "#".repeat(hashes as usize));
+            line[i..].find(&closer).map(|at| i + at + closer.len())
+        }
+    }
+}
+
+/// The state a line ends in when `state`, opened earlier on it, is still
+/// open at its end.
+fn carried_state(line: &str, from: usize, state: LineState, lang: SyntaxLang) -> LineState {
+    match state {
+        LineState::BlockComment(depth) if lang == SyntaxLang::Rust => {
+            // Count the nesting left open, since close_at only reports
+            // that the comment did not end.
+            let mut depth = depth;
+            let mut i = from;
+            while i < line.len() {
+                let rest = &line[i..];
+                if rest.starts_with("*/") {
+                    depth = depth.saturating_sub(1);
+                    i += 2;
+                } else if rest.starts_with("/*") {
+                    depth += 1;
+                    i += 2;
                 } else {
-                    break;
+                    i += rest.chars().next().map_or(1, char::len_utf8);
                 }
             }
-            let token = &line[start..i];
-            if keywords_for_lang(lang).contains(&token) {
-                spans.push(Span::styled(token.to_string(), keyword_style));
-            } else {
-                spans.push(Span::styled(token.to_string(), base));
+            LineState::BlockComment(depth.max(1))
+        }
+        LineState::Quoted(quote) if !multiline_quote(lang, quote) => LineState::Code,
+        other => other,
+    }
+}
+
+/// Split `line` into code, comment and string stretches (byte ranges),
+/// starting inside `state`, and return the state the next line starts in.
+pub(crate) fn scan_regions(
+    line: &str,
+    lang: SyntaxLang,
+    state: LineState,
+) -> (Vec<(Range<usize>, Region)>, LineState) {
+    let region_of = |state: LineState| match state {
+        LineState::BlockComment(_) => Region::Comment,
+        _ => Region::String,
+    };
+    let mut regions = Vec::new();
+    let mut i = 0usize;
+    if state != LineState::Code {
+        let Some(end) = close_at(line, 0, state, lang) else {
+            if !line.is_empty() {
+                regions.push((0..line.len(), region_of(state)));
             }
+            return (regions, carried_state(line, 0, state, lang));
+        };
+        regions.push((0..end, region_of(state)));
+        i = end;
+    }
+    let mut code_start = i;
+    while i < line.len() {
+        let Some(opener) = opener_at(line, i, lang) else {
+            i += line[i..].chars().next().map_or(1, char::len_utf8);
             continue;
+        };
+        if code_start < i {
+            regions.push((code_start..i, Region::Code));
         }
-        if ch == '{' || ch == '(' || ch == '[' {
-            let color = bracket_colors[(bd % 3) as usize];
-            spans.push(Span::styled(ch.to_string(), Style::default().fg(color)));
-            bd = bd.saturating_add(1);
-        } else if ch == '}' || ch == ')' || ch == ']' {
-            bd = bd.saturating_sub(1);
-            let color = bracket_colors[(bd % 3) as usize];
-            spans.push(Span::styled(ch.to_string(), Style::default().fg(color)));
-        } else {
-            spans.push(Span::styled(ch.to_string(), base));
+        match opener {
+            Opener::LineComment => {
+                regions.push((i..line.len(), Region::Comment));
+                return (regions, LineState::Code);
+            }
+            Opener::Enter(inner, len) => match close_at(line, i + len, inner, lang) {
+                Some(end) => {
+                    regions.push((i..end, region_of(inner)));
+                    i = end;
+                    code_start = end;
+                }
+                None => {
+                    regions.push((i..line.len(), region_of(inner)));
+                    return (regions, carried_state(line, i + len, inner, lang));
+                }
+            },
         }
-        i += ch.len_utf8();
     }
-    Line::from(spans)
+    if code_start < line.len() {
+        regions.push((code_start..line.len(), Region::Code));
+    }
+    (regions, LineState::Code)
+}
+
+/// The state each line starts in.
+pub(crate) fn compute_line_states(lines: &[String], lang: SyntaxLang) -> Vec<LineState> {
+    let mut state = LineState::Code;
+    lines
+        .iter()
+        .map(|line| {
+            let start = state;
+            state = scan_regions(line, lang, state).1;
+            start
+        })
+        .collect()
+}
+
+/// Entries kept before a [`HighlightCache`] starts over.
+const HIGHLIGHT_CACHE_LEN: usize = 4096;
+
+/// A tab's highlighted lines keyed by a hash of their text, language,
+/// bracket depth and starting state, so rows that did not change are not re-tokenized every
+/// frame. An edited line hashes differently and misses; entries are
+/// dropped when the theme changes or the cache outgrows
+/// `HIGHLIGHT_CACHE_LEN`. Rendering only borrows the tab, hence the
+/// `RefCell`s.
+#[derive(Default)]
+pub(crate) struct HighlightCache {
+    theme: RefCell<String>,
+    lines: RefCell<HashMap<u64, Line<'static>>>,
+}
+
+impl HighlightCache {
+    /// [`highlight_line`], reusing the result for text seen before.
+    pub(crate) fn highlight(
+        &self,
+        line: &str,
+        lang: SyntaxLang,
+        theme: &Theme,
+        bracket_depth: u16,
+        state: LineState,
+        bracket_colors: &[Color; 3],
+    ) -> Line<'static> {
+        if lang == SyntaxLang::Plain {
+            return highlight_line(line, lang, theme, bracket_depth, state, bracket_colors);
+        }
+        let mut lines = self.lines.borrow_mut();
+        let mut cached_theme = self.theme.borrow_mut();
+        if *cached_theme != theme.name {
+            lines.clear();
+            cached_theme.clone_from(&theme.name);
+        }
+        let mut hasher = DefaultHasher::new();
+        (line, lang, bracket_depth, state).hash(&mut hasher);
+        let key = hasher.finish();
+        if let Some(hl) = lines.get(&key) {
+            return hl.clone();
+        }
+        if lines.len() >= HIGHLIGHT_CACHE_LEN {
+            lines.clear();
+        }
+        let hl = highlight_line(line, lang, theme, bracket_depth, state, bracket_colors);
+        lines.insert(key, hl.clone());
+        hl
+    }
 }
 #[cfg(test)]
 mod syntax_and_lang_tests {
@@ -394,9 +710,20 @@ mod syntax_and_lang_tests {
             bracket_1: Color::Rgb(210, 168, 75),
             bracket_2: Color::Rgb(176, 82, 204),
             bracket_3: Color::Rgb(0, 175, 215),
+            warning: Color::Rgb(210, 168, 75),
         }
     }
 
+    #[test]
+    fn test_syntax_lang_names_round_trip() {
+        for lang in SyntaxLang::ALL {
+            assert_eq!(SyntaxLang::from_name(lang.name()), Some(lang));
+        }
+        assert_eq!(SyntaxLang::from_name(" Bash "), Some(SyntaxLang::Shell));
+        assert_eq!(SyntaxLang::from_name("ts"), Some(SyntaxLang::JsTs));
+        assert_eq!(SyntaxLang::from_name("cobol"), None);
+    }
+
     #[test]
     fn test_syntax_lang_for_path_rust() {
         assert_eq!(
@@ -781,21 +1108,42 @@ mod syntax_and_lang_tests {
     #[test]
     fn test_highlight_line_plain() {
         let theme = create_test_theme();
-        let result = highlight_line("this is plain text", SyntaxLang::Plain, &theme, 0, &BC);
+        let result = highlight_line(
+            "this is plain text",
+            SyntaxLang::Plain,
+            &theme,
+            0,
+            LineState::Code,
+            &BC,
+        );
         assert!(!result.spans.is_empty());
     }
 
     #[test]
     fn test_highlight_line_rust_keyword() {
         let theme = create_test_theme();
-        let result = highlight_line("fn main() {", SyntaxLang::Rust, &theme, 0, &BC);
+        let result = highlight_line(
+            "fn main() {",
+            SyntaxLang::Rust,
+            &theme,
+            0,
+            LineState::Code,
+            &BC,
+        );
         assert!(!result.spans.is_empty());
     }
 
     #[test]
     fn test_highlight_line_rust_comment() {
         let theme = create_test_theme();
-        let result = highlight_line("// this is a comment", SyntaxLang::Rust, &theme, 0, &BC);
+        let result = highlight_line(
+            "// this is a comment",
+            SyntaxLang::Rust,
+            &theme,
+            0,
+            LineState::Code,
+            &BC,
+        );
         assert!(!result.spans.is_empty());
     }
 
@@ -807,6 +1155,7 @@ mod syntax_and_lang_tests {
             SyntaxLang::Rust,
             &theme,
             0,
+            LineState::Code,
             &BC,
         );
         assert!(!result.spans.is_empty());
@@ -816,14 +1165,28 @@ mod syntax_and_lang_tests {
     fn test_highlight_line_python() {
         let theme = create_test_theme();
         assert!(
-            !highlight_line("def hello():", SyntaxLang::Python, &theme, 0, &BC)
-                .spans
-                .is_empty()
+            !highlight_line(
+                "def hello():",
+                SyntaxLang::Python,
+                &theme,
+                0,
+                LineState::Code,
+                &BC
+            )
+            .spans
+            .is_empty()
         );
         assert!(
-            !highlight_line("# comment", SyntaxLang::Python, &theme, 0, &BC)
-                .spans
-                .is_empty()
+            !highlight_line(
+                "# comment",
+                SyntaxLang::Python,
+                &theme,
+                0,
+                LineState::Code,
+                &BC
+            )
+            .spans
+            .is_empty()
         );
     }
 
@@ -831,29 +1194,64 @@ mod syntax_and_lang_tests {
     fn test_highlight_line_js_go_shell_css_php() {
         let theme = create_test_theme();
         assert!(
-            !highlight_line("function test() {", SyntaxLang::JsTs, &theme, 0, &BC)
-                .spans
-                .is_empty()
+            !highlight_line(
+                "function test() {",
+                SyntaxLang::JsTs,
+                &theme,
+                0,
+                LineState::Code,
+                &BC
+            )
+            .spans
+            .is_empty()
         );
         assert!(
-            !highlight_line("package main", SyntaxLang::Go, &theme, 0, &BC)
-                .spans
-                .is_empty()
+            !highlight_line(
+                "package main",
+                SyntaxLang::Go,
+                &theme,
+                0,
+                LineState::Code,
+                &BC
+            )
+            .spans
+            .is_empty()
         );
         assert!(
-            !highlight_line("if [ -f file ]; then", SyntaxLang::Shell, &theme, 0, &BC)
-                .spans
-                .is_empty()
+            !highlight_line(
+                "if [ -f file ]; then",
+                SyntaxLang::Shell,
+                &theme,
+                0,
+                LineState::Code,
+                &BC
+            )
+            .spans
+            .is_empty()
         );
         assert!(
-            !highlight_line("  display: flex;", SyntaxLang::Css, &theme, 0, &BC)
-                .spans
-                .is_empty()
+            !highlight_line(
+                "  display: flex;",
+                SyntaxLang::Css,
+                &theme,
+                0,
+                LineState::Code,
+                &BC
+            )
+            .spans
+            .is_empty()
         );
         assert!(
-            !highlight_line("function test() {", SyntaxLang::Php, &theme, 0, &BC)
-                .spans
-                .is_empty()
+            !highlight_line(
+                "function test() {",
+                SyntaxLang::Php,
+                &theme,
+                0,
+                LineState::Code,
+                &BC
+            )
+            .spans
+            .is_empty()
         );
     }
 
@@ -861,14 +1259,28 @@ mod syntax_and_lang_tests {
     fn test_highlight_line_markdown() {
         let theme = create_test_theme();
         assert!(
-            !highlight_line("# Heading 1", SyntaxLang::Markdown, &theme, 0, &BC)
-                .spans
-                .is_empty()
+            !highlight_line(
+                "# Heading 1",
+                SyntaxLang::Markdown,
+                &theme,
+                0,
+                LineState::Code,
+                &BC
+            )
+            .spans
+            .is_empty()
         );
         assert!(
-            !highlight_line("Normal text", SyntaxLang::Markdown, &theme, 0, &BC)
-                .spans
-                .is_empty()
+            !highlight_line(
+                "Normal text",
+                SyntaxLang::Markdown,
+                &theme,
+                0,
+                LineState::Code,
+                &BC
+            )
+            .spans
+            .is_empty()
         );
     }
 
@@ -881,15 +1293,23 @@ mod syntax_and_lang_tests {
                 SyntaxLang::HtmlXml,
                 &theme,
                 0,
+                LineState::Code,
                 &BC
             )
             .spans
             .is_empty()
         );
         assert!(
-            !highlight_line("<!-- comment -->", SyntaxLang::HtmlXml, &theme, 0, &BC)
-                .spans
-                .is_empty()
+            !highlight_line(
+                "<!-- comment -->",
+                SyntaxLang::HtmlXml,
+                &theme,
+                0,
+                LineState::Code,
+                &BC
+            )
+            .spans
+            .is_empty()
         );
     }
 
@@ -926,12 +1346,116 @@ mod syntax_and_lang_tests {
         }
     }
 
+    #[test]
+    fn highlight_cache_reuses_lines_until_the_text_or_theme_changes() {
+        let mut theme = create_test_theme();
+        let cache = HighlightCache::default();
+        let text = "let x = \"hi\"; // note";
+        let hl = cache.highlight(text, SyntaxLang::Rust, &theme, 0, LineState::Code, &BC);
+        assert_eq!(
+            hl,
+            highlight_line(text, SyntaxLang::Rust, &theme, 0, LineState::Code, &BC)
+        );
+        cache.highlight(text, SyntaxLang::Rust, &theme, 0, LineState::Code, &BC);
+        assert_eq!(cache.lines.borrow().len(), 1);
+        cache.highlight(
+            "let y = 2;",
+            SyntaxLang::Rust,
+            &theme,
+            0,
+            LineState::Code,
+            &BC,
+        );
+        cache.highlight(text, SyntaxLang::Rust, &theme, 1, LineState::Code, &BC);
+        assert_eq!(cache.lines.borrow().len(), 3);
+
+        theme.name = "other".to_string();
+        theme.accent = Color::Rgb(1, 2, 3);
+        let hl = cache.highlight(text, SyntaxLang::Rust, &theme, 0, LineState::Code, &BC);
+        assert_eq!(
+            hl,
+            highlight_line(text, SyntaxLang::Rust, &theme, 0, LineState::Code, &BC)
+        );
+        assert_eq!(cache.lines.borrow().len(), 1);
+    }
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn block_comments_and_multiline_strings_carry_across_lines() {
+        let rust = lines(
+            "/* a /* nested */\nstill { */ fn x() {}\nlet s = r#\"raw\n\"# ; done\nlet t = 1;",
+        );
+        let states = compute_line_states(&rust, SyntaxLang::Rust);
+        assert_eq!(
+            states,
+            [
+                LineState::Code,
+                LineState::BlockComment(1),
+                LineState::Code,
+                LineState::RawString(1),
+                LineState::Code,
+            ]
+        );
+        let (regions, next) = scan_regions(&rust[1], SyntaxLang::Rust, states[1]);
+        assert_eq!(regions[0], (0..10, Region::Comment));
+        assert_eq!(next, LineState::Code);
+        assert_eq!(
+            scan_regions(&rust[2], SyntaxLang::Rust, LineState::Code).1,
+            LineState::RawString(1)
+        );
+
+        let python = lines("x = \"\"\"doc\nstill # not a comment\n\"\"\"\ny = 1");
+        assert_eq!(
+            compute_line_states(&python, SyntaxLang::Python),
+            [
+                LineState::Code,
+                LineState::TripleQuoted('"'),
+                LineState::TripleQuoted('"'),
+                LineState::Code,
+            ]
+        );
+        // A quote left open in a language without multi-line strings ends
+        // with its line.
+        let js = lines("let s = 'open\nlet t = 1;");
+        assert_eq!(
+            compute_line_states(&js, SyntaxLang::JsTs)[1],
+            LineState::Code
+        );
+    }
+
+    #[test]
+    fn lines_inside_a_block_comment_are_styled_as_comments() {
+        let theme = create_test_theme();
+        let line = highlight_line(
+            "fn main() { \"x\" }",
+            SyntaxLang::Rust,
+            &theme,
+            0,
+            LineState::BlockComment(1),
+            &BC,
+        );
+        assert!(line.spans.iter().all(|s| s.style.fg == Some(theme.comment)));
+        let line = highlight_line(
+            "end */ fn",
+            SyntaxLang::Rust,
+            &theme,
+            0,
+            LineState::BlockComment(1),
+            &BC,
+        );
+        let fn_span = line.spans.iter().find(|s| s.content == "fn").expect("fn");
+        assert_eq!(fn_span.style.fg, Some(theme.accent));
+    }
+
     #[test]
     fn test_bracket_pair_colorization() {
         let theme = create_test_theme();
         let bc = [theme.bracket_1, theme.bracket_2, theme.bracket_3];
         // "{ ( ) }" — { at depth 0, ( at depth 1, ) at depth 1, } at depth 0
-        let result = highlight_line("{ ( ) }", SyntaxLang::Rust, &theme, 0, &bc);
+        let result = highlight_line("{ ( ) }", SyntaxLang::Rust, &theme, 0, LineState::Code, &bc);
         let bracket_spans: Vec<_> = result
             .spans
             .iter()
@@ -1,9 +1,18 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::PathBuf;
+use std::time::SystemTime;
 
-use ratatui_textarea::TextArea;
+use ratatui_textarea::{CursorMove, TextArea};
 
 use crate::lsp_client::LspDiagnostic;
+use crate::merge_conflicts::{ConflictRegion, find_conflicts};
+use crate::persistence::content_hash;
+use crate::syntax::{
+    HighlightCache, LineState, SyntaxLang, compute_line_states, syntax_lang_for_path,
+};
+use crate::util::{
+    compute_bracket_matches, compute_fold_ranges, to_u16_saturating, wrap_segments_for_line,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub(crate) enum GitLineStatus {
@@ -47,26 +56,570 @@ pub(crate) struct FoldRange {
     pub(crate) end_line: usize,
 }
 
+/// A search hit on one line, as char columns `start..end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SearchMatch {
+    pub(crate) row: usize,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+/// A command's edit recorded as one undo unit: the live-history entries it
+/// pushed, the text on either side of it, and the cursor and selection anchor
+/// to put back on undo.
+#[derive(Debug, Clone)]
+pub(crate) struct EditGroup {
+    pub(crate) steps: usize,
+    pub(crate) before_hash: u64,
+    pub(crate) after_hash: u64,
+    pub(crate) cursor_before: (usize, usize),
+    pub(crate) anchor_before: Option<(usize, usize)>,
+}
+
+/// How much of a file opened in large file mode has been read into the
+/// buffer.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LargeFile {
+    pub(crate) loaded_bytes: u64,
+    /// File size when it was last checked.
+    pub(crate) total_bytes: u64,
+    /// Modification time when it was last checked.
+    pub(crate) modified: Option<SystemTime>,
+    /// `tail_fingerprint` of the loaded bytes.
+    pub(crate) tail: u64,
+}
+
+impl LargeFile {
+    pub(crate) fn fully_loaded(&self) -> bool {
+        self.loaded_bytes >= self.total_bytes
+    }
+}
+
+/// A read or write of a tab's file running on a worker thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TabIo {
+    /// The buffer stays empty and refuses edits until the read arrives.
+    Loading {
+        force_text: bool,
+    },
+    Saving,
+}
+
 pub(crate) struct Tab {
     pub(crate) path: PathBuf,
     pub(crate) is_preview: bool,
+    /// A file outside the project opened by go-to-definition (a dependency
+    /// or the standard library); edits and saves are refused.
+    pub(crate) read_only: bool,
+    /// Kept left of unpinned tabs and spared by "close others".
+    pub(crate) pinned: bool,
+    /// Highlighting and folding language: detected from the path, or a
+    /// remembered override from "Set Language Mode".
+    pub(crate) lang: SyntaxLang,
     pub(crate) editor: TextArea<'static>,
     pub(crate) dirty: bool,
+    /// The file ends its lines with `\r\n`; saving writes them back that way.
+    pub(crate) crlf: bool,
+    /// The file is UTF-16 with a BOM, `Some(true)` little-endian; saving
+    /// writes it back that way.
+    pub(crate) utf16: Option<bool>,
     pub(crate) open_disk_snapshot: Option<String>,
     pub(crate) editor_scroll_row: usize,
     pub(crate) editor_scroll_col: usize,
     pub(crate) fold_ranges: Vec<FoldRange>,
     pub(crate) bracket_depths: Vec<u16>,
+    /// Comment or string each line starts inside, for highlighting.
+    pub(crate) line_states: Vec<LineState>,
+    /// Paired bracket positions `(row, char col)`, mapped both ways.
+    pub(crate) bracket_matches: HashMap<(usize, usize), (usize, usize)>,
     pub(crate) folded_starts: HashSet<usize>,
     pub(crate) visible_rows_map: Vec<usize>,
     pub(crate) visible_row_starts: Vec<usize>,
     pub(crate) visible_row_ends: Vec<usize>,
     pub(crate) open_doc_uri: Option<String>,
     pub(crate) open_doc_version: i32,
+    /// The document as the language server last saw it; edits are diffed
+    /// against it so only the changed lines are sent.
+    pub(crate) lsp_text: Vec<String>,
     pub(crate) diagnostics: Vec<LspDiagnostic>,
     pub(crate) conflict_prompt_open: bool,
     pub(crate) conflict_disk_text: Option<String>,
     pub(crate) recovery_prompt_open: bool,
     pub(crate) recovery_text: Option<String>,
     pub(crate) git_line_status: Vec<GitLineStatus>,
+    /// Matches of the editor's search pattern, sorted by position.
+    pub(crate) search_matches: Vec<SearchMatch>,
+    /// Versions from before the file was last reopened, oldest first; undo
+    /// continues into these once the live history runs out.
+    pub(crate) undo_snapshots: Vec<String>,
+    /// Versions stepped back over with those restored undos, newest last.
+    pub(crate) redo_snapshots: Vec<String>,
+    /// Edits made by commands, undone and redone as a whole while the live
+    /// history still lines up with them.
+    pub(crate) undo_groups: Vec<EditGroup>,
+    pub(crate) redo_groups: Vec<EditGroup>,
+    /// Set for files over the large file threshold: plain text without
+    /// folding, LSP or git markers, read in chunks as the view nears the end
+    /// of what is loaded.
+    pub(crate) large_file: Option<LargeFile>,
+    /// "Follow File": the view stays at the end and text other programs
+    /// append is added as it arrives. Edits are refused meanwhile.
+    pub(crate) follow_tail: bool,
+    pub(crate) highlight_cache: HighlightCache,
+    /// Bookmarked rows, kept in step with edits.
+    pub(crate) bookmarks: BTreeSet<usize>,
+    /// Line count when the bookmarks were last shifted; an edit that
+    /// changes it moves the bookmarks below the cursor by the difference.
+    pub(crate) bookmarks_line_count: usize,
+    /// Merge conflict blocks in the text, top to bottom.
+    pub(crate) merge_conflicts: Vec<ConflictRegion>,
+    /// Closers typed in with their opener, as (row, chars from the closer
+    /// to the end of the line), which typing inside the pair leaves as is.
+    /// Typing the closer right before one steps over it.
+    pub(crate) auto_closers: Vec<(usize, usize)>,
+    /// Set while the file is read or written in the background, on
+    /// filesystems `watch.json` marks as slow.
+    pub(crate) pending_io: Option<TabIo>,
+}
+
+impl Tab {
+    pub(crate) const UNDO_HISTORY_MAX_BYTES: usize = 4 * 1024 * 1024;
+
+    /// A clean tab for `path` with folds computed and one visible row per line.
+    pub(crate) fn new(path: PathBuf, editor: TextArea<'static>) -> Self {
+        let mut tab = Self {
+            lang: syntax_lang_for_path(Some(path.as_path())),
+            path,
+            is_preview: false,
+            read_only: false,
+            pinned: false,
+            editor,
+            dirty: false,
+            crlf: false,
+            utf16: None,
+            open_disk_snapshot: None,
+            editor_scroll_row: 0,
+            editor_scroll_col: 0,
+            fold_ranges: Vec::new(),
+            bracket_depths: Vec::new(),
+            line_states: Vec::new(),
+            bracket_matches: HashMap::new(),
+            folded_starts: HashSet::new(),
+            visible_rows_map: Vec::new(),
+            visible_row_starts: Vec::new(),
+            visible_row_ends: Vec::new(),
+            open_doc_uri: None,
+            open_doc_version: 0,
+            lsp_text: Vec::new(),
+            diagnostics: Vec::new(),
+            conflict_prompt_open: false,
+            conflict_disk_text: None,
+            recovery_prompt_open: false,
+            recovery_text: None,
+            git_line_status: Vec::new(),
+            search_matches: Vec::new(),
+            undo_snapshots: Vec::new(),
+            redo_snapshots: Vec::new(),
+            undo_groups: Vec::new(),
+            redo_groups: Vec::new(),
+            large_file: None,
+            follow_tail: false,
+            highlight_cache: HighlightCache::default(),
+            bookmarks: BTreeSet::new(),
+            bookmarks_line_count: 0,
+            merge_conflicts: Vec::new(),
+            auto_closers: Vec::new(),
+            pending_io: None,
+        };
+        tab.recompute_folds();
+        tab.rebuild_visible_rows(false, usize::MAX);
+        tab
+    }
+
+    /// Recompute fold ranges, bracket depths, line states and merge
+    /// conflicts, dropping folds whose block is gone.
+    /// Callers rebuild visible rows afterwards.
+    pub(crate) fn recompute_folds(&mut self) {
+        if self.large_file.is_some() {
+            self.fold_ranges.clear();
+            self.bracket_depths.clear();
+            self.line_states.clear();
+            self.bracket_matches.clear();
+            self.folded_starts.clear();
+            self.merge_conflicts.clear();
+            return;
+        }
+        let (fold_ranges, bracket_depths) = compute_fold_ranges(self.editor.lines(), self.lang);
+        self.fold_ranges = fold_ranges;
+        self.bracket_depths = bracket_depths;
+        self.line_states = compute_line_states(self.editor.lines(), self.lang);
+        self.bracket_matches = compute_bracket_matches(self.editor.lines(), self.lang);
+        self.merge_conflicts = find_conflicts(self.editor.lines());
+        self.folded_starts
+            .retain(|start| self.fold_ranges.iter().any(|r| r.start_line == *start));
+    }
+
+    /// Columns a tab character takes on screen, from the buffer's tab
+    /// length (the `tab_width` setting).
+    pub(crate) fn tab_width(&self) -> usize {
+        usize::from(self.editor.tab_length().max(1))
+    }
+
+    pub(crate) fn rebuild_visible_rows(&mut self, word_wrap: bool, wrap_width: usize) {
+        let tab_width = self.tab_width();
+        let lines = self.editor.lines();
+        let num_lines = lines.len();
+        // Precompute hidden rows via HashSet for O(1) lookup per row
+        let mut hidden: HashSet<usize> = HashSet::new();
+        for fr in &self.fold_ranges {
+            if self.folded_starts.contains(&fr.start_line) {
+                for row in (fr.start_line + 1)..=fr.end_line {
+                    hidden.insert(row);
+                }
+            }
+        }
+        self.visible_rows_map.clear();
+        self.visible_row_starts.clear();
+        self.visible_row_ends.clear();
+        self.visible_rows_map
+            .reserve(num_lines.saturating_sub(hidden.len()));
+        self.visible_row_starts
+            .reserve(num_lines.saturating_sub(hidden.len()));
+        self.visible_row_ends
+            .reserve(num_lines.saturating_sub(hidden.len()));
+        for (row, line) in lines.iter().enumerate() {
+            if !hidden.contains(&row) {
+                let segments = if word_wrap {
+                    wrap_segments_for_line(line, wrap_width, tab_width)
+                } else {
+                    vec![(0, line.chars().count())]
+                };
+                for (start, end) in segments {
+                    self.visible_rows_map.push(row);
+                    self.visible_row_starts.push(start);
+                    self.visible_row_ends.push(end);
+                }
+            }
+        }
+        if self.visible_rows_map.is_empty() {
+            self.visible_rows_map.push(0);
+            self.visible_row_starts.push(0);
+            self.visible_row_ends.push(0);
+        }
+        let max_scroll = self.visible_rows_map.len().saturating_sub(1);
+        self.editor_scroll_row = self.editor_scroll_row.min(max_scroll);
+    }
+
+    pub(crate) fn visible_index_of_source_row(&self, row: usize) -> usize {
+        self.visible_rows_map
+            .iter()
+            .position(|r| *r == row)
+            .unwrap_or_else(|| {
+                self.visible_rows_map
+                    .iter()
+                    .position(|r| *r > row)
+                    .unwrap_or(self.visible_rows_map.len().saturating_sub(1))
+            })
+    }
+
+    pub(crate) fn visible_index_of_source_position(&self, row: usize, col: usize) -> usize {
+        let mut fallback = None;
+        for idx in 0..self.visible_rows_map.len() {
+            if self.visible_rows_map[idx] != row {
+                continue;
+            }
+            fallback.get_or_insert(idx);
+            let start = self.visible_row_starts.get(idx).copied().unwrap_or(0);
+            let end = self.visible_row_ends.get(idx).copied().unwrap_or(start);
+            if col >= start && col < end {
+                return idx;
+            }
+            if col >= end {
+                fallback = Some(idx);
+            }
+        }
+        fallback.unwrap_or_else(|| self.visible_index_of_source_row(row))
+    }
+
+    /// Fold the innermost block starting at or containing `row`, returning its lines.
+    pub(crate) fn fold_block_at(&mut self, row: usize) -> Option<(usize, usize)> {
+        let mut candidate: Option<(usize, usize)> = None;
+        for fr in &self.fold_ranges {
+            if fr.start_line == row {
+                candidate = Some((fr.start_line, fr.end_line));
+                break;
+            }
+            if fr.start_line <= row && row <= fr.end_line {
+                candidate = Some((fr.start_line, fr.end_line));
+            }
+        }
+        if let Some((start_line, _)) = candidate {
+            self.folded_starts.insert(start_line);
+        }
+        candidate
+    }
+
+    /// Unfold one folded block starting at or containing `row`.
+    pub(crate) fn unfold_block_at(&mut self, row: usize) -> bool {
+        let found = self.folded_starts.iter().copied().find(|start| {
+            self.fold_ranges
+                .iter()
+                .find(|fr| fr.start_line == *start)
+                .is_some_and(|fr| fr.start_line <= row && row <= fr.end_line)
+        });
+        if let Some(start) = found {
+            self.folded_starts.remove(&start);
+        }
+        found.is_some()
+    }
+
+    pub(crate) fn is_folded_at(&self, row: usize) -> bool {
+        self.folded_starts.iter().any(|start| {
+            self.fold_ranges
+                .iter()
+                .find(|fr| fr.start_line == *start)
+                .is_some_and(|fr| fr.start_line <= row && row <= fr.end_line)
+        })
+    }
+
+    /// Every earlier version undo can reach, oldest first: the restored
+    /// snapshots, then each step of the live history. Keeps the newest that
+    /// fit in [`Self::UNDO_HISTORY_MAX_BYTES`].
+    pub(crate) fn undo_states(&self) -> Vec<String> {
+        let mut editor = self.editor.clone();
+        let mut states = Vec::new();
+        while editor.undo() {
+            states.push(editor.lines().join("\n"));
+        }
+        states.extend(self.undo_snapshots.iter().rev().cloned());
+        let mut bytes = 0;
+        let keep = states
+            .iter()
+            .take_while(|state| {
+                bytes += state.len();
+                bytes <= Self::UNDO_HISTORY_MAX_BYTES
+            })
+            .count();
+        states.truncate(keep);
+        states.reverse();
+        states
+    }
+
+    /// Versions the live history can redo to, in redo order.
+    pub(crate) fn redo_states(&self) -> Vec<String> {
+        let mut editor = self.editor.clone();
+        let mut states = Vec::new();
+        while editor.redo() {
+            states.push(editor.lines().join("\n"));
+        }
+        states
+    }
+
+    /// Turn the buffer into `new_lines` as a single undo step, replacing only
+    /// the rows that differ, and leave the cursor at `cursor`. Returns false
+    /// when the text is already `new_lines`.
+    pub(crate) fn apply_edit(&mut self, new_lines: &[String], cursor: (usize, usize)) -> bool {
+        let old = self.editor.lines();
+        if old == new_lines {
+            return false;
+        }
+        let mut prefix = old
+            .iter()
+            .zip(new_lines)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let mut suffix = old
+            .iter()
+            .rev()
+            .zip(new_lines.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count()
+            .min(old.len().min(new_lines.len()) - prefix);
+        // Pure insertions and deletions still replace one row, so the edit is
+        // always a selection swapped for the new rows.
+        if prefix + suffix == old.len() || prefix + suffix == new_lines.len() {
+            if prefix > 0 {
+                prefix -= 1;
+            } else {
+                suffix -= 1;
+            }
+        }
+        let last_row = old.len() - suffix - 1;
+        let last_col = old[last_row].chars().count();
+        let replacement = new_lines[prefix..new_lines.len() - suffix].join("\n");
+        let before_hash = content_hash(&old.join("\n"));
+        let cursor_before = self.editor.cursor();
+        let anchor_before = self.selection_anchor();
+
+        self.editor.cancel_selection();
+        self.jump_to((prefix, 0));
+        self.editor.start_selection();
+        self.jump_to((last_row, last_col));
+        let steps = usize::from(self.editor.delete_str(0))
+            + usize::from(self.editor.insert_str(replacement));
+        self.jump_to(cursor);
+
+        self.undo_groups.push(EditGroup {
+            steps,
+            before_hash,
+            after_hash: content_hash(&self.editor.lines().join("\n")),
+            cursor_before,
+            anchor_before,
+        });
+        if self.undo_groups.len() > self.editor.max_histories() {
+            self.undo_groups.remove(0);
+        }
+        self.redo_groups.clear();
+        true
+    }
+
+    /// Undo the last grouped edit in one go, if that is what undo reaches
+    /// next, and restore the cursor and selection from before it.
+    pub(crate) fn undo_group(&mut self) -> bool {
+        let Some(group) = self.undo_groups.last() else {
+            return false;
+        };
+        let Some(editor) = self.replay(group.steps, group.after_hash, group.before_hash, true)
+        else {
+            return false;
+        };
+        self.editor = editor;
+        let Some(group) = self.undo_groups.pop() else {
+            return false;
+        };
+        if let Some(anchor) = group.anchor_before {
+            self.jump_to(anchor);
+            self.editor.start_selection();
+        }
+        self.jump_to(group.cursor_before);
+        self.redo_groups.push(group);
+        true
+    }
+
+    /// Redo the grouped edit last undone by [`Self::undo_group`].
+    pub(crate) fn redo_group(&mut self) -> bool {
+        let Some(group) = self.redo_groups.last() else {
+            return false;
+        };
+        let Some(editor) = self.replay(group.steps, group.before_hash, group.after_hash, false)
+        else {
+            return false;
+        };
+        self.editor = editor;
+        if let Some(group) = self.redo_groups.pop() {
+            self.undo_groups.push(group);
+        }
+        true
+    }
+
+    /// Undo or redo `steps` entries on a copy of the editor, provided the
+    /// text hashes to `from` now and to `to` afterwards; typing since the
+    /// group was recorded leaves it to the ordinary history.
+    fn replay(&self, steps: usize, from: u64, to: u64, undo: bool) -> Option<TextArea<'static>> {
+        if content_hash(&self.editor.lines().join("\n")) != from {
+            return None;
+        }
+        let mut editor = self.editor.clone();
+        for _ in 0..steps {
+            let moved = if undo { editor.undo() } else { editor.redo() };
+            if !moved {
+                return None;
+            }
+        }
+        editor.cancel_selection();
+        (content_hash(&editor.lines().join("\n")) == to).then_some(editor)
+    }
+
+    /// The end of the selection opposite the cursor.
+    fn selection_anchor(&self) -> Option<(usize, usize)> {
+        let (start, end) = self.editor.selection_range()?;
+        Some(if self.editor.cursor() == start {
+            end
+        } else {
+            start
+        })
+    }
+
+    /// The bracket under the cursor, or else the one just before it,
+    /// paired with its match.
+    pub(crate) fn bracket_match_at_cursor(&self) -> Option<((usize, usize), (usize, usize))> {
+        let (row, col) = self.editor.cursor();
+        [Some(col), col.checked_sub(1)]
+            .into_iter()
+            .flatten()
+            .find_map(|c| self.bracket_matches.get(&(row, c)).map(|&m| ((row, c), m)))
+    }
+
+    /// Edits are refused in external sources, in followed files, and in
+    /// large files until the rest of the file is loaded.
+    pub(crate) fn refuses_edits(&self) -> bool {
+        self.read_only
+            || self.follow_tail
+            || self.is_loading()
+            || self.large_file.is_some_and(|f| !f.fully_loaded())
+    }
+
+    /// The buffer as written on save: joined with its line endings and
+    /// ending in one.
+    pub(crate) fn disk_text(&self) -> String {
+        let eol = if self.crlf { "\r\n" } else { "\n" };
+        let mut content = self.editor.lines().join(eol);
+        // Ensure file ends with a trailing newline (POSIX convention)
+        if !content.ends_with('\n') {
+            content.push_str(eol);
+        }
+        content
+    }
+
+    pub(crate) fn is_loading(&self) -> bool {
+        matches!(self.pending_io, Some(TabIo::Loading { .. }))
+    }
+
+    /// Add `text` after the last line, leaving the cursor where it was.
+    /// Clears the undo history, so it is only used on buffers that are
+    /// not edited yet.
+    pub(crate) fn append_text(&mut self, text: &str) {
+        let (row, col) = self.editor.cursor();
+        let max_histories = self.editor.max_histories();
+        self.editor.set_max_histories(0);
+        self.editor.cancel_selection();
+        self.editor.move_cursor(CursorMove::Bottom);
+        self.editor.move_cursor(CursorMove::End);
+        self.editor.insert_str(text);
+        self.editor.set_max_histories(max_histories);
+        if let (Ok(r), Ok(c)) = (u16::try_from(row), u16::try_from(col)) {
+            self.editor.move_cursor(CursorMove::Jump(r, c));
+            return;
+        }
+        // Jump only reaches the first 65536 rows and columns.
+        for _ in row..self.editor.cursor().0 {
+            self.editor.move_cursor(CursorMove::Up);
+        }
+        self.editor.move_cursor(CursorMove::Head);
+        for _ in 0..col {
+            self.editor.move_cursor(CursorMove::Forward);
+        }
+    }
+
+    /// Put the cursor after the last character, as Follow File keeps it.
+    pub(crate) fn move_to_end(&mut self) {
+        self.editor.cancel_selection();
+        self.editor.move_cursor(CursorMove::Bottom);
+        self.editor.move_cursor(CursorMove::End);
+    }
+
+    pub(crate) fn jump_to(&mut self, (row, col): (usize, usize)) {
+        self.editor.move_cursor(CursorMove::Jump(
+            to_u16_saturating(row),
+            to_u16_saturating(col),
+        ));
+    }
+
+    /// Fold every block, returning how many are folded.
+    pub(crate) fn fold_all(&mut self) -> usize {
+        for fr in &self.fold_ranges {
+            self.folded_starts.insert(fr.start_line);
+        }
+        self.folded_starts.len()
+    }
 }
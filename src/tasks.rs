@@ -0,0 +1,231 @@
+//! "Run Task": build, test and run commands found in the project (cargo,
+//! npm scripts, Makefile targets), run in the background with their output
+//! streamed line by line.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde_json::Value;
+
+/// A command the project defines, run from `cwd`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Task {
+    /// Shown in the picker, e.g. `cargo test` or `npm run lint`.
+    pub(crate) label: String,
+    pub(crate) program: String,
+    pub(crate) args: Vec<String>,
+    pub(crate) cwd: PathBuf,
+}
+
+impl Task {
+    pub(crate) fn new(cwd: &Path, program: &str, args: &[&str]) -> Self {
+        let label = std::iter::once(program)
+            .chain(args.iter().copied())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self {
+            label,
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            cwd: cwd.to_path_buf(),
+        }
+    }
+}
+
+/// What a running task reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TaskEvent {
+    /// A line of stdout or stderr, in the order they arrived.
+    Line(String),
+    /// The process exited; `None` when it was killed or never started.
+    Finished(Option<i32>),
+}
+
+/// The tasks in `root`: cargo's build, test and run for a `Cargo.toml`,
+/// each `scripts` entry of a `package.json`, and the targets of a
+/// Makefile.
+pub(crate) fn detect_tasks(root: &Path) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    if root.join("Cargo.toml").is_file() {
+        for sub in ["build", "test", "run", "check", "clippy"] {
+            tasks.push(Task::new(root, "cargo", &[sub]));
+        }
+    }
+    if let Ok(raw) = fs::read_to_string(root.join("package.json"))
+        && let Ok(package) = serde_json::from_str::<Value>(&raw)
+        && let Some(scripts) = package.get("scripts").and_then(Value::as_object)
+    {
+        for name in scripts.keys() {
+            tasks.push(Task::new(root, "npm", &["run", name]));
+        }
+    }
+    let makefile = ["GNUmakefile", "makefile", "Makefile"]
+        .iter()
+        .find_map(|name| fs::read_to_string(root.join(name)).ok());
+    if let Some(makefile) = makefile {
+        for target in makefile_targets(&makefile) {
+            tasks.push(Task::new(root, "make", &[target]));
+        }
+    }
+    tasks
+}
+
+/// Explicit targets of a Makefile in order: `name:` at the start of a
+/// line, skipping special (`.PHONY`), pattern (`%.o`) and variable
+/// (`CC := cc`) lines.
+pub(crate) fn makefile_targets(text: &str) -> Vec<&str> {
+    let mut targets: Vec<&str> = Vec::new();
+    for line in text.lines() {
+        let Some((head, rest)) = line.split_once(':') else {
+            continue;
+        };
+        if line.starts_with(['\t', ' ', '#', '.']) || head.contains('=') || rest.starts_with('=') {
+            continue;
+        }
+        for target in head.split_whitespace() {
+            let plain = target
+                .chars()
+                .all(|c| c.is_alphanumeric() || "_-./".contains(c));
+            if plain && !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+    }
+    targets
+}
+
+/// Forward each line read from `reader` to `tx`.
+fn forward_lines(reader: impl Read + Send + 'static, tx: Sender<TaskEvent>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(reader).split(b'\n').map_while(Result::ok) {
+            let line = String::from_utf8_lossy(&line).trim_end().to_string();
+            if tx.send(TaskEvent::Line(line)).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Start `task` on a background thread with `env` added to its
+/// environment. Output lines and then the exit status go to `tx`; setting
+/// `stop` kills the process.
+pub(crate) fn spawn_task(
+    task: &Task,
+    env: &[(String, String)],
+    tx: Sender<TaskEvent>,
+    stop: Arc<AtomicBool>,
+) -> io::Result<JoinHandle<()>> {
+    let mut child = Command::new(&task.program)
+        .args(&task.args)
+        .current_dir(&task.cwd)
+        // Plain text: the panel does not render ANSI colors.
+        .env("CARGO_TERM_COLOR", "never")
+        .env("NO_COLOR", "1")
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let readers: Vec<_> = [
+        child.stdout.take().map(|r| forward_lines(r, tx.clone())),
+        child.stderr.take().map(|r| forward_lines(r, tx.clone())),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    Ok(thread::spawn(move || {
+        let status = loop {
+            if stop.load(Ordering::Relaxed) {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+            match child.try_wait() {
+                Ok(Some(status)) => break status.code(),
+                Ok(None) => thread::sleep(Duration::from_millis(50)),
+                Err(_) => break None,
+            }
+        };
+        for reader in readers {
+            let _ = reader.join();
+        }
+        let _ = tx.send(TaskEvent::Finished(status));
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use tempfile::tempdir;
+
+    #[test]
+    fn detects_cargo_npm_and_make_tasks() {
+        let tmp = tempdir().expect("tempdir");
+        let root = tmp.path();
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"demo\"\n").expect("write");
+        fs::write(
+            root.join("package.json"),
+            r#"{"scripts":{"dev":"vite","lint":"eslint ."}}"#,
+        )
+        .expect("write");
+        fs::write(
+            root.join("Makefile"),
+            "CC := cc\n.PHONY: all clean\nall: build\n\tcc -o app main.c\n%.o: %.c\n\t$(CC) -c $<\nclean:\n\trm -f app\n# docs: skip\n",
+        )
+        .expect("write");
+        let labels: Vec<_> = detect_tasks(root).into_iter().map(|t| t.label).collect();
+        assert_eq!(
+            labels,
+            [
+                "cargo build",
+                "cargo test",
+                "cargo run",
+                "cargo check",
+                "cargo clippy",
+                "npm run dev",
+                "npm run lint",
+                "make all",
+                "make clean",
+            ]
+        );
+        assert!(detect_tasks(&root.join("missing")).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn streams_output_then_the_exit_code() {
+        let tmp = tempdir().expect("tempdir");
+        let task = Task {
+            label: "sh".to_string(),
+            program: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                "echo $GREETING; echo two; exit 3".to_string(),
+            ],
+            cwd: tmp.path().to_path_buf(),
+        };
+        let (tx, rx) = mpsc::channel();
+        let env = [("GREETING".to_string(), "one".to_string())];
+        spawn_task(&task, &env, tx, Arc::default())
+            .expect("spawn")
+            .join()
+            .expect("join");
+        let events: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            events,
+            [
+                TaskEvent::Line("one".to_string()),
+                TaskEvent::Line("two".to_string()),
+                TaskEvent::Finished(Some(3)),
+            ]
+        );
+    }
+}
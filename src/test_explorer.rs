@@ -0,0 +1,229 @@
+//! The test explorer's model: test names from `cargo test -- --list`, the
+//! module tree they form, and results read back from libtest's output.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// A test's last result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TestOutcome {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+/// One row of the tree: a module or a test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TestRow {
+    /// Full path, e.g. `app::tests::opens_file`.
+    pub(crate) path: String,
+    pub(crate) depth: usize,
+    pub(crate) is_test: bool,
+}
+
+impl TestRow {
+    /// The last path segment.
+    pub(crate) fn name(&self) -> &str {
+        self.path.rsplit("::").next().unwrap_or(&self.path)
+    }
+}
+
+/// Test names in `cargo test -- --list` output, sorted and without
+/// duplicates across test binaries. Benchmarks and doc tests are skipped.
+pub(crate) fn parse_test_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_suffix(": test"))
+        .filter(|name| !name.contains(' '))
+        .map(str::to_string)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Module and test rows for `tests`, depth first. Rows under a module in
+/// `collapsed` are left out.
+pub(crate) fn test_rows(tests: &[String], collapsed: &BTreeSet<String>) -> Vec<TestRow> {
+    let mut rows: Vec<TestRow> = Vec::new();
+    let mut modules: BTreeSet<String> = BTreeSet::new();
+    for test in tests {
+        let segments: Vec<&str> = test.split("::").collect();
+        let mut hidden = false;
+        for depth in 0..segments.len() {
+            let path = segments[..=depth].join("::");
+            let is_test = depth == segments.len() - 1;
+            if !hidden && (is_test || modules.insert(path.clone())) {
+                rows.push(TestRow {
+                    path: path.clone(),
+                    depth,
+                    is_test,
+                });
+            }
+            hidden |= collapsed.contains(&path);
+        }
+    }
+    rows
+}
+
+/// A libtest result line, `test a::b ... ok`, as the test and its outcome.
+pub(crate) fn parse_test_result(line: &str) -> Option<(&str, TestOutcome)> {
+    let (name, result) = line.strip_prefix("test ")?.split_once(" ... ")?;
+    let outcome = match result.trim() {
+        "ok" => TestOutcome::Passed,
+        "FAILED" => TestOutcome::Failed,
+        r if r.starts_with("ignored") => TestOutcome::Ignored,
+        _ => return None,
+    };
+    Some((name, outcome))
+}
+
+/// The test a panic message is about and the rest of the line, which names
+/// where it panicked: `thread 'a::b' panicked at src/lib.rs:3:5:`.
+pub(crate) fn parse_test_panic(line: &str) -> Option<(&str, &str)> {
+    let (name, rest) = line
+        .strip_prefix("thread '")?
+        .split_once("' panicked at ")?;
+    Some((name, rest))
+}
+
+/// How a module's tests did: failed if any failed, passed once all ran
+/// and passed (or were ignored), unknown otherwise.
+pub(crate) fn module_outcome(
+    module: &str,
+    tests: &[String],
+    outcomes: &HashMap<String, TestOutcome>,
+) -> Option<TestOutcome> {
+    let prefix = format!("{module}::");
+    let mut all_passed = true;
+    for test in tests.iter().filter(|t| t.starts_with(&prefix)) {
+        match outcomes.get(test) {
+            Some(TestOutcome::Failed) => return Some(TestOutcome::Failed),
+            Some(_) => {}
+            None => all_passed = false,
+        }
+    }
+    all_passed.then_some(TestOutcome::Passed)
+}
+
+/// `cargo` arguments running one test, every test under a module, or all
+/// of them.
+pub(crate) fn test_run_args(row: Option<&TestRow>) -> Vec<String> {
+    let mut args = vec!["test".to_string()];
+    match row {
+        Some(row) if row.is_test => {
+            args.extend(["--".to_string(), "--exact".to_string(), row.path.clone()]);
+        }
+        Some(row) => args.extend(["--".to_string(), format!("{}::", row.path)]),
+        None => {}
+    }
+    args
+}
+
+/// List the workspace's tests on a background thread. A build failure
+/// comes back as an error with cargo's last message.
+pub(crate) fn spawn_test_list(root: &Path) -> Receiver<io::Result<Vec<String>>> {
+    let (tx, rx) = mpsc::channel();
+    let root = root.to_path_buf();
+    thread::spawn(move || {
+        let result = Command::new("cargo")
+            .args(["test", "--quiet", "--", "--list"])
+            .env("CARGO_TERM_COLOR", "never")
+            .current_dir(&root)
+            .stdin(Stdio::null())
+            .output()
+            .and_then(|output| {
+                if output.status.success() {
+                    return Ok(parse_test_list(&String::from_utf8_lossy(&output.stdout)));
+                }
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let reason = stderr
+                    .lines()
+                    .rfind(|l| l.starts_with("error"))
+                    .unwrap_or("cargo test failed");
+                Err(io::Error::other(reason.to_string()))
+            });
+        let _ = tx.send(result);
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_the_tree_and_reads_results() {
+        let tests = parse_test_list(
+            "app::tests::opens: test\nutil::parses: test\napp::tests::saves: test\n\
+             bench_sort: benchmark\nsrc/lib.rs - add (line 3): test\nutil::parses: test\n\n\
+             3 tests, 1 benchmark\n",
+        );
+        assert_eq!(
+            tests,
+            ["app::tests::opens", "app::tests::saves", "util::parses"]
+        );
+
+        let paths = |rows: Vec<TestRow>| {
+            rows.into_iter()
+                .map(|r| format!("{}{}", "  ".repeat(r.depth), r.name()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            paths(test_rows(&tests, &BTreeSet::new())),
+            [
+                "app",
+                "  tests",
+                "    opens",
+                "    saves",
+                "util",
+                "  parses"
+            ]
+        );
+        let collapsed = BTreeSet::from(["app::tests".to_string()]);
+        assert_eq!(
+            paths(test_rows(&tests, &collapsed)),
+            ["app", "  tests", "util", "  parses"]
+        );
+
+        assert_eq!(
+            parse_test_result("test app::tests::opens ... FAILED"),
+            Some(("app::tests::opens", TestOutcome::Failed))
+        );
+        assert_eq!(
+            parse_test_result("test util::parses ... ignored, slow"),
+            Some(("util::parses", TestOutcome::Ignored))
+        );
+        assert_eq!(parse_test_result("test result: ok. 2 passed"), None);
+        assert_eq!(
+            parse_test_panic("thread 'app::tests::opens' panicked at src/app.rs:10:5:"),
+            Some(("app::tests::opens", "src/app.rs:10:5:"))
+        );
+
+        let mut outcomes = HashMap::from([("app::tests::opens".to_string(), TestOutcome::Passed)]);
+        assert_eq!(module_outcome("app", &tests, &outcomes), None);
+        outcomes.insert("app::tests::saves".to_string(), TestOutcome::Failed);
+        assert_eq!(
+            module_outcome("app::tests", &tests, &outcomes),
+            Some(TestOutcome::Failed)
+        );
+
+        let row = |path: &str, is_test| TestRow {
+            path: path.to_string(),
+            depth: 0,
+            is_test,
+        };
+        assert_eq!(
+            test_run_args(Some(&row("util::parses", true))),
+            ["test", "--", "--exact", "util::parses"]
+        );
+        assert_eq!(
+            test_run_args(Some(&row("app", false))),
+            ["test", "--", "app::"]
+        );
+        assert_eq!(test_run_args(None), ["test"]);
+    }
+}
@@ -126,6 +126,8 @@ pub(crate) struct Theme {
     pub(crate) bracket_1: Color,
     pub(crate) bracket_2: Color,
     pub(crate) bracket_3: Color,
+    /// Warning text, from the theme's `yellow`.
+    pub(crate) warning: Color,
 }
 
 #[derive(Debug, Deserialize)]
@@ -253,6 +255,13 @@ pub(crate) fn theme_from_file(tf: ThemeFile) -> Theme {
             .map_or(make_color(0, 175, 215), |c| {
                 color_from_hex(c, make_color(0, 175, 215))
             }),
+        warning: tf
+            .colors
+            .yellow
+            .as_ref()
+            .map_or(make_color(210, 168, 75), |c| {
+                color_from_hex(c, make_color(210, 168, 75))
+            }),
     }
 }
 
@@ -311,8 +320,10 @@ pub(crate) fn load_themes() -> Vec<Theme> {
 #[cfg(test)]
 mod theme_and_persistence_tests {
     use super::*;
-    use crate::persistence::PersistedState;
+    use crate::persistence::{LspSettings, PersistedState, SearchHistory};
+    use crate::syntax::SyntaxLang;
     use ratatui::style::Color;
+    use std::collections::BTreeMap;
     use std::fs;
     use std::path::PathBuf;
 
@@ -355,12 +366,53 @@ mod theme_and_persistence_tests {
             theme_name: "Dracula".to_string(),
             files_pane_width: Some(30),
             word_wrap: Some(true),
+            show_ignored: Some(true),
+            nerd_font_icons: Some(false),
+            search_history: SearchHistory {
+                find: vec!["fn main".to_string()],
+                replace: Vec::new(),
+                project: vec!["TODO".to_string()],
+            },
+            language_overrides: BTreeMap::from([(
+                PathBuf::from("/src/Justfile"),
+                SyntaxLang::Shell,
+            )]),
+            trusted_projects: BTreeMap::from([(PathBuf::from("/src"), true)]),
+            pinned_paths: BTreeMap::from([(
+                PathBuf::from("/src"),
+                vec![PathBuf::from("/src/lib.rs")],
+            )]),
+            workspace_folders: BTreeMap::from([(
+                PathBuf::from("/src"),
+                vec![PathBuf::from("/shared")],
+            )]),
+            bookmarks: BTreeMap::from([(
+                PathBuf::from("/src"),
+                BTreeMap::from([(PathBuf::from("/src/lib.rs"), [3, 10].into())]),
+            )]),
+            recent_files: BTreeMap::from([(
+                PathBuf::from("/src"),
+                vec![PathBuf::from("/src/main.rs")],
+            )]),
+            recent_projects: vec![PathBuf::from("/src"), PathBuf::from("/other")],
+            recent_commands: vec!["Go to Line".to_string()],
         };
         let json = serde_json::to_string(&state).unwrap();
         let de: PersistedState = serde_json::from_str(&json).unwrap();
         assert_eq!(de.theme_name, "Dracula");
         assert_eq!(de.files_pane_width, Some(30));
         assert_eq!(de.word_wrap, Some(true));
+        assert_eq!(de.show_ignored, Some(true));
+        assert_eq!(de.nerd_font_icons, Some(false));
+        assert_eq!(de.search_history, state.search_history);
+        assert_eq!(de.language_overrides, state.language_overrides);
+        assert_eq!(de.trusted_projects, state.trusted_projects);
+        assert_eq!(de.pinned_paths, state.pinned_paths);
+        assert_eq!(de.workspace_folders, state.workspace_folders);
+        assert_eq!(de.bookmarks, state.bookmarks);
+        assert_eq!(de.recent_projects, state.recent_projects);
+        assert_eq!(de.recent_files, state.recent_files);
+        assert_eq!(de.recent_commands, state.recent_commands);
     }
 
     #[test]
@@ -369,6 +421,17 @@ mod theme_and_persistence_tests {
             theme_name: "Nord".to_string(),
             files_pane_width: None,
             word_wrap: None,
+            show_ignored: None,
+            nerd_font_icons: None,
+            search_history: SearchHistory::default(),
+            language_overrides: BTreeMap::new(),
+            trusted_projects: BTreeMap::new(),
+            pinned_paths: BTreeMap::new(),
+            workspace_folders: BTreeMap::new(),
+            bookmarks: BTreeMap::new(),
+            recent_files: BTreeMap::new(),
+            recent_projects: Vec::new(),
+            recent_commands: Vec::new(),
         };
         let json = serde_json::to_string(&state).unwrap();
         let de: PersistedState = serde_json::from_str(&json).unwrap();
@@ -383,6 +446,100 @@ mod theme_and_persistence_tests {
         assert_eq!(de.theme_name, "Monokai Pro");
         assert_eq!(de.files_pane_width, None);
         assert_eq!(de.word_wrap, None);
+        assert_eq!(de.search_history, SearchHistory::default());
+    }
+
+    #[test]
+    fn test_state_dir_prefers_xdg_state_home() {
+        let dir = crate::persistence::state_dir_from_env(|key| match key {
+            "XDG_STATE_HOME" => Some("/xdg/state".to_string()),
+            "HOME" => Some("/home/u".to_string()),
+            _ => None,
+        });
+        assert_eq!(dir, Some(PathBuf::from("/xdg/state/lazyide")));
+    }
+
+    #[test]
+    fn test_state_dir_falls_back_to_home() {
+        let dir = crate::persistence::state_dir_from_env(|key| match key {
+            "XDG_STATE_HOME" => Some(String::new()),
+            "HOME" => Some("/home/u".to_string()),
+            _ => None,
+        })
+        .expect("state dir");
+        assert!(dir.starts_with("/home/u"));
+        assert!(dir.ends_with("lazyide"));
+        assert!(!dir.starts_with("/home/u/.config"));
+    }
+
+    #[test]
+    fn test_migrate_dir_moves_legacy_autosaves() {
+        let tmp = tempfile::tempdir().unwrap();
+        let old = tmp.path().join("config/autosave");
+        let new = tmp.path().join("state/autosave");
+        fs::create_dir_all(&old).unwrap();
+        fs::write(old.join("abc.autosave"), "draft").unwrap();
+        crate::persistence::migrate_dir(&old, &new).unwrap();
+        assert!(!old.exists());
+        assert_eq!(
+            fs::read_to_string(new.join("abc.autosave")).unwrap(),
+            "draft"
+        );
+    }
+
+    #[test]
+    fn test_migrate_dir_keeps_existing_destination() {
+        let tmp = tempfile::tempdir().unwrap();
+        let old = tmp.path().join("old");
+        let new = tmp.path().join("new");
+        fs::create_dir_all(&old).unwrap();
+        fs::create_dir_all(&new).unwrap();
+        fs::write(old.join("a.autosave"), "old").unwrap();
+        crate::persistence::migrate_dir(&old, &new).unwrap();
+        assert!(old.join("a.autosave").exists());
+        assert!(!new.join("a.autosave").exists());
+    }
+
+    #[test]
+    fn test_lsp_settings_section() {
+        let settings: LspSettings = serde_json::from_str(
+            r##"{"cargo_features":["serde"],"check_command":"clippy",
+                "target_dir":"target/ra","proc_macros":false,
+                "rust_analyzer":{"check":{"allTargets":false},"cargo":true}}"##,
+        )
+        .unwrap();
+        assert_eq!(
+            settings.rust_analyzer_section(),
+            serde_json::json!({
+                "check": { "allTargets": false, "command": "clippy" },
+                "cargo": { "features": ["serde"], "targetDir": "target/ra" },
+                "procMacro": { "enable": false }
+            })
+        );
+        assert_eq!(
+            LspSettings::default().rust_analyzer_section(),
+            serde_json::json!({})
+        );
+    }
+
+    #[test]
+    fn test_project_lsp_settings_override_user_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = crate::persistence::project_lsp_settings_path(dir.path());
+        fs::create_dir_all(project.parent().unwrap()).unwrap();
+        fs::write(
+            &project,
+            r##"{"target_dir":"target/ra","server_args":{"rust":["--log-file","ra.log"]},
+                "rust_analyzer":{"cargo":{"buildScripts":{"enable":false}}}}"##,
+        )
+        .unwrap();
+        let settings = crate::persistence::load_lsp_settings(dir.path());
+        assert_eq!(settings.target_dir.as_deref(), Some("target/ra"));
+        assert_eq!(settings.server_args_for("rust"), ["--log-file", "ra.log"]);
+        assert!(settings.server_args_for("python").is_empty());
+        let section = settings.rust_analyzer_section();
+        assert_eq!(section["cargo"]["targetDir"], "target/ra");
+        assert_eq!(section["cargo"]["buildScripts"]["enable"], false);
     }
 
     #[test]
@@ -7,4 +7,11 @@ pub(crate) struct TreeItem {
     pub(crate) depth: usize,
     pub(crate) is_dir: bool,
     pub(crate) expanded: bool,
+    /// Matched by `.gitignore` / `.ignore`; only listed when ignored files are shown.
+    pub(crate) ignored: bool,
+    /// Under the "External sources" section: its heading or a read-only
+    /// file from outside the project.
+    pub(crate) external: bool,
+    /// Under the "Pinned" section: its heading or a pinned file or folder.
+    pub(crate) pinned: bool,
 }
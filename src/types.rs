@@ -1,5 +1,15 @@
 use std::path::PathBuf;
 
+/// Severity of a status-line message. Higher levels are colored and can't be
+/// replaced by lower ones right away; errors stay until dismissed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum StatusLevel {
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum Focus {
     Tree,
@@ -12,6 +22,52 @@ pub(crate) enum PendingAction {
     Quit,
     ClosePrompt,
     Delete(PathBuf),
+    PasteOverwrite(TreePaste),
+    /// Delete of several marked tree entries, confirmed once for all.
+    DeleteMany(Vec<PathBuf>),
+    /// Paste of several tree entries into one folder, confirmed once for all.
+    PasteMany(Vec<TreePaste>),
+    /// Asked before the first command that would run project code.
+    TrustProject,
+    /// "Revert File to HEAD" on this file, which discards its changes.
+    RevertToHead(PathBuf),
+}
+
+/// Project search flags. The toggles are set with Alt keys in the search
+/// prompt; `globs` come from `-g` arguments typed after the query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ProjectSearchOptions {
+    /// Match case exactly; otherwise smart case.
+    pub(crate) case_sensitive: bool,
+    pub(crate) whole_word: bool,
+    /// Search for the query as plain text rather than a regex.
+    pub(crate) literal: bool,
+    /// ripgrep `-g` globs; a leading `!` excludes.
+    pub(crate) globs: Vec<String>,
+}
+
+/// A TODO/FIXME comment collected by the TODO export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TodoItem {
+    /// Path relative to the project root.
+    pub(crate) file: String,
+    pub(crate) line: usize,
+    pub(crate) text: String,
+    /// `git blame` author of the line; `None` outside a git repository.
+    pub(crate) author: Option<String>,
+}
+
+/// A `file:line[:col]` reference found in pasted compiler output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PastedLocation {
+    /// The path as written, before it is resolved against the project.
+    pub(crate) path: String,
+    /// 1-based line and column.
+    pub(crate) line: usize,
+    pub(crate) column: Option<usize>,
+    /// The pasted line it came from, or the line before when the
+    /// reference stands alone (rustc's `-->`, a backtrace's `at`).
+    pub(crate) context: String,
 }
 
 #[derive(Debug, Clone)]
@@ -22,7 +78,16 @@ pub(crate) enum PromptMode {
     FindInFile,
     FindInProject,
     ReplaceInFile { search: String },
+    ReplaceInProject,
     GoToLine,
+    ExportTodos,
+    LanguageMode,
+    NewFileLike { source: PathBuf },
+    NewCounterpart { source: PathBuf, test: bool },
+    AddWorkspaceFolder,
+    PasteLocations,
+    CreateBranch,
+    RenameSymbol,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -32,14 +97,90 @@ pub(crate) enum CommandAction {
     QuickOpen,
     FindInFile,
     FindInProject,
+    FindInProjectWithOptions,
     SaveFile,
     RefreshTree,
     ToggleFiles,
     GotoDefinition,
     ReplaceInFile,
+    ReplaceInProject,
     GoToLine,
     Keybinds,
     ToggleWordWrap,
+    OpenStateFolder,
+    ToggleIgnoredFiles,
+    ToggleNerdFontIcons,
+    ExportTodos,
+    MoveTabLeft,
+    MoveTabRight,
+    CloseOtherTabs,
+    CloseTabsToRight,
+    CloseAllTabs,
+    ReopenClosedTab,
+    TogglePinTab,
+    ChangedFiles,
+    SetLanguageMode,
+    LanguageOverrides,
+    ShowLastMessage,
+    JumpToBracket,
+    GoToLastEdit,
+    JumpBack,
+    JumpForward,
+    CenterCursorLine,
+    CursorLineToTop,
+    CursorLineToBottom,
+    ToggleProjectTrust,
+    PasteFromHistory,
+    ConvertIndentToSpaces,
+    ConvertIndentToTabs,
+    ReindentFile,
+    GenerateRustTest,
+    GoToTestOrImplementation,
+    EditEnvFile,
+    ToggleLineEndings,
+    ToggleFollowBroadcast,
+    FollowSession,
+    RunCodeBlock,
+    OpenLogView,
+    ToggleFollowFile,
+    OpenSettings,
+    OpenHexEditor,
+    AddWorkspaceFolder,
+    RemoveWorkspaceFolder,
+    PasteVerbatim,
+    OpenRecentProject,
+    ListBookmarks,
+    ToggleGutterLineNumbers,
+    ToggleGutterFolds,
+    ToggleGutterDiagnostics,
+    ToggleGutterGit,
+    PasteLocations,
+    GoToParentModule,
+    GoToModuleDeclaration,
+    SiblingModules,
+    RevertFileToHead,
+    RevertHunk,
+    ShowLspConfig,
+    RunTask,
+    StopTask,
+    ToggleTaskOutput,
+    CompareWithClipboard,
+    RunCargoCheck,
+    CargoCheckProblems,
+    TestExplorer,
+    GitLog,
+    GitFileHistory,
+    DiffWithHead,
+    SwitchBranch,
+    CreateBranch,
+    AcceptConflictOurs,
+    AcceptConflictTheirs,
+    AcceptConflictBoth,
+    NextConflict,
+    PreviousConflict,
+    FindReferences,
+    RenameSymbol,
+    FormatDocument,
 }
 
 #[derive(Debug, Clone)]
@@ -47,24 +188,247 @@ pub(crate) struct PromptState {
     pub(crate) title: String,
     pub(crate) value: String,
     pub(crate) cursor: usize,
+    /// Other end of the selection as a byte offset; the selected text runs
+    /// between this and `cursor`. Typing replaces it.
+    pub(crate) anchor: Option<usize>,
     pub(crate) mode: PromptMode,
+    /// Index into the search history while cycling with Up/Down, and the
+    /// text that was typed before cycling started.
+    pub(crate) history: Option<(usize, String)>,
+}
+
+impl PromptState {
+    /// Remove the selected text, if any. Returns whether anything was removed.
+    pub(crate) fn delete_selection(&mut self) -> bool {
+        let Some(anchor) = self.anchor.take() else {
+            return false;
+        };
+        let (start, end) = (anchor.min(self.cursor), anchor.max(self.cursor));
+        self.value.replace_range(start..end, "");
+        self.cursor = start;
+        start != end
+    }
+
+    /// Show the next older (or newer) history entry; stepping newer past the
+    /// most recent one restores the typed text.
+    pub(crate) fn step_history(&mut self, entries: &[String], older: bool) {
+        let current = self.history.as_ref().map(|(idx, _)| *idx);
+        let next = match (current, older) {
+            (None, true) if !entries.is_empty() => Some(0),
+            (Some(idx), true) => Some((idx + 1).min(entries.len().saturating_sub(1))),
+            (Some(idx), false) => idx.checked_sub(1),
+            _ => return,
+        };
+        match next {
+            Some(idx) => {
+                let draft = match self.history.take() {
+                    Some((_, draft)) => draft,
+                    None => self.value.clone(),
+                };
+                self.value = entries[idx].clone();
+                self.history = Some((idx, draft));
+            }
+            None => {
+                if let Some((_, draft)) = self.history.take() {
+                    self.value = draft;
+                }
+            }
+        }
+        self.cursor = self.value.len();
+        self.anchor = None;
+    }
+}
+
+/// One input of a [`FormState`]. `cursor` is a byte offset into `value`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FormField {
+    Text {
+        label: String,
+        value: String,
+        cursor: usize,
+    },
+    Checkbox {
+        label: String,
+        checked: bool,
+    },
+}
+
+impl FormField {
+    pub(crate) fn text(label: &str, value: &str) -> Self {
+        FormField::Text {
+            label: label.to_string(),
+            value: value.to_string(),
+            cursor: value.len(),
+        }
+    }
+
+    pub(crate) fn checkbox(label: &str, checked: bool) -> Self {
+        FormField::Checkbox {
+            label: label.to_string(),
+            checked,
+        }
+    }
+}
+
+/// What a submitted form is for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FormKind {
+    BatchRename { dir: PathBuf },
+    ProjectSearch,
+}
+
+/// A modal form of labelled text fields and checkboxes with OK and Cancel
+/// buttons, for flows that need more than one input at a time.
+#[derive(Debug, Clone)]
+pub(crate) struct FormState {
+    pub(crate) title: String,
+    pub(crate) fields: Vec<FormField>,
+    /// Focused field; `fields.len()` is OK and one past it Cancel.
+    pub(crate) focus: usize,
+    pub(crate) kind: FormKind,
+}
+
+impl FormState {
+    pub(crate) fn new(title: String, kind: FormKind, fields: Vec<FormField>) -> Self {
+        Self {
+            title,
+            fields,
+            focus: 0,
+            kind,
+        }
+    }
+
+    /// The value of text field `idx`, or "" for anything else.
+    pub(crate) fn text(&self, idx: usize) -> &str {
+        match self.fields.get(idx) {
+            Some(FormField::Text { value, .. }) => value,
+            _ => "",
+        }
+    }
+
+    pub(crate) fn checked(&self, idx: usize) -> bool {
+        matches!(
+            self.fields.get(idx),
+            Some(FormField::Checkbox { checked: true, .. })
+        )
+    }
+}
+
+/// A Quick Open result: a file, or a place in one (a symbol or a line).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct QuickOpenItem {
+    pub(crate) path: PathBuf,
+    /// 0-based `(row, col)` to put the cursor at; `None` opens the file
+    /// where it was.
+    pub(crate) position: Option<(usize, usize)>,
+    /// Shown instead of the path for symbols and lines.
+    pub(crate) label: Option<String>,
+}
+
+impl QuickOpenItem {
+    pub(crate) fn file(path: PathBuf) -> Self {
+        Self {
+            path,
+            position: None,
+            label: None,
+        }
+    }
+}
+
+/// What clicking a status bar breadcrumb opens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BreadcrumbTarget {
+    /// The files under a directory.
+    Files(PathBuf),
+    /// The symbols directly inside `within` (a symbol's first and last
+    /// rows; the whole file when `None`), with the one at `row` selected.
+    Symbols {
+        within: Option<(usize, usize)>,
+        row: usize,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum ContextAction {
     Open,
+    TogglePin,
     NewFile,
+    NewFileLikeThis,
     NewFolder,
     Rename,
+    BatchRename,
+    Cut,
+    Copy,
+    Paste,
+    Duplicate,
     Delete,
+    CopyRelativePath,
+    CopyAbsolutePath,
+    RevealInFileManager,
+    OpenWithDefaultApp,
     Cancel,
 }
 
+/// Files and folders marked with Cut/Copy in the tree, waiting to be pasted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TreeClipboard {
+    pub(crate) paths: Vec<PathBuf>,
+    pub(crate) cut: bool,
+}
+
+/// One entry of a paste that needs confirmation: its destination already
+/// exists, or it is part of a paste of several entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TreePaste {
+    pub(crate) from: PathBuf,
+    pub(crate) to: PathBuf,
+    pub(crate) cut: bool,
+}
+
+impl TreePaste {
+    /// `to` holds `from`, so removing it first would delete the source,
+    /// or `to` lies inside `from`, which cannot be copied into itself.
+    pub(crate) fn replaces_own_folder(&self) -> bool {
+        self.from.starts_with(&self.to) || self.to.starts_with(&self.from)
+    }
+}
+
+/// How a buffer indents, as guessed by `detect_indent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IndentStyle {
+    pub(crate) tabs: bool,
+    /// Columns per level for space indentation.
+    pub(crate) width: usize,
+    /// Some lines indent with tabs and others with spaces.
+    pub(crate) mixed: bool,
+}
+
+impl IndentStyle {
+    pub(crate) fn describe(&self) -> String {
+        let style = if self.tabs {
+            "tabs".to_string()
+        } else {
+            format!("{} spaces", self.width)
+        };
+        if self.mixed {
+            format!("{style} (mixed)")
+        } else {
+            style
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum EditorContextAction {
     Copy,
     Cut,
     Paste,
     SelectAll,
+    GoToDefinition,
+    FindReferences,
+    RenameSymbol,
+    FormatDocument,
+    ToggleComment,
+    TogglePin,
     Cancel,
 }
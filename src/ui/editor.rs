@@ -0,0 +1,394 @@
+use ratatui::layout::{Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use unicode_width::UnicodeWidthStr;
+
+use super::helpers::{apply_indent_guides, clip_spans_by_columns};
+use super::layers::{Segment, compose_spans, editor_layers, padding_style};
+use crate::lsp_client::LspDiagnostic;
+use crate::syntax::scan_regions;
+use crate::tab::{FoldRange, GitLineStatus, Tab};
+use crate::theme::Theme;
+use crate::util::{display_width_of_range, fold_summary};
+
+/// Which gutter columns to draw. The bookmark marker and the gap before
+/// the text are always there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Gutter {
+    pub(crate) line_numbers: bool,
+    pub(crate) folds: bool,
+    pub(crate) diagnostics: bool,
+    pub(crate) git: bool,
+}
+
+impl Gutter {
+    pub(crate) const ALL: Gutter = Gutter {
+        line_numbers: true,
+        folds: true,
+        diagnostics: true,
+        git: true,
+    };
+
+    /// Cells taken by the line number and bookmark marker; clicks there
+    /// select the line.
+    pub(crate) fn number_width(self) -> u16 {
+        if self.line_numbers { 6 } else { 1 }
+    }
+
+    pub(crate) fn width(self) -> u16 {
+        self.number_width()
+            + if self.folds { 2 } else { 0 }
+            + u16::from(self.diagnostics)
+            + u16::from(self.git)
+            + 1
+    }
+}
+
+fn slice_chars(s: &str, start: usize, end: usize) -> String {
+    let count = end.saturating_sub(start);
+    s.chars().skip(start).take(count).collect()
+}
+
+/// Render the rows of `tab` visible in an editor area of `inner`: gutter
+/// (line number, fold marker, diagnostic, git status), highlighted text,
+/// search matches, selection and folded-block summaries.
+pub(crate) fn editor_lines(
+    tab: &Tab,
+    theme: &Theme,
+    word_wrap: bool,
+    gutter: Gutter,
+    inner: Rect,
+) -> Vec<Line<'static>> {
+    let lang = tab.lang;
+    let visible_rows = inner.height as usize;
+    let start_row = tab
+        .editor_scroll_row
+        .min(tab.visible_rows_map.len().saturating_sub(1));
+    let (cursor_row, _) = tab.editor.cursor();
+    let scroll_col = tab.editor_scroll_col;
+    let lines_ref = tab.editor.lines();
+    let tab_width = tab.tab_width();
+    let tab_spaces = " ".repeat(tab_width);
+    let diagnostics_ref = &tab.diagnostics;
+    let fold_ranges_ref = &tab.fold_ranges;
+    let folded_starts_ref = &tab.folded_starts;
+    let visible_rows_map_ref = &tab.visible_rows_map;
+    let visible_row_starts_ref = &tab.visible_row_starts;
+    let visible_row_ends_ref = &tab.visible_row_ends;
+    let bracket_depths_ref = &tab.bracket_depths;
+    let line_states_ref = &tab.line_states;
+    let git_line_status_ref = &tab.git_line_status;
+    let layers = editor_layers(tab, theme);
+    let mut overlays = Vec::new();
+    let inner_w = inner.width as usize;
+    let blank_line = Line::from(Span::styled(
+        " ".repeat(inner_w),
+        Style::default().bg(theme.bg),
+    ));
+    // Precompute indent depths for visible rows (for indent guides)
+    let indent_depths: Vec<usize> = {
+        let total = lines_ref.len();
+        let mut depths = vec![0usize; total];
+        let mut is_blank = vec![false; total];
+        // First pass: compute depth for non-blank lines, mark blanks
+        for i in 0..total {
+            let line = &lines_ref[i];
+            let expanded = line.replace('\t', &tab_spaces);
+            let leading = expanded.len() - expanded.trim_start_matches(' ').len();
+            if expanded.trim().is_empty() {
+                is_blank[i] = true;
+                depths[i] = 0;
+            } else {
+                depths[i] = leading / tab_width;
+            }
+        }
+        // O(n) two-pass for blank lines: propagate nearest non-blank above/below
+        let mut above_depth = vec![0usize; total];
+        let mut last = 0usize;
+        for i in 0..total {
+            if !is_blank[i] {
+                last = depths[i];
+            }
+            above_depth[i] = last;
+        }
+        let mut below_depth = vec![0usize; total];
+        last = 0;
+        for i in (0..total).rev() {
+            if !is_blank[i] {
+                last = depths[i];
+            }
+            below_depth[i] = last;
+        }
+        for i in 0..total {
+            if is_blank[i] {
+                depths[i] = above_depth[i].min(below_depth[i]);
+            }
+        }
+        depths
+    };
+    let guide_style = Style::default().fg(theme.fg_muted);
+
+    let mut lines_out: Vec<Line<'static>> = Vec::with_capacity(visible_rows);
+    for visual_row in 0..visible_rows {
+        let visible_idx = start_row + visual_row;
+        let Some(&row) = visible_rows_map_ref.get(visible_idx) else {
+            lines_out.push(blank_line.clone());
+            continue;
+        };
+        let seg_start = visible_row_starts_ref
+            .get(visible_idx)
+            .copied()
+            .unwrap_or(0);
+        let seg_end = visible_row_ends_ref
+            .get(visible_idx)
+            .copied()
+            .unwrap_or(seg_start);
+        let is_first_segment = seg_start == 0;
+        if row >= lines_ref.len() {
+            lines_out.push(blank_line.clone());
+            continue;
+        }
+        let mut spans = Vec::new();
+        if gutter.line_numbers {
+            let line_num = if is_first_segment {
+                format!("{:>5}", row + 1)
+            } else {
+                "     ".to_string()
+            };
+            let line_num_style = if row == cursor_row {
+                Style::default().fg(theme.accent)
+            } else {
+                Style::default().fg(theme.fg_muted)
+            };
+            spans.push(Span::styled(line_num, line_num_style));
+        }
+        if is_first_segment && tab.bookmarks.contains(&row) {
+            spans.push(Span::styled("◆", Style::default().fg(theme.accent)));
+        } else {
+            spans.push(Span::raw(" "));
+        }
+
+        if gutter.folds {
+            let fold_indicator = if is_first_segment {
+                if let Some(fr) = fold_ranges_ref.iter().find(|fr| fr.start_line == row) {
+                    if folded_starts_ref.contains(&fr.start_line) {
+                        "▸ "
+                    } else {
+                        "▾ "
+                    }
+                } else {
+                    "  "
+                }
+            } else {
+                "↪ "
+            };
+            spans.push(Span::styled(
+                fold_indicator,
+                Style::default()
+                    .fg(theme.fg_muted)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+
+        if gutter.diagnostics {
+            let diag_for_row = diagnostics_ref
+                .iter()
+                .find(|d| is_first_segment && d.line == row + 1);
+            if let Some(diag) = diag_for_row {
+                let color = match diag.severity.as_str() {
+                    "error" => Color::Red,
+                    "warning" => Color::Yellow,
+                    "info" => Color::Cyan,
+                    _ => Color::Blue,
+                };
+                spans.push(Span::styled("●", Style::default().fg(color)));
+            } else {
+                spans.push(Span::raw(" "));
+            }
+        }
+        if gutter.git {
+            let git_status = if is_first_segment {
+                git_line_status_ref
+                    .get(row)
+                    .copied()
+                    .unwrap_or(GitLineStatus::None)
+            } else {
+                GitLineStatus::None
+            };
+            let (mark, color) = match git_status {
+                GitLineStatus::Added => ("+", Color::Green),
+                GitLineStatus::Modified => ("~", Color::Yellow),
+                GitLineStatus::Deleted => ("-", Color::Red),
+                GitLineStatus::None => (" ", Color::Reset),
+            };
+            spans.push(Span::styled(mark, Style::default().fg(color)));
+        }
+        spans.push(Span::raw(" "));
+        let segment_text =
+            slice_chars(&lines_ref[row], seg_start, seg_end).replace('\t', &tab_spaces);
+        let bracket_colors = [theme.bracket_1, theme.bracket_2, theme.bracket_3];
+        let bd = bracket_depths_ref.get(row).copied().unwrap_or(0);
+        let mut state = line_states_ref.get(row).copied().unwrap_or_default();
+        if seg_start > 0 {
+            // A wrapped segment starts in whatever the text before it left open.
+            let line = &lines_ref[row];
+            let byte = line
+                .char_indices()
+                .nth(seg_start)
+                .map_or(line.len(), |(b, _)| b);
+            state = scan_regions(&line[..byte], lang, state).1;
+        }
+        let hl =
+            tab.highlight_cache
+                .highlight(&segment_text, lang, theme, bd, state, &bracket_colors);
+        let guide_depth = indent_depths.get(row).copied().unwrap_or(0);
+        let content_spans = if is_first_segment {
+            apply_indent_guides(hl.spans, guide_depth, tab_width, guide_style)
+        } else {
+            hl.spans
+        };
+        let content_width = inner_w.saturating_sub(gutter.width() as usize);
+        let content_spans = if !word_wrap && scroll_col > 0 {
+            clip_spans_by_columns(content_spans, scroll_col, content_width)
+        } else if !word_wrap {
+            clip_spans_by_columns(content_spans, 0, content_width)
+        } else {
+            content_spans
+        };
+        // Selection, matches, diagnostics and the like over the syntax colors.
+        let segment = Segment {
+            line: &lines_ref[row],
+            row,
+            seg_start,
+            seg_end,
+            scroll: if word_wrap { 0 } else { scroll_col },
+            tab_width,
+        };
+        overlays.clear();
+        for layer in &layers {
+            layer.overlays(&segment, &mut overlays);
+        }
+        spans.extend(compose_spans(content_spans, &overlays));
+        if is_first_segment
+            && let Some(fr) = fold_ranges_ref
+                .iter()
+                .find(|fr| fr.start_line == row && folded_starts_ref.contains(&fr.start_line))
+        {
+            spans.extend(fold_placeholder(lines_ref, diagnostics_ref, fr, theme));
+        }
+        // Pad line to full width so stale characters from previous frame are overwritten
+        let used: usize = spans.iter().map(|s| s.content.width()).sum();
+        if used < inner_w {
+            spans.push(Span::styled(
+                " ".repeat(inner_w - used),
+                padding_style(Style::default().bg(theme.bg), &overlays),
+            ));
+        }
+        let hl = Line::from(spans);
+        lines_out.push(hl);
+    }
+    lines_out
+}
+
+/// Spans shown after a folded block's first line: a preview of its content,
+/// the hidden line count, and how many errors and warnings it hides, e.g.
+/// `  let x = 1; … 42 lines, 2 errors`.
+fn fold_placeholder(
+    lines: &[String],
+    diagnostics: &[LspDiagnostic],
+    fold: &FoldRange,
+    theme: &Theme,
+) -> Vec<Span<'static>> {
+    let muted = Style::default().fg(theme.fg_muted);
+    let mut spans = Vec::new();
+    if let Some(summary) = fold_summary(lines, fold, 40) {
+        spans.push(Span::styled(
+            format!("  {summary}"),
+            muted.add_modifier(Modifier::ITALIC),
+        ));
+    }
+    let folded = fold.end_line.saturating_sub(fold.start_line);
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    spans.push(Span::styled(
+        format!("  … {folded} line{}", plural(folded)),
+        muted,
+    ));
+    // Diagnostics are 1-based; the hidden rows are start_line+1..=end_line.
+    let hidden = |d: &&LspDiagnostic| d.line > fold.start_line + 1 && d.line <= fold.end_line + 1;
+    let count = |severity: &str| {
+        diagnostics
+            .iter()
+            .filter(hidden)
+            .filter(|d| d.severity == severity)
+            .count()
+    };
+    for (severity, n, color) in [
+        ("error", count("error"), Color::Red),
+        ("warning", count("warning"), Color::Yellow),
+    ] {
+        if n > 0 {
+            spans.push(Span::styled(
+                format!(", {n} {severity}{}", plural(n)),
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            ));
+        }
+    }
+    spans
+}
+
+/// Screen position of the cursor within an editor area of `inner`, or `None`
+/// when its row is scrolled out of view.
+pub(crate) fn editor_cursor_position(
+    tab: &Tab,
+    word_wrap: bool,
+    gutter: Gutter,
+    inner: Rect,
+) -> Option<Position> {
+    let (cursor_row, cursor_col) = tab.editor.cursor();
+    let start_row = tab
+        .editor_scroll_row
+        .min(tab.visible_rows_map.len().saturating_sub(1));
+    let cursor_visible = tab.visible_index_of_source_position(cursor_row, cursor_col);
+    let cursor_y = cursor_visible.checked_sub(start_row)?;
+    if cursor_y >= inner.height as usize {
+        return None;
+    }
+    let seg_start = tab
+        .visible_row_starts
+        .get(cursor_visible)
+        .copied()
+        .unwrap_or(0);
+    let seg_end = tab
+        .visible_row_ends
+        .get(cursor_visible)
+        .copied()
+        .unwrap_or(seg_start);
+    let max_x = inner.width.saturating_sub(1).saturating_sub(gutter.width()) as usize;
+    // Place the cursor by display width (wide CJK chars take two
+    // columns, tabs the tab width). When not wrapping, subtract the horizontal
+    // scroll so it renders at the right screen column.
+    let line = tab
+        .editor
+        .lines()
+        .get(cursor_row)
+        .map_or("", String::as_str);
+    let tab_width = tab.tab_width();
+    let logical_x = if !word_wrap {
+        display_width_of_range(line, 0, cursor_col, tab_width).saturating_sub(tab.editor_scroll_col)
+    } else {
+        display_width_of_range(
+            line,
+            seg_start,
+            cursor_col.clamp(seg_start, seg_end),
+            tab_width,
+        )
+    };
+    let cursor_x = logical_x.min(max_x);
+    Some(Position::new(
+        inner
+            .x
+            .saturating_add(gutter.width())
+            .saturating_add(cursor_x as u16),
+        inner.y.saturating_add(cursor_y as u16),
+    ))
+}
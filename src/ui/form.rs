@@ -0,0 +1,118 @@
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Clear, Paragraph};
+use unicode_width::UnicodeWidthStr;
+
+use super::helpers::{clip_spans_by_columns, themed_block};
+use crate::app::App;
+use crate::types::FormField;
+use crate::util::to_u16_saturating;
+
+/// The open form: one row per checkbox, a label row and an input row per
+/// text field, then the OK and Cancel buttons.
+pub(crate) fn render_form(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let Some(form) = app.form.as_ref() else {
+        return;
+    };
+    let screen = frame.area();
+    let rows: usize = form
+        .fields
+        .iter()
+        .map(|f| match f {
+            FormField::Text { .. } => 2,
+            FormField::Checkbox { .. } => 1,
+        })
+        .sum();
+    // Fields, a blank line, the buttons, the key hint and the border.
+    let height = to_u16_saturating(rows + 5).min(screen.height);
+    let width = (screen.width * 3 / 5).clamp(40.min(screen.width), screen.width);
+    let area = Rect::new(
+        screen.x + (screen.width - width) / 2,
+        screen.y + (screen.height - height) / 2,
+        width,
+        height,
+    );
+    frame.render_widget(Clear, area);
+    let block = themed_block(&theme)
+        .title(form.title.clone())
+        .style(Style::default().fg(theme.fg).bg(theme.bg_alt));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let focused = |idx: usize| {
+        if form.focus == idx {
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.fg)
+        }
+    };
+    let field_width = usize::from(inner.width.saturating_sub(2));
+    let mut lines = Vec::new();
+    let mut cursor = None;
+    for (idx, field) in form.fields.iter().enumerate() {
+        match field {
+            FormField::Text {
+                label,
+                value,
+                cursor: at,
+            } => {
+                lines.push(Line::from(Span::styled(label.clone(), focused(idx))));
+                // Scroll long values so the cursor stays in view.
+                let col = value[..*at].width();
+                let skip = col.saturating_sub(field_width.saturating_sub(1));
+                let input = Span::raw(format!("{value:<field_width$}"));
+                let mut spans = vec![Span::raw(" ")];
+                spans.extend(clip_spans_by_columns(vec![input], skip, field_width));
+                lines.push(Line::from(spans).style(Style::default().fg(theme.fg).bg(theme.bg)));
+                if form.focus == idx {
+                    cursor = Some((col - skip, lines.len() - 1));
+                }
+            }
+            FormField::Checkbox { label, checked } => {
+                let mark = if *checked { "x" } else { " " };
+                lines.push(Line::from(Span::styled(
+                    format!("[{mark}] {label}"),
+                    focused(idx),
+                )));
+            }
+        }
+    }
+    lines.push(Line::default());
+    let ok = form.fields.len();
+    let button = |label: &'static str, idx: usize| {
+        if form.focus == idx {
+            Span::styled(
+                label,
+                Style::default()
+                    .fg(theme.bg)
+                    .bg(theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::styled(label, Style::default().fg(theme.fg))
+        }
+    };
+    lines.push(Line::from(vec![
+        button("[ OK ]", ok),
+        Span::raw("  "),
+        button("[ Cancel ]", ok + 1),
+    ]));
+    lines.push(Line::from(Span::styled(
+        "Tab next field, Space toggle, Enter OK, Esc cancel",
+        Style::default().fg(theme.fg_muted),
+    )));
+    frame.render_widget(Paragraph::new(lines), inner);
+    if let Some((col, row)) = cursor
+        && row < usize::from(inner.height)
+    {
+        frame.set_cursor_position((
+            inner.x + 1 + to_u16_saturating(col),
+            inner.y + to_u16_saturating(row),
+        ));
+    }
+}
@@ -129,68 +129,19 @@ pub(crate) fn clip_spans_by_columns(
     result
 }
 
-/// Apply a style to a range of display columns within spans.
-/// `sel_start` and `sel_end` are 0-based display column indices (inclusive start, exclusive end).
-pub(crate) fn apply_selection_to_spans(
-    spans: Vec<Span<'static>>,
-    sel_start: usize,
-    sel_end: usize,
-    sel_style: Style,
-) -> Vec<Span<'static>> {
-    if sel_start >= sel_end {
-        return spans;
-    }
-    let mut chars: Vec<(char, Style)> = Vec::new();
-    for span in &spans {
-        let style = span.style;
-        for ch in span.content.chars() {
-            chars.push((ch, style));
-        }
-    }
-    let mut col = 0usize;
-    for (ch, style) in &mut chars {
-        let cw = unicode_width::UnicodeWidthChar::width(*ch).unwrap_or(0);
-        if col >= sel_start && col < sel_end {
-            *style = style.patch(sel_style);
-        }
-        col += cw;
-    }
-    // Rebuild spans, merging consecutive chars with same style
-    let mut result: Vec<Span<'static>> = Vec::new();
-    if chars.is_empty() {
-        return result;
-    }
-    let mut current_style = chars[0].1;
-    let mut current_text = String::new();
-    for (ch, style) in chars {
-        if style == current_style {
-            current_text.push(ch);
-        } else {
-            if !current_text.is_empty() {
-                result.push(Span::styled(current_text, current_style));
-                current_text = String::new();
-            }
-            current_style = style;
-            current_text.push(ch);
-        }
-    }
-    if !current_text.is_empty() {
-        result.push(Span::styled(current_text, current_style));
-    }
-    result
-}
-
-/// Replace spaces at indent guide columns (multiples of 4) with `│` within leading whitespace.
-/// `guide_depth` is the number of indent levels to draw guides for.
+/// Replace spaces at indent guide columns (multiples of `indent_width`) with `│` within
+/// leading whitespace. `guide_depth` is the number of indent levels to draw guides for.
 pub(crate) fn apply_indent_guides(
     spans: Vec<Span<'static>>,
     guide_depth: usize,
+    indent_width: usize,
     guide_style: Style,
 ) -> Vec<Span<'static>> {
     if guide_depth == 0 {
         return spans;
     }
-    let max_col = guide_depth * 4;
+    let indent_width = indent_width.max(1);
+    let max_col = guide_depth * indent_width;
     // Flatten spans into (char, style) pairs, then rebuild
     let mut chars: Vec<(char, Style)> = Vec::new();
     for span in &spans {
@@ -208,8 +159,8 @@ pub(crate) fn apply_indent_guides(
         .position(|(ch, _)| *ch != ' ')
         .unwrap_or(chars.len());
     let limit = ws_end.min(max_col);
-    // Replace spaces at guide columns (0, 4, 8, ...) with │
-    for col in (0..limit).step_by(4) {
+    // Replace spaces at guide columns (0, indent_width, ...) with │
+    for col in (0..limit).step_by(indent_width) {
         if col < chars.len() && chars[col].0 == ' ' {
             chars[col] = ('│', guide_style);
         }
@@ -247,7 +198,7 @@ mod indent_guide_tests {
     #[test]
     fn test_no_guides_at_zero_depth() {
         let spans = vec![Span::raw("    hello")];
-        let result = apply_indent_guides(spans.clone(), 0, Style::default());
+        let result = apply_indent_guides(spans.clone(), 0, 4, Style::default());
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].content.as_ref(), "    hello");
     }
@@ -256,7 +207,7 @@ mod indent_guide_tests {
     fn test_guides_at_depth_one() {
         let guide_style = Style::default().fg(Color::Gray);
         let spans = vec![Span::raw("    code")];
-        let result = apply_indent_guides(spans, 1, guide_style);
+        let result = apply_indent_guides(spans, 1, 4, guide_style);
         // First char should be │
         let full: String = result.iter().map(|s| s.content.as_ref()).collect();
         assert_eq!(full, "│   code");
@@ -266,7 +217,7 @@ mod indent_guide_tests {
     fn test_guides_at_depth_two() {
         let guide_style = Style::default().fg(Color::Gray);
         let spans = vec![Span::raw("        code")];
-        let result = apply_indent_guides(spans, 2, guide_style);
+        let result = apply_indent_guides(spans, 2, 4, guide_style);
         let full: String = result.iter().map(|s| s.content.as_ref()).collect();
         assert_eq!(full, "│   │   code");
     }
@@ -275,14 +226,14 @@ mod indent_guide_tests {
     fn test_no_guides_on_non_indented() {
         let guide_style = Style::default().fg(Color::Gray);
         let spans = vec![Span::raw("hello world")];
-        let result = apply_indent_guides(spans, 3, guide_style);
+        let result = apply_indent_guides(spans, 3, 4, guide_style);
         let full: String = result.iter().map(|s| s.content.as_ref()).collect();
         assert_eq!(full, "hello world");
     }
 
     #[test]
     fn test_empty_spans() {
-        let result = apply_indent_guides(vec![], 2, Style::default());
+        let result = apply_indent_guides(vec![], 2, 4, Style::default());
         assert!(result.is_empty());
     }
 
@@ -290,7 +241,7 @@ mod indent_guide_tests {
     fn test_blank_line_with_guides() {
         let guide_style = Style::default().fg(Color::Gray);
         let spans = vec![Span::raw("        ")];
-        let result = apply_indent_guides(spans, 2, guide_style);
+        let result = apply_indent_guides(spans, 2, 4, guide_style);
         let full: String = result.iter().map(|s| s.content.as_ref()).collect();
         assert_eq!(full, "│   │   ");
     }
@@ -299,8 +250,18 @@ mod indent_guide_tests {
 #[cfg(test)]
 mod selection_span_tests {
     use super::*;
+    use crate::ui::layers::{Overlay, compose_spans};
     use ratatui::style::Color;
 
+    fn apply_selection_to_spans(
+        spans: Vec<Span<'static>>,
+        start: usize,
+        end: usize,
+        style: Style,
+    ) -> Vec<Span<'static>> {
+        compose_spans(spans, &[Overlay { start, end, style }])
+    }
+
     fn collect_text(spans: &[Span]) -> String {
         spans.iter().map(|s| s.content.as_ref()).collect()
     }
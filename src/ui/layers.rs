@@ -0,0 +1,468 @@
+//! Highlight layers: styles laid over the syntax-highlighted spans of a
+//! line at character precision. Each layer reports ranges for one segment
+//! of the text, and `compose_spans` merges them over the base spans in one
+//! pass, patching styles so syntax colors survive under a background or an
+//! underline.
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+
+use crate::tab::Tab;
+use crate::theme::Theme;
+use crate::util::{display_width_of_range, segment_has_selection};
+
+/// A style over display columns `start..end` of a line's text; an `end` of
+/// `usize::MAX` also covers the padding after the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Overlay {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) style: Style,
+}
+
+/// The part of a line being drawn: chars `seg_start..seg_end` of `row`,
+/// with `scroll` display columns cut off on the left and tabs `tab_width`
+/// columns wide.
+pub(crate) struct Segment<'a> {
+    pub(crate) line: &'a str,
+    pub(crate) row: usize,
+    pub(crate) seg_start: usize,
+    pub(crate) seg_end: usize,
+    pub(crate) scroll: usize,
+    pub(crate) tab_width: usize,
+}
+
+impl Segment<'_> {
+    /// Display columns of chars `start..end` of the line, clipped to the
+    /// segment; `None` when nothing of it shows.
+    pub(crate) fn columns(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+        let start = start.max(self.seg_start);
+        let end = end.min(self.seg_end);
+        if start >= end {
+            return None;
+        }
+        let col = |c| {
+            display_width_of_range(self.line, self.seg_start, c, self.tab_width)
+                .saturating_sub(self.scroll)
+        };
+        let (start, end) = (col(start), col(end));
+        (start < end).then_some((start, end))
+    }
+
+    fn push(&self, out: &mut Vec<Overlay>, start: usize, end: usize, style: Style) {
+        if let Some((start, end)) = self.columns(start, end) {
+            out.push(Overlay { start, end, style });
+        }
+    }
+}
+
+/// One kind of highlight drawn over the text.
+pub(crate) trait HighlightLayer {
+    /// Add this layer's ranges on `segment` to `out`.
+    fn overlays(&self, segment: &Segment<'_>, out: &mut Vec<Overlay>);
+}
+
+/// The layers of the editor, lowest first: later ones are patched over
+/// earlier ones where they overlap.
+pub(crate) fn editor_layers<'a>(tab: &'a Tab, theme: &Theme) -> Vec<Box<dyn HighlightLayer + 'a>> {
+    let selection = tab.editor.selection_range();
+    let cursor = tab.editor.cursor();
+    vec![
+        Box::new(MergeConflicts {
+            tab,
+            body_style: Style::default().bg(theme.bg_alt),
+            ours_marker_style: Style::default()
+                .fg(theme.bg)
+                .bg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+            marker_style: Style::default()
+                .fg(theme.bg)
+                .bg(theme.accent_secondary)
+                .add_modifier(Modifier::BOLD),
+        }),
+        Box::new(CurrentLine {
+            cursor,
+            line_len: tab
+                .editor
+                .lines()
+                .get(cursor.0)
+                .map_or(0, |l| l.chars().count()),
+            selection,
+            style: Style::default().bg(theme.bg_alt),
+        }),
+        Box::new(SymbolOccurrences::at_cursor(tab, theme)),
+        Box::new(Diagnostics { tab }),
+        Box::new(SearchMatches {
+            tab,
+            cursor,
+            style: Style::default().fg(theme.bg).bg(theme.accent_secondary),
+            current_style: Style::default()
+                .fg(theme.bg)
+                .bg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        }),
+        Box::new(BracketMatch {
+            pair: tab.bracket_match_at_cursor(),
+            style: Style::default()
+                .bg(theme.selection)
+                .add_modifier(Modifier::BOLD),
+        }),
+        Box::new(Selection {
+            selection,
+            style: Style::default().bg(theme.selection),
+        }),
+    ]
+}
+
+/// Merge `overlays` over `base`, in order, splitting spans only where a
+/// style changes.
+pub(crate) fn compose_spans(base: Vec<Span<'static>>, overlays: &[Overlay]) -> Vec<Span<'static>> {
+    if overlays.iter().all(|o| o.start >= o.end) {
+        return base;
+    }
+    let mut out: Vec<Span<'static>> = Vec::with_capacity(base.len());
+    let mut push = |ch: char, style: Style| match out.last_mut() {
+        Some(last) if last.style == style => last.content.to_mut().push(ch),
+        _ => out.push(Span::styled(ch.to_string(), style)),
+    };
+    let mut col = 0usize;
+    for span in &base {
+        for ch in span.content.chars() {
+            let style = overlays
+                .iter()
+                .filter(|o| o.start <= col && col < o.end)
+                .fold(span.style, |style, o| style.patch(o.style));
+            push(ch, style);
+            col += unicode_width::UnicodeWidthChar::width(ch).unwrap_or(0);
+        }
+    }
+    out
+}
+
+/// The style for the padding after the text: the overlays that run to the
+/// end of the line, over `base`.
+pub(crate) fn padding_style(base: Style, overlays: &[Overlay]) -> Style {
+    overlays
+        .iter()
+        .filter(|o| o.end == usize::MAX)
+        .fold(base, |style, o| style.patch(o.style))
+}
+
+/// Merge conflict blocks: their marker lines stand out, our side's in the
+/// accent color, and both sides are shaded.
+struct MergeConflicts<'a> {
+    tab: &'a Tab,
+    body_style: Style,
+    ours_marker_style: Style,
+    marker_style: Style,
+}
+
+impl HighlightLayer for MergeConflicts<'_> {
+    fn overlays(&self, segment: &Segment<'_>, out: &mut Vec<Overlay>) {
+        let conflicts = &self.tab.merge_conflicts;
+        let idx = conflicts.partition_point(|c| c.end < segment.row);
+        let Some(conflict) = conflicts.get(idx).filter(|c| c.contains(segment.row)) else {
+            return;
+        };
+        let style = if segment.row == conflict.start {
+            self.ours_marker_style
+        } else if conflict.is_marker(segment.row) {
+            self.marker_style
+        } else {
+            self.body_style
+        };
+        out.push(Overlay {
+            start: 0,
+            end: usize::MAX,
+            style,
+        });
+    }
+}
+
+/// The cursor's line, unless part of it is selected.
+struct CurrentLine {
+    cursor: (usize, usize),
+    line_len: usize,
+    selection: Option<((usize, usize), (usize, usize))>,
+    style: Style,
+}
+
+impl HighlightLayer for CurrentLine {
+    fn overlays(&self, segment: &Segment<'_>, out: &mut Vec<Overlay>) {
+        let (row, col) = self.cursor;
+        let on_segment = row == segment.row
+            && col >= segment.seg_start
+            && (col < segment.seg_end || (col == segment.seg_end && col == self.line_len));
+        if on_segment
+            && !segment_has_selection(row, segment.seg_start, segment.seg_end, self.selection)
+        {
+            out.push(Overlay {
+                start: 0,
+                end: usize::MAX,
+                style: self.style,
+            });
+        }
+    }
+}
+
+/// Occurrences of the identifier under the cursor.
+struct SymbolOccurrences {
+    word: Vec<char>,
+    style: Style,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl SymbolOccurrences {
+    fn at_cursor(tab: &Tab, theme: &Theme) -> Self {
+        let (row, col) = tab.editor.cursor();
+        let chars: Vec<char> = tab
+            .editor
+            .lines()
+            .get(row)
+            .map(|l| l.chars().collect())
+            .unwrap_or_default();
+        let mut start = col.min(chars.len());
+        while start > 0 && is_word_char(chars[start - 1]) {
+            start -= 1;
+        }
+        let end = start
+            + chars[start..]
+                .iter()
+                .take_while(|c| is_word_char(**c))
+                .count();
+        let word = chars[start..end].to_vec();
+        // Single letters and numbers are too common to be worth marking.
+        let symbol =
+            word.len() > 1 && !word[0].is_ascii_digit() && tab.editor.selection_range().is_none();
+        Self {
+            word: if symbol { word } else { Vec::new() },
+            style: Style::default()
+                .bg(theme.bg_alt)
+                .add_modifier(Modifier::BOLD),
+        }
+    }
+}
+
+impl HighlightLayer for SymbolOccurrences {
+    fn overlays(&self, segment: &Segment<'_>, out: &mut Vec<Overlay>) {
+        if self.word.is_empty() {
+            return;
+        }
+        let chars: Vec<char> = segment.line.chars().collect();
+        let len = self.word.len();
+        for start in 0..chars.len().saturating_sub(len - 1) {
+            if chars[start..start + len] == self.word[..]
+                && (start == 0 || !is_word_char(chars[start - 1]))
+                && chars.get(start + len).is_none_or(|c| !is_word_char(*c))
+            {
+                segment.push(out, start, start + len, self.style);
+            }
+        }
+    }
+}
+
+/// Errors and warnings underlined where the server says they are, or
+/// across the line when it gave no range.
+struct Diagnostics<'a> {
+    tab: &'a Tab,
+}
+
+impl HighlightLayer for Diagnostics<'_> {
+    fn overlays(&self, segment: &Segment<'_>, out: &mut Vec<Overlay>) {
+        for diag in &self.tab.diagnostics {
+            let color = match diag.severity.as_str() {
+                "error" => Color::Red,
+                "warning" => Color::Yellow,
+                _ => continue,
+            };
+            let style = Style::default()
+                .add_modifier(Modifier::UNDERLINED)
+                .underline_color(color);
+            let Some(((start_row, start_col), (end_row, end_col))) = diag.range else {
+                if diag.line == segment.row + 1 && diag.severity == "error" {
+                    out.push(Overlay {
+                        start: 0,
+                        end: usize::MAX,
+                        style,
+                    });
+                }
+                continue;
+            };
+            if segment.row < start_row || segment.row > end_row {
+                continue;
+            }
+            let line_len = segment.line.chars().count();
+            let start = if segment.row == start_row {
+                start_col
+            } else {
+                0
+            };
+            let mut end = if segment.row == end_row {
+                end_col
+            } else {
+                line_len
+            };
+            if end <= start {
+                // An empty range still marks the char it points at.
+                end = start + 1;
+            }
+            segment.push(out, start, end, style);
+        }
+    }
+}
+
+/// Find matches, the one at the cursor standing out.
+struct SearchMatches<'a> {
+    tab: &'a Tab,
+    cursor: (usize, usize),
+    style: Style,
+    current_style: Style,
+}
+
+impl HighlightLayer for SearchMatches<'_> {
+    fn overlays(&self, segment: &Segment<'_>, out: &mut Vec<Overlay>) {
+        let matches = &self.tab.search_matches;
+        let first = matches.partition_point(|m| m.row < segment.row);
+        for m in matches[first..].iter().take_while(|m| m.row == segment.row) {
+            let style = if (m.row, m.start) == self.cursor {
+                self.current_style
+            } else {
+                self.style
+            };
+            segment.push(out, m.start, m.end, style);
+        }
+    }
+}
+
+/// The bracket at the cursor and its partner.
+struct BracketMatch {
+    pair: Option<((usize, usize), (usize, usize))>,
+    style: Style,
+}
+
+impl HighlightLayer for BracketMatch {
+    fn overlays(&self, segment: &Segment<'_>, out: &mut Vec<Overlay>) {
+        for (row, col) in self.pair.iter().flat_map(|&(a, b)| [a, b]) {
+            if row == segment.row {
+                segment.push(out, col, col + 1, self.style);
+            }
+        }
+    }
+}
+
+/// The selection, reaching into the padding when it continues on the next
+/// line.
+struct Selection {
+    selection: Option<((usize, usize), (usize, usize))>,
+    style: Style,
+}
+
+impl HighlightLayer for Selection {
+    fn overlays(&self, segment: &Segment<'_>, out: &mut Vec<Overlay>) {
+        let row = segment.row;
+        if !segment_has_selection(row, segment.seg_start, segment.seg_end, self.selection) {
+            return;
+        }
+        let Some((a, b)) = self.selection else {
+            return;
+        };
+        let ((start_row, start_col), (end_row, end_col)) = if a <= b { (a, b) } else { (b, a) };
+        let start = if row == start_row { start_col } else { 0 };
+        if row != end_row || end_col >= segment.seg_end {
+            let start = segment
+                .columns(start, segment.seg_end)
+                .map_or(0, |(start, _)| start);
+            out.push(Overlay {
+                start,
+                end: usize::MAX,
+                style: self.style,
+            });
+        } else {
+            segment.push(out, start, end_col, self.style);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_with_styles(spans: &[Span<'_>]) -> Vec<(String, Style)> {
+        spans
+            .iter()
+            .map(|s| (s.content.to_string(), s.style))
+            .collect()
+    }
+
+    #[test]
+    fn overlays_patch_sub_ranges_and_keep_syntax_colors() {
+        let keyword = Style::default().fg(Color::Magenta);
+        let ident = Style::default().fg(Color::Cyan);
+        let base = vec![Span::styled("let", keyword), Span::styled(" value", ident)];
+        let under = Style::default().add_modifier(Modifier::UNDERLINED);
+        let bg = Style::default().bg(Color::Blue);
+        let spans = compose_spans(
+            base,
+            &[
+                Overlay {
+                    start: 2,
+                    end: 6,
+                    style: bg,
+                },
+                Overlay {
+                    start: 5,
+                    end: usize::MAX,
+                    style: under,
+                },
+            ],
+        );
+        assert_eq!(
+            text_with_styles(&spans),
+            [
+                ("le".to_string(), keyword),
+                ("t".to_string(), keyword.bg(Color::Blue)),
+                (" v".to_string(), ident.bg(Color::Blue)),
+                (
+                    "a".to_string(),
+                    ident.bg(Color::Blue).add_modifier(Modifier::UNDERLINED)
+                ),
+                ("lue".to_string(), ident.add_modifier(Modifier::UNDERLINED)),
+            ]
+        );
+        assert_eq!(
+            padding_style(
+                Style::default(),
+                &[Overlay {
+                    start: 5,
+                    end: usize::MAX,
+                    style: under
+                }]
+            ),
+            under
+        );
+    }
+
+    #[test]
+    fn segment_columns_follow_wide_chars_tabs_and_scroll() {
+        let segment = Segment {
+            line: "\t漢字 ab",
+            row: 0,
+            seg_start: 0,
+            seg_end: 6,
+            scroll: 2,
+            tab_width: 4,
+        };
+        // The tab is four columns and each ideograph two.
+        assert_eq!(segment.columns(1, 3), Some((2, 6)));
+        assert_eq!(segment.columns(4, 10), Some((7, 9)));
+        assert_eq!(segment.columns(0, 0), None);
+        let wide = Segment {
+            tab_width: 8,
+            ..segment
+        };
+        assert_eq!(wide.columns(1, 3), Some((6, 10)));
+    }
+}
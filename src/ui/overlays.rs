@@ -1,17 +1,28 @@
 use ratatui::Frame;
 use ratatui::layout::Rect;
-use ratatui::style::{Modifier, Style};
+use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Clear, List, ListItem, Paragraph, Wrap};
+use ratatui::widgets::{Clear, List, ListItem, ListState, Paragraph, Wrap};
+use unicode_width::UnicodeWidthStr;
 
-use crate::app::App;
+use crate::app::{App, HexEdit};
+use crate::commands::command_for;
+use crate::diff::DiffOp;
+use crate::json_log::LogLevel;
 use crate::keybinds::KeyAction;
-use crate::types::PendingAction;
+use crate::preview::{format_size, half_block_cells};
+use crate::settings::{format_setting_value, matching_settings};
+use crate::tab::GitFileStatus;
+use crate::test_explorer::{TestOutcome, module_outcome};
+use crate::types::{PendingAction, ProjectSearchOptions, PromptMode, PromptState, StatusLevel};
 use crate::util::{
-    command_action_label, context_actions, context_label, editor_context_actions,
-    editor_context_label, primary_mod_label, relative_path,
+    ReplaceSegment, command_action_label, context_actions, context_label, editor_context_actions,
+    editor_context_label, editor_context_needs_lsp, extension_change, hex_dump_row,
+    parse_search_globs, pending_hint, primary_mod_label, project_search_regex, relative_path,
+    replace_segments, search_options_legend, to_u16_saturating,
 };
 
+use super::editor::{Gutter, editor_cursor_position, editor_lines};
 use super::helpers::{centered_rect, help_keybind_line, list_item_style, themed_block};
 
 pub(crate) fn render_menu(app: &mut App, frame: &mut Frame<'_>) {
@@ -30,25 +41,48 @@ pub(crate) fn render_menu(app: &mut App, frame: &mut Frame<'_>) {
             Style::default().fg(theme.fg_muted),
         ))));
     }
+    let width = usize::from(area.width.saturating_sub(2));
     let list_items: Vec<ListItem> = app
         .menu_results
         .iter()
         .enumerate()
         .map(|(idx, action)| {
-            let style = if idx == app.menu_index {
-                list_item_style(true, &theme)
+            let selected = idx == app.menu_index;
+            let style = list_item_style(selected, &theme);
+            let key_style = if selected {
+                style
             } else {
-                list_item_style(false, &theme)
+                Style::default().fg(theme.fg_muted)
             };
-            ListItem::new(Line::from(Span::styled(
-                command_action_label(*action),
-                style,
-            )))
+            let label = command_action_label(*action);
+            let key = command_for(*action)
+                .and_then(|c| c.key)
+                .and_then(|k| app.keybinds.bound_display(k))
+                .unwrap_or_default();
+            // Right-align the key hint, keeping the selection bar full width.
+            let gap = width.saturating_sub(label.width() + key.width()).max(1);
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{label}{}", " ".repeat(gap)), style),
+                Span::styled(key, key_style),
+            ]))
         })
         .collect();
     items.extend(list_items);
     let list = List::new(items).block(themed_block(&theme).title("Command Palette"));
     frame.render_widget(list, area);
+    set_query_cursor(&app.menu_query, area, frame);
+}
+
+/// Park the terminal cursor after a "Query: " line so IME composition
+/// windows open where the text will land.
+fn set_query_cursor(query: &str, area: Rect, frame: &mut Frame<'_>) {
+    let x = area
+        .x
+        .saturating_add(1)
+        .saturating_add(to_u16_saturating("Query: ".len() + query.width()));
+    if x < area.right().saturating_sub(1) {
+        frame.set_cursor_position((x, area.y.saturating_add(1)));
+    }
 }
 
 pub(crate) fn render_theme_browser(app: &mut App, frame: &mut Frame<'_>) {
@@ -86,20 +120,48 @@ pub(crate) fn render_file_picker(app: &mut App, frame: &mut Frame<'_>) {
         Span::styled(app.file_picker_query.clone(), Style::default().fg(theme.fg)),
     ]));
     lines.push(Line::from(""));
+    let muted = Style::default().fg(theme.fg_muted);
     if app.file_picker_results.is_empty() {
-        lines.push(Line::from(Span::styled(
-            "No matching files",
-            Style::default().fg(theme.fg_muted),
-        )));
+        let empty = match app.file_picker_query.chars().next() {
+            _ if app.file_picker_changed_only => "No matching files",
+            Some('@') => "No matching symbols in this file",
+            Some('#') if app.pending_workspace_symbol_request.is_some() => "Searching symbols...",
+            Some('#') => "Type to search workspace symbols",
+            Some(':') => "Type a line number, or line:column",
+            _ => "No matching files",
+        };
+        lines.push(Line::from(Span::styled(empty, muted)));
     } else {
-        for (idx, path) in app.file_picker_results.iter().take(25).enumerate() {
-            let rel = relative_path(&app.root, path).display().to_string();
+        for (idx, item) in app.file_picker_results.iter().take(25).enumerate() {
+            let rel = app.workspace_relative(&item.path).display().to_string();
             let style = if idx == app.file_picker_index {
                 list_item_style(true, &theme)
             } else {
                 list_item_style(false, &theme)
             };
-            lines.push(Line::from(Span::styled(rel, style)));
+            if let Some(label) = &item.label {
+                let mut spans = vec![Span::styled(label.clone(), style)];
+                // Lines stay in the active file; symbols say where they are.
+                if let Some((row, _)) = item.position
+                    && !app.file_picker_query.starts_with(':')
+                {
+                    spans.push(Span::styled(format!("  {rel}:{}", row + 1), muted));
+                }
+                lines.push(Line::from(spans));
+                continue;
+            }
+            let mut spans = Vec::new();
+            if app.file_picker_changed_only {
+                let status = app.changed_files.iter().find(|(p, _)| *p == item.path);
+                let (mark, color) = match status.map(|(_, status)| status) {
+                    Some(GitFileStatus::Modified) => ("M ", Color::Yellow),
+                    Some(GitFileStatus::Added) => ("A ", Color::Green),
+                    _ => ("U ", theme.fg_muted),
+                };
+                spans.push(Span::styled(mark, Style::default().fg(color)));
+            }
+            spans.push(Span::styled(rel, style));
+            lines.push(Line::from(spans));
         }
     }
     let paragraph = Paragraph::new(lines)
@@ -107,10 +169,18 @@ pub(crate) fn render_file_picker(app: &mut App, frame: &mut Frame<'_>) {
         .wrap(Wrap { trim: false })
         .block(
             themed_block(&theme)
-                .title(format!("Quick Open ({}+P)", primary_mod_label()))
+                .title(if app.file_picker_changed_only {
+                    "Changed Files (git)".to_string()
+                } else {
+                    format!(
+                        "Quick Open ({}+P) - @ symbols, # workspace symbols, : line",
+                        primary_mod_label()
+                    )
+                })
                 .style(Style::default().bg(theme.bg_alt)),
         );
     frame.render_widget(paragraph, area);
+    set_query_cursor(&app.file_picker_query, area, frame);
 }
 
 pub(crate) fn render_search_results(app: &mut App, frame: &mut Frame<'_>) {
@@ -118,27 +188,79 @@ pub(crate) fn render_search_results(app: &mut App, frame: &mut Frame<'_>) {
     let area = centered_rect(78, 72, frame.area());
     app.search_results_rect = area;
     frame.render_widget(Clear, area);
-    let list_items: Vec<ListItem> = if app.search_results.results.is_empty() {
-        vec![ListItem::new(Line::from("No results"))]
+    let results = &app.search_results;
+    // In replace mode each hit previews its line with the matches struck
+    // out and the replacement inserted after them.
+    let replace = results.replacement.as_deref().and_then(|replacement| {
+        project_search_regex(&results.query, &results.options)
+            .ok()
+            .map(|re| (re, replacement))
+    });
+    let list_items: Vec<ListItem> = if results.results.is_empty() {
+        let empty = if results.running.is_some() {
+            "Searching…"
+        } else {
+            "No results"
+        };
+        vec![ListItem::new(Line::from(empty))]
     } else {
-        app.search_results
+        results
             .results
             .iter()
             .enumerate()
             .map(|(idx, hit)| {
-                let rel = relative_path(&app.root, &hit.path);
-                let label = format!("{}:{}  {}", rel.display(), hit.line, hit.preview);
-                let style = if idx == app.search_results.index {
-                    list_item_style(true, &theme)
+                let rel = app.workspace_relative(&hit.path);
+                let style = list_item_style(idx == results.index, &theme);
+                let Some((re, replacement)) = &replace else {
+                    let label = format!("{}:{}  {}", rel.display(), hit.line, hit.preview);
+                    return ListItem::new(Line::from(Span::styled(label, style)));
+                };
+                let mark = if results.excluded.contains(&idx) {
+                    "[ ]"
                 } else {
-                    list_item_style(false, &theme)
+                    "[x]"
                 };
-                ListItem::new(Line::from(Span::styled(label, style)))
+                let mut spans = vec![Span::styled(
+                    format!("{} {}:{}  ", mark, rel.display(), hit.line),
+                    style,
+                )];
+                for segment in
+                    replace_segments(re, &hit.preview, replacement, results.options.literal)
+                {
+                    spans.push(match segment {
+                        ReplaceSegment::Keep(text) => Span::styled(text, style),
+                        ReplaceSegment::Removed(text) => Span::styled(
+                            text,
+                            style.fg(Color::Red).add_modifier(Modifier::CROSSED_OUT),
+                        ),
+                        ReplaceSegment::Inserted(text) => {
+                            Span::styled(text, style.fg(Color::Green))
+                        }
+                    });
+                }
+                ListItem::new(Line::from(spans))
             })
             .collect()
     };
-    let title = format!("Search Results: {}", app.search_results.query);
-    let list = List::new(list_items).block(themed_block(&theme).title(title));
+    const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+    let title = match (&results.running, &results.replacement) {
+        (Some(running), _) => format!(
+            "{} Searching: {} ({} hits) - Enter open, Esc cancel",
+            SPINNER[(running.started.elapsed().as_millis() / 100) as usize % SPINNER.len()],
+            results.query,
+            results.results.len()
+        ),
+        (None, Some(replacement)) => format!(
+            "Replace '{}' with '{}' ({}/{} hits) - Space toggle, a apply, r edit, Esc close",
+            results.query,
+            replacement,
+            results.results.len() - results.excluded.len(),
+            results.results.len()
+        ),
+        (None, None) => format!("Search Results: {} - Enter open, r replace", results.query),
+    };
+    let legend = Line::from(format!(" {} ", search_options_legend(&results.options)));
+    let list = List::new(list_items).block(themed_block(&theme).title(title).title_bottom(legend));
     frame.render_widget(list, area);
 }
 
@@ -377,10 +499,13 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
             sep_s,
         ),
         help_keybind_line(
-            &[(
-                &kb.display_for(KeyAction::GoToDefinition),
-                "go to definition",
-            )],
+            &[
+                (
+                    &kb.display_for(KeyAction::GoToDefinition),
+                    "go to definition",
+                ),
+                (&kb.display_for(KeyAction::ShowLastMessage), "last message"),
+            ],
             key_s,
             desc_s,
             sep_s,
@@ -389,6 +514,10 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
             &[
                 (&kb.display_for(KeyAction::FoldToggle), "toggle fold"),
                 (&kb.display_for(KeyAction::FoldAllToggle), "toggle fold all"),
+                (
+                    &kb.display_for(KeyAction::JumpToBracket),
+                    "matching bracket",
+                ),
             ],
             key_s,
             desc_s,
@@ -409,6 +538,8 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
             &[
                 (&kb.display_for(KeyAction::DupLineDown), "dup line down"),
                 (&kb.display_for(KeyAction::DupLineUp), "dup line up"),
+                (&kb.display_for(KeyAction::MoveLineDown), "move line down"),
+                (&kb.display_for(KeyAction::MoveLineUp), "move line up"),
             ],
             key_s,
             desc_s,
@@ -437,7 +568,7 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
         ),
         help_keybind_line(
             &[
-                ("Tab", "completion"),
+                ("Tab", "indent / accept completion"),
                 (&kb.display_for(KeyAction::Completion), "completion"),
             ],
             key_s,
@@ -460,6 +591,10 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
                 (&kb.display_for(KeyAction::Cut), "cut"),
                 (&kb.display_for(KeyAction::CutLine), "cut line"),
                 (&kb.display_for(KeyAction::Paste), "paste"),
+                (
+                    &kb.display_for(KeyAction::PasteFromHistory),
+                    "paste from history",
+                ),
                 (&kb.display_for(KeyAction::ToggleComment), "toggle comment"),
             ],
             key_s,
@@ -476,6 +611,17 @@ pub(crate) fn render_help(app: &mut App, frame: &mut Frame<'_>) {
             desc_s,
             sep_s,
         ),
+        help_keybind_line(
+            &[
+                (&kb.display_for(KeyAction::MoveTabLeft), "move tab left"),
+                (&kb.display_for(KeyAction::MoveTabRight), "move tab right"),
+                (&kb.display_for(KeyAction::CloseOtherTabs), "close others"),
+                (&kb.display_for(KeyAction::CloseAllTabs), "close all"),
+            ],
+            key_s,
+            desc_s,
+            sep_s,
+        ),
         Line::from(""),
         Line::from(Span::styled("Tree", heading)),
         Line::from(""),
@@ -591,14 +737,21 @@ pub(crate) fn render_editor_context_menu(app: &mut App, frame: &mut Frame<'_>) {
     let area = Rect::new(x, y, width, height);
     app.editor_context_menu_rect = area;
     frame.render_widget(Clear, area);
+    let lsp_attached = app.lsp_attached();
     let list_items: Vec<ListItem> = editor_context_actions()
         .iter()
         .enumerate()
         .map(|(idx, action)| {
-            let style = if idx == app.editor_context_menu_index {
-                list_item_style(true, &theme)
+            let selected = idx == app.editor_context_menu_index;
+            let style = if editor_context_needs_lsp(*action) && !lsp_attached {
+                let style = Style::default().fg(theme.fg_muted);
+                if selected {
+                    style.bg(theme.selection)
+                } else {
+                    style
+                }
             } else {
-                list_item_style(false, &theme)
+                list_item_style(selected, &theme)
             };
             ListItem::new(Line::from(Span::styled(
                 editor_context_label(*action),
@@ -615,13 +768,38 @@ pub(crate) fn render_prompt(app: &mut App, frame: &mut Frame<'_>) {
         return;
     };
     let title = prompt.title.clone();
-    let value = prompt.value.clone();
-    let cursor_pos = prompt.cursor;
+    let value_width = prompt.value[..prompt.cursor].width();
     let theme = app.active_theme().clone();
+    let mut lines = vec![Line::from(prompt_value_spans(prompt, &theme))];
+    if let PromptMode::Rename { target } = &prompt.mode
+        && let Some(note) = extension_change(target, prompt.value.trim())
+    {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!("Warning: {note}"),
+            Style::default().fg(theme.warning),
+        )));
+    }
+    if matches!(prompt.mode, PromptMode::FindInProject) {
+        let options = ProjectSearchOptions {
+            globs: parse_search_globs(&prompt.value).1,
+            ..app.project_search_options.clone()
+        };
+        let muted = Style::default().fg(theme.fg_muted);
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            search_options_legend(&options),
+            muted,
+        )));
+        lines.push(Line::from(Span::styled(
+            "Alt+C case  Alt+W word  Alt+R regex  -g GLOB filter files",
+            muted,
+        )));
+    }
     let area = centered_rect(60, 20, frame.area());
     app.prompt_rect = area;
     frame.render_widget(Clear, area);
-    let input = Paragraph::new(value).block(
+    let input = Paragraph::new(lines).block(
         themed_block(&theme)
             .title(title.as_str())
             .border_style(Style::default().fg(theme.accent))
@@ -629,13 +807,90 @@ pub(crate) fn render_prompt(app: &mut App, frame: &mut Frame<'_>) {
     );
     frame.render_widget(input, area);
     // Show a visible cursor at the current position in the input text
-    let cursor_x = area.x + 1 + cursor_pos as u16;
+    let cursor_x = area.x + 1 + to_u16_saturating(value_width);
     let cursor_y = area.y + 1;
     if cursor_x < area.right() {
         frame.set_cursor_position((cursor_x, cursor_y));
     }
 }
 
+/// The prompt's text with the selected part highlighted.
+fn prompt_value_spans(prompt: &PromptState, theme: &crate::theme::Theme) -> Vec<Span<'static>> {
+    let (start, end) = match prompt.anchor {
+        Some(anchor) => (anchor.min(prompt.cursor), anchor.max(prompt.cursor)),
+        None => (prompt.cursor, prompt.cursor),
+    };
+    vec![
+        Span::raw(prompt.value[..start].to_string()),
+        Span::styled(
+            prompt.value[start..end].to_string(),
+            Style::default().bg(theme.selection),
+        ),
+        Span::raw(prompt.value[end..].to_string()),
+    ]
+}
+
+/// The Find prompt, and the bar left open after confirming a search, drawn
+/// over the editor's bottom border so no text is hidden.
+pub(crate) fn render_find_bar(app: &mut App, frame: &mut Frame<'_>) {
+    let area = app.editor_rect;
+    if area.width < 4 || area.height < 2 {
+        return;
+    }
+    let theme = app.active_theme().clone();
+    let bar = Rect::new(area.x + 1, area.bottom() - 1, area.width - 2, 1);
+    let label = " Find: ";
+    let mut spans = vec![Span::styled(
+        label,
+        Style::default()
+            .fg(theme.accent)
+            .add_modifier(Modifier::BOLD),
+    )];
+    let text_x = bar.x + to_u16_saturating(label.width());
+    let typing = app
+        .prompt
+        .as_ref()
+        .filter(|p| matches!(p.mode, PromptMode::FindInFile));
+    let query = match typing {
+        Some(prompt) => {
+            spans.extend(prompt_value_spans(prompt, &theme));
+            let cursor_x = text_x + to_u16_saturating(prompt.value[..prompt.cursor].width());
+            if cursor_x < bar.right() {
+                frame.set_cursor_position((cursor_x, bar.y));
+            }
+            // Mouse clicks map columns from `prompt_rect.x + 1`.
+            app.prompt_rect = Rect::new(text_x - 1, bar.y, bar.right() - text_x + 1, 1);
+            prompt.value.clone()
+        }
+        None => {
+            let query = app
+                .active_tab()
+                .and_then(|t| t.editor.search_pattern())
+                .map(|re| re.as_str().to_string())
+                .unwrap_or_default();
+            spans.push(Span::raw(query.clone()));
+            query
+        }
+    };
+    let count = match app.search_match_position() {
+        Some((idx, total)) => format!("  {idx}/{total}"),
+        None if query.is_empty() => String::new(),
+        None => "  no matches".to_string(),
+    };
+    spans.push(Span::styled(count, Style::default().fg(theme.fg_muted)));
+    if typing.is_none() {
+        spans.push(Span::styled(
+            "   n/N: next/prev   Esc: close",
+            Style::default().fg(theme.fg_muted),
+        ));
+    }
+    frame.render_widget(Clear, bar);
+    frame.render_widget(
+        Paragraph::new(Line::from(spans)).style(Style::default().bg(theme.bg_alt).fg(theme.fg)),
+        bar,
+    );
+}
+
 fn render_dialog(
     area: Rect,
     title: &str,
@@ -659,12 +914,36 @@ pub(crate) fn render_close_prompt(app: &mut App, frame: &mut Frame<'_>) {
         "".to_string(),
         format!("Enter or {}+S: Save and close", primary_mod_label()),
         "Esc: Discard and close".to_string(),
+        "V: View diff against disk".to_string(),
         "C: Cancel".to_string(),
     ]
     .join("\n");
     render_dialog(area, "Close File", text, theme, frame);
 }
 
+/// The keys of the pending confirmation, on the row above the status bar
+/// so they stay apart from status messages.
+pub(crate) fn render_pending_banner(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme();
+    let status = app.status_rect;
+    if status.y == 0 {
+        return;
+    }
+    let area = Rect::new(status.x, status.y - 1, status.width, 1);
+    let mut text = format!(" {}", pending_hint(&app.pending));
+    if let Some(secs) = app.pending_seconds_left() {
+        text.push_str(&format!(" (cancels in {secs}s)"));
+    }
+    let banner = Paragraph::new(text).style(
+        Style::default()
+            .fg(theme.bg)
+            .bg(theme.accent)
+            .add_modifier(Modifier::BOLD),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(banner, area);
+}
+
 pub(crate) fn render_delete_prompt(app: &mut App, frame: &mut Frame<'_>) {
     let PendingAction::Delete(path) = &app.pending else {
         return;
@@ -685,6 +964,1009 @@ pub(crate) fn render_delete_prompt(app: &mut App, frame: &mut Frame<'_>) {
     render_dialog(area, "Confirm Delete", text, theme, frame);
 }
 
+pub(crate) fn render_overwrite_prompt(app: &mut App, frame: &mut Frame<'_>) {
+    let PendingAction::PasteOverwrite(paste) = &app.pending else {
+        return;
+    };
+    let theme = app.active_theme();
+    let area = centered_rect(64, 28, frame.area());
+    let text = [
+        format!(
+            "'{}' already exists. Replace it?",
+            relative_path(&app.root, &paste.to).display()
+        ),
+        "".to_string(),
+        "Enter or Y: Overwrite".to_string(),
+        "Esc or N: Cancel".to_string(),
+    ]
+    .join("\n");
+    render_dialog(area, "Confirm Overwrite", text, theme, frame);
+}
+
+/// The confirmation for a delete or paste of several marked tree entries,
+/// listing what it affects.
+pub(crate) fn render_bulk_tree_prompt(app: &mut App, frame: &mut Frame<'_>) {
+    const SHOWN: usize = 8;
+    let (title, heading, paths, confirm) = match &app.pending {
+        PendingAction::DeleteMany(paths) => (
+            "Confirm Delete",
+            format!("Delete these {} items?", paths.len()),
+            paths.iter().collect::<Vec<_>>(),
+            "Enter or Y: Confirm delete",
+        ),
+        PendingAction::PasteMany(pastes) => {
+            let cut = pastes.iter().any(|p| p.cut);
+            let dir = pastes
+                .first()
+                .and_then(|p| p.to.parent())
+                .map(|d| relative_path(&app.root, d).display().to_string())
+                .unwrap_or_default();
+            let replaced = pastes.iter().filter(|p| p.to.exists()).count();
+            let mut heading = format!(
+                "{} these {} items to '{dir}'?",
+                if cut { "Move" } else { "Copy" },
+                pastes.len()
+            );
+            if replaced > 0 {
+                heading.push_str(&format!(" {replaced} will replace existing entries."));
+            }
+            (
+                "Confirm Paste",
+                heading,
+                pastes.iter().map(|p| &p.from).collect(),
+                "Enter or Y: Confirm paste",
+            )
+        }
+        _ => return,
+    };
+    let mut lines = vec![heading, String::new()];
+    lines.extend(
+        paths
+            .iter()
+            .take(SHOWN)
+            .map(|p| format!("  {}", relative_path(&app.root, p).display())),
+    );
+    if paths.len() > SHOWN {
+        lines.push(format!("  …and {} more", paths.len() - SHOWN));
+    }
+    lines.extend([
+        String::new(),
+        confirm.to_string(),
+        "Esc or N: Cancel".to_string(),
+    ]);
+    let theme = app.active_theme();
+    let area = centered_rect(64, 50, frame.area());
+    render_dialog(area, title, lines.join("\n"), theme, frame);
+}
+
+pub(crate) fn render_revert_prompt(app: &mut App, frame: &mut Frame<'_>) {
+    let PendingAction::RevertToHead(path) = &app.pending else {
+        return;
+    };
+    let theme = app.active_theme();
+    let area = centered_rect(64, 30, frame.area());
+    let text = [
+        format!(
+            "Revert '{}' to HEAD?",
+            relative_path(&app.root, path).display()
+        ),
+        "".to_string(),
+        "Saved and unsaved changes since the last commit are".to_string(),
+        "discarded and the file is saved. Undo brings them back.".to_string(),
+        "".to_string(),
+        "Enter or Y: Revert".to_string(),
+        "V: View diff against HEAD".to_string(),
+        "Esc or N: Cancel".to_string(),
+    ]
+    .join("\n");
+    render_dialog(area, "Revert File", text, theme, frame);
+}
+
+pub(crate) fn render_trust_prompt(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme();
+    let area = centered_rect(64, 34, frame.area());
+    let text = [
+        format!("Trust '{}'?", app.root.display()),
+        "".to_string(),
+        "rust-analyzer builds the project to analyze it, which runs".to_string(),
+        "its build scripts and proc macros. Only trust code you know.".to_string(),
+        "".to_string(),
+        "Y: Trust and start rust-analyzer".to_string(),
+        "N: Don't trust (remembered)".to_string(),
+        "Esc: Ask again later".to_string(),
+    ]
+    .join("\n");
+    render_dialog(area, "Trust Project", text, theme, frame);
+}
+
+pub(crate) fn render_hex_view(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let root = app.root.clone();
+    let Some(hex) = app.hex_view.as_mut() else {
+        return;
+    };
+    let area = centered_rect(90, 85, frame.area());
+    frame.render_widget(Clear, area);
+    let muted = Style::default().fg(theme.fg_muted);
+    let mut info = format!("{}, {}", hex.kind, format_size(hex.size));
+    if let Some(image) = &hex.image {
+        info.push_str(&format!(", {} x {} px", image.width, image.height));
+    }
+    let mut lines = vec![Line::from(Span::styled(
+        info,
+        Style::default().fg(theme.accent),
+    ))];
+    if let Some(edit) = &hex.edit {
+        let mode = if edit.insert { "insert" } else { "overwrite" };
+        let column = if edit.ascii { "ASCII" } else { "hex" };
+        let dirty = if edit.dirty { ", modified" } else { "" };
+        lines.push(Line::from(Span::styled(
+            format!(
+                "Editing at {:08x}: {mode}, typing {column}{dirty}",
+                edit.cursor
+            ),
+            Style::default().fg(theme.accent),
+        )));
+    } else if let Some(reason) = &hex.reason {
+        lines.push(Line::from(Span::styled(
+            format!("Not opened as text: {reason}"),
+            muted,
+        )));
+    }
+    if let Some(image) = hex.image.as_ref().filter(|_| hex.edit.is_none()) {
+        // Up to half the overlay, leaving the rest for the hex dump.
+        let cols = area.width.saturating_sub(2);
+        let rows = area.height.saturating_sub(2) / 2;
+        for row in half_block_cells(&image.thumbnail, cols, rows) {
+            lines.push(Line::from(
+                row.into_iter()
+                    .map(|([tr, tg, tb], [br, bg, bb])| {
+                        Span::styled(
+                            "▀",
+                            Style::default()
+                                .fg(Color::Rgb(tr, tg, tb))
+                                .bg(Color::Rgb(br, bg, bb)),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            ));
+        }
+    }
+    lines.push(Line::from(""));
+    let rows = (area.height.saturating_sub(2) as usize).saturating_sub(lines.len());
+    hex.page_rows = rows.max(1);
+    let len = hex.bytes.len();
+    // While editing there is a row for the append position after a full
+    // last row.
+    let total_rows = if hex.edit.is_some() {
+        len / 16 + 1
+    } else {
+        len.div_ceil(16)
+    };
+    for row in (hex.scroll..total_rows).take(rows) {
+        let chunk = &hex.bytes[row * 16..((row + 1) * 16).min(len)];
+        let mut spans = vec![Span::styled(format!("{:08x}  ", row * 16), muted)];
+        match &hex.edit {
+            Some(edit) => spans.extend(hex_edit_row_spans(chunk, row * 16, len, edit)),
+            None => spans.push(Span::raw(hex_dump_row(chunk))),
+        }
+        lines.push(Line::from(spans));
+    }
+    let path = relative_path(&root, &hex.path);
+    let title = if hex.edit.is_some() {
+        format!(
+            "Hex Edit: {} - Tab hex/ASCII, Ins insert/overwrite, Ctrl+S save, Esc stop",
+            path.display()
+        )
+    } else {
+        format!(
+            "Preview: {} - Up/Down/PgUp/PgDn scroll, T open as text, E edit bytes, Esc close",
+            path.display()
+        )
+    };
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().fg(theme.fg).bg(theme.bg_alt))
+        .block(themed_block(&theme).title(title));
+    frame.render_widget(paragraph, area);
+}
+
+/// One hex row while editing: laid out like `hex_dump_row`, with the
+/// cursor reversed in the column being typed and underlined in the other.
+/// The slot after the last byte shows `__` when the cursor is there.
+fn hex_edit_row_spans(
+    chunk: &[u8],
+    start: usize,
+    len: usize,
+    edit: &HexEdit,
+) -> Vec<Span<'static>> {
+    let cursor_style = |typing: bool| {
+        if typing {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default().add_modifier(Modifier::UNDERLINED)
+        }
+    };
+    let mut spans = Vec::new();
+    for i in 0..16 {
+        let offset = start + i;
+        let text = match chunk.get(i) {
+            Some(b) => format!("{b:02x}"),
+            None if offset == len && edit.cursor == len => "__".to_string(),
+            None => "  ".to_string(),
+        };
+        if offset == edit.cursor {
+            spans.push(Span::styled(text, cursor_style(!edit.ascii)));
+        } else {
+            spans.push(Span::raw(text));
+        }
+        spans.push(Span::raw(if i == 7 { "  " } else { " " }));
+    }
+    spans.push(Span::raw("|"));
+    for (i, &b) in chunk.iter().enumerate() {
+        let c = if b.is_ascii_graphic() || b == b' ' {
+            b as char
+        } else {
+            '.'
+        };
+        if start + i == edit.cursor {
+            spans.push(Span::styled(c.to_string(), cursor_style(edit.ascii)));
+        } else {
+            spans.push(Span::raw(c.to_string()));
+        }
+    }
+    if edit.cursor == len && (start..start + 16).contains(&len) {
+        spans.push(Span::styled(" ", cursor_style(edit.ascii)));
+    }
+    spans.push(Span::raw("|"));
+    spans
+}
+
+pub(crate) fn render_diff_view(app: &mut App, frame: &mut Frame<'_>) {
+    let Some(diff) = app.diff_view.as_ref() else {
+        return;
+    };
+    let theme = app.active_theme().clone();
+    let area = frame.area();
+    frame.render_widget(Clear, area);
+    let rows = area.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = diff
+        .rows
+        .iter()
+        .skip(diff.scroll)
+        .take(rows)
+        .map(|(op, text)| {
+            let style = match op {
+                None => Style::default().fg(theme.accent),
+                Some(DiffOp::Delete) => Style::default().fg(Color::Red),
+                Some(DiffOp::Insert) => Style::default().fg(Color::Green),
+                Some(DiffOp::Equal) => Style::default().fg(theme.fg),
+            };
+            Line::from(Span::styled(text.clone(), style))
+        })
+        .collect();
+    let title = format!(
+        "Diff: {} ({}) - Up/Down/PgUp/PgDn scroll, n/N hunk, Esc close",
+        relative_path(&app.root, &diff.path).display(),
+        diff.legend
+    );
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().fg(theme.fg).bg(theme.bg_alt))
+        .block(themed_block(&theme).title(title));
+    frame.render_widget(paragraph, area);
+}
+
+pub(crate) fn render_language_overrides(app: &mut App, frame: &mut Frame<'_>) {
+    let Some(selected) = app.language_overrides_view else {
+        return;
+    };
+    let theme = app.active_theme().clone();
+    let area = centered_rect(62, 50, frame.area());
+    frame.render_widget(Clear, area);
+    let items: Vec<ListItem> = app
+        .language_overrides
+        .iter()
+        .enumerate()
+        .map(|(idx, (path, lang))| {
+            let label = format!(
+                "{}  →  {}",
+                relative_path(&app.root, path).display(),
+                lang.name()
+            );
+            ListItem::new(Line::from(Span::styled(
+                label,
+                list_item_style(idx == selected, &theme),
+            )))
+        })
+        .collect();
+    let list = List::new(items).block(
+        themed_block(&theme)
+            .title("Language Mode Overrides - Enter open, d forget, Esc close")
+            .style(Style::default().bg(theme.bg_alt)),
+    );
+    frame.render_widget(list, area);
+}
+
+pub(crate) fn render_clipboard_history(app: &mut App, frame: &mut Frame<'_>) {
+    let Some(selected) = app.clipboard_history_view else {
+        return;
+    };
+    let theme = app.active_theme().clone();
+    let area = centered_rect(70, 50, frame.area());
+    frame.render_widget(Clear, area);
+    let items: Vec<ListItem> = app
+        .clipboard_history
+        .iter()
+        .enumerate()
+        .map(|(idx, text)| {
+            let first = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+            let extra = text.lines().count().saturating_sub(1);
+            let label = if extra > 0 {
+                format!("{}  (+{extra} lines)", first.trim())
+            } else {
+                first.trim().to_string()
+            };
+            ListItem::new(Line::from(Span::styled(
+                label,
+                list_item_style(idx == selected, &theme),
+            )))
+        })
+        .collect();
+    let list = List::new(items).block(
+        themed_block(&theme)
+            .title("Paste from History - Enter paste, d remove, Esc close")
+            .style(Style::default().bg(theme.bg_alt)),
+    );
+    frame.render_widget(list, area);
+}
+
+pub(crate) fn render_recent_projects(app: &mut App, frame: &mut Frame<'_>) {
+    let Some(selected) = app.recent_projects_view else {
+        return;
+    };
+    let theme = app.active_theme().clone();
+    let area = centered_rect(70, 50, frame.area());
+    frame.render_widget(Clear, area);
+    let items: Vec<ListItem> = app
+        .recent_project_choices()
+        .iter()
+        .enumerate()
+        .map(|(idx, path)| {
+            let style = list_item_style(idx == selected, &theme);
+            let name = path
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let mut spans = vec![
+                Span::styled(format!("{name}  "), style.add_modifier(Modifier::BOLD)),
+                Span::styled(path.display().to_string(), style),
+            ];
+            if !path.is_dir() {
+                spans.push(Span::styled("  (missing)", style.fg(Color::Red)));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items).block(
+        themed_block(&theme)
+            .title("Open Recent Project - Enter open, d remove, Esc close")
+            .style(Style::default().bg(theme.bg_alt)),
+    );
+    frame.render_widget(list, area);
+}
+
+pub(crate) fn render_bookmarks(app: &mut App, frame: &mut Frame<'_>) {
+    let Some(view) = app.bookmarks_view.as_ref() else {
+        return;
+    };
+    let theme = app.active_theme().clone();
+    let area = centered_rect(70, 50, frame.area());
+    frame.render_widget(Clear, area);
+    let items: Vec<ListItem> = view
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(idx, (path, row, text))| {
+            let style = list_item_style(idx == view.selected, &theme);
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{}:{}  ", app.workspace_relative(path).display(), row + 1),
+                    style.add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(text.clone(), style),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).block(
+        themed_block(&theme)
+            .title("Bookmarks - Enter go to, d remove, Esc close")
+            .style(Style::default().bg(theme.bg_alt)),
+    );
+    frame.render_widget(list, area);
+}
+
+pub(crate) fn render_location_picker(app: &mut App, frame: &mut Frame<'_>) {
+    let Some(picker) = app.location_picker.as_ref() else {
+        return;
+    };
+    let theme = app.active_theme().clone();
+    let area = centered_rect(80, 50, frame.area());
+    frame.render_widget(Clear, area);
+    let items: Vec<ListItem> = picker
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(idx, (path, row, col, context))| {
+            let style = list_item_style(idx == picker.selected, &theme);
+            let name = app.workspace_relative(path).display().to_string();
+            // The start of a file is left out: the entry is the file itself.
+            let location = if *row == 0 && *col == 0 {
+                name
+            } else {
+                format!("{name}:{}:{}", row + 1, col + 1)
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{location}  "), style.add_modifier(Modifier::BOLD)),
+                Span::styled(context.clone(), style),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).block(
+        themed_block(&theme)
+            .title(picker.title.clone())
+            .style(Style::default().bg(theme.bg_alt)),
+    );
+    frame.render_widget(list, area);
+}
+
+pub(crate) fn render_task_picker(app: &mut App, frame: &mut Frame<'_>) {
+    let Some(picker) = app.task_picker.as_ref() else {
+        return;
+    };
+    let theme = app.active_theme().clone();
+    let area = centered_rect(62, 50, frame.area());
+    frame.render_widget(Clear, area);
+    let mut items = vec![ListItem::new(Line::from(vec![
+        Span::styled("Query: ", Style::default().fg(theme.fg_muted)),
+        Span::styled(picker.query.clone(), Style::default().fg(theme.fg)),
+    ]))];
+    let matches = App::task_picker_matches(picker);
+    if matches.is_empty() {
+        items.push(ListItem::new(Line::from(Span::styled(
+            "No matching tasks",
+            Style::default().fg(theme.fg_muted),
+        ))));
+    }
+    items.extend(matches.iter().enumerate().map(|(idx, task)| {
+        ListItem::new(Line::from(Span::styled(
+            task.label.clone(),
+            list_item_style(idx == picker.selected, &theme),
+        )))
+    }));
+    let list = List::new(items).block(
+        themed_block(&theme)
+            .title("Run Task - Enter run, Esc close")
+            .style(Style::default().bg(theme.bg_alt)),
+    );
+    frame.render_widget(list, area);
+    set_query_cursor(&picker.query, area, frame);
+}
+
+pub(crate) fn render_branch_picker(app: &mut App, frame: &mut Frame<'_>) {
+    let Some(picker) = app.branch_picker.as_ref() else {
+        return;
+    };
+    let theme = app.active_theme().clone();
+    let area = centered_rect(62, 50, frame.area());
+    frame.render_widget(Clear, area);
+    let muted = Style::default().fg(theme.fg_muted);
+    let mut items = vec![ListItem::new(Line::from(vec![
+        Span::styled("Branch: ", muted),
+        Span::styled(picker.query.clone(), Style::default().fg(theme.fg)),
+    ]))];
+    let matches = App::branch_picker_matches(picker);
+    if matches.is_empty() {
+        let hint = if picker.query.trim().is_empty() {
+            "No branches".to_string()
+        } else {
+            format!("Enter creates branch {}", picker.query.trim())
+        };
+        items.push(ListItem::new(Line::from(Span::styled(hint, muted))));
+    }
+    items.extend(matches.iter().enumerate().map(|(idx, branch)| {
+        let current = app.git_branch.as_deref() == Some(branch.as_str());
+        let style = list_item_style(idx == picker.selected, &theme);
+        ListItem::new(Line::from(vec![
+            Span::styled(if current { "* " } else { "  " }, style),
+            Span::styled((*branch).clone(), style),
+        ]))
+    }));
+    let list = List::new(items).block(
+        themed_block(&theme)
+            .title("Switch Branch - Enter check out, a new name creates it, Esc close")
+            .style(Style::default().bg(theme.bg_alt)),
+    );
+    frame.render_widget(list, area);
+    set_query_cursor(&picker.query, area, frame);
+}
+
+pub(crate) fn render_test_explorer(app: &mut App, frame: &mut Frame<'_>) {
+    let Some(explorer) = app.test_explorer.as_ref() else {
+        return;
+    };
+    let theme = app.active_theme().clone();
+    let area = centered_rect(70, 60, frame.area());
+    frame.render_widget(Clear, area);
+    let rows = App::test_explorer_rows(explorer);
+    let items: Vec<ListItem> = rows
+        .iter()
+        .enumerate()
+        .map(|(idx, row)| {
+            let style = list_item_style(idx == explorer.selected, &theme);
+            let outcome = if row.is_test {
+                explorer.outcomes.get(&row.path).copied()
+            } else {
+                module_outcome(&row.path, &explorer.tests, &explorer.outcomes)
+            };
+            let (icon, color) = match outcome {
+                Some(TestOutcome::Passed) => ("✓", Color::Green),
+                Some(TestOutcome::Failed) => ("✗", Color::Red),
+                Some(TestOutcome::Ignored) => ("○", theme.fg_muted),
+                None => ("·", theme.fg_muted),
+            };
+            let marker = match (row.is_test, explorer.collapsed.contains(&row.path)) {
+                (true, _) => "  ",
+                (false, true) => "▸ ",
+                (false, false) => "▾ ",
+            };
+            let icon_style = if idx == explorer.selected {
+                style
+            } else {
+                Style::default().fg(color)
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{}{icon} ", "  ".repeat(row.depth)), icon_style),
+                Span::styled(format!("{marker}{}", row.name()), style),
+            ]))
+        })
+        .collect();
+    let title = if explorer.list_rx.is_some() {
+        "Test Explorer - building and listing tests...".to_string()
+    } else {
+        format!(
+            "Test Explorer - {} tests - Enter run, a all, g go to failure, Space fold, r reload, Esc close",
+            explorer.tests.len()
+        )
+    };
+    let list = List::new(items).block(
+        themed_block(&theme)
+            .title(title)
+            .style(Style::default().bg(theme.bg_alt)),
+    );
+    let mut state = ListState::default();
+    state.select(Some(explorer.selected));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// The output of the last task, docked above the status bar. Lines naming
+/// a `file:line` are underlined; clicking one opens it.
+pub(crate) fn render_task_panel(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let area = app.task_panel_rect;
+    let Some(panel) = app.task_panel.as_mut() else {
+        return;
+    };
+    let rows = area.height.saturating_sub(2) as usize;
+    let max_scroll = panel.lines.len().saturating_sub(rows);
+    panel.scroll = if panel.follow {
+        max_scroll
+    } else {
+        panel.scroll.min(max_scroll)
+    };
+    let lines: Vec<Line> = panel.lines[panel.scroll..]
+        .iter()
+        .take(rows)
+        .map(|line| {
+            let style = if line.location.is_some() {
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::UNDERLINED)
+            } else {
+                Style::default().fg(theme.fg)
+            };
+            Line::from(Span::styled(line.text.clone(), style))
+        })
+        .collect();
+    let state = match panel.exit {
+        None => "running".to_string(),
+        Some(Some(0)) => "ok".to_string(),
+        Some(Some(code)) => format!("failed (exit {code})"),
+        Some(None) => "stopped".to_string(),
+    };
+    let env = match &panel.env_file {
+        Some(name) => format!(" (env: {name})"),
+        None => String::new(),
+    };
+    let mut block = themed_block(&theme).title(format!(
+        "Task: {}{env} - {state} - click a location to open it",
+        panel.label
+    ));
+    if matches!(panel.exit, Some(code) if code != Some(0)) {
+        block = block.border_style(Style::default().fg(Color::Red));
+    }
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+pub(crate) fn render_batch_rename(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let Some(state) = app.batch_rename.as_ref() else {
+        return;
+    };
+    let area = centered_rect(80, 60, frame.area());
+    frame.render_widget(Clear, area);
+    let name = |p: &std::path::Path| {
+        p.file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    let items: Vec<ListItem> = state
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(idx, entry)| {
+            let style = list_item_style(idx == state.selected, &theme);
+            let mut spans = vec![Span::styled(
+                format!("{}  →  {}", name(&entry.from), name(&entry.to)),
+                style,
+            )];
+            if let Some(problem) = &entry.problem {
+                spans.push(Span::styled(format!("  ({problem})"), style.fg(Color::Red)));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let mut list_state = ListState::default().with_selected(Some(state.selected));
+    let list = List::new(items).block(
+        themed_block(&theme)
+            .title(format!(
+                "Batch Rename in {} - Enter apply, Esc cancel",
+                relative_path(&app.root, &state.dir).display()
+            ))
+            .style(Style::default().bg(theme.bg_alt)),
+    );
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
+
+pub(crate) fn render_follow_view(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let area = frame.area();
+    let block = themed_block(&theme).style(Style::default().bg(theme.bg).fg(theme.fg));
+    let inner = block.inner(area);
+    app.center_follow_cursor(inner);
+    let Some(view) = app.follow_view.as_ref() else {
+        return;
+    };
+    frame.render_widget(Clear, area);
+    let Some(tab) = view.tab.as_ref() else {
+        let title = "Follow Mode - Esc to stop";
+        let text = format!("Waiting for a broadcast at {}", view.source.display());
+        frame.render_widget(Paragraph::new(text).block(block.title(title)), area);
+        return;
+    };
+    let title = if tab.path.as_os_str().is_empty() {
+        "Following (no file open) - Esc to stop".to_string()
+    } else {
+        format!(
+            "Following {} - Esc to stop",
+            relative_path(&view.root, &tab.path).display()
+        )
+    };
+    frame.render_widget(block.title(title), area);
+    let lines = editor_lines(tab, &theme, false, Gutter::ALL, inner);
+    frame.render_widget(Paragraph::new(lines), inner);
+    if let Some(pos) = editor_cursor_position(tab, false, Gutter::ALL, inner)
+        && let Some(cell) = frame.buffer_mut().cell_mut(pos)
+    {
+        cell.modifier.insert(Modifier::REVERSED);
+    }
+}
+
+pub(crate) fn render_log_view(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let root = app.root.clone();
+    let Some(view) = app.log_view.as_mut() else {
+        return;
+    };
+    let area = frame.area();
+    frame.render_widget(Clear, area);
+    let show_query = view.editing_query || !view.filter.query.is_empty();
+    let rows = area.height.saturating_sub(2 + u16::from(show_query)) as usize;
+    let max_scroll = view.visible.len().saturating_sub(rows);
+    view.scroll = view.scroll.min(max_scroll);
+    let shown: Vec<_> = view.visible[view.scroll..]
+        .iter()
+        .take(rows)
+        .map(|&i| &view.records[i])
+        .collect();
+    let time_width = shown
+        .iter()
+        .map(|r| r.time_text().width())
+        .max()
+        .unwrap_or(0);
+    let muted = Style::default().fg(theme.fg_muted);
+    let mut lines: Vec<Line> = shown
+        .iter()
+        .map(|record| {
+            let time = record.time_text();
+            let pad = time_width.saturating_sub(time.width());
+            let (label, color) = match record.level {
+                Some(LogLevel::Error) => ("ERROR", Color::Red),
+                Some(LogLevel::Warn) => ("WARN", Color::Yellow),
+                Some(LogLevel::Info) => ("INFO", Color::Green),
+                Some(LogLevel::Debug) => ("DEBUG", Color::Cyan),
+                Some(LogLevel::Trace) => ("TRACE", theme.fg_muted),
+                None => ("", theme.fg_muted),
+            };
+            let mut spans = Vec::new();
+            if time_width > 0 {
+                spans.push(Span::styled(format!("{time}{} ", " ".repeat(pad)), muted));
+            }
+            spans.push(Span::styled(
+                format!("{label:<5} "),
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            ));
+            spans.push(Span::styled(
+                record.message_text().to_string(),
+                Style::default().fg(theme.fg),
+            ));
+            for (key, value) in record.extra_fields() {
+                spans.push(Span::styled(format!("  {key}="), muted));
+                spans.push(Span::styled(
+                    value.clone(),
+                    Style::default().fg(theme.accent),
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    if show_query {
+        lines.resize(rows, Line::default());
+        let cursor = if view.editing_query { "_" } else { "" };
+        lines.push(Line::from(vec![
+            Span::styled("Filter: ", Style::default().fg(theme.accent)),
+            Span::raw(format!("{}{cursor}", view.filter.query)),
+        ]));
+    }
+    let level = view
+        .filter
+        .min_level
+        .map_or(String::new(), |l| format!(", level >= {}", l.label()));
+    let title = format!(
+        "Log: {} - {}/{} records{level}{} - / filter, l level, f follow, Esc raw file",
+        relative_path(&root, &view.path).display(),
+        view.visible.len(),
+        view.records.len(),
+        if view.follow { ", following" } else { "" }
+    );
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().fg(theme.fg).bg(theme.bg))
+        .block(themed_block(&theme).title(title));
+    frame.render_widget(paragraph, area);
+}
+
+pub(crate) fn render_snippet_output(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let Some(view) = app.snippet_output.as_mut() else {
+        return;
+    };
+    let area = centered_rect(80, 60, frame.area());
+    frame.render_widget(Clear, area);
+    let inner_width = area.width.saturating_sub(2).max(1) as usize;
+    let rows: usize = view
+        .text
+        .lines()
+        .map(|l| l.width().div_ceil(inner_width).max(1))
+        .sum();
+    let visible = area.height.saturating_sub(2) as usize;
+    view.scroll = view.scroll.min(rows.saturating_sub(visible));
+    let color = if view.success {
+        theme.fg
+    } else {
+        Color::Yellow
+    };
+    let title = format!("{} - j/k scroll, c copy, Esc close", view.title);
+    let paragraph = Paragraph::new(view.text.clone())
+        .style(Style::default().fg(color).bg(theme.bg_alt))
+        .scroll((to_u16_saturating(view.scroll), 0))
+        .wrap(Wrap { trim: false })
+        .block(themed_block(&theme).title(title));
+    frame.render_widget(paragraph, area);
+}
+
+pub(crate) fn render_settings(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let Some(view) = app.settings_view.as_ref() else {
+        return;
+    };
+    let area = centered_rect(72, 70, frame.area());
+    frame.render_widget(Clear, area);
+    let muted = Style::default().fg(theme.fg_muted);
+    let mut items = vec![ListItem::new(Line::from(vec![
+        Span::styled("Query: ", muted),
+        Span::styled(view.query.clone(), Style::default().fg(theme.fg)),
+    ]))];
+    let matches = matching_settings(&view.query);
+    if matches.is_empty() {
+        items.push(ListItem::new(Line::from(Span::styled(
+            "No settings",
+            muted,
+        ))));
+    }
+    for (idx, def) in matches.iter().enumerate() {
+        let value = app.setting_value(def);
+        let text = if value.is_null() {
+            "(default)".to_string()
+        } else {
+            format_setting_value(&value)
+        };
+        let style = list_item_style(idx == view.selected, &theme);
+        items.push(ListItem::new(Line::from(vec![
+            Span::styled(format!("{:<32}", def.name), style),
+            Span::styled(text, style.fg(theme.accent)),
+        ])));
+    }
+    items.push(ListItem::new(Line::from("")));
+    if let Some(def) = matches.get(view.selected) {
+        items.push(ListItem::new(Line::from(Span::styled(
+            def.description,
+            muted,
+        ))));
+    }
+    if let Some(text) = &view.editing {
+        items.push(ListItem::new(Line::from(vec![
+            Span::styled("New value: ", muted),
+            Span::styled(text.clone(), Style::default().fg(theme.fg)),
+        ])));
+    }
+    if let Some(err) = &view.error {
+        items.push(ListItem::new(Line::from(Span::styled(
+            err.clone(),
+            Style::default().fg(Color::Yellow),
+        ))));
+    }
+    let title = if view.editing.is_some() {
+        "Settings - Enter apply, Esc cancel"
+    } else {
+        "Settings - type to search, Enter edit/toggle, Esc close"
+    };
+    let list = List::new(items).block(
+        themed_block(&theme)
+            .title(title)
+            .style(Style::default().bg(theme.bg_alt)),
+    );
+    frame.render_widget(list, area);
+    if view.editing.is_none() {
+        set_query_cursor(&view.query, area, frame);
+    }
+}
+
+pub(crate) fn render_message_view(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let total = app.message_log.len();
+    let Some(view) = app.message_view.as_mut() else {
+        return;
+    };
+    let Some((level, text)) = app.message_log.get(view.index) else {
+        return;
+    };
+    let area = centered_rect(80, 60, frame.area());
+    frame.render_widget(Clear, area);
+    let inner_width = area.width.saturating_sub(2).max(1) as usize;
+    let rows: usize = text
+        .lines()
+        .map(|l| {
+            if view.wrap {
+                l.width().div_ceil(inner_width).max(1)
+            } else {
+                1
+            }
+        })
+        .sum();
+    let visible = area.height.saturating_sub(2) as usize;
+    view.scroll = view.scroll.min(rows.saturating_sub(visible));
+    let color = match level {
+        StatusLevel::Error => Color::Red,
+        StatusLevel::Warn => Color::Yellow,
+        StatusLevel::Info => theme.fg,
+    };
+    let title = format!(
+        "Message {}/{} - Up/Down older/newer, j/k scroll, w wrap ({}), c copy, Esc close",
+        view.index + 1,
+        total,
+        if view.wrap { "on" } else { "off" }
+    );
+    let mut paragraph = Paragraph::new(text.clone())
+        .style(Style::default().fg(color).bg(theme.bg_alt))
+        .scroll((to_u16_saturating(view.scroll), 0))
+        .block(themed_block(&theme).title(title));
+    if view.wrap {
+        paragraph = paragraph.wrap(Wrap { trim: false });
+    }
+    frame.render_widget(paragraph, area);
+}
+
+pub(crate) fn render_merge_view(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let Some(merge) = app.merge_view.as_mut() else {
+        return;
+    };
+    let area = frame.area();
+    frame.render_widget(Clear, area);
+    let chosen = |take: bool| {
+        if take {
+            Style::default().fg(theme.fg)
+        } else {
+            Style::default()
+                .fg(theme.fg_muted)
+                .add_modifier(Modifier::CROSSED_OUT)
+        }
+    };
+    let mut lines: Vec<Line> = Vec::new();
+    let mut selected_row = 0;
+    for (i, hunk) in merge.hunks.iter().enumerate() {
+        let take_disk = merge.take_disk[i];
+        if i == merge.selected {
+            selected_row = lines.len();
+        }
+        let marker = if i == merge.selected { "▶" } else { " " };
+        let header_style = if i == merge.selected {
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.fg_muted)
+        };
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{marker} Hunk {}/{} at line {}: using {}",
+                i + 1,
+                merge.hunks.len(),
+                hunk.old_start + 1,
+                if take_disk { "disk" } else { "buffer" }
+            ),
+            header_style,
+        )));
+        let buffer = &merge.buffer[hunk.old_start..hunk.old_start + hunk.old_len];
+        let disk = &merge.disk[hunk.new_start..hunk.new_start + hunk.new_len];
+        for line in buffer {
+            lines.push(Line::from(vec![
+                Span::styled("  < ", Style::default().fg(Color::Red)),
+                Span::styled(line.clone(), chosen(!take_disk)),
+            ]));
+        }
+        for line in disk {
+            lines.push(Line::from(vec![
+                Span::styled("  > ", Style::default().fg(Color::Green)),
+                Span::styled(line.clone(), chosen(take_disk)),
+            ]));
+        }
+        lines.push(Line::from(""));
+    }
+    let rows = area.height.saturating_sub(2) as usize;
+    if selected_row < merge.scroll {
+        merge.scroll = selected_row;
+    } else if selected_row >= merge.scroll + rows {
+        merge.scroll = selected_row + 1 - rows.max(1);
+    }
+    let title = "Merge (< buffer, > disk) - n/N hunk, b/d take buffer/disk, Space toggle, \
+                 B/D all, Enter apply, Esc back";
+    let paragraph = Paragraph::new(lines)
+        .scroll((to_u16_saturating(merge.scroll), 0))
+        .style(Style::default().fg(theme.fg).bg(theme.bg_alt))
+        .block(themed_block(&theme).title(title));
+    frame.render_widget(paragraph, area);
+}
+
 pub(crate) fn render_conflict_prompt(app: &mut App, frame: &mut Frame<'_>) {
     let theme = app.active_theme();
     let area = centered_rect(68, 30, frame.area());
@@ -693,6 +1975,8 @@ pub(crate) fn render_conflict_prompt(app: &mut App, frame: &mut Frame<'_>) {
         "",
         "R: Reload disk version (discard current edits)",
         "K: Keep local edits",
+        "V: View diff (disk vs buffer)",
+        "M: Merge hunk by hunk",
         "D or Esc: Decide later",
     ]
     .join("\n");
@@ -707,8 +1991,174 @@ pub(crate) fn render_recovery_prompt(app: &mut App, frame: &mut Frame<'_>) {
         "",
         "Enter or R: Recover autosave",
         "D: Discard autosave",
+        "V: View diff against the buffer",
         "Esc or C: Cancel",
     ]
     .join("\n");
     render_dialog(area, "Recover Autosave", text, theme, frame);
 }
+
+/// The "Git Log" popup: one commit per row, with the selected commit's
+/// `git show` below once Enter opens it.
+pub(crate) fn render_git_log(app: &mut App, frame: &mut Frame<'_>) {
+    let Some(log) = app.git_log.as_ref() else {
+        return;
+    };
+    let theme = app.active_theme().clone();
+    let area = centered_rect(80, 80, frame.area());
+    frame.render_widget(Clear, area);
+    let (list_area, details_area) = if log.details.is_some() {
+        let split = area.height * 2 / 5;
+        (
+            Rect {
+                height: split,
+                ..area
+            },
+            Some(Rect {
+                y: area.y + split,
+                height: area.height - split,
+                ..area
+            }),
+        )
+    } else {
+        (area, None)
+    };
+    let muted = Style::default().fg(theme.fg_muted);
+    let items: Vec<ListItem> = log
+        .commits
+        .iter()
+        .enumerate()
+        .map(|(idx, commit)| {
+            let selected = idx == log.selected;
+            let style = list_item_style(selected, &theme);
+            let meta = if selected { style } else { muted };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{} ", commit.short_hash()), meta),
+                Span::styled(format!("{} ", commit.date), meta),
+                Span::styled(commit.subject.clone(), style),
+                Span::styled(format!("  {}", commit.author), meta),
+            ]))
+        })
+        .collect();
+    let what = match &log.file {
+        Some(path) => format!("History of {}", relative_path(&app.root, path).display()),
+        None => "Git Log".to_string(),
+    };
+    let title = format!(
+        "{what} - {} commits - Enter details, o open at commit, d diff with current, Esc close",
+        log.commits.len()
+    );
+    let list = List::new(items).block(
+        themed_block(&theme)
+            .title(title)
+            .style(Style::default().bg(theme.bg_alt)),
+    );
+    let mut state = ListState::default();
+    state.select(Some(log.selected));
+    frame.render_stateful_widget(list, list_area, &mut state);
+
+    let (Some(area), Some(details)) = (details_area, log.details.as_ref()) else {
+        return;
+    };
+    let lines: Vec<Line> = details
+        .iter()
+        .map(|line| {
+            let style = if line.starts_with('+') && !line.starts_with("+++") {
+                Style::default().fg(Color::Green)
+            } else if line.starts_with('-') && !line.starts_with("---") {
+                Style::default().fg(Color::Red)
+            } else if line.starts_with("@@") || line.starts_with("commit ") {
+                Style::default().fg(theme.accent)
+            } else {
+                Style::default().fg(theme.fg)
+            };
+            Line::from(Span::styled(line.clone(), style))
+        })
+        .collect();
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().bg(theme.bg_alt))
+        .scroll((to_u16_saturating(log.details_scroll), 0))
+        .block(themed_block(&theme).title("Commit - j/k scroll, Esc back"));
+    frame.render_widget(paragraph, area);
+}
+
+/// "Diff with HEAD": HEAD on the left, the buffer on the right, with the
+/// selected hunk marked in the gutter.
+pub(crate) fn render_head_diff(app: &mut App, frame: &mut Frame<'_>) {
+    let theme = app.active_theme().clone();
+    let Some(diff) = app.head_diff.as_mut() else {
+        return;
+    };
+    let area = frame.area();
+    frame.render_widget(Clear, area);
+    let rows = area.height.saturating_sub(2) as usize;
+    diff.scroll = diff.scroll.min(diff.rows.len().saturating_sub(rows));
+    let number_width = diff.head.len().max(diff.buffer.len()).to_string().len();
+    let side = |text: &[String], index: Option<usize>, hunk: Option<usize>, color: Color| {
+        let marker = if hunk.is_some_and(|h| h == diff.selected) {
+            Span::styled("▌", Style::default().fg(theme.accent))
+        } else {
+            Span::raw(" ")
+        };
+        let Some(i) = index else {
+            return Line::from(vec![
+                marker,
+                Span::styled(
+                    format!("{:>number_width$} ", ""),
+                    Style::default().fg(theme.fg_muted),
+                ),
+            ]);
+        };
+        let style = match hunk {
+            Some(_) => Style::default().fg(color),
+            None => Style::default().fg(theme.fg),
+        };
+        Line::from(vec![
+            marker,
+            Span::styled(
+                format!("{:>number_width$} ", i + 1),
+                Style::default().fg(theme.fg_muted),
+            ),
+            Span::styled(text[i].clone(), style),
+        ])
+    };
+    let visible = &diff.rows[diff.scroll..(diff.scroll + rows).min(diff.rows.len())];
+    let head: Vec<Line> = visible
+        .iter()
+        .map(|row| side(&diff.head, row.new, row.hunk, Color::Red))
+        .collect();
+    let buffer: Vec<Line> = visible
+        .iter()
+        .map(|row| side(&diff.buffer, row.old, row.hunk, Color::Green))
+        .collect();
+    let half = area.width / 2;
+    let left = Rect {
+        width: half,
+        ..area
+    };
+    let right = Rect {
+        x: area.x + half,
+        width: area.width - half,
+        ..area
+    };
+    let style = Style::default().fg(theme.fg).bg(theme.bg_alt);
+    let head_title = format!(
+        "HEAD: {} - hunk {}/{}",
+        relative_path(&app.root, &diff.path).display(),
+        diff.selected + 1,
+        diff.hunks.len()
+    );
+    frame.render_widget(
+        Paragraph::new(head)
+            .style(style)
+            .block(themed_block(&theme).title(head_title)),
+        left,
+    );
+    frame.render_widget(
+        Paragraph::new(buffer).style(style).block(
+            themed_block(&theme)
+                .title("Buffer - n/N hunk, r revert hunk, Enter go to hunk, Esc close"),
+        ),
+        right,
+    );
+}
@@ -0,0 +1,405 @@
+use ratatui::Frame;
+use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::app::App;
+use crate::keybinds::KeyAction;
+use crate::outline::symbol_path;
+use crate::tab::Tab;
+use crate::theme::Theme;
+use crate::types::{BreadcrumbTarget, Focus, StatusLevel};
+use crate::util::{relative_path, to_u16_saturating};
+
+const SEPARATOR: &str = " │ ";
+const CRUMB_SEPARATOR: &str = " › ";
+
+/// The status bar's parts: mode, branch and dirty state on the left, the
+/// breadcrumb (or the latest message) in the middle, cursor and file facts
+/// on the right.
+pub(crate) struct StatusSegments {
+    pub(crate) left: Vec<Span<'static>>,
+    /// The message or key hint, shown when there is no breadcrumb.
+    pub(crate) center: Span<'static>,
+    /// Path components then the symbols around the cursor, each with what
+    /// clicking it opens.
+    pub(crate) breadcrumb: Vec<(String, BreadcrumbTarget)>,
+    pub(crate) right: Vec<Span<'static>>,
+}
+
+pub(crate) fn status_segments(app: &App, theme: &Theme) -> StatusSegments {
+    let tab = app.active_tab();
+    let muted = Style::default().fg(theme.fg_muted);
+
+    let mode = match (app.focus, tab) {
+        (Focus::Tree, _) => "FILES",
+        (Focus::Editor, Some(t)) if t.refuses_edits() => "VIEW",
+        (Focus::Editor, Some(_)) => "EDIT",
+        (Focus::Editor, None) => "IDLE",
+    };
+    let mut left = vec![Span::styled(
+        format!(" {mode} "),
+        Style::default()
+            .fg(theme.bg)
+            .bg(theme.accent)
+            .add_modifier(Modifier::BOLD),
+    )];
+    if let Some(branch) = app.git_branch.as_deref().filter(|b| !b.is_empty()) {
+        left.push(Span::raw(format!("⎇ {branch}")));
+    }
+    if let Some(tab) = tab {
+        left.push(if tab.dirty {
+            Span::styled("● modified", Style::default().fg(theme.accent_secondary))
+        } else {
+            Span::styled("saved", muted)
+        });
+        if tab.follow_tail {
+            left.push(Span::styled("following", muted));
+        }
+        if let Some(f) = tab.large_file {
+            left.push(Span::styled(
+                if f.fully_loaded() {
+                    "large file".to_string()
+                } else {
+                    format!(
+                        "large file {}%",
+                        f.loaded_bytes * 100 / f.total_bytes.max(1)
+                    )
+                },
+                muted,
+            ));
+        }
+    }
+    if app.word_wrap {
+        left.push(Span::styled("wrap", muted));
+    }
+
+    let mut breadcrumb = Vec::new();
+    let center = if !app.status.is_empty() {
+        match app.status_level {
+            StatusLevel::Info => Span::raw(app.status.clone()),
+            StatusLevel::Warn => {
+                Span::styled(app.status.clone(), Style::default().fg(Color::Yellow))
+            }
+            StatusLevel::Error => Span::styled(
+                format!("{} (Esc to dismiss)", app.status),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+        }
+    } else if let Some(tab) = tab {
+        breadcrumb = breadcrumb_for(app, tab);
+        Span::raw("")
+    } else {
+        Span::styled(
+            format!(
+                "{} commands   {} open   {} help",
+                app.keybinds.display_for(KeyAction::CommandPalette),
+                app.keybinds.display_for(KeyAction::QuickOpen),
+                app.keybinds.display_for(KeyAction::Help),
+            ),
+            muted,
+        )
+    };
+
+    let mut right = Vec::new();
+    if let Some(tab) = tab {
+        let (row, col) = tab.editor.cursor();
+        right.push(Span::raw(format!("Ln {}, Col {}", row + 1, col + 1)));
+        if let Some((chars, lines)) = selection_size(tab) {
+            right.push(Span::raw(if lines > 1 {
+                format!("{chars} selected ({lines} lines)")
+            } else {
+                format!("{chars} selected")
+            }));
+        }
+        let count = |severity: &str| {
+            tab.diagnostics
+                .iter()
+                .filter(|d| d.severity == severity)
+                .count()
+        };
+        let (errors, warnings) = (count("error"), count("warning"));
+        if errors + warnings > 0 {
+            right.push(Span::styled(
+                format!("E {errors}"),
+                Style::default().fg(if errors > 0 {
+                    Color::Red
+                } else {
+                    theme.fg_muted
+                }),
+            ));
+            right.push(Span::styled(
+                format!("W {warnings}"),
+                Style::default().fg(if warnings > 0 {
+                    Color::Yellow
+                } else {
+                    theme.fg_muted
+                }),
+            ));
+        }
+        right.push(Span::raw(tab.lang.name()));
+        let encoding = if tab.utf16.is_some() {
+            "UTF-16"
+        } else {
+            "UTF-8"
+        };
+        let eol = if tab.crlf { "CRLF" } else { "LF" };
+        right.push(Span::raw(format!("{encoding} {eol}")));
+    }
+    StatusSegments {
+        left,
+        center,
+        breadcrumb,
+        right,
+    }
+}
+
+/// `src › app.rs › impl App › handle_key`: directories open the files under
+/// them, the file and symbols open the symbols at their level.
+fn breadcrumb_for(app: &App, tab: &Tab) -> Vec<(String, BreadcrumbTarget)> {
+    let rel = relative_path(&app.root, &tab.path);
+    let mut crumbs = Vec::new();
+    let mut dir = app.root.clone();
+    let components: Vec<_> = rel.components().collect();
+    for (i, component) in components.iter().enumerate() {
+        let name = component.as_os_str().to_string_lossy().to_string();
+        dir.push(component);
+        let target = if i + 1 < components.len() {
+            BreadcrumbTarget::Files(dir.clone())
+        } else {
+            BreadcrumbTarget::Symbols {
+                within: None,
+                row: 0,
+            }
+        };
+        crumbs.push((name, target));
+    }
+    let (row, _) = tab.editor.cursor();
+    let path = symbol_path(tab.editor.lines(), &tab.fold_ranges, row);
+    let mut within = None;
+    for symbol in path {
+        let target = BreadcrumbTarget::Symbols {
+            within,
+            row: symbol.row,
+        };
+        within = Some((symbol.row, symbol.end));
+        crumbs.push((symbol.label, target));
+    }
+    crumbs
+}
+
+/// Characters and lines in the selection, counting line breaks as one
+/// character each.
+fn selection_size(tab: &Tab) -> Option<(usize, usize)> {
+    let ((start_row, start_col), (end_row, end_col)) = tab.editor.selection_range()?;
+    let lines = tab.editor.lines();
+    let chars = if start_row == end_row {
+        end_col.saturating_sub(start_col)
+    } else {
+        let first = lines[start_row].chars().count().saturating_sub(start_col) + 1;
+        let middle: usize = lines[start_row + 1..end_row]
+            .iter()
+            .map(|l| l.chars().count() + 1)
+            .sum();
+        first + middle + end_col
+    };
+    (chars > 0).then_some((chars, end_row - start_row + 1))
+}
+
+/// Spans of `segments` with a separator in the accent color between them.
+fn joined(segments: Vec<Span<'static>>, theme: &Theme) -> Vec<Span<'static>> {
+    let mut spans = Vec::with_capacity(segments.len() * 2);
+    for (i, segment) in segments.into_iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::styled(SEPARATOR, Style::default().fg(theme.accent)));
+        }
+        spans.push(segment);
+    }
+    spans
+}
+
+fn spans_width(spans: &[Span<'_>]) -> u16 {
+    let width: usize = spans.iter().map(|s| s.content.width()).sum();
+    u16::try_from(width).unwrap_or(u16::MAX)
+}
+
+/// The breadcrumb centred in `area`, dropping leading crumbs that do not
+/// fit, with a click target recorded for each crumb shown.
+fn render_breadcrumb(
+    app: &mut App,
+    frame: &mut Frame<'_>,
+    area: Rect,
+    mut crumbs: Vec<(String, BreadcrumbTarget)>,
+    theme: &Theme,
+) {
+    let sep_width = CRUMB_SEPARATOR.width();
+    let width = |crumbs: &[(String, BreadcrumbTarget)], elided: bool| {
+        let names: usize = crumbs.iter().map(|(name, _)| name.width()).sum();
+        names + sep_width * (crumbs.len() - 1) + if elided { 1 + sep_width } else { 0 }
+    };
+    let max = usize::from(area.width.saturating_sub(2));
+    let mut elided = false;
+    while crumbs.len() > 1 && width(&crumbs, elided) > max {
+        crumbs.remove(0);
+        elided = true;
+    }
+    let total = u16::try_from(width(&crumbs, elided)).unwrap_or(u16::MAX);
+    let mut x = area.x + area.width.saturating_sub(total) / 2;
+    let separator = Span::styled(CRUMB_SEPARATOR, Style::default().fg(theme.fg_muted));
+    let mut spans = Vec::new();
+    if elided {
+        spans.extend([Span::raw("…"), separator.clone()]);
+        x += 1 + to_u16_saturating(sep_width);
+    }
+    for (i, (name, target)) in crumbs.into_iter().enumerate() {
+        if i > 0 {
+            spans.push(separator.clone());
+            x += to_u16_saturating(sep_width);
+        }
+        let w = to_u16_saturating(name.width()).min(area.right().saturating_sub(x));
+        app.breadcrumb_rects
+            .push((Rect::new(x, area.y, w, 1), target));
+        x += w;
+        spans.push(Span::raw(name));
+    }
+    frame.render_widget(
+        Paragraph::new(Line::from(spans)).alignment(Alignment::Center),
+        area,
+    );
+}
+
+pub(crate) fn render_status_bar(app: &mut App, frame: &mut Frame<'_>, area: Rect) {
+    let theme = app.active_theme().clone();
+    let segments = status_segments(app, &theme);
+    app.status_rect = area;
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.border))
+        .style(Style::default().fg(theme.fg).bg(theme.bg_alt));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let left = joined(segments.left, &theme);
+    let right = joined(segments.right, &theme);
+    // The middle gets what is left, keeping a gap on either side.
+    let right_width = spans_width(&right).min(inner.width);
+    let left_width = spans_width(&left).min(inner.width - right_width);
+    let parts = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(left_width),
+            Constraint::Min(0),
+            Constraint::Length(right_width),
+        ])
+        .split(inner);
+    app.branch_rect = Rect::default();
+    let mut x = parts[0].x;
+    for span in &left {
+        let width = to_u16_saturating(span.content.width());
+        if span.content.starts_with('⎇') {
+            app.branch_rect = Rect::new(x, parts[0].y, width, 1).intersection(parts[0]);
+        }
+        x += width;
+    }
+    frame.render_widget(Paragraph::new(Line::from(left)), parts[0]);
+    app.breadcrumb_rects.clear();
+    if !segments.breadcrumb.is_empty() {
+        render_breadcrumb(app, frame, parts[1], segments.breadcrumb, &theme);
+    } else {
+        let mut center = segments.center;
+        let max = usize::from(parts[1].width.saturating_sub(2));
+        if center.content.width() > max {
+            let mut width = 1;
+            let head: String = center
+                .content
+                .chars()
+                .take_while(|c| {
+                    width += c.width().unwrap_or(0);
+                    width <= max
+                })
+                .collect();
+            center.content = format!("{head}…").into();
+        }
+        frame.render_widget(
+            Paragraph::new(Line::from(center)).alignment(Alignment::Center),
+            parts[1],
+        );
+    }
+    frame.render_widget(
+        Paragraph::new(Line::from(right)).alignment(Alignment::Right),
+        parts[2],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsp_client::LspDiagnostic;
+    use ratatui_textarea::CursorMove;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn texts(spans: &[Span<'_>]) -> Vec<String> {
+        spans.iter().map(|s| s.content.to_string()).collect()
+    }
+
+    #[test]
+    fn segments_show_mode_breadcrumb_and_cursor_facts() {
+        let tmp = tempdir().expect("tempdir");
+        fs::create_dir(tmp.path().join("src")).expect("mkdir");
+        let file = tmp.path().join("src/lib.rs");
+        fs::write(&file, "fn a() {}\nfn b() {}\n").expect("write");
+        let mut app = App::new_for_test(tmp.path());
+        let theme = app.active_theme().clone();
+        app.status.clear();
+        let idle = status_segments(&app, &theme);
+        assert!(idle.right.is_empty());
+        assert!(idle.center.content.contains("commands"));
+
+        app.open_file(file).expect("open");
+        app.status.clear();
+        let tab = &mut app.tabs[0];
+        tab.dirty = true;
+        tab.editor.move_cursor(CursorMove::Jump(0, 3));
+        tab.editor.start_selection();
+        tab.editor.move_cursor(CursorMove::Jump(1, 2));
+        tab.diagnostics.push(LspDiagnostic {
+            line: 1,
+            severity: "error".to_string(),
+            message: "oops".to_string(),
+            range: None,
+        });
+        let segments = status_segments(&app, &theme);
+        assert_eq!(texts(&segments.left), [" EDIT ", "● modified"]);
+        let crumbs: Vec<_> = segments
+            .breadcrumb
+            .iter()
+            .map(|(n, _)| n.as_str())
+            .collect();
+        assert_eq!(crumbs, ["src", "lib.rs", "b"]);
+        assert_eq!(
+            segments.breadcrumb[0].1,
+            BreadcrumbTarget::Files(tmp.path().join("src"))
+        );
+        assert_eq!(
+            segments.breadcrumb[2].1,
+            BreadcrumbTarget::Symbols {
+                within: None,
+                row: 1
+            }
+        );
+        assert_eq!(
+            texts(&segments.right),
+            [
+                "Ln 2, Col 3",
+                "9 selected (2 lines)",
+                "E 1",
+                "W 0",
+                "rust",
+                "UTF-8 LF"
+            ]
+        );
+    }
+}
@@ -1,14 +1,213 @@
 use std::collections::HashMap;
 use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::LazyLock;
+use std::time::SystemTime;
 
+use notify::EventKind;
+use ratatui::crossterm::event::KeyModifiers;
 use ratatui::layout::Rect;
+use regex::{Regex, RegexBuilder};
+use serde_json::{Value, json};
 use url::Url;
 
-use crate::syntax::{SyntaxLang, comment_start_for_lang, syntax_lang_for_path};
+use crate::commands::command_for;
+use crate::ignore::IgnoreRules;
+use crate::persistence::FileTypeConfig;
+use crate::syntax::{
+    LineState, Region, SyntaxLang, comment_start_for_lang, scan_regions, syntax_lang_for_path,
+};
 use crate::tab::{FoldRange, GitChangeSummary, GitFileStatus, GitLineStatus, ProjectSearchHit};
-use crate::types::{CommandAction, ContextAction, EditorContextAction, PendingAction};
+use crate::types::{
+    CommandAction, ContextAction, EditorContextAction, IndentStyle, PastedLocation, PendingAction,
+    ProjectSearchOptions, TodoItem,
+};
+
+/// Extensions that are never opened as text unless listed in
+/// `text_extensions` in `filetypes.json`.
+const BINARY_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "bmp", "ico", "webp", "tiff", "psd", "pdf", "zip", "gz", "tgz",
+    "bz2", "xz", "zst", "7z", "rar", "tar", "jar", "war", "class", "exe", "dll", "so", "dylib",
+    "o", "a", "lib", "obj", "rlib", "wasm", "bin", "dat", "db", "sqlite", "sqlite3", "woff",
+    "woff2", "ttf", "otf", "eot", "mp3", "mp4", "m4a", "wav", "flac", "ogg", "mov", "avi", "mkv",
+    "webm", "pyc",
+];
+
+const DEFAULT_MAX_TEXT_BYTES: u64 = 20 * 1024 * 1024;
+const DEFAULT_LARGE_FILE_BYTES: u64 = 5 * 1024 * 1024;
+/// A line this long near the top of a file usually means minified output.
+const MINIFIED_LINE_BYTES: usize = 20_000;
+
+/// Why `bytes` (the contents of `path`) should not be opened as text, or
+/// `None` when it looks like text. UTF-16 with a BOM counts as text.
+pub(crate) fn binary_reason(path: &Path, bytes: &[u8], config: &FileTypeConfig) -> Option<String> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let listed = |list: &[String]| list.iter().any(|e| e.eq_ignore_ascii_case(&ext));
+    if !ext.is_empty() && listed(&config.text_extensions) {
+        return None;
+    }
+    if !ext.is_empty()
+        && (BINARY_EXTENSIONS.contains(&ext.as_str()) || listed(&config.binary_extensions))
+    {
+        return Some(format!(".{ext} is a binary file type"));
+    }
+    let max = config.max_text_bytes.unwrap_or(DEFAULT_MAX_TEXT_BYTES);
+    if bytes.len() as u64 > max {
+        return Some(format!("file is {} KB", bytes.len() / 1024));
+    }
+    if utf16_bom(bytes).is_some() {
+        return None;
+    }
+    let head = &bytes[..bytes.len().min(8192)];
+    if head.contains(&0) {
+        return Some("file contains NUL bytes".to_string());
+    }
+    let probe = &bytes[..bytes.len().min(MINIFIED_LINE_BYTES * 4)];
+    if probe
+        .split(|&b| b == b'\n')
+        .any(|line| line.len() > MINIFIED_LINE_BYTES)
+    {
+        return Some("file has very long lines (minified?)".to_string());
+    }
+    None
+}
+
+/// Size above which a file opens in large file mode: plain text loaded in
+/// chunks, without folding, LSP or git markers.
+pub(crate) fn large_file_threshold(config: &FileTypeConfig) -> u64 {
+    config.large_file_bytes.unwrap_or(DEFAULT_LARGE_FILE_BYTES)
+}
+
+/// Up to `max_bytes` of `path` from byte `offset`, ending after the last
+/// whole line that fits. A line longer than that is cut at a char boundary
+/// and continues in the next chunk. Empty at the end of the file.
+pub(crate) fn read_line_chunk(path: &Path, offset: u64, max_bytes: usize) -> io::Result<Vec<u8>> {
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut chunk = Vec::with_capacity(max_bytes);
+    file.take(max_bytes as u64).read_to_end(&mut chunk)?;
+    if chunk.len() < max_bytes || chunk.ends_with(b"\n") {
+        return Ok(chunk);
+    }
+    if let Some(newline) = chunk.iter().rposition(|&b| b == b'\n') {
+        chunk.truncate(newline + 1);
+        return Ok(chunk);
+    }
+    // Drop a UTF-8 sequence the cut left incomplete.
+    let tail = chunk.len().saturating_sub(3);
+    if let Some(lead) = chunk[tail..].iter().rposition(|&b| b & 0xC0 != 0x80)
+        && tail + lead > 0
+        && std::str::from_utf8(&chunk[tail + lead..]).is_err()
+    {
+        chunk.truncate(tail + lead);
+    }
+    Ok(chunk)
+}
+
+/// Fingerprint of the last bytes of `bytes`, to tell later whether the
+/// text a large file tab already loaded is still what the file holds.
+pub(crate) fn tail_fingerprint(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes[bytes.len().saturating_sub(64)..].hash(&mut hasher);
+    hasher.finish()
+}
+
+/// `tail_fingerprint` of the bytes of `path` before byte `end`.
+pub(crate) fn file_tail_fingerprint(path: &Path, end: u64) -> io::Result<u64> {
+    let start = end.saturating_sub(64);
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.take(end - start).read_to_end(&mut bytes)?;
+    Ok(tail_fingerprint(&bytes))
+}
+
+/// `Some(true)` for a UTF-16 little-endian BOM, `Some(false)` for big-endian.
+pub(crate) fn utf16_bom(bytes: &[u8]) -> Option<bool> {
+    match bytes {
+        [0xFF, 0xFE, ..] => Some(true),
+        [0xFE, 0xFF, ..] => Some(false),
+        _ => None,
+    }
+}
+
+/// Decode file contents for the editor. UTF-16 with a BOM is converted;
+/// everything else is read as (lossy) UTF-8.
+pub(crate) fn decode_text(bytes: &[u8]) -> String {
+    let Some(little_endian) = utf16_bom(bytes) else {
+        return String::from_utf8_lossy(bytes).to_string();
+    };
+    let units = bytes[2..].chunks_exact(2).map(|pair| {
+        if little_endian {
+            u16::from_le_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_be_bytes([pair[0], pair[1]])
+        }
+    });
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Encode editor text for writing: UTF-16 with a BOM in the byte order
+/// `utf16_bom` reported (`Some(true)` little-endian), else UTF-8.
+pub(crate) fn encode_text(text: &str, utf16: Option<bool>) -> Vec<u8> {
+    let Some(little_endian) = utf16 else {
+        return text.as_bytes().to_vec();
+    };
+    let mut bytes = if little_endian {
+        vec![0xFF, 0xFE]
+    } else {
+        vec![0xFE, 0xFF]
+    };
+    for unit in text.encode_utf16() {
+        bytes.extend(if little_endian {
+            unit.to_le_bytes()
+        } else {
+            unit.to_be_bytes()
+        });
+    }
+    bytes
+}
+
+/// One hex viewer row: up to 16 bytes as hex, split in two groups of 8,
+/// followed by the printable ASCII rendering.
+pub(crate) fn hex_dump_row(chunk: &[u8]) -> String {
+    let mut hex = String::with_capacity(50);
+    for i in 0..16 {
+        match chunk.get(i) {
+            Some(b) => hex.push_str(&format!("{b:02x} ")),
+            None => hex.push_str("   "),
+        }
+        if i == 7 {
+            hex.push(' ');
+        }
+    }
+    let ascii: String = chunk
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    format!("{hex} |{ascii}|")
+}
+
+/// Whether most line breaks in `text` are `\r\n`.
+pub(crate) fn uses_crlf(text: &str) -> bool {
+    let crlf = text.matches("\r\n").count();
+    crlf > 0 && crlf * 2 > text.matches('\n').count()
+}
 
 /// Convert a text string to editor lines, preserving a trailing newline as an
 /// empty final line so the cursor can be positioned after the last content line.
@@ -33,7 +232,7 @@ pub(crate) fn pending_hint(pending: &PendingAction) -> String {
         PendingAction::Quit => format!("Pending quit: {}+Q confirm, Esc cancel", m),
         PendingAction::ClosePrompt => {
             format!(
-                "Pending close: Enter/{}+S save+close, Esc discard, C cancel",
+                "Pending close: Enter/{}+S save+close, Esc discard, V diff, C cancel",
                 m
             )
         }
@@ -43,48 +242,90 @@ pub(crate) fn pending_hint(pending: &PendingAction) -> String {
                 .map(|s| s.to_string_lossy().to_string())
                 .unwrap_or_else(|| path.display().to_string()),
         ),
+        PendingAction::PasteOverwrite(paste) => format!(
+            "Pending overwrite {}: Enter/Y confirm, Esc/N cancel",
+            paste
+                .to
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| paste.to.display().to_string()),
+        ),
+        PendingAction::DeleteMany(paths) => format!(
+            "Pending delete of {} items: Enter/Y confirm, Esc/N cancel",
+            paths.len()
+        ),
+        PendingAction::PasteMany(pastes) => format!(
+            "Pending paste of {} items: Enter/Y confirm, Esc/N cancel",
+            pastes.len()
+        ),
+        PendingAction::TrustProject => {
+            "Trust this project? Y trust (rust-analyzer, tasks and builds may run its code), N don't trust, Esc ask later"
+                .to_string()
+        }
+        PendingAction::RevertToHead(path) => format!(
+            "Pending revert {} to HEAD: Enter/Y confirm, V diff, Esc/N cancel",
+            path.file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string()),
+        ),
     }
 }
 
+/// Confirmations that cancel themselves after a while and let navigation
+/// keys through meanwhile: what they act on was fixed when they were asked.
+pub(crate) fn pending_auto_cancels(pending: &PendingAction) -> bool {
+    matches!(
+        pending,
+        PendingAction::Quit
+            | PendingAction::Delete(_)
+            | PendingAction::PasteOverwrite(_)
+            | PendingAction::DeleteMany(_)
+            | PendingAction::PasteMany(_)
+    )
+}
+
 pub(crate) fn primary_mod_label() -> &'static str {
     "Ctrl"
 }
 
 pub(crate) fn command_action_label(action: CommandAction) -> &'static str {
-    match action {
-        CommandAction::Theme => "Theme Picker",
-        CommandAction::Help => "Help",
-        CommandAction::QuickOpen => "Quick Open Files",
-        CommandAction::FindInFile => "Find in File",
-        CommandAction::FindInProject => "Search in Project",
-        CommandAction::SaveFile => "Save File",
-        CommandAction::RefreshTree => "Refresh Tree",
-        CommandAction::ToggleFiles => "Toggle Files Pane",
-        CommandAction::GotoDefinition => "Go to Definition",
-        CommandAction::ReplaceInFile => "Find and Replace",
-        CommandAction::GoToLine => "Go to Line",
-        CommandAction::Keybinds => "Keybind Editor",
-        CommandAction::ToggleWordWrap => "Toggle Word Wrap",
-    }
-}
-
-pub(crate) fn context_actions() -> [ContextAction; 6] {
+    command_for(action).map_or("", |c| c.label)
+}
+
+pub(crate) fn context_actions() -> [ContextAction; 17] {
     [
         ContextAction::Open,
+        ContextAction::TogglePin,
         ContextAction::NewFile,
+        ContextAction::NewFileLikeThis,
         ContextAction::NewFolder,
         ContextAction::Rename,
+        ContextAction::BatchRename,
+        ContextAction::Cut,
+        ContextAction::Copy,
+        ContextAction::Paste,
+        ContextAction::Duplicate,
         ContextAction::Delete,
+        ContextAction::CopyRelativePath,
+        ContextAction::CopyAbsolutePath,
+        ContextAction::RevealInFileManager,
+        ContextAction::OpenWithDefaultApp,
         ContextAction::Cancel,
     ]
 }
 
-pub(crate) fn editor_context_actions() -> [EditorContextAction; 5] {
+pub(crate) fn editor_context_actions() -> [EditorContextAction; 11] {
     [
         EditorContextAction::Copy,
         EditorContextAction::Cut,
         EditorContextAction::Paste,
         EditorContextAction::SelectAll,
+        EditorContextAction::GoToDefinition,
+        EditorContextAction::FindReferences,
+        EditorContextAction::RenameSymbol,
+        EditorContextAction::FormatDocument,
+        EditorContextAction::ToggleComment,
+        EditorContextAction::TogglePin,
         EditorContextAction::Cancel,
     ]
 }
@@ -92,10 +333,21 @@ pub(crate) fn editor_context_actions() -> [EditorContextAction; 5] {
 pub(crate) fn context_label(action: ContextAction) -> &'static str {
     match action {
         ContextAction::Open => "Open",
+        ContextAction::TogglePin => "Pin/Unpin",
         ContextAction::NewFile => "New File",
+        ContextAction::NewFileLikeThis => "New File Like This",
         ContextAction::NewFolder => "New Folder",
         ContextAction::Rename => "Rename",
+        ContextAction::BatchRename => "Batch Rename…",
+        ContextAction::Cut => "Cut",
+        ContextAction::Copy => "Copy",
+        ContextAction::Paste => "Paste",
+        ContextAction::Duplicate => "Duplicate",
         ContextAction::Delete => "Delete",
+        ContextAction::CopyRelativePath => "Copy Relative Path",
+        ContextAction::CopyAbsolutePath => "Copy Absolute Path",
+        ContextAction::RevealInFileManager => "Reveal in File Manager",
+        ContextAction::OpenWithDefaultApp => "Open in Default App",
         ContextAction::Cancel => "Cancel",
     }
 }
@@ -106,10 +358,27 @@ pub(crate) fn editor_context_label(action: EditorContextAction) -> &'static str
         EditorContextAction::Cut => "Cut",
         EditorContextAction::Paste => "Paste",
         EditorContextAction::SelectAll => "Select All",
+        EditorContextAction::GoToDefinition => "Go to Definition",
+        EditorContextAction::FindReferences => "Find References",
+        EditorContextAction::RenameSymbol => "Rename Symbol",
+        EditorContextAction::FormatDocument => "Format Document",
+        EditorContextAction::ToggleComment => "Toggle Comment",
+        EditorContextAction::TogglePin => "Pin/Unpin Tab",
         EditorContextAction::Cancel => "Cancel",
     }
 }
 
+/// Entries shown disabled while no language server has the file open. Go
+/// to Definition stays on since it falls back to a search of the file.
+pub(crate) fn editor_context_needs_lsp(action: EditorContextAction) -> bool {
+    matches!(
+        action,
+        EditorContextAction::FindReferences
+            | EditorContextAction::RenameSymbol
+            | EditorContextAction::FormatDocument
+    )
+}
+
 pub(crate) fn leading_indent_bytes(line: &str) -> usize {
     let mut i = 0usize;
     let bytes = line.as_bytes();
@@ -119,6 +388,164 @@ pub(crate) fn leading_indent_bytes(line: &str) -> usize {
     i
 }
 
+/// Guess whether `lines` indent with tabs or spaces and, for spaces, how
+/// wide a level is: the most common step between consecutive indents, or
+/// `default_width` when none stands out. `None` if no line is indented.
+pub(crate) fn detect_indent(lines: &[String], default_width: usize) -> Option<IndentStyle> {
+    let (mut tab_lines, mut space_lines) = (0usize, 0usize);
+    let mut steps = [0usize; 9];
+    let mut prev = 0usize;
+    for line in lines.iter().filter(|l| !l.trim().is_empty()) {
+        if line.starts_with('\t') {
+            tab_lines += 1;
+            continue;
+        }
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        if spaces > 0 {
+            space_lines += 1;
+        }
+        if spaces > prev && spaces - prev < steps.len() {
+            steps[spaces - prev] += 1;
+        }
+        prev = spaces;
+    }
+    if tab_lines + space_lines == 0 {
+        return None;
+    }
+    // Single-space steps are usually comment continuations, not levels.
+    let width = (2..steps.len())
+        .filter(|&w| steps[w] > 0)
+        .max_by_key(|&w| (steps[w], std::cmp::Reverse(w)))
+        .unwrap_or(default_width);
+    Some(IndentStyle {
+        tabs: tab_lines > space_lines,
+        width,
+        mixed: tab_lines > 0 && space_lines > 0,
+    })
+}
+
+/// Rewrite the leading whitespace of `line` as one `unit` per level of
+/// `from_width` columns, tabs counting up to the next multiple of
+/// `tab_width`. Columns short of a full level stay as spaces.
+pub(crate) fn reindent_line(line: &str, tab_width: usize, from_width: usize, unit: &str) -> String {
+    let lead = leading_indent_bytes(line);
+    if lead == line.len() {
+        return line.to_string();
+    }
+    let col = line[..lead].chars().fold(0, |col, c| match c {
+        '\t' => (col / tab_width + 1) * tab_width,
+        _ => col + 1,
+    });
+    let from_width = from_width.max(1);
+    format!(
+        "{}{}{}",
+        unit.repeat(col / from_width),
+        " ".repeat(col % from_width),
+        &line[lead..]
+    )
+}
+
+/// Width in columns of `line`'s leading whitespace.
+fn indent_columns(line: &str, tab_width: usize) -> usize {
+    line[..leading_indent_bytes(line)]
+        .chars()
+        .fold(0, |col, c| match c {
+            '\t' => (col / tab_width + 1) * tab_width,
+            _ => col + 1,
+        })
+}
+
+/// Re-indent the lines after the first of a multi-line paste so the block
+/// sits at `target` indentation, keeping their indentation relative to
+/// each other. The block's own base level is the least indented of its
+/// lines. A first line not indented by whole levels was likely copied from
+/// mid-line, so it doesn't count; then if it ends in one of `opens` and
+/// the body after it is at the base, the base is one level less. Blank
+/// lines are emptied; the first line is returned as is.
+pub(crate) fn reindent_pasted(
+    text: &str,
+    target: &str,
+    unit: &str,
+    tab_width: usize,
+    opens: &[char],
+) -> String {
+    let tab_width = tab_width.max(1);
+    let mut lines = text.split('\n');
+    let first = lines.next().unwrap_or_default();
+    let rest: Vec<&str> = lines.collect();
+    let Some(min_rest) = rest
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| indent_columns(l, tab_width))
+        .min()
+    else {
+        return text.to_string();
+    };
+    let unit_width = if unit == "\t" {
+        tab_width
+    } else {
+        unit.len().max(1)
+    };
+    let first_indent = indent_columns(first, tab_width);
+    let base = if first_indent > 0 && first_indent.is_multiple_of(unit_width) {
+        min_rest.min(first_indent)
+    } else {
+        let opens_block = first
+            .trim_end()
+            .chars()
+            .last()
+            .is_some_and(|c| opens.contains(&c));
+        let body_at_base = rest
+            .iter()
+            .find(|l| !l.trim().is_empty())
+            .is_some_and(|l| indent_columns(l, tab_width) == min_rest);
+        if opens_block && body_at_base {
+            min_rest.saturating_sub(unit_width)
+        } else {
+            min_rest
+        }
+    };
+    let mut out = first.to_string();
+    for line in rest {
+        out.push('\n');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let extra = indent_columns(line, tab_width) - base;
+        out.push_str(target);
+        out.push_str(&unit.repeat(extra / unit_width));
+        out.push_str(&" ".repeat(extra % unit_width));
+        out.push_str(&line[leading_indent_bytes(line)..]);
+    }
+    out
+}
+
+/// A Markdown list item (`- `, `* `, `+ `, `1. `, `1) `) or block quote
+/// (`> `): the byte length of its indent and marker, and the prefix that
+/// continues it on the next line, with ordered list numbers counted up.
+pub(crate) fn markdown_list_prefix(line: &str) -> Option<(usize, String)> {
+    let indent = leading_indent_bytes(line);
+    let rest = &line[indent..];
+    if rest.starts_with('>') {
+        let len = rest.len() - rest.trim_start_matches(['>', ' ']).len();
+        return Some((indent + len, line[..indent + len].to_string()));
+    }
+    if ["- ", "* ", "+ "].iter().any(|m| rest.starts_with(m)) {
+        return Some((indent + 2, line[..indent + 2].to_string()));
+    }
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    let after = &rest[digits..];
+    if digits == 0 || digits > 9 || !(after.starts_with(". ") || after.starts_with(") ")) {
+        return None;
+    }
+    let n: u64 = rest[..digits].parse().ok()?;
+    let delim = &after[..1];
+    Some((
+        indent + digits + 2,
+        format!("{}{}{delim} ", &line[..indent], n + 1),
+    ))
+}
+
 pub(crate) fn comment_prefix_for_path(path: &Path) -> Option<&'static str> {
     comment_start_for_lang(syntax_lang_for_path(Some(path))).or_else(|| {
         match path
@@ -150,6 +577,312 @@ pub(crate) fn parse_rg_line(line: &str) -> Option<ProjectSearchHit> {
     })
 }
 
+/// The regex a project search matched with, mirroring the ripgrep flags
+/// built from `options`.
+pub(crate) fn project_search_regex(
+    query: &str,
+    options: &ProjectSearchOptions,
+) -> Result<Regex, regex::Error> {
+    let mut pattern = if options.literal {
+        regex::escape(query)
+    } else {
+        query.to_string()
+    };
+    if options.whole_word {
+        pattern = format!(r"\b(?:{pattern})\b");
+    }
+    let smart_case = !query.chars().any(char::is_uppercase);
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive && smart_case)
+        .build()
+}
+
+/// Split `foo bar -g '*.rs' -g !target` into the query and its globs.
+/// Input that does not end in well-formed `-g GLOB` pairs is all query.
+pub(crate) fn parse_search_globs(input: &str) -> (String, Vec<String>) {
+    let whole = (input.trim().to_string(), Vec::new());
+    let (query, mut rest) = match input.find(" -g ") {
+        Some(idx) => (input[..idx].trim(), &input[idx..]),
+        None if input.starts_with("-g ") => ("", input),
+        None => return whole,
+    };
+    let mut globs = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        let Some(after) = rest.strip_prefix("-g ") else {
+            return whole;
+        };
+        let after = after.trim_start();
+        let (glob, tail) = match after.chars().next() {
+            Some(quote @ ('\'' | '"')) => match after[1..].find(quote) {
+                Some(end) => (&after[1..end + 1], &after[end + 2..]),
+                None => return whole,
+            },
+            _ => after.split_at(after.find(' ').unwrap_or(after.len())),
+        };
+        if glob.is_empty() {
+            return whole;
+        }
+        globs.push(glob.to_string());
+        rest = tail;
+    }
+    (query.to_string(), globs)
+}
+
+/// Short summary of project search flags, e.g. `case: smart  word: off
+/// regex: on  -g *.rs`.
+pub(crate) fn search_options_legend(options: &ProjectSearchOptions) -> String {
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    let mut legend = format!(
+        "case: {}  word: {}  regex: {}",
+        if options.case_sensitive {
+            "exact"
+        } else {
+            "smart"
+        },
+        on_off(options.whole_word),
+        on_off(!options.literal),
+    );
+    for glob in &options.globs {
+        legend.push_str("  -g ");
+        legend.push_str(glob);
+    }
+    legend
+}
+
+/// A piece of a line after replacement, for the project replace preview.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ReplaceSegment {
+    Keep(String),
+    Removed(String),
+    Inserted(String),
+}
+
+/// Split `line` around each match of `re`, pairing every removed match
+/// with its replacement. Outside literal mode the replacement may refer
+/// to capture groups as `$1` or `${name}`, as with `rg --replace`.
+pub(crate) fn replace_segments(
+    re: &Regex,
+    line: &str,
+    replacement: &str,
+    literal: bool,
+) -> Vec<ReplaceSegment> {
+    let mut segments = Vec::new();
+    let mut last = 0;
+    for caps in re.captures_iter(line) {
+        let Some(m) = caps.get(0).filter(|m| !m.is_empty()) else {
+            continue;
+        };
+        if m.start() > last {
+            segments.push(ReplaceSegment::Keep(line[last..m.start()].to_string()));
+        }
+        segments.push(ReplaceSegment::Removed(m.as_str().to_string()));
+        let mut inserted = String::new();
+        if literal {
+            inserted.push_str(replacement);
+        } else {
+            caps.expand(replacement, &mut inserted);
+        }
+        if !inserted.is_empty() {
+            segments.push(ReplaceSegment::Inserted(inserted));
+        }
+        last = m.end();
+    }
+    if last < line.len() {
+        segments.push(ReplaceSegment::Keep(line[last..].to_string()));
+    }
+    segments
+}
+
+/// Comment markers the TODO export searches for, as whole words.
+pub(crate) const TODO_MARKERS: [&str; 2] = ["TODO", "FIXME"];
+
+static TODO_MARKER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?:TODO|FIXME)\b").expect("valid TODO regex"));
+
+/// The comment text of a TODO/FIXME line, from the first marker on.
+pub(crate) fn todo_comment_text(line: &str) -> Option<String> {
+    let m = TODO_MARKER_RE.find(line)?;
+    let text = line[m.start()..].trim_end();
+    Some(text.trim_end_matches("*/").trim_end().to_string())
+}
+
+static RUST_FN_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:(?:const|async|unsafe)\s+)*(?:extern\s+"[^"]*"\s+)?fn\s+([A-Za-z_][A-Za-z0-9_]*)"#,
+    )
+    .expect("valid fn regex")
+});
+
+/// The name of the Rust function declared nearest above `row`, `row` included.
+pub(crate) fn rust_fn_name_at(lines: &[String], row: usize) -> Option<String> {
+    lines
+        .iter()
+        .take(row + 1)
+        .rev()
+        .find_map(|line| RUST_FN_RE.captures(line))
+        .map(|caps| caps[1].to_string())
+}
+
+/// Authors of each line of `path` per `git blame`, indexed by zero-based
+/// line. Empty when the file is not tracked or git is unavailable.
+pub(crate) fn git_blame_authors(root: &Path, path: &Path) -> Vec<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["blame", "--line-porcelain", "--"])
+        .arg(path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            parse_blame_authors(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// `--line-porcelain` repeats the commit header for every line, so each
+/// `author` header belongs to the next line of the file.
+fn parse_blame_authors(raw: &str) -> Vec<String> {
+    raw.lines()
+        .filter_map(|line| line.strip_prefix("author "))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Render the TODO export as CSV with a header row, or as a JSON array.
+pub(crate) fn todo_report(items: &[TodoItem], csv: bool) -> String {
+    if csv {
+        let mut out = String::from("file,line,text,author\n");
+        for item in items {
+            out.push_str(&format!(
+                "{},{},{},{}\n",
+                csv_field(&item.file),
+                item.line,
+                csv_field(&item.text),
+                csv_field(item.author.as_deref().unwrap_or_default())
+            ));
+        }
+        return out;
+    }
+    let rows: Vec<Value> = items
+        .iter()
+        .map(|item| {
+            json!({
+                "file": item.file,
+                "line": item.line,
+                "text": item.text,
+                "author": item.author,
+            })
+        })
+        .collect();
+    let mut out = serde_json::to_string_pretty(&rows).unwrap_or_default();
+    out.push('\n');
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Line search used when ripgrep is unavailable, with the regex from
+/// [`project_search_regex`]. Files that are not valid UTF-8 are skipped.
+pub(crate) fn search_files(files: &[PathBuf], re: &Regex) -> Vec<ProjectSearchHit> {
+    let mut hits = Vec::new();
+    for path in files {
+        let Ok(text) = fs::read_to_string(path) else {
+            continue;
+        };
+        for (idx, line) in text.lines().enumerate() {
+            if re.is_match(line) {
+                hits.push(ProjectSearchHit {
+                    path: path.clone(),
+                    line: idx + 1,
+                    preview: line.to_string(),
+                });
+            }
+        }
+    }
+    hits
+}
+
+/// Parse a go-to-line target: `42`, `42:7`, or the vim-style `:42`.
+/// Returns 1-based `(line, column)`; the column is `None` when omitted.
+pub(crate) fn parse_goto_target(value: &str) -> Option<(usize, Option<usize>)> {
+    let value = value.trim();
+    let value = value.strip_prefix(':').unwrap_or(value);
+    let (line, col) = match value.split_once(':') {
+        Some((line, col)) => (line, Some(col.trim())),
+        None => (value, None),
+    };
+    let line = line.trim().parse::<usize>().ok()?;
+    let col = match col {
+        Some(c) => Some(c.parse::<usize>().ok()?),
+        None => None,
+    };
+    Some((line, col))
+}
+
+static FILE_LOCATION_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"([^\s:'"`()\[\]<>,]+):(\d+)(?::(\d+))?"#).expect("valid location regex")
+});
+
+/// Every `file:line[:col]` in `text`, such as pasted rustc errors or a
+/// backtrace, in order and without repeats. Only references whose path
+/// has a `/` or an extension count, so times and `error:` labels do not.
+pub(crate) fn parse_pasted_locations(text: &str) -> Vec<PastedLocation> {
+    let mut found: Vec<PastedLocation> = Vec::new();
+    let mut previous = "";
+    for line in text.lines() {
+        let trimmed = line.trim();
+        for caps in FILE_LOCATION_RE.captures_iter(trimmed) {
+            let path = &caps[1];
+            let named =
+                (path.contains('/') || path.contains('.')) && path.chars().any(char::is_alphabetic);
+            if !named {
+                continue;
+            }
+            let Ok(line_no) = caps[2].parse::<usize>() else {
+                continue;
+            };
+            let column = caps.get(3).and_then(|c| c.as_str().parse().ok());
+            let rest = trimmed
+                .trim_start_matches("-->")
+                .trim_start_matches("at ")
+                .trim();
+            let context = if rest == &caps[0] && !previous.is_empty() {
+                previous
+            } else {
+                trimmed
+            };
+            let location = PastedLocation {
+                path: path.to_string(),
+                line: line_no.max(1),
+                column,
+                context: context.to_string(),
+            };
+            if !found.iter().any(|l| {
+                (&l.path, l.line, l.column) == (&location.path, location.line, location.column)
+            }) {
+                found.push(location);
+            }
+        }
+        if !trimmed.is_empty() {
+            previous = trimmed;
+        }
+    }
+    found
+}
+
 pub(crate) fn fuzzy_score(query: &str, candidate: &str) -> Option<usize> {
     if query.is_empty() {
         return Some(0);
@@ -245,6 +978,26 @@ pub(crate) fn compute_git_line_status(
     result
 }
 
+/// The text of `file_path` as committed at HEAD, or `None` when it is not
+/// tracked there or git is unavailable.
+pub(crate) fn git_head_text(file_path: &Path) -> Option<String> {
+    let dir = file_path.parent()?;
+    let name = file_path.file_name()?.to_string_lossy();
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!("HEAD:./{name}"))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(decode_text(&output.stdout))
+}
+
 fn parse_unified_diff_into(diff: &str, result: &mut [GitLineStatus]) {
     let mut new_line: usize = 0;
     let mut in_hunk = false;
@@ -316,7 +1069,7 @@ fn parse_unified_diff_into(diff: &str, result: &mut [GitLineStatus]) {
 
 pub(crate) fn compute_git_file_statuses(root: &Path) -> HashMap<PathBuf, GitFileStatus> {
     let mut map = HashMap::new();
-    let Some(entries) = git_status_entries(root) else {
+    let Some(entries) = git_status_entries(root, false) else {
         return map;
     };
     for (path_str, status) in entries {
@@ -347,6 +1100,29 @@ pub(crate) fn compute_git_file_statuses(root: &Path) -> HashMap<PathBuf, GitFile
     map
 }
 
+/// Files git reports as changed or untracked, including each file inside an
+/// untracked directory, most recently modified first. Deleted files are left
+/// out since there is nothing to open.
+pub(crate) fn compute_git_changed_files(root: &Path) -> Vec<(PathBuf, GitFileStatus)> {
+    let Some(entries) = git_status_entries(root, true) else {
+        return Vec::new();
+    };
+    let mut files: Vec<(SystemTime, PathBuf, GitFileStatus)> = entries
+        .into_iter()
+        .filter_map(|(path, status)| {
+            let path = root.join(path);
+            let meta = fs::metadata(&path).ok().filter(|m| m.is_file())?;
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((modified, path, status))
+        })
+        .collect();
+    files.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    files
+        .into_iter()
+        .map(|(_, path, status)| (path, status))
+        .collect()
+}
+
 pub(crate) fn compute_git_change_summary(root: &Path) -> GitChangeSummary {
     let mut summary = GitChangeSummary::default();
     let output = Command::new("git")
@@ -377,11 +1153,14 @@ pub(crate) fn compute_git_change_summary(root: &Path) -> GitChangeSummary {
     summary
 }
 
-fn git_status_entries(root: &Path) -> Option<Vec<(String, GitFileStatus)>> {
+/// `all_untracked` lists the files inside untracked directories rather than
+/// the directories themselves.
+fn git_status_entries(root: &Path, all_untracked: bool) -> Option<Vec<(String, GitFileStatus)>> {
     let output = Command::new("git")
         .arg("-C")
         .arg(root)
         .args(["status", "--porcelain", "-z"])
+        .args(all_untracked.then_some("-uall"))
         .stdout(Stdio::piped())
         .stderr(Stdio::null())
         .output();
@@ -455,6 +1234,165 @@ pub(crate) fn file_uri(path: &Path) -> Option<String> {
     Url::from_file_path(abs).ok().map(|u| u.to_string())
 }
 
+/// Files whose changes rust-analyzer needs to hear about, outside `.git`
+/// and the root `target` directory.
+pub(crate) fn lsp_watches_path(root: &Path, path: &Path) -> bool {
+    if path.starts_with(root.join(".git")) || path.starts_with(root.join("target")) {
+        return false;
+    }
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    name.ends_with(".rs")
+        || matches!(
+            name,
+            "Cargo.toml" | "Cargo.lock" | "rust-toolchain" | "rust-toolchain.toml"
+        )
+}
+
+/// Lines `start..old_end` of one version became `start..new_end` of the
+/// next; everything outside that run is unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LineChange {
+    pub(crate) start: usize,
+    pub(crate) old_end: usize,
+    pub(crate) new_end: usize,
+}
+
+/// The shortest run of lines that differs between `old` and `new`, or
+/// `None` when they are equal.
+pub(crate) fn changed_lines(old: &[String], new: &[String]) -> Option<LineChange> {
+    let start = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    if start == old.len() && start == new.len() {
+        return None;
+    }
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(old.len().min(new.len()) - start)
+        .take_while(|(a, b)| a == b)
+        .count();
+    Some(LineChange {
+        start,
+        old_end: old.len() - suffix,
+        new_end: new.len() - suffix,
+    })
+}
+
+/// Ranged `contentChanges` entry replacing `change` in `old` with the
+/// matching lines of `new`. Characters are UTF-16 code units, as LSP expects.
+pub(crate) fn lsp_content_change(old: &[String], new: &[String], change: LineChange) -> Value {
+    let utf16_len = |line: &String| line.encode_utf16().count();
+    let position = |line: usize, character: usize| json!({ "line": line, "character": character });
+    let replaced = &new[change.start..change.new_end];
+    if change.old_end < old.len() {
+        // Whole lines, up to the start of the first unchanged one.
+        let text: String = replaced.iter().map(|l| format!("{l}\n")).collect();
+        return json!({
+            "range": { "start": position(change.start, 0), "end": position(change.old_end, 0) },
+            "text": text
+        });
+    }
+    // The change runs to the end of the document, which has no newline to
+    // stop at, so it starts from the end of the line before instead.
+    let end = position(old.len().saturating_sub(1), old.last().map_or(0, utf16_len));
+    let (start, text) = match change.start.checked_sub(1) {
+        Some(prev) if replaced.is_empty() => (position(prev, utf16_len(&old[prev])), String::new()),
+        Some(prev) => (
+            position(prev, utf16_len(&old[prev])),
+            format!("\n{}", replaced.join("\n")),
+        ),
+        None => (position(0, 0), replaced.join("\n")),
+    };
+    json!({ "range": { "start": start, "end": end }, "text": text })
+}
+
+/// LSP `FileChangeType` for a watcher event: 1 created, 2 changed,
+/// 3 deleted. Renames and missed events are settled by whether the path
+/// still exists.
+pub(crate) fn lsp_file_change_type(kind: &EventKind, exists: bool) -> u8 {
+    match kind {
+        _ if !exists => 3,
+        EventKind::Create(_) => 1,
+        EventKind::Remove(_) => 3,
+        _ => 2,
+    }
+}
+
+/// Preview of a folded block for its placeholder: the first hidden line with
+/// any letters or digits, trimmed and cut to `max_chars`.
+pub(crate) fn fold_summary(lines: &[String], fold: &FoldRange, max_chars: usize) -> Option<String> {
+    let last = fold.end_line.min(lines.len().saturating_sub(1));
+    let line = lines
+        .get(fold.start_line + 1..=last)?
+        .iter()
+        .map(|line| line.trim())
+        .find(|line| line.chars().any(char::is_alphanumeric))?;
+    if line.chars().count() <= max_chars {
+        return Some(line.to_string());
+    }
+    let mut cut: String = line.chars().take(max_chars.saturating_sub(1)).collect();
+    cut.push('…');
+    Some(cut)
+}
+
+/// Brackets on `line` outside strings and comments, with their char
+/// columns, and the state the next line starts in.
+fn code_brackets(
+    line: &str,
+    lang: SyntaxLang,
+    state: LineState,
+) -> (Vec<(usize, char)>, LineState) {
+    let (regions, next) = scan_regions(line, lang, state);
+    let out = line
+        .char_indices()
+        .enumerate()
+        .filter(|(_, (byte, ch))| {
+            matches!(ch, '{' | '(' | '[' | '}' | ')' | ']')
+                && regions
+                    .iter()
+                    .any(|(range, region)| *region == Region::Code && range.contains(byte))
+        })
+        .map(|(col, (_, ch))| (col, ch))
+        .collect();
+    (out, next)
+}
+
+/// Every paired bracket position mapped to its partner, both ways, keyed
+/// by `(row, char col)`. Closers that don't match the innermost opener
+/// are left unpaired.
+pub(crate) fn compute_bracket_matches(
+    lines: &[String],
+    lang: SyntaxLang,
+) -> HashMap<(usize, usize), (usize, usize)> {
+    let mut matches = HashMap::new();
+    let mut stack: Vec<(char, (usize, usize))> = Vec::new();
+    let mut state = LineState::Code;
+    for (row, line) in lines.iter().enumerate() {
+        let (brackets, next) = code_brackets(line, lang, state);
+        state = next;
+        for (col, ch) in brackets {
+            let opener = match ch {
+                '}' => '{',
+                ')' => '(',
+                ']' => '[',
+                _ => {
+                    stack.push((ch, (row, col)));
+                    continue;
+                }
+            };
+            if stack.last().is_some_and(|(open, _)| *open == opener) {
+                let (_, open_pos) = stack.pop().expect("non-empty stack");
+                matches.insert(open_pos, (row, col));
+                matches.insert((row, col), open_pos);
+            }
+        }
+    }
+    matches
+}
+
 pub(crate) fn compute_fold_ranges(
     lines: &[String],
     lang: SyntaxLang,
@@ -465,57 +1403,30 @@ pub(crate) fn compute_fold_ranges(
     // Brace / bracket folding + unified bracket depth tracking
     let mut stack: Vec<(char, usize)> = Vec::new();
     let mut depth: u16 = 0;
+    let mut state = LineState::Code;
     for (row, line) in lines.iter().enumerate() {
         bracket_depths.push(depth);
-        let mut in_string = false;
-        let mut quote = '\0';
-        let chars: Vec<char> = line.chars().collect();
-        let mut i = 0usize;
-        while i < chars.len() {
-            let ch = chars[i];
-            if !in_string {
-                if let Some(cs) = comment_start_for_lang(lang) {
-                    if cs == "//" && i + 1 < chars.len() && chars[i] == '/' && chars[i + 1] == '/' {
-                        break;
-                    }
-                    if cs == "#" && chars[i] == '#' {
-                        break;
-                    }
-                    if cs == "/*" && i + 1 < chars.len() && chars[i] == '/' && chars[i + 1] == '*' {
-                        break;
-                    }
+        let (brackets, next) = code_brackets(line, lang, state);
+        state = next;
+        for (_, ch) in brackets {
+            if ch == '{' || ch == '(' || ch == '[' {
+                if ch == '{' {
+                    stack.push((ch, row));
                 }
-                if ch == '"' || ch == '\'' {
-                    in_string = true;
-                    quote = ch;
-                    i += 1;
-                    continue;
-                }
-                if ch == '{' || ch == '(' || ch == '[' {
-                    if ch == '{' {
-                        stack.push((ch, row));
-                    }
-                    depth = depth.saturating_add(1);
-                } else if ch == '}' || ch == ')' || ch == ']' {
-                    depth = depth.saturating_sub(1);
-                    if ch == '}' {
-                        if let Some((_, start)) = stack.pop() {
-                            if row > start {
-                                ranges.push(FoldRange {
-                                    start_line: start,
-                                    end_line: row,
-                                });
-                            }
+                depth = depth.saturating_add(1);
+            } else {
+                depth = depth.saturating_sub(1);
+                if ch == '}' {
+                    if let Some((_, start)) = stack.pop() {
+                        if row > start {
+                            ranges.push(FoldRange {
+                                start_line: start,
+                                end_line: row,
+                            });
                         }
                     }
                 }
-            } else if ch == '\\' {
-                i += 2;
-                continue;
-            } else if ch == quote {
-                in_string = false;
             }
-            i += 1;
         }
     }
 
@@ -624,9 +1535,81 @@ pub(crate) fn row_has_selection(
     true
 }
 
-pub(crate) fn wrap_segments_for_line(line: &str, wrap_width: usize) -> Vec<(usize, usize)> {
-    use unicode_width::UnicodeWidthChar;
+/// Display width of a single char as drawn in the editor, where a tab
+/// takes `tab_width` columns.
+pub(crate) fn char_display_width(ch: char, tab_width: usize) -> usize {
+    if ch == '\t' {
+        tab_width
+    } else {
+        unicode_width::UnicodeWidthChar::width(ch).unwrap_or(0)
+    }
+}
+
+/// Display width of chars `start..end` (char indices) of `line`.
+pub(crate) fn display_width_of_range(
+    line: &str,
+    start: usize,
+    end: usize,
+    tab_width: usize,
+) -> usize {
+    line.chars()
+        .skip(start)
+        .take(end.saturating_sub(start))
+        .map(|ch| char_display_width(ch, tab_width))
+        .sum()
+}
+
+/// Byte index in `text` of the char under screen column `col`, for
+/// single-line inputs where the cursor is a byte offset.
+pub(crate) fn byte_index_at_display_col(text: &str, col: usize) -> usize {
+    let mut width = 0usize;
+    for (idx, ch) in text.char_indices() {
+        // Single-line inputs have no tab width setting of their own.
+        let cw = char_display_width(ch, 4);
+        if width + cw > col {
+            return idx;
+        }
+        width += cw;
+    }
+    text.len()
+}
+
+/// Char index in `line` under screen column `offset` of the segment
+/// `start..end`, as the wrapped editor lays it out; past the segment's
+/// text it is `end`.
+pub(crate) fn char_index_at_display_offset(
+    line: &str,
+    start: usize,
+    end: usize,
+    offset: usize,
+    tab_width: usize,
+) -> usize {
+    let mut col = start;
+    let mut width = 0usize;
+    for ch in line.chars().skip(start).take(end.saturating_sub(start)) {
+        let cw = char_display_width(ch, tab_width);
+        if width + cw > offset {
+            break;
+        }
+        width += cw;
+        col += 1;
+    }
+    col
+}
+
+/// Whether a key event carrying a char should be typed as text. AltGr
+/// arrives as Ctrl+Alt on some platforms and is needed for many layouts.
+pub(crate) fn is_text_input(modifiers: KeyModifiers) -> bool {
+    let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+    let alt = modifiers.contains(KeyModifiers::ALT);
+    ctrl == alt
+}
 
+pub(crate) fn wrap_segments_for_line(
+    line: &str,
+    wrap_width: usize,
+    tab_width: usize,
+) -> Vec<(usize, usize)> {
     let chars: Vec<char> = line.chars().collect();
     let len = chars.len();
     if len == 0 {
@@ -641,7 +1624,7 @@ pub(crate) fn wrap_segments_for_line(line: &str, wrap_width: usize) -> Vec<(usiz
     let mut cum_width = Vec::with_capacity(len + 1);
     cum_width.push(0usize);
     for &ch in &chars {
-        let w = UnicodeWidthChar::width(ch).unwrap_or(0);
+        let w = char_display_width(ch, tab_width);
         cum_width.push(cum_width.last().unwrap() + w);
     }
     let total_width = *cum_width.last().unwrap();
@@ -710,6 +1693,37 @@ pub(crate) fn segment_has_selection(
     sel_start < seg_end_col && sel_end > seg_start_col
 }
 
+/// The tabs to show in a tab bar `avail` columns wide, given each label's
+/// width: starts from `scroll` where possible, always includes `active`, and
+/// leaves a column for the ‹ › marker on each side with hidden tabs.
+pub(crate) fn tab_strip_window(
+    widths: &[usize],
+    active: usize,
+    scroll: usize,
+    avail: usize,
+) -> Range<usize> {
+    let fits = |start: usize, end: usize| {
+        let labels: usize = widths[start..end].iter().sum();
+        let separators = end - start - 1;
+        let markers = usize::from(start > 0) + usize::from(end < widths.len());
+        labels + separators + markers <= avail
+    };
+    let mut start = scroll.min(active);
+    while start < active && !fits(start, active + 1) {
+        start += 1;
+    }
+    let mut end = active + 1;
+    while end < widths.len() && fits(start, end + 1) {
+        end += 1;
+    }
+    // Use room left over on the right, e.g. after closing tabs, for tabs
+    // scrolled off to the left.
+    while start > 0 && fits(start - 1, end) {
+        start -= 1;
+    }
+    start..end
+}
+
 pub(crate) fn inside(x: u16, y: u16, rect: Rect) -> bool {
     x >= rect.x
         && x < rect.x.saturating_add(rect.width)
@@ -717,7 +1731,7 @@ pub(crate) fn inside(x: u16, y: u16, rect: Rect) -> bool {
         && y < rect.y.saturating_add(rect.height)
 }
 
-pub(crate) fn collect_all_files(dir: &Path, out: &mut Vec<PathBuf>) {
+pub(crate) fn collect_all_files(dir: &Path, ignore: &IgnoreRules, out: &mut Vec<PathBuf>) {
     let Ok(entries) = fs::read_dir(dir) else {
         return;
     };
@@ -726,7 +1740,7 @@ pub(crate) fn collect_all_files(dir: &Path, out: &mut Vec<PathBuf>) {
         let Ok(ft) = fs::symlink_metadata(&path).map(|m| m.file_type()) else {
             continue;
         };
-        if ft.is_symlink() {
+        if ft.is_symlink() || ignore.is_ignored(&path, ft.is_dir()) {
             continue;
         }
         if ft.is_dir() {
@@ -735,13 +1749,100 @@ pub(crate) fn collect_all_files(dir: &Path, out: &mut Vec<PathBuf>) {
             if name.starts_with('.') || name == "target" || name == "node_modules" {
                 continue;
             }
-            collect_all_files(&path, out);
+            collect_all_files(&path, ignore, out);
         } else {
             out.push(path);
         }
     }
 }
 
+/// Copy a file, or a folder and everything under it, to `to`. Symlinks
+/// are copied as links to the same target, never followed.
+pub(crate) fn copy_path_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    let ft = fs::symlink_metadata(from)?.file_type();
+    if ft.is_symlink() {
+        return copy_symlink(from, to);
+    }
+    if !ft.is_dir() {
+        fs::copy(from, to)?;
+        return Ok(());
+    }
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy_path_recursive(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    let target = fs::read_link(from)?;
+    if fs::metadata(from).is_ok_and(|m| m.is_dir()) {
+        std::os::windows::fs::symlink_dir(target, to)
+    } else {
+        std::os::windows::fs::symlink_file(target, to)
+    }
+}
+
+/// Remove a file or symlink, or a folder and everything under it.
+pub(crate) fn remove_path(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Open `path` with the desktop's default handler (`open` on macOS,
+/// `explorer` on Windows, `xdg-open` elsewhere), without waiting for it.
+pub(crate) fn open_with_system(path: &Path) -> io::Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    Command::new(opener)
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(drop)
+}
+
+/// The extension of `name` including its dot, or `""` when it has none.
+/// Dotfiles such as `.gitignore` have no extension.
+pub(crate) fn file_extension(name: &str) -> &str {
+    match name.rfind('.') {
+        Some(idx) if idx > 0 => &name[idx..],
+        _ => "",
+    }
+}
+
+/// Describe how renaming the file `target` to `new_name` changes its
+/// extension, or `None` when it stays the same or `target` is a folder.
+pub(crate) fn extension_change(target: &Path, new_name: &str) -> Option<String> {
+    if target.is_dir() {
+        return None;
+    }
+    let old_name = target.file_name()?.to_string_lossy();
+    let (old, new) = (file_extension(&old_name), file_extension(new_name));
+    if old == new {
+        return None;
+    }
+    let old = if old.is_empty() { "none" } else { old };
+    let new = if new.is_empty() { "none" } else { new };
+    Some(format!("extension changes from {old} to {new}"))
+}
+
 pub(crate) fn relative_path(root: &Path, path: &Path) -> PathBuf {
     path.strip_prefix(root).unwrap_or(path).to_path_buf()
 }
@@ -952,6 +2053,43 @@ diff --git a/file.rs b/file.rs
         assert_eq!(map.get(&root.join("src")), Some(&GitFileStatus::Modified));
     }
 
+    #[test]
+    fn test_markdown_list_prefix() {
+        assert_eq!(markdown_list_prefix("- item"), Some((2, "- ".to_string())));
+        assert_eq!(
+            markdown_list_prefix("  9. ninth"),
+            Some((5, "  10. ".to_string()))
+        );
+        assert_eq!(markdown_list_prefix("3) x"), Some((3, "4) ".to_string())));
+        assert_eq!(
+            markdown_list_prefix("> > quote"),
+            Some((4, "> > ".to_string()))
+        );
+        assert_eq!(markdown_list_prefix("-not a list"), None);
+        assert_eq!(markdown_list_prefix("2024.10 release"), None);
+    }
+
+    #[test]
+    fn test_tab_strip_window() {
+        let widths = [10, 10, 10, 10];
+        // Everything fits: 4 labels + 3 separators.
+        assert_eq!(tab_strip_window(&widths, 0, 0, 43), 0..4);
+        // Room for two tabs plus the right marker.
+        assert_eq!(tab_strip_window(&widths, 0, 0, 22), 0..2);
+        // Scrolling to the last tab shows it with the one before it.
+        assert_eq!(tab_strip_window(&widths, 3, 0, 22), 2..4);
+        // Moving back left keeps the strip where it was while it can.
+        assert_eq!(tab_strip_window(&widths, 2, 2, 22), 2..4);
+        assert_eq!(tab_strip_window(&widths, 1, 2, 23), 1..3);
+        // A tab wider than the bar is still shown on its own.
+        assert_eq!(tab_strip_window(&[50, 10], 0, 0, 20), 0..1);
+    }
+
+    #[test]
+    fn test_compute_git_changed_files_empty_on_non_repo() {
+        assert!(compute_git_changed_files(Path::new("/definitely/not/a/git/repo")).is_empty());
+    }
+
     #[test]
     fn test_compute_git_change_summary_empty_on_non_repo() {
         let summary = compute_git_change_summary(Path::new("/definitely/not/a/git/repo"));
@@ -965,6 +2103,53 @@ mod fold_and_selection_tests {
     use super::*;
     use std::collections::HashSet;
 
+    #[test]
+    fn test_fold_summary() {
+        let lines: Vec<String> = [
+            "fn main() {",
+            "",
+            "    {",
+            "    let total = compute(1, 2);",
+            "}",
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+        let fold = FoldRange {
+            start_line: 0,
+            end_line: 4,
+        };
+        assert_eq!(
+            fold_summary(&lines, &fold, 40).as_deref(),
+            Some("let total = compute(1, 2);")
+        );
+        assert_eq!(
+            fold_summary(&lines, &fold, 10).as_deref(),
+            Some("let total…")
+        );
+        let braces_only = FoldRange {
+            start_line: 1,
+            end_line: 2,
+        };
+        assert_eq!(fold_summary(&lines, &braces_only, 40), None);
+    }
+
+    #[test]
+    fn test_bracket_matches_skip_strings_and_comments() {
+        let lines = vec![
+            "fn f(a: [u8; 2]) {".to_string(),
+            "    g(\")\"); // )".to_string(),
+            "}".to_string(),
+        ];
+        let matches = compute_bracket_matches(&lines, SyntaxLang::Rust);
+        assert_eq!(matches.get(&(0, 4)), Some(&(0, 15)));
+        assert_eq!(matches.get(&(0, 15)), Some(&(0, 4)));
+        assert_eq!(matches.get(&(0, 8)), Some(&(0, 14)));
+        assert_eq!(matches.get(&(0, 17)), Some(&(2, 0)));
+        assert_eq!(matches.get(&(1, 5)), Some(&(1, 9)));
+        assert_eq!(matches.len(), 8);
+    }
+
     #[test]
     fn test_fold_ranges_simple_function_with_braces() {
         let lines = vec![
@@ -1098,6 +2283,23 @@ mod fold_and_selection_tests {
         assert!(ranges.iter().any(|r| r.start_line == 3 && r.end_line == 4));
     }
 
+    #[test]
+    fn test_fold_ranges_ignore_brackets_in_block_comments_and_strings() {
+        let lines: Vec<String> = [
+            "fn a() {",
+            "    /* {",
+            "    */",
+            "    let s = \"}",
+            "    \";",
+            "}",
+        ]
+        .map(str::to_string)
+        .to_vec();
+        let (ranges, depths) = compute_fold_ranges(&lines, SyntaxLang::Rust);
+        assert!(ranges.iter().any(|r| r.start_line == 0 && r.end_line == 5));
+        assert_eq!(depths, [0, 1, 1, 1, 1, 1]);
+    }
+
     #[test]
     fn test_fold_ranges_python_empty_lines_in_blocks() {
         let lines = vec![
@@ -1207,13 +2409,13 @@ mod fold_and_selection_tests {
 
     #[test]
     fn test_wrap_segments_for_line_breaks_long_text() {
-        let segs = wrap_segments_for_line("alpha beta gamma", 6);
+        let segs = wrap_segments_for_line("alpha beta gamma", 6, 4);
         assert_eq!(segs, vec![(0, 6), (6, 11), (11, 16)]);
     }
 
     #[test]
     fn test_wrap_segments_for_line_handles_empty() {
-        let segs = wrap_segments_for_line("", 8);
+        let segs = wrap_segments_for_line("", 8, 4);
         assert_eq!(segs, vec![(0, 0)]);
     }
 
@@ -1240,7 +2442,7 @@ mod fold_and_selection_tests {
         // "你好世界" = 4 chars, 8 display cells.
         // With wrap_width=5, first segment can fit "你好" (4 cells) but not
         // "你好世" (6 cells), so it should break after 2 chars.
-        let segs = wrap_segments_for_line("你好世界", 5);
+        let segs = wrap_segments_for_line("你好世界", 5, 4);
         assert_eq!(segs, vec![(0, 2), (2, 4)]);
     }
 
@@ -1248,7 +2450,7 @@ mod fold_and_selection_tests {
     fn test_wrap_segments_mixed_ascii_and_wide() {
         // "hi你好" = 4 chars, 2+2+2 = 6 display cells for "hi你" would be 4 cells
         // wrap_width=4: "hi你" = 4 cells fits, "hi你好" = 6 cells doesn't
-        let segs = wrap_segments_for_line("hi你好", 4);
+        let segs = wrap_segments_for_line("hi你好", 4, 4);
         assert_eq!(segs, vec![(0, 3), (3, 4)]);
     }
 
@@ -1256,7 +2458,7 @@ mod fold_and_selection_tests {
     fn test_wrap_segments_emoji() {
         // Most emoji are 2 cells wide.
         // "a😀b😀c" = 5 chars, 1+2+1+2+1 = 7 display cells
-        let segs = wrap_segments_for_line("a😀b😀c", 4);
+        let segs = wrap_segments_for_line("a😀b😀c", 4, 4);
         // "a😀" = 3 cells, "a😀b" = 4 cells fits
         // next: "😀c" = 3 cells fits
         assert_eq!(segs, vec![(0, 3), (3, 5)]);
@@ -1266,20 +2468,20 @@ mod fold_and_selection_tests {
     fn test_wrap_segments_single_wide_char_exceeds_width() {
         // A single wide char (2 cells) with wrap_width=1 should still take at
         // least one char per segment (no infinite loop).
-        let segs = wrap_segments_for_line("你好", 1);
+        let segs = wrap_segments_for_line("你好", 1, 4);
         assert_eq!(segs, vec![(0, 1), (1, 2)]);
     }
 
     #[test]
     fn test_wrap_segments_no_wrap_needed() {
-        let segs = wrap_segments_for_line("hello", 10);
+        let segs = wrap_segments_for_line("hello", 10, 4);
         assert_eq!(segs, vec![(0, 5)]);
     }
 
     #[test]
     fn test_wrap_segments_exact_width() {
         // Line display width equals wrap width — no wrapping.
-        let segs = wrap_segments_for_line("abcde", 5);
+        let segs = wrap_segments_for_line("abcde", 5, 4);
         assert_eq!(segs, vec![(0, 5)]);
     }
 
@@ -1287,21 +2489,21 @@ mod fold_and_selection_tests {
     fn test_wrap_segments_word_boundary_break() {
         // "hello world" = 11 chars, wrap at 8.
         // Should prefer breaking at the space: "hello " (6) then "world" (5).
-        let segs = wrap_segments_for_line("hello world", 8);
+        let segs = wrap_segments_for_line("hello world", 8, 4);
         assert_eq!(segs, vec![(0, 6), (6, 11)]);
     }
 
     #[test]
     fn test_wrap_segments_no_spaces_hard_break() {
         // "abcdefghij" = 10 chars, no spaces, wrap at 4.
-        let segs = wrap_segments_for_line("abcdefghij", 4);
+        let segs = wrap_segments_for_line("abcdefghij", 4, 4);
         assert_eq!(segs, vec![(0, 4), (4, 8), (8, 10)]);
     }
 
     #[test]
     fn test_wrap_segments_zero_width() {
         // wrap_width=0 should disable wrapping.
-        let segs = wrap_segments_for_line("hello", 0);
+        let segs = wrap_segments_for_line("hello", 0, 4);
         assert_eq!(segs, vec![(0, 5)]);
     }
 
@@ -1310,7 +2512,7 @@ mod fold_and_selection_tests {
         // Tabs should be replaced before wrapping in practice, but the function
         // operates on the already-replaced string. Test with spaces directly.
         let line = "a   b   c"; // simulating tab->4-space replacement
-        let segs = wrap_segments_for_line(line, 5);
+        let segs = wrap_segments_for_line(line, 5, 4);
         // "a   " = 4 cells, word break at space index 3 → break at 4
         // "b   c" = 5 cells, fits in width 5 → single segment
         assert_eq!(segs, vec![(0, 4), (4, 9)]);
@@ -1397,11 +2599,153 @@ mod fold_and_selection_tests {
 mod utility_tests {
     use super::*;
     use crate::theme::color_from_hex;
+    use crate::types::TreePaste;
     use crate::ui::centered_rect;
     use ratatui::layout::Rect;
     use ratatui::style::Color;
     use std::path::{Path, PathBuf};
 
+    // display width tests
+
+    #[test]
+    fn test_display_width_of_range_counts_wide_chars_and_tabs() {
+        assert_eq!(display_width_of_range("日本語abc", 0, 3, 4), 6);
+        assert_eq!(display_width_of_range("日本語abc", 2, 5, 4), 4);
+        assert_eq!(display_width_of_range("\tx", 0, 2, 4), 5);
+        assert_eq!(display_width_of_range("\tx", 0, 2, 8), 9);
+        assert_eq!(display_width_of_range("abc", 1, 10, 4), 2);
+    }
+
+    #[test]
+    fn test_byte_index_at_display_col() {
+        let text = "a日本b";
+        assert_eq!(byte_index_at_display_col(text, 0), 0);
+        assert_eq!(byte_index_at_display_col(text, 1), 1);
+        // Column 2 is the right half of the first wide char.
+        assert_eq!(byte_index_at_display_col(text, 2), 1);
+        assert_eq!(byte_index_at_display_col(text, 3), 4);
+        assert_eq!(byte_index_at_display_col(text, 50), text.len());
+    }
+
+    #[test]
+    fn test_is_text_input_allows_altgr() {
+        assert!(is_text_input(KeyModifiers::NONE));
+        assert!(is_text_input(KeyModifiers::SHIFT));
+        assert!(is_text_input(KeyModifiers::CONTROL | KeyModifiers::ALT));
+        assert!(!is_text_input(KeyModifiers::CONTROL));
+        assert!(!is_text_input(KeyModifiers::ALT));
+    }
+
+    // search_files tests
+
+    #[test]
+    fn test_search_files_literal_smart_case() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let file = tmp.path().join("a.txt");
+        std::fs::write(&file, "Hello world\nhello again\nbye\n").expect("write");
+        let files = vec![file.clone()];
+        let literal = ProjectSearchOptions {
+            literal: true,
+            ..Default::default()
+        };
+
+        let hits = search_files(&files, &project_search_regex("hello", &literal).unwrap());
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].line, 1);
+        assert_eq!(hits[1].preview, "hello again");
+
+        let hits = search_files(&files, &project_search_regex("Hello", &literal).unwrap());
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].path, file);
+    }
+
+    #[test]
+    fn test_search_options_case_and_word() {
+        let options = ProjectSearchOptions {
+            case_sensitive: true,
+            whole_word: true,
+            ..Default::default()
+        };
+        let re = project_search_regex("id", &options).unwrap();
+        assert!(re.is_match("let id = 1;"));
+        assert!(!re.is_match("let user_id = 1;"));
+        assert!(!re.is_match("let ID = 1;"));
+    }
+
+    #[test]
+    fn test_parse_search_globs() {
+        assert_eq!(
+            parse_search_globs("fn main -g '*.rs' -g !target/**"),
+            (
+                "fn main".to_string(),
+                vec!["*.rs".to_string(), "!target/**".to_string()]
+            )
+        );
+        assert_eq!(
+            parse_search_globs("a -g \"src/*.rs\""),
+            ("a".to_string(), vec!["src/*.rs".to_string()])
+        );
+        // Not a trailing glob list: keep everything as the query.
+        assert_eq!(
+            parse_search_globs("x -g y z"),
+            ("x -g y z".to_string(), Vec::new())
+        );
+        assert_eq!(
+            parse_search_globs("a -g '*.rs"),
+            ("a -g '*.rs".to_string(), Vec::new())
+        );
+    }
+
+    // parse_goto_target tests
+
+    #[test]
+    fn test_parse_goto_target_line_only() {
+        assert_eq!(parse_goto_target("42"), Some((42, None)));
+        assert_eq!(parse_goto_target(" :42 "), Some((42, None)));
+    }
+
+    #[test]
+    fn test_parse_goto_target_line_and_column() {
+        assert_eq!(parse_goto_target("42:7"), Some((42, Some(7))));
+    }
+
+    #[test]
+    fn test_parse_goto_target_rejects_garbage() {
+        assert_eq!(parse_goto_target(""), None);
+        assert_eq!(parse_goto_target("abc"), None);
+        assert_eq!(parse_goto_target("4:x"), None);
+    }
+
+    #[test]
+    fn test_parse_pasted_locations_from_rustc_and_backtraces() {
+        let text = "error[E0308]: mismatched types\n  --> src/app.rs:233:17\n    |\n\
+                    warning: unused at 12:30:00 in main.rs:4\n\
+                    \x20 3: lazyide::run\n             at ./src/lib.rs:10:5\n\
+                    error: src/app.rs:233:17 again";
+        let found = parse_pasted_locations(text);
+        let refs: Vec<_> = found
+            .iter()
+            .map(|l| (l.path.as_str(), l.line, l.column))
+            .collect();
+        assert_eq!(
+            refs,
+            [
+                ("src/app.rs", 233, Some(17)),
+                ("main.rs", 4, None),
+                ("./src/lib.rs", 10, Some(5)),
+            ]
+        );
+        let contexts: Vec<_> = found.iter().map(|l| l.context.as_str()).collect();
+        assert_eq!(
+            contexts,
+            [
+                "error[E0308]: mismatched types",
+                "warning: unused at 12:30:00 in main.rs:4",
+                "3: lazyide::run",
+            ]
+        );
+    }
+
     // color_from_hex tests
 
     #[test]
@@ -1536,6 +2880,14 @@ mod utility_tests {
         assert_eq!(relative_path(Path::new("/home/user/project"), path), path);
     }
 
+    #[test]
+    fn test_file_extension() {
+        assert_eq!(file_extension("main.rs"), ".rs");
+        assert_eq!(file_extension("archive.tar.gz"), ".gz");
+        assert_eq!(file_extension(".gitignore"), "");
+        assert_eq!(file_extension("Makefile"), "");
+    }
+
     // parse_rg_line tests
 
     #[test]
@@ -1576,6 +2928,144 @@ mod utility_tests {
         assert_eq!(result.preview, "pub struct Ast {");
     }
 
+    #[test]
+    fn test_todo_report() {
+        assert_eq!(
+            todo_comment_text("    /* FIXME: leaks, \"sometimes\" */"),
+            Some("FIXME: leaks, \"sometimes\"".to_string())
+        );
+        assert_eq!(todo_comment_text("let todos = TODOS;"), None);
+        let blame = "abc123 1 1 1\nauthor Ada\nauthor-mail <a@x>\n\tfn a() {}\n\
+                     def456 2 2 1\nauthor Linus\n\tauthor x\n";
+        assert_eq!(parse_blame_authors(blame), ["Ada", "Linus"]);
+
+        let items = [
+            TodoItem {
+                file: "src/a.rs".to_string(),
+                line: 3,
+                text: "FIXME: leaks, \"sometimes\"".to_string(),
+                author: Some("Ada".to_string()),
+            },
+            TodoItem {
+                file: "b.rs".to_string(),
+                line: 1,
+                text: "TODO".to_string(),
+                author: None,
+            },
+        ];
+        assert_eq!(
+            todo_report(&items, true),
+            "file,line,text,author\n\
+             src/a.rs,3,\"FIXME: leaks, \"\"sometimes\"\"\",Ada\n\
+             b.rs,1,TODO,\n"
+        );
+        let json: Value = serde_json::from_str(&todo_report(&items, false)).expect("json");
+        assert_eq!(json[0]["author"], "Ada");
+        assert_eq!(json[1]["author"], Value::Null);
+        assert_eq!(json[1]["line"], 1);
+    }
+
+    #[test]
+    fn test_replace_segments() {
+        use ReplaceSegment::*;
+        let regex = ProjectSearchOptions::default();
+        let literal = ProjectSearchOptions {
+            literal: true,
+            ..Default::default()
+        };
+        let re = project_search_regex(r"(\w+)_id", &regex).unwrap();
+        assert_eq!(
+            replace_segments(&re, "let user_id = 1;", "${1}Id", false),
+            vec![
+                Keep("let ".into()),
+                Removed("user_id".into()),
+                Inserted("userId".into()),
+                Keep(" = 1;".into()),
+            ]
+        );
+        // Smart case: lowercase queries match any case; literal mode keeps `$`.
+        let re = project_search_regex("a.b", &literal).unwrap();
+        assert_eq!(
+            replace_segments(&re, "A.B axb", "$1", true),
+            vec![
+                Removed("A.B".into()),
+                Inserted("$1".into()),
+                Keep(" axb".into()),
+            ]
+        );
+        assert!(!project_search_regex("Foo", &regex).unwrap().is_match("foo"));
+    }
+
+    #[test]
+    fn test_lsp_watched_files() {
+        let root = Path::new("/proj");
+        assert!(lsp_watches_path(root, Path::new("/proj/src/lib.rs")));
+        assert!(lsp_watches_path(root, Path::new("/proj/a/Cargo.toml")));
+        assert!(!lsp_watches_path(root, Path::new("/proj/README.md")));
+        assert!(!lsp_watches_path(root, Path::new("/proj/target/out.rs")));
+        let create = EventKind::Create(notify::event::CreateKind::File);
+        assert_eq!(lsp_file_change_type(&create, true), 1);
+        assert_eq!(lsp_file_change_type(&create, false), 3);
+        assert_eq!(lsp_file_change_type(&EventKind::Any, true), 2);
+    }
+
+    #[test]
+    fn lsp_content_changes_replay_to_the_new_text() {
+        fn apply(old: &[String], change: &Value) -> String {
+            let text = old.join("\n");
+            let offset = |pos: &Value| {
+                let line = pos["line"].as_u64().unwrap() as usize;
+                let units = pos["character"].as_u64().unwrap() as usize;
+                let before: usize = old[..line].iter().map(|l| l.len() + 1).sum();
+                let mut seen = 0;
+                let within: usize = old[line]
+                    .chars()
+                    .take_while(|c| {
+                        seen += c.len_utf16();
+                        seen <= units
+                    })
+                    .map(char::len_utf8)
+                    .sum();
+                before + within
+            };
+            let start = offset(&change["range"]["start"]);
+            let end = offset(&change["range"]["end"]);
+            format!(
+                "{}{}{}",
+                &text[..start],
+                change["text"].as_str().unwrap(),
+                &text[end..]
+            )
+        }
+        let lines = |s: &str| text_to_lines(s);
+        let cases = [
+            ("a\nb\nc", "a\nB\nc"),
+            ("a\nb\nc", "a\nb\nx\ny\nc"),
+            ("a\nb\nc\nd", "a\nd"),
+            ("a\nb", "a\nb\nc"),
+            ("a\nb\n😀 c", "a\nb"),
+            ("a\n😀 c", "a\n😀 cd"),
+            ("one", "two"),
+            ("a\nb\n", "a\nb\nc\n"),
+            ("x\na\nx", "a\nx"),
+        ];
+        for (old, new) in cases {
+            let (old, new) = (lines(old), lines(new));
+            let change = changed_lines(&old, &new).expect("differs");
+            let sent = lsp_content_change(&old, &new, change);
+            assert_eq!(apply(&old, &sent), new.join("\n"), "{old:?} -> {new:?}");
+        }
+        assert_eq!(changed_lines(&lines("a\nb"), &lines("a\nb")), None);
+        assert_eq!(
+            changed_lines(&lines("a\nb\nc"), &lines("a\nB\nc")),
+            Some(LineChange {
+                start: 1,
+                old_end: 2,
+                new_end: 2
+            })
+        );
+    }
+
     // fuzzy_score tests
 
     #[test]
@@ -1660,6 +3150,17 @@ mod utility_tests {
         assert!(hint.contains("file.rs"));
     }
 
+    #[test]
+    fn test_pending_hint_paste_overwrite() {
+        let hint = pending_hint(&PendingAction::PasteOverwrite(TreePaste {
+            from: PathBuf::from("/home/user/project/a/file.rs"),
+            to: PathBuf::from("/home/user/project/b/file.rs"),
+            cut: true,
+        }));
+        assert!(hint.contains("overwrite"));
+        assert!(hint.contains("file.rs"));
+    }
+
     // command_action_label tests
 
     #[test]
@@ -1695,6 +3196,30 @@ mod utility_tests {
             command_action_label(CommandAction::ReplaceInFile),
             "Find and Replace"
         );
+        assert_eq!(
+            command_action_label(CommandAction::ReplaceInProject),
+            "Replace in Project"
+        );
+        assert_eq!(
+            command_action_label(CommandAction::ExportTodos),
+            "Export TODOs (JSON/CSV)"
+        );
+        assert_eq!(
+            command_action_label(CommandAction::CloseOtherTabs),
+            "Close Other Tabs"
+        );
+        assert_eq!(
+            command_action_label(CommandAction::ChangedFiles),
+            "Open Changed File (git)"
+        );
+        assert_eq!(
+            command_action_label(CommandAction::SetLanguageMode),
+            "Set Language Mode"
+        );
+        assert_eq!(
+            command_action_label(CommandAction::ShowLastMessage),
+            "Show Last Message"
+        );
     }
 
     // context_label tests
@@ -1705,6 +3230,9 @@ mod utility_tests {
         assert_eq!(context_label(ContextAction::NewFile), "New File");
         assert_eq!(context_label(ContextAction::NewFolder), "New Folder");
         assert_eq!(context_label(ContextAction::Rename), "Rename");
+        assert_eq!(context_label(ContextAction::Cut), "Cut");
+        assert_eq!(context_label(ContextAction::Copy), "Copy");
+        assert_eq!(context_label(ContextAction::Paste), "Paste");
         assert_eq!(context_label(ContextAction::Delete), "Delete");
         assert_eq!(context_label(ContextAction::Cancel), "Cancel");
     }
@@ -1720,7 +3248,21 @@ mod utility_tests {
             editor_context_label(EditorContextAction::SelectAll),
             "Select All"
         );
+        assert_eq!(
+            editor_context_label(EditorContextAction::TogglePin),
+            "Pin/Unpin Tab"
+        );
         assert_eq!(editor_context_label(EditorContextAction::Cancel), "Cancel");
+        assert_eq!(
+            editor_context_label(EditorContextAction::RenameSymbol),
+            "Rename Symbol"
+        );
+        assert!(editor_context_needs_lsp(
+            EditorContextAction::FormatDocument
+        ));
+        assert!(!editor_context_needs_lsp(
+            EditorContextAction::ToggleComment
+        ));
     }
 }
 
@@ -1962,3 +3504,62 @@ mod indent_depth_tests {
         assert_eq!(depths, vec![0, 1, 2]);
     }
 }
+
+#[cfg(test)]
+mod filetype_tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn binary_reason_uses_extension_lists() {
+        let config = FileTypeConfig::default();
+        assert!(binary_reason(Path::new("a.png"), b"text", &config).is_some());
+        assert!(binary_reason(Path::new("a.rs"), b"fn main() {}\n", &config).is_none());
+        let config = FileTypeConfig {
+            binary_extensions: vec!["pak".to_string()],
+            text_extensions: vec!["png".to_string()],
+            max_text_bytes: None,
+            large_file_bytes: None,
+        };
+        assert!(binary_reason(Path::new("a.png"), b"\0\0", &config).is_none());
+        assert!(binary_reason(Path::new("a.PAK"), b"text", &config).is_some());
+    }
+
+    #[test]
+    fn binary_reason_sniffs_content() {
+        let config = FileTypeConfig::default();
+        let path = Path::new("blob");
+        assert!(binary_reason(path, b"abc\0def", &config).is_some());
+        let minified = "x".repeat(MINIFIED_LINE_BYTES + 1);
+        assert!(binary_reason(path, minified.as_bytes(), &config).is_some());
+        let utf16 = [0xFF, 0xFE, b'h', 0, b'i', 0];
+        assert!(binary_reason(path, &utf16, &config).is_none());
+        let small = FileTypeConfig {
+            max_text_bytes: Some(4),
+            ..FileTypeConfig::default()
+        };
+        assert!(binary_reason(path, b"hello", &small).is_some());
+    }
+
+    #[test]
+    fn decode_text_handles_utf16_boms() {
+        assert_eq!(decode_text(&[0xFF, 0xFE, b'h', 0, b'i', 0]), "hi");
+        assert_eq!(decode_text(&[0xFE, 0xFF, 0, b'h', 0, b'i']), "hi");
+        assert_eq!(decode_text(b"plain"), "plain");
+        for bytes in [
+            &[0xFF, 0xFE, b'h', 0, b'i', 0][..],
+            &[0xFE, 0xFF, 0, b'h', 0, b'i'],
+        ] {
+            assert_eq!(encode_text(&decode_text(bytes), utf16_bom(bytes)), bytes);
+        }
+        assert_eq!(encode_text("plain", None), b"plain");
+    }
+
+    #[test]
+    fn hex_dump_row_pads_short_rows() {
+        let row = hex_dump_row(b"AB\n");
+        assert!(row.starts_with("41 42 0a "));
+        assert!(row.ends_with("|AB.|"));
+        assert_eq!(row.len(), hex_dump_row(&[0u8; 16]).len() - 13);
+    }
+}